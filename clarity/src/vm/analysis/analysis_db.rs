@@ -21,7 +21,8 @@ use stacks_common::types::StacksEpochId;
 use crate::vm::analysis::errors::{CheckError, CheckErrors, CheckResult};
 use crate::vm::analysis::type_checker::ContractAnalysis;
 use crate::vm::database::{
-    ClarityBackingStore, ClarityDeserializable, ClaritySerializable, RollbackWrapper,
+    ClarityBackingStore, ClarityDatabase, ClarityDeserializable, ClaritySerializable,
+    RollbackWrapper, StoreType,
 };
 use crate::vm::representations::ClarityName;
 use crate::vm::types::signatures::FunctionSignature;
@@ -113,6 +114,43 @@ impl<'a> AnalysisDatabase<'a> {
             .transpose()
     }
 
+    /// Load a contract's source code, if it was stored when the contract was published.
+    pub fn get_contract_src(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+    ) -> Option<String> {
+        let key = ClarityDatabase::make_metadata_key(StoreType::Contract, "contract-src");
+        self.store
+            .get_metadata(contract_identifier, &key)
+            .ok()
+            .flatten()
+    }
+
+    /// Get the size of a published contract, including its data, as charged when it is loaded.
+    pub fn get_contract_size(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+    ) -> Option<u64> {
+        let contract_size = self.get_contract_metadata_u64(contract_identifier, "contract-size")?;
+        let data_size = self
+            .get_contract_metadata_u64(contract_identifier, "contract-data-size")
+            .unwrap_or(0);
+        Some(contract_size.saturating_add(data_size))
+    }
+
+    fn get_contract_metadata_u64(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+        name: &str,
+    ) -> Option<u64> {
+        let key = ClarityDatabase::make_metadata_key(StoreType::Contract, name);
+        self.store
+            .get_metadata(contract_identifier, &key)
+            .ok()
+            .flatten()
+            .and_then(|x| u64::deserialize(&x).ok())
+    }
+
     pub fn load_contract(
         &mut self,
         contract_identifier: &QualifiedContractIdentifier,
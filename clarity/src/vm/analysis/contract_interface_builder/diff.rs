@@ -0,0 +1,416 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compares the interfaces of two versions of a contract, to tell integrators which
+//! changes break callers of the old version.
+//!
+//! A change is breaking if a call, read or trait use which worked against the old
+//! version can fail against the new one: removing or retyping a public or read-only
+//! function, a data variable, a constant, a map or a token, or no longer implementing
+//! or defining a trait. Private functions are not part of the interface.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{
+    build_contract_interface, ContractInterface, ContractInterfaceFunction,
+    ContractInterfaceFunctionAccess,
+};
+use crate::vm::analysis::types::ContractAnalysis;
+use crate::vm::analysis::CheckResult;
+use crate::vm::types::FunctionSignature;
+use crate::vm::ClarityName;
+
+/// The kind of definition which changed between two versions of a contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractInterfaceItem {
+    Function,
+    Variable,
+    Map,
+    FungibleToken,
+    NonFungibleToken,
+    Trait,
+    ImplementedTrait,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceChange {
+    pub item: ContractInterfaceItem,
+    pub name: String,
+    pub description: String,
+}
+
+impl fmt::Display for ContractInterfaceChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} `{}`: {}", self.item, self.name, self.description)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceDiff {
+    pub breaking: Vec<ContractInterfaceChange>,
+    pub additive: Vec<ContractInterfaceChange>,
+}
+
+impl ContractInterfaceDiff {
+    /// Can callers of the old version use the new one unchanged?
+    pub fn is_compatible(&self) -> bool {
+        self.breaking.is_empty()
+    }
+
+    fn breaking(&mut self, item: ContractInterfaceItem, name: &str, description: String) {
+        self.breaking.push(ContractInterfaceChange {
+            item,
+            name: name.to_string(),
+            description,
+        });
+    }
+
+    fn additive(&mut self, item: ContractInterfaceItem, name: &str, description: String) {
+        self.additive.push(ContractInterfaceChange {
+            item,
+            name: name.to_string(),
+            description,
+        });
+    }
+
+    /// Reports the items only in `old` as removed, those only in `new` as added, and
+    /// passes those in both to `compare`, which describes how they changed, if at all.
+    fn compare_items<T, F>(
+        &mut self,
+        item: ContractInterfaceItem,
+        old: BTreeMap<String, T>,
+        mut new: BTreeMap<String, T>,
+        compare: F,
+    ) where
+        F: Fn(&T, &T) -> Option<String>,
+    {
+        for (name, old_value) in old.into_iter() {
+            match new.remove(&name) {
+                Some(new_value) => {
+                    if let Some(description) = compare(&old_value, &new_value) {
+                        self.breaking(item, &name, description);
+                    }
+                }
+                None => self.breaking(item, &name, "removed".into()),
+            }
+        }
+        for name in new.into_keys() {
+            self.additive(item, &name, "added".into());
+        }
+    }
+}
+
+/// Compares the interfaces built by `build_contract_interface`. These do not record
+/// traits: use `diff_contract_analyses` to compare trait definitions and conformance too.
+pub fn diff_contract_interfaces(
+    old: &ContractInterface,
+    new: &ContractInterface,
+) -> ContractInterfaceDiff {
+    let mut diff = ContractInterfaceDiff::default();
+
+    let callable = |interface: &ContractInterface| -> BTreeMap<String, ContractInterfaceFunction> {
+        interface
+            .functions
+            .iter()
+            .filter(|function| function.access != ContractInterfaceFunctionAccess::private)
+            .map(|function| (function.name.clone(), function.clone()))
+            .collect()
+    };
+    diff.compare_items(
+        ContractInterfaceItem::Function,
+        callable(old),
+        callable(new),
+        |old, new| {
+            if old.access != new.access {
+                Some(format!("changed from {:?} to {:?}", old.access, new.access))
+            } else {
+                let (old_signature, new_signature) =
+                    (function_signature(old), function_signature(new));
+                (old_signature != new_signature).then(|| {
+                    format!(
+                        "signature changed from `{}` to `{}`",
+                        old_signature, new_signature
+                    )
+                })
+            }
+        },
+    );
+
+    diff.compare_items(
+        ContractInterfaceItem::Variable,
+        old.variables
+            .iter()
+            .map(|variable| (variable.name.clone(), variable))
+            .collect(),
+        new.variables
+            .iter()
+            .map(|variable| (variable.name.clone(), variable))
+            .collect(),
+        |old, new| {
+            if old.access != new.access {
+                Some(format!("changed from {:?} to {:?}", old.access, new.access))
+            } else {
+                type_change("type", &old.type_f, &new.type_f)
+            }
+        },
+    );
+
+    diff.compare_items(
+        ContractInterfaceItem::Map,
+        old.maps.iter().map(|map| (map.name.clone(), map)).collect(),
+        new.maps.iter().map(|map| (map.name.clone(), map)).collect(),
+        |old, new| {
+            let changes: Vec<String> = [
+                type_change("key type", &old.key, &new.key),
+                type_change("value type", &old.value, &new.value),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            (!changes.is_empty()).then(|| changes.join(", "))
+        },
+    );
+
+    diff.compare_items(
+        ContractInterfaceItem::FungibleToken,
+        old.fungible_tokens
+            .iter()
+            .map(|token| (token.name.clone(), ()))
+            .collect(),
+        new.fungible_tokens
+            .iter()
+            .map(|token| (token.name.clone(), ()))
+            .collect(),
+        |_, _| None,
+    );
+
+    diff.compare_items(
+        ContractInterfaceItem::NonFungibleToken,
+        old.non_fungible_tokens
+            .iter()
+            .map(|token| (token.name.clone(), &token.type_f))
+            .collect(),
+        new.non_fungible_tokens
+            .iter()
+            .map(|token| (token.name.clone(), &token.type_f))
+            .collect(),
+        |old, new| type_change("asset type", old, new),
+    );
+
+    diff
+}
+
+/// Compares the interfaces of two analyzed contracts, including the traits they define
+/// and implement.
+pub fn diff_contract_analyses(
+    old: &ContractAnalysis,
+    new: &ContractAnalysis,
+) -> CheckResult<ContractInterfaceDiff> {
+    let mut diff = diff_contract_interfaces(
+        &build_contract_interface(old)?,
+        &build_contract_interface(new)?,
+    );
+
+    let defined_traits = |analysis: &ContractAnalysis| -> BTreeMap<String, String> {
+        analysis
+            .defined_traits
+            .iter()
+            .map(|(name, functions)| (name.to_string(), trait_signature(functions)))
+            .collect()
+    };
+    diff.compare_items(
+        ContractInterfaceItem::Trait,
+        defined_traits(old),
+        defined_traits(new),
+        |old, new| (old != new).then(|| format!("functions changed from `{}` to `{}`", old, new)),
+    );
+
+    let implemented_traits = |analysis: &ContractAnalysis| -> BTreeMap<String, ()> {
+        analysis
+            .implemented_traits
+            .iter()
+            .map(|trait_identifier| (trait_identifier.to_string(), ()))
+            .collect()
+    };
+    let mut conformance = ContractInterfaceDiff::default();
+    conformance.compare_items(
+        ContractInterfaceItem::ImplementedTrait,
+        implemented_traits(old),
+        implemented_traits(new),
+        |_, _| None,
+    );
+    for mut change in conformance.breaking.into_iter() {
+        change.description = "no longer implemented".into();
+        diff.breaking.push(change);
+    }
+    for mut change in conformance.additive.into_iter() {
+        change.description = "now implemented".into();
+        diff.additive.push(change);
+    }
+
+    Ok(diff)
+}
+
+fn type_change<T: fmt::Display + PartialEq>(what: &str, old: &T, new: &T) -> Option<String> {
+    (old != new).then(|| format!("{} changed from `{}` to `{}`", what, old, new))
+}
+
+/// The argument and return types of a function. Argument names are not part of the
+/// signature, since calls pass arguments by position.
+fn function_signature(function: &ContractInterfaceFunction) -> String {
+    let args: Vec<String> = function
+        .args
+        .iter()
+        .map(|arg| arg.type_f.to_string())
+        .collect();
+    format!("({}) -> {}", args.join(" "), function.outputs.type_f)
+}
+
+fn trait_signature(functions: &BTreeMap<ClarityName, FunctionSignature>) -> String {
+    let functions: Vec<String> = functions
+        .iter()
+        .map(|(name, signature)| {
+            let args: Vec<String> = signature.args.iter().map(|arg| arg.to_string()).collect();
+            format!("({} ({}) {})", name, args.join(" "), signature.returns)
+        })
+        .collect();
+    format!("({})", functions.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use stacks_common::types::StacksEpochId;
+
+    use super::*;
+    use crate::vm::analysis::mem_type_check;
+    use crate::vm::types::TraitIdentifier;
+    use crate::vm::ClarityVersion;
+
+    fn diff(old: &str, new: &str) -> ContractInterfaceDiff {
+        let analyze = |source| {
+            mem_type_check(source, ClarityVersion::latest(), StacksEpochId::latest())
+                .unwrap()
+                .1
+        };
+        diff_contract_analyses(&analyze(old), &analyze(new)).unwrap()
+    }
+
+    fn describe(changes: &[ContractInterfaceChange]) -> Vec<String> {
+        changes.iter().map(|change| change.to_string()).collect()
+    }
+
+    const V1: &str =
+        "(define-trait transferable ((transfer (uint principal) (response bool uint))))
+        (define-fungible-token token)
+        (define-non-fungible-token badge uint)
+        (define-map balances principal uint)
+        (define-data-var paused bool false)
+        (define-constant owner tx-sender)
+        (define-public (transfer (amount uint) (to principal))
+            (ft-transfer? token amount tx-sender to))
+        (define-read-only (get-balance (who principal))
+            (default-to u0 (map-get? balances who)))
+        (define-read-only (get-owner) owner)
+        (define-private (helper (x uint)) x)";
+
+    #[test]
+    fn test_compatible_changes() {
+        // renaming arguments and changing private functions are compatible
+        let v2 = "(define-trait transferable ((transfer (uint principal) (response bool uint))))
+            (define-fungible-token token)
+            (define-fungible-token points)
+            (define-non-fungible-token badge uint)
+            (define-map balances principal uint)
+            (define-data-var paused bool false)
+            (define-constant owner tx-sender)
+            (define-public (transfer (value uint) (recipient principal))
+                (ft-transfer? token value tx-sender recipient))
+            (define-read-only (get-balance (who principal))
+                (default-to u0 (map-get? balances who)))
+            (define-read-only (get-owner) owner)
+            (define-read-only (get-paused) (var-get paused))
+            (define-private (helper (x int)) x)";
+        let diff = diff(V1, v2);
+        assert!(diff.is_compatible(), "{:?}", diff.breaking);
+        assert_eq!(
+            describe(&diff.additive),
+            vec![
+                "Function `get-paused`: added",
+                "FungibleToken `points`: added",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let v2 = "(define-trait transferable ((transfer (uint principal principal) (response bool uint))))
+            (define-non-fungible-token badge (string-ascii 10))
+            (define-map balances { owner: principal } uint)
+            (define-data-var paused bool false)
+            (define-data-var owner principal tx-sender)
+            (define-public (transfer (amount uint) (to principal))
+                (stx-transfer? amount tx-sender to))
+            (define-public (get-balance (who principal))
+                (ok (default-to u0 (map-get? balances { owner: who }))))
+            (define-read-only (get-owner) (some (var-get owner)))";
+        let diff = diff(V1, v2);
+        assert!(!diff.is_compatible());
+        assert_eq!(
+            describe(&diff.breaking),
+            vec![
+                "Function `get-balance`: changed from read_only to public",
+                "Function `get-owner`: signature changed from `() -> principal` to `() -> (optional principal)`",
+                "Variable `owner`: changed from constant to variable",
+                "Map `balances`: key type changed from `principal` to `(tuple (owner principal))`",
+                "FungibleToken `token`: removed",
+                "NonFungibleToken `badge`: asset type changed from `uint` to `(string-ascii 10)`",
+                "Trait `transferable`: functions changed from `((transfer (uint principal) (response bool uint)))` to `((transfer (uint principal principal) (response bool uint)))`",
+            ]
+        );
+        assert!(diff.additive.is_empty());
+    }
+
+    #[test]
+    fn test_trait_conformance() {
+        let (_, plain) = mem_type_check(
+            "(define-public (get-value) (ok u1))",
+            ClarityVersion::latest(),
+            StacksEpochId::latest(),
+        )
+        .unwrap();
+        let mut implementing = plain.clone();
+        implementing.implemented_traits.insert(
+            TraitIdentifier::parse_fully_qualified(
+                "SP000000000000000000002Q6VF78.getter-trait.getter",
+            )
+            .unwrap(),
+        );
+
+        let diff = diff_contract_analyses(&implementing, &plain).unwrap();
+        assert_eq!(
+            describe(&diff.breaking),
+            vec!["ImplementedTrait `SP000000000000000000002Q6VF78.getter-trait.getter`: no longer implemented"]
+        );
+        let diff = diff_contract_analyses(&plain, &implementing).unwrap();
+        assert!(diff.is_compatible());
+        assert_eq!(
+            describe(&diff.additive),
+            vec!["ImplementedTrait `SP000000000000000000002Q6VF78.getter-trait.getter`: now implemented"]
+        );
+    }
+}
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
 use stacks_common::types::StacksEpochId;
 
@@ -26,6 +27,9 @@ use crate::vm::types::{
 };
 use crate::vm::{CheckErrors, ClarityName, ClarityVersion};
 
+#[allow(clippy::result_large_err)]
+pub mod diff;
+
 pub fn build_contract_interface(
     contract_analysis: &ContractAnalysis,
 ) -> CheckResult<ContractInterface> {
@@ -51,6 +55,7 @@ pub fn build_contract_interface(
         cost_track: _,
         contract_interface: _,
         is_cost_contract_eligible: _,
+        function_cost_bounds: _,
     } = contract_analysis;
 
     contract_interface
@@ -230,6 +235,35 @@ impl ContractInterfaceAtomType {
     }
 }
 
+/// Displays the type in Clarity's syntax, as `TypeSignature` does
+impl fmt::Display for ContractInterfaceAtomType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ContractInterfaceAtomType::*;
+
+        match self {
+            none => write!(f, "UnknownType"),
+            int128 => write!(f, "int"),
+            uint128 => write!(f, "uint"),
+            bool => write!(f, "bool"),
+            principal => write!(f, "principal"),
+            buffer { length } => write!(f, "(buff {})", length),
+            string_utf8 { length } => write!(f, "(string-utf8 {})", length),
+            string_ascii { length } => write!(f, "(string-ascii {})", length),
+            tuple(entries) => {
+                write!(f, "(tuple")?;
+                for entry in entries.iter() {
+                    write!(f, " ({} {})", entry.name, entry.type_f)?;
+                }
+                write!(f, ")")
+            }
+            optional(type_f) => write!(f, "(optional {})", type_f),
+            response { ok, error } => write!(f, "(response {} {})", ok, error),
+            list { type_f, length } => write!(f, "(list {} {})", length, type_f),
+            trait_reference => write!(f, "<trait>"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContractInterfaceFunctionArg {
     pub name: String,
@@ -0,0 +1,838 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hashbrown::{HashMap, HashSet};
+use stacks_common::types::StacksEpochId;
+
+pub use super::errors::{CheckError, CheckErrors, CheckResult};
+use super::AnalysisDatabase;
+use crate::vm::analysis::run_analysis;
+use crate::vm::analysis::type_checker::contexts::TypeMap;
+use crate::vm::analysis::types::{AnalysisPass, ContractAnalysis};
+use crate::vm::ast::{build_ast_with_rules, ASTRules};
+use crate::vm::callables::CallableType;
+use crate::vm::costs::cost_functions::ClarityCostFunction;
+use crate::vm::costs::{CostTracker, ExecutionCost, LimitedCostTracker};
+use crate::vm::functions::define::DefineFunctionsParsed;
+use crate::vm::functions::{lookup_reserved_functions, NativeFunctions};
+use crate::vm::representations::SymbolicExpressionType::{
+    Atom, AtomValue, Field, List, LiteralValue, TraitReference,
+};
+use crate::vm::representations::{ClarityName, SymbolicExpression};
+use crate::vm::types::signatures::{SequenceSubtype, StringSubtype};
+use crate::vm::types::{
+    FunctionType, PrincipalData, QualifiedContractIdentifier, TypeSignature, Value, MAX_VALUE_SIZE,
+};
+use crate::vm::variables::NativeVariables;
+
+#[cfg(test)]
+mod tests;
+
+/// The result of the static cost analysis for a public or read-only function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCostBound {
+    /// Upper bound on the cost of a single invocation of the function
+    pub cost: ExecutionCost,
+    /// Whether or not `cost` exceeds the block limit it was checked against
+    pub exceeds_block_limit: bool,
+    /// `contract.function` calls whose cost could not be bounded, and which are therefore
+    /// *not* included in `cost`. For dynamic dispatch, `contract` is the name of the trait
+    /// reference.
+    pub unbounded_calls: BTreeSet<String>,
+}
+
+/// An upper bound on the cost of evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+struct CostBound {
+    cost: ExecutionCost,
+    unbounded_calls: BTreeSet<String>,
+}
+
+impl CostBound {
+    fn zero() -> CostBound {
+        CostBound {
+            cost: ExecutionCost::zero(),
+            unbounded_calls: BTreeSet::new(),
+        }
+    }
+
+    fn from_cost(cost: ExecutionCost) -> CostBound {
+        CostBound {
+            cost,
+            unbounded_calls: BTreeSet::new(),
+        }
+    }
+
+    /// Account for evaluating `other` after `self`
+    fn add(&mut self, other: &CostBound) {
+        if self.cost.add(&other.cost).is_err() {
+            self.cost = ExecutionCost::max_value();
+        }
+        self.unbounded_calls
+            .extend(other.unbounded_calls.iter().cloned());
+    }
+
+    /// Account for evaluating either `self` or `other`
+    fn join(&mut self, other: &CostBound) {
+        self.cost = ExecutionCost::max_cost(self.cost.clone(), other.cost.clone());
+        self.unbounded_calls
+            .extend(other.unbounded_calls.iter().cloned());
+    }
+
+    /// Account for evaluating `self` `times` times
+    fn multiply(&mut self, times: u64) {
+        if self.cost.multiply(times).is_err() {
+            self.cost = ExecutionCost::max_value();
+        }
+    }
+}
+
+/// The bounds of a contract's public and read-only functions, and the contract's size
+type ContractBounds = (BTreeMap<ClarityName, CostBound>, u64);
+
+/// The functions of a contract being analyzed, and the bounds found for them so far
+struct ContractFunctions<'c> {
+    analysis: &'c ContractAnalysis,
+    type_map: &'c TypeMap,
+    bodies: HashMap<&'c ClarityName, &'c SymbolicExpression>,
+    bounds: HashMap<ClarityName, CostBound>,
+    in_progress: HashSet<ClarityName>,
+}
+
+impl<'c> ContractFunctions<'c> {
+    fn new(analysis: &'c ContractAnalysis) -> CheckResult<ContractFunctions<'c>> {
+        use crate::vm::functions::define::DefineFunctionsParsed::*;
+
+        let type_map = analysis.type_map.as_ref().ok_or_else(|| {
+            CheckErrors::Expects("Cost analysis requires the contract's type map".into())
+        })?;
+        let mut bodies = HashMap::new();
+        for expression in analysis.expressions.iter() {
+            match DefineFunctionsParsed::try_parse(expression)? {
+                Some(PrivateFunction { signature, body })
+                | Some(ReadOnlyFunction { signature, body })
+                | Some(PublicFunction { signature, body }) => {
+                    let name = signature
+                        .first()
+                        .and_then(|name| name.match_atom())
+                        .ok_or(CheckErrors::DefineFunctionBadSignature)?;
+                    bodies.insert(name, body);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ContractFunctions {
+            analysis,
+            type_map,
+            bodies,
+            bounds: HashMap::new(),
+            in_progress: HashSet::new(),
+        })
+    }
+
+    fn get_function_type(&self, name: &str) -> Option<&'c FunctionType> {
+        self.analysis
+            .get_public_function_type(name)
+            .or_else(|| self.analysis.get_read_only_function_type(name))
+            .or_else(|| self.analysis.get_private_function(name))
+    }
+
+    fn get_type(&self, expr: &SymbolicExpression) -> Option<&'c TypeSignature> {
+        self.type_map.get_type_expected(expr)
+    }
+}
+
+/// `CostChecker` computes an upper bound on the `ExecutionCost` of each public and read-only
+/// function of a contract, without executing it.
+///
+/// The bound is derived from the contract's type signatures: every value is assumed to be as
+/// large as its type allows (e.g., lists are at their maximum length), every iteration runs
+/// over the longest possible sequence, and every branch costs as much as its most expensive
+/// arm. `contract-call?`s on published contracts include the bound of the called function,
+/// whereas calls through traits can't be bounded, and are reported separately.
+///
+/// This pass is not part of `run_analysis`. It requires the type map of the contract, and uses
+/// the contract analysis's cost tracker to evaluate the cost functions. Free cost trackers
+/// produce zero costs.
+pub struct CostChecker<'a, 'b> {
+    db: &'a mut AnalysisDatabase<'b>,
+    cost_tracker: &'a mut dyn CostTracker,
+    cost_function_cache: HashMap<(ClarityCostFunction, u64), ExecutionCost>,
+    /// Bounds of the public and read-only functions of called contracts, along with the size
+    /// of each contract. `None` if the contract couldn't be analyzed.
+    contract_bounds: HashMap<QualifiedContractIdentifier, Option<ContractBounds>>,
+    contracts_in_progress: HashSet<QualifiedContractIdentifier>,
+}
+
+impl<'a, 'b> AnalysisPass for CostChecker<'a, 'b> {
+    fn run_pass(
+        _epoch: &StacksEpochId,
+        contract_analysis: &mut ContractAnalysis,
+        analysis_db: &mut AnalysisDatabase,
+    ) -> CheckResult<()> {
+        let mut cost_tracker = contract_analysis.take_contract_cost_tracker();
+        let block_limit = cost_tracker.get_limit();
+        let result =
+            CostChecker::new(analysis_db, &mut cost_tracker).run(contract_analysis, &block_limit);
+        contract_analysis.replace_contract_cost_tracker(cost_tracker);
+        contract_analysis.function_cost_bounds = Some(result?);
+        Ok(())
+    }
+}
+
+impl<'a, 'b> CostChecker<'a, 'b> {
+    pub fn new(
+        db: &'a mut AnalysisDatabase<'b>,
+        cost_tracker: &'a mut dyn CostTracker,
+    ) -> CostChecker<'a, 'b> {
+        Self {
+            db,
+            cost_tracker,
+            cost_function_cache: HashMap::new(),
+            contract_bounds: HashMap::new(),
+            contracts_in_progress: HashSet::new(),
+        }
+    }
+
+    /// Compute the bounds of each public and read-only function in `contract_analysis`, which
+    /// must have been built with a type map.
+    pub fn run(
+        &mut self,
+        contract_analysis: &ContractAnalysis,
+        block_limit: &ExecutionCost,
+    ) -> CheckResult<BTreeMap<ClarityName, FunctionCostBound>> {
+        // called contracts are only read, but reading requires a nested context
+        self.db.begin();
+        let result = self.get_contract_bounds(contract_analysis);
+        self.db.roll_back()?;
+        Ok(result?
+            .into_iter()
+            .map(|(name, bound)| {
+                let exceeds_block_limit = bound.cost.exceeds(block_limit);
+                (
+                    name,
+                    FunctionCostBound {
+                        cost: bound.cost,
+                        exceeds_block_limit,
+                        unbounded_calls: bound.unbounded_calls,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn get_contract_bounds(
+        &mut self,
+        contract_analysis: &ContractAnalysis,
+    ) -> CheckResult<BTreeMap<ClarityName, CostBound>> {
+        let mut functions = ContractFunctions::new(contract_analysis)?;
+        let mut bounds = BTreeMap::new();
+        for name in contract_analysis
+            .public_function_types
+            .keys()
+            .chain(contract_analysis.read_only_function_types.keys())
+        {
+            let bound = self.user_function_bound(&mut functions, name)?;
+            bounds.insert(name.clone(), bound);
+        }
+        Ok(bounds)
+    }
+
+    fn cost(&mut self, cost_function: ClarityCostFunction, input: u64) -> CheckResult<CostBound> {
+        if let Some(cost) = self.cost_function_cache.get(&(cost_function, input)) {
+            return Ok(CostBound::from_cost(cost.clone()));
+        }
+        let cost = self.cost_tracker.compute_cost(cost_function, &[input])?;
+        self.cost_function_cache
+            .insert((cost_function, input), cost.clone());
+        Ok(CostBound::from_cost(cost))
+    }
+
+    /// The bound for invoking a function defined in this contract, including the cost of
+    /// binding its arguments.
+    fn user_function_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        name: &ClarityName,
+    ) -> CheckResult<CostBound> {
+        if let Some(bound) = functions.bounds.get(name) {
+            return Ok(bound.clone());
+        }
+        let (Some(FunctionType::Fixed(function_type)), Some(body)) = (
+            functions.get_function_type(name),
+            functions.bodies.get(name),
+        ) else {
+            return Err(CheckErrors::UndefinedFunction(name.to_string()).into());
+        };
+        if !functions.in_progress.insert(name.clone()) {
+            return Err(CheckErrors::CircularReference(vec![name.to_string()]).into());
+        }
+
+        let mut bound = self.cost(
+            ClarityCostFunction::UserFunctionApplication,
+            function_type.args.len() as u64,
+        )?;
+        for arg in function_type.args.iter() {
+            bound.add(&self.cost(
+                ClarityCostFunction::InnerTypeCheckCost,
+                type_size(&arg.signature),
+            )?);
+        }
+        bound.add(&self.expression_bound(functions, body, 0)?);
+
+        functions.in_progress.remove(name);
+        functions.bounds.insert(name.clone(), bound.clone());
+        Ok(bound)
+    }
+
+    /// The bound for evaluating `expr`, where `depth` is the depth of the local context
+    /// (i.e., how many `let`s and `match`es enclose the expression).
+    fn expression_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        expr: &SymbolicExpression,
+        depth: u64,
+    ) -> CheckResult<CostBound> {
+        match expr.expr {
+            AtomValue(_) | LiteralValue(_) | Field(_) | TraitReference(..) => Ok(CostBound::zero()),
+            Atom(ref name) => {
+                if NativeVariables::lookup_by_name_at_version(
+                    name,
+                    &functions.analysis.clarity_version,
+                )
+                .is_some()
+                {
+                    return self.cost(ClarityCostFunction::FetchVar, 1);
+                }
+                let mut bound = self.cost(ClarityCostFunction::LookupVariableDepth, depth)?;
+                let size = functions.get_type(expr).map(type_size).unwrap_or(0);
+                bound.add(&self.cost(ClarityCostFunction::LookupVariableSize, size)?);
+                Ok(bound)
+            }
+            List(ref expressions) => self.application_bound(functions, expr, expressions, depth),
+        }
+    }
+
+    fn all_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        expressions: &[SymbolicExpression],
+        depth: u64,
+    ) -> CheckResult<CostBound> {
+        let mut bound = CostBound::zero();
+        for expr in expressions.iter() {
+            bound.add(&self.expression_bound(functions, expr, depth)?);
+        }
+        Ok(bound)
+    }
+
+    fn application_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        expr: &SymbolicExpression,
+        expressions: &[SymbolicExpression],
+        depth: u64,
+    ) -> CheckResult<CostBound> {
+        let Some((function_name, args)) = expressions.split_first() else {
+            return Ok(CostBound::zero());
+        };
+        let Some(function_name) = function_name.match_atom() else {
+            return Ok(CostBound::zero());
+        };
+
+        let mut bound = self.cost(ClarityCostFunction::LookupFunction, 0)?;
+        if let Some(native_function) = NativeFunctions::lookup_by_name_at_version(
+            function_name,
+            &functions.analysis.clarity_version,
+        ) {
+            bound.add(&self.native_function_bound(
+                functions,
+                native_function,
+                expr,
+                args,
+                depth,
+            )?);
+        } else {
+            bound.add(&self.all_bound(functions, args, depth)?);
+            bound.add(&self.user_function_bound(functions, function_name)?);
+        }
+        Ok(bound)
+    }
+
+    /// The bound for applying a function that takes evaluated arguments of the given types,
+    /// as done by `map`, `filter` and `fold` for each element.
+    fn apply_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        function_name: &ClarityName,
+        arg_types: &[TypeSignature],
+    ) -> CheckResult<CostBound> {
+        match lookup_reserved_functions(function_name, &functions.analysis.clarity_version) {
+            Some(CallableType::NativeFunction(_, _, cost_function)) => {
+                self.cost(cost_function, arg_types.len() as u64)
+            }
+            Some(CallableType::NativeFunction205(_, _, cost_function, _)) => {
+                let input = if functions.analysis.epoch >= StacksEpochId::Epoch2_05 {
+                    arg_types.iter().map(serialized_size).sum()
+                } else {
+                    arg_types.len() as u64
+                };
+                self.cost(cost_function, input)
+            }
+            Some(_) => Err(CheckErrors::Expects(format!(
+                "Cannot iterate with special function {}",
+                function_name
+            ))
+            .into()),
+            None => self.user_function_bound(functions, function_name),
+        }
+    }
+
+    fn native_function_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        function: NativeFunctions,
+        expr: &SymbolicExpression,
+        args: &[SymbolicExpression],
+        depth: u64,
+    ) -> CheckResult<CostBound> {
+        use crate::vm::functions::NativeFunctions::*;
+
+        let args_size = |functions: &ContractFunctions, args: &[SymbolicExpression]| -> u64 {
+            args.iter()
+                .map(|arg| functions.get_type(arg).map(type_size).unwrap_or(0))
+                .sum()
+        };
+        let name_arg = |i: usize| -> CheckResult<&ClarityName> {
+            args.get(i)
+                .and_then(|arg| arg.match_atom())
+                .ok_or_else(|| CheckErrors::ExpectedName.into())
+        };
+
+        let mut bound = match function {
+            If => {
+                let mut bound = self.cost(ClarityCostFunction::If, 0)?;
+                bound.add(&self.all_bound(functions, args.get(0..1).unwrap_or(&[]), depth)?);
+                let mut branches =
+                    self.all_bound(functions, args.get(1..2).unwrap_or(&[]), depth)?;
+                branches.join(&self.all_bound(functions, args.get(2..3).unwrap_or(&[]), depth)?);
+                bound.add(&branches);
+                return Ok(bound);
+            }
+            Match => {
+                // (match input some-name some-branch none-branch) or
+                // (match input ok-name ok-branch err-name err-branch)
+                let mut bound = self.cost(ClarityCostFunction::Match, 0)?;
+                bound.add(&self.all_bound(functions, args.get(0..1).unwrap_or(&[]), depth)?);
+                let mut branches =
+                    self.all_bound(functions, args.get(2..3).unwrap_or(&[]), depth + 1)?;
+                let other_branch = if args.len() == 5 { 4 } else { 3 };
+                branches.join(&self.all_bound(
+                    functions,
+                    args.get(other_branch..other_branch + 1).unwrap_or(&[]),
+                    depth + 1,
+                )?);
+                bound.add(&branches);
+                return Ok(bound);
+            }
+            Let => {
+                let bindings = args
+                    .first()
+                    .and_then(|bindings| bindings.match_list())
+                    .ok_or(CheckErrors::BadLetSyntax)?;
+                let mut bound = self.cost(ClarityCostFunction::Let, bindings.len() as u64)?;
+                for binding in bindings.iter() {
+                    let value = binding
+                        .match_list()
+                        .and_then(|pair| pair.get(1))
+                        .ok_or(CheckErrors::BadSyntaxBinding)?;
+                    bound.add(&self.expression_bound(functions, value, depth + 1)?);
+                }
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth + 1)?);
+                return Ok(bound);
+            }
+            TupleCons => {
+                let mut bound = self.cost(ClarityCostFunction::TupleCons, args.len() as u64)?;
+                for pair in args.iter() {
+                    let value = pair
+                        .match_list()
+                        .and_then(|pair| pair.get(1))
+                        .ok_or(CheckErrors::BadSyntaxBinding)?;
+                    bound.add(&self.expression_bound(functions, value, depth)?);
+                }
+                return Ok(bound);
+            }
+            TupleGet => {
+                let fields = match args.get(1).and_then(|arg| functions.get_type(arg)) {
+                    Some(TypeSignature::TupleType(tuple_type)) => tuple_type.len(),
+                    Some(TypeSignature::OptionalType(inner)) => match inner.as_ref() {
+                        TypeSignature::TupleType(tuple_type) => tuple_type.len(),
+                        _ => 0,
+                    },
+                    _ => 0,
+                };
+                let mut bound = self.cost(ClarityCostFunction::TupleGet, fields)?;
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            Map | Filter | Fold => {
+                let function_name = name_arg(0)?;
+                let sequences = args.get(1..).unwrap_or(&[]);
+                let sequences = if function == Fold {
+                    sequences.get(0..1).unwrap_or(&[])
+                } else {
+                    sequences
+                };
+                let mut iterations: Option<u64> = None;
+                let mut arg_types = vec![];
+                for sequence in sequences.iter() {
+                    let Some(TypeSignature::SequenceType(sequence_type)) =
+                        functions.get_type(sequence)
+                    else {
+                        return Err(CheckErrors::ExpectedSequence(TypeSignature::NoType).into());
+                    };
+                    let max_len = sequence_max_len(sequence_type);
+                    iterations = Some(iterations.map_or(max_len, |n| n.min(max_len)));
+                    arg_types.push(sequence_type.unit_type()?);
+                }
+                if function == Fold {
+                    // the accumulator is at most as large as the result
+                    arg_types.push(
+                        functions
+                            .get_type(expr)
+                            .cloned()
+                            .unwrap_or(TypeSignature::NoType),
+                    );
+                }
+
+                let mut bound = match function {
+                    Map => self.cost(ClarityCostFunction::Map, args.len() as u64)?,
+                    Filter => self.cost(ClarityCostFunction::Filter, 0)?,
+                    _ => self.cost(ClarityCostFunction::Fold, 0)?,
+                };
+                bound.add(&self.cost(ClarityCostFunction::LookupFunction, 0)?);
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                let mut iteration = self.apply_bound(functions, function_name, &arg_types)?;
+                iteration.multiply(iterations.unwrap_or(0));
+                bound.add(&iteration);
+                return Ok(bound);
+            }
+            FetchVar | SetVar => {
+                let var_size = functions
+                    .analysis
+                    .get_persisted_variable_type(name_arg(0)?)
+                    .map(type_size)
+                    .unwrap_or(0);
+                let cost_function = if function == FetchVar {
+                    ClarityCostFunction::FetchVar
+                } else {
+                    ClarityCostFunction::SetVar
+                };
+                let mut bound = self.cost(cost_function, var_size)?;
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            FetchEntry | SetEntry | InsertEntry | DeleteEntry => {
+                let entry_size = functions
+                    .analysis
+                    .get_map_type(name_arg(0)?)
+                    .map(|(key_type, value_type)| {
+                        type_size(key_type).saturating_add(type_size(value_type))
+                    })
+                    .unwrap_or(0);
+                let cost_function = if function == FetchEntry {
+                    ClarityCostFunction::FetchEntry
+                } else {
+                    ClarityCostFunction::SetEntry
+                };
+                let mut bound = self.cost(cost_function, entry_size)?;
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            MintAsset | TransferAsset | GetAssetOwner | BurnAsset => {
+                let asset_size = functions
+                    .analysis
+                    .non_fungible_tokens
+                    .get(name_arg(0)?)
+                    .map(type_size)
+                    .unwrap_or(0);
+                let cost_function = match function {
+                    MintAsset => ClarityCostFunction::NftMint,
+                    TransferAsset => ClarityCostFunction::NftTransfer,
+                    GetAssetOwner => ClarityCostFunction::NftOwner,
+                    _ => ClarityCostFunction::NftBurn,
+                };
+                let mut bound = self.cost(cost_function, asset_size)?;
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            MintToken | TransferToken | GetTokenBalance | BurnToken | GetTokenSupply => {
+                let cost_function = match function {
+                    MintToken => ClarityCostFunction::FtMint,
+                    TransferToken => ClarityCostFunction::FtTransfer,
+                    GetTokenBalance => ClarityCostFunction::FtBalance,
+                    BurnToken => ClarityCostFunction::FtBurn,
+                    _ => ClarityCostFunction::FtSupply,
+                };
+                let mut bound = self.cost(cost_function, 0)?;
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            GetBlockInfo | GetBurnBlockInfo | FromConsensusBuff => {
+                // the first argument is a property name or a type
+                let mut bound = match function {
+                    GetBlockInfo => self.cost(ClarityCostFunction::BlockInfo, 0)?,
+                    GetBurnBlockInfo => self.cost(ClarityCostFunction::GetBurnBlockInfo, 0)?,
+                    _ => self.cost(
+                        ClarityCostFunction::FromConsensusBuff,
+                        args_size(functions, args.get(1..).unwrap_or(&[])),
+                    )?,
+                };
+                bound.add(&self.all_bound(functions, args.get(1..).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            AsMaxLen => {
+                let mut bound = self.cost(ClarityCostFunction::AsMaxLen, 0)?;
+                bound.add(&self.all_bound(functions, args.get(0..1).unwrap_or(&[]), depth)?);
+                return Ok(bound);
+            }
+            ContractCall => return self.contract_call_bound(functions, args, depth),
+            CmpGeq | CmpLeq | CmpLess | CmpGreater => {
+                let cost_function = match function {
+                    CmpGeq => ClarityCostFunction::Geq,
+                    CmpLeq => ClarityCostFunction::Leq,
+                    CmpLess => ClarityCostFunction::Le,
+                    _ => ClarityCostFunction::Ge,
+                };
+                // comparisons of sequences are also charged by the size of the operands
+                let mut bound = self.cost(cost_function, args.len() as u64)?;
+                let max_size = args
+                    .iter()
+                    .map(|arg| functions.get_type(arg).map(type_size).unwrap_or(0))
+                    .max()
+                    .unwrap_or(0);
+                bound.add(&self.cost(cost_function, max_size)?);
+                bound
+            }
+            And => self.cost(ClarityCostFunction::And, args.len() as u64)?,
+            Or => self.cost(ClarityCostFunction::Or, args.len() as u64)?,
+            // these are charged by the size of their arguments, if at all
+            Asserts | AsContract | AtBlock | Print | Concat | Append | Slice | ListCons
+            | ReplaceAt | IsStandard | PrincipalDestruct | PrincipalConstruct
+            | Secp256k1Recover | Secp256k1Verify | ContractOf | PrincipalOf | GetStxBalance
+            | StxTransfer | StxTransferMemo | StxBurn | StxGetAccount => {
+                let cost_function = match function {
+                    Asserts => ClarityCostFunction::Asserts,
+                    AsContract => ClarityCostFunction::AsContract,
+                    AtBlock => ClarityCostFunction::AtBlock,
+                    Print => ClarityCostFunction::Print,
+                    Concat => ClarityCostFunction::Concat,
+                    Append => ClarityCostFunction::Append,
+                    Slice => ClarityCostFunction::Slice,
+                    ListCons => ClarityCostFunction::ListCons,
+                    ReplaceAt => ClarityCostFunction::ReplaceAt,
+                    IsStandard => ClarityCostFunction::IsStandard,
+                    PrincipalDestruct => ClarityCostFunction::PrincipalDestruct,
+                    PrincipalConstruct => ClarityCostFunction::PrincipalConstruct,
+                    Secp256k1Recover => ClarityCostFunction::Secp256k1recover,
+                    Secp256k1Verify => ClarityCostFunction::Secp256k1verify,
+                    ContractOf => ClarityCostFunction::ContractOf,
+                    PrincipalOf => ClarityCostFunction::PrincipalOf,
+                    GetStxBalance => ClarityCostFunction::StxBalance,
+                    StxGetAccount => ClarityCostFunction::StxGetAccount,
+                    StxTransferMemo => ClarityCostFunction::StxTransferMemo,
+                    _ => ClarityCostFunction::StxTransfer,
+                };
+                self.cost(cost_function, args_size(functions, args))?
+            }
+            _ => match lookup_reserved_functions(
+                function.get_name_str(),
+                &functions.analysis.clarity_version,
+            ) {
+                Some(CallableType::NativeFunction(_, _, cost_function)) => {
+                    self.cost(cost_function, args.len() as u64)?
+                }
+                Some(CallableType::NativeFunction205(_, _, cost_function, _)) => {
+                    let input = if functions.analysis.epoch >= StacksEpochId::Epoch2_05 {
+                        args.iter()
+                            .map(|arg| functions.get_type(arg).map(serialized_size).unwrap_or(0))
+                            .sum()
+                    } else {
+                        args.len() as u64
+                    };
+                    self.cost(cost_function, input)?
+                }
+                _ => {
+                    return Err(CheckErrors::Expects(format!(
+                        "Unexpected special function: {}",
+                        function.get_name_str()
+                    ))
+                    .into())
+                }
+            },
+        };
+        bound.add(&self.all_bound(functions, args, depth)?);
+        Ok(bound)
+    }
+
+    fn contract_call_bound(
+        &mut self,
+        functions: &mut ContractFunctions,
+        args: &[SymbolicExpression],
+        depth: u64,
+    ) -> CheckResult<CostBound> {
+        let (Some(callee), Some(function_name)) =
+            (args.first(), args.get(1).and_then(|arg| arg.match_atom()))
+        else {
+            return Err(CheckErrors::ContractCallExpectName.into());
+        };
+
+        let mut bound = self.cost(ClarityCostFunction::ContractCall, 0)?;
+        bound.add(&self.all_bound(functions, args.get(2..).unwrap_or(&[]), depth)?);
+
+        let contract_identifier = match callee.expr {
+            LiteralValue(Value::Principal(PrincipalData::Contract(ref contract_identifier)))
+            | AtomValue(Value::Principal(PrincipalData::Contract(ref contract_identifier))) => {
+                contract_identifier
+            }
+            _ => {
+                let callee = callee
+                    .match_atom()
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| callee.to_string());
+                bound
+                    .unbounded_calls
+                    .insert(format!("{}.{}", callee, function_name));
+                return Ok(bound);
+            }
+        };
+
+        match self.get_external_function_bound(contract_identifier, function_name)? {
+            Some(callee_bound) => bound.add(&callee_bound),
+            None => {
+                bound
+                    .unbounded_calls
+                    .insert(format!("{}.{}", contract_identifier, function_name));
+            }
+        }
+        Ok(bound)
+    }
+
+    /// The bound for calling a public or read-only function of a published contract,
+    /// including the cost of loading the contract. Returns `None` if the contract's source
+    /// isn't available, or if it (transitively) calls the contract being analyzed.
+    fn get_external_function_bound(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+        function_name: &ClarityName,
+    ) -> CheckResult<Option<CostBound>> {
+        if !self.contract_bounds.contains_key(contract_identifier) {
+            if !self
+                .contracts_in_progress
+                .insert(contract_identifier.clone())
+            {
+                return Ok(None);
+            }
+            let result = self.analyze_external_contract(contract_identifier);
+            self.contracts_in_progress.remove(contract_identifier);
+            self.contract_bounds
+                .insert(contract_identifier.clone(), result?);
+        }
+
+        let Some(Some((bounds, contract_size))) = self.contract_bounds.get(contract_identifier)
+        else {
+            return Ok(None);
+        };
+        let Some(function_bound) = bounds.get(function_name).cloned() else {
+            return Ok(None);
+        };
+        let contract_size = *contract_size;
+        let mut bound = self.cost(ClarityCostFunction::LoadContract, contract_size)?;
+        bound.add(&function_bound);
+        Ok(Some(bound))
+    }
+
+    fn analyze_external_contract(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+    ) -> CheckResult<Option<ContractBounds>> {
+        let Some(stored_analysis) = self.db.load_contract_non_canonical(contract_identifier)?
+        else {
+            return Ok(None);
+        };
+        let Some(contract_src) = self.db.get_contract_src(contract_identifier) else {
+            return Ok(None);
+        };
+        let contract_size = self
+            .db
+            .get_contract_size(contract_identifier)
+            .unwrap_or(contract_src.len() as u64);
+
+        let expressions = build_ast_with_rules(
+            contract_identifier,
+            &contract_src,
+            &mut (),
+            stored_analysis.clarity_version,
+            stored_analysis.epoch,
+            ASTRules::PrecheckSize,
+        )
+        .map_err(|e| CheckErrors::Expects(format!("Failed to parse {contract_identifier}: {e}")))?
+        .expressions;
+        let analysis = run_analysis(
+            contract_identifier,
+            &expressions,
+            self.db,
+            false,
+            LimitedCostTracker::new_free(),
+            stored_analysis.epoch,
+            stored_analysis.clarity_version,
+            true,
+        )
+        .map_err(|(e, _)| e)?;
+
+        let bounds = self.get_contract_bounds(&analysis)?;
+        Ok(Some((bounds, contract_size)))
+    }
+}
+
+/// The size of the largest value of this type
+fn type_size(type_signature: &TypeSignature) -> u64 {
+    type_signature
+        .size()
+        .map(u64::from)
+        .unwrap_or(u64::from(MAX_VALUE_SIZE))
+}
+
+/// The length of the largest serialization of a value of this type
+fn serialized_size(type_signature: &TypeSignature) -> u64 {
+    type_signature
+        .max_serialized_size()
+        .map(u64::from)
+        .unwrap_or(0)
+}
+
+/// The number of elements in the longest sequence of this type
+fn sequence_max_len(sequence_type: &SequenceSubtype) -> u64 {
+    match sequence_type {
+        SequenceSubtype::ListType(list_type) => u64::from(list_type.get_max_len()),
+        SequenceSubtype::BufferType(len)
+        | SequenceSubtype::StringType(StringSubtype::ASCII(len)) => u64::from(u32::from(len)),
+        SequenceSubtype::StringType(StringSubtype::UTF8(len)) => u64::from(u32::from(len)),
+    }
+}
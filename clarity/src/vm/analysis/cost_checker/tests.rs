@@ -0,0 +1,233 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use stacks_common::types::StacksEpochId;
+
+use crate::vm::analysis::cost_checker::{CostChecker, FunctionCostBound};
+use crate::vm::analysis::types::AnalysisPass;
+use crate::vm::analysis::{type_check, AnalysisDatabase, ContractAnalysis};
+use crate::vm::ast::parse;
+use crate::vm::costs::cost_functions::ClarityCostFunction;
+use crate::vm::costs::{CostErrors, CostTracker, ExecutionCost};
+use crate::vm::database::MemoryBackingStore;
+use crate::vm::representations::ClarityName;
+use crate::vm::tooling::mem_type_check;
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::ClarityVersion;
+
+const EPOCH: StacksEpochId = StacksEpochId::Epoch21;
+const VERSION: ClarityVersion = ClarityVersion::Clarity2;
+
+/// Charges one unit of runtime for every cost function evaluation, so that bounds count the
+/// number of charges.
+struct UnitCostTracker;
+
+impl CostTracker for UnitCostTracker {
+    fn compute_cost(
+        &mut self,
+        _cost_function: ClarityCostFunction,
+        _input: &[u64],
+    ) -> Result<ExecutionCost, CostErrors> {
+        Ok(ExecutionCost {
+            runtime: 1,
+            ..ExecutionCost::zero()
+        })
+    }
+    fn add_cost(&mut self, _cost: ExecutionCost) -> Result<(), CostErrors> {
+        Ok(())
+    }
+    fn add_memory(&mut self, _memory: u64) -> Result<(), CostErrors> {
+        Ok(())
+    }
+    fn drop_memory(&mut self, _memory: u64) -> Result<(), CostErrors> {
+        Ok(())
+    }
+    fn reset_memory(&mut self) {}
+    fn short_circuit_contract_call(
+        &mut self,
+        _contract: &QualifiedContractIdentifier,
+        _function: &ClarityName,
+        _input: &[u64],
+    ) -> Result<bool, CostErrors> {
+        Ok(false)
+    }
+}
+
+fn check_contract(
+    db: &mut AnalysisDatabase,
+    contract_identifier: &QualifiedContractIdentifier,
+    contract: &str,
+) -> ContractAnalysis {
+    let mut expressions = parse(contract_identifier, contract, VERSION, EPOCH).unwrap();
+    db.execute(|db| {
+        type_check(
+            contract_identifier,
+            &mut expressions,
+            db,
+            true,
+            &EPOCH,
+            &VERSION,
+        )
+    })
+    .unwrap()
+}
+
+fn cost_bounds(
+    db: &mut AnalysisDatabase,
+    analysis: &ContractAnalysis,
+) -> BTreeMap<ClarityName, FunctionCostBound> {
+    CostChecker::new(db, &mut UnitCostTracker)
+        .run(analysis, &ExecutionCost::max_value())
+        .unwrap()
+}
+
+fn bound<'a>(
+    bounds: &'a BTreeMap<ClarityName, FunctionCostBound>,
+    name: &str,
+) -> &'a FunctionCostBound {
+    bounds
+        .get(&ClarityName::from(name))
+        .unwrap_or_else(|| panic!("No bound for {}", name))
+}
+
+#[test]
+fn test_iteration_scales_with_list_length() {
+    let contract = "(define-read-only (sum-10 (l (list 10 uint))) (fold + l u0))
+        (define-read-only (sum-100 (l (list 100 uint))) (fold + l u0))
+        (define-private (sum-private (l (list 100 uint))) (fold + l u0))";
+    let contract_identifier = QualifiedContractIdentifier::local("sums").unwrap();
+
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    let analysis = check_contract(&mut db, &contract_identifier, contract);
+    let bounds = cost_bounds(&mut db, &analysis);
+
+    // only public and read-only functions are reported
+    assert_eq!(bounds.len(), 2);
+    // function application (2), lookup of `fold` (1), fold (1), lookup of `+` (1),
+    //  lookup of `l` (2), and one `+` per element
+    assert_eq!(bound(&bounds, "sum-10").cost.runtime, 17);
+    assert_eq!(bound(&bounds, "sum-100").cost.runtime, 107);
+    assert!(bound(&bounds, "sum-100").unbounded_calls.is_empty());
+}
+
+#[test]
+fn test_branches_cost_the_most_expensive_arm() {
+    let contract = "(define-private (small (l (list 10 uint))) (fold + l u0))
+        (define-private (big (l (list 100 uint))) (fold + l u0))
+        (define-read-only (either (flag bool) (l (list 10 uint))) (if flag (small l) (big l)))
+        (define-read-only (only-big (flag bool) (l (list 10 uint))) (if flag u0 (big l)))
+        (define-read-only (only-small (flag bool) (l (list 10 uint))) (if flag (small l) u0))
+        (define-read-only (matched (input (optional (list 10 uint))))
+          (match input l (big l) u0))";
+    let contract_identifier = QualifiedContractIdentifier::local("branches").unwrap();
+
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    let analysis = check_contract(&mut db, &contract_identifier, contract);
+    let bounds = cost_bounds(&mut db, &analysis);
+
+    let either = bound(&bounds, "either");
+    assert_eq!(either, bound(&bounds, "only-big"));
+    assert!(either.cost.runtime > bound(&bounds, "only-small").cost.runtime);
+    assert!(bound(&bounds, "matched").cost.runtime > 107);
+}
+
+#[test]
+fn test_contract_call_bounds() {
+    let callee = "(define-public (sum (l (list 100 uint))) (ok (fold + l u0)))";
+    let caller = "(define-trait summer ((sum ((list 100 uint)) (response uint uint))))
+        (define-public (call-sum (l (list 100 uint)))
+          (contract-call? .callee sum l))
+        (define-public (call-trait (s <summer>) (l (list 100 uint)))
+          (contract-call? s sum l))";
+    let callee_identifier = QualifiedContractIdentifier::local("callee").unwrap();
+    let caller_identifier = QualifiedContractIdentifier::local("caller").unwrap();
+
+    let mut marf = MemoryBackingStore::new();
+    {
+        let mut clarity_db = marf.as_clarity_db();
+        clarity_db.begin();
+        clarity_db
+            .insert_contract_hash(&callee_identifier, callee)
+            .unwrap();
+        clarity_db.commit().unwrap();
+    }
+    let mut db = marf.as_analysis_db();
+    let callee_analysis = check_contract(&mut db, &callee_identifier, callee);
+    let caller_analysis = check_contract(&mut db, &caller_identifier, caller);
+
+    let callee_bounds = cost_bounds(&mut db, &callee_analysis);
+    let caller_bounds = cost_bounds(&mut db, &caller_analysis);
+
+    // function application (2), lookup of `contract-call?` (1), contract-call (1),
+    //  lookup of `l` (2), and loading the callee (1)
+    let call_sum = bound(&caller_bounds, "call-sum");
+    assert_eq!(
+        call_sum.cost.runtime,
+        bound(&callee_bounds, "sum").cost.runtime + 7
+    );
+    assert!(call_sum.unbounded_calls.is_empty());
+
+    // calls through traits can't be bounded
+    let call_trait = bound(&caller_bounds, "call-trait");
+    assert_eq!(
+        call_trait.unbounded_calls,
+        BTreeSet::from(["s.sum".to_string()])
+    );
+    assert!(call_trait.cost.runtime < call_sum.cost.runtime);
+}
+
+#[test]
+fn test_exceeds_block_limit() {
+    let contract = "(define-read-only (sum-10 (l (list 10 uint))) (fold + l u0))
+        (define-read-only (sum-100 (l (list 100 uint))) (fold + l u0))";
+    let contract_identifier = QualifiedContractIdentifier::local("sums").unwrap();
+
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    let analysis = check_contract(&mut db, &contract_identifier, contract);
+    let block_limit = ExecutionCost {
+        runtime: 50,
+        ..ExecutionCost::max_value()
+    };
+    let bounds = CostChecker::new(&mut db, &mut UnitCostTracker)
+        .run(&analysis, &block_limit)
+        .unwrap();
+
+    assert!(!bound(&bounds, "sum-10").exceeds_block_limit);
+    assert!(bound(&bounds, "sum-100").exceeds_block_limit);
+}
+
+#[test]
+fn test_run_pass_requires_type_map() {
+    let contract = "(define-read-only (sum (l (list 10 uint))) (fold + l u0))";
+
+    let mut analysis = mem_type_check(contract, VERSION, EPOCH).unwrap().1;
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    CostChecker::run_pass(&EPOCH, &mut analysis, &mut db).unwrap();
+
+    // the free cost tracker charges nothing
+    let bounds = analysis.function_cost_bounds.as_ref().unwrap();
+    assert_eq!(bound(bounds, "sum").cost, ExecutionCost::zero());
+    assert!(!bound(bounds, "sum").exceeds_block_limit);
+
+    analysis.type_map = None;
+    CostChecker::run_pass(&EPOCH, &mut analysis, &mut db).unwrap_err();
+}
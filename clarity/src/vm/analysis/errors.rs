@@ -479,4 +479,127 @@ impl DiagnosableError for CheckErrors {
             _ => None,
         }
     }
+
+    fn code(&self) -> Option<String> {
+        let code = match self {
+            CheckErrors::CostOverflow => "CostOverflow",
+            CheckErrors::CostBalanceExceeded(..) => "CostBalanceExceeded",
+            CheckErrors::MemoryBalanceExceeded(..) => "MemoryBalanceExceeded",
+            CheckErrors::CostComputationFailed(..) => "CostComputationFailed",
+            CheckErrors::ValueTooLarge => "ValueTooLarge",
+            CheckErrors::ValueOutOfBounds => "ValueOutOfBounds",
+            CheckErrors::TypeSignatureTooDeep => "TypeSignatureTooDeep",
+            CheckErrors::ExpectedName => "ExpectedName",
+            CheckErrors::SupertypeTooLarge => "SupertypeTooLarge",
+            CheckErrors::Expects(..) => "Expects",
+            CheckErrors::BadMatchOptionSyntax(..) => "BadMatchOptionSyntax",
+            CheckErrors::BadMatchResponseSyntax(..) => "BadMatchResponseSyntax",
+            CheckErrors::BadMatchInput(..) => "BadMatchInput",
+            CheckErrors::UnknownListConstructionFailure => "UnknownListConstructionFailure",
+            CheckErrors::ListTypesMustMatch => "ListTypesMustMatch",
+            CheckErrors::ConstructedListTooLarge => "ConstructedListTooLarge",
+            CheckErrors::TypeError(..) => "TypeError",
+            CheckErrors::TypeLiteralError(..) => "TypeLiteralError",
+            CheckErrors::TypeValueError(..) => "TypeValueError",
+            CheckErrors::NoSuperType(..) => "NoSuperType",
+            CheckErrors::InvalidTypeDescription => "InvalidTypeDescription",
+            CheckErrors::UnknownTypeName(..) => "UnknownTypeName",
+            CheckErrors::UnionTypeError(..) => "UnionTypeError",
+            CheckErrors::UnionTypeValueError(..) => "UnionTypeValueError",
+            CheckErrors::ExpectedLiteral => "ExpectedLiteral",
+            CheckErrors::ExpectedOptionalType(..) => "ExpectedOptionalType",
+            CheckErrors::ExpectedResponseType(..) => "ExpectedResponseType",
+            CheckErrors::ExpectedOptionalOrResponseType(..) => "ExpectedOptionalOrResponseType",
+            CheckErrors::ExpectedOptionalValue(..) => "ExpectedOptionalValue",
+            CheckErrors::ExpectedResponseValue(..) => "ExpectedResponseValue",
+            CheckErrors::ExpectedOptionalOrResponseValue(..) => "ExpectedOptionalOrResponseValue",
+            CheckErrors::CouldNotDetermineResponseOkType => "CouldNotDetermineResponseOkType",
+            CheckErrors::CouldNotDetermineResponseErrType => "CouldNotDetermineResponseErrType",
+            CheckErrors::CouldNotDetermineSerializationType => "CouldNotDetermineSerializationType",
+            CheckErrors::UncheckedIntermediaryResponses => "UncheckedIntermediaryResponses",
+            CheckErrors::CouldNotDetermineMatchTypes => "CouldNotDetermineMatchTypes",
+            CheckErrors::CouldNotDetermineType => "CouldNotDetermineType",
+            CheckErrors::TypeAlreadyAnnotatedFailure => "TypeAlreadyAnnotatedFailure",
+            CheckErrors::TypeAnnotationExpectedFailure => "TypeAnnotationExpectedFailure",
+            CheckErrors::CheckerImplementationFailure => "CheckerImplementationFailure",
+            CheckErrors::BadTokenName => "BadTokenName",
+            CheckErrors::DefineFTBadSignature => "DefineFTBadSignature",
+            CheckErrors::DefineNFTBadSignature => "DefineNFTBadSignature",
+            CheckErrors::NoSuchNFT(..) => "NoSuchNFT",
+            CheckErrors::NoSuchFT(..) => "NoSuchFT",
+            CheckErrors::BadTransferSTXArguments => "BadTransferSTXArguments",
+            CheckErrors::BadTransferFTArguments => "BadTransferFTArguments",
+            CheckErrors::BadTransferNFTArguments => "BadTransferNFTArguments",
+            CheckErrors::BadMintFTArguments => "BadMintFTArguments",
+            CheckErrors::BadBurnFTArguments => "BadBurnFTArguments",
+            CheckErrors::BadTupleFieldName => "BadTupleFieldName",
+            CheckErrors::ExpectedTuple(..) => "ExpectedTuple",
+            CheckErrors::NoSuchTupleField(..) => "NoSuchTupleField",
+            CheckErrors::EmptyTuplesNotAllowed => "EmptyTuplesNotAllowed",
+            CheckErrors::BadTupleConstruction => "BadTupleConstruction",
+            CheckErrors::TupleExpectsPairs => "TupleExpectsPairs",
+            CheckErrors::NoSuchDataVariable(..) => "NoSuchDataVariable",
+            CheckErrors::BadMapName => "BadMapName",
+            CheckErrors::NoSuchMap(..) => "NoSuchMap",
+            CheckErrors::DefineFunctionBadSignature => "DefineFunctionBadSignature",
+            CheckErrors::BadFunctionName => "BadFunctionName",
+            CheckErrors::BadMapTypeDefinition => "BadMapTypeDefinition",
+            CheckErrors::PublicFunctionMustReturnResponse(..) => "PublicFunctionMustReturnResponse",
+            CheckErrors::DefineVariableBadSignature => "DefineVariableBadSignature",
+            CheckErrors::ReturnTypesMustMatch(..) => "ReturnTypesMustMatch",
+            CheckErrors::CircularReference(..) => "CircularReference",
+            CheckErrors::NoSuchContract(..) => "NoSuchContract",
+            CheckErrors::NoSuchPublicFunction(..) => "NoSuchPublicFunction",
+            CheckErrors::PublicFunctionNotReadOnly(..) => "PublicFunctionNotReadOnly",
+            CheckErrors::ContractAlreadyExists(..) => "ContractAlreadyExists",
+            CheckErrors::ContractCallExpectName => "ContractCallExpectName",
+            CheckErrors::ExpectedCallableType(..) => "ExpectedCallableType",
+            CheckErrors::NoSuchBlockInfoProperty(..) => "NoSuchBlockInfoProperty",
+            CheckErrors::NoSuchBurnBlockInfoProperty(..) => "NoSuchBurnBlockInfoProperty",
+            CheckErrors::GetBlockInfoExpectPropertyName => "GetBlockInfoExpectPropertyName",
+            CheckErrors::GetBurnBlockInfoExpectPropertyName => "GetBurnBlockInfoExpectPropertyName",
+            CheckErrors::NameAlreadyUsed(..) => "NameAlreadyUsed",
+            CheckErrors::ReservedWord(..) => "ReservedWord",
+            CheckErrors::NonFunctionApplication => "NonFunctionApplication",
+            CheckErrors::ExpectedListApplication => "ExpectedListApplication",
+            CheckErrors::ExpectedSequence(..) => "ExpectedSequence",
+            CheckErrors::MaxLengthOverflow => "MaxLengthOverflow",
+            CheckErrors::BadLetSyntax => "BadLetSyntax",
+            CheckErrors::BadSyntaxBinding => "BadSyntaxBinding",
+            CheckErrors::BadSyntaxExpectedListOfPairs => "BadSyntaxExpectedListOfPairs",
+            CheckErrors::MaxContextDepthReached => "MaxContextDepthReached",
+            CheckErrors::UndefinedFunction(..) => "UndefinedFunction",
+            CheckErrors::UndefinedVariable(..) => "UndefinedVariable",
+            CheckErrors::RequiresAtLeastArguments(..) => "RequiresAtLeastArguments",
+            CheckErrors::RequiresAtMostArguments(..) => "RequiresAtMostArguments",
+            CheckErrors::IncorrectArgumentCount(..) => "IncorrectArgumentCount",
+            CheckErrors::IfArmsMustMatch(..) => "IfArmsMustMatch",
+            CheckErrors::MatchArmsMustMatch(..) => "MatchArmsMustMatch",
+            CheckErrors::DefaultTypesMustMatch(..) => "DefaultTypesMustMatch",
+            CheckErrors::TooManyExpressions => "TooManyExpressions",
+            CheckErrors::IllegalOrUnknownFunctionApplication(..) => {
+                "IllegalOrUnknownFunctionApplication"
+            }
+            CheckErrors::UnknownFunction(..) => "UnknownFunction",
+            CheckErrors::NoSuchTrait(..) => "NoSuchTrait",
+            CheckErrors::TraitReferenceUnknown(..) => "TraitReferenceUnknown",
+            CheckErrors::TraitMethodUnknown(..) => "TraitMethodUnknown",
+            CheckErrors::ExpectedTraitIdentifier => "ExpectedTraitIdentifier",
+            CheckErrors::ImportTraitBadSignature => "ImportTraitBadSignature",
+            CheckErrors::TraitReferenceNotAllowed => "TraitReferenceNotAllowed",
+            CheckErrors::BadTraitImplementation(..) => "BadTraitImplementation",
+            CheckErrors::DefineTraitBadSignature => "DefineTraitBadSignature",
+            CheckErrors::DefineTraitDuplicateMethod(..) => "DefineTraitDuplicateMethod",
+            CheckErrors::UnexpectedTraitOrFieldReference => "UnexpectedTraitOrFieldReference",
+            CheckErrors::TraitBasedContractCallInReadOnly => "TraitBasedContractCallInReadOnly",
+            CheckErrors::ContractOfExpectsTrait => "ContractOfExpectsTrait",
+            CheckErrors::IncompatibleTrait(..) => "IncompatibleTrait",
+            CheckErrors::InvalidCharactersDetected => "InvalidCharactersDetected",
+            CheckErrors::InvalidUTF8Encoding => "InvalidUTF8Encoding",
+            CheckErrors::InvalidSecp65k1Signature => "InvalidSecp65k1Signature",
+            CheckErrors::WriteAttemptedInReadOnly => "WriteAttemptedInReadOnly",
+            CheckErrors::AtBlockClosureMustBeReadOnly => "AtBlockClosureMustBeReadOnly",
+        };
+        Some(code.into())
+    }
 }
@@ -0,0 +1,699 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The linter reports suspicious, but valid, code in a contract as warnings.
+//!
+//! Unlike the other analysis passes, the linter never rejects a contract and it is
+//! not part of `run_analysis`: it is run on demand by developer tools, on a contract
+//! which has already been type-checked.
+
+use std::collections::BTreeMap;
+
+use hashbrown::HashSet;
+
+use super::errors::CheckResult;
+use crate::vm::analysis::type_checker::contexts::TypeMap;
+use crate::vm::analysis::types::ContractAnalysis;
+use crate::vm::diagnostic::{Diagnostic, Level};
+use crate::vm::functions::define::DefineFunctionsParsed;
+use crate::vm::functions::NativeFunctions;
+use crate::vm::representations::{ClarityName, SymbolicExpression};
+use crate::vm::types::signatures::{SequenceSubtype, StringSubtype};
+use crate::vm::types::TypeSignature;
+use crate::vm::variables::NativeVariables;
+use crate::vm::{is_reserved, ClarityVersion};
+
+#[cfg(test)]
+mod tests;
+
+/// Sequences longer than this are reported by `unbounded-iteration` by default
+pub const DEFAULT_MAX_ITERATION_LENGTH: u64 = 200;
+
+/// The rules checked by the `Linter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// A `let` binding which is never referenced
+    UnusedBinding,
+    /// A private function which is never called
+    UnusedPrivateFunction,
+    /// A constant which is never referenced
+    UnusedConstant,
+    /// `unwrap-panic` or `unwrap-err-panic` on a value derived from the arguments of a
+    /// public function, which aborts the transaction without an error code
+    UnwrapPanicOnInput,
+    /// `asserts!` on `is-ok`, `is-err`, `is-some` or `is-none` of a call with side
+    /// effects, which discards the error returned by the call
+    UncheckedResultInAsserts,
+    /// A name which is reserved by a later Clarity version
+    ShadowedName,
+    /// Authorization by comparing `tx-sender`, which any contract the sender calls
+    /// can act as
+    TxSenderAuthorization,
+    /// `map`, `filter` or `fold` over a sequence which can be very long
+    UnboundedIteration,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 8] = [
+        LintRule::UnusedBinding,
+        LintRule::UnusedPrivateFunction,
+        LintRule::UnusedConstant,
+        LintRule::UnwrapPanicOnInput,
+        LintRule::UncheckedResultInAsserts,
+        LintRule::ShadowedName,
+        LintRule::TxSenderAuthorization,
+        LintRule::UnboundedIteration,
+    ];
+
+    /// The name of the rule, as used in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::UnusedBinding => "unused-binding",
+            LintRule::UnusedPrivateFunction => "unused-private-function",
+            LintRule::UnusedConstant => "unused-constant",
+            LintRule::UnwrapPanicOnInput => "unwrap-panic-on-input",
+            LintRule::UncheckedResultInAsserts => "unchecked-result-in-asserts",
+            LintRule::ShadowedName => "shadowed-name",
+            LintRule::TxSenderAuthorization => "tx-sender-authorization",
+            LintRule::UnboundedIteration => "unbounded-iteration",
+        }
+    }
+}
+
+/// Configuration of the `Linter`, usually read from a JSON file such as:
+///
+/// ```json
+/// {
+///     "rules": { "tx-sender-authorization": false },
+///     "max-iteration-length": 500
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LintConfig {
+    /// Rules which are switched on or off. Rules which are not listed are enabled.
+    pub rules: BTreeMap<LintRule, bool>,
+    /// Longest sequence which `map`, `filter` and `fold` may iterate over without
+    /// being reported by `unbounded-iteration`
+    pub max_iteration_length: u64,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: BTreeMap::new(),
+            max_iteration_length: DEFAULT_MAX_ITERATION_LENGTH,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn from_json(json: &str) -> Result<LintConfig, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        self.rules.get(&rule).copied().unwrap_or(true)
+    }
+}
+
+/// `Linter` checks a type-checked contract against the enabled `LintRule`s, and
+/// returns its findings as warnings.
+pub struct Linter<'a> {
+    config: &'a LintConfig,
+    clarity_version: ClarityVersion,
+    type_map: Option<&'a TypeMap>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a LintConfig) -> Linter<'a> {
+        Self {
+            config,
+            clarity_version: ClarityVersion::latest(),
+            type_map: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Lints `contract_analysis`, returning the warnings ordered by position.
+    /// `unbounded-iteration` needs the type map of the contract, and is skipped
+    /// when the analysis was run without one.
+    pub fn run(mut self, contract_analysis: &'a ContractAnalysis) -> CheckResult<Vec<Diagnostic>> {
+        self.clarity_version = contract_analysis.clarity_version;
+        self.type_map = contract_analysis.type_map.as_ref();
+
+        let mut used = HashSet::new();
+        for expression in contract_analysis.expressions.iter() {
+            collect_top_level_references(expression, self.clarity_version, &mut used)?;
+        }
+        let is_unused = |name: &ClarityName| !used.contains(name);
+
+        for expression in contract_analysis.expressions.iter() {
+            let define = match DefineFunctionsParsed::try_parse(expression)? {
+                Some(define) => define,
+                None => {
+                    self.check_expression(expression, &mut HashSet::new());
+                    continue;
+                }
+            };
+            match define {
+                DefineFunctionsParsed::Constant { name, value } => {
+                    self.check_shadowed_name(name, expression);
+                    if is_unused(name) {
+                        self.report(
+                            LintRule::UnusedConstant,
+                            expression,
+                            format!("constant `{}` is never used", name),
+                            None,
+                        );
+                    }
+                    self.check_expression(value, &mut HashSet::new());
+                }
+                DefineFunctionsParsed::PrivateFunction { signature, body } => {
+                    self.check_signature(signature);
+                    if let Some(name) = signature.first().and_then(|e| e.match_atom()) {
+                        if is_unused(name) {
+                            self.report(
+                                LintRule::UnusedPrivateFunction,
+                                expression,
+                                format!("private function `{}` is never called", name),
+                                None,
+                            );
+                        }
+                    }
+                    self.check_expression(body, &mut HashSet::new());
+                }
+                DefineFunctionsParsed::ReadOnlyFunction { signature, body } => {
+                    self.check_signature(signature);
+                    self.check_expression(body, &mut HashSet::new());
+                }
+                DefineFunctionsParsed::PublicFunction { signature, body } => {
+                    let mut inputs = self.check_signature(signature);
+                    self.check_expression(body, &mut inputs);
+                }
+                DefineFunctionsParsed::PersistedVariable { name, initial, .. } => {
+                    self.check_shadowed_name(name, expression);
+                    self.check_expression(initial, &mut HashSet::new());
+                }
+                DefineFunctionsParsed::BoundedFungibleToken { name, max_supply } => {
+                    self.check_shadowed_name(name, expression);
+                    self.check_expression(max_supply, &mut HashSet::new());
+                }
+                DefineFunctionsParsed::NonFungibleToken { name, .. }
+                | DefineFunctionsParsed::UnboundedFungibleToken { name }
+                | DefineFunctionsParsed::Map { name, .. }
+                | DefineFunctionsParsed::Trait { name, .. }
+                | DefineFunctionsParsed::UseTrait { name, .. } => {
+                    self.check_shadowed_name(name, expression);
+                }
+                DefineFunctionsParsed::ImplTrait { .. } => {}
+            }
+        }
+
+        self.diagnostics.sort_by_key(|diagnostic| {
+            diagnostic
+                .spans
+                .first()
+                .map(|span| (span.start_line, span.start_column))
+        });
+        Ok(self.diagnostics)
+    }
+
+    fn report(
+        &mut self,
+        rule: LintRule,
+        expression: &SymbolicExpression,
+        message: String,
+        suggestion: Option<String>,
+    ) {
+        if !self.config.is_enabled(rule) {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            level: Level::Warning,
+            message: format!("{} [{}]", message, rule.name()),
+            spans: vec![expression.span().clone()],
+            suggestion,
+            code: Some(rule.name().to_string()),
+        });
+    }
+
+    /// Checks the names of a function signature, returning its parameter names
+    fn check_signature(&mut self, signature: &[SymbolicExpression]) -> HashSet<ClarityName> {
+        let mut parameters = HashSet::new();
+        if let Some(name_expression) = signature.first() {
+            if let Some(name) = name_expression.match_atom() {
+                self.check_shadowed_name(name, name_expression);
+            }
+        }
+        for parameter in signature.iter().skip(1) {
+            if let Some(name) = parameter
+                .match_list()
+                .and_then(|pair| pair.first())
+                .and_then(|e| e.match_atom())
+            {
+                self.check_shadowed_name(name, parameter);
+                parameters.insert(name.clone());
+            }
+        }
+        parameters
+    }
+
+    fn check_shadowed_name(&mut self, name: &ClarityName, expression: &SymbolicExpression) {
+        let latest = ClarityVersion::latest();
+        if is_reserved(name, &latest) && !is_reserved(name, &self.clarity_version) {
+            self.report(
+                LintRule::ShadowedName,
+                expression,
+                format!("`{}` shadows a builtin in {}", name, latest),
+                Some(format!(
+                    "rename `{}` so that the contract can be upgraded to {}",
+                    name, latest
+                )),
+            );
+        }
+    }
+
+    /// Checks `expression` and its subexpressions. `inputs` holds the names bound to
+    /// values derived from the arguments of a public function.
+    fn check_expression(
+        &mut self,
+        expression: &SymbolicExpression,
+        inputs: &mut HashSet<ClarityName>,
+    ) {
+        let list = match expression.match_list() {
+            Some(list) => list,
+            None => return,
+        };
+        let native = list.first().and_then(|e| e.match_atom()).and_then(|name| {
+            NativeFunctions::lookup_by_name_at_version(name, &self.clarity_version)
+        });
+        let args = list.get(1..).unwrap_or_default();
+        match native {
+            Some(NativeFunctions::Let) => return self.check_let(args, inputs),
+            Some(NativeFunctions::Match) => return self.check_match(args, inputs),
+            Some(NativeFunctions::Unwrap) | Some(NativeFunctions::UnwrapErr)
+                if args.iter().any(|arg| references_any(arg, inputs)) =>
+            {
+                self.report(
+                    LintRule::UnwrapPanicOnInput,
+                    expression,
+                    "unwrap of a value derived from the function arguments can abort the transaction without an error code".into(),
+                    Some("use `unwrap!` or `unwrap-err!` to return an error instead".into()),
+                );
+            }
+            Some(NativeFunctions::Asserts) => {
+                if let Some(condition) = args.first() {
+                    self.check_asserts_condition(condition);
+                    self.check_authorization(condition);
+                }
+            }
+            Some(NativeFunctions::If) => {
+                if let Some(condition) = args.first() {
+                    self.check_authorization(condition);
+                }
+            }
+            Some(NativeFunctions::Map)
+            | Some(NativeFunctions::Filter)
+            | Some(NativeFunctions::Fold) => self.check_iteration(expression, args),
+            _ => {}
+        }
+        for subexpression in list.iter() {
+            self.check_expression(subexpression, inputs);
+        }
+    }
+
+    fn check_let(&mut self, args: &[SymbolicExpression], inputs: &mut HashSet<ClarityName>) {
+        let (bindings, body) = match args.split_first() {
+            Some((bindings, body)) => (bindings.match_list().unwrap_or_default(), body),
+            None => return,
+        };
+        for (i, binding) in bindings.iter().enumerate() {
+            let pair = binding.match_list().unwrap_or_default();
+            let (name, value) = match (pair.first().and_then(|e| e.match_atom()), pair.get(1)) {
+                (Some(name), Some(value)) => (name, value),
+                _ => continue,
+            };
+            self.check_shadowed_name(name, binding);
+            self.check_expression(value, inputs);
+            if references_any(value, inputs) {
+                inputs.insert(name.clone());
+            }
+
+            let mut used = HashSet::new();
+            for later in bindings[i + 1..]
+                .iter()
+                .filter_map(|later| later.match_list().and_then(|pair| pair.get(1)))
+                .chain(body.iter())
+            {
+                collect_references(later, self.clarity_version, &mut vec![], &mut used);
+            }
+            let is_used = used.contains(name);
+            if !is_used {
+                self.report(
+                    LintRule::UnusedBinding,
+                    binding,
+                    format!("let binding `{}` is never used", name),
+                    None,
+                );
+            }
+        }
+        for expression in body.iter() {
+            self.check_expression(expression, inputs);
+        }
+    }
+
+    fn check_match(&mut self, args: &[SymbolicExpression], inputs: &mut HashSet<ClarityName>) {
+        let (input, branches) = match args.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        self.check_expression(input, inputs);
+        let is_input = references_any(input, inputs);
+        // `(match opt some-name some-branch none-branch)` or
+        // `(match res ok-name ok-branch err-name err-branch)`
+        for (i, branch) in branches.iter().enumerate() {
+            if i % 2 == 0 && i + 1 < branches.len() {
+                if let Some(name) = branch.match_atom() {
+                    self.check_shadowed_name(name, branch);
+                    if is_input {
+                        inputs.insert(name.clone());
+                    }
+                    continue;
+                }
+            }
+            self.check_expression(branch, inputs);
+        }
+    }
+
+    fn check_asserts_condition(&mut self, condition: &SymbolicExpression) {
+        let (function, args) = match condition.match_list().and_then(|list| list.split_first()) {
+            Some(split) => split,
+            None => return,
+        };
+        let is_check = matches!(
+            function
+                .match_atom()
+                .and_then(|name| NativeFunctions::lookup_by_name_at_version(
+                    name,
+                    &self.clarity_version
+                )),
+            Some(NativeFunctions::IsOkay)
+                | Some(NativeFunctions::IsErr)
+                | Some(NativeFunctions::IsSome)
+                | Some(NativeFunctions::IsNone)
+        );
+        if !is_check {
+            return;
+        }
+        if args.iter().any(|arg| self.has_side_effects(arg)) {
+            self.report(
+                LintRule::UncheckedResultInAsserts,
+                condition,
+                "`asserts!` discards the error returned by a call with side effects".into(),
+                Some("use `try!` or `unwrap!` to propagate the error of the call".into()),
+            );
+        }
+    }
+
+    fn has_side_effects(&self, expression: &SymbolicExpression) -> bool {
+        let list = match expression.match_list() {
+            Some(list) => list,
+            None => return false,
+        };
+        let native = list.first().and_then(|e| e.match_atom()).and_then(|name| {
+            NativeFunctions::lookup_by_name_at_version(name, &self.clarity_version)
+        });
+        use crate::vm::functions::NativeFunctions::*;
+        if matches!(
+            native,
+            Some(ContractCall)
+                | Some(StxTransfer)
+                | Some(StxTransferMemo)
+                | Some(StxBurn)
+                | Some(TransferToken)
+                | Some(MintToken)
+                | Some(BurnToken)
+                | Some(TransferAsset)
+                | Some(MintAsset)
+                | Some(BurnAsset)
+        ) {
+            return true;
+        }
+        list.iter().any(|e| self.has_side_effects(e))
+    }
+
+    fn check_authorization(&mut self, condition: &SymbolicExpression) {
+        let list = match condition.match_list() {
+            Some(list) => list,
+            None => return,
+        };
+        let native = list.first().and_then(|e| e.match_atom()).and_then(|name| {
+            NativeFunctions::lookup_by_name_at_version(name, &self.clarity_version)
+        });
+        match native {
+            Some(NativeFunctions::Equals) => {
+                let is_native_variable = |e: &SymbolicExpression, variable: NativeVariables| {
+                    e.match_atom()
+                        .and_then(|name| {
+                            NativeVariables::lookup_by_name_at_version(name, &self.clarity_version)
+                        })
+                        .is_some_and(|found| found == variable)
+                };
+                let args = list.get(1..).unwrap_or_default();
+                if args
+                    .iter()
+                    .any(|arg| is_native_variable(arg, NativeVariables::TxSender))
+                    && !args
+                        .iter()
+                        .any(|arg| is_native_variable(arg, NativeVariables::ContractCaller))
+                {
+                    self.report(
+                        LintRule::TxSenderAuthorization,
+                        condition,
+                        "authorization by `tx-sender` can be exercised by any contract the sender calls".into(),
+                        Some("compare `contract-caller` instead, unless the check is meant to allow calls through other contracts".into()),
+                    );
+                }
+            }
+            Some(NativeFunctions::And) | Some(NativeFunctions::Or) | Some(NativeFunctions::Not) => {
+                for arg in list.iter().skip(1) {
+                    self.check_authorization(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_iteration(&mut self, expression: &SymbolicExpression, args: &[SymbolicExpression]) {
+        let type_map = match self.type_map {
+            Some(type_map) => type_map,
+            None => return,
+        };
+        // the first argument is the function to apply, and the last argument of `fold` is
+        // its initial value
+        let longest = args
+            .iter()
+            .skip(1)
+            .filter_map(|arg| match type_map.get_type_expected(arg) {
+                Some(TypeSignature::SequenceType(sequence)) => Some(sequence_max_len(sequence)),
+                _ => None,
+            })
+            .max();
+        if let Some(longest) = longest {
+            if longest > self.config.max_iteration_length {
+                self.report(
+                    LintRule::UnboundedIteration,
+                    expression,
+                    format!(
+                        "iteration over a sequence of up to {} elements, more than the limit of {}",
+                        longest, self.config.max_iteration_length
+                    ),
+                    Some("bound the length of the sequence in its type".into()),
+                );
+            }
+        }
+    }
+}
+
+fn sequence_max_len(sequence_type: &SequenceSubtype) -> u64 {
+    match sequence_type {
+        SequenceSubtype::ListType(list_type) => u64::from(list_type.get_max_len()),
+        SequenceSubtype::BufferType(len)
+        | SequenceSubtype::StringType(StringSubtype::ASCII(len)) => u64::from(u32::from(len)),
+        SequenceSubtype::StringType(StringSubtype::UTF8(len)) => u64::from(u32::from(len)),
+    }
+}
+
+/// Collects the names of contract definitions which are referenced by a top-level expression.
+/// The name of a definition, and the types in its signature, are not references.
+fn collect_top_level_references(
+    expression: &SymbolicExpression,
+    version: ClarityVersion,
+    used: &mut HashSet<ClarityName>,
+) -> CheckResult<()> {
+    let define = match DefineFunctionsParsed::try_parse(expression)? {
+        Some(define) => define,
+        None => {
+            collect_references(expression, version, &mut vec![], used);
+            return Ok(());
+        }
+    };
+    match define {
+        DefineFunctionsParsed::Constant { value, .. } => {
+            collect_references(value, version, &mut vec![], used)
+        }
+        DefineFunctionsParsed::PrivateFunction { signature, body }
+        | DefineFunctionsParsed::ReadOnlyFunction { signature, body }
+        | DefineFunctionsParsed::PublicFunction { signature, body } => {
+            let mut parameters = signature
+                .iter()
+                .skip(1)
+                .filter_map(|parameter| {
+                    parameter
+                        .match_list()
+                        .and_then(|pair| pair.first())
+                        .and_then(|e| e.match_atom())
+                        .cloned()
+                })
+                .collect();
+            collect_references(body, version, &mut parameters, used);
+        }
+        DefineFunctionsParsed::PersistedVariable { initial, .. } => {
+            collect_references(initial, version, &mut vec![], used)
+        }
+        DefineFunctionsParsed::BoundedFungibleToken { max_supply, .. } => {
+            collect_references(max_supply, version, &mut vec![], used)
+        }
+        DefineFunctionsParsed::NonFungibleToken { .. }
+        | DefineFunctionsParsed::UnboundedFungibleToken { .. }
+        | DefineFunctionsParsed::Map { .. }
+        | DefineFunctionsParsed::Trait { .. }
+        | DefineFunctionsParsed::UseTrait { .. }
+        | DefineFunctionsParsed::ImplTrait { .. } => {}
+    }
+    Ok(())
+}
+
+/// Collects the names referenced by `expression` which are not bound by one of the enclosing
+/// `locals` (function parameters, `let` and `match` bindings). Tuple keys, the field names of
+/// `get` and the function names of `contract-call?` are not references.
+fn collect_references(
+    expression: &SymbolicExpression,
+    version: ClarityVersion,
+    locals: &mut Vec<ClarityName>,
+    used: &mut HashSet<ClarityName>,
+) {
+    if let Some(name) = expression.match_atom() {
+        if !locals.contains(name) {
+            used.insert(name.clone());
+        }
+        return;
+    }
+    let list = match expression.match_list() {
+        Some(list) => list,
+        None => return,
+    };
+    let native = list
+        .first()
+        .and_then(|e| e.match_atom())
+        .and_then(|name| NativeFunctions::lookup_by_name_at_version(name, &version));
+    let args = list.get(1..).unwrap_or_default();
+    match native {
+        Some(NativeFunctions::Let) => {
+            let (bindings, body) = match args.split_first() {
+                Some((bindings, body)) => (bindings.match_list().unwrap_or_default(), body),
+                None => return,
+            };
+            let scope = locals.len();
+            for binding in bindings.iter() {
+                let pair = binding.match_list().unwrap_or_default();
+                if let Some(value) = pair.get(1) {
+                    collect_references(value, version, locals, used);
+                }
+                if let Some(name) = pair.first().and_then(|e| e.match_atom()) {
+                    locals.push(name.clone());
+                }
+            }
+            for expression in body.iter() {
+                collect_references(expression, version, locals, used);
+            }
+            locals.truncate(scope);
+        }
+        Some(NativeFunctions::Match) => {
+            let (input, branches) = match args.split_first() {
+                Some(split) => split,
+                None => return,
+            };
+            collect_references(input, version, locals, used);
+            let mut i = 0;
+            while i < branches.len() {
+                match branches[i].match_atom() {
+                    // a binding name, which is in scope in the branch after it
+                    Some(name) if i % 2 == 0 && i + 1 < branches.len() => {
+                        locals.push(name.clone());
+                        collect_references(&branches[i + 1], version, locals, used);
+                        locals.pop();
+                        i += 2;
+                    }
+                    _ => {
+                        collect_references(&branches[i], version, locals, used);
+                        i += 1;
+                    }
+                }
+            }
+        }
+        Some(NativeFunctions::TupleCons) => {
+            for pair in args.iter() {
+                if let Some(value) = pair.match_list().and_then(|pair| pair.get(1)) {
+                    collect_references(value, version, locals, used);
+                }
+            }
+        }
+        Some(NativeFunctions::TupleGet) => {
+            for arg in args.iter().skip(1) {
+                collect_references(arg, version, locals, used);
+            }
+        }
+        Some(NativeFunctions::ContractCall) => {
+            for (i, arg) in args.iter().enumerate() {
+                if i != 1 {
+                    collect_references(arg, version, locals, used);
+                }
+            }
+        }
+        _ => {
+            for subexpression in list.iter() {
+                collect_references(subexpression, version, locals, used);
+            }
+        }
+    }
+}
+
+fn references_any(expression: &SymbolicExpression, names: &HashSet<ClarityName>) -> bool {
+    if names.is_empty() {
+        return false;
+    }
+    if let Some(atom) = expression.match_atom() {
+        names.contains(atom)
+    } else if let Some(list) = expression.match_list() {
+        list.iter().any(|e| references_any(e, names))
+    } else {
+        false
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use stacks_common::types::StacksEpochId;
+
+use super::{LintConfig, LintRule, Linter};
+use crate::vm::analysis::mem_type_check;
+use crate::vm::diagnostic::Level;
+use crate::vm::ClarityVersion;
+
+fn lint_with(snippet: &str, version: ClarityVersion, config: &LintConfig) -> Vec<String> {
+    let (_, analysis) = mem_type_check(snippet, version, StacksEpochId::latest()).unwrap();
+    let diagnostics = Linter::new(config).run(&analysis).unwrap();
+    assert!(diagnostics.iter().all(|d| d.level == Level::Warning));
+    diagnostics.into_iter().map(|d| d.message).collect()
+}
+
+fn lint(snippet: &str) -> Vec<String> {
+    lint_with(snippet, ClarityVersion::latest(), &LintConfig::default())
+}
+
+#[test]
+fn test_unused_definitions() {
+    let contract = "(define-constant used u1)
+        (define-constant unused u2)
+        (define-private (helper (x uint)) (+ x used))
+        (define-private (dead) u0)
+        (define-read-only (get-value)
+            (let ((a (helper u1)) (b u2) (c (+ a u1)))
+                c))";
+    assert_eq!(
+        lint(contract),
+        vec![
+            "constant `unused` is never used [unused-constant]",
+            "private function `dead` is never called [unused-private-function]",
+            "let binding `b` is never used [unused-binding]",
+        ]
+    );
+
+    // functions passed to `map` are used
+    let contract = "(define-private (double (x uint)) (* x u2))
+        (define-read-only (double-all (l (list 10 uint))) (map double l))";
+    assert!(lint(contract).is_empty());
+}
+
+#[test]
+fn test_unused_definitions_are_resolved_by_scope() {
+    // tuple keys, `get` fields and the functions of `contract-call?` are not references
+    let contract = "(define-constant owner 'SP000000000000000000002Q6VF78)
+        (define-constant amount u1)
+        (define-private (transfer) u0)
+        (define-read-only (get-entry)
+            (let ((entry {owner: tx-sender, amount: u2}))
+                (get owner entry)))
+        (define-public (call-transfer (target <trait>))
+            (contract-call? target transfer))
+        (define-trait trait ((transfer () (response uint uint))))";
+    assert_eq!(
+        lint(contract),
+        vec![
+            "constant `owner` is never used [unused-constant]",
+            "constant `amount` is never used [unused-constant]",
+            "private function `transfer` is never called [unused-private-function]",
+        ]
+    );
+
+    // nor are they references to `let` bindings
+    let contract = "(define-read-only (make-pair)
+            (let ((first u1) (second u2))
+                {first: second, second: u3}))";
+    assert_eq!(
+        lint(contract),
+        vec!["let binding `first` is never used [unused-binding]"]
+    );
+}
+
+#[test]
+fn test_unwrap_panic_on_input() {
+    let contract = "(define-map balances principal uint)
+        (define-public (withdraw (who principal) (amount (optional uint)))
+            (let ((requested (unwrap-panic amount))
+                  (stored (unwrap-panic (map-get? balances who))))
+                (ok (- stored requested))))
+        (define-public (fixed)
+            (ok (unwrap-panic (some u1))))";
+    assert_eq!(
+        lint(contract),
+        vec![
+            "unwrap of a value derived from the function arguments can abort the transaction without an error code [unwrap-panic-on-input]",
+            "unwrap of a value derived from the function arguments can abort the transaction without an error code [unwrap-panic-on-input]",
+        ]
+    );
+
+    // values derived through `match` bindings are inputs too
+    let contract = "(define-public (first (l (list 5 (optional uint))))
+            (match (element-at? l u0)
+                item (ok (unwrap-panic item))
+                (err u1)))";
+    assert_eq!(lint(contract).len(), 1);
+}
+
+#[test]
+fn test_unchecked_result_in_asserts() {
+    let contract = "(define-public (pay (to principal))
+            (begin
+                (asserts! (is-ok (stx-transfer? u10 tx-sender to)) (err u1))
+                (asserts! (is-some (some to)) (err u2))
+                (ok true)))";
+    assert_eq!(
+        lint(contract),
+        vec!["`asserts!` discards the error returned by a call with side effects [unchecked-result-in-asserts]"]
+    );
+}
+
+#[test]
+fn test_shadowed_name() {
+    // `tenure-height` is a builtin since Clarity 3
+    let contract = "(define-data-var tenure-height uint u0)
+        (define-read-only (get-height) (var-get tenure-height))";
+    assert_eq!(
+        lint_with(contract, ClarityVersion::Clarity2, &LintConfig::default()),
+        vec!["`tenure-height` shadows a builtin in Clarity 3 [shadowed-name]"]
+    );
+
+    // `is-standard` is a builtin since Clarity 2
+    let contract = "(define-read-only (get (is-standard bool)) is-standard)";
+    assert_eq!(
+        lint_with(contract, ClarityVersion::Clarity1, &LintConfig::default()),
+        vec!["`is-standard` shadows a builtin in Clarity 3 [shadowed-name]"]
+    );
+}
+
+#[test]
+fn test_tx_sender_authorization() {
+    let contract = "(define-constant owner tx-sender)
+        (define-data-var value uint u0)
+        (define-public (set-value (v uint))
+            (begin
+                (asserts! (and (> v u0) (is-eq tx-sender owner)) (err u1))
+                (ok (var-set value v))))
+        (define-public (set-value-caller (v uint))
+            (begin
+                (asserts! (is-eq contract-caller owner) (err u1))
+                (ok (var-set value v))))";
+    assert_eq!(
+        lint(contract),
+        vec!["authorization by `tx-sender` can be exercised by any contract the sender calls [tx-sender-authorization]"]
+    );
+}
+
+#[test]
+fn test_unbounded_iteration() {
+    let contract = "(define-private (add (x uint) (sum uint)) (+ x sum))
+        (define-read-only (small (l (list 10 uint))) (fold add l u0))
+        (define-read-only (large (l (list 1000 uint))) (fold add l u0))";
+    assert_eq!(
+        lint(contract),
+        vec!["iteration over a sequence of up to 1000 elements, more than the limit of 200 [unbounded-iteration]"]
+    );
+
+    let config = LintConfig::from_json(r#"{"max-iteration-length": 1000}"#).unwrap();
+    assert!(lint_with(contract, ClarityVersion::latest(), &config).is_empty());
+}
+
+#[test]
+fn test_config() {
+    let config =
+        LintConfig::from_json(r#"{"rules": {"unused-constant": false, "unused-binding": true}}"#)
+            .unwrap();
+    assert!(!config.is_enabled(LintRule::UnusedConstant));
+    for rule in LintRule::ALL
+        .iter()
+        .filter(|r| **r != LintRule::UnusedConstant)
+    {
+        assert!(config.is_enabled(*rule));
+    }
+
+    let contract = "(define-constant unused u2)
+        (define-read-only (get-value) (let ((b u2)) u1))";
+    assert_eq!(
+        lint_with(contract, ClarityVersion::latest(), &config),
+        vec!["let binding `b` is never used [unused-binding]"]
+    );
+
+    assert!(LintConfig::from_json(r#"{"rules": {"no-such-rule": false}}"#).is_err());
+    assert!(LintConfig::from_json(r#"{"max-length": 10}"#).is_err());
+}
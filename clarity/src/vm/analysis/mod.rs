@@ -17,8 +17,11 @@
 pub mod analysis_db;
 pub mod arithmetic_checker;
 pub mod contract_interface_builder;
+pub mod cost_checker;
 #[allow(clippy::result_large_err)]
 pub mod errors;
+#[allow(clippy::result_large_err)]
+pub mod linter;
 pub mod read_only_checker;
 pub mod trait_checker;
 pub mod type_checker;
@@ -21,6 +21,7 @@ use stacks_common::types::StacksEpochId;
 
 use crate::vm::analysis::analysis_db::AnalysisDatabase;
 use crate::vm::analysis::contract_interface_builder::ContractInterface;
+use crate::vm::analysis::cost_checker::FunctionCostBound;
 use crate::vm::analysis::errors::{CheckErrors, CheckResult};
 use crate::vm::analysis::type_checker::contexts::TypeMap;
 use crate::vm::costs::{CostTracker, ExecutionCost, LimitedCostTracker};
@@ -56,6 +57,10 @@ pub struct ContractAnalysis {
     pub implemented_traits: BTreeSet<TraitIdentifier>,
     pub contract_interface: Option<ContractInterface>,
     pub is_cost_contract_eligible: bool,
+    /// Upper bounds on the cost of each public and read-only function, if computed by the
+    /// `CostChecker`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_cost_bounds: Option<BTreeMap<ClarityName, FunctionCostBound>>,
     pub epoch: StacksEpochId,
     pub clarity_version: ClarityVersion,
     #[serde(skip)]
@@ -91,6 +96,7 @@ impl ContractAnalysis {
             non_fungible_tokens: BTreeMap::new(),
             cost_track: Some(cost_track),
             is_cost_contract_eligible: false,
+            function_cost_bounds: None,
             epoch,
             clarity_version,
         }
@@ -89,16 +89,20 @@ impl DefinitionSorter {
         let sorted_indexes = walker.get_sorted_dependencies(&self.graph)?;
 
         if let Some(deps) = walker.get_cycling_dependencies(&self.graph, &sorted_indexes) {
-            let functions_names = deps
+            let cycle = deps
                 .into_iter()
                 .filter_map(|i| {
                     let exp = &contract_ast.pre_expressions[i];
                     self.find_expression_definition(exp)
+                        .map(|(name, _, name_expression)| {
+                            (name.to_string(), name_expression.clone())
+                        })
                 })
-                .map(|i| i.0.to_string())
                 .collect::<Vec<_>>();
+            let (functions_names, definitions): (Vec<_>, Vec<_>) = cycle.into_iter().unzip();
 
-            let error = ParseError::new(ParseErrors::CircularReference(functions_names));
+            let mut error = ParseError::new(ParseErrors::CircularReference(functions_names));
+            error.set_pre_expressions(definitions);
             return Err(error);
         }
 
@@ -112,6 +112,25 @@ fn should_raise_dependency_cycle_case_2(#[case] version: ClarityVersion) {
     assert!(matches!(err.err, ParseErrors::CircularReference(_)));
 }
 
+#[test]
+fn should_locate_dependency_cycle() {
+    let contract = r#"
+        (define-private (a (x int)) (b x))
+        (define-private (b (x int)) (a x))
+    "#;
+
+    let err = run_scoped_parsing_helper(contract, ClarityVersion::Clarity2).unwrap_err();
+    assert_eq!(err.diagnostic.code.as_deref(), Some("CircularReference"));
+    // one span for the name of each definition in the cycle
+    assert_eq!(err.diagnostic.spans.len(), 2);
+    #[cfg(feature = "developer-mode")]
+    {
+        let mut lines: Vec<_> = err.diagnostic.spans.iter().map(|s| s.start_line).collect();
+        lines.sort();
+        assert_eq!(lines, vec![2, 3]);
+    }
+}
+
 #[apply(test_clarity_versions_definition_sorter)]
 fn should_not_raise_dependency_cycle_case_let(#[case] version: ClarityVersion) {
     let contract = r#"
@@ -318,6 +318,70 @@ impl DiagnosableError for ParseErrors {
             _ => Level::Error,
         }
     }
+    fn code(&self) -> Option<String> {
+        let code = match self {
+            ParseErrors::Lexer(lexer_error) => return lexer_error.code(),
+            ParseErrors::CostOverflow => "CostOverflow",
+            ParseErrors::CostBalanceExceeded(..) => "CostBalanceExceeded",
+            ParseErrors::MemoryBalanceExceeded(..) => "MemoryBalanceExceeded",
+            ParseErrors::TooManyExpressions => "TooManyExpressions",
+            ParseErrors::ExpressionStackDepthTooDeep => "ExpressionStackDepthTooDeep",
+            ParseErrors::VaryExpressionStackDepthTooDeep => "VaryExpressionStackDepthTooDeep",
+            ParseErrors::FailedCapturingInput => "FailedCapturingInput",
+            ParseErrors::SeparatorExpected(..) => "SeparatorExpected",
+            ParseErrors::SeparatorExpectedAfterColon(..) => "SeparatorExpectedAfterColon",
+            ParseErrors::ProgramTooLarge => "ProgramTooLarge",
+            ParseErrors::IllegalVariableName(..) => "IllegalVariableName",
+            ParseErrors::IllegalContractName(..) => "IllegalContractName",
+            ParseErrors::UnknownQuotedValue(..) => "UnknownQuotedValue",
+            ParseErrors::FailedParsingIntValue(..) => "FailedParsingIntValue",
+            ParseErrors::FailedParsingUIntValue(..) => "FailedParsingUIntValue",
+            ParseErrors::FailedParsingBuffer(..) => "FailedParsingBuffer",
+            ParseErrors::FailedParsingHexValue(..) => "FailedParsingHexValue",
+            ParseErrors::FailedParsingPrincipal(..) => "FailedParsingPrincipal",
+            ParseErrors::FailedParsingField(..) => "FailedParsingField",
+            ParseErrors::FailedParsingRemainder(..) => "FailedParsingRemainder",
+            ParseErrors::ClosingParenthesisUnexpected => "ClosingParenthesisUnexpected",
+            ParseErrors::ClosingParenthesisExpected => "ClosingParenthesisExpected",
+            ParseErrors::ClosingTupleLiteralUnexpected => "ClosingTupleLiteralUnexpected",
+            ParseErrors::ClosingTupleLiteralExpected => "ClosingTupleLiteralExpected",
+            ParseErrors::CircularReference(..) => "CircularReference",
+            ParseErrors::TupleColonExpected(..) => "TupleColonExpected",
+            ParseErrors::TupleCommaExpected(..) => "TupleCommaExpected",
+            ParseErrors::TupleItemExpected(..) => "TupleItemExpected",
+            ParseErrors::NameAlreadyUsed(..) => "NameAlreadyUsed",
+            ParseErrors::TraitReferenceNotAllowed => "TraitReferenceNotAllowed",
+            ParseErrors::ImportTraitBadSignature => "ImportTraitBadSignature",
+            ParseErrors::DefineTraitBadSignature => "DefineTraitBadSignature",
+            ParseErrors::ImplTraitBadSignature => "ImplTraitBadSignature",
+            ParseErrors::TraitReferenceUnknown(..) => "TraitReferenceUnknown",
+            ParseErrors::CommaSeparatorUnexpected => "CommaSeparatorUnexpected",
+            ParseErrors::ColonSeparatorUnexpected => "ColonSeparatorUnexpected",
+            ParseErrors::InvalidCharactersDetected => "InvalidCharactersDetected",
+            ParseErrors::InvalidEscaping => "InvalidEscaping",
+            ParseErrors::CostComputationFailed(..) => "CostComputationFailed",
+            ParseErrors::ContractNameTooLong(..) => "ContractNameTooLong",
+            ParseErrors::ExpectedContractIdentifier => "ExpectedContractIdentifier",
+            ParseErrors::ExpectedTraitIdentifier => "ExpectedTraitIdentifier",
+            ParseErrors::IllegalTraitName(..) => "IllegalTraitName",
+            ParseErrors::InvalidPrincipalLiteral => "InvalidPrincipalLiteral",
+            ParseErrors::InvalidBuffer => "InvalidBuffer",
+            ParseErrors::NameTooLong(..) => "NameTooLong",
+            ParseErrors::UnexpectedToken(..) => "UnexpectedToken",
+            ParseErrors::ExpectedClosing(..) => "ExpectedClosing",
+            ParseErrors::TupleColonExpectedv2 => "TupleColonExpectedv2",
+            ParseErrors::TupleCommaExpectedv2 => "TupleCommaExpectedv2",
+            ParseErrors::TupleValueExpected => "TupleValueExpected",
+            ParseErrors::IllegalClarityName(..) => "IllegalClarityName",
+            ParseErrors::IllegalASCIIString(..) => "IllegalASCIIString",
+            ParseErrors::IllegalUtf8String(..) => "IllegalUtf8String",
+            ParseErrors::ExpectedWhitespace => "ExpectedWhitespace",
+            ParseErrors::NoteToMatchThis(..) => "NoteToMatchThis",
+            ParseErrors::UnexpectedParserFailure => "UnexpectedParserFailure",
+            ParseErrors::InterpreterFailure => "InterpreterFailure",
+        };
+        Some(code.into())
+    }
 }
 
 pub struct PlacedError {
@@ -0,0 +1,728 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A canonical pretty-printer for Clarity source code.
+//!
+//! The source is checked with the v2 parser, then read into a tree of lists, tuples, atoms and
+//! comments from the lexer's tokens, and the tree is printed back out:
+//!
+//! * a list or tuple which fits on the rest of its line is printed on one line, unless it is a
+//!   function definition, a `let` or a `begin`, whose bodies always go on their own lines;
+//! * otherwise, special forms (`define-*`, `let`, `match`, `if`, ...) keep their first few
+//!   arguments on the opening line and indent the rest by `INDENT_WIDTH`, `match` branches are
+//!   printed next to their binding names, and the arguments of other function calls are aligned
+//!   under the first one;
+//! * tuple literals which don't fit are printed with one `key: value` entry per line;
+//! * comments at the end of a line stay there, and other comments get a line of their own;
+//!   runs of blank lines between expressions are collapsed into one.
+//!
+//! Atoms, literals and comments are copied from the source as they are. Their positions come
+//! from the lexer rather than from the parsed expressions, which only record them in
+//! `developer-mode` builds.
+
+use crate::vm::ast::errors::{ParseError, ParseErrors, ParseResult};
+use crate::vm::ast::parser::v2::lexer::token::Token;
+use crate::vm::ast::parser::v2::lexer::Lexer;
+use crate::vm::ast::parser::v2::parse;
+use crate::vm::representations::{PreSymbolicExpression, PreSymbolicExpressionType, Span};
+
+/// The width the formatter tries to keep lines within
+pub const MAX_LINE_WIDTH: usize = 100;
+/// The indentation of the body of a special form
+pub const INDENT_WIDTH: usize = 2;
+
+/// Special forms whose body always goes on its own lines
+const BLOCK_FORMS: &[&str] = &[
+    "define-public",
+    "define-private",
+    "define-read-only",
+    "let",
+    "begin",
+];
+
+/// The number of arguments which special forms keep on their opening line when they don't fit
+/// on one line
+fn header_arguments(name: &str) -> Option<usize> {
+    match name {
+        "begin" | "tuple" => Some(0),
+        "define-public"
+        | "define-private"
+        | "define-read-only"
+        | "define-constant"
+        | "define-map"
+        | "define-fungible-token"
+        | "define-non-fungible-token"
+        | "define-trait"
+        | "let"
+        | "match"
+        | "if" => Some(1),
+        "define-data-var" | "contract-call?" => Some(2),
+        _ => None,
+    }
+}
+
+/// Format a Clarity program in the canonical layout.
+pub fn format_source(source: &str) -> ParseResult<String> {
+    parse(source)?;
+    let expressions = read_nodes(source)?;
+    let formatter = Formatter {
+        lines: source.lines().collect(),
+    };
+
+    let mut out = String::new();
+    for (i, item) in formatter.items(&expressions).iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if item.blank_before {
+                out.push('\n');
+            }
+        }
+        match item.piece {
+            Piece::Comment(ref comment) => out.push_str(comment),
+            Piece::Expression {
+                expression,
+                ref trailing,
+            } => {
+                out.push_str(&formatter.render(expression, 0, 0));
+                push_trailing(&mut out, trailing);
+            }
+        }
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Whether `formatted` is the same program as `source`: both parse, to the same expressions once
+/// comments and positions are left out.
+pub fn is_equivalent(source: &str, formatted: &str) -> bool {
+    match (parse(source), parse(formatted)) {
+        (Ok(source), Ok(formatted)) => strip(&source) == strip(&formatted),
+        _ => false,
+    }
+}
+
+/// The expressions of a program, without comments or spans
+fn strip(expressions: &[PreSymbolicExpression]) -> Vec<PreSymbolicExpression> {
+    expressions
+        .iter()
+        .filter(|expression| expression.match_comment().is_none())
+        .map(|expression| {
+            let mut expression = match expression.pre_expr {
+                PreSymbolicExpressionType::List(ref children) => {
+                    PreSymbolicExpression::list(strip(children))
+                }
+                PreSymbolicExpressionType::Tuple(ref children) => {
+                    PreSymbolicExpression::tuple(strip(children))
+                }
+                _ => expression.clone(),
+            };
+            expression.copy_span(&Span::zero());
+            expression.id = 0;
+            expression
+        })
+        .collect()
+}
+
+/// A node of the source, as the formatter sees it
+enum Node {
+    List(Vec<Node>, Span),
+    Tuple(Vec<Node>, Span),
+    /// An atom or a literal. `identifier` is set for the names which the parser reads as atoms.
+    Atom {
+        span: Span,
+        identifier: bool,
+    },
+    Comment(Span),
+}
+
+impl Node {
+    fn span(&self) -> &Span {
+        match self {
+            Node::List(_, span) | Node::Tuple(_, span) | Node::Comment(span) => span,
+            Node::Atom { span, .. } => span,
+        }
+    }
+
+    fn is_comment(&self) -> bool {
+        matches!(self, Node::Comment(_))
+    }
+}
+
+/// Read the nodes of a program which the parser accepts from the lexer's tokens. Tokens which
+/// are not separated by whitespace or punctuation, like the `.` and the name of a contract
+/// identifier, make up a single atom.
+fn read_nodes(source: &str) -> ParseResult<Vec<Node>> {
+    let mut lexer = Lexer::new(source, true).map_err(|e| ParseError::new(ParseErrors::Lexer(e)))?;
+    let mut open: Vec<(Vec<Node>, Span)> = vec![];
+    let mut nodes = vec![];
+    let mut in_atom = false;
+    loop {
+        let placed = lexer
+            .read_token()
+            .map_err(|e| ParseError::new(ParseErrors::Lexer(e)))?;
+        let mut continues_atom = false;
+        match placed.token {
+            Token::Eof => break,
+            Token::Whitespace | Token::Colon | Token::Comma => {}
+            Token::Lparen | Token::Lbrace => {
+                open.push((std::mem::take(&mut nodes), placed.span));
+            }
+            Token::Rparen | Token::Rbrace => {
+                let (parent, mut span) = open
+                    .pop()
+                    .ok_or_else(|| ParseError::new(ParseErrors::ClosingParenthesisUnexpected))?;
+                span.end_line = placed.span.end_line;
+                span.end_column = placed.span.end_column;
+                let children = std::mem::replace(&mut nodes, parent);
+                nodes.push(if placed.token == Token::Rparen {
+                    Node::List(children, span)
+                } else {
+                    Node::Tuple(children, span)
+                });
+            }
+            Token::Comment(_) => nodes.push(Node::Comment(placed.span)),
+            ref token => {
+                continues_atom = true;
+                match nodes.last_mut() {
+                    Some(Node::Atom { span, identifier }) if in_atom => {
+                        span.end_line = placed.span.end_line;
+                        span.end_column = placed.span.end_column;
+                        *identifier = false;
+                    }
+                    _ => nodes.push(Node::Atom {
+                        span: placed.span.clone(),
+                        identifier: matches!(
+                            token,
+                            Token::Ident(_)
+                                | Token::Plus
+                                | Token::Minus
+                                | Token::Multiply
+                                | Token::Divide
+                                | Token::Less
+                                | Token::LessEqual
+                                | Token::Greater
+                                | Token::GreaterEqual
+                        ),
+                    }),
+                }
+            }
+        }
+        in_atom = continues_atom;
+    }
+    if !open.is_empty() {
+        return Err(ParseError::new(ParseErrors::ClosingParenthesisExpected));
+    }
+    Ok(nodes)
+}
+
+/// An element of a list, tuple or program, with the comments that follow it on the same line
+/// attached to it.
+enum Piece<'a> {
+    Expression {
+        expression: &'a Node,
+        trailing: Option<String>,
+    },
+    /// A comment on a line of its own
+    Comment(String),
+}
+
+struct Item<'a> {
+    piece: Piece<'a>,
+    /// Whether the source has a blank line between this element and the previous one
+    blank_before: bool,
+}
+
+impl Item<'_> {
+    fn ends_with_comment(&self) -> bool {
+        match self.piece {
+            Piece::Expression { ref trailing, .. } => trailing.is_some(),
+            Piece::Comment(_) => true,
+        }
+    }
+}
+
+/// A `key: value` entry of a tuple literal
+struct TupleEntry<'a> {
+    /// Comments on the lines before the entry
+    leading: Vec<String>,
+    key: &'a Node,
+    value: &'a Node,
+    trailing: Vec<String>,
+}
+
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Formatter<'a> {
+    /// The source text of a single-line expression
+    fn text(&self, span: &Span) -> String {
+        let start = span.start_column.saturating_sub(1) as usize;
+        let end = span.end_column as usize;
+        self.line(span.start_line)
+            .chars()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect()
+    }
+
+    /// The name of an atom which the parser reads as a name
+    fn name(&self, node: &Node) -> Option<String> {
+        match node {
+            Node::Atom {
+                span,
+                identifier: true,
+            } => Some(self.text(span)),
+            _ => None,
+        }
+    }
+
+    /// The source text of a comment, which runs to the end of its line
+    fn comment(&self, span: &Span) -> String {
+        let start = span.start_column.saturating_sub(1) as usize;
+        let comment: String = self.line(span.start_line).chars().skip(start).collect();
+        comment.trim_end().to_string()
+    }
+
+    fn line(&self, line: u32) -> &str {
+        self.lines
+            .get((line as usize).saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn items(&self, expressions: &'a [Node]) -> Vec<Item<'a>> {
+        let mut items: Vec<Item<'a>> = vec![];
+        let mut last_line = None;
+        for expression in expressions.iter() {
+            let span = expression.span();
+            let blank_before = last_line.is_some_and(|line| span.start_line > line + 1);
+            last_line = Some(span.end_line);
+            if !expression.is_comment() {
+                items.push(Item {
+                    piece: Piece::Expression {
+                        expression,
+                        trailing: None,
+                    },
+                    blank_before,
+                });
+                continue;
+            }
+
+            let comment = self.comment(span);
+            if let Some(Item {
+                piece:
+                    Piece::Expression {
+                        expression: previous,
+                        trailing: trailing @ None,
+                    },
+                ..
+            }) = items.last_mut()
+            {
+                if previous.span().end_line == span.start_line {
+                    *trailing = Some(comment);
+                    continue;
+                }
+            }
+            items.push(Item {
+                piece: Piece::Comment(comment),
+                blank_before,
+            });
+        }
+        items
+    }
+
+    /// Print an expression on a single line, if it has no comments
+    fn flat(&self, expression: &Node) -> Option<String> {
+        match expression {
+            Node::List(children, _) => {
+                let children = children
+                    .iter()
+                    .map(|child| self.flat(child))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("({})", children.join(" ")))
+            }
+            Node::Tuple(children, _) => {
+                let children = children
+                    .iter()
+                    .map(|child| self.flat(child))
+                    .collect::<Option<Vec<_>>>()?;
+                let entries: Vec<_> = children.chunks(2).map(|entry| entry.join(": ")).collect();
+                Some(format!("{{{}}}", entries.join(", ")))
+            }
+            Node::Comment(_) => None,
+            Node::Atom { span, .. } => Some(self.text(span)),
+        }
+    }
+
+    /// Print an expression which starts at column `column`, and is followed by `trail` closing
+    /// characters on its last line.
+    fn render(&self, expression: &Node, column: usize, trail: usize) -> String {
+        match expression {
+            Node::List(children, _) => {
+                let is_block = children
+                    .first()
+                    .and_then(|head| self.name(head))
+                    .is_some_and(|head| BLOCK_FORMS.contains(&head.as_str()));
+                if !is_block {
+                    if let Some(flat) = self.flat(expression) {
+                        if column + flat.len() + trail <= MAX_LINE_WIDTH {
+                            return flat;
+                        }
+                    }
+                }
+                self.render_list(children, column, trail)
+            }
+            Node::Tuple(children, _) => {
+                if let Some(flat) = self.flat(expression) {
+                    if column + flat.len() + trail <= MAX_LINE_WIDTH {
+                        return flat;
+                    }
+                }
+                self.render_tuple(children, column, trail)
+            }
+            Node::Comment(span) => self.comment(span),
+            Node::Atom { span, .. } => self.text(span),
+        }
+    }
+
+    fn render_list(&self, children: &'a [Node], column: usize, trail: usize) -> String {
+        let items = self.items(children);
+        let head = children.first().and_then(|head| self.name(head));
+        // how many arguments follow the head on the opening line, and where the others go
+        let (header, body_column) = match head.as_deref() {
+            Some(head) => match header_arguments(head) {
+                Some(header) => (header, column + INDENT_WIDTH),
+                None if column + head.len() + 2 <= MAX_LINE_WIDTH / 2 => {
+                    (1, column + head.len() + 2)
+                }
+                None => (0, column + INDENT_WIDTH),
+            },
+            None => (0, column + 1),
+        };
+        // the binding names of `match` are printed on the same line as their branch
+        let expression_count = items
+            .iter()
+            .filter(|item| matches!(item.piece, Piece::Expression { .. }))
+            .count();
+        let bindings: &[usize] = match (head.as_deref(), expression_count) {
+            (Some("match"), 5) => &[2],
+            (Some("match"), 7) => &[2, 4],
+            _ => &[],
+        };
+
+        let mut out = String::from("(");
+        let mut line_end = column + 1;
+        let mut broken = false;
+        let mut position = 0;
+        let mut i = 0;
+        while i < items.len() {
+            let item = &items[i];
+            let item_trail = if i + 1 == items.len() { trail + 1 } else { 0 };
+            let on_opening_line = i == 0
+                || (!broken
+                    && position <= header
+                    && !item.blank_before
+                    && matches!(item.piece, Piece::Expression { .. }));
+            let item_column = if i == 0 {
+                column + 1
+            } else if on_opening_line {
+                out.push(' ');
+                line_end + 1
+            } else {
+                broken = true;
+                if item.blank_before {
+                    out.push('\n');
+                }
+                out.push('\n');
+                out.push_str(&" ".repeat(body_column));
+                body_column
+            };
+
+            match item.piece {
+                Piece::Comment(ref comment) => {
+                    out.push_str(comment);
+                    broken = true;
+                }
+                Piece::Expression {
+                    expression,
+                    ref trailing,
+                } => {
+                    let mut text = self.render(expression, item_column, item_trail);
+                    let mut trailing = trailing;
+                    if let (
+                        true,
+                        Some(Item {
+                            piece:
+                                Piece::Expression {
+                                    expression: branch,
+                                    trailing: branch_trailing,
+                                },
+                            blank_before: false,
+                        }),
+                    ) = (
+                        bindings.contains(&position) && trailing.is_none(),
+                        items.get(i + 1),
+                    ) {
+                        let branch_trail = if i + 2 == items.len() { trail + 1 } else { 0 };
+                        let branch_column = item_column + text.len() + 1;
+                        text.push(' ');
+                        text.push_str(&self.render(branch, branch_column, branch_trail));
+                        trailing = branch_trailing;
+                        position += 1;
+                        i += 1;
+                    }
+                    out.push_str(&text);
+                    line_end = end_column(item_column, &text);
+                    if trailing.is_some() {
+                        push_trailing(&mut out, trailing);
+                        broken = true;
+                    }
+                    position += 1;
+                }
+            }
+            i += 1;
+        }
+
+        if items.last().is_some_and(|item| item.ends_with_comment()) {
+            out.push('\n');
+            out.push_str(&" ".repeat(body_column));
+        }
+        out.push(')');
+        out
+    }
+
+    fn render_tuple(&self, children: &'a [Node], column: usize, trail: usize) -> String {
+        // comments between a key and its value are moved before the entry
+        let mut entries = vec![];
+        let mut leading = vec![];
+        let mut key: Option<(&Node, Vec<String>)> = None;
+        for item in self.items(children).into_iter() {
+            match item.piece {
+                Piece::Comment(comment) => leading.push(comment),
+                Piece::Expression {
+                    expression,
+                    trailing,
+                } => match key.take() {
+                    None => key = Some((expression, trailing.into_iter().collect())),
+                    Some((key, mut comments)) => {
+                        comments.extend(trailing);
+                        entries.push(TupleEntry {
+                            leading: std::mem::take(&mut leading),
+                            key,
+                            value: expression,
+                            trailing: comments,
+                        });
+                    }
+                },
+            }
+        }
+
+        let key_column = column + 1;
+        let mut out = String::from("{");
+        let mut at_start = true;
+        let mut ends_with_comment = false;
+        let entry_count = entries.len();
+        for (i, entry) in entries.into_iter().enumerate() {
+            for comment in entry.leading.iter() {
+                push_line(&mut out, &mut at_start, key_column);
+                out.push_str(comment);
+            }
+            if at_start {
+                at_start = false;
+            } else {
+                out.push('\n');
+                out.push_str(&" ".repeat(key_column));
+            }
+            let key = self.text(entry.key.span());
+            let value_trail = if i + 1 == entry_count { trail + 1 } else { 1 };
+            out.push_str(&key);
+            out.push_str(": ");
+            out.push_str(&self.render(entry.value, key_column + key.len() + 2, value_trail));
+            if i + 1 < entry_count {
+                out.push(',');
+            }
+            ends_with_comment = !entry.trailing.is_empty();
+            if ends_with_comment {
+                out.push(' ');
+                out.push_str(&entry.trailing.join(" "));
+            }
+        }
+        for comment in leading.iter() {
+            push_line(&mut out, &mut at_start, key_column);
+            out.push_str(comment);
+            ends_with_comment = true;
+        }
+
+        if ends_with_comment {
+            out.push('\n');
+            out.push_str(&" ".repeat(key_column));
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Start a new line at `column`, unless nothing follows the opening brace yet
+fn push_line(out: &mut String, at_start: &mut bool, column: usize) {
+    if *at_start {
+        out.push(' ');
+        *at_start = false;
+    } else {
+        out.push('\n');
+        out.push_str(&" ".repeat(column));
+    }
+}
+
+fn push_trailing(out: &mut String, trailing: &Option<String>) {
+    if let Some(trailing) = trailing {
+        out.push(' ');
+        out.push_str(trailing);
+    }
+}
+
+/// The column at which `text`, printed from column `start`, ends
+fn end_column(start: usize, text: &str) -> usize {
+    match text.rfind('\n') {
+        Some(newline) => text.len() - newline - 1,
+        None => start + text.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_canonical(source: &str) -> String {
+        let formatted = format_source(source).unwrap();
+        assert!(is_equivalent(source, &formatted));
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+        formatted
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let source = r#"
+(define-data-var counter   uint u0)
+(define-map owners {id: uint, index: uint} {owner: principal, name: (string-ascii 64), created-at: uint, updated-at: uint})
+(define-public (increment (by uint)) (begin (var-set counter (+ (var-get counter) by)) (ok (var-get counter))))
+(define-read-only (get-owner (id uint))
+  (match (map-get? owners {id: id, index: u0})
+      entry (ok {owner: (get owner entry), name: (get name entry)})
+      (err u404)))
+(define-private (transfer (amount uint) (sender principal) (recipient principal) (memo (optional (buff 34))))
+  (let ((sender-balance (default-to u0 (map-get? balances sender)))
+        (recipient-balance (default-to u0 (map-get? balances recipient))))
+    (asserts! (>= sender-balance amount) (err u1))
+    (map-set balances recipient (+ recipient-balance amount)) (print {event: "transfer", amount: amount, sender: sender, recipient: recipient, memo: memo, height: block-height})
+    (ok true)))
+(define-constant ERR (err u1))(define-constant x (contract-call? .other-contract some-function 'SP000000000000000000002Q6VF78 u1 "a string" u"utf8" 0x1234))
+"#;
+        let expected = r#"(define-data-var counter uint u0)
+(define-map owners
+  {id: uint, index: uint}
+  {owner: principal, name: (string-ascii 64), created-at: uint, updated-at: uint})
+(define-public (increment (by uint))
+  (begin
+    (var-set counter (+ (var-get counter) by))
+    (ok (var-get counter))))
+(define-read-only (get-owner (id uint))
+  (match (map-get? owners {id: id, index: u0})
+    entry (ok {owner: (get owner entry), name: (get name entry)})
+    (err u404)))
+(define-private (transfer (amount uint)
+                          (sender principal)
+                          (recipient principal)
+                          (memo (optional (buff 34))))
+  (let ((sender-balance (default-to u0 (map-get? balances sender)))
+        (recipient-balance (default-to u0 (map-get? balances recipient))))
+    (asserts! (>= sender-balance amount) (err u1))
+    (map-set balances recipient (+ recipient-balance amount))
+    (print {event: "transfer",
+            amount: amount,
+            sender: sender,
+            recipient: recipient,
+            memo: memo,
+            height: block-height})
+    (ok true)))
+(define-constant ERR (err u1))
+(define-constant x
+  (contract-call? .other-contract some-function
+    'SP000000000000000000002Q6VF78
+    u1
+    "a string"
+    u"utf8"
+    0x1234))
+"#;
+        assert_eq!(assert_canonical(source), expected);
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let source = r#";;;; Header
+;; counter contract
+(define-data-var counter   uint u0) ;; the count
+
+
+
+(define-public (increment) ;; no arguments
+  ;; bump the counter
+  (begin (var-set counter (+ (var-get counter) u1))
+
+    (ok { ;; the result
+      count: (var-get counter), ;; new value
+      ;; the caller
+      caller: tx-sender })
+    ;; trailing remark
+    ))
+(use-trait token-trait .token.token-trait)
+(define-public (burn (token <token-trait>)) (contract-call? token burn u1 .token.token-trait))
+"#;
+        let expected = r#";;;; Header
+;; counter contract
+(define-data-var counter uint u0) ;; the count
+
+(define-public (increment) ;; no arguments
+  ;; bump the counter
+  (begin
+    (var-set counter (+ (var-get counter) u1))
+
+    (ok { ;; the result
+         count: (var-get counter), ;; new value
+         ;; the caller
+         caller: tx-sender})
+    ;; trailing remark
+    ))
+(use-trait token-trait .token.token-trait)
+(define-public (burn (token <token-trait>))
+  (contract-call? token burn u1 .token.token-trait))
+"#;
+        assert_eq!(assert_canonical(source), expected);
+        assert_eq!(format_source("").unwrap(), "");
+        assert!(format_source("(define-constant x (+ 1 2)").is_err());
+        assert!(!is_equivalent("(ok u1)", "(ok u2)"));
+        assert!(!is_equivalent("(ok u1)", "(ok u1"));
+    }
+
+    #[test]
+    fn test_format_sample_contracts() {
+        assert_canonical(include_str!("../../../../sample-contracts/tokens.clar"));
+        assert_canonical(include_str!("../../../../sample-contracts/names.clar"));
+        assert_canonical(include_str!("../../../../sample-contracts/tokens-ft.clar"));
+    }
+}
@@ -20,6 +20,7 @@ pub mod parser;
 pub mod traits_resolver;
 
 pub mod errors;
+pub mod formatter;
 pub mod stack_depth_checker;
 pub mod sugar_expander;
 pub mod types;
@@ -210,6 +211,7 @@ fn inner_build_ast<T: CostTracker>(
                 message: format!("runtime_cost error: {:?}", e),
                 spans: vec![],
                 suggestion: None,
+                code: Some(e.code().into()),
             },
         );
     }
@@ -76,4 +76,30 @@ impl DiagnosableError for LexerError {
             _ => Level::Error,
         }
     }
+    fn code(&self) -> Option<String> {
+        let code = match self {
+            LexerError::InvalidCharInt(..) => "InvalidCharInt",
+            LexerError::InvalidCharUint(..) => "InvalidCharUint",
+            LexerError::InvalidCharBuffer(..) => "InvalidCharBuffer",
+            LexerError::InvalidCharIdent(..) => "InvalidCharIdent",
+            LexerError::InvalidCharTraitIdent(..) => "InvalidCharTraitIdent",
+            LexerError::InvalidCharPrincipal(..) => "InvalidCharPrincipal",
+            LexerError::InvalidBufferLength(..) => "InvalidBufferLength",
+            LexerError::UnknownEscapeChar(..) => "UnknownEscapeChar",
+            LexerError::IllegalCharString(..) => "IllegalCharString",
+            LexerError::IllegalCharUTF8Encoding(..) => "IllegalCharUTF8Encoding",
+            LexerError::UnterminatedUTF8Encoding => "UnterminatedUTF8Encoding",
+            LexerError::ExpectedClosing(..) => "ExpectedClosing",
+            LexerError::ExpectedSeparator => "ExpectedSeparator",
+            LexerError::EmptyUTF8Encoding => "EmptyUTF8Encoding",
+            LexerError::InvalidUTF8Encoding => "InvalidUTF8Encoding",
+            LexerError::SingleSemiColon => "SingleSemiColon",
+            LexerError::UnknownSymbol(..) => "UnknownSymbol",
+            LexerError::NonASCIIChar(..) => "NonASCIIChar",
+            LexerError::NoteToMatchThis(..) => "NoteToMatchThis",
+            LexerError::UnsupportedLineEnding => "UnsupportedLineEnding",
+            LexerError::EditorCRLFMode => "EditorCRLFMode",
+        };
+        Some(code.into())
+    }
 }
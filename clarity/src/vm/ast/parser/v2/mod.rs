@@ -1110,6 +1110,7 @@ pub fn parse_collect_diagnostics(
             message: e.e.message(),
             spans: vec![e.span.clone()],
             suggestion: None,
+            code: e.e.code(),
         })
         .collect();
     (stmts, diagnostics, parser.success)
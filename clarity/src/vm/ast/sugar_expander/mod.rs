@@ -120,7 +120,10 @@ impl SugarExpander {
                     if let Some(trait_reference) = contract_ast.get_referenced_trait(&name) {
                         SymbolicExpression::trait_reference(name, trait_reference.clone())
                     } else {
-                        return Err(ParseErrors::TraitReferenceUnknown(name.to_string()).into());
+                        let mut error =
+                            ParseError::new(ParseErrors::TraitReferenceUnknown(name.to_string()));
+                        error.diagnostic.spans = vec![span];
+                        return Err(error);
                     }
                 }
                 #[cfg(not(feature = "developer-mode"))]
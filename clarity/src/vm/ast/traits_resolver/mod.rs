@@ -32,6 +32,12 @@ use crate::vm::ClarityVersion;
 
 pub struct TraitsResolver {}
 
+fn error_at(err: ParseErrors, expr: &PreSymbolicExpression) -> ParseError {
+    let mut error = ParseError::new(err);
+    error.set_pre_expression(expr);
+    error
+}
+
 impl BuildASTPass for TraitsResolver {
     fn run_pass(contract_ast: &mut ContractAST, _version: ClarityVersion) -> ParseResult<()> {
         let mut command = TraitsResolver::new();
@@ -58,16 +64,17 @@ impl TraitsResolver {
             match define_type {
                 DefineFunctions::Trait => {
                     if args.len() != 2 {
-                        return Err(ParseErrors::DefineTraitBadSignature.into());
+                        return Err(error_at(ParseErrors::DefineTraitBadSignature, exp));
                     }
 
                     match (&args[0].pre_expr, &args[1].pre_expr) {
                         (Atom(trait_name), List(trait_definition)) => {
                             // Check for collisions
                             if contract_ast.referenced_traits.contains_key(trait_name) {
-                                return Err(
-                                    ParseErrors::NameAlreadyUsed(trait_name.to_string()).into()
-                                );
+                                return Err(error_at(
+                                    ParseErrors::NameAlreadyUsed(trait_name.to_string()),
+                                    args[0],
+                                ));
                             }
 
                             // Traverse and probe for generics nested in the trait definition
@@ -85,18 +92,21 @@ impl TraitsResolver {
                                 .referenced_traits
                                 .insert(trait_name.clone(), TraitDefinition::Defined(trait_id));
                         }
-                        _ => return Err(ParseErrors::DefineTraitBadSignature.into()),
+                        _ => return Err(error_at(ParseErrors::DefineTraitBadSignature, exp)),
                     }
                 }
                 DefineFunctions::UseTrait => {
                     if args.len() != 2 {
-                        return Err(ParseErrors::ImportTraitBadSignature.into());
+                        return Err(error_at(ParseErrors::ImportTraitBadSignature, exp));
                     }
 
                     if let Some(trait_name) = args[0].match_atom() {
                         // Check for collisions
                         if contract_ast.referenced_traits.contains_key(trait_name) {
-                            return Err(ParseErrors::NameAlreadyUsed(trait_name.to_string()).into());
+                            return Err(error_at(
+                                ParseErrors::NameAlreadyUsed(trait_name.to_string()),
+                                args[0],
+                            ));
                         }
 
                         let trait_id = match &args[1].pre_expr {
@@ -111,18 +121,18 @@ impl TraitsResolver {
                                 }
                             }
                             FieldIdentifier(trait_identifier) => trait_identifier.clone(),
-                            _ => return Err(ParseErrors::ImportTraitBadSignature.into()),
+                            _ => return Err(error_at(ParseErrors::ImportTraitBadSignature, exp)),
                         };
                         contract_ast
                             .referenced_traits
                             .insert(trait_name.clone(), TraitDefinition::Imported(trait_id));
                     } else {
-                        return Err(ParseErrors::ImportTraitBadSignature.into());
+                        return Err(error_at(ParseErrors::ImportTraitBadSignature, exp));
                     }
                 }
                 DefineFunctions::ImplTrait => {
                     if args.len() != 1 {
-                        return Err(ParseErrors::ImplTraitBadSignature.into());
+                        return Err(error_at(ParseErrors::ImplTraitBadSignature, exp));
                     }
 
                     let trait_id = match &args[0].pre_expr {
@@ -137,7 +147,7 @@ impl TraitsResolver {
                             }
                         }
                         FieldIdentifier(trait_identifier) => trait_identifier.clone(),
-                        _ => return Err(ParseErrors::ImplTraitBadSignature.into()),
+                        _ => return Err(error_at(ParseErrors::ImplTraitBadSignature, exp)),
                     };
                     contract_ast.implemented_traits.insert(trait_id);
                 }
@@ -168,11 +178,10 @@ impl TraitsResolver {
                 .referenced_traits
                 .contains_key(&trait_reference)
             {
-                let mut err = ParseError::new(ParseErrors::TraitReferenceUnknown(
-                    trait_reference.to_string(),
+                return Err(error_at(
+                    ParseErrors::TraitReferenceUnknown(trait_reference.to_string()),
+                    &expr,
                 ));
-                err.set_pre_expression(&expr);
-                return Err(err);
             }
         }
 
@@ -209,7 +218,7 @@ impl TraitsResolver {
                     if should_reference {
                         referenced_traits.insert(trait_name.clone(), expression.clone());
                     } else {
-                        return Err(ParseErrors::TraitReferenceNotAllowed.into());
+                        return Err(error_at(ParseErrors::TraitReferenceNotAllowed, expression));
                     }
                 }
                 Tuple(atoms) => {
@@ -62,7 +62,7 @@ pub enum DefineType {
     Private,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DefinedFunction {
     identifier: FunctionIdentifier,
     name: ClarityName,
@@ -365,6 +365,14 @@ impl DefinedFunction {
         &self.arg_types
     }
 
+    pub fn get_name(&self) -> &ClarityName {
+        &self.name
+    }
+
+    pub fn get_body(&self) -> &SymbolicExpression {
+        &self.body
+    }
+
     pub fn canonicalize_types(&mut self, epoch: &StacksEpochId) {
         for i in 0..self.arguments.len() {
             self.arg_types[i] = self.arg_types[i].canonicalize(epoch);
@@ -205,7 +205,7 @@ pub struct GlobalContext<'a, 'hooks> {
     pub eval_hooks: Option<Vec<&'hooks mut dyn EvalHook>>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct ContractContext {
     pub contract_identifier: QualifiedContractIdentifier,
     pub variables: HashMap<ClarityName, Value>,
@@ -1802,6 +1802,14 @@ impl ContractContext {
         self.functions.get(name).cloned()
     }
 
+    /// Returns the user-defined function whose body is `body`, which is how `EvalHook`s can tell
+    /// that a function is being entered.
+    pub fn lookup_function_by_body(&self, body: &SymbolicExpression) -> Option<&DefinedFunction> {
+        self.functions
+            .values()
+            .find(|function| function.get_body().id == body.id && function.get_body() == body)
+    }
+
     pub fn lookup_trait_definition(
         &self,
         name: &str,
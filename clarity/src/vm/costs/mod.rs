@@ -333,6 +333,19 @@ pub enum CostErrors {
 }
 
 impl CostErrors {
+    /// Stable identifier of the kind of error, used as the `code` of diagnostics
+    pub fn code(&self) -> &'static str {
+        match self {
+            CostErrors::CostComputationFailed(..) => "CostComputationFailed",
+            CostErrors::CostOverflow => "CostOverflow",
+            CostErrors::CostBalanceExceeded(..) => "CostBalanceExceeded",
+            CostErrors::MemoryBalanceExceeded(..) => "MemoryBalanceExceeded",
+            CostErrors::CostContractLoadFailure => "CostContractLoadFailure",
+            CostErrors::InterpreterFailure => "InterpreterFailure",
+            CostErrors::Expect(..) => "Expect",
+        }
+    }
+
     fn rejectable(&self) -> bool {
         match self {
             CostErrors::InterpreterFailure => true,
@@ -0,0 +1,836 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, Write};
+
+use super::EvalHook;
+use crate::vm::ast::{build_ast_with_rules, ASTRules};
+use crate::vm::contexts::{Environment, LocalContext};
+use crate::vm::errors::Error;
+use crate::vm::functions::NativeFunctions;
+use crate::vm::representations::{ClarityName, SymbolicExpression};
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::{eval, ExecutionResult, Value};
+
+const HELP: &str = "Commands:
+  s, step                  stop at the next expression, stepping into function calls
+  n, next                  stop at the next expression, stepping over function calls
+  f, finish                run until the current function returns
+  c, continue              run until a breakpoint or watch is hit
+  q, quit                  stop debugging and run to completion
+  b, break <location>      add a breakpoint at <line>, <contract>:<line>, <function>
+                           or <contract>::<function>
+  w, watch <name>          stop when the data-var or map <name> (or <contract>::<name>)
+                           is written
+  d, delete <id>           remove a breakpoint or watch
+  i, info                  list breakpoints and watches
+  l, locals                print the local bindings
+  bt, backtrace            print the functions being evaluated
+  p, print <expr>          evaluate <expr> in the current context, discarding any writes
+  h, help                  print this message";
+
+/// Longest expression printed when stopping
+const MAX_EXPRESSION_DISPLAY: usize = 80;
+
+/// When the debugger should next stop, besides breakpoints and watches
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepMode {
+    Continue,
+    /// Stop before the next expression is evaluated
+    Into,
+    /// Stop before the next expression at this evaluation depth (or shallower) is evaluated
+    Over(usize),
+    /// Stop when the function at this frame index returns
+    Out(usize),
+    /// Never stop again, not even at breakpoints
+    Detached,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Location {
+    Line(u32),
+    Function(ClarityName),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Breakpoint {
+    id: usize,
+    /// A contract identifier or contract name, or `None` for any contract
+    contract: Option<String>,
+    location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Watch {
+    id: usize,
+    contract: Option<String>,
+    name: ClarityName,
+}
+
+/// A user-defined function whose body is being evaluated
+struct Frame {
+    contract: QualifiedContractIdentifier,
+    function: ClarityName,
+    body_id: u64,
+    depth: usize,
+}
+
+/// A write to a watched data-var or map, which is being evaluated
+struct PendingWrite {
+    watch_id: usize,
+    expr_id: u64,
+    depth: usize,
+    contract: QualifiedContractIdentifier,
+    name: ClarityName,
+    /// For maps, the id of the key expression, and its value once evaluated
+    key_expr_id: Option<u64>,
+    key: Option<Value>,
+    old_value: Option<Value>,
+}
+
+/// An interactive debugger for Clarity programs, driven by `EvalHook` callbacks.
+///
+/// The debugger stops before the first evaluated expression, and then whenever a breakpoint,
+/// a watch, or a step command is hit. While stopped, it reads commands from its input until it
+/// is told to resume. Since the callbacks are invoked across `contract-call?`s, breakpoints
+/// and steps apply to the called contracts as well.
+///
+/// Line breakpoints rely on expression spans, which are only recorded in builds with the
+/// `developer-mode` feature.
+pub struct Debugger {
+    input: Box<dyn FnMut() -> Option<String>>,
+    output: Box<dyn Write>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Watch>,
+    next_id: usize,
+    step_mode: StepMode,
+    /// The number of expressions currently being evaluated
+    depth: usize,
+    frames: Vec<Frame>,
+    pending_writes: Vec<PendingWrite>,
+    last_line: Option<(QualifiedContractIdentifier, u32)>,
+}
+
+impl Debugger {
+    /// Create a debugger which reads commands, one per line, from `input`, and writes its
+    /// output to `output`. The debugger detaches once `input` is exhausted.
+    pub fn new(input: Box<dyn FnMut() -> Option<String>>, output: Box<dyn Write>) -> Debugger {
+        Debugger {
+            input,
+            output,
+            breakpoints: vec![],
+            watches: vec![],
+            next_id: 1,
+            step_mode: StepMode::Into,
+            depth: 0,
+            frames: vec![],
+            pending_writes: vec![],
+            last_line: None,
+        }
+    }
+
+    /// Create a debugger which reads commands from stdin and writes to stderr, leaving
+    /// stdout to the program being debugged.
+    pub fn stdio() -> Debugger {
+        Debugger::new(
+            Box::new(|| {
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => Some(line),
+                }
+            }),
+            Box::new(io::stderr()),
+        )
+    }
+
+    /// Stop again before the next evaluated expression, as a new debugger would, unless the
+    /// debugger was told to quit. Breakpoints and watches are kept. This is for debuggers that
+    /// are shared by several evaluations, such as the inputs of a REPL.
+    pub fn restart(&mut self) {
+        if self.step_mode != StepMode::Detached {
+            self.step_mode = StepMode::Into;
+        }
+        self.depth = 0;
+        self.frames.clear();
+        self.pending_writes.clear();
+        self.last_line = None;
+    }
+
+    /// Add a breakpoint, as given to the `break` command. Returns the breakpoint's id.
+    pub fn add_breakpoint(&mut self, spec: &str) -> Option<usize> {
+        let (contract, location) = if let Some((contract, function)) = spec.rsplit_once("::") {
+            let function = ClarityName::try_from(function.to_string()).ok()?;
+            (Some(contract.to_string()), Location::Function(function))
+        } else if let Some((contract, line)) = spec.rsplit_once(':') {
+            (
+                Some(contract.to_string()),
+                Location::Line(line.parse().ok()?),
+            )
+        } else if let Ok(line) = spec.parse() {
+            (None, Location::Line(line))
+        } else {
+            let function = ClarityName::try_from(spec.to_string()).ok()?;
+            (None, Location::Function(function))
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.breakpoints.push(Breakpoint {
+            id,
+            contract,
+            location,
+        });
+        Some(id)
+    }
+
+    /// Add a watch on a data-var or map, as given to the `watch` command. Returns the watch's
+    /// id.
+    pub fn add_watch(&mut self, spec: &str) -> Option<usize> {
+        let (contract, name) = match spec.rsplit_once("::") {
+            Some((contract, name)) => (Some(contract.to_string()), name),
+            None => (None, spec),
+        };
+        let name = ClarityName::try_from(name.to_string()).ok()?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watches.push(Watch { id, contract, name });
+        Some(id)
+    }
+
+    fn output(&mut self, message: &str) {
+        let _ = writeln!(self.output, "{}", message);
+    }
+
+    /// Find the user-defined function whose body is `expr`, if any
+    fn function_of_body(env: &Environment, expr: &SymbolicExpression) -> Option<ClarityName> {
+        env.contract_context
+            .lookup_function_by_body(expr)
+            .map(|function| function.get_name().clone())
+    }
+
+    /// If `expr` writes to a watched data-var or map, returns the id of the watch, the
+    /// written name, and whether it's a map.
+    fn watched_write(
+        &self,
+        env: &Environment,
+        expr: &SymbolicExpression,
+    ) -> Option<(usize, ClarityName, bool)> {
+        let list = expr.match_list()?;
+        let function = list.first()?.match_atom()?;
+        let name = list.get(1)?.match_atom()?;
+        let is_map = match NativeFunctions::lookup_by_name_at_version(
+            function,
+            env.contract_context.get_clarity_version(),
+        )? {
+            NativeFunctions::SetVar => false,
+            NativeFunctions::SetEntry
+            | NativeFunctions::InsertEntry
+            | NativeFunctions::DeleteEntry => true,
+            _ => return None,
+        };
+        let contract = &env.contract_context.contract_identifier;
+        self.watches
+            .iter()
+            .find(|watch| &watch.name == name && contract_matches(&watch.contract, contract))
+            .map(|watch| (watch.id, name.clone(), is_map))
+    }
+
+    fn stop(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+        reason: Option<String>,
+    ) {
+        self.step_mode = StepMode::Continue;
+        if let Some(reason) = reason {
+            self.output(&reason);
+        }
+        let location = describe_location(&env.contract_context.contract_identifier, expr);
+        self.output(&location);
+        self.command_loop(env, context);
+    }
+
+    /// Read and run commands until one resumes the evaluation
+    fn command_loop(&mut self, env: &mut Environment, context: &LocalContext) {
+        loop {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+            let Some(line) = (self.input)() else {
+                self.step_mode = StepMode::Detached;
+                return;
+            };
+            let line = line.trim();
+            let (command, arg) = line
+                .split_once(char::is_whitespace)
+                .map(|(command, arg)| (command, arg.trim()))
+                .unwrap_or((line, ""));
+
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    self.step_mode = StepMode::Into;
+                    return;
+                }
+                "n" | "next" => {
+                    self.step_mode = StepMode::Over(self.depth);
+                    return;
+                }
+                "f" | "finish" => {
+                    self.step_mode = match self.frames.len() {
+                        0 => StepMode::Continue,
+                        n => StepMode::Out(n - 1),
+                    };
+                    return;
+                }
+                "c" | "continue" => {
+                    self.step_mode = StepMode::Continue;
+                    return;
+                }
+                "q" | "quit" => {
+                    self.step_mode = StepMode::Detached;
+                    return;
+                }
+                "b" | "break" => match self.add_breakpoint(arg) {
+                    Some(id) => {
+                        self.output(&format!("breakpoint {}: {}", id, arg));
+                        if !cfg!(feature = "developer-mode")
+                            && matches!(
+                                self.breakpoints.last().map(|b| &b.location),
+                                Some(Location::Line(_))
+                            )
+                        {
+                            self.output("warning: line breakpoints require a developer-mode build");
+                        }
+                    }
+                    None => self.output(&format!("invalid breakpoint location: {}", arg)),
+                },
+                "w" | "watch" => match self.add_watch(arg) {
+                    Some(id) => self.output(&format!("watch {}: {}", id, arg)),
+                    None => self.output(&format!("invalid watch: {}", arg)),
+                },
+                "d" | "delete" => {
+                    let id = arg.parse::<usize>().ok();
+                    let count = self.breakpoints.len() + self.watches.len();
+                    self.breakpoints.retain(|b| Some(b.id) != id);
+                    self.watches.retain(|w| Some(w.id) != id);
+                    if count == self.breakpoints.len() + self.watches.len() {
+                        self.output(&format!("no breakpoint or watch {}", arg));
+                    }
+                }
+                "i" | "info" => self.print_info(),
+                "l" | "locals" => self.print_locals(context),
+                "bt" | "backtrace" => self.print_backtrace(env),
+                "p" | "print" => self.print_expression(env, context, arg),
+                "h" | "help" => self.output(HELP),
+                _ => self.output(&format!("unknown command: {} (try `help`)", command)),
+            }
+        }
+    }
+
+    fn print_info(&mut self) {
+        let mut lines = vec![];
+        for breakpoint in self.breakpoints.iter() {
+            let location = match breakpoint.location {
+                Location::Line(line) => format!("line {}", line),
+                Location::Function(ref function) => format!("function {}", function),
+            };
+            lines.push(match breakpoint.contract {
+                Some(ref contract) => {
+                    format!("breakpoint {}: {} in {}", breakpoint.id, location, contract)
+                }
+                None => format!("breakpoint {}: {}", breakpoint.id, location),
+            });
+        }
+        for watch in self.watches.iter() {
+            lines.push(match watch.contract {
+                Some(ref contract) => format!("watch {}: {}::{}", watch.id, contract, watch.name),
+                None => format!("watch {}: {}", watch.id, watch.name),
+            });
+        }
+        if lines.is_empty() {
+            lines.push("no breakpoints or watches".into());
+        }
+        self.output(&lines.join("\n"));
+    }
+
+    fn print_locals(&mut self, context: &LocalContext) {
+        let mut lines = vec![];
+        let mut current = Some(context);
+        while let Some(context) = current {
+            let mut variables: Vec<_> = context.variables.iter().collect();
+            variables.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in variables {
+                lines.push(format!("{} = {}", name, value));
+            }
+            let mut callables: Vec<_> = context.callable_contracts.iter().collect();
+            callables.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, callable) in callables {
+                lines.push(format!("{} = {}", name, callable.contract_identifier));
+            }
+            current = context.parent;
+        }
+        if lines.is_empty() {
+            lines.push("no local bindings".into());
+        }
+        self.output(&lines.join("\n"));
+    }
+
+    fn print_backtrace(&mut self, env: &Environment) {
+        let mut lines: Vec<_> = self
+            .frames
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, frame)| format!("#{} {}::{}", i, frame.contract, frame.function))
+            .collect();
+        if lines.is_empty() {
+            lines.push("not in a function".into());
+        }
+        // the full call stack is only recorded in developer-mode builds
+        let call_stack = env.call_stack.make_stack_trace();
+        if !call_stack.is_empty() {
+            lines.push("call stack:".into());
+            lines.extend(call_stack.iter().rev().map(|f| format!("  {}", f)));
+        }
+        self.output(&lines.join("\n"));
+    }
+
+    fn print_expression(&mut self, env: &mut Environment, context: &LocalContext, program: &str) {
+        let contract_identifier = env.contract_context.contract_identifier.clone();
+        let clarity_version = *env.contract_context.get_clarity_version();
+        let epoch = *env.epoch();
+        let expressions = match build_ast_with_rules(
+            &contract_identifier,
+            program,
+            &mut (),
+            clarity_version,
+            epoch,
+            ASTRules::PrecheckSize,
+        ) {
+            Ok(ast) => ast.expressions,
+            Err(e) => {
+                self.output(&format!("parse error: {}", e));
+                return;
+            }
+        };
+
+        // the expression is evaluated on behalf of the user, not the program being debugged, so
+        // neither its writes nor its costs may be visible once it has been printed.
+        let saved_cost_track = env.global_context.cost_track.clone();
+        env.global_context.begin();
+        let mut result = Ok(Value::none());
+        for expression in expressions.iter() {
+            result = eval(expression, env, context);
+            if result.is_err() {
+                break;
+            }
+        }
+        let _ = env.global_context.roll_back();
+        env.global_context.cost_track = saved_cost_track;
+
+        match result {
+            Ok(value) => self.output(&value.to_string()),
+            Err(e) => self.output(&format!("error: {}", e)),
+        }
+    }
+
+    fn read_watched(
+        env: &mut Environment,
+        contract: &QualifiedContractIdentifier,
+        name: &str,
+        key: Option<&Value>,
+    ) -> Option<Value> {
+        let epoch = *env.epoch();
+        match key {
+            Some(key) => env
+                .global_context
+                .database
+                .fetch_entry_unknown_descriptor(contract, name, key, &epoch)
+                .ok(),
+            None => env
+                .global_context
+                .database
+                .lookup_variable_unknown_descriptor(contract, name, &epoch)
+                .ok(),
+        }
+    }
+}
+
+impl EvalHook for Debugger {
+    fn will_begin_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+    ) {
+        if self.step_mode == StepMode::Detached {
+            return;
+        }
+        let contract = env.contract_context.contract_identifier.clone();
+        let mut reason = None;
+
+        if let Some(function) = Debugger::function_of_body(env, expr) {
+            if let Some(breakpoint) = self.breakpoints.iter().find(|b| {
+                b.location == Location::Function(function.clone())
+                    && contract_matches(&b.contract, &contract)
+            }) {
+                reason = Some(format!(
+                    "breakpoint {}: entering {}::{}",
+                    breakpoint.id, contract, function
+                ));
+            }
+            self.frames.push(Frame {
+                contract: contract.clone(),
+                function,
+                body_id: expr.id,
+                depth: self.depth,
+            });
+        }
+
+        let line = expr.span().start_line;
+        let current_line = Some((contract.clone(), line));
+        if line > 0 && self.last_line != current_line {
+            if let Some(breakpoint) = self.breakpoints.iter().find(|b| {
+                b.location == Location::Line(line) && contract_matches(&b.contract, &contract)
+            }) {
+                reason = reason.or(Some(format!("breakpoint {}: line {}", breakpoint.id, line)));
+            }
+        }
+        self.last_line = current_line;
+
+        if let Some((watch_id, name, is_map)) = self.watched_write(env, expr) {
+            let old_value = if is_map {
+                None
+            } else {
+                Debugger::read_watched(env, &contract, &name, None)
+            };
+            self.pending_writes.push(PendingWrite {
+                watch_id,
+                expr_id: expr.id,
+                depth: self.depth,
+                contract: contract.clone(),
+                name,
+                key_expr_id: if is_map {
+                    expr.match_list()
+                        .and_then(|list| list.get(2))
+                        .map(|key| key.id)
+                } else {
+                    None
+                },
+                key: None,
+                old_value,
+            });
+        }
+
+        let step = match self.step_mode {
+            StepMode::Into => true,
+            StepMode::Over(depth) => self.depth <= depth,
+            _ => false,
+        };
+        if reason.is_some() || step {
+            self.stop(env, context, expr, reason);
+        }
+        self.depth += 1;
+    }
+
+    fn did_finish_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+        res: &Result<Value, Error>,
+    ) {
+        if self.step_mode == StepMode::Detached {
+            return;
+        }
+        self.depth = self.depth.saturating_sub(1);
+        let mut reason = None;
+
+        if let Some(pending) = self.pending_writes.last_mut() {
+            if pending.key_expr_id == Some(expr.id) && pending.depth + 1 == self.depth {
+                if let Ok(key) = res {
+                    pending.old_value =
+                        Debugger::read_watched(env, &pending.contract, &pending.name, Some(key));
+                    pending.key = Some(key.clone());
+                }
+            }
+        }
+        if self
+            .pending_writes
+            .last()
+            .is_some_and(|p| p.expr_id == expr.id && p.depth == self.depth)
+        {
+            if let Some(pending) = self.pending_writes.pop() {
+                if res.is_ok() {
+                    let new_value = Debugger::read_watched(
+                        env,
+                        &pending.contract,
+                        &pending.name,
+                        pending.key.as_ref(),
+                    );
+                    let name = match pending.key {
+                        Some(ref key) => format!("{}::{}[{}]", pending.contract, pending.name, key),
+                        None => format!("{}::{}", pending.contract, pending.name),
+                    };
+                    reason = Some(format!(
+                        "watch {}: {}: {} -> {}",
+                        pending.watch_id,
+                        name,
+                        display_optional(&pending.old_value),
+                        display_optional(&new_value)
+                    ));
+                }
+            }
+        }
+
+        if self
+            .frames
+            .last()
+            .is_some_and(|f| f.body_id == expr.id && f.depth == self.depth)
+        {
+            if let Some(frame) = self.frames.pop() {
+                if self.step_mode == StepMode::Out(self.frames.len()) {
+                    let returned = match res {
+                        Ok(value) => value.to_string(),
+                        Err(e) => format!("error: {}", e),
+                    };
+                    let message = format!(
+                        "returned from {}::{}: {}",
+                        frame.contract, frame.function, returned
+                    );
+                    reason = Some(match reason {
+                        Some(reason) => format!("{}\n{}", reason, message),
+                        None => message,
+                    });
+                }
+            }
+        }
+
+        if reason.is_some() {
+            self.stop(env, context, expr, reason);
+        }
+    }
+
+    fn did_complete(&mut self, _result: core::result::Result<&mut ExecutionResult, String>) {}
+}
+
+fn contract_matches(spec: &Option<String>, contract: &QualifiedContractIdentifier) -> bool {
+    match spec {
+        None => true,
+        Some(spec) => spec == &contract.to_string() || spec == contract.name.as_str(),
+    }
+}
+
+fn display_optional(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "?".into())
+}
+
+fn describe_location(contract: &QualifiedContractIdentifier, expr: &SymbolicExpression) -> String {
+    let mut expression = expr.to_string();
+    if expression.len() > MAX_EXPRESSION_DISPLAY {
+        let mut end = MAX_EXPRESSION_DISPLAY;
+        while !expression.is_char_boundary(end) {
+            end -= 1;
+        }
+        expression.truncate(end);
+        expression.push_str("...");
+    }
+    match expr.span().start_line {
+        0 => format!("at {}: {}", contract, expression),
+        line => format!("at {}:{}: {}", contract, line, expression),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use stacks_common::types::StacksEpochId;
+
+    use super::*;
+    use crate::vm::contexts::OwnedEnvironment;
+    use crate::vm::database::MemoryBackingStore;
+    use crate::vm::types::{PrincipalData, StandardPrincipalData};
+
+    const COUNTER: &str = "(define-data-var counter uint u0)
+        (define-map balances principal uint)
+        (define-private (add (a uint) (b uint)) (+ a b))
+        (define-public (bump (n uint))
+          (begin
+            (var-set counter (add (var-get counter) n))
+            (map-set balances tx-sender (var-get counter))
+            (ok (var-get counter))))";
+    const CALLER: &str = "(define-public (call (n uint)) (contract-call? .counter bump n))";
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Call `caller.call` with `u5` under the debugger, which is given `commands`. Returns the
+    /// transaction's result and the debugger's output.
+    fn debug_call(commands: &[&str]) -> (Value, String) {
+        let output = SharedOutput::default();
+        let mut commands = commands
+            .iter()
+            .map(|command| command.to_string())
+            .collect::<Vec<_>>()
+            .into_iter();
+        let mut debugger =
+            Debugger::new(Box::new(move || commands.next()), Box::new(output.clone()));
+
+        let counter_id = QualifiedContractIdentifier::local("counter").unwrap();
+        let caller_id = QualifiedContractIdentifier::local("caller").unwrap();
+        let mut marf = MemoryBackingStore::new();
+        let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db(), StacksEpochId::Epoch21);
+        owned_env
+            .initialize_contract(counter_id, COUNTER, None, ASTRules::PrecheckSize)
+            .unwrap();
+        owned_env
+            .initialize_contract(caller_id.clone(), CALLER, None, ASTRules::PrecheckSize)
+            .unwrap();
+
+        owned_env.add_eval_hook(&mut debugger);
+        let (result, _, _) = owned_env
+            .execute_transaction(
+                PrincipalData::Standard(StandardPrincipalData::transient()),
+                None,
+                caller_id,
+                "call",
+                &[SymbolicExpression::atom_value(Value::UInt(5))],
+            )
+            .unwrap();
+
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        (result, output)
+    }
+
+    #[test]
+    fn test_function_breakpoint_across_contract_call() {
+        let (result, output) = debug_call(&[
+            "break counter::add",
+            "continue",
+            "locals",
+            "finish",
+            "continue",
+        ]);
+
+        assert_eq!(result, Value::okay(Value::UInt(5)).unwrap());
+        assert!(output.contains("breakpoint 1: counter::add"), "{}", output);
+        assert!(output.contains("breakpoint 1: entering"), "{}", output);
+        assert!(output.contains("a = u0\nb = u5"), "{}", output);
+        assert!(output.contains(".counter::add: u5"), "{}", output);
+    }
+
+    #[test]
+    fn test_step_over_skips_calls() {
+        let (_, output) = debug_call(&[
+            "break bump",
+            "continue",
+            "step",
+            "next",
+            "backtrace",
+            "quit",
+        ]);
+
+        assert!(
+            output.contains("( map-set balances tx-sender ( var-get counter ) )"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("( + a b )"), "{}", output);
+        assert!(output.contains("#0 "), "{}", output);
+        assert!(output.contains(".counter::bump"), "{}", output);
+    }
+
+    #[test]
+    fn test_watches() {
+        let (result, output) = debug_call(&[
+            "watch counter",
+            "watch counter::balances",
+            "info",
+            "continue",
+            "continue",
+            "continue",
+        ]);
+
+        assert_eq!(result, Value::okay(Value::UInt(5)).unwrap());
+        assert!(
+            output.contains("watch 1: counter\nwatch 2: counter::balances"),
+            "{}",
+            output
+        );
+        assert!(output.contains(".counter::counter: u0 -> u5"), "{}", output);
+        assert!(output.contains(".counter::balances["), "{}", output);
+        assert!(output.contains("]: none -> (some u5)"), "{}", output);
+    }
+
+    #[test]
+    fn test_print_discards_writes() {
+        let (result, output) = debug_call(&[
+            "break add",
+            "continue",
+            "print (+ a b u1)",
+            "print (var-set counter u100)",
+            "print (var-get counter)",
+            "print (undefined-function)",
+            "delete 1",
+            "continue",
+        ]);
+
+        assert_eq!(result, Value::okay(Value::UInt(5)).unwrap());
+        let printed: Vec<_> = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("(debug) "))
+            .collect();
+        assert_eq!(printed[2..5], ["u6", "true", "u0"]);
+        assert!(printed[5].starts_with("error: "), "{}", output);
+    }
+
+    #[test]
+    fn test_restart_stops_again_until_quit() {
+        let output = SharedOutput::default();
+        let mut commands = vec!["continue".to_string(), "quit".to_string()].into_iter();
+        let mut debugger =
+            Debugger::new(Box::new(move || commands.next()), Box::new(output.clone()));
+
+        let mut marf = MemoryBackingStore::new();
+        for _ in 0..3 {
+            debugger.restart();
+            let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db(), StacksEpochId::Epoch21);
+            owned_env.add_eval_hook(&mut debugger);
+            let (result, _, _) = owned_env.eval_raw("(+ 1 2)").unwrap();
+            assert_eq!(result, Value::Int(3));
+        }
+
+        // once for each evaluation before quitting
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert_eq!(output.matches("(debug) ").count(), 2, "{}", output);
+    }
+}
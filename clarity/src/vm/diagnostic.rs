@@ -33,6 +33,10 @@ pub trait DiagnosableError {
     fn level(&self) -> Level {
         Level::Error
     }
+    /// Stable identifier of the kind of error, for tools which match on diagnostics
+    fn code(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -41,6 +45,8 @@ pub struct Diagnostic {
     pub message: String,
     pub spans: Vec<Span>,
     pub suggestion: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
 impl Diagnostic {
@@ -50,6 +56,7 @@ impl Diagnostic {
             level: Level::Error,
             message: error.message(),
             suggestion: error.suggestion(),
+            code: error.code(),
         }
     }
 
@@ -235,6 +235,7 @@ impl NFTTransferEventData {
             "sender": format!("{}",self.sender),
             "recipient": format!("{}",self.recipient),
             "value": self.value,
+            "json_value": self.value.serialize_to_json(),
             "raw_value": format!("0x{}", raw_value.join("")),
         }))
     }
@@ -259,6 +260,7 @@ impl NFTMintEventData {
             "asset_identifier": format!("{}", self.asset_identifier),
             "recipient": format!("{}",self.recipient),
             "value": self.value,
+            "json_value": self.value.serialize_to_json(),
             "raw_value": format!("0x{}", raw_value.join("")),
         }))
     }
@@ -283,6 +285,7 @@ impl NFTBurnEventData {
             "asset_identifier": format!("{}", self.asset_identifier),
             "sender": format!("{}",self.sender),
             "value": self.value,
+            "json_value": self.value.serialize_to_json(),
             "raw_value": format!("0x{}", raw_value.join("")),
         }))
     }
@@ -359,6 +362,7 @@ impl SmartContractEventData {
             "contract_identifier": self.key.0.to_string(),
             "topic": self.key.1,
             "value": self.value,
+            "json_value": self.value.serialize_to_json(),
             "raw_value": format!("0x{}", raw_value.join("")),
         }))
     }
@@ -38,6 +38,8 @@ pub mod docs;
 pub mod version;
 
 pub mod coverage;
+pub mod debugger;
+pub mod profiler;
 
 pub mod events;
 
@@ -0,0 +1,366 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use serde_json::Value as JsonValue;
+
+use super::EvalHook;
+use crate::vm::contexts::{Environment, LocalContext};
+use crate::vm::costs::{ExecutionCost, LimitedCostTracker};
+use crate::vm::errors::Error;
+use crate::vm::representations::SymbolicExpression;
+use crate::vm::{ExecutionResult, Value};
+
+/// A dimension of `ExecutionCost`, used to weigh the stacks of a folded-stack profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostDimension {
+    Runtime,
+    ReadCount,
+    ReadLength,
+    WriteCount,
+    WriteLength,
+}
+
+impl CostDimension {
+    pub const ALL: [CostDimension; 5] = [
+        CostDimension::Runtime,
+        CostDimension::ReadCount,
+        CostDimension::ReadLength,
+        CostDimension::WriteCount,
+        CostDimension::WriteLength,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CostDimension::Runtime => "runtime",
+            CostDimension::ReadCount => "read_count",
+            CostDimension::ReadLength => "read_length",
+            CostDimension::WriteCount => "write_count",
+            CostDimension::WriteLength => "write_length",
+        }
+    }
+
+    pub fn of(&self, cost: &ExecutionCost) -> u64 {
+        match self {
+            CostDimension::Runtime => cost.runtime,
+            CostDimension::ReadCount => cost.read_count,
+            CostDimension::ReadLength => cost.read_length,
+            CostDimension::WriteCount => cost.write_count,
+            CostDimension::WriteLength => cost.write_length,
+        }
+    }
+}
+
+/// The costs attributed to a user-defined function
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunctionProfile {
+    pub contract: String,
+    pub function: String,
+    pub calls: u64,
+    /// Cost charged while evaluating the function's own body
+    pub self_cost: ExecutionCost,
+    /// Cost charged while evaluating the function, including the functions it called
+    pub total_cost: ExecutionCost,
+}
+
+/// The costs charged while a source expression was the innermost one being evaluated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpanProfile {
+    pub contract: String,
+    pub line: u32,
+    pub column: u32,
+    pub cost: ExecutionCost,
+}
+
+/// A function (or a contract's top-level code) which is being evaluated
+struct Frame {
+    /// `<contract>::<function>`, or the contract identifier for top-level code
+    name: String,
+    /// The function's key in `functions`, and the id of its body
+    function: Option<((String, String), u64)>,
+    /// The evaluation depth of the function body, or of the top-level expression
+    depth: usize,
+    entry_cost: ExecutionCost,
+}
+
+/// An execution profiler for Clarity programs, driven by `EvalHook` callbacks.
+///
+/// Every time the cost tracker's total grows between two callbacks, the difference is charged to
+/// the innermost expression being evaluated, and to the stack of functions being evaluated. The
+/// accumulated costs can be written out as a JSON summary, or as folded stacks (one line per
+/// stack, with its cost in one dimension) for flamegraph tools.
+///
+/// The profiler only sees the costs charged while expressions are being evaluated: the costs of
+/// parsing, analyzing and loading a transaction's contract are not attributed. Source lines and
+/// columns rely on expression spans, which are only recorded in builds with the
+/// `developer-mode` feature, and are not stored with deployed contracts: the costs of
+/// expressions without a span are left out of `spans()`.
+pub struct ExecutionProfiler {
+    functions: BTreeMap<(String, String), FunctionProfile>,
+    spans: BTreeMap<(String, u32, u32), ExecutionCost>,
+    stacks: BTreeMap<String, ExecutionCost>,
+    total: ExecutionCost,
+    frames: Vec<Frame>,
+    /// The contract and span of each expression being evaluated, or `None` if its span is unknown
+    expressions: Vec<Option<(String, u32, u32)>>,
+    last_total: ExecutionCost,
+}
+
+impl Default for ExecutionProfiler {
+    fn default() -> ExecutionProfiler {
+        ExecutionProfiler::new()
+    }
+}
+
+impl ExecutionProfiler {
+    pub fn new() -> ExecutionProfiler {
+        ExecutionProfiler {
+            functions: BTreeMap::new(),
+            spans: BTreeMap::new(),
+            stacks: BTreeMap::new(),
+            total: ExecutionCost::zero(),
+            frames: vec![],
+            expressions: vec![],
+            last_total: ExecutionCost::zero(),
+        }
+    }
+
+    /// The total cost attributed so far
+    pub fn total(&self) -> &ExecutionCost {
+        &self.total
+    }
+
+    /// The profiled functions, most expensive (in runtime) first
+    pub fn functions(&self) -> Vec<&FunctionProfile> {
+        let mut functions: Vec<_> = self.functions.values().collect();
+        functions.sort_by_key(|function| Reverse(function.total_cost.runtime));
+        functions
+    }
+
+    /// The profiled expressions, most expensive (in runtime) first. This is empty if none of
+    /// the evaluated expressions had a span.
+    pub fn spans(&self) -> Vec<SpanProfile> {
+        let mut spans: Vec<_> = self
+            .spans
+            .iter()
+            .map(|((contract, line, column), cost)| SpanProfile {
+                contract: contract.clone(),
+                line: *line,
+                column: *column,
+                cost: cost.clone(),
+            })
+            .collect();
+        spans.sort_by_key(|span| Reverse(span.cost.runtime));
+        spans
+    }
+
+    /// The JSON summary. `spans` is left out if no expression positions are known, rather than
+    /// listing every cost at line 0.
+    pub fn to_json(&self) -> JsonValue {
+        let mut summary = json!({
+            "total": self.total,
+            "functions": self.functions(),
+        });
+        if !self.spans.is_empty() {
+            summary["spans"] = json!(self.spans());
+        }
+        summary
+    }
+
+    /// Write one line per stack of evaluated functions, with the stack's own cost in
+    /// `dimension`. Stacks which cost nothing in `dimension` are omitted.
+    pub fn write_folded<W: Write>(&self, out: &mut W, dimension: CostDimension) -> io::Result<()> {
+        for (stack, cost) in self.stacks.iter() {
+            let weight = dimension.of(cost);
+            if weight > 0 {
+                writeln!(out, "{} {}", stack, weight)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the JSON summary to `<prefix>.json`, and a folded-stack file for every cost
+    /// dimension to `<prefix>.<dimension>.folded`.
+    pub fn write_files(&self, prefix: &str) -> io::Result<()> {
+        let summary = File::create(format!("{}.json", prefix))?;
+        serde_json::to_writer_pretty(summary, &self.to_json())?;
+        for dimension in CostDimension::ALL.iter() {
+            let mut folded = BufWriter::new(File::create(format!(
+                "{}.{}.folded",
+                prefix,
+                dimension.name()
+            ))?);
+            self.write_folded(&mut folded, *dimension)?;
+            folded.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Charge the cost incurred since the last callback to the expression and the functions
+    /// being evaluated.
+    fn charge(&mut self, env: &Environment) {
+        // `contract-call?` short-circuiting evaluates the call with a free tracker: leave the
+        //  baseline alone so that the charge is attributed once the original tracker is back.
+        if let LimitedCostTracker::Free = env.global_context.cost_track {
+            return;
+        }
+        let current = env.global_context.cost_track.get_total();
+        let cost = ExecutionCost {
+            runtime: current.runtime.saturating_sub(self.last_total.runtime),
+            read_count: current
+                .read_count
+                .saturating_sub(self.last_total.read_count),
+            read_length: current
+                .read_length
+                .saturating_sub(self.last_total.read_length),
+            write_count: current
+                .write_count
+                .saturating_sub(self.last_total.write_count),
+            write_length: current
+                .write_length
+                .saturating_sub(self.last_total.write_length),
+        };
+        self.last_total = current;
+        if cost == ExecutionCost::zero() {
+            return;
+        }
+        let Some(expression) = self.expressions.last() else {
+            return;
+        };
+
+        add_cost(&mut self.total, &cost);
+        if let Some(expression) = expression {
+            add_cost(
+                self.spans
+                    .entry(expression.clone())
+                    .or_insert_with(ExecutionCost::zero),
+                &cost,
+            );
+        }
+        let stack = self
+            .frames
+            .iter()
+            .map(|frame| frame.name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        add_cost(
+            self.stacks.entry(stack).or_insert_with(ExecutionCost::zero),
+            &cost,
+        );
+        if let Some((key, _)) = self.frames.last().and_then(|frame| frame.function.as_ref()) {
+            if let Some(function) = self.functions.get_mut(key) {
+                add_cost(&mut function.self_cost, &cost);
+            }
+        }
+    }
+}
+
+fn add_cost(total: &mut ExecutionCost, cost: &ExecutionCost) {
+    // the tracker's totals can't overflow, so neither can their differences
+    total.runtime = total.runtime.saturating_add(cost.runtime);
+    total.read_count = total.read_count.saturating_add(cost.read_count);
+    total.read_length = total.read_length.saturating_add(cost.read_length);
+    total.write_count = total.write_count.saturating_add(cost.write_count);
+    total.write_length = total.write_length.saturating_add(cost.write_length);
+}
+
+impl EvalHook for ExecutionProfiler {
+    fn will_begin_eval(
+        &mut self,
+        env: &mut Environment,
+        _context: &LocalContext,
+        expr: &SymbolicExpression,
+    ) {
+        let contract = env.contract_context.contract_identifier.to_string();
+        if self.expressions.is_empty() {
+            // a new evaluation: costs charged outside of it aren't attributed
+            self.last_total = env.global_context.cost_track.get_total();
+        } else {
+            self.charge(env);
+        }
+        let depth = self.expressions.len();
+
+        if let Some(function) = env.contract_context.lookup_function_by_body(expr) {
+            let key = (contract.clone(), function.get_name().to_string());
+            self.functions
+                .entry(key.clone())
+                .or_insert_with(|| FunctionProfile {
+                    contract: key.0.clone(),
+                    function: key.1.clone(),
+                    calls: 0,
+                    self_cost: ExecutionCost::zero(),
+                    total_cost: ExecutionCost::zero(),
+                })
+                .calls += 1;
+            self.frames.push(Frame {
+                name: format!("{}::{}", key.0, key.1),
+                function: Some((key, expr.id)),
+                depth,
+                entry_cost: self.last_total.clone(),
+            });
+        } else if self.frames.is_empty() {
+            self.frames.push(Frame {
+                name: contract.clone(),
+                function: None,
+                depth,
+                entry_cost: self.last_total.clone(),
+            });
+        }
+
+        let span = expr.span();
+        self.expressions.push(
+            (span.start_line > 0).then(|| (contract, span.start_line, span.start_column)),
+        );
+    }
+
+    fn did_finish_eval(
+        &mut self,
+        env: &mut Environment,
+        _context: &LocalContext,
+        expr: &SymbolicExpression,
+        _res: &Result<Value, Error>,
+    ) {
+        self.charge(env);
+        self.expressions.pop();
+        let depth = self.expressions.len();
+
+        let finished = self.frames.last().is_some_and(|frame| {
+            frame.depth == depth
+                && match frame.function {
+                    Some((_, body_id)) => body_id == expr.id,
+                    None => true,
+                }
+        });
+        if finished {
+            if let Some(frame) = self.frames.pop() {
+                if let Some((key, _)) = frame.function {
+                    if let Some(function) = self.functions.get_mut(&key) {
+                        let mut cost = self.last_total.clone();
+                        if cost.sub(&frame.entry_cost).is_ok() {
+                            add_cost(&mut function.total_cost, &cost);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn did_complete(&mut self, _result: core::result::Result<&mut ExecutionResult, String>) {}
+}
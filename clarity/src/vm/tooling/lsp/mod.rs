@@ -0,0 +1,1020 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A language server for Clarity contracts, speaking the Language Server Protocol
+//! (JSON-RPC with `Content-Length` framing) over a pair of streams.
+//!
+//! Every open document is a contract, named after its file, deployed by the same
+//! transient issuer: `contract-call?`s between open documents are analyzed and
+//! resolved as if the documents were deployed together. Positions are converted
+//! from the source spans of the AST, which are only tracked with the
+//! `developer-mode` feature. Spans count characters, so the server offers the
+//! `utf-32` position encoding, and otherwise converts columns to and from the
+//! protocol's default of UTF-16 code units.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value as JsonValue};
+use stacks_common::types::StacksEpochId;
+
+use crate::vm::analysis::linter::{LintConfig, Linter};
+use crate::vm::analysis::{run_analysis, ContractAnalysis};
+use crate::vm::ast::build_ast_with_diagnostics;
+use crate::vm::costs::LimitedCostTracker;
+use crate::vm::database::MemoryBackingStore;
+use crate::vm::diagnostic::{Diagnostic, Level};
+use crate::vm::docs::{
+    make_api_reference, make_define_reference, make_keyword_reference, FunctionAPI,
+};
+use crate::vm::errors::InterpreterResult;
+use crate::vm::functions::define::DefineFunctions;
+use crate::vm::functions::NativeFunctions;
+use crate::vm::representations::{ClarityName, Span, SymbolicExpression};
+use crate::vm::types::{
+    FixedFunction, FunctionType, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData,
+    Value,
+};
+use crate::vm::variables::NativeVariables;
+use crate::vm::{ClarityVersion, ContractName};
+
+#[cfg(test)]
+mod tests;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// `DiagnosticSeverity` values of the protocol
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+const SEVERITY_INFORMATION: u32 = 3;
+
+/// `CompletionItemKind` values of the protocol
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+const COMPLETION_CONSTANT: u32 = 21;
+
+/// `SymbolKind` values of the protocol
+const SYMBOL_INTERFACE: u32 = 11;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_VARIABLE: u32 = 13;
+const SYMBOL_CONSTANT: u32 = 14;
+const SYMBOL_STRUCT: u32 = 23;
+
+/// `TextDocumentSyncKind.Full`: clients send the whole text of a document on each change
+const SYNC_FULL: u32 = 1;
+
+/// How the `character` of a protocol position is counted, as negotiated by `initialize`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PositionEncoding {
+    /// UTF-16 code units, the protocol's default
+    Utf16,
+    /// Unicode code points, i.e. characters, as counted by the AST spans
+    Utf32,
+}
+
+impl PositionEncoding {
+    fn name(&self) -> &'static str {
+        match self {
+            PositionEncoding::Utf16 => "utf-16",
+            PositionEncoding::Utf32 => "utf-32",
+        }
+    }
+}
+
+type RpcResult = Result<JsonValue, (i64, String)>;
+
+/// Reads one message, returning `None` at the end of the stream. Malformed
+/// bodies are returned as `Some(Err(..))`, so that the server can answer them.
+pub fn read_message<R: BufRead>(
+    reader: &mut R,
+) -> io::Result<Option<Result<JsonValue, serde_json::Error>>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // tolerate blank lines between messages
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length =
+                    Some(value.trim().parse::<usize>().map_err(|e| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("{}", e))
+                    })?);
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &JsonValue) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// An open document, and the results of its last analysis
+struct Document {
+    text: String,
+    contract_identifier: QualifiedContractIdentifier,
+    expressions: Vec<SymbolicExpression>,
+    analysis: Option<ContractAnalysis>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A top-level definition of a contract
+struct Definition<'a> {
+    define_type: DefineFunctions,
+    name: &'a ClarityName,
+    name_expression: &'a SymbolicExpression,
+    expression: &'a SymbolicExpression,
+}
+
+pub struct LanguageServer {
+    documents: BTreeMap<String, Document>,
+    epoch: StacksEpochId,
+    clarity_version: ClarityVersion,
+    lint_config: LintConfig,
+    position_encoding: PositionEncoding,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        LanguageServer::new(StacksEpochId::latest(), ClarityVersion::latest())
+    }
+}
+
+impl LanguageServer {
+    pub fn new(epoch: StacksEpochId, clarity_version: ClarityVersion) -> LanguageServer {
+        LanguageServer {
+            documents: BTreeMap::new(),
+            epoch,
+            clarity_version,
+            lint_config: LintConfig::default(),
+            position_encoding: PositionEncoding::Utf16,
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    /// Serves messages from `reader` until the client sends `exit` or closes the
+    /// stream, returning the process exit code the protocol asks for.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> io::Result<i32> {
+        while let Some(message) = read_message(&mut reader)? {
+            let outgoing = match message {
+                Ok(message) => self.handle(&message),
+                Err(e) => vec![error_response(
+                    &JsonValue::Null,
+                    PARSE_ERROR,
+                    format!("{}", e),
+                )],
+            };
+            for message in outgoing.iter() {
+                write_message(&mut writer, message)?;
+            }
+            if self.exit {
+                break;
+            }
+        }
+        Ok(if self.shutdown { 0 } else { 1 })
+    }
+
+    /// Handles one message from the client, returning the messages to send back
+    pub fn handle(&mut self, message: &JsonValue) -> Vec<JsonValue> {
+        let params = message.get("params").unwrap_or(&JsonValue::Null);
+        let method = message.get("method").and_then(|method| method.as_str());
+        match (message.get("id"), method) {
+            (Some(id), Some(method)) => {
+                let response = match self.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, error)) => error_response(id, code, error),
+                };
+                vec![response]
+            }
+            (None, Some(method)) => self.handle_notification(method, params),
+            (Some(id), None) => {
+                vec![error_response(id, INVALID_REQUEST, "missing method".into())]
+            }
+            // the server doesn't send requests, so there are no responses to handle
+            (None, None) => vec![],
+        }
+    }
+
+    fn handle_request(&mut self, method: &str, params: &JsonValue) -> RpcResult {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "the server is shut down".into()));
+        }
+        match method {
+            "initialize" => {
+                let offers_utf32 = params["capabilities"]["general"]["positionEncodings"]
+                    .as_array()
+                    .is_some_and(|encodings| encodings.iter().any(|e| e == "utf-32"));
+                self.position_encoding = if offers_utf32 {
+                    PositionEncoding::Utf32
+                } else {
+                    PositionEncoding::Utf16
+                };
+                Ok(json!({
+                    "capabilities": {
+                        "positionEncoding": self.position_encoding.name(),
+                        "textDocumentSync": SYNC_FULL,
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                        "completionProvider": { "triggerCharacters": ["("] },
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "clarity-lsp" },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(JsonValue::Null)
+            }
+            "textDocument/hover" => {
+                let (uri, line, column) = self.document_position(params)?;
+                Ok(self.hover(uri, line, column).unwrap_or(JsonValue::Null))
+            }
+            "textDocument/definition" => {
+                let (uri, line, column) = self.document_position(params)?;
+                Ok(self
+                    .definition(uri, line, column)
+                    .unwrap_or(JsonValue::Null))
+            }
+            "textDocument/completion" => {
+                let (uri, line, column) = self.document_position(params)?;
+                Ok(JsonValue::Array(self.completion(uri, line, column)))
+            }
+            "textDocument/documentSymbol" => {
+                let uri = document_uri(params)?;
+                Ok(JsonValue::Array(self.document_symbols(uri)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method: {}", method))),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &JsonValue) -> Vec<JsonValue> {
+        match method {
+            "exit" => {
+                self.exit = true;
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                match (document["uri"].as_str(), document["text"].as_str()) {
+                    (Some(uri), Some(text)) => self.update_document(uri, text.to_string()),
+                    _ => vec![],
+                }
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str();
+                // with full synchronization, the last change holds the whole text
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (uri, text) {
+                    (Some(uri), Some(text)) => self.update_document(uri, text.to_string()),
+                    _ => vec![],
+                }
+            }
+            "textDocument/didClose" => match params["textDocument"]["uri"].as_str() {
+                Some(uri) if self.documents.remove(uri).is_some() => {
+                    let mut outgoing = vec![publish_diagnostics(uri, vec![])];
+                    outgoing.append(&mut self.analyze());
+                    outgoing
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    fn update_document(&mut self, uri: &str, text: String) -> Vec<JsonValue> {
+        let contract_identifier = contract_name_from_uri(uri)
+            .map(|name| QualifiedContractIdentifier::new(StandardPrincipalData::transient(), name))
+            .unwrap_or_else(|_| QualifiedContractIdentifier::transient());
+        self.documents.insert(
+            uri.to_string(),
+            Document {
+                text,
+                contract_identifier,
+                expressions: vec![],
+                analysis: None,
+                diagnostics: vec![],
+            },
+        );
+        self.analyze()
+    }
+
+    /// Analyzes every open document, and publishes their diagnostics. Documents
+    /// which fail the analysis are retried as long as others pass, since they
+    /// may call contracts which had not been analyzed yet.
+    fn analyze(&mut self) -> Vec<JsonValue> {
+        let mut store = MemoryBackingStore::new();
+        let mut pending = vec![];
+        for (uri, document) in self.documents.iter_mut() {
+            let (contract_ast, diagnostics, success) = build_ast_with_diagnostics(
+                &document.contract_identifier,
+                &document.text,
+                &mut (),
+                self.clarity_version,
+                self.epoch,
+            );
+            document.expressions = contract_ast.expressions;
+            document.analysis = None;
+            document.diagnostics = diagnostics;
+            // contracts are only found by the analysis once their hash is committed
+            if success
+                && register_contract(&mut store, &document.contract_identifier, &document.text)
+                    .is_ok()
+            {
+                pending.push(uri.clone());
+            }
+        }
+
+        let mut failures = BTreeMap::new();
+        loop {
+            let mut progress = false;
+            failures.clear();
+            for uri in pending.iter() {
+                let Some(document) = self.documents.get_mut(uri) else {
+                    continue;
+                };
+                let result = run_analysis(
+                    &document.contract_identifier,
+                    &document.expressions,
+                    &mut store.as_analysis_db(),
+                    true,
+                    LimitedCostTracker::new_free(),
+                    self.epoch,
+                    self.clarity_version,
+                    true,
+                );
+                match result {
+                    Ok(analysis) => {
+                        if let Ok(mut warnings) = Linter::new(&self.lint_config).run(&analysis) {
+                            document.diagnostics.append(&mut warnings);
+                        }
+                        document.analysis = Some(analysis);
+                        progress = true;
+                    }
+                    Err((error, _)) => {
+                        failures.insert(uri.clone(), error.diagnostic);
+                    }
+                }
+            }
+            if !progress || failures.is_empty() {
+                break;
+            }
+            pending = failures.keys().cloned().collect();
+        }
+        for (uri, diagnostic) in failures.into_iter() {
+            if let Some(document) = self.documents.get_mut(&uri) {
+                document.diagnostics.push(diagnostic);
+            }
+        }
+
+        self.documents
+            .iter()
+            .map(|(uri, document)| {
+                let diagnostics = document
+                    .diagnostics
+                    .iter()
+                    .map(|diagnostic| {
+                        to_lsp_diagnostic(&document.text, self.position_encoding, diagnostic)
+                    })
+                    .collect();
+                publish_diagnostics(uri, diagnostics)
+            })
+            .collect()
+    }
+
+    /// The open document deploying the contract called by a `contract-call?`
+    fn contract_call_target(&self, call: &[SymbolicExpression]) -> Option<(&String, &Document)> {
+        if call.first()?.match_atom()?.as_str() != "contract-call?" {
+            return None;
+        }
+        let Value::Principal(PrincipalData::Contract(contract_identifier)) =
+            call.get(1)?.match_literal_value()?
+        else {
+            return None;
+        };
+        self.documents
+            .iter()
+            .find(|(_, document)| document.contract_identifier == *contract_identifier)
+    }
+
+    fn hover(&self, uri: &str, line: u32, column: u32) -> Option<JsonValue> {
+        let document = self.documents.get(uri)?;
+        let mut path = vec![];
+        find_path(&document.expressions, line, column, &mut path);
+        let expression = *path.last()?;
+        let contents = self.describe(document, &path)?;
+        Some(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": to_lsp_range(&document.text, self.position_encoding, expression.span()),
+        }))
+    }
+
+    /// Documents the innermost expression of `path`: builtins with their reference
+    /// documentation, names of definitions with their signatures, and any other
+    /// expression with the type the type checker inferred for it.
+    fn describe(&self, document: &Document, path: &[&SymbolicExpression]) -> Option<String> {
+        let expression = *path.last()?;
+        let analysis = document.analysis.as_ref();
+        if let Some(name) = expression.match_atom() {
+            let siblings = path
+                .len()
+                .checked_sub(2)
+                .and_then(|i| path[i].match_list())
+                .unwrap_or_default();
+            let position = siblings
+                .iter()
+                .position(|sibling| std::ptr::eq(sibling, expression));
+            if position == Some(0) {
+                if let Some(function) =
+                    NativeFunctions::lookup_by_name_at_version(name, &self.clarity_version)
+                {
+                    return Some(function_documentation(&make_api_reference(&function)));
+                }
+                if let Some(define_type) = DefineFunctions::lookup_by_name(name) {
+                    return Some(function_documentation(&make_define_reference(&define_type)));
+                }
+            }
+            if position == Some(2) {
+                if let Some((_, target)) = self.contract_call_target(siblings) {
+                    let target_analysis = target.analysis.as_ref()?;
+                    if let Some(function_type) = target_analysis.get_public_function_type(name) {
+                        return Some(code_block(&function_signature(
+                            DefineFunctions::PublicFunction,
+                            name,
+                            function_type,
+                        )));
+                    }
+                    let function_type = target_analysis.get_read_only_function_type(name)?;
+                    return Some(code_block(&function_signature(
+                        DefineFunctions::ReadOnlyFunction,
+                        name,
+                        function_type,
+                    )));
+                }
+            }
+            if let Some(keyword) =
+                NativeVariables::lookup_by_name_at_version(name, &self.clarity_version)
+                    .as_ref()
+                    .and_then(make_keyword_reference)
+            {
+                return Some(format!(
+                    "{}\n\n`{}`\n\n{}",
+                    code_block(keyword.name),
+                    keyword.output_type,
+                    keyword.description
+                ));
+            }
+            if let Some(description) = analysis.and_then(|a| describe_definition(a, name)) {
+                return Some(description);
+            }
+        }
+        let signature = analysis?.type_map.as_ref()?.get_type_expected(expression)?;
+        Some(code_block(&signature.to_string()))
+    }
+
+    fn definition(&self, uri: &str, line: u32, column: u32) -> Option<JsonValue> {
+        let document = self.documents.get(uri)?;
+        let mut path = vec![];
+        find_path(&document.expressions, line, column, &mut path);
+        let expression = *path.last()?;
+
+        let siblings = path
+            .len()
+            .checked_sub(2)
+            .and_then(|i| path[i].match_list())
+            .unwrap_or_default();
+        if let Some((target_uri, target)) = self.contract_call_target(siblings) {
+            match siblings
+                .iter()
+                .position(|sibling| std::ptr::eq(sibling, expression))
+            {
+                Some(1) => return Some(self.location(target_uri, &target.text, &Span::ZERO)),
+                Some(2) => {
+                    let name = expression.match_atom()?;
+                    let definition = definitions(&target.expressions)
+                        .into_iter()
+                        .find(|definition| definition.name == name)?;
+                    return Some(self.location(
+                        target_uri,
+                        &target.text,
+                        definition.name_expression.span(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let name = expression.match_atom()?;
+        if let Some((_, binding)) = bindings_in_scope(&path)
+            .into_iter()
+            .find(|(binding, _)| *binding == name)
+        {
+            return Some(self.location(uri, &document.text, binding.span()));
+        }
+        let definition = definitions(&document.expressions)
+            .into_iter()
+            .find(|definition| definition.name == name)?;
+        Some(self.location(uri, &document.text, definition.name_expression.span()))
+    }
+
+    fn completion(&self, uri: &str, line: u32, column: u32) -> Vec<JsonValue> {
+        let Some(document) = self.documents.get(uri) else {
+            return vec![];
+        };
+        let prefix = word_before(&document.text, line, column);
+        let mut path = vec![];
+        find_path(&document.expressions, line, column, &mut path);
+
+        let mut items = vec![];
+        for (name, _) in bindings_in_scope(&path) {
+            items.push(completion_item(name, COMPLETION_VARIABLE, None));
+        }
+        for definition in definitions(&document.expressions) {
+            let kind = match definition.define_type {
+                DefineFunctions::PrivateFunction
+                | DefineFunctions::PublicFunction
+                | DefineFunctions::ReadOnlyFunction => COMPLETION_FUNCTION,
+                DefineFunctions::Constant => COMPLETION_CONSTANT,
+                _ => COMPLETION_VARIABLE,
+            };
+            items.push(completion_item(
+                definition.name,
+                kind,
+                Some(definition.define_type.get_name_str()),
+            ));
+        }
+        for function in NativeFunctions::ALL.iter() {
+            if NativeFunctions::lookup_by_name_at_version(
+                function.get_name_str(),
+                &self.clarity_version,
+            )
+            .is_some()
+            {
+                let api = make_api_reference(function);
+                items.push(completion_item(
+                    function.get_name_str(),
+                    COMPLETION_FUNCTION,
+                    Some(&api.signature),
+                ));
+            }
+        }
+        for define_type in DefineFunctions::ALL.iter() {
+            items.push(completion_item(
+                define_type.get_name_str(),
+                COMPLETION_KEYWORD,
+                None,
+            ));
+        }
+        for variable in NativeVariables::ALL.iter() {
+            if NativeVariables::lookup_by_name_at_version(
+                variable.get_name_str(),
+                &self.clarity_version,
+            )
+            .is_some()
+            {
+                items.push(completion_item(
+                    variable.get_name_str(),
+                    COMPLETION_KEYWORD,
+                    None,
+                ));
+            }
+        }
+        items.retain(|item| {
+            item["label"]
+                .as_str()
+                .is_some_and(|label| label.starts_with(prefix.as_str()))
+        });
+        items
+    }
+
+    /// Extracts `(uri, line, column)` from text document position parameters, with
+    /// the position converted to the 1-based lines and columns of the AST spans.
+    fn document_position<'a>(
+        &self,
+        params: &'a JsonValue,
+    ) -> Result<(&'a str, u32, u32), (i64, String)> {
+        let uri = document_uri(params)?;
+        let position = &params["position"];
+        let line = position["line"]
+            .as_u64()
+            .and_then(|x| u32::try_from(x).ok());
+        let character = position["character"]
+            .as_u64()
+            .and_then(|x| u32::try_from(x).ok());
+        match (line, character) {
+            (Some(line), Some(character)) => {
+                let column = match self.documents.get(uri) {
+                    Some(document) => {
+                        span_column(&document.text, line, character, self.position_encoding)
+                    }
+                    None => character,
+                };
+                Ok((uri, line.saturating_add(1), column.saturating_add(1)))
+            }
+            _ => Err((INVALID_PARAMS, "missing position".into())),
+        }
+    }
+
+    fn location(&self, uri: &str, text: &str, span: &Span) -> JsonValue {
+        json!({ "uri": uri, "range": to_lsp_range(text, self.position_encoding, span) })
+    }
+
+    fn document_symbols(&self, uri: &str) -> Vec<JsonValue> {
+        let Some(document) = self.documents.get(uri) else {
+            return vec![];
+        };
+        definitions(&document.expressions)
+            .into_iter()
+            .map(|definition| {
+                let kind = match definition.define_type {
+                    DefineFunctions::PrivateFunction
+                    | DefineFunctions::PublicFunction
+                    | DefineFunctions::ReadOnlyFunction => SYMBOL_FUNCTION,
+                    DefineFunctions::Constant => SYMBOL_CONSTANT,
+                    DefineFunctions::Map => SYMBOL_STRUCT,
+                    DefineFunctions::Trait | DefineFunctions::UseTrait => SYMBOL_INTERFACE,
+                    _ => SYMBOL_VARIABLE,
+                };
+                json!({
+                    "name": definition.name.as_str(),
+                    "detail": definition.define_type.get_name_str(),
+                    "kind": kind,
+                    "range": to_lsp_range(
+                        &document.text,
+                        self.position_encoding,
+                        definition.expression.span(),
+                    ),
+                    "selectionRange": to_lsp_range(
+                        &document.text,
+                        self.position_encoding,
+                        definition.name_expression.span(),
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+fn register_contract(
+    store: &mut MemoryBackingStore,
+    contract_identifier: &QualifiedContractIdentifier,
+    source: &str,
+) -> InterpreterResult<()> {
+    let mut clarity_db = store.as_clarity_db();
+    clarity_db.begin();
+    if let Err(e) = clarity_db.insert_contract_hash(contract_identifier, source) {
+        clarity_db.roll_back()?;
+        return Err(e);
+    }
+    clarity_db.commit()
+}
+
+/// The contract name of a document: the file name, up to its extension
+fn contract_name_from_uri(uri: &str) -> Result<ContractName, String> {
+    let file_name = uri.rsplit('/').next().unwrap_or(uri);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    ContractName::try_from(stem.to_string()).map_err(|e| format!("{:?}", e))
+}
+
+fn document_uri(params: &JsonValue) -> Result<&str, (i64, String)> {
+    params["textDocument"]["uri"]
+        .as_str()
+        .ok_or_else(|| (INVALID_PARAMS, "missing textDocument.uri".to_string()))
+}
+
+/// Does `span` contain the 1-based position? The column just past the end of an
+/// expression counts as inside, which is where the cursor is after typing it.
+fn span_contains(span: &Span, line: u32, column: u32) -> bool {
+    (span.start_line, span.start_column) <= (line, column)
+        && (line, column) <= (span.end_line, span.end_column.saturating_add(1))
+}
+
+/// Collects the expressions enclosing a position, outermost first. Expressions
+/// synthesized by the parser have no span, but their children may.
+fn find_path<'a>(
+    expressions: &'a [SymbolicExpression],
+    line: u32,
+    column: u32,
+    path: &mut Vec<&'a SymbolicExpression>,
+) -> bool {
+    // search backwards, so that a position between two expressions finds the latter
+    for expression in expressions.iter().rev() {
+        let span = expression.span();
+        if *span == Span::ZERO {
+            if let Some(children) = expression.match_list() {
+                path.push(expression);
+                if find_path(children, line, column, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        } else if span_contains(span, line, column) {
+            path.push(expression);
+            if let Some(children) = expression.match_list() {
+                find_path(children, line, column, path);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// The names bound by the `let`s, `match`es and function signatures enclosing the
+/// innermost expression of `path`, innermost first, with the expressions binding them.
+fn bindings_in_scope<'a>(
+    path: &[&'a SymbolicExpression],
+) -> Vec<(&'a ClarityName, &'a SymbolicExpression)> {
+    let mut bindings = vec![];
+    for expression in path.iter().rev() {
+        let Some(list) = expression.match_list() else {
+            continue;
+        };
+        let Some(head) = list.first().and_then(|head| head.match_atom()) else {
+            continue;
+        };
+        let names: Vec<&SymbolicExpression> = match head.as_str() {
+            "let" => list
+                .get(1)
+                .and_then(|pairs| pairs.match_list())
+                .unwrap_or_default()
+                .iter()
+                .rev()
+                .filter_map(|pair| pair.match_list()?.first())
+                .collect(),
+            // (match option some-name some-branch none-branch)
+            // (match response ok-name ok-branch err-name err-branch)
+            "match" if list.len() == 6 => {
+                [list.get(4), list.get(2)].into_iter().flatten().collect()
+            }
+            "match" => list.get(2).into_iter().collect(),
+            "define-private" | "define-public" | "define-read-only" => list
+                .get(1)
+                .and_then(|signature| signature.match_list())
+                .unwrap_or_default()
+                .iter()
+                .skip(1)
+                .filter_map(|argument| argument.match_list()?.first())
+                .collect(),
+            _ => continue,
+        };
+        for name_expression in names.into_iter() {
+            if let Some(name) = name_expression.match_atom() {
+                bindings.push((name, name_expression));
+            }
+        }
+    }
+    bindings
+}
+
+/// The named top-level definitions of a contract
+fn definitions(expressions: &[SymbolicExpression]) -> Vec<Definition<'_>> {
+    expressions
+        .iter()
+        .filter_map(|expression| {
+            let list = expression.match_list()?;
+            let define_type = DefineFunctions::lookup_by_name(list.first()?.match_atom()?)?;
+            let name_expression = match define_type {
+                DefineFunctions::PrivateFunction
+                | DefineFunctions::PublicFunction
+                | DefineFunctions::ReadOnlyFunction => list.get(1)?.match_list()?.first()?,
+                _ => list.get(1)?,
+            };
+            Some(Definition {
+                define_type,
+                name: name_expression.match_atom()?,
+                name_expression,
+                expression,
+            })
+        })
+        .collect()
+}
+
+/// Describes a top-level definition by the types the analysis assigned it
+fn describe_definition(analysis: &ContractAnalysis, name: &ClarityName) -> Option<String> {
+    let signature = if let Some(function_type) = analysis.get_public_function_type(name) {
+        function_signature(DefineFunctions::PublicFunction, name, function_type)
+    } else if let Some(function_type) = analysis.get_read_only_function_type(name) {
+        function_signature(DefineFunctions::ReadOnlyFunction, name, function_type)
+    } else if let Some(function_type) = analysis.get_private_function(name) {
+        function_signature(DefineFunctions::PrivateFunction, name, function_type)
+    } else if let Some(constant_type) = analysis.get_variable_type(name) {
+        format!("(define-constant {}) ;; {}", name, constant_type)
+    } else if let Some(data_type) = analysis.get_persisted_variable_type(name) {
+        format!("(define-data-var {} {})", name, data_type)
+    } else if let Some((key_type, value_type)) = analysis.get_map_type(name) {
+        format!("(define-map {} {} {})", name, key_type, value_type)
+    } else if analysis.fungible_tokens.contains(name) {
+        format!("(define-fungible-token {})", name)
+    } else if let Some(nft_type) = analysis.non_fungible_tokens.get(name) {
+        format!("(define-non-fungible-token {} {})", name, nft_type)
+    } else if let Some(functions) = analysis.defined_traits.get(name) {
+        let functions: Vec<String> = functions
+            .iter()
+            .map(|(function_name, signature)| {
+                let args: Vec<String> = signature.args.iter().map(|arg| arg.to_string()).collect();
+                format!(
+                    "\n  ({} ({}) {})",
+                    function_name,
+                    args.join(" "),
+                    signature.returns
+                )
+            })
+            .collect();
+        format!("(define-trait {} ({}))", name, functions.concat())
+    } else {
+        return None;
+    };
+    Some(code_block(&signature))
+}
+
+fn function_signature(
+    define_type: DefineFunctions,
+    name: &str,
+    function_type: &FunctionType,
+) -> String {
+    match function_type {
+        FunctionType::Fixed(FixedFunction { args, returns }) => {
+            let args: String = args
+                .iter()
+                .map(|arg| format!(" ({} {})", arg.name, arg.signature))
+                .collect();
+            format!(
+                "({} ({}{}) {})",
+                define_type.get_name_str(),
+                name,
+                args,
+                returns
+            )
+        }
+        _ => format!("({} ({}))", define_type.get_name_str(), name),
+    }
+}
+
+fn function_documentation(api: &FunctionAPI) -> String {
+    format!(
+        "{}\n\n`{}` -> `{}`\n\n{}",
+        code_block(&api.signature),
+        api.input_type,
+        api.output_type,
+        api.description
+    )
+}
+
+fn code_block(code: &str) -> String {
+    format!("```clarity\n{}\n```", code)
+}
+
+/// The part of a name typed before the 1-based position
+fn word_before(text: &str, line: u32, column: u32) -> String {
+    let Some(line) = text.lines().nth(line.saturating_sub(1) as usize) else {
+        return String::new();
+    };
+    let before: Vec<char> = line
+        .chars()
+        .take(column.saturating_sub(1) as usize)
+        .collect();
+    let start = before
+        .iter()
+        .rposition(|c| !(c.is_ascii_alphanumeric() || "-_!?+*/<>=".contains(*c)))
+        .map_or(0, |i| i + 1);
+    before[start..].iter().collect()
+}
+
+fn completion_item(label: &str, kind: u32, detail: Option<&str>) -> JsonValue {
+    let mut item = json!({ "label": label, "kind": kind });
+    if let Some(detail) = detail {
+        item["detail"] = json!(detail);
+    }
+    item
+}
+
+/// Converts the number of characters before a position on the 0-based `line` of
+/// `text` to the position's `character` in `encoding`
+fn lsp_character(text: &str, line: u32, column: u32, encoding: PositionEncoding) -> u32 {
+    match (encoding, text.lines().nth(line as usize)) {
+        (PositionEncoding::Utf16, Some(line)) => {
+            let units: usize = line
+                .chars()
+                .take(column as usize)
+                .map(char::len_utf16)
+                .sum();
+            // past the end of the line, count the missing characters as one unit each
+            let missing = (column as usize).saturating_sub(line.chars().count());
+            u32::try_from(units + missing).unwrap_or(u32::MAX)
+        }
+        _ => column,
+    }
+}
+
+/// Converts the `character` of a position on the 0-based `line` of `text` in
+/// `encoding` to the number of characters before it. A position inside a
+/// surrogate pair is moved to the end of its character.
+fn span_column(text: &str, line: u32, character: u32, encoding: PositionEncoding) -> u32 {
+    match (encoding, text.lines().nth(line as usize)) {
+        (PositionEncoding::Utf16, Some(line)) => {
+            let mut units = 0;
+            let mut column = 0;
+            for c in line.chars() {
+                if units >= character as usize {
+                    return column;
+                }
+                units += c.len_utf16();
+                column += 1;
+            }
+            let missing = (character as usize).saturating_sub(units);
+            column.saturating_add(u32::try_from(missing).unwrap_or(u32::MAX))
+        }
+        _ => character,
+    }
+}
+
+/// Converts a span to a protocol range: 0-based, with an exclusive end
+fn to_lsp_range(text: &str, encoding: PositionEncoding, span: &Span) -> JsonValue {
+    let start_line = span.start_line.saturating_sub(1);
+    let start_column = span.start_column.saturating_sub(1);
+    let end_line = span.end_line.saturating_sub(1);
+    json!({
+        "start": {
+            "line": start_line,
+            "character": lsp_character(text, start_line, start_column, encoding),
+        },
+        "end": {
+            "line": end_line,
+            "character": lsp_character(text, end_line, span.end_column, encoding),
+        },
+    })
+}
+
+fn to_lsp_diagnostic(text: &str, encoding: PositionEncoding, diagnostic: &Diagnostic) -> JsonValue {
+    let severity = match diagnostic.level {
+        Level::Error => SEVERITY_ERROR,
+        Level::Warning => SEVERITY_WARNING,
+        Level::Note => SEVERITY_INFORMATION,
+    };
+    let message = match diagnostic.suggestion {
+        Some(ref suggestion) => format!("{}\n{}", diagnostic.message, suggestion),
+        None => diagnostic.message.clone(),
+    };
+    let mut lsp_diagnostic = json!({
+        "range": to_lsp_range(text, encoding, diagnostic.spans.first().unwrap_or(&Span::ZERO)),
+        "severity": severity,
+        "source": "clarity",
+        "message": message,
+    });
+    if let Some(ref code) = diagnostic.code {
+        lsp_diagnostic["code"] = json!(code);
+    }
+    lsp_diagnostic
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<JsonValue>) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn error_response(id: &JsonValue, code: i64, message: String) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
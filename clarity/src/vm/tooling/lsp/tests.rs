@@ -0,0 +1,396 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Cursor;
+
+use serde_json::{json, Value as JsonValue};
+
+use super::{read_message, write_message, LanguageServer};
+
+const CALLEE_URI: &str = "file:///contracts/callee.clar";
+const CALLER_URI: &str = "file:///contracts/caller.clar";
+
+const CALLEE: &str = "(define-map balances principal uint)
+(define-read-only (get-balance (who principal))
+  (default-to u0 (map-get? balances who)))";
+
+const CALLER: &str = "(define-constant owner tx-sender)
+(define-public (check (amount uint))
+  (let ((balance (contract-call? .callee get-balance owner)))
+    (ok (>= balance amount))))";
+
+fn open(server: &mut LanguageServer, uri: &str, text: &str) -> Vec<JsonValue> {
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+            "textDocument": { "uri": uri, "languageId": "clarity", "version": 1, "text": text }
+        },
+    }))
+}
+
+fn request(server: &mut LanguageServer, method: &str, params: JsonValue) -> JsonValue {
+    let mut responses = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    }));
+    assert_eq!(responses.len(), 1);
+    responses.remove(0)
+}
+
+fn at(uri: &str, line: u32, character: u32) -> JsonValue {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn range(start_line: u32, start: u32, end_line: u32, end: u32) -> JsonValue {
+    json!({
+        "start": { "line": start_line, "character": start },
+        "end": { "line": end_line, "character": end },
+    })
+}
+
+/// The diagnostics published for `uri` among `messages`
+fn published<'a>(messages: &'a [JsonValue], uri: &str) -> &'a Vec<JsonValue> {
+    messages
+        .iter()
+        .find(|message| {
+            message["method"] == "textDocument/publishDiagnostics"
+                && message["params"]["uri"] == uri
+        })
+        .and_then(|message| message["params"]["diagnostics"].as_array())
+        .unwrap()
+}
+
+fn errors(diagnostics: &[JsonValue]) -> Vec<&JsonValue> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic["severity"] == 1)
+        .collect()
+}
+
+fn workspace() -> LanguageServer {
+    let mut server = LanguageServer::default();
+    open(&mut server, CALLEE_URI, CALLEE);
+    let messages = open(&mut server, CALLER_URI, CALLER);
+    let caller = published(&messages, CALLER_URI);
+    assert!(errors(caller).is_empty(), "{:?}", caller);
+    server
+}
+
+#[test]
+fn test_lifecycle() {
+    let mut input = vec![];
+    for message in [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/rename", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+    input.extend_from_slice(b"Content-Length: 8\r\n\r\nnot json");
+
+    let mut output = vec![];
+    let mut server = LanguageServer::default();
+    let code = server.run(Cursor::new(input), &mut output).unwrap();
+    assert_eq!(code, 0);
+
+    let mut reader = Cursor::new(output);
+    let initialized = read_message(&mut reader).unwrap().unwrap().unwrap();
+    assert_eq!(initialized["id"], 1);
+    let capabilities = &initialized["result"]["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+
+    let unsupported = read_message(&mut reader).unwrap().unwrap().unwrap();
+    assert_eq!(unsupported["error"]["code"], -32601);
+    let shutdown = read_message(&mut reader).unwrap().unwrap().unwrap();
+    assert_eq!(shutdown["result"], JsonValue::Null);
+    // the server stops reading at `exit`
+    assert!(read_message(&mut reader).unwrap().is_none());
+
+    // malformed messages are answered, and exiting without a shutdown is an error
+    let mut input = vec![];
+    input.extend_from_slice(b"Content-Length: 8\r\n\r\nnot json");
+    let mut output = vec![];
+    let code = LanguageServer::default()
+        .run(Cursor::new(input), &mut output)
+        .unwrap();
+    assert_eq!(code, 1);
+    let response = read_message(&mut Cursor::new(output))
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(response["error"]["code"], -32700);
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut server = LanguageServer::default();
+
+    // the callee is not open yet
+    let messages = open(&mut server, CALLER_URI, CALLER);
+    let caller_errors = errors(published(&messages, CALLER_URI)).len();
+    assert_eq!(caller_errors, 1);
+    assert_eq!(
+        errors(published(&messages, CALLER_URI))[0]["code"],
+        "NoSuchContract"
+    );
+
+    // opening it fixes the caller, regardless of the order of the documents
+    let messages = open(&mut server, CALLEE_URI, CALLEE);
+    assert!(errors(published(&messages, CALLEE_URI)).is_empty());
+    assert!(errors(published(&messages, CALLER_URI)).is_empty());
+
+    // changes to the callee are checked against the caller
+    let messages = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": CALLEE_URI, "version": 2 },
+            "contentChanges": [{ "text": "(define-read-only (get-balance (who principal)) true)" }],
+        },
+    }));
+    assert!(errors(published(&messages, CALLEE_URI)).is_empty());
+    let caller = published(&messages, CALLER_URI);
+    assert_eq!(errors(caller).len(), 1);
+    assert_eq!(caller[0]["source"], "clarity");
+
+    // every syntax error is reported, with its location
+    let messages = open(
+        &mut server,
+        "file:///contracts/broken.clar",
+        "(define-constant a u1)\n(define-constant b $)\n(define-constant c #)",
+    );
+    let broken = errors(published(&messages, "file:///contracts/broken.clar"));
+    assert_eq!(broken.len(), 2);
+    assert_eq!(broken[0]["range"]["start"]["line"], 1);
+    assert_eq!(broken[1]["range"]["start"]["line"], 2);
+
+    // closing a document clears its diagnostics
+    let messages = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didClose",
+        "params": { "textDocument": { "uri": "file:///contracts/broken.clar" } },
+    }));
+    assert!(published(&messages, "file:///contracts/broken.clar").is_empty());
+}
+
+#[test]
+fn test_hover() {
+    let mut server = workspace();
+    let contents = |server: &mut LanguageServer, line, character| {
+        let response = request(
+            server,
+            "textDocument/hover",
+            at(CALLER_URI, line, character),
+        );
+        response["result"]["contents"]["value"]
+            .as_str()
+            .map(String::from)
+    };
+
+    // native functions, define keywords and native variables are documented
+    let native = contents(&mut server, 3, 10).unwrap();
+    assert!(native.starts_with("```clarity\n(>= "), "{}", native);
+    let define = contents(&mut server, 0, 3).unwrap();
+    assert!(define.contains("define-constant"), "{}", define);
+    let keyword = contents(&mut server, 0, 25).unwrap();
+    assert!(
+        keyword.starts_with("```clarity\ntx-sender\n```"),
+        "{}",
+        keyword
+    );
+
+    // functions of called contracts, with their signatures
+    assert_eq!(
+        contents(&mut server, 2, 45).unwrap(),
+        "```clarity\n(define-read-only (get-balance (who principal)) uint)\n```"
+    );
+    // local definitions and bindings, with their types
+    assert_eq!(
+        contents(&mut server, 2, 55).unwrap(),
+        "```clarity\n(define-constant owner) ;; principal\n```"
+    );
+    assert_eq!(
+        contents(&mut server, 3, 14).unwrap(),
+        "```clarity\nuint\n```"
+    );
+    let response = request(&mut server, "textDocument/hover", at(CALLER_URI, 3, 14));
+    assert_eq!(response["result"]["range"], range(3, 12, 3, 19));
+
+    // whitespace between definitions
+    assert_eq!(contents(&mut server, 10, 0), None);
+}
+
+#[test]
+fn test_definition() {
+    let mut server = workspace();
+    let definition = |server: &mut LanguageServer, line, character| {
+        request(
+            server,
+            "textDocument/definition",
+            at(CALLER_URI, line, character),
+        )["result"]
+            .clone()
+    };
+
+    // let bindings, function arguments and top-level definitions
+    assert_eq!(
+        definition(&mut server, 3, 14),
+        json!({ "uri": CALLER_URI, "range": range(2, 9, 2, 16) })
+    );
+    assert_eq!(
+        definition(&mut server, 3, 22),
+        json!({ "uri": CALLER_URI, "range": range(1, 23, 1, 29) })
+    );
+    assert_eq!(
+        definition(&mut server, 2, 55),
+        json!({ "uri": CALLER_URI, "range": range(0, 17, 0, 22) })
+    );
+
+    // across `contract-call?`, to the called contract and function
+    assert_eq!(
+        definition(&mut server, 2, 45),
+        json!({ "uri": CALLEE_URI, "range": range(1, 19, 1, 30) })
+    );
+    assert_eq!(
+        definition(&mut server, 2, 35),
+        json!({ "uri": CALLEE_URI, "range": range(0, 0, 0, 0) })
+    );
+
+    // builtins have no definition
+    assert_eq!(definition(&mut server, 3, 10), JsonValue::Null);
+}
+
+#[test]
+fn test_completion() {
+    let mut server = workspace();
+    let labels = |server: &mut LanguageServer, line, character| -> Vec<String> {
+        request(
+            server,
+            "textDocument/completion",
+            at(CALLER_URI, line, character),
+        )["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // after `(am`, in the body of `check`
+    assert_eq!(labels(&mut server, 3, 22), vec!["amount"]);
+
+    // after `(`, everything in scope
+    let all = labels(&mut server, 3, 9);
+    for label in [
+        "balance",
+        "amount",
+        "owner",
+        "check",
+        "map-get?",
+        "define-public",
+        "tx-sender",
+    ] {
+        assert!(all.iter().any(|l| l == label), "missing {}", label);
+    }
+
+    // bindings are only in scope in their bodies
+    let top_level = labels(&mut server, 0, 1);
+    assert!(!top_level.iter().any(|l| l == "balance"));
+    assert!(top_level.iter().any(|l| l == "define-constant"));
+}
+
+#[test]
+fn test_document_symbols() {
+    let mut server = workspace();
+    let response = request(
+        &mut server,
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": CALLEE_URI } }),
+    );
+    assert_eq!(
+        response["result"],
+        json!([
+            {
+                "name": "balances",
+                "detail": "define-map",
+                "kind": 23,
+                "range": range(0, 0, 0, 36),
+                "selectionRange": range(0, 12, 0, 20),
+            },
+            {
+                "name": "get-balance",
+                "detail": "define-read-only",
+                "kind": 12,
+                "range": range(1, 0, 2, 42),
+                "selectionRange": range(1, 19, 1, 30),
+            },
+        ])
+    );
+
+    let response = request(
+        &mut server,
+        "textDocument/documentSymbol",
+        json!({ "textDocument": {} }),
+    );
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[test]
+fn test_position_encoding() {
+    const SMILE_URI: &str = "file:///contracts/smile.clar";
+    // the emoji is one character, but two UTF-16 code units
+    const SMILE: &str = "(define-constant smile u\"\u{1F600}\") (define-constant copy smile)";
+
+    // positions are in UTF-16 code units unless the client offers UTF-32
+    let mut server = LanguageServer::default();
+    let initialized = request(&mut server, "initialize", json!({}));
+    assert_eq!(
+        initialized["result"]["capabilities"]["positionEncoding"],
+        "utf-16"
+    );
+    let messages = open(&mut server, SMILE_URI, SMILE);
+    let diagnostics = published(&messages, SMILE_URI);
+    assert_eq!(diagnostics[0]["code"], "NonASCIIChar");
+    assert_eq!(diagnostics[0]["range"], range(0, 25, 0, 27));
+    let response = request(&mut server, "textDocument/hover", at(SMILE_URI, 0, 31));
+    assert_eq!(response["result"]["range"], range(0, 31, 0, 46));
+
+    let mut server = LanguageServer::default();
+    let initialized = request(
+        &mut server,
+        "initialize",
+        json!({ "capabilities": { "general": { "positionEncodings": ["utf-32", "utf-16"] } } }),
+    );
+    assert_eq!(
+        initialized["result"]["capabilities"]["positionEncoding"],
+        "utf-32"
+    );
+    let messages = open(&mut server, SMILE_URI, SMILE);
+    let diagnostics = published(&messages, SMILE_URI);
+    assert_eq!(diagnostics[0]["range"], range(0, 25, 0, 26));
+    let response = request(&mut server, "textDocument/hover", at(SMILE_URI, 0, 30));
+    assert_eq!(response["result"]["range"], range(0, 30, 0, 45));
+}
@@ -14,6 +14,10 @@ use crate::vm::costs::LimitedCostTracker;
 use crate::vm::database::MemoryBackingStore;
 use crate::vm::types::QualifiedContractIdentifier;
 
+#[cfg(feature = "developer-mode")]
+#[allow(clippy::result_large_err)]
+pub mod lsp;
+
 /// Used by CLI tools like the docs generator. Not used in production
 pub fn mem_type_check(
     snippet: &str,
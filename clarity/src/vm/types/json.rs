@@ -0,0 +1,389 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Canonical JSON representation of Clarity values.
+//!
+//! | Clarity type        | JSON                                               |
+//! |---------------------|----------------------------------------------------|
+//! | `int`, `uint`       | decimal string: `"-5"`, `"5"`                      |
+//! | `bool`              | `true`, `false`                                    |
+//! | `principal`         | string: `"ST1PQ...GZGM"`, `"ST1PQ...GZGM.token"`   |
+//! | `(buff n)`          | `0x`-prefixed hex string: `"0x0102"`               |
+//! | `(string-ascii n)`  | string                                             |
+//! | `(string-utf8 n)`   | string                                             |
+//! | `(optional t)`      | `null`, or the JSON of the contained value         |
+//! | `(response ok err)` | `{"ok": ...}` or `{"err": ...}`                    |
+//! | `(list n t)`        | array                                              |
+//! | `(tuple ...)`       | object with one member per field                   |
+//!
+//! Integers are strings because JSON numbers cannot represent 128-bit values losslessly.
+//! When an optional directly contains another optional, `(some x)` is `{"some": ...}`,
+//! so that `none` and `(some none)` remain distinct.
+//!
+//! The representation is not self-describing, so decoding is driven by the expected
+//! `TypeSignature`, and enforces its bounds. Trait references decode to the principal
+//! of the contract passed for them.
+
+use serde_json::{json, Map};
+use stacks_common::util::hash::hex_bytes;
+
+use crate::vm::types::serialization::SerializationError;
+use crate::vm::types::signatures::{CallableSubtype, SequenceSubtype, StringSubtype};
+use crate::vm::types::{
+    ASCIIData, BuffData, CharType, ListData, OptionalData, PrincipalData, ResponseData,
+    SequenceData, TupleData, TypeSignature, UTF8Data, Value,
+};
+
+impl Value {
+    /// Encode this value in the canonical JSON representation
+    pub fn serialize_to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Int(value) => json!(value.to_string()),
+            Value::UInt(value) => json!(value.to_string()),
+            Value::Bool(value) => json!(value),
+            Value::Principal(principal) => json!(principal.to_string()),
+            Value::CallableContract(callable) => {
+                json!(callable.contract_identifier.to_string())
+            }
+            Value::Sequence(SequenceData::Buffer(buff)) => json!(format!("0x{}", buff)),
+            Value::Sequence(SequenceData::String(CharType::ASCII(ASCIIData { data }))) => {
+                json!(String::from_utf8_lossy(data))
+            }
+            Value::Sequence(SequenceData::String(CharType::UTF8(UTF8Data { data }))) => {
+                let bytes: Vec<u8> = data.iter().flatten().copied().collect();
+                json!(String::from_utf8_lossy(&bytes))
+            }
+            Value::Sequence(SequenceData::List(ListData { data, .. })) => {
+                serde_json::Value::Array(data.iter().map(Value::serialize_to_json).collect())
+            }
+            Value::Optional(OptionalData { data: None }) => serde_json::Value::Null,
+            Value::Optional(OptionalData { data: Some(inner) }) => match inner.as_ref() {
+                Value::Optional(_) => json!({ "some": inner.serialize_to_json() }),
+                inner => inner.serialize_to_json(),
+            },
+            Value::Response(ResponseData { committed, data }) => {
+                let key = if *committed { "ok" } else { "err" };
+                json!({ key: data.serialize_to_json() })
+            }
+            Value::Tuple(TupleData { data_map, .. }) => serde_json::Value::Object(
+                data_map
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.serialize_to_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Decode a value of type `expected_type` from its canonical JSON representation
+    pub fn try_deserialize_json(
+        json: &serde_json::Value,
+        expected_type: &TypeSignature,
+    ) -> Result<Value, SerializationError> {
+        let mismatch = || SerializationError::DeserializeExpected(expected_type.clone());
+        match expected_type {
+            TypeSignature::IntType => json
+                .as_str()
+                .filter(|s| !s.starts_with('+'))
+                .and_then(|s| s.parse::<i128>().ok())
+                .map(Value::Int)
+                .ok_or_else(mismatch),
+            TypeSignature::UIntType => json
+                .as_str()
+                .filter(|s| !s.starts_with('+'))
+                .and_then(|s| s.parse::<u128>().ok())
+                .map(Value::UInt)
+                .ok_or_else(mismatch),
+            TypeSignature::BoolType => json.as_bool().map(Value::Bool).ok_or_else(mismatch),
+            TypeSignature::PrincipalType => {
+                let principal = json.as_str().ok_or_else(mismatch)?;
+                PrincipalData::parse(principal)
+                    .map(Value::Principal)
+                    .map_err(|_| {
+                        SerializationError::DeserializationError(format!(
+                            "Invalid principal: {}",
+                            principal
+                        ))
+                    })
+            }
+            TypeSignature::CallableType(CallableSubtype::Principal(contract_identifier)) => {
+                let value = Value::try_deserialize_json(json, &TypeSignature::PrincipalType)?;
+                match &value {
+                    Value::Principal(PrincipalData::Contract(contract))
+                        if contract == contract_identifier =>
+                    {
+                        Ok(value)
+                    }
+                    _ => Err(mismatch()),
+                }
+            }
+            TypeSignature::CallableType(CallableSubtype::Trait(_))
+            | TypeSignature::TraitReferenceType(_)
+            | TypeSignature::ListUnionType(_) => {
+                let value = Value::try_deserialize_json(json, &TypeSignature::PrincipalType)?;
+                match value {
+                    Value::Principal(PrincipalData::Contract(_)) => Ok(value),
+                    _ => Err(mismatch()),
+                }
+            }
+            TypeSignature::SequenceType(SequenceSubtype::BufferType(max_len)) => {
+                let hex = json
+                    .as_str()
+                    .and_then(|s| s.strip_prefix("0x"))
+                    .ok_or_else(mismatch)?;
+                let data = hex_bytes(hex).map_err(|_| {
+                    SerializationError::DeserializationError(format!(
+                        "Invalid hex string: 0x{}",
+                        hex
+                    ))
+                })?;
+                if data.len() > u32::from(max_len) as usize {
+                    return Err(mismatch());
+                }
+                Ok(Value::Sequence(SequenceData::Buffer(BuffData { data })))
+            }
+            TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(
+                max_len,
+            ))) => {
+                let string = json.as_str().ok_or_else(mismatch)?;
+                if string.len() > u32::from(max_len) as usize {
+                    return Err(mismatch());
+                }
+                Value::string_ascii_from_bytes(string.as_bytes().to_vec()).map_err(|_| {
+                    SerializationError::DeserializationError(format!(
+                        "Invalid ASCII string: {}",
+                        string
+                    ))
+                })
+            }
+            TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(
+                max_len,
+            ))) => {
+                let string = json.as_str().ok_or_else(mismatch)?;
+                if string.chars().count() > u32::from(max_len) as usize {
+                    return Err(mismatch());
+                }
+                Value::string_utf8_from_bytes(string.as_bytes().to_vec()).map_err(|_| {
+                    SerializationError::DeserializationError(format!(
+                        "Invalid UTF-8 string: {}",
+                        string
+                    ))
+                })
+            }
+            TypeSignature::SequenceType(SequenceSubtype::ListType(list_type)) => {
+                let items = json.as_array().ok_or_else(mismatch)?;
+                if items.len() > list_type.get_max_len() as usize {
+                    return Err(mismatch());
+                }
+                let items = items
+                    .iter()
+                    .map(|item| Value::try_deserialize_json(item, list_type.get_list_item_type()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Value::cons_list_unsanitized(items).map_err(|_| "Illegal list type".into())
+            }
+            TypeSignature::OptionalType(inner_type) => {
+                if json.is_null() {
+                    return Ok(Value::none());
+                }
+                let inner = if let TypeSignature::OptionalType(_) = inner_type.as_ref() {
+                    single_member(json, &["some"])
+                        .map(|(_, inner)| inner)
+                        .ok_or_else(mismatch)?
+                } else {
+                    json
+                };
+                let inner = Value::try_deserialize_json(inner, inner_type)?;
+                Value::some(inner).map_err(|_| "Illegal optional type".into())
+            }
+            TypeSignature::ResponseType(inner_types) => {
+                let (key, inner) = single_member(json, &["ok", "err"]).ok_or_else(mismatch)?;
+                if key == "ok" {
+                    let inner = Value::try_deserialize_json(inner, &inner_types.0)?;
+                    Value::okay(inner).map_err(|_| "Illegal response type".into())
+                } else {
+                    let inner = Value::try_deserialize_json(inner, &inner_types.1)?;
+                    Value::error(inner).map_err(|_| "Illegal response type".into())
+                }
+            }
+            TypeSignature::TupleType(tuple_type) => {
+                let members = json.as_object().ok_or_else(mismatch)?;
+                if members.len() != tuple_type.len() as usize {
+                    return Err(mismatch());
+                }
+                let fields = tuple_type
+                    .get_type_map()
+                    .iter()
+                    .map(|(name, field_type)| {
+                        let member = members.get(name.as_str()).ok_or_else(mismatch)?;
+                        let value = Value::try_deserialize_json(member, field_type)?;
+                        Ok((name.clone(), value))
+                    })
+                    .collect::<Result<Vec<_>, SerializationError>>()?;
+                TupleData::from_data(fields)
+                    .map(Value::from)
+                    .map_err(|_| "Illegal tuple type".into())
+            }
+            TypeSignature::NoType => Err(mismatch()),
+        }
+    }
+}
+
+/// The key and value of `json` if it is an object with a single member named one of `keys`
+fn single_member<'a>(
+    json: &'a serde_json::Value,
+    keys: &[&'static str],
+) -> Option<(&'static str, &'a serde_json::Value)> {
+    let members: &Map<String, serde_json::Value> = json.as_object()?;
+    if members.len() != 1 {
+        return None;
+    }
+    keys.iter()
+        .find_map(|key| members.get(*key).map(|value| (*key, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use stacks_common::types::StacksEpochId;
+
+    use super::*;
+    use crate::vm::{execute_v2, ClarityVersion};
+
+    fn type_of(repr: &str) -> TypeSignature {
+        TypeSignature::from_string(repr, ClarityVersion::Clarity2, StacksEpochId::latest())
+    }
+
+    fn eval(program: &str) -> Value {
+        execute_v2(program).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            (
+                "int",
+                "-170141183460469231731687303715884105728",
+                json!("-170141183460469231731687303715884105728"),
+            ),
+            (
+                "uint",
+                "u340282366920938463463374607431768211455",
+                json!("340282366920938463463374607431768211455"),
+            ),
+            ("bool", "true", json!(true)),
+            (
+                "principal",
+                "'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM",
+                json!("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM"),
+            ),
+            (
+                "principal",
+                "'ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.token",
+                json!("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.token"),
+            ),
+            ("(buff 4)", "0x00ff", json!("0x00ff")),
+            (
+                "(string-ascii 16)",
+                "\"hello \\\"world\\\"\"",
+                json!("hello \"world\""),
+            ),
+            (
+                "(string-utf8 16)",
+                "u\"caf\\u{e9} \\u{1F30E}\"",
+                json!("caf\u{e9} \u{1F30E}"),
+            ),
+            ("(optional uint)", "none", json!(null)),
+            ("(optional uint)", "(some u1)", json!("1")),
+            (
+                "(optional (optional uint))",
+                "(some none)",
+                json!({ "some": null }),
+            ),
+            (
+                "(optional (optional uint))",
+                "(some (some u1))",
+                json!({ "some": "1" }),
+            ),
+            ("(response uint int)", "(ok u1)", json!({ "ok": "1" })),
+            ("(response uint int)", "(err -1)", json!({ "err": "-1" })),
+            ("(list 3 int)", "(list)", json!([])),
+            ("(list 3 int)", "(list 1 2 3)", json!(["1", "2", "3"])),
+            (
+                "(tuple (a (list 2 (optional bool))) (b (response (buff 1) uint)))",
+                "{ b: (ok 0x01), a: (list none (some false)) }",
+                json!({ "a": [null, false], "b": { "ok": "0x01" } }),
+            ),
+        ];
+        for (type_repr, program, expected) in cases {
+            let value = eval(program);
+            let json = value.serialize_to_json();
+            assert_eq!(json, expected, "encoding {}", program);
+            let decoded = Value::try_deserialize_json(&json, &type_of(type_repr)).unwrap();
+            assert_eq!(decoded, value, "decoding {}", json);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_checks_type() {
+        let rejected = [
+            ("int", json!(1)),
+            ("int", json!("+1")),
+            ("uint", json!("-1")),
+            ("uint", json!("0x01")),
+            ("bool", json!("true")),
+            ("principal", json!("not-a-principal")),
+            ("(buff 2)", json!("0x010203")),
+            ("(buff 2)", json!("0102")),
+            ("(buff 2)", json!("0x0")),
+            ("(string-ascii 4)", json!("hello")),
+            ("(string-ascii 8)", json!("caf\u{e9}")),
+            ("(string-utf8 4)", json!("hello")),
+            ("(optional (optional uint))", json!("1")),
+            ("(response uint uint)", json!({ "ok": "1", "err": "1" })),
+            ("(response uint uint)", json!({ "some": "1" })),
+            ("(list 2 uint)", json!(["1", "2", "3"])),
+            ("(list 2 uint)", json!(["1", 2])),
+            ("(tuple (a uint))", json!({})),
+            ("(tuple (a uint))", json!({ "a": "1", "b": "2" })),
+            ("(tuple (a uint))", json!({ "b": "1" })),
+        ];
+        for (type_repr, json) in rejected {
+            assert!(
+                Value::try_deserialize_json(&json, &type_of(type_repr)).is_err(),
+                "{} should not decode as {}",
+                json,
+                type_repr
+            );
+        }
+
+        // principals of contracts are accepted for trait references
+        let callable = TypeSignature::CallableType(CallableSubtype::Trait(
+            crate::vm::types::TraitIdentifier::parse_fully_qualified(
+                "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.traits.token",
+            )
+            .unwrap(),
+        ));
+        assert!(Value::try_deserialize_json(
+            &json!("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.token"),
+            &callable
+        )
+        .is_ok());
+        assert!(Value::try_deserialize_json(
+            &json!("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM"),
+            &callable
+        )
+        .is_err());
+    }
+}
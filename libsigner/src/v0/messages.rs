@@ -55,7 +55,10 @@ use tiny_http::{
 };
 
 use crate::http::{decode_http_body, decode_http_request};
-use crate::{BlockProposal, EventError};
+use crate::{
+    BlockProposal, EventError, MessageSlotID as MessageSlotIDTrait,
+    SignerMessage as SignerMessageTrait,
+};
 
 define_u8_enum!(
 /// Enum representing the stackerdb message identifier: this is
@@ -94,6 +97,21 @@ impl MessageSlotID {
 }
 
 #[cfg_attr(test, mutants::skip)]
+impl MessageSlotIDTrait for MessageSlotID {
+    fn stacker_db_contract(&self, mainnet: bool, reward_cycle: u64) -> QualifiedContractIdentifier {
+        MessageSlotID::stacker_db_contract(self, mainnet, reward_cycle)
+    }
+    fn all() -> &'static [Self] {
+        MessageSlotID::ALL
+    }
+}
+
+impl SignerMessageTrait<MessageSlotID> for SignerMessage {
+    fn msg_id(&self) -> MessageSlotID {
+        self.msg_id()
+    }
+}
+
 impl Display for MessageSlotID {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}({})", self, self.to_u8())
@@ -102,8 +102,8 @@ impl<const MAX_SIZE: u16> Serialize for BitVec<MAX_SIZE> {
 
 impl<'de, const MAX_SIZE: u16> Deserialize<'de> for BitVec<MAX_SIZE> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let hex: &str = Deserialize::deserialize(deserializer)?;
-        let bytes = hex_bytes(hex).map_err(serde::de::Error::custom)?;
+        let hex: String = Deserialize::deserialize(deserializer)?;
+        let bytes = hex_bytes(&hex).map_err(serde::de::Error::custom)?;
         Self::consensus_deserialize(&mut bytes.as_slice()).map_err(serde::de::Error::custom)
     }
 }
@@ -256,6 +256,11 @@ mod test {
         let byte_ser = input.serialize_to_vec();
         let deserialized = BitVec::consensus_deserialize(&mut byte_ser.as_slice()).unwrap();
         assert_eq!(input, &deserialized);
+
+        // must also decode from an owned JSON value, not just a borrowed string
+        let json_value = serde_json::to_value(input).unwrap();
+        let deserialized: BitVec<{ u16::MAX }> = serde_json::from_value(json_value).unwrap();
+        assert_eq!(input, &deserialized);
     }
 
     fn check_ok_vector(input: &[bool]) {
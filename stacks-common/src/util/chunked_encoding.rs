@@ -391,6 +391,14 @@ impl<'a, 'state, W: Write> HttpChunkedTransferWriter<'a, 'state, W> {
         to_copy
     }
 
+    /// Send out any buffered data as a chunk, without ending the stream
+    pub fn send_buffered_chunk(&mut self) -> io::Result<()> {
+        if !self.state.corked && !self.state.chunk_buf.is_empty() {
+            self.flush_chunk()?;
+        }
+        Ok(())
+    }
+
     pub fn cork(&mut self) {
         // block future flushes from sending trailing empty chunks -- we're done sending
         self.state.corked = true;
@@ -121,6 +121,9 @@ pub struct RunSignerArgs {
     /// Path to config file
     #[arg(long, short, value_name = "FILE")]
     pub config: PathBuf,
+    /// Run the v0 signer, which signs blocks with the stacker key instead of WSTS
+    #[arg(long)]
+    pub v0: bool,
 }
 
 #[derive(Clone, Debug)]
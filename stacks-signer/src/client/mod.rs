@@ -430,6 +430,8 @@ pub(crate) mod tests {
                     fee: thread_rng().next_u64(),
                 },
             ],
+            estimations_within_target: vec![],
+            target_blocks: 0,
         };
         let fee_response_json = serde_json::to_string(&fee_response)
             .expect("Failed to serialize fee estimate response");
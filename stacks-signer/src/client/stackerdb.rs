@@ -17,11 +17,11 @@
 use blockstack_lib::chainstate::stacks::StacksTransaction;
 use blockstack_lib::net::api::poststackerdbchunk::StackerDBErrorCodes;
 use hashbrown::HashMap;
-use libsigner::v1::messages::{MessageSlotID, SignerMessage};
-use libsigner::{SignerSession, StackerDBSession};
+use libsigner::v1::messages::{MessageSlotID as MessageSlotIDV1, SignerMessage as SignerMessageV1};
+use libsigner::{MessageSlotID, SignerMessage, SignerSession, StackerDBSession};
 use libstackerdb::{StackerDBChunkAckData, StackerDBChunkData};
 use slog::{slog_debug, slog_error, slog_warn};
-use stacks_common::codec::{read_next, StacksMessageCodec};
+use stacks_common::codec::read_next;
 use stacks_common::types::chainstate::StacksPrivateKey;
 use stacks_common::{debug, error, warn};
 use wsts::net::Packet;
@@ -42,14 +42,14 @@ impl std::fmt::Display for SignerSlotID {
 
 /// The StackerDB client for communicating with the .signers contract
 #[derive(Debug)]
-pub struct StackerDB {
+pub struct StackerDB<M: MessageSlotID> {
     /// The stacker-db sessions for each signer set and message type.
     /// Maps message ID to the DB session.
-    signers_message_stackerdb_sessions: HashMap<MessageSlotID, StackerDBSession>,
+    signers_message_stackerdb_sessions: HashMap<M, StackerDBSession>,
     /// The private key used in all stacks node communications
     stacks_private_key: StacksPrivateKey,
     /// A map of a message ID to last chunk version for each session
-    slot_versions: HashMap<MessageSlotID, HashMap<SignerSlotID, u32>>,
+    slot_versions: HashMap<M, HashMap<SignerSlotID, u32>>,
     /// The signer slot ID -- the index into the signer list for this signer daemon's signing key.
     signer_slot_id: SignerSlotID,
     /// The reward cycle of the connecting signer
@@ -58,7 +58,7 @@ pub struct StackerDB {
     next_transaction_session: StackerDBSession,
 }
 
-impl From<&SignerConfig> for StackerDB {
+impl<M: MessageSlotID + 'static> From<&SignerConfig> for StackerDB<M> {
     fn from(config: &SignerConfig) -> Self {
         Self::new(
             &config.node_host,
@@ -69,7 +69,7 @@ impl From<&SignerConfig> for StackerDB {
         )
     }
 }
-impl StackerDB {
+impl<M: MessageSlotID + 'static> StackerDB<M> {
     /// Create a new StackerDB client
     pub fn new(
         host: &str,
@@ -79,7 +79,7 @@ impl StackerDB {
         signer_slot_id: SignerSlotID,
     ) -> Self {
         let mut signers_message_stackerdb_sessions = HashMap::new();
-        for msg_id in MessageSlotID::ALL {
+        for msg_id in M::all() {
             signers_message_stackerdb_sessions.insert(
                 *msg_id,
                 StackerDBSession::new(host, msg_id.stacker_db_contract(is_mainnet, reward_cycle)),
@@ -87,7 +87,7 @@ impl StackerDB {
         }
         let next_transaction_session = StackerDBSession::new(
             host,
-            MessageSlotID::Transactions
+            MessageSlotIDV1::Transactions
                 .stacker_db_contract(is_mainnet, reward_cycle.wrapping_add(1)),
         );
 
@@ -102,9 +102,9 @@ impl StackerDB {
     }
 
     /// Sends messages to the .signers stacker-db with an exponential backoff retry
-    pub fn send_message_with_retry(
+    pub fn send_message_with_retry<T: SignerMessage<M>>(
         &mut self,
-        message: SignerMessage,
+        message: T,
     ) -> Result<StackerDBChunkAckData, ClientError> {
         let msg_id = message.msg_id();
         let message_bytes = message.serialize_to_vec();
@@ -115,7 +115,7 @@ impl StackerDB {
     /// exponential backoff retry
     pub fn send_message_bytes_with_retry(
         &mut self,
-        msg_id: &MessageSlotID,
+        msg_id: &M,
         message_bytes: Vec<u8>,
     ) -> Result<StackerDBChunkAckData, ClientError> {
         let slot_id = self.signer_slot_id;
@@ -138,11 +138,11 @@ impl StackerDB {
             chunk.sign(&self.stacks_private_key)?;
 
             let Some(session) = self.signers_message_stackerdb_sessions.get_mut(msg_id) else {
-                panic!("FATAL: would loop forever trying to send a message with ID {}, for which we don't have a session", msg_id);
+                panic!("FATAL: would loop forever trying to send a message with ID {:?}, for which we don't have a session", msg_id);
             };
 
             debug!(
-                "Sending a chunk to stackerdb slot ID {slot_id} with version {slot_version} and message ID {msg_id} to contract {:?}!\n{chunk:?}",
+                "Sending a chunk to stackerdb slot ID {slot_id} with version {slot_version} and message ID {msg_id:?} to contract {:?}!\n{chunk:?}",
                 &session.stackerdb_contract_id
             );
 
@@ -192,10 +192,10 @@ impl StackerDB {
     }
 
     /// Get all signer messages from stackerdb for the given slot IDs
-    fn get_messages(
+    pub fn get_messages<T: SignerMessage<M>>(
         session: &mut StackerDBSession,
         slot_ids: &[u32],
-    ) -> Result<Vec<SignerMessage>, ClientError> {
+    ) -> Result<Vec<T>, ClientError> {
         let mut messages = vec![];
         let send_request = || {
             session
@@ -207,7 +207,7 @@ impl StackerDB {
             let Some(data) = chunk else {
                 continue;
             };
-            let Ok(message) = read_next::<SignerMessage, _>(&mut &data[..]) else {
+            let Ok(message) = read_next::<T, _>(&mut &data[..]) else {
                 if !data.is_empty() {
                     warn!("Failed to deserialize chunk data into a SignerMessage");
                     debug!("slot #{i}: Failed chunk ({}): {data:?}", &data.len(),);
@@ -219,18 +219,30 @@ impl StackerDB {
         Ok(messages)
     }
 
+    /// Retrieve the signer set this stackerdb client is attached to
+    pub fn get_signer_set(&self) -> u32 {
+        u32::try_from(self.reward_cycle % 2).expect("FATAL: reward cycle % 2 exceeds u32::MAX")
+    }
+
+    /// Retrieve the signer slot ID
+    pub fn get_signer_slot_id(&mut self) -> SignerSlotID {
+        self.signer_slot_id
+    }
+}
+
+impl StackerDB<MessageSlotIDV1> {
     /// Get the ordered DKG packets from stackerdb for the signer slot IDs.
     pub fn get_dkg_packets(
         &mut self,
         signer_ids: &[SignerSlotID],
     ) -> Result<Vec<Packet>, ClientError> {
         let packet_slots = &[
-            MessageSlotID::DkgBegin,
-            MessageSlotID::DkgPublicShares,
-            MessageSlotID::DkgPrivateBegin,
-            MessageSlotID::DkgPrivateShares,
-            MessageSlotID::DkgEndBegin,
-            MessageSlotID::DkgEnd,
+            MessageSlotIDV1::DkgBegin,
+            MessageSlotIDV1::DkgPublicShares,
+            MessageSlotIDV1::DkgPrivateBegin,
+            MessageSlotIDV1::DkgPrivateShares,
+            MessageSlotIDV1::DkgEndBegin,
+            MessageSlotIDV1::DkgEnd,
         ];
         let slot_ids = signer_ids.iter().map(|id| id.0).collect::<Vec<_>>();
         let mut packets = vec![];
@@ -241,7 +253,7 @@ impl StackerDB {
                 .ok_or(ClientError::NotConnected)?;
             let messages = Self::get_messages(session, &slot_ids)?;
             for message in messages {
-                let SignerMessage::Packet(packet) = message else {
+                let SignerMessageV1::Packet(packet) = message else {
                     warn!("Found an unexpected type in a packet slot {packet_slot}");
                     continue;
                 };
@@ -260,7 +272,7 @@ impl StackerDB {
         let messages = Self::get_messages(transactions_session, &slot_ids)?;
         let mut transactions = vec![];
         for message in messages {
-            let SignerMessage::Transactions(chunk_transactions) = message else {
+            let SignerMessageV1::Transactions(chunk_transactions) = message else {
                 warn!("Signer wrote an unexpected type to the transactions slot");
                 continue;
             };
@@ -273,7 +285,7 @@ impl StackerDB {
     pub fn get_current_transactions(&mut self) -> Result<Vec<StacksTransaction>, ClientError> {
         let Some(transactions_session) = self
             .signers_message_stackerdb_sessions
-            .get_mut(&MessageSlotID::Transactions)
+            .get_mut(&MessageSlotIDV1::Transactions)
         else {
             return Err(ClientError::NotConnected);
         };
@@ -297,7 +309,7 @@ impl StackerDB {
         debug!("Getting the persisted encrypted state for signer {signer_id}");
         let Some(state_session) = self
             .signers_message_stackerdb_sessions
-            .get_mut(&MessageSlotID::EncryptedSignerState)
+            .get_mut(&MessageSlotIDV1::EncryptedSignerState)
         else {
             return Err(ClientError::NotConnected);
         };
@@ -324,8 +336,8 @@ impl StackerDB {
             return Ok(None);
         }
 
-        let SignerMessage::EncryptedSignerState(state) =
-            read_next::<SignerMessage, _>(&mut chunk.as_slice())?
+        let SignerMessageV1::EncryptedSignerState(state) =
+            read_next::<SignerMessageV1, _>(&mut chunk.as_slice())?
         else {
             error!("Wrong message type stored in signer state slot for signer {signer_id}");
             return Ok(None);
@@ -333,16 +345,6 @@ impl StackerDB {
 
         Ok(Some(state))
     }
-
-    /// Retrieve the signer set this stackerdb client is attached to
-    pub fn get_signer_set(&self) -> u32 {
-        u32::try_from(self.reward_cycle % 2).expect("FATAL: reward cycle % 2 exceeds u32::MAX")
-    }
-
-    /// Retrieve the signer slot ID
-    pub fn get_signer_slot_id(&mut self) -> SignerSlotID {
-        self.signer_slot_id
-    }
 }
 
 #[cfg(test)]
@@ -355,6 +357,7 @@ mod tests {
         TransactionSmartContract, TransactionVersion,
     };
     use blockstack_lib::util_lib::strings::StacksString;
+    use stacks_common::codec::StacksMessageCodec;
 
     use super::*;
     use crate::client::tests::{generate_signer_config, mock_server_from_config, write_response};
@@ -364,7 +367,7 @@ mod tests {
     fn get_signer_transactions_should_succeed() {
         let config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
         let signer_config = generate_signer_config(&config, 5, 20);
-        let mut stackerdb = StackerDB::from(&signer_config);
+        let mut stackerdb = StackerDB::<MessageSlotIDV1>::from(&signer_config);
         let sk = StacksPrivateKey::new();
         let tx = StacksTransaction {
             version: TransactionVersion::Testnet,
@@ -382,7 +385,7 @@ mod tests {
             ),
         };
 
-        let signer_message = SignerMessage::Transactions(vec![tx.clone()]);
+        let signer_message = SignerMessageV1::Transactions(vec![tx.clone()]);
         let message = signer_message.serialize_to_vec();
 
         let signer_slot_ids = vec![SignerSlotID(0), SignerSlotID(1)];
@@ -392,7 +395,7 @@ mod tests {
         let mock_server = mock_server_from_config(&config);
         write_response(mock_server, response_bytes.as_slice());
 
-        let signer_message = SignerMessage::Transactions(vec![]);
+        let signer_message = SignerMessageV1::Transactions(vec![]);
         let message = signer_message.serialize_to_vec();
         let mut response_bytes = b"HTTP/1.1 200 OK\n\n".to_vec();
         response_bytes.extend(message);
@@ -407,7 +410,7 @@ mod tests {
     fn send_signer_message_should_succeed() {
         let config = GlobalConfig::load_from_file("./src/tests/conf/signer-1.toml").unwrap();
         let signer_config = generate_signer_config(&config, 5, 20);
-        let mut stackerdb = StackerDB::from(&signer_config);
+        let mut stackerdb = StackerDB::<MessageSlotIDV1>::from(&signer_config);
 
         let sk = StacksPrivateKey::new();
         let tx = StacksTransaction {
@@ -426,7 +429,7 @@ mod tests {
             ),
         };
 
-        let signer_message = SignerMessage::Transactions(vec![tx]);
+        let signer_message = SignerMessageV1::Transactions(vec![tx]);
         let ack = StackerDBChunkAckData {
             accepted: true,
             reason: None,
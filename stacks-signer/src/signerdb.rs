@@ -16,15 +16,64 @@
 
 use std::path::Path;
 
+use blockstack_lib::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockVote};
 use blockstack_lib::util_lib::db::{
     query_row, sqlite_open, table_exists, u64_to_sql, Error as DBError,
 };
+use libsigner::BlockProposal;
 use rusqlite::{params, Connection, Error as SqliteError, OpenFlags, NO_PARAMS};
+use serde_derive::{Deserialize, Serialize};
 use slog::slog_debug;
 use stacks_common::debug;
 use stacks_common::util::hash::Sha512Trunc256Sum;
+use wsts::net::NonceRequest;
+
+/// Additional Info about a proposed block
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlockInfo {
+    /// The block we are considering
+    pub block: NakamotoBlock,
+    /// The burn block height at which the block was proposed
+    pub burn_block_height: u64,
+    /// The reward cycle the block belongs to
+    pub reward_cycle: u64,
+    /// Our vote on the block if we have one yet
+    pub vote: Option<NakamotoBlockVote>,
+    /// Whether the block contents are valid
+    pub valid: Option<bool>,
+    /// The associated packet nonce request if we have one
+    pub nonce_request: Option<NonceRequest>,
+    /// Whether this block is already being signed over
+    pub signed_over: bool,
+}
+
+impl From<BlockProposal> for BlockInfo {
+    fn from(value: BlockProposal) -> Self {
+        Self {
+            block: value.block,
+            burn_block_height: value.burn_height,
+            reward_cycle: value.reward_cycle,
+            vote: None,
+            valid: None,
+            nonce_request: None,
+            signed_over: false,
+        }
+    }
+}
+impl BlockInfo {
+    /// Create a new BlockInfo with an associated nonce request packet
+    pub fn new_with_request(block_proposal: BlockProposal, nonce_request: NonceRequest) -> Self {
+        let mut block_info = BlockInfo::from(block_proposal);
+        block_info.nonce_request = Some(nonce_request);
+        block_info.signed_over = true;
+        block_info
+    }
 
-use crate::v1::signer::BlockInfo;
+    /// Return the block's signer signature hash
+    pub fn signer_signature_hash(&self) -> Sha512Trunc256Sum {
+        self.block.header.signer_signature_hash()
+    }
+}
 
 /// This struct manages a SQLite database connection
 /// for the signer.
@@ -166,6 +215,7 @@ where
 }
 
 #[cfg(test)]
+/// Create a test signer db at the given path, removing any existing db first
 pub fn test_signer_db(db_path: &str) -> SignerDb {
     use std::fs;
 
@@ -180,10 +230,7 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
-    use blockstack_lib::chainstate::nakamoto::{
-        NakamotoBlock, NakamotoBlockHeader, NakamotoBlockVote,
-    };
-    use libsigner::BlockProposal;
+    use blockstack_lib::chainstate::nakamoto::NakamotoBlockHeader;
 
     use super::*;
 
@@ -13,3 +13,13 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+/// The signer module for processing events
+pub mod signer;
+
+use libsigner::v0::messages::SignerMessage;
+
+use crate::v0::signer::Signer;
+
+/// A v0 spawned signer
+pub type SpawnedSigner = crate::SpawnedSigner<Signer, SignerMessage>;
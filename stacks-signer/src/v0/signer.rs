@@ -0,0 +1,476 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use std::fmt::Debug;
+use std::sync::mpsc::Sender;
+
+use blockstack_lib::chainstate::nakamoto::NakamotoBlockVote;
+use blockstack_lib::net::api::postblock_proposal::BlockValidateResponse;
+use libsigner::v0::messages::{BlockResponse, MessageSlotID, RejectCode, SignerMessage};
+use libsigner::{BlockProposal, SignerEvent};
+use slog::{slog_debug, slog_error, slog_info, slog_warn};
+use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
+use stacks_common::types::PrivateKey;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+use stacks_common::util::secp256k1::MessageSignature;
+use stacks_common::{debug, error, info, warn};
+use wsts::state_machine::OperationResult;
+
+use crate::client::{SignerSlotID, StackerDB, StacksClient};
+use crate::config::SignerConfig;
+use crate::runloop::RunLoopCommand;
+use crate::signerdb::{BlockInfo, SignerDb};
+use crate::Signer as SignerTrait;
+
+/// The stacks signer registered for the reward cycle
+#[derive(Debug)]
+pub struct Signer {
+    /// The private key of the signer
+    private_key: StacksPrivateKey,
+    /// The stackerdb client
+    pub stackerdb: StackerDB<MessageSlotID>,
+    /// Whether the signer is a mainnet signer or not
+    pub mainnet: bool,
+    /// The signer id
+    pub signer_id: u32,
+    /// The signer slot ids for the signers in the reward cycle
+    pub signer_slot_ids: Vec<SignerSlotID>,
+    /// The addresses of other signers
+    pub signer_addresses: Vec<StacksAddress>,
+    /// The reward cycle this signer belongs to
+    pub reward_cycle: u64,
+    /// SignerDB for state management
+    pub signer_db: SignerDb,
+}
+
+impl std::fmt::Display for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cycle #{} Signer #{}", self.reward_cycle, self.signer_id)
+    }
+}
+
+impl SignerTrait<SignerMessage> for Signer {
+    /// Create a new signer from the given configuration
+    fn new(config: SignerConfig) -> Self {
+        Self::from(config)
+    }
+
+    /// The v0 signer does not need to know about the next reward cycle's signers
+    fn update_next_signer_data(&mut self, _next_signer_config: &SignerConfig) {}
+
+    /// Return the reward cycle of the signer
+    fn reward_cycle(&self) -> u64 {
+        self.reward_cycle
+    }
+
+    /// Process the event
+    fn process_event(
+        &mut self,
+        stacks_client: &StacksClient,
+        event: Option<&SignerEvent<SignerMessage>>,
+        _res: Sender<Vec<OperationResult>>,
+        current_reward_cycle: u64,
+    ) {
+        let event_parity = match event {
+            // Block proposal events do have reward cycles, but each proposal has its own cycle,
+            //  and the vec could be heterogenous, so, don't differentiate.
+            Some(SignerEvent::BlockValidationResponse(_))
+            | Some(SignerEvent::MinerMessages(..))
+            | Some(SignerEvent::NewBurnBlock(_))
+            | Some(SignerEvent::StatusCheck)
+            | None => None,
+            Some(SignerEvent::SignerMessages(msg_parity, ..)) => Some(u64::from(*msg_parity) % 2),
+        };
+        let other_signer_parity = (self.reward_cycle + 1) % 2;
+        if event_parity == Some(other_signer_parity) {
+            return;
+        }
+        debug!("{self}: Processing event: {event:?}");
+        match event {
+            Some(SignerEvent::BlockValidationResponse(block_validate_response)) => {
+                debug!("{self}: Received a block proposal result from the stacks node...");
+                self.handle_block_validate_response(block_validate_response)
+            }
+            Some(SignerEvent::SignerMessages(_signer_set, messages)) => {
+                debug!(
+                    "{self}: Received {} messages from the other signers. Ignoring...",
+                    messages.len()
+                );
+            }
+            Some(SignerEvent::MinerMessages(messages, miner_pubkey)) => {
+                if current_reward_cycle != self.reward_cycle {
+                    // There is not point in processing blocks if we are not the current reward cycle (we can never actually contribute to signing these blocks)
+                    debug!("{self}: Received a proposed block, but this signer's reward cycle is not the current one ({current_reward_cycle}). Ignoring...");
+                    return;
+                }
+                debug!(
+                    "{self}: Received {} messages from the miner",
+                    messages.len();
+                    "miner_pubkey" => ?miner_pubkey,
+                );
+                for message in messages {
+                    if let SignerMessage::BlockProposal(block_proposal) = message {
+                        self.handle_block_proposal(stacks_client, block_proposal, miner_pubkey);
+                    }
+                }
+            }
+            Some(SignerEvent::StatusCheck) => {
+                debug!("{self}: Received a status check event.")
+            }
+            Some(SignerEvent::NewBurnBlock(height)) => {
+                debug!("{self}: Receved a new burn block event for block height {height}")
+            }
+            None => {
+                // No event. Do nothing.
+                debug!("{self}: No event received")
+            }
+        }
+    }
+
+    fn process_command(
+        &mut self,
+        _stacks_client: &StacksClient,
+        _current_reward_cycle: u64,
+        command: Option<RunLoopCommand>,
+    ) {
+        if let Some(command) = command {
+            warn!("{self}: Received a command: {command:?}. V0 Signers do not support commands. Ignoring...")
+        }
+    }
+}
+
+impl From<SignerConfig> for Signer {
+    fn from(signer_config: SignerConfig) -> Self {
+        let stackerdb = StackerDB::from(&signer_config);
+        debug!(
+            "Reward cycle #{} Signer #{}",
+            signer_config.reward_cycle, signer_config.signer_id,
+        );
+        let signer_db =
+            SignerDb::new(&signer_config.db_path).expect("Failed to connect to signer Db");
+
+        Self {
+            private_key: signer_config.stacks_private_key,
+            stackerdb,
+            mainnet: signer_config.mainnet,
+            signer_id: signer_config.signer_id,
+            signer_addresses: signer_config
+                .signer_entries
+                .signer_ids
+                .into_keys()
+                .collect(),
+            signer_slot_ids: signer_config.signer_slot_ids.clone(),
+            reward_cycle: signer_config.reward_cycle,
+            signer_db,
+        }
+    }
+}
+
+impl Signer {
+    /// Handle block proposal messages submitted to signers stackerdb
+    fn handle_block_proposal(
+        &mut self,
+        stacks_client: &StacksClient,
+        block_proposal: &BlockProposal,
+        miner_pubkey: &StacksPublicKey,
+    ) {
+        debug!("{self}: Received a block proposal: {block_proposal:?}");
+        if block_proposal.reward_cycle != self.reward_cycle {
+            // We are not signing for this reward cycle. Ignore the block.
+            debug!(
+                "{self}: Received a block proposal for a different reward cycle. Ignore it.";
+                "requested_reward_cycle" => block_proposal.reward_cycle
+            );
+            return;
+        }
+        // TODO: should add a check to ignore an old burn block height if we know its oudated. Would require us to store the burn block height we last saw on the side.
+        let signer_signature_hash = block_proposal.block.header.signer_signature_hash();
+        match self
+            .signer_db
+            .block_lookup(self.reward_cycle, &signer_signature_hash)
+        {
+            Ok(Some(block_info)) => {
+                debug!(
+                    "{self}: Received a block proposal for a block we have already seen. Ignore it.";
+                    "signer_sighash" => %signer_signature_hash,
+                    "valid" => ?block_info.valid,
+                );
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("{self}: Failed to lookup block in signer db: {e:?}");
+                return;
+            }
+        }
+        crate::monitoring::increment_block_proposals_received();
+        info!(
+            "{self}: received a block proposal for a new block. Submit block for validation. ";
+            "signer_sighash" => %signer_signature_hash,
+            "block_id" => %block_proposal.block.block_id(),
+            "burn_height" => block_proposal.burn_height,
+            "miner_pubkey" => ?miner_pubkey,
+        );
+        let mut block_info = BlockInfo::from(block_proposal.clone());
+        if let Err(e) = stacks_client.submit_block_for_validation(block_info.block.clone()) {
+            warn!("{self}: Failed to submit block for validation: {e:?}");
+            // We cannot determine the validity of the block. Reject it so the miner knows to try again.
+            block_info.valid = Some(false);
+            block_info.vote = Some(NakamotoBlockVote {
+                signer_signature_hash,
+                rejected: true,
+            });
+            self.send_block_response(BlockResponse::rejected(
+                signer_signature_hash,
+                RejectCode::ConnectivityIssues,
+            ));
+        }
+        self.signer_db
+            .insert_block(&block_info)
+            .unwrap_or_else(|_| panic!("{self}: Failed to insert block in DB"));
+    }
+
+    /// Handle the block validate response returned from our prior calls to submit a block for validation
+    fn handle_block_validate_response(&mut self, block_validate_response: &BlockValidateResponse) {
+        let (signer_signature_hash, is_valid) = match block_validate_response {
+            BlockValidateResponse::Ok(block_validate_ok) => {
+                (block_validate_ok.signer_signature_hash, true)
+            }
+            BlockValidateResponse::Reject(block_validate_reject) => {
+                (block_validate_reject.signer_signature_hash, false)
+            }
+        };
+        let mut block_info = match self
+            .signer_db
+            .block_lookup(self.reward_cycle, &signer_signature_hash)
+        {
+            Ok(Some(block_info)) => block_info,
+            Ok(None) => {
+                // We have not seen this block before. Why are we getting a response for it?
+                debug!("{self}: Received a block validate response for a block we have not seen before. Ignoring...");
+                return;
+            }
+            Err(e) => {
+                error!("{self}: Failed to lookup block in signer db: {e:?}");
+                return;
+            }
+        };
+        if block_info.vote.is_some() {
+            debug!(
+                "{self}: Received a block validate response for a block we have already voted on. Ignoring...";
+                "signer_sighash" => %signer_signature_hash,
+            );
+            return;
+        }
+        crate::monitoring::increment_block_validation_responses(is_valid);
+        let block_response = match block_validate_response {
+            BlockValidateResponse::Ok(_) => {
+                let Some(signature) = self.sign_block(&signer_signature_hash) else {
+                    return;
+                };
+                block_info.signed_over = true;
+                BlockResponse::accepted(signer_signature_hash, signature)
+            }
+            BlockValidateResponse::Reject(block_validate_reject) => {
+                BlockResponse::Rejected(block_validate_reject.clone().into())
+            }
+        };
+        block_info.valid = Some(is_valid);
+        block_info.vote = Some(NakamotoBlockVote {
+            signer_signature_hash,
+            rejected: !is_valid,
+        });
+        info!(
+            "{self}: Broadcasting a block response to the miners";
+            "signer_sighash" => %signer_signature_hash,
+            "block_id" => %block_info.block.block_id(),
+            "valid" => is_valid,
+        );
+        self.send_block_response(block_response);
+        self.signer_db
+            .insert_block(&block_info)
+            .unwrap_or_else(|_| panic!("{self}: Failed to insert block in DB"));
+    }
+
+    /// Sign the provided block signer signature hash with the signer's stacks private key
+    fn sign_block(&self, signer_signature_hash: &Sha512Trunc256Sum) -> Option<MessageSignature> {
+        self.private_key
+            .sign(signer_signature_hash.as_bytes())
+            .map_err(|e| {
+                error!("{self}: Failed to sign block: {e}";
+                    "signer_sighash" => %signer_signature_hash,
+                );
+            })
+            .ok()
+    }
+
+    /// Send a block response to the miners through the signers stackerdb
+    fn send_block_response(&mut self, block_response: BlockResponse) {
+        let accepted = matches!(block_response, BlockResponse::Accepted(_));
+        match self
+            .stackerdb
+            .send_message_with_retry::<SignerMessage>(block_response.into())
+        {
+            Ok(ack) => {
+                crate::monitoring::increment_block_responses_sent(accepted);
+                debug!("{self}: Block response ACK: {ack:?}");
+            }
+            Err(e) => {
+                warn!("{self}: Failed to send block response to stacker-db: {e:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::spawn;
+
+    use blockstack_lib::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
+    use blockstack_lib::net::api::postblock_proposal::{
+        BlockValidateOk, BlockValidateReject, ValidateRejectCode,
+    };
+    use clarity::vm::costs::ExecutionCost;
+    use libstackerdb::StackerDBChunkAckData;
+
+    use super::*;
+    use crate::client::tests::{generate_signer_config, mock_server_random, write_response};
+    use crate::config::GlobalConfig;
+
+    fn signer_with_proposal() -> (Signer, std::net::TcpListener, Sha512Trunc256Sum) {
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        let (mock_server, mock_server_addr) = mock_server_random();
+        config.node_host = mock_server_addr.to_string();
+        let signer_config = generate_signer_config(&config, 5, 20);
+        let mut signer = Signer::from(signer_config);
+        // The signer db stores reward cycles as i64, so keep the random cycle in range
+        signer.reward_cycle = 1;
+        let block_proposal = BlockProposal {
+            block: NakamotoBlock {
+                header: NakamotoBlockHeader::empty(),
+                txs: vec![],
+            },
+            burn_height: 7,
+            reward_cycle: signer.reward_cycle,
+        };
+        let block_info = BlockInfo::from(block_proposal);
+        let signer_signature_hash = block_info.signer_signature_hash();
+        signer
+            .signer_db
+            .insert_block(&block_info)
+            .expect("Unable to insert block into db");
+        (signer, mock_server, signer_signature_hash)
+    }
+
+    fn ack_response() -> Vec<u8> {
+        let ack = StackerDBChunkAckData {
+            accepted: true,
+            reason: None,
+            metadata: None,
+            code: None,
+        };
+        let mut response_bytes = b"HTTP/1.1 200 OK\n\n".to_vec();
+        let payload = serde_json::to_string(&ack).expect("Failed to serialize ack");
+        response_bytes.extend(payload.as_bytes());
+        response_bytes
+    }
+
+    #[test]
+    fn valid_block_is_signed_and_recorded() {
+        let (mut signer, mock_server, signer_signature_hash) = signer_with_proposal();
+        let response = BlockValidateResponse::Ok(BlockValidateOk {
+            signer_signature_hash,
+            cost: ExecutionCost::zero(),
+            size: 0,
+        });
+        let h = spawn(move || {
+            signer.handle_block_validate_response(&response);
+            signer
+        });
+        write_response(mock_server, ack_response().as_slice());
+        let signer = h.join().unwrap();
+
+        let block_info = signer
+            .signer_db
+            .block_lookup(signer.reward_cycle, &signer_signature_hash)
+            .unwrap()
+            .expect("Unable to get block from db");
+        assert_eq!(block_info.valid, Some(true));
+        assert!(block_info.signed_over);
+        assert_eq!(
+            block_info.vote,
+            Some(NakamotoBlockVote {
+                signer_signature_hash,
+                rejected: false
+            })
+        );
+
+        let signature = signer.sign_block(&signer_signature_hash).unwrap();
+        let public_key =
+            StacksPublicKey::recover_to_pubkey(signer_signature_hash.as_bytes(), &signature)
+                .unwrap();
+        assert_eq!(
+            public_key,
+            StacksPublicKey::from_private(&signer.private_key)
+        );
+    }
+
+    #[test]
+    fn invalid_block_is_rejected_and_recorded() {
+        let (mut signer, mock_server, signer_signature_hash) = signer_with_proposal();
+        let response = BlockValidateResponse::Reject(BlockValidateReject {
+            signer_signature_hash,
+            reason: "Invalid block".into(),
+            reason_code: ValidateRejectCode::InvalidBlock,
+        });
+        let h = spawn(move || {
+            signer.handle_block_validate_response(&response);
+            signer
+        });
+        write_response(mock_server, ack_response().as_slice());
+        let signer = h.join().unwrap();
+
+        let block_info = signer
+            .signer_db
+            .block_lookup(signer.reward_cycle, &signer_signature_hash)
+            .unwrap()
+            .expect("Unable to get block from db");
+        assert_eq!(block_info.valid, Some(false));
+        assert!(!block_info.signed_over);
+        assert_eq!(
+            block_info.vote,
+            Some(NakamotoBlockVote {
+                signer_signature_hash,
+                rejected: true
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_block_validation_is_ignored() {
+        let (mut signer, _mock_server, _) = signer_with_proposal();
+        let signer_signature_hash = Sha512Trunc256Sum([0x01; 32]);
+        let response = BlockValidateResponse::Ok(BlockValidateOk {
+            signer_signature_hash,
+            cost: ExecutionCost::zero(),
+            size: 0,
+        });
+        signer.handle_block_validate_response(&response);
+        assert!(signer
+            .signer_db
+            .block_lookup(signer.reward_cycle, &signer_signature_hash)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -32,7 +32,6 @@ use libsigner::v1::messages::{
 };
 use libsigner::{BlockProposal, SignerEvent};
 use rand_core::OsRng;
-use serde_derive::{Deserialize, Serialize};
 use slog::{slog_debug, slog_error, slog_info, slog_warn};
 use stacks_common::codec::{read_next, StacksMessageCodec};
 use stacks_common::types::chainstate::{ConsensusHash, StacksAddress};
@@ -56,57 +55,10 @@ use wsts::v2;
 use crate::client::{ClientError, SignerSlotID, StackerDB, StacksClient};
 use crate::config::SignerConfig;
 use crate::runloop::{RunLoopCommand, SignerCommand};
+use crate::signerdb::{BlockInfo, SignerDb};
 use crate::v1::coordinator::CoordinatorSelector;
-use crate::v1::signerdb::SignerDb;
 use crate::Signer as SignerTrait;
 
-/// Additional Info about a proposed block
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub struct BlockInfo {
-    /// The block we are considering
-    pub block: NakamotoBlock,
-    /// The burn block height at which the block was proposed
-    pub burn_block_height: u64,
-    /// The reward cycle the block belongs to
-    pub reward_cycle: u64,
-    /// Our vote on the block if we have one yet
-    pub vote: Option<NakamotoBlockVote>,
-    /// Whether the block contents are valid
-    valid: Option<bool>,
-    /// The associated packet nonce request if we have one
-    nonce_request: Option<NonceRequest>,
-    /// Whether this block is already being signed over
-    pub signed_over: bool,
-}
-
-impl From<BlockProposal> for BlockInfo {
-    fn from(value: BlockProposal) -> Self {
-        Self {
-            block: value.block,
-            burn_block_height: value.burn_height,
-            reward_cycle: value.reward_cycle,
-            vote: None,
-            valid: None,
-            nonce_request: None,
-            signed_over: false,
-        }
-    }
-}
-impl BlockInfo {
-    /// Create a new BlockInfo with an associated nonce request packet
-    pub fn new_with_request(block_proposal: BlockProposal, nonce_request: NonceRequest) -> Self {
-        let mut block_info = BlockInfo::from(block_proposal);
-        block_info.nonce_request = Some(nonce_request);
-        block_info.signed_over = true;
-        block_info
-    }
-
-    /// Return the block's signer signature hash
-    pub fn signer_signature_hash(&self) -> Sha512Trunc256Sum {
-        self.block.header.signer_signature_hash()
-    }
-}
-
 /// The specific operations that a signer can perform
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Operation {
@@ -139,7 +91,7 @@ pub struct Signer {
     /// Received Commands that need to be processed
     pub commands: VecDeque<SignerCommand>,
     /// The stackerdb client
-    pub stackerdb: StackerDB,
+    pub stackerdb: StackerDB<MessageSlotID>,
     /// Whether the signer is a mainnet signer or not
     pub mainnet: bool,
     /// The signer id
@@ -577,7 +529,9 @@ impl Signer {
                 );
                 match self.coordinator.start_dkg_round() {
                     Ok(msg) => {
-                        let ack = self.stackerdb.send_message_with_retry(msg.into());
+                        let ack = self
+                            .stackerdb
+                            .send_message_with_retry::<SignerMessage>(msg.into());
                         debug!("{self}: ACK: {ack:?}",);
                         self.update_operation(Operation::Dkg);
                     }
@@ -619,7 +573,9 @@ impl Signer {
                     *merkle_root,
                 ) {
                     Ok(msg) => {
-                        let ack = self.stackerdb.send_message_with_retry(msg.into());
+                        let ack = self
+                            .stackerdb
+                            .send_message_with_retry::<SignerMessage>(msg.into());
                         debug!("{self}: ACK: {ack:?}",);
                         block_info.signed_over = true;
                         self.signer_db
@@ -703,7 +659,7 @@ impl Signer {
                 warn!("{self}: Broadcasting a block rejection due to stacks node validation failure...");
                 if let Err(e) = self
                     .stackerdb
-                    .send_message_with_retry(block_validate_reject.clone().into())
+                    .send_message_with_retry::<SignerMessage>(block_validate_reject.clone().into())
                 {
                     warn!("{self}: Failed to send block rejection to stacker-db: {e:?}",);
                 }
@@ -996,7 +952,7 @@ impl Signer {
                 // Submit signature result to miners to observe
                 if let Err(e) = self
                     .stackerdb
-                    .send_message_with_retry(block_rejection.into())
+                    .send_message_with_retry::<SignerMessage>(block_rejection.into())
                 {
                     warn!("{self}: Failed to send block rejection to stacker-db: {e:?}",);
                 }
@@ -1012,7 +968,7 @@ impl Signer {
             // Submit signature result to miners to observe
             if let Err(e) = self
                 .stackerdb
-                .send_message_with_retry(block_rejection.into())
+                .send_message_with_retry::<SignerMessage>(block_rejection.into())
             {
                 warn!("{self}: Failed to send block submission to stacker-db: {e:?}",);
             }
@@ -1314,7 +1270,8 @@ impl Signer {
         // For all Pox-4 epochs onwards, broadcast the results also to stackerDB for other signers/miners to observe
         signer_transactions.push(new_transaction);
         let signer_message = SignerMessage::Transactions(signer_transactions);
-        self.stackerdb.send_message_with_retry(signer_message)?;
+        self.stackerdb
+            .send_message_with_retry::<SignerMessage>(signer_message)?;
         crate::monitoring::increment_dkg_votes_submitted();
         info!("{self}: Broadcasted DKG vote transaction ({txid}) to stacker DB");
         Ok(())
@@ -1344,7 +1301,7 @@ impl Signer {
         info!("{self}: Submit block response: {block_submission}");
         if let Err(e) = self
             .stackerdb
-            .send_message_with_retry(block_submission.into())
+            .send_message_with_retry::<SignerMessage>(block_submission.into())
         {
             warn!("{self}: Failed to send block submission to stacker-db: {e:?}");
         }
@@ -1384,7 +1341,7 @@ impl Signer {
         // Submit signature result to miners to observe
         if let Err(e) = self
             .stackerdb
-            .send_message_with_retry(block_rejection.into())
+            .send_message_with_retry::<SignerMessage>(block_rejection.into())
         {
             warn!("{self}: Failed to send block rejection submission to stacker-db: {e:?}");
         }
@@ -1439,7 +1396,7 @@ impl Signer {
          * This is a no-op until the number of signer slots can be expanded to 14
          *
         let message = SignerMessage::EncryptedSignerState(encrypted_state);
-        self.stackerdb.send_message_with_retry(message)?;
+        self.stackerdb.send_message_with_retry::<SignerMessage>(message)?;
         */
         Ok(())
     }
@@ -1468,7 +1425,9 @@ impl Signer {
             outbound_messages.len()
         );
         for msg in outbound_messages {
-            let ack = self.stackerdb.send_message_with_retry(msg.into());
+            let ack = self
+                .stackerdb
+                .send_message_with_retry::<SignerMessage>(msg.into());
             if let Ok(ack) = ack {
                 debug!("{self}: send outbound ACK: {ack:?}");
             } else {
@@ -1683,7 +1642,7 @@ trait SignerStateStorage {
     ) -> Result<Option<Vec<u8>>, PersistenceError>;
 }
 
-impl SignerStateStorage for &mut StackerDB {
+impl SignerStateStorage for &mut StackerDB<MessageSlotID> {
     type IdType = SignerSlotID;
 
     fn get_encrypted_signer_state(
@@ -592,6 +592,13 @@ impl<
                 }
             }
 
+            if let Err(e) = self
+                .chain_state_db
+                .maybe_prune_chainstate(&self.sortition_db)
+            {
+                warn!("Error pruning chainstate: {:?}", e);
+            }
+
             signal_mining_ready(miner_status.clone());
         }
         if (bits & (CoordinatorEvents::NEW_BURN_BLOCK as u8)) != 0 {
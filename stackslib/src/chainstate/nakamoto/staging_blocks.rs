@@ -27,7 +27,7 @@ use stacks_common::util::{get_epoch_time_secs, sleep_ms};
 
 use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandle};
 use crate::chainstate::burn::BlockSnapshot;
-use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoChainState};
+use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader, NakamotoChainState};
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::index::marf::MarfConnection;
 use crate::chainstate::stacks::{Error as ChainstateError, StacksBlock, StacksBlockHeader};
@@ -228,6 +228,38 @@ impl<'a> NakamotoStagingBlocksConnRef<'a> {
         Ok(Some(block))
     }
 
+    /// Get the headers of all staged Nakamoto blocks in a tenure, in order by height, along with
+    /// whether or not each block has been processed and whether or not it has been orphaned.
+    /// Blocks are only staged once their signer signatures have been verified, so every header
+    /// returned here carries a valid signer signature.
+    pub fn get_nakamoto_tenure_block_headers(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Result<Vec<(NakamotoBlockHeader, bool, bool)>, ChainstateError> {
+        let qry = "SELECT data, processed, orphaned FROM nakamoto_staging_blocks WHERE consensus_hash = ?1 ORDER BY height, index_block_hash";
+        let args: &[&dyn ToSql] = &[consensus_hash];
+        let mut stmt = self.prepare(qry)?;
+        let mut rows = stmt.query(args)?;
+        let mut headers = vec![];
+        while let Some(row) = rows.next()? {
+            let block_bytes: Vec<u8> = row.get(0)?;
+            let processed: bool = row.get(1)?;
+            let orphaned: bool = row.get(2)?;
+
+            // the header is a prefix of the block
+            let header = NakamotoBlockHeader::consensus_deserialize(&mut block_bytes.as_slice())?;
+            if &header.consensus_hash != consensus_hash {
+                error!(
+                    "Staging DB corruption: expected {}, got {}",
+                    consensus_hash, header.consensus_hash
+                );
+                return Err(DBError::Corruption.into());
+            }
+            headers.push((header, processed, orphaned));
+        }
+        Ok(headers)
+    }
+
     /// Get the rowid of a Nakamoto block
     pub fn get_nakamoto_block_rowid(
         &self,
@@ -599,6 +599,7 @@ impl StacksChainState {
     }
 
     /// Have we processed and stored a particular block?
+    /// Also true if the block's data was stored and then pruned.
     pub fn has_stored_block(
         blocks_db: &DBConn,
         blocks_dir: &str,
@@ -624,8 +625,11 @@ impl StacksChainState {
                     Ok(false)
                 } else {
                     // have a row in the DB at least.
-                    // only accepted if we stored it
-                    StacksChainState::has_block_indexed(blocks_dir, &index_block_hash)
+                    // only accepted if we stored it (even if it was pruned since)
+                    Ok(
+                        StacksChainState::has_block_indexed(blocks_dir, &index_block_hash)?
+                            || StacksChainState::is_block_pruned(blocks_db, &index_block_hash)?,
+                    )
                 }
             }
             None => {
@@ -1949,8 +1953,14 @@ impl StacksChainState {
                             block_bits.push(status);
                         }
                         _ => {
-                            test_debug!("Do not have anchored block {}", &index_block_hash);
-                            block_bits.push(false);
+                            if StacksChainState::is_block_pruned(self.db(), &index_block_hash)? {
+                                // we had it, so we don't need it again
+                                test_debug!("Pruned anchored block {}", &index_block_hash);
+                                block_bits.push(true);
+                            } else {
+                                test_debug!("Do not have anchored block {}", &index_block_hash);
+                                block_bits.push(false);
+                            }
                         }
                     }
 
@@ -2912,7 +2922,19 @@ impl StacksChainState {
         blocks_path: &str,
         index_block_hash: &StacksBlockId,
     ) -> Result<ExtendedStacksHeader, Error> {
-        let header = StacksChainState::load_block_header_indexed(blocks_path, index_block_hash)?
+        let header =
+            match StacksChainState::load_block_header_indexed(blocks_path, index_block_hash) {
+                Ok(header) => header,
+                Err(Error::DBError(db_error::NotFoundError)) => {
+                    // the block file may have been pruned, but its header is still indexed
+                    StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        db,
+                        index_block_hash,
+                    )?
+                    .and_then(|header_info| header_info.anchored_header.as_stacks_epoch2().cloned())
+                }
+                Err(e) => return Err(e),
+            }
             .ok_or(Error::NoSuchBlockError)?;
 
         let header_info = StacksChainState::load_staging_block_info(db, index_block_hash)?
@@ -84,7 +84,8 @@ use crate::net::atlas::BNS_CHARS_REGEX;
 use crate::net::Error as net_error;
 use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
 use crate::util_lib::db::{
-    query_count, query_row, tx_begin_immediate, tx_busy_handler, DBConn, DBTx, Error as db_error,
+    query_count, query_row, query_rows, tx_begin_immediate, tx_busy_handler, DBConn, DBTx,
+    Error as db_error,
     FromColumn, FromRow, IndexDBConn, IndexDBTx,
 };
 
@@ -92,6 +93,7 @@ pub mod accounts;
 pub mod blocks;
 pub mod contracts;
 pub mod headers;
+pub mod prune;
 pub mod transactions;
 pub mod unconfirmed;
 
@@ -125,9 +127,42 @@ pub struct StacksChainState {
     pub root_path: String,
     pub unconfirmed_state: Option<UnconfirmedState>,
     pub fault_injection: StacksChainStateFaults,
+    /// If true, then every processed transaction is recorded in the `transactions` table so it
+    /// can later be looked up by txid. Defaults to the value of STACKS_TRANSACTION_LOG.
+    pub txindex: bool,
+    /// If set, then the data of processed blocks more than this many blocks below the chain tip
+    /// is deleted.  See `db::prune`.
+    pub prune_depth: Option<u64>,
     marf_opts: Option<MARFOpenOpts>,
 }
 
+/// A transaction recorded in the `transactions` table (the txid index)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedTransaction {
+    pub txid: Txid,
+    /// the block which processed this transaction
+    pub index_block_hash: StacksBlockId,
+    /// hex-encoded transaction, or `BTC(<txid>)` for a burnchain operation
+    pub tx_hex: String,
+    /// the transaction's result, as a Clarity value representation
+    pub result: String,
+}
+
+impl FromRow<IndexedTransaction> for IndexedTransaction {
+    fn from_row<'a>(row: &'a Row) -> Result<IndexedTransaction, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let tx_hex: String = row.get_unwrap("tx_hex");
+        let result: String = row.get_unwrap("result");
+        Ok(IndexedTransaction {
+            txid,
+            index_block_hash,
+            tx_hex,
+            result,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StacksAccount {
     pub principal: PrincipalData,
@@ -330,6 +365,14 @@ impl StacksBlockHeaderTypes {
         }
     }
 
+    /// Get the root hash of the Clarity state MARF as of this block
+    pub fn state_index_root(&self) -> TrieHash {
+        match self {
+            StacksBlockHeaderTypes::Epoch2(x) => x.state_index_root.clone(),
+            StacksBlockHeaderTypes::Nakamoto(x) => x.state_index_root.clone(),
+        }
+    }
+
     /// Get the total spend by miners for this block
     pub fn total_burns(&self) -> u64 {
         match self {
@@ -599,6 +642,7 @@ pub struct ChainstateTx<'a> {
     pub blocks_path: String,
     pub tx: StacksDBTx<'a>,
     pub root_path: String,
+    pub txindex: bool,
 }
 
 impl<'a> ChainstateTx<'a> {
@@ -607,12 +651,14 @@ impl<'a> ChainstateTx<'a> {
         blocks_path: String,
         root_path: String,
         config: DBConfig,
+        txindex: bool,
     ) -> ChainstateTx<'a> {
         ChainstateTx {
             config,
             blocks_path,
             tx,
             root_path,
+            txindex,
         }
     }
 
@@ -633,7 +679,7 @@ impl<'a> ChainstateTx<'a> {
         block_id: &StacksBlockId,
         events: &[StacksTransactionReceipt],
     ) {
-        if *TRANSACTION_LOG {
+        if self.txindex {
             let insert =
                 "INSERT INTO transactions (txid, index_block_hash, tx_hex, result) VALUES (?, ?, ?, ?)";
             for tx_event in events.iter() {
@@ -668,7 +714,7 @@ impl<'a> DerefMut for ChainstateTx<'a> {
     }
 }
 
-pub const CHAINSTATE_VERSION: &'static str = "4";
+pub const CHAINSTATE_VERSION: &'static str = "5";
 
 const CHAINSTATE_INITIAL_SCHEMA: &'static [&'static str] = &[
     "PRAGMA foreign_keys = ON;",
@@ -865,6 +911,27 @@ const CHAINSTATE_SCHEMA_3: &'static [&'static str] = &[
     "#,
 ];
 
+const CHAINSTATE_SCHEMA_5: &'static [&'static str] = &[
+    // new in schema version 5
+    // track how far a pruning node has deleted block data (see `db::prune`), so that it survives
+    // restarts and every open chainstate handle agrees on what has been pruned.
+    r#"
+    CREATE TABLE chainstate_pruning(
+        -- processed block data below this height has been (or is being) deleted
+        pruned_height INTEGER NOT NULL,
+        -- the block files below this height have been deleted
+        deleted_height INTEGER NOT NULL,
+        -- the Nakamoto block bodies below this height have been deleted
+        nakamoto_deleted_height INTEGER NOT NULL
+    );"#,
+    r#"
+    INSERT INTO chainstate_pruning (pruned_height, deleted_height, nakamoto_deleted_height) VALUES (0, 0, 0);
+    "#,
+    r#"
+    UPDATE db_config SET version = "5";
+    "#,
+];
+
 const CHAINSTATE_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS index_block_hash_to_primary_key ON block_headers(index_block_hash,consensus_hash,block_hash);",
     "CREATE INDEX IF NOT EXISTS block_headers_hash_index ON block_headers(block_hash,block_height);",
@@ -1079,6 +1146,13 @@ impl StacksChainState {
                             tx.execute_batch(cmd)?;
                         }
                     }
+                    "4" => {
+                        // migrate to 5
+                        info!("Migrating chainstate schema from version 4 to 5");
+                        for cmd in CHAINSTATE_SCHEMA_5.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
                     _ => {
                         error!(
                             "Invalid chain state database: expected version = {}, got {}",
@@ -1815,6 +1889,8 @@ impl StacksChainState {
             root_path: path_str.to_string(),
             unconfirmed_state: None,
             fault_injection: StacksChainStateFaults::new(),
+            txindex: *TRANSACTION_LOG,
+            prune_depth: None,
             marf_opts: marf_opts,
         };
 
@@ -1870,8 +1946,13 @@ impl StacksChainState {
         let clarity_instance = &mut self.clarity_state;
         let inner_tx = StacksDBTx::new(&mut self.state_index, ());
 
-        let chainstate_tx =
-            ChainstateTx::new(inner_tx, blocks_path, self.root_path.clone(), config);
+        let chainstate_tx = ChainstateTx::new(
+            inner_tx,
+            blocks_path,
+            self.root_path.clone(),
+            config,
+            self.txindex,
+        );
 
         Ok((chainstate_tx, clarity_instance))
     }
@@ -2456,6 +2537,19 @@ impl StacksChainState {
         Ok(txids)
     }
 
+    /// Get every block which processed the given transaction, according to the txid index.
+    /// There can be more than one if the transaction was mined in several forks.
+    /// The index is only populated if `txindex` was enabled when the blocks were processed.
+    pub fn get_indexed_transactions(
+        conn: &Connection,
+        txid: &Txid,
+    ) -> Result<Vec<IndexedTransaction>, Error> {
+        let sql = "SELECT txid, index_block_hash, tx_hex, result FROM transactions WHERE txid = ?1";
+        let args: &[&dyn ToSql] = &[txid];
+        let rows = query_rows(conn, sql, args)?;
+        Ok(rows)
+    }
+
     /// Get the txids of the burnchain operations applied in the past N Stacks blocks.
     pub fn get_burnchain_txids_in_ancestors(
         conn: &Connection,
@@ -0,0 +1,538 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chainstate pruning for non-archival nodes.
+//!
+//! If `prune_depth` is set to N, then once the canonical tip is at height H, the node deletes:
+//!
+//! * the block files of processed epoch 2.x Stacks blocks below height H - N,
+//! * the data of processed microblock streams confirmed by those blocks,
+//! * the bodies of processed Nakamoto blocks below height H - N, other than tenure-start blocks,
+//!   and
+//! * the Clarity MARF tries (and their contract metadata) of forks which were abandoned more
+//!   than N blocks below the highest trie.
+//!
+//! N must be at least `MIN_PRUNE_DEPTH`, one reward cycle.
+//!
+//! Nearly all of the space this saves is block and microblock data.  The Clarity state of the
+//! canonical chain does not shrink: every one of its tries is kept, since later tries point into
+//! them and hash over their roots, and so are all of the values in the Clarity side store (they
+//! are keyed by their hash and may be shared between tries), including those which only a
+//! dropped trie used.  So a pruned node's Clarity MARF keeps growing like an archival node's,
+//! less the trie nodes of orphaned forks.  Block headers, the headers index MARF, and the
+//! `transactions` table are kept as well.
+//!
+//! A pruned Nakamoto block keeps its header in the staging DB, since the header is a prefix of
+//! the stored block and the tenure endpoints read tenures' headers from there.  Tenure-start
+//! blocks are kept whole: the downloader reads them to check the tenures it fetches.  SQLite
+//! reuses the pages that pruned bodies leave free, so `nakamoto.sqlite` stops growing once
+//! pruning catches up, but it does not shrink.
+//!
+//! Dropped tries leave holes in the Clarity MARF's `.blobs` file, which `compact_clarity_marf()`
+//! reclaims; the node does this at startup, before the chainstate is opened.
+//!
+//! The prune height is stored in the chainstate DB, so it survives restarts and every
+//! chainstate handle (such as the RPC interface's) agrees on what has been pruned.  A pass
+//! records the new prune height before it deletes anything.  Readers of block data pin it with
+//! `pin_block()`, `pin_microblock_stream()` or `pin_nakamoto_block()`, which fail with
+//! `PrunedError` once the data is below the prune height; data which is pinned when a pass runs
+//! is deleted once it is released (block files) or by the next pass (microblock data and
+//! Nakamoto block bodies).  So a reader either gets all of the data
+//! it asked for, or is told that it was pruned.
+//!
+//! A pruned node can no longer serve the deleted blocks and microblocks (the RPC interface
+//! reports them as pruned), nor can it process a fork which is more than N blocks deep.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::{fs, io};
+
+use lazy_static::lazy_static;
+use rusqlite::{OpenFlags, OptionalExtension, ToSql, NO_PARAMS};
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::nakamoto::{
+    NakamotoBlockHeader, NakamotoChainState, NakamotoStagingBlocksConnRef,
+};
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::index::file::TrieFile;
+use crate::chainstate::stacks::Error;
+use crate::core::POX_REWARD_CYCLE_LENGTH;
+use crate::util_lib::db::{query_row_columns, sqlite_open, u64_to_sql, DBConn, Error as db_error};
+
+/// Number of blocks the prune height must advance by before the chainstate is pruned again
+pub const PRUNE_INTERVAL: u64 = 100;
+
+/// The smallest allowed `prune_depth`: one reward cycle.  Anything shallower would delete blocks
+/// which peers that are still syncing the current reward cycle may ask for, and which the node
+/// needs to process a reorg.
+pub const MIN_PRUNE_DEPTH: u64 = POX_REWARD_CYCLE_LENGTH as u64;
+
+lazy_static! {
+    /// The readers of anchored blocks' data, by block file path.  See `BlockDataPin`.
+    static ref BLOCK_DATA_READERS: Mutex<HashMap<PathBuf, BlockDataReaders>> =
+        Mutex::new(HashMap::new());
+}
+
+fn block_data_readers() -> MutexGuard<'static, HashMap<PathBuf, BlockDataReaders>> {
+    // the map is consistent between statements, so a panic while holding the lock is harmless
+    BLOCK_DATA_READERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The readers of one anchored block's data
+#[derive(Debug, Default)]
+struct BlockDataReaders {
+    /// Number of live `BlockDataPin`s
+    count: u64,
+    /// If true, then the block was pruned while pinned, and its file is deleted with the last pin
+    pruned: bool,
+}
+
+/// Keeps the data of a processed anchored block -- its block file, and the data of the
+/// microblock stream it confirms -- from being deleted while it is read.  If the block is pruned
+/// in the meantime, its file is deleted when the last pin on it is dropped.
+#[derive(Debug, PartialEq)]
+pub struct BlockDataPin {
+    block_path: PathBuf,
+}
+
+impl Clone for BlockDataPin {
+    fn clone(&self) -> Self {
+        block_data_readers()
+            .entry(self.block_path.clone())
+            .or_default()
+            .count += 1;
+        BlockDataPin {
+            block_path: self.block_path.clone(),
+        }
+    }
+}
+
+impl Drop for BlockDataPin {
+    fn drop(&mut self) {
+        let mut readers = block_data_readers();
+        let Some(entry) = readers.get_mut(&self.block_path) else {
+            return;
+        };
+        entry.count = entry.count.saturating_sub(1);
+        if entry.count > 0 {
+            return;
+        }
+        let pruned = entry.pruned;
+        readers.remove(&self.block_path);
+        if !pruned {
+            return;
+        }
+        if let Err(e) = fs::remove_file(&self.block_path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    "Failed to delete pruned block file {}: {:?}",
+                    self.block_path.display(),
+                    &e
+                );
+            }
+        }
+    }
+}
+
+/// What a pruning pass deleted
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChainstatePruneSummary {
+    /// Block data below this height was pruned
+    pub prune_height: u64,
+    /// Number of block files deleted.  This includes the files of pinned blocks, which are
+    /// deleted once they are released.
+    pub blocks: u64,
+    /// Number of microblocks whose data was deleted
+    pub microblocks: u64,
+    /// Number of Nakamoto blocks whose bodies were deleted
+    pub nakamoto_blocks: u64,
+    /// Number of Clarity MARF tries dropped
+    pub tries: u64,
+}
+
+impl StacksChainState {
+    /// Get the prune height, and the height below which the block files have all been deleted.
+    /// The latter trails the former only while a pass is deleting them, or if it was interrupted.
+    fn get_prune_heights(conn: &DBConn) -> Result<(u64, u64), Error> {
+        let (pruned_height, deleted_height): (i64, i64) = conn.query_row(
+            "SELECT pruned_height, deleted_height FROM chainstate_pruning",
+            NO_PARAMS,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let pruned_height =
+            u64::try_from(pruned_height).map_err(|_| Error::DBError(db_error::ParseError))?;
+        let deleted_height =
+            u64::try_from(deleted_height).map_err(|_| Error::DBError(db_error::ParseError))?;
+        Ok((pruned_height, deleted_height))
+    }
+
+    /// Get the height below which the bodies of Nakamoto blocks have all been deleted, except for
+    /// those which were being read at the time
+    fn get_nakamoto_deleted_height(conn: &DBConn) -> Result<u64, Error> {
+        let deleted_height: i64 = conn.query_row(
+            "SELECT nakamoto_deleted_height FROM chainstate_pruning",
+            NO_PARAMS,
+            |row| row.get(0),
+        )?;
+        u64::try_from(deleted_height).map_err(|_| Error::DBError(db_error::ParseError))
+    }
+
+    /// Get the height below which processed block data has been pruned
+    pub fn get_pruned_height(conn: &DBConn) -> Result<u64, Error> {
+        let (pruned_height, _) = StacksChainState::get_prune_heights(conn)?;
+        Ok(pruned_height)
+    }
+
+    /// Prune the chainstate if `prune_depth` is set and the prune height has advanced by at least
+    /// `PRUNE_INTERVAL` blocks since the last pass.  Returns what was pruned, if anything.
+    pub fn maybe_prune_chainstate(
+        &mut self,
+        sortdb: &SortitionDB,
+    ) -> Result<Option<ChainstatePruneSummary>, Error> {
+        let Some(prune_depth) = self.prune_depth else {
+            return Ok(None);
+        };
+        let Some(tip) = NakamotoChainState::get_canonical_block_header(self.db(), sortdb)? else {
+            return Ok(None);
+        };
+        let prune_height = tip.stacks_block_height.saturating_sub(prune_depth);
+        let pruned_height = StacksChainState::get_pruned_height(self.db())?;
+        if prune_height < pruned_height.saturating_add(PRUNE_INTERVAL) {
+            return Ok(None);
+        }
+        self.prune_chainstate(tip.stacks_block_height, prune_depth)
+            .map(Some)
+    }
+
+    /// Delete the block data that a node with the given `prune_depth` no longer needs, given
+    /// the height of its canonical tip.  See the module documentation for what is (and is not)
+    /// deleted.
+    pub fn prune_chainstate(
+        &mut self,
+        tip_height: u64,
+        prune_depth: u64,
+    ) -> Result<ChainstatePruneSummary, Error> {
+        let (pruned_height, deleted_height) = StacksChainState::get_prune_heights(self.db())?;
+        let prune_height = tip_height.saturating_sub(prune_depth).max(pruned_height);
+        let mut summary = ChainstatePruneSummary {
+            prune_height,
+            ..ChainstatePruneSummary::default()
+        };
+
+        // Record the new prune height before deleting anything.  From here on, no reader can pin
+        // the data below it, so whatever is not pinned yet can go.
+        let tx = self.db_tx_begin()?;
+        let args: &[&dyn ToSql] = &[&u64_to_sql(prune_height)?];
+        tx.execute("UPDATE chainstate_pruning SET pruned_height = ?1", args)?;
+        tx.commit()?;
+
+        // anchored blocks
+        let sql = "SELECT index_block_hash FROM staging_blocks \
+                   WHERE processed = 1 AND orphaned = 0 AND height >= ?1 AND height < ?2";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(deleted_height)?, &u64_to_sql(prune_height)?];
+        let block_ids: Vec<StacksBlockId> =
+            query_row_columns(self.db(), sql, args, "index_block_hash")?;
+        {
+            let mut readers = block_data_readers();
+            for block_id in block_ids.iter() {
+                let block_path = PathBuf::from(StacksChainState::get_index_block_path(
+                    &self.blocks_path,
+                    block_id,
+                )?);
+                if let Some(entry) = readers.get_mut(&block_path) {
+                    // deleted by the last reader
+                    entry.pruned = true;
+                    summary.blocks += 1;
+                    continue;
+                }
+                match fs::remove_file(&block_path) {
+                    Ok(()) => summary.blocks += 1,
+                    Err(e) => {
+                        if e.kind() != io::ErrorKind::NotFound {
+                            return Err(Error::DBError(db_error::IOError(e)));
+                        }
+                    }
+                }
+            }
+        }
+        let tx = self.db_tx_begin()?;
+        let args: &[&dyn ToSql] = &[&u64_to_sql(prune_height)?];
+        tx.execute("UPDATE chainstate_pruning SET deleted_height = ?1", args)?;
+        tx.commit()?;
+
+        // microblock streams.  The stream off of a block at height h is confirmed at height
+        // h + 1, so it can go once that block is pruned.  Streams which are being read are left
+        // for the next pass.
+        let sql = "SELECT DISTINCT staging_microblocks.index_block_hash FROM staging_microblocks \
+                   JOIN staging_microblocks_data ON staging_microblocks.microblock_hash = staging_microblocks_data.block_hash \
+                   JOIN staging_blocks ON staging_microblocks.index_block_hash = staging_blocks.index_block_hash \
+                   WHERE staging_microblocks.processed = 1 AND staging_blocks.height + 1 < ?1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(prune_height)?];
+        let parent_ids: Vec<StacksBlockId> =
+            query_row_columns(self.db(), sql, args, "index_block_hash")?;
+        let mut parents = vec![];
+        for parent_id in parent_ids.into_iter() {
+            let block_path = PathBuf::from(StacksChainState::get_index_block_path(
+                &self.blocks_path,
+                &parent_id,
+            )?);
+            parents.push((parent_id, block_path));
+        }
+        {
+            let readers = block_data_readers();
+            let tx = self.db_tx_begin()?;
+            let sql = "DELETE FROM staging_microblocks_data WHERE block_hash IN \
+                       (SELECT microblock_hash FROM staging_microblocks \
+                        WHERE index_block_hash = ?1 AND processed = 1)";
+            for (parent_id, block_path) in parents.iter() {
+                if readers.contains_key(block_path) {
+                    continue;
+                }
+                let args: &[&dyn ToSql] = &[parent_id];
+                summary.microblocks += tx.execute(sql, args)? as u64;
+            }
+            tx.commit()?;
+        }
+
+        // Nakamoto blocks.  Their bodies are cut down to their headers; blocks which are being read
+        // are left for the next pass, which starts over from the lowest of them.
+        let nakamoto_deleted_height = StacksChainState::get_nakamoto_deleted_height(self.db())?;
+        let sql = "SELECT index_block_hash, height FROM nakamoto_staging_blocks \
+                   WHERE processed = 1 AND orphaned = 0 AND is_tenure_start = 0 \
+                   AND height >= ?1 AND height < ?2";
+        let args: &[&dyn ToSql] = &[
+            &u64_to_sql(nakamoto_deleted_height)?,
+            &u64_to_sql(prune_height)?,
+        ];
+        let mut nakamoto_blocks = vec![];
+        {
+            let staging_conn = self.nakamoto_blocks_db();
+            let mut stmt = staging_conn.prepare(sql)?;
+            let mut rows = stmt.query(args)?;
+            while let Some(row) = rows.next()? {
+                let block_id: StacksBlockId = row.get(0)?;
+                let height: i64 = row.get(1)?;
+                let height =
+                    u64::try_from(height).map_err(|_| Error::DBError(db_error::ParseError))?;
+                let block_path = PathBuf::from(StacksChainState::get_index_block_path(
+                    &self.blocks_path,
+                    &block_id,
+                )?);
+                nakamoto_blocks.push((block_id, height, block_path));
+            }
+        }
+        let mut next_nakamoto_deleted_height = prune_height;
+        {
+            let readers = block_data_readers();
+            let tx = self.staging_db_tx_begin()?;
+            for (block_id, height, block_path) in nakamoto_blocks.iter() {
+                if readers.contains_key(block_path) {
+                    next_nakamoto_deleted_height = next_nakamoto_deleted_height.min(*height);
+                    continue;
+                }
+                let args: &[&dyn ToSql] = &[block_id];
+                let block_bytes: Vec<u8> = tx.query_row(
+                    "SELECT data FROM nakamoto_staging_blocks WHERE index_block_hash = ?1",
+                    args,
+                    |row| row.get(0),
+                )?;
+                // the header is a prefix of the block
+                let header_bytes =
+                    NakamotoBlockHeader::consensus_deserialize(&mut block_bytes.as_slice())?
+                        .serialize_to_vec();
+                if header_bytes.len() >= block_bytes.len() {
+                    // pruned by an earlier pass
+                    continue;
+                }
+                let args: &[&dyn ToSql] = &[&header_bytes, block_id];
+                tx.execute(
+                    "UPDATE nakamoto_staging_blocks SET data = ?1 WHERE index_block_hash = ?2",
+                    args,
+                )?;
+                summary.nakamoto_blocks += 1;
+            }
+            tx.commit()?;
+        }
+        let tx = self.db_tx_begin()?;
+        let args: &[&dyn ToSql] = &[&u64_to_sql(next_nakamoto_deleted_height)?];
+        tx.execute(
+            "UPDATE chainstate_pruning SET nakamoto_deleted_height = ?1",
+            args,
+        )?;
+        tx.commit()?;
+
+        // Clarity state
+        let depth = u32::try_from(prune_depth).unwrap_or(u32::MAX);
+        summary.tries = self.clarity_state.prune_unreachable_tries(depth)?.len() as u64;
+
+        info!("Pruned chainstate";
+              "prune_height" => prune_height,
+              "blocks" => summary.blocks,
+              "microblocks" => summary.microblocks,
+              "nakamoto_blocks" => summary.nakamoto_blocks,
+              "tries" => summary.tries);
+        Ok(summary)
+    }
+
+    /// Was this anchored block processed, but its data then pruned?
+    pub fn is_block_pruned(
+        blocks_conn: &DBConn,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT 1 FROM staging_blocks \
+                   WHERE index_block_hash = ?1 AND processed = 1 AND orphaned = 0 \
+                   AND height < (SELECT pruned_height FROM chainstate_pruning)";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        let pruned = blocks_conn
+            .query_row(sql, args, |_row| Ok(()))
+            .optional()?
+            .is_some();
+        Ok(pruned)
+    }
+
+    /// Was this microblock processed, but its data then pruned?
+    pub fn is_microblock_pruned(
+        &self,
+        index_microblock_hash: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT 1 FROM staging_microblocks JOIN staging_blocks \
+                   ON staging_microblocks.index_block_hash = staging_blocks.index_block_hash \
+                   WHERE staging_microblocks.index_microblock_hash = ?1 \
+                   AND staging_microblocks.processed = 1 \
+                   AND staging_blocks.height + 1 < (SELECT pruned_height FROM chainstate_pruning)";
+        let args: &[&dyn ToSql] = &[index_microblock_hash];
+        let pruned = self
+            .db()
+            .query_row(sql, args, |_row| Ok(()))
+            .optional()?
+            .is_some();
+        Ok(pruned)
+    }
+
+    /// Was this Nakamoto block processed, but its body then pruned?  `headers_conn` is a
+    /// connection to the chainstate DB, which holds the prune height.
+    pub fn is_nakamoto_block_pruned(
+        headers_conn: &DBConn,
+        staging_conn: &NakamotoStagingBlocksConnRef,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let pruned_height = StacksChainState::get_pruned_height(headers_conn)?;
+        let sql = "SELECT 1 FROM nakamoto_staging_blocks \
+                   WHERE index_block_hash = ?1 AND processed = 1 AND orphaned = 0 \
+                   AND is_tenure_start = 0 AND height < ?2";
+        let args: &[&dyn ToSql] = &[index_block_hash, &u64_to_sql(pruned_height)?];
+        let pruned = staging_conn
+            .query_row(sql, args, |_row| Ok(()))
+            .optional()?
+            .is_some();
+        Ok(pruned)
+    }
+
+    /// Pin the data of an anchored block, so that it can be read without being pruned in the
+    /// meantime.  Fails with `PrunedError` if it was already pruned.
+    pub fn pin_block(&self, index_block_hash: &StacksBlockId) -> Result<BlockDataPin, Error> {
+        StacksChainState::pin_block_data(&self.blocks_path, index_block_hash, || {
+            StacksChainState::is_block_pruned(self.db(), index_block_hash)
+        })
+    }
+
+    /// Pin the data of the microblock stream which builds on `parent_index_block_hash` and ends
+    /// at `tail_index_microblock_hash`, so that it can be read without being pruned in the
+    /// meantime.  Fails with `PrunedError` if it was already pruned.
+    pub fn pin_microblock_stream(
+        &self,
+        parent_index_block_hash: &StacksBlockId,
+        tail_index_microblock_hash: &StacksBlockId,
+    ) -> Result<BlockDataPin, Error> {
+        StacksChainState::pin_block_data(&self.blocks_path, parent_index_block_hash, || {
+            self.is_microblock_pruned(tail_index_microblock_hash)
+        })
+    }
+
+    /// Pin the body of a Nakamoto block, so that it can be read without being pruned in the
+    /// meantime.  Fails with `PrunedError` if it was already pruned.
+    pub fn pin_nakamoto_block(
+        &self,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<BlockDataPin, Error> {
+        StacksChainState::pin_staged_nakamoto_block(
+            &self.blocks_path,
+            self.db(),
+            &self.nakamoto_blocks_db(),
+            index_block_hash,
+        )
+    }
+
+    /// Like `pin_nakamoto_block()`, for readers which hold their own connections to the
+    /// chainstate DB and the staging DB
+    pub fn pin_staged_nakamoto_block(
+        blocks_path: &str,
+        headers_conn: &DBConn,
+        staging_conn: &NakamotoStagingBlocksConnRef,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<BlockDataPin, Error> {
+        StacksChainState::pin_block_data(blocks_path, index_block_hash, || {
+            StacksChainState::is_nakamoto_block_pruned(headers_conn, staging_conn, index_block_hash)
+        })
+    }
+
+    /// Pin the data of the block with the given index block hash, unless `is_pruned()` says that
+    /// it is already gone.  Nakamoto blocks have no block file, but their would-be path still
+    /// names them uniquely.
+    fn pin_block_data<F>(
+        blocks_path: &str,
+        index_block_hash: &StacksBlockId,
+        is_pruned: F,
+    ) -> Result<BlockDataPin, Error>
+    where
+        F: FnOnce() -> Result<bool, Error>,
+    {
+        let block_path = PathBuf::from(StacksChainState::get_index_block_path(
+            blocks_path,
+            index_block_hash,
+        )?);
+        // check under the lock, so the data cannot be deleted between the check and the pin
+        let mut readers = block_data_readers();
+        if is_pruned()? {
+            return Err(Error::PrunedError);
+        }
+        readers.entry(block_path.clone()).or_default().count += 1;
+        Ok(BlockDataPin { block_path })
+    }
+
+    /// Reclaim the space left in the Clarity MARF's trie blobs file by pruned tries.  Returns the
+    /// number of bytes reclaimed.
+    /// NOTE: the chainstate at `path_str` must not be open anywhere, including in this process.
+    pub fn compact_clarity_marf(path_str: &str) -> Result<u64, Error> {
+        let marf_path = StacksChainState::vm_state_index_marf_path(PathBuf::from(path_str));
+        if fs::metadata(&marf_path).is_err() {
+            return Ok(0);
+        }
+        let marf_path_str = marf_path
+            .to_str()
+            .ok_or_else(|| Error::DBError(db_error::ParseError))?
+            .to_string();
+        let mut db = sqlite_open(&marf_path, OpenFlags::SQLITE_OPEN_READ_WRITE, true)?;
+        let reclaimed = TrieFile::compact_blobs(&mut db, &marf_path_str)?;
+        Ok(reclaimed)
+    }
+}
@@ -29,7 +29,7 @@ use clarity::vm::contexts::{AssetMap, AssetMapEntry, Environment};
 use clarity::vm::contracts::Contract;
 use clarity::vm::costs::cost_functions::ClarityCostFunction;
 use clarity::vm::costs::{cost_functions, runtime_cost, CostTracker, ExecutionCost};
-use clarity::vm::database::{ClarityBackingStore, ClarityDatabase};
+use clarity::vm::database::{BurnStateDB, ClarityBackingStore, ClarityDatabase};
 use clarity::vm::errors::Error as InterpreterError;
 use clarity::vm::representations::{ClarityName, ContractName};
 use clarity::vm::types::serialization::SerializationError as ClaritySerializationError;
@@ -522,6 +522,16 @@ impl StacksChainState {
         config: &DBConfig,
         tx: &StacksTransaction,
         epoch_id: StacksEpochId,
+    ) -> Result<(), Error> {
+        StacksChainState::inner_process_transaction_precheck(config, tx, epoch_id, true)
+    }
+
+    /// Pre-check a transaction, optionally without verifying its signatures
+    fn inner_process_transaction_precheck(
+        config: &DBConfig,
+        tx: &StacksTransaction,
+        epoch_id: StacksEpochId,
+        verify_signatures: bool,
     ) -> Result<(), Error> {
         // valid auth?
         if !tx.auth.is_supported_in_epoch(epoch_id) {
@@ -533,7 +543,9 @@ impl StacksChainState {
 
             return Err(Error::InvalidStacksTransaction(msg, false));
         }
-        tx.verify().map_err(Error::NetError)?;
+        if verify_signatures {
+            tx.verify().map_err(Error::NetError)?;
+        }
 
         // destined for us?
         if config.chain_id != tx.chain_id {
@@ -1473,11 +1485,26 @@ impl StacksChainState {
         tx: &StacksTransaction,
         quiet: bool,
         ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        StacksChainState::inner_process_transaction(clarity_block, tx, quiet, ast_rules, true)
+    }
+
+    fn inner_process_transaction(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+        ast_rules: ASTRules,
+        verify_signatures: bool,
     ) -> Result<(u64, StacksTransactionReceipt), Error> {
         debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
         let epoch = clarity_block.get_epoch();
 
-        StacksChainState::process_transaction_precheck(&clarity_block.config, tx, epoch)?;
+        StacksChainState::inner_process_transaction_precheck(
+            &clarity_block.config,
+            tx,
+            epoch,
+            verify_signatures,
+        )?;
 
         // what version of Clarity did the transaction caller want? And, is it valid now?
         let clarity_version = StacksChainState::get_tx_clarity_version(clarity_block, tx)?;
@@ -1567,6 +1594,60 @@ impl StacksChainState {
 
         Ok((fee, tx_receipt))
     }
+
+    /// Evaluate a transaction on top of the given chain tip as if it were mined in the next
+    /// block, and return the fee it would pay and its receipt.  The resulting state is
+    /// discarded.
+    ///
+    /// The transaction's signatures are not checked.  If `use_account_nonces` is true, then the
+    /// transaction's origin and sponsor nonces are replaced with the accounts' next nonces, so
+    /// the caller does not need to know them in advance.
+    ///
+    /// The simulation runs on a freshly-opened handle to the chainstate, so the caller's
+    /// connections (i.e. the RPC thread's) are never used to open a write transaction.
+    pub fn simulate_transaction(
+        chainstate_handle: &StacksChainState,
+        burn_dbconn: &dyn BurnStateDB,
+        parent_tip: &StacksBlockId,
+        tx: &StacksTransaction,
+        use_account_nonces: bool,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        let parent_header =
+            NakamotoChainState::get_block_header(chainstate_handle.db(), parent_tip)?
+                .ok_or(Error::NoSuchBlockError)?;
+        let ast_rules = burn_dbconn.get_ast_rules(parent_header.burn_header_height);
+
+        let (mut chainstate, _) = chainstate_handle.reopen()?;
+        let mut clarity_tx = chainstate.block_begin(
+            burn_dbconn,
+            &parent_header.consensus_hash,
+            &parent_header.anchored_header.block_hash(),
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+
+        let mut tx = tx.clone();
+        if use_account_nonces {
+            let origin =
+                StacksChainState::get_account(&mut clarity_tx, &tx.origin_address().into());
+            tx.set_origin_nonce(origin.nonce);
+            if let Some(sponsor_address) = tx.sponsor_address() {
+                let sponsor =
+                    StacksChainState::get_account(&mut clarity_tx, &sponsor_address.into());
+                tx.set_sponsor_nonce(sponsor.nonce)?;
+            }
+        }
+
+        let result = StacksChainState::inner_process_transaction(
+            &mut clarity_tx,
+            &tx,
+            true,
+            ast_rules,
+            false,
+        );
+        clarity_tx.rollback_block();
+        result
+    }
 }
 
 #[cfg(test)]
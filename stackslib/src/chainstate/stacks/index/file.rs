@@ -156,7 +156,7 @@ impl TrieFile {
     }
 
     /// Read a trie blob in its entirety from the DB
-    fn read_trie_blob_from_db(db: &Connection, block_id: u32) -> Result<Vec<u8>, Error> {
+    pub fn read_trie_blob_from_db(db: &Connection, block_id: u32) -> Result<Vec<u8>, Error> {
         let trie_blob = {
             let mut fd = trie_sql::open_trie_blob_readonly(db, block_id)?;
             let mut trie_blob = vec![];
@@ -167,7 +167,6 @@ impl TrieFile {
     }
 
     /// Read a trie blob in its entirety from the blobs file
-    #[cfg(test)]
     pub fn read_trie_blob(&mut self, db: &Connection, block_id: u32) -> Result<Vec<u8>, Error> {
         let (offset, length) = trie_sql::get_external_trie_offset_length(db, block_id)?;
         self.seek(SeekFrom::Start(offset))?;
@@ -245,6 +244,122 @@ impl TrieFile {
         }
     }
 
+    /// Path to the scratch file used while compacting `$db_path.blobs`
+    fn compact_blobs_path(db_path: &str) -> String {
+        format!("{}.blobs.compact", db_path)
+    }
+
+    /// Finish or discard an interrupted compaction of `$db_path.blobs` (see `compact_blobs()`).
+    /// The compacted copy is installed only if the DB's offsets were already updated to point
+    /// into it, which is the case exactly when the DB's tries end where the copy ends.  Before
+    /// that update, the DB's tries end further out, since the copy omits at least one dropped
+    /// trie (or else the offsets don't change at all).
+    pub fn recover_compaction(db: &Connection, db_path: &str) -> Result<(), Error> {
+        if db_path == ":memory:" {
+            return Ok(());
+        }
+        let compact_path = TrieFile::compact_blobs_path(db_path);
+        let compact_len = match fs::metadata(&compact_path) {
+            Ok(md) => md.len(),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    return Ok(());
+                } else {
+                    return Err(e.into());
+                }
+            }
+        };
+
+        if trie_sql::get_external_blobs_length(db)? == compact_len {
+            info!("Finish compacting trie blobs into {}.blobs", db_path);
+            fs::rename(&compact_path, format!("{}.blobs", db_path))?;
+        } else {
+            info!("Discard interrupted compaction of {}.blobs", db_path);
+            fs::remove_file(&compact_path)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite `$db_path.blobs` so that it holds only the tries the DB still refers to, in their
+    /// original order, and return the number of bytes reclaimed.  The tries are copied to a
+    /// scratch file first, and the DB's offsets are updated in one transaction before the
+    /// scratch file replaces the blobs file.  If this is interrupted, the next read/write open
+    /// of the MARF finishes or discards the compaction.
+    /// NOTE: this is *not* thread-safe.  Do not call while the MARF is open anywhere else, since
+    /// open TrieFiles cache their tries' offsets.
+    pub fn compact_blobs(db: &mut Connection, db_path: &str) -> Result<u64, Error> {
+        if db_path == ":memory:" {
+            return Ok(0);
+        }
+        TrieFile::recover_compaction(db, db_path)?;
+
+        let blobs_path = format!("{}.blobs", db_path);
+        let blobs_len = match fs::metadata(&blobs_path) {
+            Ok(md) => md.len(),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    return Ok(0);
+                } else {
+                    return Err(e.into());
+                }
+            }
+        };
+
+        let tries = trie_sql::read_external_trie_offsets_lengths(db)?;
+        let mut compact_len = 0;
+        let mut already_compact = true;
+        for (_, offset, length) in tries.iter() {
+            if *offset != compact_len {
+                already_compact = false;
+            }
+            compact_len += *length;
+        }
+        if already_compact && compact_len == blobs_len {
+            debug!("Trie blobs in {} are already compact", &blobs_path);
+            return Ok(0);
+        }
+
+        info!(
+            "Compact {} trie blobs in {} from {} to {} bytes",
+            tries.len(),
+            &blobs_path,
+            blobs_len,
+            compact_len
+        );
+
+        let compact_path = TrieFile::compact_blobs_path(db_path);
+        let mut src = fs::File::open(&blobs_path)?;
+        let mut dest = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&compact_path)?,
+        );
+        let mut new_offsets = Vec::with_capacity(tries.len());
+        let mut new_offset = 0;
+        let mut buf = vec![];
+        for (block_id, offset, length) in tries.into_iter() {
+            buf.resize(length as usize, 0);
+            src.seek(SeekFrom::Start(offset))?;
+            src.read_exact(&mut buf)?;
+            dest.write_all(&buf)?;
+            new_offsets.push((block_id, new_offset));
+            new_offset += length;
+        }
+        let dest = dest.into_inner().map_err(|e| e.into_error())?;
+        dest.sync_all()?;
+
+        let tx = tx_begin_immediate(db)?;
+        for (block_id, offset) in new_offsets.into_iter() {
+            trie_sql::set_external_trie_offset(&tx, block_id, offset)?;
+        }
+        tx.commit()?;
+
+        fs::rename(&compact_path, &blobs_path)?;
+        Ok(blobs_len.saturating_sub(new_offset))
+    }
+
     /// Copy the trie blobs out of a sqlite3 DB into their own file.
     /// NOTE: this is *not* thread-safe.  Do not call while the DB is being used by another thread.
     pub fn export_trie_blobs<T: MarfTrieId>(
@@ -408,6 +523,18 @@ impl TrieFile {
         read_nodetype_at_head_nohash(self, ptr.id())
     }
 
+    /// Read the block hash of a trie's parent, which is stored at the start of the trie's blob
+    pub fn read_trie_parent_hash<T: MarfTrieId>(
+        &mut self,
+        db: &Connection,
+        block_id: u32,
+    ) -> Result<T, Error> {
+        let offset = self.get_trie_offset(db, block_id)?;
+        self.seek(SeekFrom::Start(offset))?;
+        let hash_buff = read_hash_bytes(self)?;
+        Ok(T::from_bytes(hash_buff))
+    }
+
     /// Obtain a TrieHash for a node, given the node's block's hash (used only in testing)
     #[cfg(test)]
     pub fn get_node_hash_bytes_by_bhh<T: MarfTrieId>(
@@ -423,7 +550,6 @@ impl TrieFile {
     }
 
     /// Get all (root hash, trie hash) pairs for this TrieFile
-    #[cfg(test)]
     pub fn read_all_block_hashes_and_roots<T: MarfTrieId>(
         &mut self,
         db: &Connection,
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, HashSet};
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::ops::DerefMut;
 use std::path::PathBuf;
@@ -35,7 +36,8 @@ use crate::chainstate::stacks::index::storage::{
 };
 use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::{
-    ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieHashExtension, TrieLeaf, TrieMerkleProof,
+    trie_sql, ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieHashExtension, TrieLeaf,
+    TrieMerkleProof,
 };
 use crate::util_lib::db::Error as db_error;
 
@@ -306,6 +308,137 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         self.storage.sqlite_tx_mut()
     }
 
+    /// Drop every confirmed trie that is neither within `depth` blocks of the highest trie nor an
+    /// ancestor of one that is.  Tries on the surviving forks are kept in full, since their
+    /// descendants read them through back-pointers and ancestor root hashes.  A dropped trie's
+    /// bytes stay in the blobs file (if any) until `TrieFile::compact_blobs()` runs.
+    ///
+    /// The trie with the highest block ID and the trie stored last in the blobs file are never
+    /// dropped, so that neither a block ID nor a region of the blobs file gets reused while
+    /// other connections to this MARF may still have it cached.
+    ///
+    /// Each trie's parent and height are recorded in the `trie_ancestry` table the first time a
+    /// pass sees it, so a pass only reads the parents of the tries added since the last one.  It
+    /// then walks down from the lowest height it keeps, one height at a time, and stops once it
+    /// reaches a height below which the last pass found every trie reachable.
+    ///
+    /// Returns the block hashes of the dropped tries.  Call commit() to persist the changes.
+    pub fn prune_unreachable_tries(&mut self, depth: u32) -> Result<Vec<T>, Error> {
+        if self.storage.readonly() {
+            return Err(Error::ReadOnlyError);
+        }
+        if self.open_chain_tip.is_some() {
+            return Err(Error::InProgressError);
+        }
+
+        trie_sql::create_trie_ancestry_tables_if_needed(self.storage.sqlite_tx())?;
+
+        // index the tries added since the last pass
+        let new_tries: Vec<(u32, T)> =
+            trie_sql::read_unindexed_confirmed_block_identifiers(self.storage.sqlite_tx())?;
+        let mut parents = HashMap::with_capacity(new_tries.len());
+        for (block_id, block_hash) in new_tries.iter() {
+            let parent = self.storage.read_trie_parent_hash(*block_id)?;
+            parents.insert(block_hash.clone(), parent);
+        }
+
+        // each new trie's distance from the first trie in its chain
+        let mut heights: HashMap<&T, u32> = HashMap::with_capacity(new_tries.len());
+        for (_, block_hash) in new_tries.iter() {
+            let mut unvisited = vec![];
+            let mut cur = block_hash;
+            let mut height = loop {
+                if let Some(height) = heights.get(cur) {
+                    break *height + 1;
+                }
+                let Some(parent) = parents.get(cur) else {
+                    // either an indexed trie, or not a trie, in which case its child is the
+                    // first trie in the chain
+                    break trie_sql::get_trie_height(self.storage.sqlite_tx(), cur)?
+                        .map(|height| height + 1)
+                        .unwrap_or(0);
+                };
+                if unvisited.len() > parents.len() {
+                    return Err(Error::CorruptionError(format!(
+                        "Trie {} has cyclic ancestry",
+                        block_hash
+                    )));
+                }
+                unvisited.push(cur);
+                cur = parent;
+            };
+            for trie in unvisited.into_iter().rev() {
+                heights.insert(trie, height);
+                height += 1;
+            }
+        }
+        for (block_hash, height) in heights.iter() {
+            let parent = parents
+                .get(*block_hash)
+                .expect("FATAL: indexed a trie with no parent");
+            trie_sql::insert_trie_ancestry(self.storage.sqlite_tx(), *block_hash, parent, *height)?;
+        }
+
+        let Some(max_height) = trie_sql::get_max_trie_height(self.storage.sqlite_tx())? else {
+            return Ok(vec![]);
+        };
+        let min_height = max_height.saturating_sub(depth);
+
+        // Below the last pass's floor, every trie had a child one height up.  So once a height
+        // below it (and below every new trie) turns out to be entirely reachable, so is every
+        // height below that.
+        let mut floor = trie_sql::get_trie_prune_floor(self.storage.sqlite_tx())?;
+        if let Some(lowest_new_height) = heights.values().min() {
+            floor = floor.min(*lowest_new_height);
+        }
+
+        let max_block_id = trie_sql::get_max_block_identifier(self.storage.sqlite_tx())?;
+        let last_blob_block_id =
+            trie_sql::get_last_external_trie_block_identifier(self.storage.sqlite_tx())?;
+
+        let mut pruned = vec![];
+        let mut next_floor = min_height;
+        let mut reachable: HashSet<T> =
+            trie_sql::read_tries_at_height(self.storage.sqlite_tx(), min_height)?
+                .into_iter()
+                .map(|(_, _, parent)| parent)
+                .collect();
+        for height in (0..min_height).rev() {
+            let tries: Vec<(u32, T, T)> =
+                trie_sql::read_tries_at_height(self.storage.sqlite_tx(), height)?;
+            let mut next_reachable = HashSet::with_capacity(reachable.len());
+            let mut all_reachable = true;
+            for (block_id, block_hash, parent) in tries.into_iter() {
+                if reachable.contains(&block_hash) {
+                    next_reachable.insert(parent);
+                    continue;
+                }
+                all_reachable = false;
+                if block_id == max_block_id || Some(block_id) == last_blob_block_id {
+                    // kept for now, so the next pass has to look at this height again
+                    next_floor = height;
+                    continue;
+                }
+                trie_sql::drop_confirmed_trie(self.storage.sqlite_tx(), &block_hash)?;
+                pruned.push(block_hash);
+            }
+            if all_reachable && height < floor {
+                break;
+            }
+            reachable = next_reachable;
+        }
+        trie_sql::set_trie_prune_floor(self.storage.sqlite_tx(), next_floor)?;
+
+        debug!(
+            "Pruned {} tries more than {} blocks below height {} ({} newly indexed)",
+            pruned.len(),
+            depth,
+            max_height,
+            new_tries.len()
+        );
+        Ok(pruned)
+    }
+
     /// Reopen this MARF transaction with readonly storage.
     ///   NOTE: any pending operations in the SQLite transaction _will not_
     ///         have materialized in the reopened view.
@@ -14,6 +14,37 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+//! MARF merkle proofs of inclusion.
+//!
+//! The RPC endpoints which take `?proof=1` (e.g. `getmapentry`, `getdatavar`, `getaccount`)
+//! return a hex-encoded `TrieMerkleProof`.  It proves that a MARF key maps to
+//! `MARFValue::from_value(value)` as of a block, where `value` is the hex string the node
+//! stored for that key.  A client can check it with `TrieMerkleProof::verify_entry()` against
+//! the block's `state_index_root` and the `state_index_root -> block ID` mapping of the
+//! block's ancestors, both of which it must obtain from headers it already trusts.
+//!
+//! Wire format (big-endian integers; a vector is a `u32` length followed by its items):
+//!
+//! ```text
+//! proof      := vec(entry)
+//! entry      := type:u8 body
+//!   type 0-3 := node4/node16/node48/node256
+//!               body = chr:u8 node hashes     (3, 15, 47 or 255 sibling hashes)
+//!   type 4   := leaf
+//!               body = chr:u8 path:vec(u8) data:[u8; 40]
+//!   type 5   := shunt
+//!               body = idx:i64 hashes:vec([u8; 32])
+//! node       := id:u8 path:vec(u8) ptrs:vec(ptr)
+//! ptr        := id:u8 chr:u8 back_block:[u8; 32]
+//! ```
+//!
+//! Entries are ordered from the leaf upward.  Each run of node entries is a segment proof,
+//! which hashes the leaf (or the previous segment's root) up to the root of one trie.  Each
+//! run of shunt entries links that root to the root of a later trie via the tries' ancestor
+//! root hashes.  `idx` is the position at which the hash computed so far is inserted into
+//! `hashes` before hashing them together.  A node pointer's `back_block` is the block ID of
+//! the trie it points back into, or all zeros if it is not a back-pointer.
+
 use std::char::from_digit;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
@@ -27,7 +58,7 @@ use stacks_common::codec::{read_next, Error as codec_error, StacksMessageCodec};
 use stacks_common::types::chainstate::{
     BlockHeaderHash, TrieHash, BLOCK_HEADER_HASH_ENCODED_SIZE, TRIEHASH_ENCODED_SIZE,
 };
-use stacks_common::util::hash::to_hex;
+use stacks_common::util::hash::{hex_bytes, to_hex};
 use stacks_common::util::slice_partialeq;
 
 use crate::chainstate::stacks::index::bits::{
@@ -369,6 +400,23 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         to_hex(&marf_proof)
     }
 
+    /// Decode a proof from its hex encoding, with or without a leading `0x`.
+    /// The whole string must be consumed.
+    pub fn from_hex(hex: &str) -> Result<TrieMerkleProof<T>, codec_error> {
+        let hex = hex.strip_prefix("0x").unwrap_or(hex);
+        let bytes = hex_bytes(hex)
+            .map_err(|e| codec_error::DeserializeError(format!("Invalid proof hex: {:?}", &e)))?;
+        let mut cursor = bytes.as_slice();
+        let proof = TrieMerkleProof::consensus_deserialize(&mut cursor)?;
+        if !cursor.is_empty() {
+            return Err(codec_error::DeserializeError(format!(
+                "Trailing {} bytes after proof",
+                cursor.len()
+            )));
+        }
+        Ok(proof)
+    }
+
     fn make_proof_hashes(
         node: &TrieNodeType,
         all_hashes: &Vec<TrieHash>,
@@ -1360,6 +1408,22 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         TrieMerkleProof::<T>::verify_proof(&self.0, &path, &marf_value, root_hash, root_to_block)
     }
 
+    /// Verify that this proof shows `key` mapped to `value` in the trie with root `root_hash`.
+    /// `value` is the string whose hash is stored in the MARF (for Clarity data, the
+    /// hex-serialized value).  `root_to_block` must map the trie roots of the block's ancestors to
+    /// their block IDs, and must come from a trusted source, such as verified block headers.
+    pub fn verify_entry(
+        &self,
+        key: &str,
+        value: &str,
+        root_hash: &TrieHash,
+        root_to_block: &HashMap<TrieHash, T>,
+    ) -> bool {
+        let path = TriePath::from_key(key);
+        let marf_value = MARFValue::from_value(value);
+        self.verify(&path, &marf_value, root_hash, root_to_block)
+    }
+
     /// Walk down the trie pointed to by s until we reach a backptr or a leaf
     fn walk_to_leaf_or_backptr(
         storage: &mut TrieStorageConnection<T>,
@@ -1418,6 +1418,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             trie_sql::create_tables_if_needed(&mut db)?;
         }
 
+        if marf_opts.external_blobs && !readonly {
+            // finish (or discard) an interrupted compaction before opening the blobs file
+            TrieFile::recover_compaction(&db, &db_path)?;
+        }
+
         let mut blobs = if marf_opts.external_blobs {
             Some(TrieFile::from_db_path(&db_path, readonly)?)
         } else {
@@ -1961,6 +1966,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         self.data.readonly
     }
 
+    pub fn sqlite_conn(&self) -> &Connection {
+        &self.db
+    }
+
     pub fn unconfirmed(&self) -> bool {
         self.data.unconfirmed
     }
@@ -2050,17 +2059,73 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         }
     }
 
-    #[cfg(test)]
-    fn inner_read_persisted_root_to_blocks(&mut self) -> Result<HashMap<TrieHash, T>, Error> {
-        let ret = match self.blobs.as_mut() {
+    /// Get all (root hash, block hash) pairs for the confirmed tries in persisted storage
+    pub fn read_all_block_hashes_and_roots(&mut self) -> Result<Vec<(TrieHash, T)>, Error> {
+        match self.blobs.as_mut() {
+            Some(blobs) => blobs.read_all_block_hashes_and_roots(&self.db),
+            None => trie_sql::read_all_block_hashes_and_roots(&self.db),
+        }
+    }
+
+    /// Read a persisted trie's serialized blob in its entirety, given its block ID
+    pub fn read_trie_blob(&mut self, block_id: u32) -> Result<Vec<u8>, Error> {
+        match self.blobs.as_mut() {
+            Some(blobs) => blobs.read_trie_blob(&self.db, block_id),
+            None => TrieFile::read_trie_blob_from_db(&self.db, block_id),
+        }
+    }
+
+    /// Read the block hash of a persisted trie's parent, given the trie's block ID
+    pub fn read_trie_parent_hash(&mut self, block_id: u32) -> Result<T, Error> {
+        match self.blobs.as_mut() {
+            Some(blobs) => blobs.read_trie_parent_hash(&self.db, block_id),
+            None => trie_sql::read_trie_parent_hash(&self.db, block_id),
+        }
+    }
+
+    /// Store a serialized trie blob verbatim under the given block ID.  The blob's back-pointers
+    /// must refer to ancestor tries that were (or will be) stored under their original block IDs.
+    /// Node hashes are not checked; use `Trie::verify_trie_hashes()` once the trie's ancestors
+    /// are present.
+    pub fn write_trie_blob_with_id(
+        &mut self,
+        block_id: u32,
+        bhh: &T,
+        blob: &[u8],
+    ) -> Result<(), Error> {
+        if self.data.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        if blob.len() < (TrieStorageConnection::<T>::root_ptr_disk() as usize) {
+            return Err(Error::CorruptionError(format!(
+                "Trie blob for {} is too short ({} bytes)",
+                bhh,
+                blob.len()
+            )));
+        }
+        match self.blobs.as_mut() {
             Some(blobs) => {
-                HashMap::from_iter(blobs.read_all_block_hashes_and_roots(&self.db)?.into_iter())
+                let offset = blobs.append_trie_blob(&self.db, blob)?;
+                trie_sql::write_external_trie_blob_with_id(
+                    &self.db,
+                    block_id,
+                    bhh,
+                    offset,
+                    blob.len() as u64,
+                )?;
             }
             None => {
-                HashMap::from_iter(trie_sql::read_all_block_hashes_and_roots(&self.db)?.into_iter())
+                trie_sql::write_trie_blob_with_id(&self.db, block_id, bhh, blob)?;
             }
-        };
-        Ok(ret)
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn inner_read_persisted_root_to_blocks(&mut self) -> Result<HashMap<TrieHash, T>, Error> {
+        Ok(HashMap::from_iter(
+            self.read_all_block_hashes_and_roots()?.into_iter(),
+        ))
     }
 
     /// Generate a mapping between Trie root hashes and the blocks that contain them
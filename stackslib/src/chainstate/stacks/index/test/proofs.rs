@@ -197,3 +197,61 @@ fn ncc_verifier_catches_stale_proof() {
     println!("DEBUG: verify(old_v)");
     assert!(!proof_5.verify(&triepath_4, &marf_value_4, &root_hash_5, &root_to_block));
 }
+
+#[test]
+fn proof_hex_round_trip_and_verify_entry() {
+    let marf_opts = MARFOpenOpts::default();
+    let mut m = MARF::from_path(":memory:", marf_opts).unwrap();
+
+    let sentinel_block = BlockHeaderHash::sentinel();
+    let block_0 = BlockHeaderHash([0u8; 32]);
+    let block_1 = BlockHeaderHash([1u8; 32]);
+    let block_2 = BlockHeaderHash([2u8; 32]);
+
+    let k1 = "K1".to_string();
+    let k2 = "K2".to_string();
+    let v1 = "V1".to_string();
+    let v2 = "V2".to_string();
+
+    m.begin(&sentinel_block, &block_0).unwrap();
+    m.commit().unwrap();
+
+    m.begin(&block_0, &block_1).unwrap();
+    m.insert(&k1, MARFValue::from_value(&v1)).unwrap();
+    m.commit().unwrap();
+
+    // k1 is only reachable from block_2 via a back-pointer, so its proof has shunts
+    m.begin(&block_1, &block_2).unwrap();
+    m.insert(&k2, MARFValue::from_value(&v2)).unwrap();
+    m.seal().unwrap();
+    let (_, root_hash_2) = Trie::read_root(&mut m.borrow_storage_backend()).unwrap();
+    m.commit().unwrap();
+
+    let root_to_block = m
+        .borrow_storage_backend()
+        .read_root_to_block_table()
+        .unwrap();
+
+    let proof =
+        TrieMerkleProof::from_entry(&mut m.borrow_storage_backend(), &k1, &v1, &block_2).unwrap();
+    assert!(proof
+        .iter()
+        .any(|entry| matches!(entry, TrieMerkleProofType::Shunt(_))));
+
+    let proof_hex = proof.to_hex();
+    for hex in [proof_hex.clone(), format!("0x{}", &proof_hex)] {
+        let decoded = TrieMerkleProof::<BlockHeaderHash>::from_hex(&hex).unwrap();
+        assert_eq!(decoded.to_hex(), proof_hex);
+        assert!(decoded.verify_entry(&k1, &v1, &root_hash_2, &root_to_block));
+        assert!(!decoded.verify_entry(&k1, &v2, &root_hash_2, &root_to_block));
+        assert!(!decoded.verify_entry(&k2, &v1, &root_hash_2, &root_to_block));
+
+        // the verifier needs to know which blocks the ancestor tries belong to
+        assert!(!decoded.verify_entry(&k1, &v1, &root_hash_2, &HashMap::new()));
+    }
+
+    // malformed encodings
+    assert!(TrieMerkleProof::<BlockHeaderHash>::from_hex("zz").is_err());
+    assert!(TrieMerkleProof::<BlockHeaderHash>::from_hex(&proof_hex[2..]).is_err());
+    assert!(TrieMerkleProof::<BlockHeaderHash>::from_hex(&format!("{}00", &proof_hex)).is_err());
+}
@@ -1029,4 +1029,84 @@ impl Trie {
     ) -> Result<(), Error> {
         Trie::recalculate_root_hash(storage, cursor, false)
     }
+
+    /// Visit every node stored in the currently-open trie, along with its stored hash.  Nodes in
+    /// ancestor tries (i.e. reachable only through back-pointers) are not visited.  The root is
+    /// visited first.
+    pub fn walk_trie_nodes<T: MarfTrieId, F>(
+        storage: &mut TrieStorageConnection<T>,
+        mut visit: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&mut TrieStorageConnection<T>, &TriePtr, &TrieNodeType, TrieHash) -> Result<(), Error>,
+    {
+        let (root, root_hash) = Trie::read_root(storage)?;
+        let mut nodes = vec![(storage.root_trieptr(), root, root_hash)];
+        while let Some((ptr, node, node_hash)) = nodes.pop() {
+            for child_ptr in node.ptrs().iter() {
+                if child_ptr.id() == TrieNodeID::Empty as u8 || is_backptr(child_ptr.id()) {
+                    continue;
+                }
+                let (child, child_hash) = storage.read_nodetype(child_ptr)?;
+                nodes.push((child_ptr.clone(), child, child_hash));
+            }
+            visit(storage, &ptr, &node, node_hash)?;
+        }
+        Ok(())
+    }
+
+    /// Recompute the hash of every node in the currently-open trie from the node's contents and
+    /// its children's hashes, and check it against the hash stored alongside the node.  The root
+    /// node's hash additionally mixes in the root hashes of the trie's ancestors, so those tries
+    /// must already be present (and should have been verified first).
+    ///
+    /// Returns the recomputed root hash on success, and a `CorruptionError` naming the first
+    /// node whose stored hash does not match.  `storage` must not be in deferred hashing mode.
+    pub fn verify_trie_hashes<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+    ) -> Result<TrieHash, Error> {
+        if storage.hash_calculation_mode == TrieHashCalculationMode::Deferred {
+            return Err(Error::CorruptionError(
+                "Cannot verify trie hashes in deferred hashing mode".to_string(),
+            ));
+        }
+
+        let root_ptr = storage.root_trieptr();
+        let mut root_hashes = None;
+        Trie::walk_trie_nodes(storage, |storage, ptr, node, stored_hash| {
+            let node_hash = match node {
+                TrieNodeType::Leaf(ref leaf) => get_leaf_hash(leaf),
+                _ => get_nodetype_hash(storage, node)?,
+            };
+            if *ptr == root_ptr {
+                // finding the ancestor tries means reading this trie's leaves, so only mix them in
+                // once every other node is known to be intact.
+                root_hashes = Some((node_hash, stored_hash));
+                return Ok(());
+            }
+            if node_hash != stored_hash {
+                return Err(Error::CorruptionError(format!(
+                    "Node {:?} in trie {} is stored with hash {}, but hashes to {}",
+                    ptr,
+                    &storage.get_cur_block(),
+                    &stored_hash,
+                    &node_hash
+                )));
+            }
+            Ok(())
+        })?;
+
+        let (root_node_hash, stored_hash) = root_hashes
+            .ok_or_else(|| Error::CorruptionError("Trie has no root node".to_string()))?;
+        let root_hash = Trie::get_trie_root_hash(storage, &root_node_hash)?;
+        if root_hash != stored_hash {
+            return Err(Error::CorruptionError(format!(
+                "Root of trie {} is stored with hash {}, but hashes to {}",
+                &storage.get_cur_block(),
+                &stored_hash,
+                &root_hash
+            )));
+        }
+        Ok(root_hash)
+    }
 }
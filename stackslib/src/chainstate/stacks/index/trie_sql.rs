@@ -94,6 +94,22 @@ INSERT OR REPLACE INTO schema_version (version) VALUES (2);
 INSERT OR REPLACE INTO migrated_version (version) VALUES (1);
 ";
 
+static SQL_TRIE_ANCESTRY_TABLES: &str = "
+-- each confirmed trie's parent, and its distance from the first trie in its chain.
+-- maintained by MARF::prune_unreachable_tries(), so it only covers the tries it has seen.
+CREATE TABLE IF NOT EXISTS trie_ancestry (
+   block_hash TEXT PRIMARY KEY,
+   parent_hash TEXT NOT NULL,
+   height INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS height_trie_ancestry ON trie_ancestry(height);
+
+-- the height below which the last pruning pass found every trie reachable
+CREATE TABLE IF NOT EXISTS trie_prune_floor (
+   height INTEGER NOT NULL
+);
+";
+
 pub static SQL_MARF_SCHEMA_VERSION: u64 = 2;
 
 pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
@@ -102,10 +118,17 @@ pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
     tx.execute_batch(SQL_MARF_DATA_TABLE)?;
     tx.execute_batch(SQL_MARF_MINED_TABLE)?;
     tx.execute_batch(SQL_EXTENSION_LOCKS_TABLE)?;
+    tx.execute_batch(SQL_TRIE_ANCESTRY_TABLES)?;
 
     tx.commit().map_err(|e| e.into())
 }
 
+/// Create the tables which track trie ancestry for pruning, if this MARF predates them
+pub fn create_trie_ancestry_tables_if_needed(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch(SQL_TRIE_ANCESTRY_TABLES)?;
+    Ok(())
+}
+
 fn get_schema_version(conn: &Connection) -> u64 {
     // if the table doesn't exist, then the version is 1.
     let sql = "SELECT version FROM schema_version";
@@ -307,6 +330,52 @@ fn inner_write_external_trie_blob<T: MarfTrieId>(
     Ok(block_id)
 }
 
+/// Write a serialized trie to sqlite under a caller-chosen block ID, instead of the next free row
+/// ID.  Used when importing tries, since their back-pointers refer to ancestor tries by block ID.
+pub fn write_trie_blob_with_id<T: MarfTrieId>(
+    conn: &Connection,
+    block_id: u32,
+    block_hash: &T,
+    data: &[u8],
+) -> Result<u32, Error> {
+    let args: &[&dyn ToSql] = &[&block_id, block_hash, &data, &0, &0, &0];
+    let mut s =
+        conn.prepare("INSERT INTO marf_data (block_id, block_hash, data, unconfirmed, external_offset, external_length) VALUES (?, ?, ?, ?, ?, ?)")?;
+    s.execute(args)?;
+
+    debug!("Wrote block trie {} to rowid {}", block_hash, block_id);
+    Ok(block_id)
+}
+
+/// Add a row for an external trie blob under a caller-chosen block ID.  See
+/// `write_trie_blob_with_id()`.
+pub fn write_external_trie_blob_with_id<T: MarfTrieId>(
+    conn: &Connection,
+    block_id: u32,
+    block_hash: &T,
+    offset: u64,
+    length: u64,
+) -> Result<u32, Error> {
+    let empty_blob: &[u8] = &[];
+    let args: &[&dyn ToSql] = &[
+        &block_id,
+        block_hash,
+        &empty_blob,
+        &0,
+        &u64_to_sql(offset)?,
+        &u64_to_sql(length)?,
+    ];
+    let mut s =
+        conn.prepare("INSERT INTO marf_data (block_id, block_hash, data, unconfirmed, external_offset, external_length) VALUES (?, ?, ?, ?, ?, ?)")?;
+    s.execute(args)?;
+
+    debug!(
+        "Wrote block trie {} to rowid {} offset {}",
+        block_hash, block_id, offset
+    );
+    Ok(block_id)
+}
+
 /// Update the row for an external trie blob -- i.e. we're migrating blobs from sqlite storage to
 /// file storage.
 pub fn update_external_trie_blob<T: MarfTrieId>(
@@ -419,7 +488,7 @@ pub fn open_trie_blob_readonly<'a>(conn: &'a Connection, block_id: u32) -> Resul
     Ok(blob)
 }
 
-#[cfg(test)]
+/// Get all (root hash, block hash) pairs for tries stored in the DB (i.e. not in a blobs file)
 pub fn read_all_block_hashes_and_roots<T: MarfTrieId>(
     conn: &Connection,
 ) -> Result<Vec<(TrieHash, T)>, Error> {
@@ -542,6 +611,157 @@ pub fn get_external_blobs_length(conn: &Connection) -> Result<u64, Error> {
     Ok(max_len)
 }
 
+/// Get the block ID of the trie stored last in the blobs file, if there are any.
+pub fn get_last_external_trie_block_identifier(conn: &Connection) -> Result<Option<u32>, Error> {
+    let qry = "SELECT block_id FROM marf_data ORDER BY external_offset DESC LIMIT 1";
+    let block_id = query_row(conn, qry, NO_PARAMS)?;
+    Ok(block_id)
+}
+
+/// Get the (block ID, offset, length) of every trie stored in the blobs file, in file order.
+pub fn read_external_trie_offsets_lengths(
+    conn: &Connection,
+) -> Result<Vec<(u32, u64, u64)>, Error> {
+    let mut s = conn.prepare("SELECT block_id, external_offset, external_length FROM marf_data WHERE external_length > 0 ORDER BY external_offset")?;
+    let rows = s.query_and_then(NO_PARAMS, |row| {
+        let block_id: u32 = row.get_unwrap("block_id");
+        let offset_i64: i64 = row.get_unwrap("external_offset");
+        let length_i64: i64 = row.get_unwrap("external_length");
+        Ok((block_id, offset_i64 as u64, length_i64 as u64))
+    })?;
+    rows.collect()
+}
+
+/// Move an external trie blob to a new offset in the blobs file (i.e. when compacting it)
+pub fn set_external_trie_offset(
+    conn: &Connection,
+    block_id: u32,
+    offset: u64,
+) -> Result<(), Error> {
+    let args: &[&dyn ToSql] = &[&u64_to_sql(offset)?, &block_id];
+    conn.execute(
+        "UPDATE marf_data SET external_offset = ?1 WHERE block_id = ?2",
+        args,
+    )?;
+    Ok(())
+}
+
+/// Read the block hash of a sqlite-stored trie's parent, given the trie's block ID
+pub fn read_trie_parent_hash<T: MarfTrieId>(conn: &Connection, block_id: u32) -> Result<T, Error> {
+    let mut blob = open_trie_blob_readonly(conn, block_id)?;
+    let hash_buff = read_hash_bytes(&mut blob)?;
+    Ok(T::from_bytes(hash_buff))
+}
+
+/// Get the (block ID, block hash) of every confirmed trie
+pub fn read_confirmed_block_identifiers<T: MarfTrieId>(
+    conn: &Connection,
+) -> Result<Vec<(u32, T)>, Error> {
+    let mut s = conn.prepare(
+        "SELECT block_id, block_hash FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id",
+    )?;
+    let rows = s.query_and_then(NO_PARAMS, |row| {
+        let block_id: u32 = row.get_unwrap("block_id");
+        let block_hash: T = row.get_unwrap("block_hash");
+        Ok((block_id, block_hash))
+    })?;
+    rows.collect()
+}
+
+/// Get the (block ID, block hash) of every confirmed trie which is not in the `trie_ancestry`
+/// table yet
+pub fn read_unindexed_confirmed_block_identifiers<T: MarfTrieId>(
+    conn: &Connection,
+) -> Result<Vec<(u32, T)>, Error> {
+    let mut s = conn.prepare(
+        "SELECT block_id, block_hash FROM marf_data WHERE unconfirmed = 0 \
+         AND block_hash NOT IN (SELECT block_hash FROM trie_ancestry) ORDER BY block_id",
+    )?;
+    let rows = s.query_and_then(NO_PARAMS, |row| {
+        let block_id: u32 = row.get_unwrap("block_id");
+        let block_hash: T = row.get_unwrap("block_hash");
+        Ok((block_id, block_hash))
+    })?;
+    rows.collect()
+}
+
+/// Get the height of a trie in the `trie_ancestry` table, if it is there
+pub fn get_trie_height<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<Option<u32>, Error> {
+    let qry = "SELECT height FROM trie_ancestry WHERE block_hash = ?1";
+    let height = query_row(conn, qry, &[bhh])?;
+    Ok(height)
+}
+
+/// Record a confirmed trie's parent and height in the `trie_ancestry` table
+pub fn insert_trie_ancestry<T: MarfTrieId>(
+    conn: &Connection,
+    bhh: &T,
+    parent: &T,
+    height: u32,
+) -> Result<(), Error> {
+    let args: &[&dyn ToSql] = &[bhh, parent, &height];
+    conn.execute(
+        "INSERT OR REPLACE INTO trie_ancestry (block_hash, parent_hash, height) VALUES (?1, ?2, ?3)",
+        args,
+    )?;
+    Ok(())
+}
+
+/// Get the greatest height in the `trie_ancestry` table, if it has any rows
+pub fn get_max_trie_height(conn: &Connection) -> Result<Option<u32>, Error> {
+    let height = conn.query_row("SELECT MAX(height) FROM trie_ancestry", NO_PARAMS, |row| {
+        row.get(0)
+    })?;
+    Ok(height)
+}
+
+/// Get the (block ID, block hash, parent block hash) of every confirmed trie at the given height
+/// in the `trie_ancestry` table
+pub fn read_tries_at_height<T: MarfTrieId>(
+    conn: &Connection,
+    height: u32,
+) -> Result<Vec<(u32, T, T)>, Error> {
+    let mut s = conn.prepare(
+        "SELECT marf_data.block_id, trie_ancestry.block_hash, trie_ancestry.parent_hash \
+         FROM trie_ancestry JOIN marf_data ON trie_ancestry.block_hash = marf_data.block_hash \
+         WHERE trie_ancestry.height = ?1 AND marf_data.unconfirmed = 0 ORDER BY marf_data.block_id",
+    )?;
+    let rows = s.query_and_then(&[&height], |row| {
+        let block_id: u32 = row.get_unwrap("block_id");
+        let block_hash: T = row.get_unwrap("block_hash");
+        let parent_hash: T = row.get_unwrap("parent_hash");
+        Ok((block_id, block_hash, parent_hash))
+    })?;
+    rows.collect()
+}
+
+/// Get the height below which the last pruning pass found every trie reachable (0 if there was
+/// no such pass)
+pub fn get_trie_prune_floor(conn: &Connection) -> Result<u32, Error> {
+    let height = query_row(conn, "SELECT height FROM trie_prune_floor", NO_PARAMS)?;
+    Ok(height.unwrap_or(0))
+}
+
+/// Set the height below which the last pruning pass found every trie reachable
+pub fn set_trie_prune_floor(conn: &Connection, height: u32) -> Result<(), Error> {
+    conn.execute("DELETE FROM trie_prune_floor", NO_PARAMS)?;
+    conn.execute(
+        "INSERT INTO trie_prune_floor (height) VALUES (?1)",
+        &[&height],
+    )?;
+    Ok(())
+}
+
+/// Get the highest block ID in use, confirmed or not
+pub fn get_max_block_identifier(conn: &Connection) -> Result<u32, Error> {
+    let result = conn.query_row(
+        "SELECT IFNULL(MAX(block_id), 0) AS max_id FROM marf_data",
+        NO_PARAMS,
+        |row| row.get("max_id"),
+    )?;
+    Ok(result)
+}
+
 /// Do we have a partially-migrated database?
 /// Either all tries have offset and length 0, or they all don't.  If we have a mixture, then we're
 /// corrupted.
@@ -697,6 +917,18 @@ pub fn drop_unconfirmed_trie<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Resul
     Ok(())
 }
 
+/// Delete a confirmed trie's row.  If the trie was stored in a blobs file, its bytes stay there
+/// until the file is compacted.
+pub fn drop_confirmed_trie<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<(), Error> {
+    debug!("Drop confirmed trie {}", bhh);
+    conn.execute(
+        "DELETE FROM marf_data WHERE block_hash = ? AND unconfirmed = 0",
+        &[bhh],
+    )?;
+    conn.execute("DELETE FROM trie_ancestry WHERE block_hash = ?", &[bhh])?;
+    Ok(())
+}
+
 pub fn clear_lock_data(conn: &Connection) -> Result<(), Error> {
     conn.execute("DELETE FROM block_extension_locks", NO_PARAMS)?;
     Ok(())
@@ -706,5 +938,8 @@ pub fn clear_tables(tx: &Transaction) -> Result<(), Error> {
     tx.execute("DELETE FROM block_extension_locks", NO_PARAMS)?;
     tx.execute("DELETE FROM marf_data", NO_PARAMS)?;
     tx.execute("DELETE FROM mined_blocks", NO_PARAMS)?;
+    tx.execute_batch(SQL_TRIE_ANCESTRY_TABLES)?;
+    tx.execute("DELETE FROM trie_ancestry", NO_PARAMS)?;
+    tx.execute("DELETE FROM trie_prune_floor", NO_PARAMS)?;
     Ok(())
 }
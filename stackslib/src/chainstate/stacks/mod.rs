@@ -99,6 +99,8 @@ pub enum Error {
     StacksTransactionSkipped(String),
     PostConditionFailed(String),
     NoSuchBlockError,
+    /// The requested block data existed, but was deleted by chainstate pruning
+    PrunedError,
     InvalidChainstateDB,
     BlockTooBigError,
     TransactionTooBigError,
@@ -163,6 +165,7 @@ impl fmt::Display for Error {
             Error::InvalidStacksTransaction(ref s, _) => fmt::Display::fmt(s, f),
             Error::PostConditionFailed(ref s) => fmt::Display::fmt(s, f),
             Error::NoSuchBlockError => write!(f, "No such Stacks block"),
+            Error::PrunedError => write!(f, "Stacks block data has been pruned"),
             Error::InvalidChainstateDB => write!(f, "Invalid chainstate database"),
             Error::BlockTooBigError => write!(f, "Too much data in block"),
             Error::TransactionTooBigError => write!(f, "Too much data in transaction"),
@@ -238,6 +241,7 @@ impl error::Error for Error {
             Error::InvalidStacksTransaction(ref _s, _q) => None,
             Error::PostConditionFailed(ref _s) => None,
             Error::NoSuchBlockError => None,
+            Error::PrunedError => None,
             Error::InvalidChainstateDB => None,
             Error::BlockTooBigError => None,
             Error::TransactionTooBigError => None,
@@ -282,6 +286,7 @@ impl Error {
             Error::InvalidStacksTransaction(ref _s, _q) => "InvalidStacksTransaction",
             Error::PostConditionFailed(ref _s) => "PostConditionFailed",
             Error::NoSuchBlockError => "NoSuchBlockError",
+            Error::PrunedError => "PrunedError",
             Error::InvalidChainstateDB => "InvalidChainstateDB",
             Error::BlockTooBigError => "BlockTooBigError",
             Error::TransactionTooBigError => "TransactionTooBigError",
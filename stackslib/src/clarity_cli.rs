@@ -14,13 +14,19 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cell::RefCell;
 use std::ffi::OsStr;
 use std::io::{Read, Write};
+use std::net::ToSocketAddrs;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::str::FromStr;
 use std::{env, fs, io, process};
 
+use clarity::vm::ast::formatter::{format_source, is_equivalent};
 use clarity::vm::coverage::CoverageReporter;
+use clarity::vm::debugger::Debugger;
+use clarity::vm::profiler::ExecutionProfiler;
 use lazy_static::lazy_static;
 use rand::Rng;
 use rusqlite::types::ToSql;
@@ -45,24 +51,39 @@ use crate::chainstate::stacks::boot::{
 };
 use crate::chainstate::stacks::index::storage::TrieFileStorage;
 use crate::chainstate::stacks::index::{ClarityMarfTrieId, MarfTrieId};
-use crate::clarity::vm::analysis::contract_interface_builder::build_contract_interface;
+use crate::clarity::vm::analysis::contract_interface_builder::diff::diff_contract_analyses;
+use crate::clarity::vm::analysis::contract_interface_builder::{
+    build_contract_interface, ContractInterface,
+};
+use crate::clarity::vm::analysis::cost_checker::CostChecker;
 use crate::clarity::vm::analysis::errors::{CheckError, CheckResult};
-use crate::clarity::vm::analysis::{AnalysisDatabase, ContractAnalysis};
-use crate::clarity::vm::ast::{build_ast_with_rules, ASTRules};
-use crate::clarity::vm::contexts::{AssetMap, GlobalContext, OwnedEnvironment};
+use crate::clarity::vm::analysis::linter::{LintConfig, Linter};
+use crate::clarity::vm::analysis::{AnalysisDatabase, AnalysisPass, ContractAnalysis};
+use crate::clarity::vm::ast::{build_ast_with_diagnostics, build_ast_with_rules, ASTRules};
+use crate::clarity::vm::contexts::{
+    AssetMap, Environment, GlobalContext, LocalContext, OwnedEnvironment,
+};
 use crate::clarity::vm::costs::{ExecutionCost, LimitedCostTracker};
 use crate::clarity::vm::database::{
     BurnStateDB, ClarityDatabase, HeadersDB, STXBalance, SqliteConnection, NULL_BURN_STATE_DB,
 };
+use crate::clarity::vm::diagnostic::{Diagnostic, Level};
 use crate::clarity::vm::errors::{Error, InterpreterResult, RuntimeErrorType};
-use crate::clarity::vm::types::{OptionalData, PrincipalData, QualifiedContractIdentifier};
+use crate::clarity::vm::functions::define::DefineFunctionsParsed;
+use crate::clarity::vm::representations::Span;
+use crate::clarity::vm::types::{
+    OptionalData, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData,
+};
 use crate::clarity::vm::{
-    analysis, ast, eval_all, ClarityVersion, ContractContext, ContractName, SymbolicExpression,
-    SymbolicExpressionType, Value,
+    analysis, ast, eval_all, ClarityVersion, ContractContext, ContractName, EvalHook,
+    ExecutionResult, SymbolicExpression, SymbolicExpressionType, Value,
 };
+use crate::clarity_vm::bindgen::{generate_bindings, parse_event_type, BindingOptions};
 use crate::clarity_vm::database::marf::{MarfedKV, WritableMarfStore};
+use crate::clarity_vm::database::remote::{block_info_height, RemoteClarityNode, RemoteForkStore};
 use crate::clarity_vm::database::MemoryBackingStore;
 use crate::core::{StacksEpochId, BLOCK_LIMIT_MAINNET_205, HELIUM_BLOCK_LIMIT_20};
+use crate::net::api::getclarityblockinfo::ClarityBlockInfoResponse;
 use crate::util_lib::boot::{boot_code_addr, boot_code_id};
 use crate::util_lib::db::{sqlite_open, FromColumn};
 use crate::util_lib::strings::StacksString;
@@ -122,6 +143,29 @@ where command is one of:
   repl               to typecheck and evaluate expressions in a stdin/stdout loop.
   execute            to execute a public function of a defined contract.
   generate_address   to generate a random Stacks public address for testing purposes.
+  abi-diff           to compare the interfaces of two versions of a contract, and report the
+                     changes which break callers of the old version.
+  bindgen            to generate typed Rust bindings from a contract interface, as output by
+                     `check --output_analysis`.
+  fmt                to format programs in the canonical Clarity layout (with --check, to
+                     only check whether they are formatted).
+  test               to deploy a directory of contracts into a fresh state database, and run their
+                     public `test-` functions, with JUnit XML and lcov coverage reports.
+
+Pass --debug to `eval`, `execute` or `repl` to step through the evaluation interactively,
+reading debugger commands from stdin. Pass --profile <prefix> to `execute` to write the costs
+of each function and expression to <prefix>.json, and folded stacks for flamegraph tools to
+<prefix>.<cost-dimension>.folded.
+
+Pass --format=json to `check` to report its findings as a list of diagnostics, each with a
+stable code and its position. The parser recovers from errors, so every parse error is reported;
+the type checker stops at its first error, so at most one type-checking error is reported,
+and lint warnings only when type checking succeeds.
+
+Pass --fork host:port to `initialize` to fork the state of the chain served by a node at its
+chain tip (or at the block given by --at index-block-hash) instead of installing the boot code.
+`eval`, `eval_at_chaintip`, `check`, `launch` and `execute` then read the forked state lazily
+over the node's RPC interface, and keep their own writes in the local state database.
 ",
         invoked_by
     );
@@ -147,7 +191,6 @@ fn friendly_expect_opt<A>(input: Option<A>, msg: &str) -> A {
 pub const DEFAULT_CLI_EPOCH: StacksEpochId = StacksEpochId::Epoch25;
 
 struct EvalInput {
-    marf_kv: MarfedKV,
     contract_identifier: QualifiedContractIdentifier,
     content: String,
 }
@@ -192,6 +235,20 @@ impl ClarityStorage for WritableMarfStore<'_> {
     }
 }
 
+impl ClarityStorage for RemoteForkStore {
+    fn get_clarity_db<'a>(
+        &'a mut self,
+        headers_db: &'a dyn HeadersDB,
+        burn_db: &'a dyn BurnStateDB,
+    ) -> ClarityDatabase<'a> {
+        self.as_clarity_db(headers_db, burn_db)
+    }
+
+    fn get_analysis_db<'a>(&'a mut self) -> AnalysisDatabase<'a> {
+        self.as_analysis_db()
+    }
+}
+
 impl ClarityStorage for MemoryBackingStore {
     fn get_clarity_db<'a>(
         &'a mut self,
@@ -206,7 +263,7 @@ impl ClarityStorage for MemoryBackingStore {
     }
 }
 
-fn run_analysis_free<C: ClarityStorage>(
+fn run_analysis_free<C: ClarityStorage + ?Sized>(
     contract_identifier: &QualifiedContractIdentifier,
     expressions: &mut [SymbolicExpression],
     marf_kv: &mut C,
@@ -226,12 +283,13 @@ fn run_analysis_free<C: ClarityStorage>(
     )
 }
 
-fn run_analysis<C: ClarityStorage>(
+fn run_analysis<C: ClarityStorage + ?Sized>(
     contract_identifier: &QualifiedContractIdentifier,
     expressions: &mut [SymbolicExpression],
     header_db: &CLIHeadersDB,
     marf_kv: &mut C,
     save_contract: bool,
+    build_type_map: bool,
 ) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
     let mainnet = header_db.is_mainnet();
     let clarity_version = ClarityVersion::default_for_epoch(DEFAULT_CLI_EPOCH);
@@ -255,8 +313,96 @@ fn run_analysis<C: ClarityStorage>(
         cost_track,
         DEFAULT_CLI_EPOCH,
         clarity_version,
-        // no type map data is used in the clarity_cli
-        false,
+        // type map data is only used for static cost analysis and linting
+        build_type_map,
+    )
+}
+
+/// Compute upper bounds on the costs of the public and read-only functions of a contract, which
+/// must have been analyzed with a type map, using the analysis's cost tracker.
+fn run_cost_checker<C: ClarityStorage + ?Sized>(
+    contract_analysis: &mut ContractAnalysis,
+    marf_kv: &mut C,
+) -> Result<(), (CheckError, LimitedCostTracker)> {
+    CostChecker::run_pass(
+        &DEFAULT_CLI_EPOCH,
+        contract_analysis,
+        &mut marf_kv.get_analysis_db(),
+    )
+    .map_err(|e| (e, contract_analysis.take_contract_cost_tracker()))
+}
+
+/// Type-check a contract source file for `abi-diff`, against the state in `vm_filename` if
+/// given, so that it may call deployed contracts.
+fn analyze_contract_file(
+    contract_identifier: &QualifiedContractIdentifier,
+    path: &str,
+    vm_filename: Option<&str>,
+    mainnet: bool,
+) -> Result<ContractAnalysis, CheckError> {
+    let content = friendly_expect(
+        fs::read_to_string(path),
+        &format!("Error reading file: {}", path),
+    );
+    let mut ast = friendly_expect(
+        parse(contract_identifier, &content, ClarityVersion::Clarity2),
+        &format!("Failed to parse program: {}", path),
+    );
+    let result = match vm_filename {
+        Some(vm_filename) => {
+            let header_db =
+                friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
+            let marf_kv = friendly_expect(
+                MarfedKV::open(vm_filename, None, None),
+                "Failed to open VM database.",
+            );
+            at_chaintip(vm_filename, marf_kv, |mut marf| {
+                let result = run_analysis(
+                    contract_identifier,
+                    &mut ast,
+                    &header_db,
+                    &mut marf,
+                    false,
+                    false,
+                );
+                (marf, result)
+            })
+        }
+        None => {
+            let header_db = CLIHeadersDB::new_memory(mainnet);
+            let mut analysis_marf = MemoryBackingStore::new();
+            install_boot_code(&header_db, &mut analysis_marf);
+            run_analysis(
+                contract_identifier,
+                &mut ast,
+                &header_db,
+                &mut analysis_marf,
+                false,
+                false,
+            )
+        }
+    };
+    result.map_err(|(e, _)| e)
+}
+
+/// Load the analysis of a contract deployed in the state in `vm_filename`
+fn load_deployed_analysis(
+    contract_identifier: &QualifiedContractIdentifier,
+    vm_filename: &str,
+) -> ContractAnalysis {
+    let marf_kv = friendly_expect(
+        MarfedKV::open(vm_filename, None, None),
+        "Failed to open VM database.",
+    );
+    let analysis = at_chaintip(vm_filename, marf_kv, |mut marf| {
+        let result = marf
+            .get_analysis_db()
+            .execute(|db| db.load_contract(contract_identifier, &DEFAULT_CLI_EPOCH));
+        (marf, result)
+    });
+    friendly_expect_opt(
+        friendly_expect(analysis, "Failed to load contract analysis"),
+        &format!("No such contract: {}", contract_identifier),
     )
 }
 
@@ -403,6 +549,60 @@ where
     result
 }
 
+// like in_block, but for either kind of CLI DB: one with a local MARF, or one which forks a
+// remote chain (see `initialize --fork`).
+fn in_cli_block<F, R>(mut headers_db: CLIHeadersDB, f: F) -> (CLIHeadersDB, R)
+where
+    F: FnOnce(&CLIHeadersDB, &mut dyn ClarityStorage) -> R,
+{
+    if headers_db.is_fork() {
+        let mut fork_store = headers_db.open_fork_store();
+        let (from, to) = headers_db.advance_cli_chain_tip();
+        friendly_expect(fork_store.begin(&from, &to), "FATAL: failed to begin block");
+        let result = f(&headers_db, &mut fork_store);
+        friendly_expect(fork_store.commit(), "FATAL: failed to commit block");
+        (headers_db, result)
+    } else {
+        let marf_kv = friendly_expect(
+            MarfedKV::open(&headers_db.db_path, None, None),
+            "Failed to open VM database.",
+        );
+        let (headers_db, _, result) = in_block(headers_db, marf_kv, |headers_db, mut marf| {
+            let result = f(&headers_db, &mut marf);
+            (headers_db, marf, result)
+        });
+        (headers_db, result)
+    }
+}
+
+// like at_chaintip, but for either kind of CLI DB.
+fn at_cli_chaintip<F, R>(headers_db: &CLIHeadersDB, f: F) -> R
+where
+    F: FnOnce(&mut dyn ClarityStorage) -> R,
+{
+    if headers_db.is_fork() {
+        let mut fork_store = headers_db.open_fork_store();
+        let from = get_cli_chain_tip(headers_db.conn());
+        let to = StacksBlockId([2u8; 32]); // 0x0202020202 ... (pattern not used anywhere else)
+        friendly_expect(fork_store.begin(&from, &to), "FATAL: failed to begin block");
+        let result = f(&mut fork_store);
+        friendly_expect(
+            fork_store.rollback_block(),
+            "FATAL: failed to roll back block",
+        );
+        result
+    } else {
+        let marf_kv = friendly_expect(
+            MarfedKV::open(&headers_db.db_path, None, None),
+            "Failed to open VM database.",
+        );
+        at_chaintip(&headers_db.db_path, marf_kv, |mut marf| {
+            let result = f(&mut marf);
+            (marf, result)
+        })
+    }
+}
+
 fn default_chain_id(mainnet: bool) -> u32 {
     let chain_id = if mainnet {
         CHAIN_ID_MAINNET
@@ -412,17 +612,49 @@ fn default_chain_id(mainnet: bool) -> u32 {
     chain_id
 }
 
-fn with_env_costs<F, R>(
+/// A debugger which is shared between the REPL, which restarts it before each input, and the
+/// environment which evaluates the inputs
+struct SharedDebugger(Rc<RefCell<Debugger>>);
+
+impl EvalHook for SharedDebugger {
+    fn will_begin_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+    ) {
+        self.0.borrow_mut().will_begin_eval(env, context, expr)
+    }
+
+    fn did_finish_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+        res: &Result<Value, Error>,
+    ) {
+        self.0.borrow_mut().did_finish_eval(env, context, expr, res)
+    }
+
+    fn did_complete(&mut self, result: Result<&mut ExecutionResult, String>) {
+        self.0.borrow_mut().did_complete(result)
+    }
+}
+
+fn with_env_costs<C, F, R>(
     mainnet: bool,
     header_db: &CLIHeadersDB,
-    marf: &mut WritableMarfStore,
+    marf: &mut C,
     coverage: Option<&mut CoverageReporter>,
+    debugger: Option<&mut Debugger>,
+    profiler: Option<&mut ExecutionProfiler>,
     f: F,
 ) -> (R, ExecutionCost)
 where
+    C: ClarityStorage + ?Sized,
     F: FnOnce(&mut OwnedEnvironment) -> R,
 {
-    let mut db = marf.as_clarity_db(header_db, &NULL_BURN_STATE_DB);
+    let mut db = marf.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
     let cost_track = LimitedCostTracker::new(
         mainnet,
         default_chain_id(mainnet),
@@ -445,6 +677,12 @@ where
     if let Some(coverage) = coverage {
         vm_env.add_eval_hook(coverage);
     }
+    if let Some(debugger) = debugger {
+        vm_env.add_eval_hook(debugger);
+    }
+    if let Some(profiler) = profiler {
+        vm_env.add_eval_hook(profiler);
+    }
     let result = f(&mut vm_env);
     let cost = vm_env.get_cost_total();
     (result, cost)
@@ -499,9 +737,465 @@ fn save_coverage(
     }
 }
 
+fn save_profile(profile_prefix: Option<String>, profiler: Option<ExecutionProfiler>) {
+    if let (Some(profile_prefix), Some(profiler)) = (profile_prefix, profiler) {
+        friendly_expect(
+            profiler.write_files(&profile_prefix),
+            &format!("Failed to write profile to {}.*", profile_prefix),
+        );
+    }
+}
+
+/// uSTX credited to the sender of the `test` command's transactions
+const TEST_SENDER_BALANCE: u64 = 1_000_000_000_000_000;
+
+/// The outcome of deploying a contract, or of running one of its test functions
+struct TestOutcome {
+    /// The contract's identifier, or `<sender>.<file-name>` if its file name is not a valid
+    /// contract name
+    contract: String,
+    /// The test function, or `None` for the contract's deployment
+    test: Option<String>,
+    /// The value returned by the test function
+    output: Option<Value>,
+    error: Option<String>,
+    cost: ExecutionCost,
+    duration_ms: u64,
+}
+
+impl TestOutcome {
+    fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut result = json!({
+            "contract": self.contract,
+            "success": self.is_success(),
+            "costs": self.cost,
+            "duration_ms": self.duration_ms,
+        });
+        if let Some(ref test) = self.test {
+            result["test"] = json!(test);
+        }
+        if let Some(ref output) = self.output {
+            result["output"] = serde_json::to_value(output).unwrap();
+        }
+        if let Some(ref error) = self.error {
+            result["error"] = json!(error);
+        }
+        result
+    }
+}
+
+fn xml_escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Render a JUnit XML report with one test suite per contract. A failed deployment is reported
+/// as a `deploy` test case of its contract's suite.
+fn junit_report<'a>(outcomes: impl Iterator<Item = &'a TestOutcome>) -> String {
+    let mut suites: Vec<(&str, Vec<&TestOutcome>)> = vec![];
+    for outcome in outcomes {
+        if outcome.test.is_none() && outcome.is_success() {
+            continue;
+        }
+        match suites
+            .iter_mut()
+            .find(|(contract, _)| *contract == outcome.contract)
+        {
+            Some((_, cases)) => cases.push(outcome),
+            None => suites.push((&outcome.contract, vec![outcome])),
+        }
+    }
+
+    let count_failures =
+        |cases: &[&TestOutcome]| cases.iter().filter(|case| !case.is_success()).count();
+    let total_tests: usize = suites.iter().map(|(_, cases)| cases.len()).sum();
+    let total_failures: usize = suites.iter().map(|(_, cases)| count_failures(cases)).sum();
+
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    report.push_str(&format!(
+        "<testsuites name=\"clarity\" tests=\"{}\" failures=\"{}\">\n",
+        total_tests, total_failures
+    ));
+    for (contract, cases) in suites.iter() {
+        let contract = xml_escape(contract);
+        report.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            contract,
+            cases.len(),
+            count_failures(cases)
+        ));
+        for case in cases.iter() {
+            let name = xml_escape(case.test.as_deref().unwrap_or("deploy"));
+            let time = case.duration_ms as f64 / 1000.0;
+            match case.error {
+                None => report.push_str(&format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"/>\n",
+                    contract, name, time
+                )),
+                Some(ref error) => {
+                    report.push_str(&format!(
+                        "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n",
+                        contract, name, time
+                    ));
+                    report.push_str(&format!(
+                        "      <failure message=\"{}\"/>\n",
+                        xml_escape(error)
+                    ));
+                    report.push_str("    </testcase>\n");
+                }
+            }
+        }
+        report.push_str("  </testsuite>\n");
+    }
+    report.push_str("</testsuites>\n");
+    report
+}
+
+/// The public functions of a contract whose names start with `test-`, in source order, with
+/// their number of arguments.
+fn find_test_functions(ast: &[SymbolicExpression]) -> Vec<(String, usize)> {
+    ast.iter()
+        .filter_map(
+            |expression| match DefineFunctionsParsed::try_parse(expression) {
+                Ok(Some(DefineFunctionsParsed::PublicFunction { signature, .. })) => {
+                    let name = signature.first()?.match_atom()?;
+                    if name.starts_with("test-") {
+                        Some((name.to_string(), signature.len() - 1))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Deploy every `.clar` file of `test_dir` as `<sender>.<file-name>` into a fresh, throwaway
+/// chain state, then run each public function whose name starts with `test-` as a transaction
+/// from `sender`, in a block of its own. A test fails if it returns an `(err ...)`, or aborts
+/// with a runtime error; its writes are only kept if it passes.
+///
+/// Contracts are deployed in file name order, but a contract which fails analysis is retried
+/// once the others are deployed, so that contracts may depend on contracts which sort after
+/// them. A file whose name is not a valid contract name, or which can't be read, is reported
+/// as a failed deployment. Tests are run in file name order. Returns the deployment outcomes
+/// and the test outcomes.
+fn run_contract_tests(
+    test_dir: &str,
+    sender: &StandardPrincipalData,
+    mainnet: bool,
+    blocks_between_tests: u64,
+    lcov_file: Option<&str>,
+) -> (Vec<TestOutcome>, Vec<TestOutcome>) {
+    let mut sources = vec![];
+    for entry in friendly_expect(
+        fs::read_dir(test_dir),
+        &format!("Failed to read directory: {}", test_dir),
+    ) {
+        let path = friendly_expect(entry, "Failed to read directory entry").path();
+        if path.is_file() && path.extension() == Some(OsStr::new("clar")) {
+            sources.push(path);
+        }
+    }
+    sources.sort();
+
+    let state_dir = env::temp_dir().join(format!(
+        "clarity-test-{}",
+        bytes_to_hex(&rand::thread_rng().gen::<[u8; 16]>())
+    ));
+    friendly_expect(
+        fs::create_dir_all(&state_dir),
+        "Failed to create the test state directory",
+    );
+    let mut header_db = CLIHeadersDB::new_memory(mainnet);
+    let mut marf_kv = friendly_expect(
+        MarfedKV::open(&state_dir.join("vm-state").to_string_lossy(), None, None),
+        "Failed to open VM database.",
+    );
+    let sender_principal = PrincipalData::Standard(sender.clone());
+
+    (header_db, marf_kv, _) = in_block(header_db, marf_kv, |header_db, mut marf| {
+        install_boot_code(&header_db, &mut marf);
+        (header_db, marf, ())
+    });
+    (header_db, marf_kv, _) = in_block(header_db, marf_kv, |header_db, mut kv| {
+        {
+            let mut db = kv.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+            db.begin();
+            let mut snapshot = db
+                .get_stx_balance_snapshot_genesis(&sender_principal)
+                .unwrap();
+            snapshot.set_balance(STXBalance::initial(TEST_SENDER_BALANCE as u128));
+            snapshot.save().unwrap();
+            db.commit().unwrap();
+        }
+        (header_db, kv, ())
+    });
+
+    let mut coverage = lcov_file.map(|_| CoverageReporter::new());
+    let mut register_files = vec![];
+    let mut deployments = vec![];
+    let mut deployed = vec![];
+
+    let mut pending = vec![];
+    for path in sources.into_iter() {
+        let contract_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let contract_identifier = match ContractName::try_from(contract_name.clone()) {
+            Ok(contract_name) => QualifiedContractIdentifier::new(sender.clone(), contract_name),
+            Err(e) => {
+                deployments.push(TestOutcome {
+                    contract: format!("{}.{}", sender, contract_name),
+                    test: None,
+                    output: None,
+                    error: Some(format!(
+                        "Invalid contract name for {}: {}",
+                        path.display(),
+                        e
+                    )),
+                    cost: ExecutionCost::zero(),
+                    duration_ms: 0,
+                });
+                continue;
+            }
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                deployments.push(TestOutcome {
+                    contract: contract_identifier.to_string(),
+                    test: None,
+                    output: None,
+                    error: Some(format!("Error reading file {}: {}", path.display(), e)),
+                    cost: ExecutionCost::zero(),
+                    duration_ms: 0,
+                });
+                continue;
+            }
+        };
+        pending.push((contract_identifier, path, content));
+    }
+
+    loop {
+        let mut deferred = vec![];
+        let deployed_before = deployed.len();
+        for (contract_identifier, path, content) in pending.into_iter() {
+            let start = get_epoch_time_ms();
+            let mut ast = match parse(&contract_identifier, &content, ClarityVersion::Clarity2) {
+                Ok(ast) => ast,
+                Err(e) => {
+                    deployments.push(TestOutcome {
+                        contract: contract_identifier.to_string(),
+                        test: None,
+                        output: None,
+                        error: Some(format!("Failed to parse program: {}", e)),
+                        cost: ExecutionCost::zero(),
+                        duration_ms: 0,
+                    });
+                    continue;
+                }
+            };
+
+            let result;
+            (header_db, marf_kv, result) = in_block(header_db, marf_kv, |header_db, mut marf| {
+                let analysis_result = run_analysis(
+                    &contract_identifier,
+                    &mut ast,
+                    &header_db,
+                    &mut marf,
+                    true,
+                    false,
+                );
+                let result = match analysis_result {
+                    Err((error, _)) => Err(error),
+                    Ok(_) => Ok(with_env_costs(
+                        mainnet,
+                        &header_db,
+                        &mut marf,
+                        coverage.as_mut(),
+                        None,
+                        None,
+                        |vm_env| {
+                            vm_env.initialize_versioned_contract(
+                                contract_identifier.clone(),
+                                ClarityVersion::Clarity2,
+                                &content,
+                                None,
+                                ASTRules::PrecheckSize,
+                            )
+                        },
+                    )),
+                };
+                (header_db, marf, result)
+            });
+
+            let (error, cost) = match result {
+                Err(error) => {
+                    deferred.push((contract_identifier, path, content, error));
+                    continue;
+                }
+                Ok((Err(error), cost)) => (Some(format!("Initialization error: {}", error)), cost),
+                Ok((Ok(_), cost)) => {
+                    if coverage.is_some() {
+                        let register_file =
+                            state_dir.join(format!("{}.clarcovref", contract_identifier.name));
+                        friendly_expect(
+                            CoverageReporter::register_src_file(
+                                &contract_identifier,
+                                &path.to_string_lossy(),
+                                &ast,
+                                &register_file,
+                            ),
+                            "Coverage reference file generation failure",
+                        );
+                        register_files.push(register_file);
+                    }
+                    deployed.push((contract_identifier.clone(), find_test_functions(&ast)));
+                    (None, cost)
+                }
+            };
+            deployments.push(TestOutcome {
+                contract: contract_identifier.to_string(),
+                test: None,
+                output: None,
+                error,
+                cost,
+                duration_ms: (get_epoch_time_ms() - start) as u64,
+            });
+        }
+
+        if deferred.is_empty() || deployed.len() == deployed_before {
+            // no progress was made: the remaining contracts can't be analyzed
+            for (contract_identifier, _, _, error) in deferred.into_iter() {
+                deployments.push(TestOutcome {
+                    contract: contract_identifier.to_string(),
+                    test: None,
+                    output: None,
+                    error: Some(format!("Analysis error: {}", error)),
+                    cost: ExecutionCost::zero(),
+                    duration_ms: 0,
+                });
+            }
+            break;
+        }
+        pending = deferred
+            .into_iter()
+            .map(|(contract_identifier, path, content, _)| (contract_identifier, path, content))
+            .collect();
+    }
+
+    // tests run in file name order, whatever the order the contracts were deployed in
+    deployed.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+    let mut tests = vec![];
+    for (contract_identifier, test_functions) in deployed.into_iter() {
+        for (test_name, argument_count) in test_functions.into_iter() {
+            if argument_count > 0 {
+                tests.push(TestOutcome {
+                    contract: contract_identifier.to_string(),
+                    test: Some(test_name),
+                    output: None,
+                    error: Some("Test functions cannot take arguments".to_string()),
+                    cost: ExecutionCost::zero(),
+                    duration_ms: 0,
+                });
+                continue;
+            }
+            if !tests.is_empty() {
+                for _ in 0..blocks_between_tests {
+                    (header_db, marf_kv, _) =
+                        in_block(header_db, marf_kv, |header_db, marf| (header_db, marf, ()));
+                }
+            }
+
+            let start = get_epoch_time_ms();
+            let result_and_cost;
+            (header_db, marf_kv, result_and_cost) =
+                in_block(header_db, marf_kv, |header_db, mut marf| {
+                    let result_and_cost = with_env_costs(
+                        mainnet,
+                        &header_db,
+                        &mut marf,
+                        coverage.as_mut(),
+                        None,
+                        None,
+                        |vm_env| {
+                            vm_env.execute_transaction(
+                                sender_principal.clone(),
+                                None,
+                                contract_identifier.clone(),
+                                &test_name,
+                                &[],
+                            )
+                        },
+                    );
+                    (header_db, marf, result_and_cost)
+                });
+
+            let (output, error) = match result_and_cost.0 {
+                Ok((Value::Response(data), ..)) => {
+                    if data.committed {
+                        (Some(*data.data), None)
+                    } else {
+                        (Some(*data.data), Some("Returned an (err ...)".to_string()))
+                    }
+                }
+                Ok((output, ..)) => (
+                    Some(output),
+                    Some("Expected a ResponseType result from transaction.".to_string()),
+                ),
+                Err(error) => (None, Some(format!("Runtime error: {}", error))),
+            };
+            tests.push(TestOutcome {
+                contract: contract_identifier.to_string(),
+                test: Some(test_name),
+                output,
+                error,
+                cost: result_and_cost.1,
+                duration_ms: (get_epoch_time_ms() - start) as u64,
+            });
+        }
+    }
+
+    if let (Some(lcov_file), Some(coverage)) = (lcov_file, coverage) {
+        let coverage_file = state_dir.join("test.clarcov");
+        friendly_expect(
+            coverage.to_file(&coverage_file),
+            "Coverage file generation failure",
+        );
+        friendly_expect(
+            CoverageReporter::produce_lcov(lcov_file, &register_files, &[coverage_file]),
+            "Failed to produce an lcov output",
+        );
+    }
+    drop(marf_kv);
+    let _ = fs::remove_dir_all(&state_dir);
+
+    (deployments, tests)
+}
+
+/// The remote chain that a CLI DB forks.  Its first block is the fork point, so local blocks
+/// have heights (row IDs) greater than 1.
+struct CLIFork {
+    node: RemoteClarityNode,
+    block_id: StacksBlockId,
+    block_height: u32,
+}
+
 struct CLIHeadersDB {
     db_path: String,
     conn: Connection,
+    fork: Option<CLIFork>,
 }
 
 impl CLIHeadersDB {
@@ -541,15 +1235,105 @@ impl CLIHeadersDB {
         );
     }
 
+    /// Make this DB a fork of the chain served by `node`, at the block `fork_point`.  Must be
+    /// called on a freshly-instantiated DB, since the fork point becomes its first block.
+    fn instantiate_fork(
+        &mut self,
+        node: RemoteClarityNode,
+        fork_point: &ClarityBlockInfoResponse,
+    ) -> InterpreterResult<()> {
+        let block_height = block_info_height(fork_point)?;
+        let cli_db_path = self.get_cli_db_path();
+        let tx = friendly_expect(
+            self.conn.transaction(),
+            &format!("FATAL: failed to begin transaction on '{}'", cli_db_path),
+        );
+
+        friendly_expect(
+            tx.execute(
+                "CREATE TABLE cli_fork(node TEXT NOT NULL, block_id TEXT NOT NULL, block_height INTEGER NOT NULL);",
+                NO_PARAMS,
+            ),
+            &format!("FATAL: failed to create 'cli_fork' table"),
+        );
+
+        friendly_expect(
+            tx.execute(
+                "INSERT INTO cli_fork (node, block_id, block_height) VALUES (?1, ?2, ?3)",
+                rusqlite::params![
+                    node.node().to_string(),
+                    &fork_point.index_block_hash,
+                    block_height
+                ],
+            ),
+            &format!("FATAL: failed to store fork point"),
+        );
+
+        friendly_expect(
+            tx.execute(
+                "INSERT INTO cli_chain_tips (block_hash) VALUES (?1)",
+                &[&fork_point.index_block_hash],
+            ),
+            &format!("FATAL: failed to store fork point"),
+        );
+
+        friendly_expect(
+            tx.commit(),
+            &format!("FATAL: failed to instantiate CLI DB at {:?}", &cli_db_path),
+        );
+
+        self.fork = Some(CLIFork {
+            node,
+            block_id: fork_point.index_block_hash.clone(),
+            block_height,
+        });
+        Ok(())
+    }
+
+    /// Load the remote chain that the DB in `conn` forks, if any
+    fn load_fork(conn: &Connection) -> Option<CLIFork> {
+        let has_fork: bool = friendly_expect(
+            conn.query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'cli_fork'",
+                NO_PARAMS,
+                |row| row.get(0),
+            ),
+            "FATAL: could not query CLI DB tables",
+        );
+        if !has_fork {
+            return None;
+        }
+
+        let (node, block_id, block_height): (String, StacksBlockId, u32) = friendly_expect(
+            conn.query_row(
+                "SELECT node, block_id, block_height FROM cli_fork LIMIT 1",
+                NO_PARAMS,
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            ),
+            "FATAL: could not read fork point",
+        );
+        let node = friendly_expect(
+            node.parse(),
+            &format!("FATAL: could not parse node address '{}'", &node),
+        );
+        Some(CLIFork {
+            node: RemoteClarityNode::new(node),
+            block_id,
+            block_height,
+        })
+    }
+
     /// Create or open a new CLI DB at db_path.  If it already exists, then this method is a no-op.
     pub fn new(db_path: &str, mainnet: bool) -> CLIHeadersDB {
         let instantiate = db_path == ":memory:" || fs::metadata(&db_path).is_err();
 
         let cli_db_path = get_cli_db_path(db_path);
         let conn = create_or_open_db(&cli_db_path);
+        let fork = CLIHeadersDB::load_fork(&conn);
         let mut db = CLIHeadersDB {
             db_path: db_path.to_string(),
             conn: conn,
+            fork,
         };
 
         if instantiate {
@@ -567,9 +1351,11 @@ impl CLIHeadersDB {
             return Err(format!("Failed to access {:?}: {:?}", &cli_db_path, &e));
         }
         let conn = create_or_open_db(&cli_db_path);
+        let fork = CLIHeadersDB::load_fork(&conn);
         let db = CLIHeadersDB {
             db_path: db_path.to_string(),
             conn: conn,
+            fork,
         };
 
         Ok(db)
@@ -589,6 +1375,65 @@ impl CLIHeadersDB {
         &self.conn
     }
 
+    pub fn is_fork(&self) -> bool {
+        self.fork.is_some()
+    }
+
+    /// Open the backing store of a DB which forks a remote chain
+    fn open_fork_store(&self) -> RemoteForkStore {
+        let fork = self
+            .fork
+            .as_ref()
+            .expect("BUG: CLI DB does not fork a remote chain");
+        friendly_expect(
+            RemoteForkStore::open(
+                &self.db_path,
+                Box::new(fork.node.clone()),
+                fork.block_id.clone(),
+                fork.block_height,
+            ),
+            "Failed to open forked VM database.",
+        )
+    }
+
+    /// What the forked chain knows about `id_bhh`.  None if this DB is not a fork, or if
+    /// `id_bhh` is a local block.
+    fn get_fork_block_info(&self, id_bhh: &StacksBlockId) -> Option<ClarityBlockInfoResponse> {
+        let fork = self.fork.as_ref()?;
+        if get_cli_block_height(&self.conn, id_bhh).is_some_and(|height| height > 1) {
+            return None;
+        }
+        match fork.node.get_block_info(id_bhh) {
+            Ok(info) => info,
+            Err(e) => {
+                warn!(
+                    "Failed to load block info for {} from {}: {:?}",
+                    id_bhh,
+                    fork.node.node(),
+                    &e
+                );
+                None
+            }
+        }
+    }
+
+    /// The mocked burnchain height and timestamp of a local block.  The local blocks of a fork
+    /// follow the fork point's burnchain block, one burnchain block apart.
+    fn get_cli_burn_block(&self, id_bhh: &StacksBlockId) -> Option<(u64, u64)> {
+        let height = get_cli_block_height(&self.conn, id_bhh)?;
+        match self.fork {
+            Some(ref fork) => {
+                let fork_point = self.get_fork_block_info(&fork.block_id)?;
+                let offset = height - 1;
+                Some((
+                    u64::from(fork_point.burn_block_height) + offset,
+                    fork_point.burn_block_time + offset * 600,
+                ))
+            }
+            None => Some((height, height * 600 + 1231006505)),
+        }
+    }
+
     pub fn is_mainnet(&self) -> bool {
         let mut stmt = friendly_expect(
             self.conn.prepare("SELECT testnet FROM cli_config LIMIT 1"),
@@ -645,6 +1490,9 @@ impl HeadersDB for CLIHeadersDB {
         &self,
         id_bhh: &StacksBlockId,
     ) -> Option<BurnchainHeaderHash> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return Some(info.burn_header_hash);
+        }
         // mock it
         let conn = self.conn();
         if let Some(_) = get_cli_block_height(&conn, id_bhh) {
@@ -656,6 +1504,9 @@ impl HeadersDB for CLIHeadersDB {
     }
 
     fn get_consensus_hash_for_block(&self, id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return Some(info.consensus_hash);
+        }
         // mock it
         let conn = self.conn();
         if let Some(_) = get_cli_block_height(&conn, id_bhh) {
@@ -667,6 +1518,9 @@ impl HeadersDB for CLIHeadersDB {
     }
 
     fn get_vrf_seed_for_block(&self, id_bhh: &StacksBlockId) -> Option<VRFSeed> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return info.vrf_seed;
+        }
         let conn = self.conn();
         if let Some(_) = get_cli_block_height(&conn, id_bhh) {
             // mock it, but make it unique
@@ -682,6 +1536,9 @@ impl HeadersDB for CLIHeadersDB {
         &self,
         id_bhh: &StacksBlockId,
     ) -> Option<BlockHeaderHash> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return Some(info.block_hash);
+        }
         let conn = self.conn();
         if let Some(_) = get_cli_block_height(&conn, id_bhh) {
             // mock it, but make it unique
@@ -695,38 +1552,45 @@ impl HeadersDB for CLIHeadersDB {
     }
 
     fn get_burn_block_time_for_block(&self, id_bhh: &StacksBlockId) -> Option<u64> {
-        let conn = self.conn();
-        if let Some(height) = get_cli_block_height(&conn, id_bhh) {
-            Some((height * 600 + 1231006505) as u64)
-        } else {
-            None
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return Some(info.burn_block_time);
         }
+        self.get_cli_burn_block(id_bhh).map(|(_, time)| time)
     }
 
     fn get_burn_block_height_for_block(&self, id_bhh: &StacksBlockId) -> Option<u32> {
-        let conn = self.conn();
-        if let Some(height) = get_cli_block_height(&conn, id_bhh) {
-            Some(height as u32)
-        } else {
-            None
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return Some(info.burn_block_height);
         }
+        self.get_cli_burn_block(id_bhh)
+            .map(|(height, _)| height as u32)
     }
 
-    fn get_miner_address(&self, _id_bhh: &StacksBlockId) -> Option<StacksAddress> {
-        None
+    fn get_miner_address(&self, id_bhh: &StacksBlockId) -> Option<StacksAddress> {
+        let info = self.get_fork_block_info(id_bhh)?;
+        StacksAddress::from_string(&info.miner_address?)
     }
 
     fn get_burnchain_tokens_spent_for_block(&self, id_bhh: &StacksBlockId) -> Option<u128> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return info.burnchain_tokens_spent;
+        }
         // if the block is defined at all, then return a constant
         get_cli_block_height(&self.conn(), id_bhh).map(|_| 2000)
     }
 
     fn get_burnchain_tokens_spent_for_winning_block(&self, id_bhh: &StacksBlockId) -> Option<u128> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return info.burnchain_tokens_spent_for_winning_block;
+        }
         // if the block is defined at all, then return a constant
         get_cli_block_height(&self.conn(), id_bhh).map(|_| 1000)
     }
 
     fn get_tokens_earned_for_block(&self, id_bhh: &StacksBlockId) -> Option<u128> {
+        if let Some(info) = self.get_fork_block_info(id_bhh) {
+            return info.tokens_earned;
+        }
         // if the block is defined at all, then return a constant
         get_cli_block_height(&self.conn(), id_bhh).map(|_| 3000)
     }
@@ -741,8 +1605,6 @@ fn get_eval_input(invoked_by: &str, args: &[String]) -> EvalInput {
         panic_test!();
     }
 
-    let vm_filename = if args.len() == 3 { &args[2] } else { &args[3] };
-
     let content: String = {
         if args.len() == 3 {
             let mut buffer = String::new();
@@ -764,13 +1626,7 @@ fn get_eval_input(invoked_by: &str, args: &[String]) -> EvalInput {
         "Failed to parse contract identifier.",
     );
 
-    let marf_kv = friendly_expect(
-        MarfedKV::open(vm_filename, None, None),
-        "Failed to open VM database.",
-    );
-    // return (marf_kv, contract_identifier, vm_filename, content);
     return EvalInput {
-        marf_kv,
         contract_identifier,
         content,
     };
@@ -821,7 +1677,7 @@ fn consume_arg(
 }
 
 /// This function uses Clarity1 to parse the boot code.
-fn install_boot_code<C: ClarityStorage>(header_db: &CLIHeadersDB, marf: &mut C) {
+fn install_boot_code<C: ClarityStorage + ?Sized>(header_db: &CLIHeadersDB, marf: &mut C) {
     let mainnet = header_db.is_mainnet();
     let boot_code = if mainnet {
         *STACKS_BOOT_CODE_MAINNET_2_1
@@ -947,6 +1803,43 @@ pub fn add_serialized_output(result: &mut serde_json::Value, value: Value) {
         bytes_to_hex(&bytes)
     };
     result["output_serialized"] = serde_json::to_value(result_raw.as_str()).unwrap();
+    result["output_json"] = value.serialize_to_json();
+}
+
+/// Flattens a diagnostic into the entry reported by `check --format=json`, positioned at its first
+/// span. Positions which are not known are null: the analysis only tracks them in `developer-mode`
+/// builds.
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> serde_json::Value {
+    let span = diagnostic.spans.first().filter(|span| **span != Span::ZERO);
+    let severity = match diagnostic.level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note => "note",
+    };
+    json!({
+        "code": diagnostic.code,
+        "severity": severity,
+        "message": diagnostic.message,
+        "suggestion": diagnostic.suggestion,
+        "start_line": span.map(|span| span.start_line),
+        "start_column": span.map(|span| span.start_column),
+        "end_line": span.map(|span| span.end_line),
+        "end_column": span.map(|span| span.end_column),
+    })
+}
+
+/// The output of `check --format=json`. `diagnostics` holds either every parse error, or the
+/// first (and only) error of the analysis passes, which stop at the first error they find, or
+/// the lint warnings of a program which passed its checks.
+fn diagnostics_result(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let success = !diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.level == Level::Error);
+    json!({
+        "message": if success { "Checks passed." } else { "Checks failed." },
+        "success": success,
+        "diagnostics": diagnostics.iter().map(diagnostic_to_json).collect::<Vec<_>>(),
+    })
 }
 
 /// Returns (process-exit-code, Option<json-output>)
@@ -960,10 +1853,24 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
         "initialize" => {
             let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
 
-            let mainnet = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
-                false
-            } else {
+            let testnet_given = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
                 true
+            } else {
+                false
+            };
+            let fork_node = match consume_arg(&mut argv, &["--fork"], true) {
+                Ok(fork_node) => fork_node,
+                Err(_) => {
+                    eprintln!("Expected argument for --fork");
+                    panic_test!();
+                }
+            };
+            let fork_at = match consume_arg(&mut argv, &["--at"], true) {
+                Ok(fork_at) => fork_at,
+                Err(_) => {
+                    eprintln!("Expected argument for --at");
+                    panic_test!();
+                }
             };
 
             let (db_name, allocations) = if argv.len() == 3 {
@@ -1002,35 +1909,104 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 (&argv[1], Vec::new())
             } else {
                 eprintln!(
-                    "Usage: {} {} [--testnet] (initial-allocations.json) [vm-state.db]",
+                    "Usage: {} {} [--testnet] [--fork host:port [--at index-block-hash]] (initial-allocations.json) [vm-state.db]",
                     invoked_by, argv[0]
                 );
                 eprintln!("   initial-allocations.json is a JSON array of {{ principal: \"ST...\", amount: 100 }} like objects.");
                 eprintln!("   if the provided filename is `-`, the JSON is read from stdin.");
                 eprintln!("   If --testnet is given, then testnet bootcode and block-limits are used instead of mainnet.");
+                eprintln!("   If --fork is given, then the state is a fork of the chain served by the node at host:port,");
+                eprintln!("   as of its chain tip (or the block given by --at), which is read lazily over RPC.");
                 panic_test!();
             };
 
-            debug!("Initialize {}", &db_name);
-            let mut header_db = CLIHeadersDB::new(&db_name, mainnet);
-            let mut marf_kv = friendly_expect(
-                MarfedKV::open(db_name, None, None),
-                "Failed to open VM database.",
-            );
+            let fork = fork_node.map(|fork_node| {
+                let node_addr = friendly_expect_opt(
+                    friendly_expect(
+                        fork_node.to_socket_addrs(),
+                        &format!("Failed to resolve node address '{}'", &fork_node),
+                    )
+                    .next(),
+                    &format!("Failed to resolve node address '{}'", &fork_node),
+                );
+                let node = RemoteClarityNode::new(node_addr);
+                let peer_info = friendly_expect(
+                    node.get_peer_info(),
+                    &format!("Failed to get info from {}", &fork_node),
+                );
+                let block_id = match fork_at {
+                    Some(ref block_hex) => friendly_expect(
+                        StacksBlockId::from_hex(block_hex),
+                        &format!("Failed to parse index block hash '{}'", block_hex),
+                    ),
+                    None => StacksBlockId::new(
+                        &peer_info.stacks_tip_consensus_hash,
+                        &peer_info.stacks_tip,
+                    ),
+                };
+                let block_info = friendly_expect_opt(
+                    friendly_expect(
+                        node.get_block_info(&block_id),
+                        &format!("Failed to get block {} from {}", &block_id, &fork_node),
+                    ),
+                    &format!("No such block {} on {}", &block_id, &fork_node),
+                );
+                (node, peer_info.network_id == CHAIN_ID_MAINNET, block_info)
+            });
+            if fork.is_none() && fork_at.is_some() {
+                eprintln!("--at requires --fork");
+                panic_test!();
+            }
+
+            let mainnet = match fork {
+                Some((_, fork_mainnet, _)) => {
+                    if testnet_given {
+                        eprintln!(
+                            "WARN: ignoring --testnet in favor of the network of the forked node"
+                        );
+                    }
+                    fork_mainnet
+                }
+                None => !testnet_given,
+            };
 
-            // install bootcode
-            let state = in_block(header_db, marf_kv, |header_db, mut marf| {
-                install_boot_code(&header_db, &mut marf);
-                (header_db, marf, ())
+            let fork_point = fork.as_ref().map(|(node, _, block_info)| {
+                json!({
+                    "node": node.node().to_string(),
+                    "index_block_hash": block_info.index_block_hash,
+                    "block_height": block_info.block_height,
+                })
             });
 
-            header_db = state.0;
-            marf_kv = state.1;
+            debug!("Initialize {}", &db_name);
+            let header_db = match fork {
+                Some((node, _, ref block_info)) => {
+                    if fs::metadata(db_name).is_ok() {
+                        eprintln!("Cannot fork into the existing state database {}", db_name);
+                        panic_test!();
+                    }
+                    let mut header_db = CLIHeadersDB::new(&db_name, mainnet);
+                    friendly_expect(
+                        header_db.instantiate_fork(node, block_info),
+                        &format!("Failed to fork {}", &block_info.index_block_hash),
+                    );
+                    header_db
+                }
+                None => {
+                    let header_db = CLIHeadersDB::new(&db_name, mainnet);
+
+                    // install bootcode
+                    let (header_db, _) = in_cli_block(header_db, |header_db, marf| {
+                        install_boot_code(header_db, marf);
+                    });
+                    header_db
+                }
+            };
 
             // set initial balances
-            in_block(header_db, marf_kv, |header_db, mut kv| {
+            in_cli_block(header_db, |header_db, kv| {
                 {
-                    let mut db = kv.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                    let mut db = kv.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
                     db.begin();
                     for (principal, amount) in allocations.iter() {
                         let balance = STXBalance::initial(*amount as u128);
@@ -1044,26 +2020,23 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                     }
                     db.commit().unwrap();
                 };
-                (header_db, kv, ())
             });
 
-            if mainnet {
-                (
-                    0,
-                    Some(json!({
-                        "message": "Database created.",
-                        "network": "mainnet"
-                    })),
-                )
+            let mut result = if mainnet {
+                json!({
+                    "message": "Database created.",
+                    "network": "mainnet"
+                })
             } else {
-                (
-                    0,
-                    Some(json!({
-                        "message": "Database created.",
-                        "network": "testnet"
-                    })),
-                )
+                json!({
+                    "message": "Database created.",
+                    "network": "testnet"
+                })
+            };
+            if let Some(fork) = fork_point {
+                result["fork"] = fork;
             }
+            (0, Some(result))
         }
         "generate_address" => {
             // random 20 bytes
@@ -1077,7 +2050,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
         "check" => {
             if args.len() < 2 {
                 eprintln!(
-                    "Usage: {} {} [program-file.clar] [--contract_id CONTRACT_ID] [--output_analysis] [--costs] [--testnet] (vm-state.db)",
+                    "Usage: {} {} [program-file.clar] [--contract_id CONTRACT_ID] [--output_analysis] [--costs] [--lint] [--lint_config CONFIG.json] [--format=json] [--testnet] (vm-state.db)",
                     invoked_by, args[0]
                 );
                 panic_test!();
@@ -1112,6 +2085,37 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 false
             };
 
+            let lint_config = match consume_arg(&mut argv, &["--lint_config"], true) {
+                Ok(Some(path)) => {
+                    let json = friendly_expect(
+                        fs::read_to_string(&path),
+                        &format!("Error reading lint config file: {}", path),
+                    );
+                    Some(friendly_expect(
+                        LintConfig::from_json(&json),
+                        &format!("Error parsing lint config file: {}", path),
+                    ))
+                }
+                Ok(None) => None,
+                Err(_) => {
+                    eprintln!("Expected argument for --lint_config");
+                    panic_test!();
+                }
+            };
+            let lint_config = if let Ok(Some(_)) = consume_arg(&mut argv, &["--lint"], false) {
+                Some(lint_config.unwrap_or_default())
+            } else {
+                lint_config
+            };
+            let build_type_map = costs || lint_config.is_some();
+
+            let format_json = if let Ok(Some(_)) = consume_arg(&mut argv, &["--format=json"], false)
+            {
+                true
+            } else {
+                false
+            };
+
             // NOTE: ignored if we're using a DB
             let mut testnet_given = false;
             let mainnet = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
@@ -1136,10 +2140,27 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             };
 
             // TODO: Add --clarity_version as command line argument
-            let mut ast = friendly_expect(
-                parse(&contract_id, &content, ClarityVersion::Clarity2),
-                "Failed to parse program",
-            );
+            let mut diagnostics = vec![];
+            let mut ast = if format_json {
+                // keep parsing past errors, to report all of them at once
+                let (contract_ast, parse_diagnostics, success) = build_ast_with_diagnostics(
+                    &contract_id,
+                    &content,
+                    &mut (),
+                    ClarityVersion::Clarity2,
+                    DEFAULT_CLI_EPOCH,
+                );
+                if !success {
+                    return (1, Some(diagnostics_result(&parse_diagnostics)));
+                }
+                diagnostics = parse_diagnostics;
+                contract_ast.expressions
+            } else {
+                friendly_expect(
+                    parse(&contract_id, &content, ClarityVersion::Clarity2),
+                    "Failed to parse program",
+                )
+            };
 
             let contract_analysis_res = {
                 if argv.len() >= 3 {
@@ -1151,17 +2172,23 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                     let vm_filename = &argv[2];
                     let header_db =
                         friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-                    let marf_kv = friendly_expect(
-                        MarfedKV::open(vm_filename, None, None),
-                        "Failed to open VM database.",
-                    );
 
-                    let result = at_chaintip(&argv[2], marf_kv, |mut marf| {
-                        let result =
-                            run_analysis(&contract_id, &mut ast, &header_db, &mut marf, false);
-                        (marf, result)
-                    });
-                    result
+                    at_cli_chaintip(&header_db, |marf| {
+                        run_analysis(
+                            &contract_id,
+                            &mut ast,
+                            &header_db,
+                            marf,
+                            false,
+                            build_type_map,
+                        )
+                        .and_then(|mut contract_analysis| {
+                            if costs {
+                                run_cost_checker(&mut contract_analysis, marf)?;
+                            }
+                            Ok(contract_analysis)
+                        })
+                    })
                 } else {
                     let header_db = CLIHeadersDB::new_memory(mainnet);
                     let mut analysis_marf = MemoryBackingStore::new();
@@ -1173,33 +2200,67 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                         &header_db,
                         &mut analysis_marf,
                         false,
+                        build_type_map,
                     )
+                    .and_then(|mut contract_analysis| {
+                        if costs {
+                            run_cost_checker(&mut contract_analysis, &mut analysis_marf)?;
+                        }
+                        Ok(contract_analysis)
+                    })
                 }
             };
 
             let mut contract_analysis = match contract_analysis_res {
                 Ok(contract_analysis) => contract_analysis,
                 Err((e, cost_tracker)) => {
-                    let mut result = json!({
-                        "message": "Checks failed.",
-                        "error": {
-                            "analysis": serde_json::to_value(&e.diagnostic).unwrap(),
-                        }
-                    });
+                    let mut result = if format_json {
+                        // the analysis passes stop at their first error, so this is the only one
+                        diagnostics.push(e.diagnostic);
+                        diagnostics_result(&diagnostics)
+                    } else {
+                        json!({
+                            "message": "Checks failed.",
+                            "error": {
+                                "analysis": serde_json::to_value(&e.diagnostic).unwrap(),
+                            }
+                        })
+                    };
                     add_costs(&mut result, costs, cost_tracker.get_total());
                     return (1, Some(result));
                 }
             };
 
-            let mut result = json!({
-                "message": "Checks passed."
-            });
+            let mut warnings = vec![];
+            if let Some(lint_config) = lint_config.as_ref() {
+                warnings = friendly_expect(
+                    Linter::new(lint_config).run(&contract_analysis),
+                    "Failed to lint program",
+                );
+            }
+
+            let mut result = if format_json {
+                diagnostics.append(&mut warnings);
+                diagnostics_result(&diagnostics)
+            } else {
+                let mut result = json!({
+                    "message": "Checks passed."
+                });
+                if lint_config.is_some() {
+                    result["warnings"] = serde_json::to_value(&warnings).unwrap();
+                }
+                result
+            };
 
             add_costs(
                 &mut result,
                 costs,
                 contract_analysis.take_contract_cost_tracker().get_total(),
             );
+            if costs {
+                result["cost_bounds"] =
+                    serde_json::to_value(&contract_analysis.function_cost_bounds).unwrap();
+            }
 
             if output_analysis {
                 result["analysis"] =
@@ -1208,6 +2269,167 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             }
             (0, Some(result))
         }
+        "abi-diff" => {
+            if args.len() < 3 {
+                eprintln!(
+                    "Usage: {} {} [old-program.clar|old-contract-identifier] [new-program.clar] [--contract_id CONTRACT_ID] [--testnet] (vm-state.db)",
+                    invoked_by, args[0]
+                );
+                eprintln!("   Given vm-state.db, the old version may be a deployed contract, and both are checked against its state.");
+                eprintln!("   Exits with 1 if the new version has breaking changes.");
+                panic_test!();
+            }
+
+            let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
+            let contract_id = if let Ok(optarg) = consume_arg(&mut argv, &["--contract_id"], true) {
+                optarg
+                    .map(|optarg_str| {
+                        friendly_expect(
+                            QualifiedContractIdentifier::parse(&optarg_str),
+                            &format!("Error parsing contract identifier '{}", &optarg_str),
+                        )
+                    })
+                    .unwrap_or(QualifiedContractIdentifier::transient())
+            } else {
+                eprintln!("Expected argument for --contract-id");
+                panic_test!();
+            };
+
+            // NOTE: ignored if we're using a DB
+            let mainnet = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
+                false
+            } else {
+                true
+            };
+
+            let vm_filename = argv.get(3).map(|x| x.as_str());
+            let deployed_id =
+                vm_filename.and_then(|_| QualifiedContractIdentifier::parse(&argv[1]).ok());
+            // the new version replaces the deployed one, so check it under the same name
+            let contract_id = deployed_id.clone().unwrap_or(contract_id);
+
+            let analyses = match deployed_id {
+                Some(deployed_id) => {
+                    let old = load_deployed_analysis(&deployed_id, &argv[3]);
+                    analyze_contract_file(&contract_id, &argv[2], vm_filename, mainnet)
+                        .map(|new| (old, new))
+                        .map_err(|e| ("new", e))
+                }
+                None => analyze_contract_file(&contract_id, &argv[1], vm_filename, mainnet)
+                    .map_err(|e| ("old", e))
+                    .and_then(|old| {
+                        analyze_contract_file(&contract_id, &argv[2], vm_filename, mainnet)
+                            .map(|new| (old, new))
+                            .map_err(|e| ("new", e))
+                    }),
+            };
+            let (old, new) = match analyses {
+                Ok(analyses) => analyses,
+                Err((version, e)) => {
+                    let result = json!({
+                        "message": format!("Checks failed for the {} version.", version),
+                        "error": {
+                            "analysis": serde_json::to_value(&e.diagnostic).unwrap(),
+                        }
+                    });
+                    return (1, Some(result));
+                }
+            };
+
+            let diff = friendly_expect(
+                diff_contract_analyses(&old, &new),
+                "Failed to build contract interfaces",
+            );
+            let compatible = diff.is_compatible();
+            let mut result = serde_json::to_value(&diff).unwrap();
+            result["message"] = json!(if compatible {
+                "No breaking changes."
+            } else {
+                "Breaking changes found."
+            });
+            result["compatible"] = json!(compatible);
+            (if compatible { 0 } else { 1 }, Some(result))
+        }
+        "bindgen" => {
+            let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
+            let mut options = BindingOptions::default();
+            if let Ok(Some(name)) = consume_arg(&mut argv, &["--contract_name"], true) {
+                options.contract_name = Some(name);
+            }
+            if let Ok(Some(crate_path)) = consume_arg(&mut argv, &["--crate"], true) {
+                options.crate_path = crate_path;
+            }
+            let output = if let Ok(optarg) = consume_arg(&mut argv, &["--output"], true) {
+                optarg
+            } else {
+                eprintln!("Expected argument for --output");
+                panic_test!();
+            };
+            while let Ok(Some(event)) = consume_arg(&mut argv, &["--event"], true) {
+                let Some((name, type_repr)) = event.split_once('=') else {
+                    eprintln!("Expected --event NAME=TYPE, found '{}'", event);
+                    panic_test!();
+                };
+                let type_f = friendly_expect(
+                    parse_event_type(type_repr),
+                    &format!("Error parsing the type of event '{}'", name),
+                );
+                options.events.push((name.to_string(), type_f));
+            }
+
+            if argv.len() != 2 {
+                eprintln!(
+                    "Usage: {} {} [interface.json] [--contract_name NAME] [--crate PATH] [--event NAME=TYPE]... [--output bindings.rs]",
+                    invoked_by, argv[0]
+                );
+                eprintln!("   The interface is the JSON output of `check --output_analysis`, or its `analysis` field. If the filename is `-`, it is read from stdin.");
+                eprintln!("   Event types are written in Clarity syntax, e.g. --event 'transfer=(tuple (amount uint))'.");
+                eprintln!("   --crate is the path of stackslib in the crate using the bindings (default: blockstack_lib).");
+                panic_test!();
+            }
+
+            let content = if argv[1] == "-" {
+                let mut buffer = String::new();
+                friendly_expect(
+                    io::stdin().read_to_string(&mut buffer),
+                    "Error reading from stdin.",
+                );
+                buffer
+            } else {
+                friendly_expect(
+                    fs::read_to_string(&argv[1]),
+                    &format!("Error reading file: {}", argv[1]),
+                )
+            };
+            let mut json: serde_json::Value =
+                friendly_expect(serde_json::from_str(&content), "Error parsing JSON");
+            if let Some(analysis) = json.get_mut("analysis") {
+                json = analysis.take();
+            }
+            let interface: ContractInterface = friendly_expect(
+                serde_json::from_value(json),
+                "Error parsing the contract interface",
+            );
+
+            let bindings = generate_bindings(&interface, &options);
+            match output {
+                Some(output) => {
+                    friendly_expect(
+                        fs::write(&output, bindings),
+                        &format!("Error writing file: {}", output),
+                    );
+                    let result = json!({
+                        "message": "Bindings generated.",
+                        "output": output,
+                    });
+                    (0, Some(result))
+                }
+                None => {
+                    print!("{}", bindings);
+                    (0, None)
+                }
+            }
+        }
         "repl" => {
             let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
             let mainnet = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
@@ -1215,6 +2437,15 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 true
             };
+            // The debugger reads its commands from stdin only while an input is being evaluated,
+            // and the REPL reads the next input only once it has been, so they take turns reading
+            // lines from the same stdin.
+            let debugger = if let Ok(Some(_)) = consume_arg(&mut argv, &["--debug"], false) {
+                Some(Rc::new(RefCell::new(Debugger::stdio())))
+            } else {
+                None
+            };
+            let mut debug_hook = debugger.clone().map(SharedDebugger);
             let mut marf = MemoryBackingStore::new();
             let mut vm_env = OwnedEnvironment::new_free(
                 mainnet,
@@ -1222,6 +2453,9 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 marf.as_clarity_db(),
                 DEFAULT_CLI_EPOCH,
             );
+            if let Some(debug_hook) = debug_hook.as_mut() {
+                vm_env.add_eval_hook(debug_hook);
+            }
             let mut placeholder_context = ContractContext::new(
                 QualifiedContractIdentifier::transient(),
                 ClarityVersion::Clarity2,
@@ -1243,6 +2477,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                         panic!("Failed to flush stdout prompt string:\n{}", e);
                     });
                     match io::stdin().read_line(&mut buffer) {
+                        Ok(0) => break,
                         Ok(_) => buffer,
                         Err(error) => {
                             eprintln!("Error reading from stdin:\n{}", error);
@@ -1267,6 +2502,10 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                     }
                 }
 
+                // each input is debugged from its first expression, like a program given to `eval`
+                if let Some(debugger) = debugger.as_ref() {
+                    debugger.borrow_mut().restart();
+                }
                 let eval_result =
                     match exec_env.eval_raw_with_rules(&content, ASTRules::PrecheckSize) {
                         Ok(val) => val,
@@ -1278,6 +2517,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
 
                 println!("{}", eval_result);
             }
+            (0, None)
         }
         "eval_raw" => {
             let content: String = {
@@ -1348,24 +2588,37 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 false
             };
+            let mut debugger = if let Ok(Some(_)) = consume_arg(&mut argv, &["--debug"], false) {
+                if argv.len() == 3 {
+                    eprintln!(
+                        "--debug requires a program file: debugger commands are read from stdin"
+                    );
+                    panic_test!();
+                }
+                Some(Debugger::stdio())
+            } else {
+                None
+            };
 
             let evalInput = get_eval_input(invoked_by, &argv);
             let vm_filename = if argv.len() == 3 { &argv[2] } else { &argv[3] };
             let header_db =
                 friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-            let marf_kv = friendly_expect(
-                MarfedKV::open(vm_filename, None, None),
-                "Failed to open VM database.",
-            );
             let mainnet = header_db.is_mainnet();
             let mut placeholder_context = ContractContext::new(
                 QualifiedContractIdentifier::transient(),
                 ClarityVersion::Clarity2,
             );
 
-            let (_, _, result_and_cost) = in_block(header_db, marf_kv, |header_db, mut marf| {
-                let result_and_cost =
-                    with_env_costs(mainnet, &header_db, &mut marf, None, |vm_env| {
+            let (_, result_and_cost) = in_cli_block(header_db, |header_db, marf| {
+                with_env_costs(
+                    mainnet,
+                    header_db,
+                    marf,
+                    None,
+                    debugger.as_mut(),
+                    None,
+                    |vm_env| {
                         vm_env
                             .get_exec_environment(None, None, &mut placeholder_context)
                             .eval_read_only_with_rules(
@@ -1373,8 +2626,8 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                                 &evalInput.content,
                                 ASTRules::PrecheckSize,
                             )
-                    });
-                (header_db, marf, result_and_cost)
+                    },
+                )
             });
 
             match result_and_cost {
@@ -1421,10 +2674,6 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             let vm_filename = if argv.len() == 3 { &argv[2] } else { &argv[3] };
             let header_db =
                 friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-            let marf_kv = friendly_expect(
-                MarfedKV::open(vm_filename, None, None),
-                "Failed to open VM database.",
-            );
 
             let mainnet = header_db.is_mainnet();
             let mut placeholder_context = ContractContext::new(
@@ -1436,12 +2685,14 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 None
             };
-            let result_and_cost = at_chaintip(vm_filename, marf_kv, |mut marf| {
-                let result_and_cost = with_env_costs(
+            let result_and_cost = at_cli_chaintip(&header_db, |marf| {
+                with_env_costs(
                     mainnet,
                     &header_db,
-                    &mut marf,
+                    marf,
                     coverage.as_mut(),
+                    None,
+                    None,
                     |vm_env| {
                         vm_env
                             .get_exec_environment(None, None, &mut placeholder_context)
@@ -1451,10 +2702,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                                 ASTRules::PrecheckSize,
                             )
                     },
-                );
-                let (result, cost) = result_and_cost;
-
-                (marf, (result, cost))
+                )
             });
 
             match result_and_cost {
@@ -1518,6 +2766,10 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             let vm_filename = &argv[3];
             let header_db =
                 friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
+            if header_db.is_fork() {
+                eprintln!("eval_at_block is not supported on a fork of a remote chain: use `at-block` with eval_at_chaintip instead");
+                panic_test!();
+            }
             let marf_kv = friendly_expect(
                 MarfedKV::open(vm_filename, None, None),
                 "Failed to open VM database.",
@@ -1529,7 +2781,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             );
             let result_and_cost = at_block(chain_tip, marf_kv, |mut marf| {
                 let result_and_cost =
-                    with_env_costs(mainnet, &header_db, &mut marf, None, |vm_env| {
+                    with_env_costs(mainnet, &header_db, &mut marf, None, None, None, |vm_env| {
                         vm_env
                             .get_exec_environment(None, None, &mut placeholder_context)
                             .eval_read_only_with_rules(
@@ -1637,10 +2889,6 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
 
             let header_db =
                 friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-            let marf_kv = friendly_expect(
-                MarfedKV::open(vm_filename, None, None),
-                "Failed to open VM database.",
-            );
             let mainnet = header_db.is_mainnet();
 
             let mut coverage = if coverage_folder.is_some() {
@@ -1648,33 +2896,33 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 None
             };
-            let (_, _, analysis_result_and_cost) =
-                in_block(header_db, marf_kv, |header_db, mut marf| {
-                    let analysis_result =
-                        run_analysis(&contract_identifier, &mut ast, &header_db, &mut marf, true);
-                    match analysis_result {
-                        Err(e) => (header_db, marf, Err(e)),
-                        Ok(analysis) => {
-                            let result_and_cost = with_env_costs(
-                                mainnet,
-                                &header_db,
-                                &mut marf,
-                                coverage.as_mut(),
-                                |vm_env| {
-                                    vm_env.initialize_versioned_contract(
-                                        contract_identifier,
-                                        ClarityVersion::Clarity2,
-                                        &contract_content,
-                                        None,
-                                        ASTRules::PrecheckSize,
-                                    )
-                                },
-                            );
-                            let (result, cost) = result_and_cost;
-                            (header_db, marf, Ok((analysis, (result, cost))))
-                        }
+            let (_, analysis_result_and_cost) = in_cli_block(header_db, |header_db, marf| {
+                let analysis_result =
+                    run_analysis(&contract_identifier, &mut ast, header_db, marf, true, false);
+                match analysis_result {
+                    Err(e) => Err(e),
+                    Ok(analysis) => {
+                        let result_and_cost = with_env_costs(
+                            mainnet,
+                            header_db,
+                            marf,
+                            coverage.as_mut(),
+                            None,
+                            None,
+                            |vm_env| {
+                                vm_env.initialize_versioned_contract(
+                                    contract_identifier,
+                                    ClarityVersion::Clarity2,
+                                    &contract_content,
+                                    None,
+                                    ASTRules::PrecheckSize,
+                                )
+                            },
+                        );
+                        Ok((analysis, result_and_cost))
                     }
-                });
+                }
+            });
 
             match analysis_result_and_cost {
                 Ok((contract_analysis, (Ok((_x, asset_map, events)), cost))) => {
@@ -1740,19 +2988,21 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 false
             };
+            let mut debugger = if let Ok(Some(_)) = consume_arg(&mut argv, &["--debug"], false) {
+                Some(Debugger::stdio())
+            } else {
+                None
+            };
+            let profile_prefix = consume_arg(&mut argv, &["--profile"], true).unwrap_or_default();
 
             if argv.len() < 5 {
-                eprintln!("Usage: {} {} [--costs] [--assets] [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, argv[0]);
+                eprintln!("Usage: {} {} [--costs] [--assets] [--debug] [--profile output-prefix] [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, argv[0]);
                 panic_test!();
             }
 
             let vm_filename = &argv[1];
             let header_db =
                 friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-            let marf_kv = friendly_expect(
-                MarfedKV::open(vm_filename, None, None),
-                "Failed to open VM database.",
-            );
             let mainnet = header_db.is_mainnet();
             let contract_identifier = friendly_expect(
                 QualifiedContractIdentifier::parse(&argv[2]),
@@ -1792,12 +3042,19 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             } else {
                 None
             };
-            let (_, _, result_and_cost) = in_block(header_db, marf_kv, |header_db, mut marf| {
-                let result_and_cost = with_env_costs(
+            let mut profiler = if profile_prefix.is_some() {
+                Some(ExecutionProfiler::new())
+            } else {
+                None
+            };
+            let (_, result_and_cost) = in_cli_block(header_db, |header_db, marf| {
+                with_env_costs(
                     mainnet,
-                    &header_db,
-                    &mut marf,
+                    header_db,
+                    marf,
                     coverage.as_mut(),
+                    debugger.as_mut(),
+                    profiler.as_mut(),
                     |vm_env| {
                         vm_env.execute_transaction(
                             sender,
@@ -1807,10 +3064,9 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                             &arguments,
                         )
                     },
-                );
-                let (result, cost) = result_and_cost;
-                (header_db, marf, (result, cost))
+                )
             });
+            save_profile(profile_prefix, profiler);
 
             match result_and_cost {
                 (Ok((x, asset_map, events)), cost) => {
@@ -1872,6 +3128,164 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 }
             }
         }
+        "test" => {
+            let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
+
+            let mainnet = if let Ok(Some(_)) = consume_arg(&mut argv, &["--testnet"], false) {
+                false
+            } else {
+                true
+            };
+            let sender_in = consume_arg(&mut argv, &["--sender"], true)
+                .unwrap_or_default()
+                .unwrap_or_else(|| "S1G2081040G2081040G2081040G208105NK8PE5".to_string());
+            let blocks_between_tests =
+                consume_arg(&mut argv, &["--blocks-between-tests"], true).unwrap_or_default();
+            let junit_file = consume_arg(&mut argv, &["--junit"], true).unwrap_or_default();
+            let lcov_file = consume_arg(&mut argv, &["--coverage"], true).unwrap_or_default();
+
+            if argv.len() != 2 {
+                eprintln!("Usage: {} {} [--testnet] [--sender sender-address] [--blocks-between-tests N] [--junit report.xml] [--coverage coverage.lcov] [contracts-directory]", invoked_by, argv[0]);
+                eprintln!("   Every .clar file of the directory is deployed as <sender-address>.<file-name>, and each public");
+                eprintln!("   function whose name starts with `test-` is run as a separate transaction from the sender.");
+                panic_test!();
+            }
+
+            let sender = match PrincipalData::parse_standard_principal(&sender_in) {
+                Ok(sender) => sender,
+                Err(_) => {
+                    eprintln!("Unexpected result parsing sender: {}", sender_in);
+                    panic_test!();
+                }
+            };
+            let blocks_between_tests = match blocks_between_tests {
+                Some(blocks) => friendly_expect(
+                    blocks.parse::<u64>(),
+                    "Failed to parse the number of blocks between tests",
+                ),
+                None => 0,
+            };
+
+            let (deployments, tests) = run_contract_tests(
+                &argv[1],
+                &sender,
+                mainnet,
+                blocks_between_tests,
+                lcov_file.as_deref(),
+            );
+
+            if let Some(junit_file) = junit_file {
+                friendly_expect(
+                    fs::write(&junit_file, junit_report(deployments.iter().chain(&tests))),
+                    &format!("Failed to write JUnit report to {}", junit_file),
+                );
+            }
+
+            let passed = tests.iter().filter(|test| test.is_success()).count();
+            let failed = deployments
+                .iter()
+                .chain(&tests)
+                .filter(|outcome| !outcome.is_success())
+                .count();
+            let result = json!({
+                "message": format!("{} passed, {} failed.", passed, failed),
+                "success": failed == 0,
+                "passed": passed,
+                "failed": failed,
+                "deployments": deployments.iter().map(TestOutcome::to_json).collect::<Vec<_>>(),
+                "tests": tests.iter().map(TestOutcome::to_json).collect::<Vec<_>>(),
+            });
+            (if failed == 0 { 0 } else { 1 }, Some(result))
+        }
+        "fmt" => {
+            let mut argv: Vec<String> = args.into_iter().map(|x| x.clone()).collect();
+
+            let check = if let Ok(Some(_)) = consume_arg(&mut argv, &["--check"], false) {
+                true
+            } else {
+                false
+            };
+
+            if argv.len() < 2 {
+                eprintln!(
+                    "Usage: {} {} [--check] [program-file.clar]...",
+                    invoked_by, argv[0]
+                );
+                eprintln!("   Files are formatted in place. If the filename is `-`, the program is read from stdin, and written to stdout.");
+                eprintln!("   If --check is given, no file is written, and the command fails if any of them is not formatted.");
+                panic_test!();
+            }
+
+            let mut changed = vec![];
+            for filename in argv[1..].iter() {
+                let content = if filename == "-" {
+                    let mut buffer = String::new();
+                    friendly_expect(
+                        io::stdin().read_to_string(&mut buffer),
+                        "Error reading from stdin.",
+                    );
+                    buffer
+                } else {
+                    friendly_expect(
+                        fs::read_to_string(filename),
+                        &format!("Error reading file: {}", filename),
+                    )
+                };
+                let formatted = match format_source(&content) {
+                    Ok(formatted) => formatted,
+                    Err(error) => {
+                        let result = json!({
+                            "message": format!("Failed to parse {}", filename),
+                            "error": { "parse": error.to_string() },
+                            "success": false,
+                        });
+                        return (1, Some(result));
+                    }
+                };
+                if !is_equivalent(&content, &formatted) {
+                    let result = json!({
+                        "message": format!("Refusing to format {}: the formatted program differs from the original", filename),
+                        "success": false,
+                    });
+                    return (1, Some(result));
+                }
+                if filename == "-" && !check {
+                    print!("{}", formatted);
+                    continue;
+                }
+                if formatted != content {
+                    if !check {
+                        friendly_expect(
+                            fs::write(filename, &formatted),
+                            &format!("Error writing file: {}", filename),
+                        );
+                    }
+                    changed.push(filename.clone());
+                }
+            }
+
+            if check {
+                let result = json!({
+                    "message": if changed.is_empty() {
+                        "All files are formatted."
+                    } else {
+                        "Some files are not formatted."
+                    },
+                    "unformatted": changed,
+                    "success": changed.is_empty(),
+                });
+                (if changed.is_empty() { 0 } else { 1 }, Some(result))
+            } else if argv[1..].iter().any(|filename| filename == "-") {
+                (0, None)
+            } else {
+                let result = json!({
+                    "message": format!("Formatted {} files.", changed.len()),
+                    "formatted": changed,
+                    "success": true,
+                });
+                (0, Some(result))
+            }
+        }
         "make_lcov" => {
             let mut register_files = vec![];
             let mut coverage_files = vec![];
@@ -1953,6 +3367,148 @@ mod test {
         assert_eq!(exit, 0);
     }
 
+    #[test]
+    fn test_abi_diff() {
+        let old_name = format!(
+            "/tmp/test-abi-diff-old_{}.clar",
+            rand::thread_rng().gen::<i32>()
+        );
+        let new_name = format!(
+            "/tmp/test-abi-diff-new_{}.clar",
+            rand::thread_rng().gen::<i32>()
+        );
+        let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
+        fs::write(
+            &old_name,
+            "(define-map balances principal uint)
+             (define-read-only (get-balance (who principal))
+               (default-to u0 (map-get? balances who)))",
+        )
+        .unwrap();
+        fs::write(
+            &new_name,
+            "(define-map balances principal uint)
+             (define-read-only (get-balance (who principal))
+               (default-to u0 (map-get? balances who)))
+             (define-read-only (get-balances (who (list 10 principal)))
+               (map get-balance who))",
+        )
+        .unwrap();
+
+        eprintln!("additive changes");
+        let invoked = invoke_command(
+            "test",
+            &["abi-diff".to_string(), old_name.clone(), new_name.clone()],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        assert_eq!(result["compatible"], json!(true));
+        assert_eq!(result["breaking"], json!([]));
+        assert_eq!(
+            result["additive"],
+            json!([{ "item": "function", "name": "get-balances", "description": "added" }])
+        );
+
+        eprintln!("breaking changes");
+        let invoked = invoke_command(
+            "test",
+            &["abi-diff".to_string(), new_name.clone(), old_name.clone()],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 1);
+        assert_eq!(result["compatible"], json!(false));
+        assert_eq!(
+            result["breaking"],
+            json!([{ "item": "function", "name": "get-balances", "description": "removed" }])
+        );
+
+        eprintln!("against a deployed contract");
+        let invoked = invoke_command("test", &["initialize".to_string(), db_name.clone()]);
+        assert_eq!(invoked.0, 0);
+        let invoked = invoke_command(
+            "test",
+            &[
+                "launch".to_string(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.balances".to_string(),
+                new_name,
+                db_name.clone(),
+            ],
+        );
+        assert_eq!(invoked.0, 0);
+
+        let invoked = invoke_command(
+            "test",
+            &[
+                "abi-diff".to_string(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.balances".to_string(),
+                old_name,
+                db_name,
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 1);
+        assert_eq!(
+            result["breaking"],
+            json!([{ "item": "function", "name": "get-balances", "description": "removed" }])
+        );
+    }
+
+    #[test]
+    fn test_bindgen() {
+        let contract_name = format!("/tmp/test-bindgen_{}.clar", rand::thread_rng().gen::<i32>());
+        let interface_name = format!("/tmp/test-bindgen_{}.json", rand::thread_rng().gen::<i32>());
+        let output_name = format!("/tmp/test-bindgen_{}.rs", rand::thread_rng().gen::<i32>());
+        fs::write(
+            &contract_name,
+            "(define-map balances principal uint)
+             (define-read-only (get-balance (who principal))
+               (default-to u0 (map-get? balances who)))",
+        )
+        .unwrap();
+
+        let invoked = invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                contract_name,
+                "--output_analysis".to_string(),
+            ],
+        );
+        assert_eq!(invoked.0, 0);
+        fs::write(&interface_name, invoked.1.unwrap().to_string()).unwrap();
+
+        eprintln!("bindgen");
+        let invoked = invoke_command(
+            "test",
+            &[
+                "bindgen".to_string(),
+                interface_name.clone(),
+                "--contract_name".to_string(),
+                "balances".to_string(),
+                "--event".to_string(),
+                "deposit=(tuple (amount uint))".to_string(),
+                "--output".to_string(),
+                output_name.clone(),
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        assert_eq!(result["output"], json!(output_name));
+        let bindings = fs::read_to_string(&output_name).unwrap();
+        assert!(bindings.starts_with("//! Bindings for the `balances` contract"));
+        assert!(bindings.contains("use blockstack_lib::clarity_vm::bindgen::runtime"));
+        assert!(bindings.contains("pub fn get_balance(&self, who: PrincipalData)"));
+        assert!(bindings.contains("pub fn decode_deposit_event(value: Value)"));
+    }
+
     #[test]
     fn test_init_mainnet() {
         let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
@@ -2114,6 +3670,7 @@ mod test {
         assert_eq!(exit, 0);
         assert!(result["message"].as_str().unwrap().len() > 0);
         assert!(result["costs"] != json!(null));
+        assert!(result["cost_bounds"] != json!(null));
         assert!(result["assets"] == json!(null));
 
         eprintln!("launch names with costs and assets");
@@ -2157,6 +3714,7 @@ mod test {
         assert!(result["message"].as_str().unwrap().len() > 0);
         assert!(result["events"].as_array().unwrap().len() == 0);
         assert_eq!(result["output"], json!({"UInt": 1000}));
+        assert_eq!(result["output_json"], json!("1000"));
 
         eprintln!("eval tokens");
         let invoked = invoke_command(
@@ -2363,4 +3921,457 @@ mod test {
                 })
         );
     }
+
+    #[test]
+    fn test_check_cost_bounds() {
+        let clar_name = format!(
+            "/tmp/test-cost-bounds_{}.clar",
+            rand::thread_rng().gen::<i32>()
+        );
+        fs::write(
+            &clar_name,
+            "(define-map blobs uint (buff 1000000))
+             (define-private (blob-size (key uint) (total uint))
+               (+ total (len (default-to 0x (map-get? blobs key)))))
+             (define-read-only (total-size (keys (list 1000 uint)))
+               (fold blob-size keys u0))
+             (define-read-only (one-size (key uint))
+               (blob-size key u0))",
+        )
+        .unwrap();
+
+        eprintln!("check without costs");
+        let invoked = invoke_command("test", &["check".to_string(), clar_name.clone()]);
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        assert!(result["cost_bounds"] == json!(null));
+
+        eprintln!("check with costs");
+        let invoked = invoke_command(
+            "test",
+            &["check".to_string(), "--costs".to_string(), clar_name],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        let cost_bounds = result["cost_bounds"].as_object().unwrap();
+        assert_eq!(cost_bounds.len(), 2);
+
+        // each lookup may read up to 1MB, which is fine once, but not 1000 times
+        let one_size = &cost_bounds["one-size"];
+        assert_eq!(one_size["exceeds_block_limit"], json!(false));
+        assert!(one_size["cost"]["read_length"].as_u64().unwrap() >= 1_000_000);
+
+        let total_size = &cost_bounds["total-size"];
+        assert_eq!(total_size["exceeds_block_limit"], json!(true));
+        assert!(
+            total_size["cost"]["read_length"].as_u64().unwrap()
+                > BLOCK_LIMIT_MAINNET_205.read_length
+        );
+        assert_eq!(total_size["unbounded_calls"], json!([]));
+    }
+
+    #[test]
+    fn test_check_lint() {
+        let clar_name = format!("/tmp/test-lint_{}.clar", rand::thread_rng().gen::<i32>());
+        let config_name = format!("/tmp/test-lint_{}.json", rand::thread_rng().gen::<i32>());
+        fs::write(
+            &clar_name,
+            "(define-constant owner tx-sender)
+             (define-constant unused u0)
+             (define-data-var value uint u0)
+             (define-public (set-value (v uint))
+               (begin
+                 (asserts! (is-eq tx-sender owner) (err u1))
+                 (ok (var-set value v))))",
+        )
+        .unwrap();
+        fs::write(&config_name, r#"{"rules": {"unused-constant": false}}"#).unwrap();
+
+        eprintln!("check without lint");
+        let invoked = invoke_command("test", &["check".to_string(), clar_name.clone()]);
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        assert!(result["warnings"] == json!(null));
+
+        eprintln!("check with lint");
+        let invoked = invoke_command(
+            "test",
+            &["check".to_string(), "--lint".to_string(), clar_name.clone()],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        let warnings = result["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0]["level"], json!("Warning"));
+        assert_eq!(
+            warnings[0]["message"],
+            json!("constant `unused` is never used [unused-constant]")
+        );
+
+        eprintln!("check with lint config");
+        let invoked = invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                "--lint_config".to_string(),
+                config_name,
+                clar_name,
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        let warnings = result["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0]["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("[tx-sender-authorization]"));
+    }
+
+    #[test]
+    fn test_check_format_json() {
+        let clar_name = format!(
+            "/tmp/test-check-format_{}.clar",
+            rand::thread_rng().gen::<i32>()
+        );
+
+        eprintln!("check with parse errors");
+        fs::write(
+            &clar_name,
+            "(define-constant a 0x1g)\n(define-constant b 0xzz)",
+        )
+        .unwrap();
+        let invoked = invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                "--format=json".to_string(),
+                clar_name.clone(),
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 1);
+        assert_eq!(result["success"], json!(false));
+        let diagnostics = result["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        for (diagnostic, line) in diagnostics.iter().zip([1, 2]) {
+            assert_eq!(diagnostic["code"], json!("InvalidCharBuffer"));
+            assert_eq!(diagnostic["severity"], json!("error"));
+            assert_eq!(diagnostic["start_line"], json!(line));
+            assert!(diagnostic["end_column"].is_u64());
+        }
+
+        eprintln!("check with a type error");
+        fs::write(&clar_name, "(define-read-only (f)\n  (+ u1 1))").unwrap();
+        let invoked = invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                "--format=json".to_string(),
+                clar_name.clone(),
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 1);
+        assert_eq!(result["message"], json!("Checks failed."));
+        let diagnostics = result["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], json!("TypeError"));
+        #[cfg(feature = "developer-mode")]
+        assert_eq!(diagnostics[0]["start_line"], json!(2));
+
+        eprintln!("check with lint warnings");
+        fs::write(&clar_name, "(define-constant unused u1)").unwrap();
+        let invoked = invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                "--format=json".to_string(),
+                "--lint".to_string(),
+                clar_name,
+            ],
+        );
+        let exit = invoked.0;
+        let result = invoked.1.unwrap();
+
+        assert_eq!(exit, 0);
+        assert_eq!(result["success"], json!(true));
+        let diagnostics = result["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], json!("unused-constant"));
+        assert_eq!(diagnostics[0]["severity"], json!("warning"));
+    }
+
+    #[test]
+    fn test_execute_profile() {
+        let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
+        let profile_prefix = format!("/tmp/profile_{}", rand::thread_rng().gen::<i32>());
+
+        invoke_command("test", &["initialize".to_string(), db_name.clone()]);
+        let invoked = invoke_command(
+            "test",
+            &[
+                "launch".to_string(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(),
+                "../sample-contracts/tokens.clar".to_string(),
+                db_name.clone(),
+            ],
+        );
+        assert_eq!(invoked.0, 0);
+
+        eprintln!("execute tokens with profile");
+        let invoked = invoke_command(
+            "test",
+            &[
+                "execute".to_string(),
+                "--profile".to_string(),
+                profile_prefix.clone(),
+                db_name.clone(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(),
+                "mint!".to_string(),
+                "SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR".to_string(),
+                "u1000".to_string(),
+            ],
+        );
+        assert_eq!(invoked.0, 0);
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(format!("{}.json", profile_prefix)).unwrap())
+                .unwrap();
+        let functions = summary["functions"].as_array().unwrap();
+        // `mint!` is the most expensive, since it includes everything else
+        assert_eq!(functions[0]["function"], json!("mint!"));
+        assert_eq!(functions[0]["calls"], json!(1));
+        assert_eq!(functions[0]["total_cost"], summary["total"]);
+        assert!(functions
+            .iter()
+            .any(|function| function["function"] == json!("token-credit!")));
+
+        let folded = fs::read_to_string(format!("{}.runtime.folded", profile_prefix)).unwrap();
+        assert!(folded.lines().any(|line| line.starts_with(
+            "S1G2081040G2081040G2081040G208105NK8PE5.tokens::mint!;S1G2081040G2081040G2081040G208105NK8PE5.tokens::token-credit! "
+        )));
+        assert!(fs::metadata(format!("{}.write_count.folded", profile_prefix)).is_ok());
+    }
+
+    #[test]
+    fn test_debugger_print_is_free() {
+        let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
+
+        invoke_command("test", &["initialize".to_string(), db_name.clone()]);
+        let invoked = invoke_command(
+            "test",
+            &[
+                "launch".to_string(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(),
+                "../sample-contracts/tokens.clar".to_string(),
+                db_name.clone(),
+            ],
+        );
+        assert_eq!(invoked.0, 0);
+
+        let header_db = CLIHeadersDB::resume(&db_name).unwrap();
+        let contract_id =
+            QualifiedContractIdentifier::parse("S1G2081040G2081040G2081040G208105NK8PE5.tokens")
+                .unwrap();
+        let sender = PrincipalData::parse("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR").unwrap();
+        let mainnet = header_db.is_mainnet();
+        let mint = |header_db: CLIHeadersDB, debugger: Option<&mut Debugger>| {
+            let (header_db, (result, cost)) = in_cli_block(header_db, |header_db, marf| {
+                with_env_costs(mainnet, header_db, marf, None, debugger, None, |vm_env| {
+                    vm_env.execute_transaction(
+                        sender.clone(),
+                        None,
+                        contract_id.clone(),
+                        "mint!",
+                        &[SymbolicExpression::atom_value(Value::UInt(1000))],
+                    )
+                })
+            });
+            (header_db, result.unwrap().0, cost)
+        };
+
+        let (header_db, result, cost) = mint(header_db, None);
+
+        let mut commands = [
+            "print (get-balance tx-sender)",
+            "print (map-set tokens { account: tx-sender } { balance: u1 })",
+            "print (fold + (list u1 u2 u3 u4 u5 u6 u7 u8) u0)",
+            "continue",
+        ]
+        .into_iter()
+        .map(|command| command.to_string());
+        let mut debugger = Debugger::new(Box::new(move || commands.next()), Box::new(io::sink()));
+        let (_, debug_result, debug_cost) = mint(header_db, Some(&mut debugger));
+
+        assert_eq!(debug_result, result);
+        assert_eq!(debug_cost, cost);
+    }
+
+    #[test]
+    fn test_test_runner() {
+        let test_dir = format!("/tmp/clarity-tests_{}", rand::thread_rng().gen::<i32>());
+        let junit_file = format!("{}.xml", test_dir);
+        let lcov_file = format!("{}.lcov", test_dir);
+        fs::create_dir_all(&test_dir).unwrap();
+        // `caller` sorts before the `counter` contract it depends on
+        fs::write(
+            format!("{}/caller.clar", test_dir),
+            r#"
+(define-public (test-calls-counter)
+  (contract-call? .counter increment))
+(define-public (test-returns-err)
+  (err u1))
+(define-public (test-runtime-error)
+  (ok (unwrap-panic (element-at (list u1) u1))))
+(define-public (not-a-test)
+  (err u2))
+"#,
+        )
+        .unwrap();
+        fs::write(
+            format!("{}/counter.clar", test_dir),
+            r#"
+(define-data-var counter uint u0)
+(define-data-var last-height uint u0)
+(define-public (increment)
+  (begin
+    (var-set counter (+ (var-get counter) u1))
+    (ok (var-get counter))))
+(define-public (test-increment)
+  (begin
+    (var-set last-height block-height)
+    (increment)))
+(define-public (test-state-persists)
+  (begin
+    (asserts! (is-eq (var-get counter) u2) (err (var-get counter)))
+    (asserts! (is-eq block-height (+ (var-get last-height) u3)) (err block-height))
+    (ok true)))
+"#,
+        )
+        .unwrap();
+        // not a valid contract name: reported as a failed deployment
+        fs::write(format!("{}/2-invalid.clar", test_dir), "(ok true)").unwrap();
+
+        let invoked = invoke_command(
+            "test",
+            &[
+                "test".to_string(),
+                "--testnet".to_string(),
+                "--blocks-between-tests".to_string(),
+                "2".to_string(),
+                "--junit".to_string(),
+                junit_file.clone(),
+                "--coverage".to_string(),
+                lcov_file.clone(),
+                test_dir.clone(),
+            ],
+        );
+        let result = invoked.1.unwrap();
+        eprintln!("{}", serde_json::to_string(&result).unwrap());
+
+        assert_eq!(invoked.0, 1);
+        assert_eq!(result["success"], json!(false));
+        assert_eq!(result["passed"], json!(3));
+        assert_eq!(result["failed"], json!(3));
+        let deployments = result["deployments"].as_array().unwrap();
+        assert_eq!(deployments.len(), 3);
+        assert_eq!(
+            deployments[0]["contract"],
+            json!("S1G2081040G2081040G2081040G208105NK8PE5.2-invalid")
+        );
+        assert_eq!(deployments[0]["success"], json!(false));
+        assert!(deployments[0]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid contract name"));
+        assert!(deployments[1..]
+            .iter()
+            .all(|deployment| deployment["success"] == json!(true)));
+
+        let tests = result["tests"].as_array().unwrap();
+        let outcomes: Vec<_> = tests
+            .iter()
+            .map(|test| {
+                (
+                    test["test"].as_str().unwrap(),
+                    test["success"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("test-calls-counter", true),
+                ("test-returns-err", false),
+                ("test-runtime-error", false),
+                ("test-increment", true),
+                ("test-state-persists", true),
+            ]
+        );
+        assert_eq!(tests[1]["output"], json!(Value::UInt(1)));
+        assert!(tests[2]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Runtime error"));
+
+        let junit = fs::read_to_string(&junit_file).unwrap();
+        assert!(junit.contains("<testsuites name=\"clarity\" tests=\"6\" failures=\"3\">"));
+        assert!(junit.contains(
+            "<testcase classname=\"S1G2081040G2081040G2081040G208105NK8PE5.2-invalid\" name=\"deploy\""
+        ));
+        assert!(junit.contains(
+            "<testsuite name=\"S1G2081040G2081040G2081040G208105NK8PE5.caller\" tests=\"3\" failures=\"2\">"
+        ));
+        assert!(junit.contains("<failure message=\"Returned an (err ...)\"/>"));
+
+        let lcov = fs::read_to_string(&lcov_file).unwrap();
+        assert!(lcov.contains(&format!("SF:{}/counter.clar", test_dir)));
+    }
+
+    #[test]
+    fn test_fmt() {
+        let clar_name = format!("/tmp/fmt_{}.clar", rand::thread_rng().gen::<i32>());
+        fs::write(
+            &clar_name,
+            "(define-data-var counter   uint u0) ;; the count\n(define-public (increment) (begin (var-set counter (+ (var-get counter) u1)) (ok true)))\n",
+        )
+        .unwrap();
+
+        let invoked = invoke_command(
+            "test",
+            &["fmt".to_string(), "--check".to_string(), clar_name.clone()],
+        );
+        assert_eq!(invoked.0, 1);
+        assert_eq!(invoked.1.unwrap()["unformatted"], json!([clar_name]));
+
+        let invoked = invoke_command("test", &["fmt".to_string(), clar_name.clone()]);
+        assert_eq!(invoked.0, 0);
+        assert_eq!(invoked.1.unwrap()["formatted"], json!([clar_name]));
+        assert_eq!(
+            fs::read_to_string(&clar_name).unwrap(),
+            "(define-data-var counter uint u0) ;; the count\n(define-public (increment)\n  (begin\n    (var-set counter (+ (var-get counter) u1))\n    (ok true)))\n"
+        );
+
+        let invoked = invoke_command(
+            "test",
+            &["fmt".to_string(), "--check".to_string(), clar_name.clone()],
+        );
+        assert_eq!(invoked.0, 0);
+        assert_eq!(invoked.1.unwrap()["success"], json!(true));
+    }
 }
@@ -0,0 +1,35 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+extern crate blockstack_lib;
+
+use std::{io, process};
+
+use blockstack_lib::clarity::vm::tooling::lsp::LanguageServer;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    let mut server = LanguageServer::default();
+    match server.run(stdin.lock(), stdout.lock()) {
+        Ok(exit_code) => process::exit(exit_code),
+        Err(e) => {
+            eprintln!("clarity-lsp: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generates typed Rust bindings from a contract interface.
+//!
+//! The generated module has a struct for every tuple type in the interface, and a
+//! `Contract` type with, for every public and read-only function:
+//!
+//! * `<function>(&self, ...)`, which builds the payload of a transaction calling it;
+//! * `<function>_args(...)`, which encodes its arguments;
+//! * `decode_<function>(value)`, which decodes its result.
+//!
+//! The types of `print` events are not part of contract interfaces, so they are given
+//! separately, and get a `decode_<name>_event(value)` decoder each.
+
+use std::fmt::Write;
+
+use clarity::vm::analysis::contract_interface_builder::{
+    ContractInterface, ContractInterfaceAtomType, ContractInterfaceFunction,
+    ContractInterfaceFunctionAccess, ContractInterfaceTupleEntryType,
+};
+use clarity::vm::ast::{self, ASTRules};
+use clarity::vm::types::{QualifiedContractIdentifier, TypeSignature};
+use clarity::vm::ClarityVersion;
+use stacks_common::types::StacksEpochId;
+
+pub mod runtime;
+
+#[cfg(test)]
+mod tests;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingOptions {
+    /// Path of the `stackslib` crate in the code using the bindings
+    pub crate_path: String,
+    /// Name of the contract, for documentation
+    pub contract_name: Option<String>,
+    /// Names and types of the values printed by the contract
+    pub events: Vec<(String, ContractInterfaceAtomType)>,
+}
+
+impl Default for BindingOptions {
+    fn default() -> Self {
+        BindingOptions {
+            crate_path: "blockstack_lib".into(),
+            contract_name: None,
+            events: vec![],
+        }
+    }
+}
+
+/// Parse the type of an event, written in Clarity syntax, e.g. `(tuple (amount uint))`
+pub fn parse_event_type(type_repr: &str) -> Result<ContractInterfaceAtomType, String> {
+    let epoch = StacksEpochId::latest();
+    let ast = ast::build_ast_with_rules(
+        &QualifiedContractIdentifier::transient(),
+        type_repr,
+        &mut (),
+        ClarityVersion::latest(),
+        epoch,
+        ASTRules::PrecheckSize,
+    )
+    .map_err(|e| e.to_string())?;
+    let [expression] = ast.expressions.as_slice() else {
+        return Err(format!("Expected a single type, found `{}`", type_repr));
+    };
+    let signature =
+        TypeSignature::parse_type_repr(epoch, expression, &mut ()).map_err(|e| e.to_string())?;
+    Ok(ContractInterfaceAtomType::from_type_signature(&signature))
+}
+
+/// Generate the source of a Rust module binding a contract with the given interface
+pub fn generate_bindings(interface: &ContractInterface, options: &BindingOptions) -> String {
+    let mut generator = Generator::default();
+    let functions: Vec<_> = interface
+        .functions
+        .iter()
+        .filter(|function| function.access != ContractInterfaceFunctionAccess::private)
+        .collect();
+
+    // register the tuple types first, in the order they appear
+    for function in functions.iter() {
+        let prefix = pascal_case(&function.name);
+        for arg in function.args.iter() {
+            generator.register(
+                &arg.type_f,
+                &format!("{}{}", prefix, pascal_case(&arg.name)),
+            );
+        }
+        generator.register(&function.outputs.type_f, &format!("{}Output", prefix));
+    }
+    for (name, type_f) in options.events.iter() {
+        generator.register(type_f, &format!("{}Event", pascal_case(name)));
+    }
+
+    let mut body = String::new();
+    for index in 0..generator.structs.len() {
+        body.push_str(&generator.render_struct(index));
+    }
+
+    body.push_str(
+        "/// A deployed instance of the contract\n\
+         #[derive(Debug, Clone, PartialEq)]\n\
+         pub struct Contract {\n\
+         pub contract_identifier: QualifiedContractIdentifier,\n\
+         }\n\n\
+         impl Contract {\n",
+    );
+    for function in functions.iter() {
+        body.push_str(&generator.render_function(function));
+    }
+    for (name, type_f) in options.events.iter() {
+        let _ = write!(
+            body,
+            "/// Decode the value of a `{name}` event: `{type_f}`\n\
+             pub fn decode_{ident}_event(value: Value) -> Result<{rust_type}, BindingError> {{\n\
+             {decode}\n\
+             }}\n\n",
+            ident = snake_case(name),
+            rust_type = generator.rust_type(type_f),
+            decode = generator.decode_expr(type_f, "value"),
+        );
+    }
+    body.push_str("}\n");
+
+    let mut source = String::new();
+    match options.contract_name.as_ref() {
+        Some(name) => {
+            let _ = writeln!(
+                source,
+                "//! Bindings for the `{}` contract, generated by `clarity-cli bindgen`.",
+                name
+            );
+        }
+        None => source.push_str("//! Contract bindings, generated by `clarity-cli bindgen`.\n"),
+    }
+    source.push_str("//!\n//! Do not edit this file by hand.\n\n");
+    let crate_path = &options.crate_path;
+    if body.contains("TransactionPayload") {
+        let _ = writeln!(
+            source,
+            "use {}::chainstate::stacks::TransactionPayload;",
+            crate_path
+        );
+    }
+    let types: Vec<_> = ["PrincipalData", "QualifiedContractIdentifier", "Value"]
+        .into_iter()
+        .filter(|name| body.contains(name))
+        .collect();
+    let _ = writeln!(
+        source,
+        "use {}::clarity::vm::types::{{{}}};",
+        crate_path,
+        types.join(", ")
+    );
+    let _ = writeln!(
+        source,
+        "use {}::clarity_vm::bindgen::runtime::{{self, BindingError}};\n",
+        crate_path
+    );
+    source.push_str(&body);
+    source
+}
+
+#[derive(Default)]
+struct Generator {
+    /// Names and entries of the generated tuple structs
+    structs: Vec<(String, Vec<ContractInterfaceTupleEntryType>)>,
+}
+
+impl Generator {
+    /// Register the structs needed by a type, naming them after `hint`
+    fn register(&mut self, type_f: &ContractInterfaceAtomType, hint: &str) {
+        use ContractInterfaceAtomType::*;
+
+        match type_f {
+            optional(type_f) => self.register(type_f, hint),
+            response { ok, error } => {
+                self.register(ok, &format!("{}Ok", hint));
+                self.register(error, &format!("{}Err", hint));
+            }
+            list { type_f, .. } => self.register(type_f, &format!("{}Item", hint)),
+            tuple(entries) => {
+                if self.struct_name(entries).is_some() {
+                    return;
+                }
+                let mut name = hint.to_string();
+                let mut suffix = 1;
+                while self.structs.iter().any(|(other, _)| *other == name) || name == "Contract" {
+                    suffix += 1;
+                    name = format!("{}{}", hint, suffix);
+                }
+                self.structs.push((name.clone(), entries.clone()));
+                for entry in entries.iter() {
+                    self.register(
+                        &entry.type_f,
+                        &format!("{}{}", name, pascal_case(&entry.name)),
+                    );
+                }
+            }
+            none
+            | int128
+            | uint128
+            | bool
+            | principal
+            | buffer { .. }
+            | string_utf8 { .. }
+            | string_ascii { .. }
+            | trait_reference => {}
+        }
+    }
+
+    fn struct_name(&self, entries: &[ContractInterfaceTupleEntryType]) -> Option<&str> {
+        self.structs
+            .iter()
+            .find(|(_, other)| other == entries)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn tuple_struct(&self, entries: &[ContractInterfaceTupleEntryType]) -> &str {
+        self.struct_name(entries)
+            .expect("BUG: tuple type was not registered")
+    }
+
+    fn rust_type(&self, type_f: &ContractInterfaceAtomType) -> String {
+        use ContractInterfaceAtomType::*;
+
+        match type_f {
+            none => "Value".into(),
+            int128 => "i128".into(),
+            uint128 => "u128".into(),
+            bool => "bool".into(),
+            principal | trait_reference => "PrincipalData".into(),
+            buffer { .. } => "Vec<u8>".into(),
+            string_utf8 { .. } | string_ascii { .. } => "String".into(),
+            tuple(entries) => self.tuple_struct(entries).into(),
+            optional(type_f) => format!("Option<{}>", self.rust_type(type_f)),
+            response { ok, error } => {
+                format!("Result<{}, {}>", self.rust_type(ok), self.rust_type(error))
+            }
+            list { type_f, .. } => format!("Vec<{}>", self.rust_type(type_f)),
+        }
+    }
+
+    /// An expression encoding `expr` as a Clarity value of the given type
+    fn encode_expr(&self, type_f: &ContractInterfaceAtomType, expr: &str) -> String {
+        use ContractInterfaceAtomType::*;
+
+        match type_f {
+            none => format!("runtime::encode_value({})", expr),
+            int128 => format!("runtime::encode_int({})", expr),
+            uint128 => format!("runtime::encode_uint({})", expr),
+            bool => format!("runtime::encode_bool({})", expr),
+            principal | trait_reference => format!("runtime::encode_principal({})", expr),
+            buffer { length } => format!("runtime::encode_buff({}, {})", expr, length),
+            string_utf8 { length } => format!("runtime::encode_string_utf8({}, {})", expr, length),
+            string_ascii { length } => {
+                format!("runtime::encode_string_ascii({}, {})", expr, length)
+            }
+            tuple(entries) => format!("{}::into_value({})", self.tuple_struct(entries), expr),
+            optional(type_f) => format!(
+                "runtime::encode_optional({}, {})",
+                expr,
+                self.encoder(type_f)
+            ),
+            response { ok, error } => format!(
+                "runtime::encode_response({}, {}, {})",
+                expr,
+                self.encoder(ok),
+                self.encoder(error)
+            ),
+            list { type_f, length } => format!(
+                "runtime::encode_list({}, {}, {})",
+                expr,
+                length,
+                self.encoder(type_f)
+            ),
+        }
+    }
+
+    /// An expression decoding the Clarity value `expr` of the given type
+    fn decode_expr(&self, type_f: &ContractInterfaceAtomType, expr: &str) -> String {
+        use ContractInterfaceAtomType::*;
+
+        match type_f {
+            none => format!("runtime::decode_value({})", expr),
+            int128 => format!("runtime::decode_int({})", expr),
+            uint128 => format!("runtime::decode_uint({})", expr),
+            bool => format!("runtime::decode_bool({})", expr),
+            principal | trait_reference => format!("runtime::decode_principal({})", expr),
+            buffer { .. } => format!("runtime::decode_buff({})", expr),
+            string_utf8 { .. } => format!("runtime::decode_string_utf8({})", expr),
+            string_ascii { .. } => format!("runtime::decode_string_ascii({})", expr),
+            tuple(entries) => format!("{}::from_value({})", self.tuple_struct(entries), expr),
+            optional(type_f) => format!(
+                "runtime::decode_optional({}, {})",
+                expr,
+                self.decoder(type_f)
+            ),
+            response { ok, error } => format!(
+                "runtime::decode_response({}, {}, {})",
+                expr,
+                self.decoder(ok),
+                self.decoder(error)
+            ),
+            list { type_f, .. } => {
+                format!("runtime::decode_list({}, {})", expr, self.decoder(type_f))
+            }
+        }
+    }
+
+    /// A function encoding values of the given type
+    fn encoder(&self, type_f: &ContractInterfaceAtomType) -> String {
+        as_function(self.encode_expr(type_f, "x"))
+    }
+
+    /// A function decoding values of the given type
+    fn decoder(&self, type_f: &ContractInterfaceAtomType) -> String {
+        as_function(self.decode_expr(type_f, "x"))
+    }
+
+    fn render_struct(&self, index: usize) -> String {
+        let (name, entries) = &self.structs[index];
+        let mut fields = String::new();
+        let mut encode = String::new();
+        let mut decode = String::new();
+        for entry in entries.iter() {
+            let ident = snake_case(&entry.name);
+            let _ = writeln!(fields, "pub {}: {},", ident, self.rust_type(&entry.type_f));
+            let _ = writeln!(
+                encode,
+                "(\"{}\", {}?),",
+                entry.name,
+                self.encode_expr(&entry.type_f, &format!("self.{}", ident))
+            );
+            let _ = writeln!(
+                decode,
+                "{}: {}?,",
+                ident,
+                self.decode_expr(
+                    &entry.type_f,
+                    &format!("runtime::take_field(&mut tuple, \"{}\")?", entry.name)
+                )
+            );
+        }
+        format!(
+            "/// `{type_f}`\n\
+             #[derive(Debug, Clone, PartialEq)]\n\
+             pub struct {name} {{\n\
+             {fields}\
+             }}\n\n\
+             impl {name} {{\n\
+             pub fn into_value(self) -> Result<Value, BindingError> {{\n\
+             runtime::encode_tuple(vec![\n\
+             {encode}\
+             ])\n\
+             }}\n\n\
+             pub fn from_value(value: Value) -> Result<Self, BindingError> {{\n\
+             let mut tuple = runtime::decode_tuple(value)?;\n\
+             Ok(Self {{\n\
+             {decode}\
+             }})\n\
+             }}\n\
+             }}\n\n",
+            type_f = ContractInterfaceAtomType::tuple(entries.clone()),
+        )
+    }
+
+    fn render_function(&self, function: &ContractInterfaceFunction) -> String {
+        let ident = snake_case(&function.name);
+        let define = match function.access {
+            ContractInterfaceFunctionAccess::read_only => "define-read-only",
+            _ => "define-public",
+        };
+        let signature: Vec<_> = function
+            .args
+            .iter()
+            .map(|arg| format!(" ({} {})", arg.name, arg.type_f))
+            .collect();
+        let doc = format!(
+            "`({} ({}{}) {})`",
+            define,
+            function.name,
+            signature.join(""),
+            function.outputs.type_f
+        );
+
+        let params: Vec<_> = function
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", snake_case(&arg.name), self.rust_type(&arg.type_f)))
+            .collect();
+        let names: Vec<_> = function
+            .args
+            .iter()
+            .map(|arg| snake_case(&arg.name))
+            .collect();
+        let encoded: Vec<_> = function
+            .args
+            .iter()
+            .map(|arg| format!("{}?", self.encode_expr(&arg.type_f, &snake_case(&arg.name))))
+            .collect();
+        // `&self` counts towards clippy's limit on arguments
+        let allow = if function.args.len() >= 7 {
+            "#[allow(clippy::too_many_arguments)]\n"
+        } else {
+            ""
+        };
+        let self_params = if params.is_empty() {
+            "&self".to_string()
+        } else {
+            format!("&self, {}", params.join(", "))
+        };
+
+        format!(
+            "/// Build the payload of a transaction calling\n\
+             /// {doc}\n\
+             {allow}\
+             pub fn {ident}({self_params}) -> Result<TransactionPayload, BindingError> {{\n\
+             runtime::contract_call(\n\
+             &self.contract_identifier,\n\
+             \"{name}\",\n\
+             Self::{ident}_args({names})?,\n\
+             )\n\
+             }}\n\n\
+             /// Encode the arguments of `{name}`\n\
+             {allow}\
+             pub fn {ident}_args({params}) -> Result<Vec<Value>, BindingError> {{\n\
+             Ok(vec![{encoded}])\n\
+             }}\n\n\
+             /// Decode the result of `{name}`\n\
+             pub fn decode_{ident}(value: Value) -> Result<{output}, BindingError> {{\n\
+             {decode}\n\
+             }}\n\n",
+            name = function.name,
+            names = names.join(", "),
+            params = params.join(", "),
+            encoded = encoded.join(", "),
+            output = self.rust_type(&function.outputs.type_f),
+            decode = self.decode_expr(&function.outputs.type_f, "value"),
+        )
+    }
+}
+
+/// Turn an expression applying a function to `x` into that function
+fn as_function(expr: String) -> String {
+    match expr.strip_suffix("(x)") {
+        Some(function) if !function.contains('(') => function.to_string(),
+        _ => format!("|x| {}", expr),
+    }
+}
+
+/// Split a Clarity name into its words, e.g. `get-balance?` or `getBalance`
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lowercase = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && previous_lowercase {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// A Rust identifier for a Clarity name, e.g. `get_balance` for `get-balance?`
+fn snake_case(name: &str) -> String {
+    let ident = words(name).join("_");
+    if RUST_KEYWORDS.contains(&ident.as_str()) {
+        format!("{}_", ident)
+    } else {
+        ident
+    }
+}
+
+/// A Rust type name for a Clarity name, e.g. `GetBalance` for `get-balance?`
+fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
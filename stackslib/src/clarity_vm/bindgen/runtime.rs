@@ -0,0 +1,327 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversions between Rust and Clarity values used by generated contract bindings.
+//!
+//! Encoders check the length bounds of the types declared by the contract, so that
+//! oversized arguments are caught before a transaction is signed.
+
+use std::{error, fmt};
+
+use clarity::vm::errors::Error as ClarityError;
+use clarity::vm::types::serialization::SerializationError;
+use clarity::vm::types::{
+    ASCIIData, BuffData, CharType, ListData, OptionalData, PrincipalData,
+    QualifiedContractIdentifier, ResponseData, SequenceData, TupleData, UTF8Data, Value,
+};
+use clarity::vm::ClarityName;
+
+use crate::chainstate::stacks::{TransactionContractCall, TransactionPayload};
+
+#[derive(Debug)]
+pub enum BindingError {
+    /// A value did not have the type the bindings expected
+    UnexpectedValue {
+        expected: &'static str,
+        value: Value,
+    },
+    /// A tuple lacked a field
+    MissingField(String),
+    /// A sequence is longer than its declared type allows
+    TooLong { max_length: u32, length: usize },
+    /// Clarity rejected a value, or a name
+    Clarity(ClarityError),
+    /// A serialized value could not be decoded
+    Serialization(SerializationError),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingError::UnexpectedValue { expected, value } => {
+                write!(f, "expected a value of type {}, found {}", expected, value)
+            }
+            BindingError::MissingField(name) => write!(f, "missing tuple field `{}`", name),
+            BindingError::TooLong { max_length, length } => write!(
+                f,
+                "sequence of length {} exceeds the maximum length {}",
+                length, max_length
+            ),
+            BindingError::Clarity(e) => write!(f, "{}", e),
+            BindingError::Serialization(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for BindingError {}
+
+impl From<ClarityError> for BindingError {
+    fn from(e: ClarityError) -> Self {
+        BindingError::Clarity(e)
+    }
+}
+
+fn unexpected<T>(expected: &'static str, value: Value) -> Result<T, BindingError> {
+    Err(BindingError::UnexpectedValue { expected, value })
+}
+
+fn check_length(max_length: u32, length: usize) -> Result<(), BindingError> {
+    if length > max_length as usize {
+        return Err(BindingError::TooLong { max_length, length });
+    }
+    Ok(())
+}
+
+/// Build the payload of a transaction calling `function_name` of a contract
+pub fn contract_call(
+    contract_identifier: &QualifiedContractIdentifier,
+    function_name: &str,
+    function_args: Vec<Value>,
+) -> Result<TransactionPayload, BindingError> {
+    let function_name = ClarityName::try_from(function_name.to_string())
+        .map_err(|e| BindingError::Clarity(e.into()))?;
+    Ok(TransactionPayload::ContractCall(TransactionContractCall {
+        address: contract_identifier.issuer.clone().into(),
+        contract_name: contract_identifier.name.clone(),
+        function_name,
+        function_args,
+    }))
+}
+
+pub fn encode_value(value: Value) -> Result<Value, BindingError> {
+    Ok(value)
+}
+
+pub fn encode_int(value: i128) -> Result<Value, BindingError> {
+    Ok(Value::Int(value))
+}
+
+pub fn encode_uint(value: u128) -> Result<Value, BindingError> {
+    Ok(Value::UInt(value))
+}
+
+pub fn encode_bool(value: bool) -> Result<Value, BindingError> {
+    Ok(Value::Bool(value))
+}
+
+pub fn encode_principal(value: PrincipalData) -> Result<Value, BindingError> {
+    Ok(Value::Principal(value))
+}
+
+pub fn encode_buff(value: Vec<u8>, max_length: u32) -> Result<Value, BindingError> {
+    check_length(max_length, value.len())?;
+    Ok(Value::buff_from(value)?)
+}
+
+pub fn encode_string_ascii(value: String, max_length: u32) -> Result<Value, BindingError> {
+    check_length(max_length, value.len())?;
+    Ok(Value::string_ascii_from_bytes(value.into_bytes())?)
+}
+
+pub fn encode_string_utf8(value: String, max_length: u32) -> Result<Value, BindingError> {
+    check_length(max_length, value.chars().count())?;
+    Ok(Value::string_utf8_from_bytes(value.into_bytes())?)
+}
+
+pub fn encode_optional<T, F>(value: Option<T>, encode: F) -> Result<Value, BindingError>
+where
+    F: Fn(T) -> Result<Value, BindingError>,
+{
+    match value {
+        Some(value) => Ok(Value::some(encode(value)?)?),
+        None => Ok(Value::none()),
+    }
+}
+
+pub fn encode_response<T, E, F, G>(
+    value: Result<T, E>,
+    encode_ok: F,
+    encode_err: G,
+) -> Result<Value, BindingError>
+where
+    F: Fn(T) -> Result<Value, BindingError>,
+    G: Fn(E) -> Result<Value, BindingError>,
+{
+    match value {
+        Ok(value) => Ok(Value::okay(encode_ok(value)?)?),
+        Err(value) => Ok(Value::error(encode_err(value)?)?),
+    }
+}
+
+pub fn encode_list<T, F>(value: Vec<T>, max_length: u32, encode: F) -> Result<Value, BindingError>
+where
+    F: Fn(T) -> Result<Value, BindingError>,
+{
+    check_length(max_length, value.len())?;
+    let items = value
+        .into_iter()
+        .map(encode)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::cons_list_unsanitized(items)?)
+}
+
+pub fn encode_tuple(fields: Vec<(&str, Value)>) -> Result<Value, BindingError> {
+    let fields = fields
+        .into_iter()
+        .map(|(name, value)| {
+            let name = ClarityName::try_from(name.to_string())
+                .map_err(|e| BindingError::Clarity(e.into()))?;
+            Ok((name, value))
+        })
+        .collect::<Result<Vec<_>, BindingError>>()?;
+    Ok(Value::Tuple(TupleData::from_data(fields)?))
+}
+
+pub fn decode_value(value: Value) -> Result<Value, BindingError> {
+    Ok(value)
+}
+
+pub fn decode_int(value: Value) -> Result<i128, BindingError> {
+    match value {
+        Value::Int(value) => Ok(value),
+        value => unexpected("int", value),
+    }
+}
+
+pub fn decode_uint(value: Value) -> Result<u128, BindingError> {
+    match value {
+        Value::UInt(value) => Ok(value),
+        value => unexpected("uint", value),
+    }
+}
+
+pub fn decode_bool(value: Value) -> Result<bool, BindingError> {
+    match value {
+        Value::Bool(value) => Ok(value),
+        value => unexpected("bool", value),
+    }
+}
+
+/// Decodes principals, including the contracts passed for trait references
+pub fn decode_principal(value: Value) -> Result<PrincipalData, BindingError> {
+    match value {
+        Value::Principal(value) => Ok(value),
+        Value::CallableContract(callable) => {
+            Ok(PrincipalData::Contract(callable.contract_identifier))
+        }
+        value => unexpected("principal", value),
+    }
+}
+
+pub fn decode_buff(value: Value) -> Result<Vec<u8>, BindingError> {
+    match value {
+        Value::Sequence(SequenceData::Buffer(BuffData { data })) => Ok(data),
+        value => unexpected("buff", value),
+    }
+}
+
+pub fn decode_string_ascii(value: Value) -> Result<String, BindingError> {
+    match value {
+        Value::Sequence(SequenceData::String(CharType::ASCII(ASCIIData { data }))) => {
+            // ASCII strings are valid UTF-8
+            String::from_utf8(data).or_else(|e| {
+                unexpected(
+                    "string-ascii",
+                    Value::Sequence(SequenceData::String(CharType::ASCII(ASCIIData {
+                        data: e.into_bytes(),
+                    }))),
+                )
+            })
+        }
+        value => unexpected("string-ascii", value),
+    }
+}
+
+pub fn decode_string_utf8(value: Value) -> Result<String, BindingError> {
+    match value {
+        Value::Sequence(SequenceData::String(CharType::UTF8(UTF8Data { data }))) => {
+            let bytes: Vec<u8> = data.iter().flatten().copied().collect();
+            String::from_utf8(bytes).or_else(|_| {
+                unexpected(
+                    "string-utf8",
+                    Value::Sequence(SequenceData::String(CharType::UTF8(UTF8Data { data }))),
+                )
+            })
+        }
+        value => unexpected("string-utf8", value),
+    }
+}
+
+pub fn decode_optional<T, F>(value: Value, decode: F) -> Result<Option<T>, BindingError>
+where
+    F: Fn(Value) -> Result<T, BindingError>,
+{
+    match value {
+        Value::Optional(OptionalData { data: Some(value) }) => Ok(Some(decode(*value)?)),
+        Value::Optional(OptionalData { data: None }) => Ok(None),
+        value => unexpected("optional", value),
+    }
+}
+
+pub fn decode_response<T, E, F, G>(
+    value: Value,
+    decode_ok: F,
+    decode_err: G,
+) -> Result<Result<T, E>, BindingError>
+where
+    F: Fn(Value) -> Result<T, BindingError>,
+    G: Fn(Value) -> Result<E, BindingError>,
+{
+    match value {
+        Value::Response(ResponseData {
+            committed: true,
+            data,
+        }) => Ok(Ok(decode_ok(*data)?)),
+        Value::Response(ResponseData {
+            committed: false,
+            data,
+        }) => Ok(Err(decode_err(*data)?)),
+        value => unexpected("response", value),
+    }
+}
+
+pub fn decode_list<T, F>(value: Value, decode: F) -> Result<Vec<T>, BindingError>
+where
+    F: Fn(Value) -> Result<T, BindingError>,
+{
+    match value {
+        Value::Sequence(SequenceData::List(ListData { data, .. })) => {
+            data.into_iter().map(decode).collect()
+        }
+        value => unexpected("list", value),
+    }
+}
+
+pub fn decode_tuple(value: Value) -> Result<TupleData, BindingError> {
+    match value {
+        Value::Tuple(tuple) => Ok(tuple),
+        value => unexpected("tuple", value),
+    }
+}
+
+/// Takes a field out of a decoded tuple
+pub fn take_field(tuple: &mut TupleData, name: &str) -> Result<Value, BindingError> {
+    tuple
+        .data_map
+        .remove(name)
+        .ok_or_else(|| BindingError::MissingField(name.to_string()))
+}
+
+/// Decodes a value from its hex-encoded consensus serialization, as found in the
+/// results of read-only calls and the payloads of contract events
+pub fn value_from_hex(hex: &str) -> Result<Value, BindingError> {
+    Value::try_deserialize_hex_untyped(hex).map_err(BindingError::Serialization)
+}
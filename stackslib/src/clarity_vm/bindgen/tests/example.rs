@@ -0,0 +1,241 @@
+//! Bindings for the `example` contract, generated by `clarity-cli bindgen`.
+//!
+//! Do not edit this file by hand.
+
+use crate::chainstate::stacks::TransactionPayload;
+use crate::clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use crate::clarity_vm::bindgen::runtime::{self, BindingError};
+
+/// `(tuple (bio (string-utf8 64)) (name (string-ascii 16)) (tags (list 4 (string-ascii 8))))`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetProfileProfile {
+    pub bio: String,
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+impl SetProfileProfile {
+    pub fn into_value(self) -> Result<Value, BindingError> {
+        runtime::encode_tuple(vec![
+            ("bio", runtime::encode_string_utf8(self.bio, 64)?),
+            ("name", runtime::encode_string_ascii(self.name, 16)?),
+            (
+                "tags",
+                runtime::encode_list(self.tags, 4, |x| runtime::encode_string_ascii(x, 8))?,
+            ),
+        ])
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, BindingError> {
+        let mut tuple = runtime::decode_tuple(value)?;
+        Ok(Self {
+            bio: runtime::decode_string_utf8(runtime::take_field(&mut tuple, "bio")?)?,
+            name: runtime::decode_string_ascii(runtime::take_field(&mut tuple, "name")?)?,
+            tags: runtime::decode_list(
+                runtime::take_field(&mut tuple, "tags")?,
+                runtime::decode_string_ascii,
+            )?,
+        })
+    }
+}
+
+/// `(tuple (delta int) (holders (list 1 (tuple (balance uint) (owner principal)))) (total uint))`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetStatsOutputOk {
+    pub delta: i128,
+    pub holders: Vec<GetStatsOutputOkHoldersItem>,
+    pub total: u128,
+}
+
+impl GetStatsOutputOk {
+    pub fn into_value(self) -> Result<Value, BindingError> {
+        runtime::encode_tuple(vec![
+            ("delta", runtime::encode_int(self.delta)?),
+            (
+                "holders",
+                runtime::encode_list(self.holders, 1, GetStatsOutputOkHoldersItem::into_value)?,
+            ),
+            ("total", runtime::encode_uint(self.total)?),
+        ])
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, BindingError> {
+        let mut tuple = runtime::decode_tuple(value)?;
+        Ok(Self {
+            delta: runtime::decode_int(runtime::take_field(&mut tuple, "delta")?)?,
+            holders: runtime::decode_list(
+                runtime::take_field(&mut tuple, "holders")?,
+                GetStatsOutputOkHoldersItem::from_value,
+            )?,
+            total: runtime::decode_uint(runtime::take_field(&mut tuple, "total")?)?,
+        })
+    }
+}
+
+/// `(tuple (balance uint) (owner principal))`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetStatsOutputOkHoldersItem {
+    pub balance: u128,
+    pub owner: PrincipalData,
+}
+
+impl GetStatsOutputOkHoldersItem {
+    pub fn into_value(self) -> Result<Value, BindingError> {
+        runtime::encode_tuple(vec![
+            ("balance", runtime::encode_uint(self.balance)?),
+            ("owner", runtime::encode_principal(self.owner)?),
+        ])
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, BindingError> {
+        let mut tuple = runtime::decode_tuple(value)?;
+        Ok(Self {
+            balance: runtime::decode_uint(runtime::take_field(&mut tuple, "balance")?)?,
+            owner: runtime::decode_principal(runtime::take_field(&mut tuple, "owner")?)?,
+        })
+    }
+}
+
+/// `(tuple (amount uint) (memo (optional (buff 34))) (recipient principal))`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferEvent {
+    pub amount: u128,
+    pub memo: Option<Vec<u8>>,
+    pub recipient: PrincipalData,
+}
+
+impl TransferEvent {
+    pub fn into_value(self) -> Result<Value, BindingError> {
+        runtime::encode_tuple(vec![
+            ("amount", runtime::encode_uint(self.amount)?),
+            (
+                "memo",
+                runtime::encode_optional(self.memo, |x| runtime::encode_buff(x, 34))?,
+            ),
+            ("recipient", runtime::encode_principal(self.recipient)?),
+        ])
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, BindingError> {
+        let mut tuple = runtime::decode_tuple(value)?;
+        Ok(Self {
+            amount: runtime::decode_uint(runtime::take_field(&mut tuple, "amount")?)?,
+            memo: runtime::decode_optional(
+                runtime::take_field(&mut tuple, "memo")?,
+                runtime::decode_buff,
+            )?,
+            recipient: runtime::decode_principal(runtime::take_field(&mut tuple, "recipient")?)?,
+        })
+    }
+}
+
+/// A deployed instance of the contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub contract_identifier: QualifiedContractIdentifier,
+}
+
+impl Contract {
+    /// Build the payload of a transaction calling
+    /// `(define-public (set-profile (profile (tuple (bio (string-utf8 64)) (name (string-ascii 16)) (tags (list 4 (string-ascii 8)))))) (response (tuple (bio (string-utf8 64)) (name (string-ascii 16)) (tags (list 4 (string-ascii 8)))) uint))`
+    pub fn set_profile(
+        &self,
+        profile: SetProfileProfile,
+    ) -> Result<TransactionPayload, BindingError> {
+        runtime::contract_call(
+            &self.contract_identifier,
+            "set-profile",
+            Self::set_profile_args(profile)?,
+        )
+    }
+
+    /// Encode the arguments of `set-profile`
+    pub fn set_profile_args(profile: SetProfileProfile) -> Result<Vec<Value>, BindingError> {
+        Ok(vec![SetProfileProfile::into_value(profile)?])
+    }
+
+    /// Decode the result of `set-profile`
+    pub fn decode_set_profile(
+        value: Value,
+    ) -> Result<Result<SetProfileProfile, u128>, BindingError> {
+        runtime::decode_response(value, SetProfileProfile::from_value, runtime::decode_uint)
+    }
+
+    /// Build the payload of a transaction calling
+    /// `(define-public (transfer (amount uint) (recipient principal) (memo (optional (buff 34)))) (response bool uint))`
+    pub fn transfer(
+        &self,
+        amount: u128,
+        recipient: PrincipalData,
+        memo: Option<Vec<u8>>,
+    ) -> Result<TransactionPayload, BindingError> {
+        runtime::contract_call(
+            &self.contract_identifier,
+            "transfer",
+            Self::transfer_args(amount, recipient, memo)?,
+        )
+    }
+
+    /// Encode the arguments of `transfer`
+    pub fn transfer_args(
+        amount: u128,
+        recipient: PrincipalData,
+        memo: Option<Vec<u8>>,
+    ) -> Result<Vec<Value>, BindingError> {
+        Ok(vec![
+            runtime::encode_uint(amount)?,
+            runtime::encode_principal(recipient)?,
+            runtime::encode_optional(memo, |x| runtime::encode_buff(x, 34))?,
+        ])
+    }
+
+    /// Decode the result of `transfer`
+    pub fn decode_transfer(value: Value) -> Result<Result<bool, u128>, BindingError> {
+        runtime::decode_response(value, runtime::decode_bool, runtime::decode_uint)
+    }
+
+    /// Build the payload of a transaction calling
+    /// `(define-read-only (get-balance (who principal)) uint)`
+    pub fn get_balance(&self, who: PrincipalData) -> Result<TransactionPayload, BindingError> {
+        runtime::contract_call(
+            &self.contract_identifier,
+            "get-balance",
+            Self::get_balance_args(who)?,
+        )
+    }
+
+    /// Encode the arguments of `get-balance`
+    pub fn get_balance_args(who: PrincipalData) -> Result<Vec<Value>, BindingError> {
+        Ok(vec![runtime::encode_principal(who)?])
+    }
+
+    /// Decode the result of `get-balance`
+    pub fn decode_get_balance(value: Value) -> Result<u128, BindingError> {
+        runtime::decode_uint(value)
+    }
+
+    /// Build the payload of a transaction calling
+    /// `(define-read-only (get-stats) (response (tuple (delta int) (holders (list 1 (tuple (balance uint) (owner principal)))) (total uint)) UnknownType))`
+    pub fn get_stats(&self) -> Result<TransactionPayload, BindingError> {
+        runtime::contract_call(
+            &self.contract_identifier,
+            "get-stats",
+            Self::get_stats_args()?,
+        )
+    }
+
+    /// Encode the arguments of `get-stats`
+    pub fn get_stats_args() -> Result<Vec<Value>, BindingError> {
+        Ok(vec![])
+    }
+
+    /// Decode the result of `get-stats`
+    pub fn decode_get_stats(value: Value) -> Result<Result<GetStatsOutputOk, Value>, BindingError> {
+        runtime::decode_response(value, GetStatsOutputOk::from_value, runtime::decode_value)
+    }
+
+    /// Decode the value of a `transfer` event: `(tuple (amount uint) (memo (optional (buff 34))) (recipient principal))`
+    pub fn decode_transfer_event(value: Value) -> Result<TransferEvent, BindingError> {
+        TransferEvent::from_value(value)
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::analysis::contract_interface_builder::{
+    build_contract_interface, ContractInterface,
+};
+use clarity::vm::analysis::run_analysis;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use clarity::vm::{ast, ClarityVersion};
+use stacks_common::types::StacksEpochId;
+
+use super::runtime::{self, BindingError};
+use super::{generate_bindings, parse_event_type, pascal_case, snake_case, BindingOptions};
+use crate::chainstate::stacks::TransactionPayload;
+use crate::clarity_cli::vm_execute;
+use crate::clarity_vm::database::MemoryBackingStore;
+
+/// The bindings generated for `EXAMPLE`, with `example_options()`
+mod example;
+
+const ALICE: &str = "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM";
+
+const EXAMPLE: &str = "(define-map balances principal uint)
+
+(define-public (transfer (amount uint) (recipient principal) (memo (optional (buff 34))))
+  (begin
+    (asserts! (> amount u0) (err u1))
+    (print { amount: amount, recipient: recipient, memo: memo })
+    (ok true)))
+
+(define-public (set-profile (profile { name: (string-ascii 16), bio: (string-utf8 64), tags: (list 4 (string-ascii 8)) }))
+  (if (is-eq (len (get name profile)) u0)
+    (err u2)
+    (ok profile)))
+
+(define-read-only (get-balance (who principal))
+  (default-to u0 (map-get? balances who)))
+
+(define-read-only (get-stats)
+  (ok { total: u10, delta: -1, holders: (list { owner: tx-sender, balance: u1 }) }))
+
+(define-private (double (x uint))
+  (* x u2))";
+
+fn example_interface() -> ContractInterface {
+    let contract_identifier = QualifiedContractIdentifier::local("example").unwrap();
+    let expressions = ast::parse(
+        &contract_identifier,
+        EXAMPLE,
+        ClarityVersion::Clarity2,
+        StacksEpochId::Epoch25,
+    )
+    .unwrap();
+    let mut marf = MemoryBackingStore::new();
+    let analysis = run_analysis(
+        &contract_identifier,
+        &expressions,
+        &mut marf.as_analysis_db(),
+        false,
+        LimitedCostTracker::new_free(),
+        StacksEpochId::Epoch25,
+        ClarityVersion::Clarity2,
+        false,
+    )
+    .map_err(|(e, _)| e)
+    .unwrap();
+    build_contract_interface(&analysis).unwrap()
+}
+
+fn example_options() -> BindingOptions {
+    BindingOptions {
+        crate_path: "crate".into(),
+        contract_name: Some("example".into()),
+        events: vec![(
+            "transfer".into(),
+            parse_event_type(
+                "(tuple (amount uint) (recipient principal) (memo (optional (buff 34))))",
+            )
+            .unwrap(),
+        )],
+    }
+}
+
+/// Strip the layout, which `generate_bindings` leaves to rustfmt
+fn normalize(source: &str) -> String {
+    let mut normalized: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+    for close in [")", "]", "}"] {
+        normalized = normalized.replace(&format!(",{}", close), close);
+    }
+    normalized
+}
+
+fn eval(program: &str) -> Value {
+    vm_execute(program, ClarityVersion::Clarity2)
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn test_generate_bindings() {
+    let generated = generate_bindings(&example_interface(), &example_options());
+    assert_eq!(
+        normalize(&generated),
+        normalize(include_str!("example.rs")),
+        "generated bindings differ from tests/example.rs:\n{}",
+        generated
+    );
+
+    // only the types which are used are imported
+    let mut interface = example_interface();
+    interface
+        .functions
+        .retain(|function| function.name == "get-stats");
+    let generated = generate_bindings(&interface, &BindingOptions::default());
+    assert!(generated.contains("use blockstack_lib::clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};"));
+    interface.functions.clear();
+    let generated = generate_bindings(&interface, &BindingOptions::default());
+    assert!(!generated.contains("TransactionPayload"));
+    assert!(generated
+        .contains("use blockstack_lib::clarity::vm::types::{QualifiedContractIdentifier};"));
+}
+
+#[test]
+fn test_encode_arguments() {
+    let contract = example::Contract {
+        contract_identifier: QualifiedContractIdentifier::parse(&format!("{}.example", ALICE))
+            .unwrap(),
+    };
+    let alice = PrincipalData::parse(ALICE).unwrap();
+
+    let payload = contract
+        .transfer(10, alice.clone(), Some(vec![1, 2, 3]))
+        .unwrap();
+    let expected = TransactionPayload::new_contract_call(
+        PrincipalData::parse_standard_principal(ALICE)
+            .unwrap()
+            .into(),
+        "example",
+        "transfer",
+        vec![
+            Value::UInt(10),
+            Value::Principal(alice.clone()),
+            eval("(some 0x010203)"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(payload, expected);
+
+    let profile = example::SetProfileProfile {
+        bio: "hello \u{1F30E}".into(),
+        name: "alice".into(),
+        tags: vec!["a".into(), "b".into()],
+    };
+    assert_eq!(
+        example::Contract::set_profile_args(profile.clone()).unwrap(),
+        vec![eval(
+            r#"{ bio: u"hello \u{1F30E}", name: "alice", tags: (list "a" "b") }"#
+        )]
+    );
+
+    // the bounds of the declared types are checked
+    assert!(matches!(
+        contract.transfer(10, alice.clone(), Some(vec![0; 35])),
+        Err(BindingError::TooLong {
+            max_length: 34,
+            length: 35
+        })
+    ));
+    let mut too_many_tags = profile;
+    too_many_tags.tags = vec!["a".into(); 5];
+    assert!(matches!(
+        contract.set_profile(too_many_tags),
+        Err(BindingError::TooLong {
+            max_length: 4,
+            length: 5
+        })
+    ));
+}
+
+#[test]
+fn test_decode_results() {
+    let alice = PrincipalData::parse(ALICE).unwrap();
+
+    let stats = example::Contract::decode_get_stats(eval(&format!(
+        "(ok {{ total: u10, delta: -1, holders: (list {{ owner: '{}, balance: u1 }}) }})",
+        ALICE
+    )))
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        stats,
+        example::GetStatsOutputOk {
+            delta: -1,
+            holders: vec![example::GetStatsOutputOkHoldersItem {
+                balance: 1,
+                owner: alice.clone(),
+            }],
+            total: 10,
+        }
+    );
+    assert_eq!(
+        example::Contract::decode_transfer(eval("(err u1)")).unwrap(),
+        Err(1)
+    );
+
+    // events are decoded from the hex serialization of their payloads
+    let event = eval(&format!(
+        "{{ amount: u5, recipient: '{}, memo: none }}",
+        ALICE
+    ));
+    let hex = event.serialize_to_hex().unwrap();
+    assert_eq!(
+        example::Contract::decode_transfer_event(runtime::value_from_hex(&hex).unwrap()).unwrap(),
+        example::TransferEvent {
+            amount: 5,
+            memo: None,
+            recipient: alice,
+        }
+    );
+
+    // values of the wrong type are rejected
+    assert!(matches!(
+        example::Contract::decode_get_balance(Value::Int(1)),
+        Err(BindingError::UnexpectedValue {
+            expected: "uint",
+            ..
+        })
+    ));
+    assert!(matches!(
+        example::Contract::decode_transfer_event(eval("{ amount: u5 }")),
+        Err(BindingError::MissingField(_))
+    ));
+    assert!(runtime::value_from_hex("zz").is_err());
+}
+
+#[test]
+fn test_names() {
+    assert_eq!(snake_case("get-balance?"), "get_balance");
+    assert_eq!(snake_case("getBalance"), "get_balance");
+    assert_eq!(snake_case("type"), "type_");
+    assert_eq!(pascal_case("set-owner!"), "SetOwner");
+    assert_eq!(pascal_case("nft-id-2"), "NftId2");
+
+    assert!(parse_event_type("(buff 32)").is_ok());
+    assert!(parse_event_type("uint uint").is_err());
+    assert!(parse_event_type("(list u10 uint)").is_err());
+}
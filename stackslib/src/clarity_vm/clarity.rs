@@ -29,6 +29,7 @@ use clarity::vm::database::{
     STXBalance, SqliteConnection, NULL_BURN_STATE_DB, NULL_HEADER_DB,
 };
 use clarity::vm::errors::Error as InterpreterError;
+use clarity::vm::profiler::ExecutionProfiler;
 use clarity::vm::representations::SymbolicExpression;
 use clarity::vm::types::{
     AssetIdentifier, BuffData, OptionalData, PrincipalData, QualifiedContractIdentifier, TupleData,
@@ -62,6 +63,7 @@ use crate::chainstate::stacks::{
     TransactionPublicKeyEncoding, TransactionSmartContract, TransactionSpendingCondition,
     TransactionVersion,
 };
+use crate::clarity_vm::database::map_index::MapEntriesPage;
 use crate::clarity_vm::database::marf::{MarfedKV, ReadOnlyMarfStore, WritableMarfStore};
 use crate::core::{StacksEpoch, StacksEpochId, FIRST_STACKS_BLOCK_ID, GENESIS_EPOCH};
 use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
@@ -94,6 +96,8 @@ pub struct ClarityInstance {
     datastore: MarfedKV,
     mainnet: bool,
     chain_id: u32,
+    /// If set, profiles the transactions of the blocks begun with `begin_block()`
+    profiler: Option<ExecutionProfiler>,
 }
 
 ///
@@ -123,6 +127,7 @@ pub struct ClarityBlockConnection<'a, 'b> {
     mainnet: bool,
     chain_id: u32,
     epoch: StacksEpochId,
+    profiler: Option<&'a mut ExecutionProfiler>,
 }
 
 ///
@@ -139,6 +144,7 @@ pub struct ClarityTransactionConnection<'a, 'b> {
     mainnet: bool,
     chain_id: u32,
     epoch: StacksEpochId,
+    profiler: Option<&'a mut ExecutionProfiler>,
 }
 
 pub struct ClarityReadOnlyConnection<'a> {
@@ -191,6 +197,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             mainnet: false,
             chain_id: CHAIN_ID_TESTNET,
             epoch: epoch,
+            profiler: None,
         }
     }
 
@@ -234,6 +241,7 @@ impl ClarityInstance {
             datastore,
             mainnet,
             chain_id,
+            profiler: None,
         }
     }
 
@@ -248,6 +256,16 @@ impl ClarityInstance {
         self.mainnet
     }
 
+    /// Profile the transactions evaluated in the blocks begun from now on with `profiler`
+    pub fn set_profiler(&mut self, profiler: ExecutionProfiler) {
+        self.profiler = Some(profiler);
+    }
+
+    /// Stop profiling, and return the profiler
+    pub fn take_profiler(&mut self) -> Option<ExecutionProfiler> {
+        self.profiler.take()
+    }
+
     /// Returns the Stacks epoch of the burn block that elected `stacks_block`
     fn get_epoch_of(
         stacks_block: &StacksBlockId,
@@ -302,6 +320,7 @@ impl ClarityInstance {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             epoch: epoch.epoch_id,
+            profiler: self.profiler.as_mut(),
         }
     }
 
@@ -326,6 +345,7 @@ impl ClarityInstance {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             epoch,
+            profiler: None,
         }
     }
 
@@ -352,6 +372,7 @@ impl ClarityInstance {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             epoch,
+            profiler: None,
         };
 
         let use_mainnet = self.mainnet;
@@ -448,6 +469,7 @@ impl ClarityInstance {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             epoch,
+            profiler: None,
         };
 
         let use_mainnet = self.mainnet;
@@ -524,6 +546,36 @@ impl ClarityInstance {
         Ok(())
     }
 
+    /// Drop the state of every block that is neither within `depth` blocks of the highest block
+    /// nor an ancestor of one that is.  Returns the dropped blocks.
+    pub fn prune_unreachable_tries(&mut self, depth: u32) -> Result<Vec<StacksBlockId>, Error> {
+        let pruned = self.datastore.prune_unreachable_tries(depth)?;
+        Ok(pruned)
+    }
+
+    /// Start recording the data map keys written by each block, so that data maps can be listed
+    /// with `get_map_entries()`.
+    pub fn enable_map_index(&mut self) -> Result<(), Error> {
+        self.datastore.enable_map_index()?;
+        Ok(())
+    }
+
+    /// List up to `limit` entries of a data map as of the block `tip`, starting after the
+    /// hex-encoded key `cursor`.  Returns None if the map index has not been enabled.
+    pub fn get_map_entries(
+        &mut self,
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        map_name: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Option<MapEntriesPage>, Error> {
+        let page = self
+            .datastore
+            .get_map_entries(tip, contract, map_name, cursor, limit)?;
+        Ok(page)
+    }
+
     pub fn begin_unconfirmed<'a, 'b>(
         &'a mut self,
         current: &StacksBlockId,
@@ -556,6 +608,7 @@ impl ClarityInstance {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             epoch: epoch.epoch_id,
+            profiler: None,
         }
     }
 
@@ -1543,6 +1596,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             mainnet,
             chain_id,
             epoch: self.epoch,
+            profiler: self.profiler.as_deref_mut(),
         }
     }
 
@@ -1687,6 +1741,9 @@ impl<'a, 'b> TransactionConnection for ClarityTransactionConnection<'a, 'b> {
                     cost_track,
                     self.epoch,
                 );
+                if let Some(profiler) = self.profiler.as_deref_mut() {
+                    vm_env.add_eval_hook(profiler);
+                }
                 let result = to_do(&mut vm_env);
                 let (mut db, cost_track) = vm_env
                     .destruct()
@@ -0,0 +1,304 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Optional index of the unhashed keys of contract data maps.
+//!
+//! The MARF only stores the hashes of the keys it is given, so a data map's entries cannot be
+//! listed from it.  If the map index is enabled, then every data map key written in a block is
+//! also recorded in the Clarity MARF's side store, alongside the block that wrote it.  To list
+//! a map at a given tip, the keys ever written to it are read back in order, and each key's
+//! value is looked up in the MARF at that tip; keys which were deleted, or which were only
+//! written in other forks, have no value there and are skipped.
+//!
+//! Only keys written while the index is enabled are recorded, so a node must enable it before
+//! it processes the blocks whose maps it should list.  The MARF cannot be used to backfill the
+//! index, since it never had the unhashed keys, so instead the height of the first indexed block
+//! is recorded and reported with each page.
+
+use clarity::vm::database::{ClarityBackingStore, ClarityDatabase, StoreType};
+use clarity::vm::errors::{IncomparableError, InterpreterError, InterpreterResult};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::Value;
+use rusqlite::{params, Connection, OptionalExtension, NO_PARAMS};
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::clarity_vm::database::marf::MarfedKV;
+
+/// Number of candidate keys read from the index at a time
+const MAP_INDEX_BATCH_SIZE: u32 = 256;
+
+/// Maximum number of candidate keys to check when listing a page of map entries.  If this many
+/// keys are checked without filling the page (i.e. most of them were deleted or written in
+/// other forks), the page is returned short along with a cursor to resume from.
+pub const MAP_INDEX_SCAN_LIMIT: usize = 10_000;
+
+const MAP_INDEX_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS map_keys_table(
+        contract TEXT NOT NULL,
+        map_name TEXT NOT NULL,
+        -- hex-encoded serialized Clarity key
+        key TEXT NOT NULL,
+        -- block which wrote this key
+        blockhash TEXT NOT NULL,
+        PRIMARY KEY(contract, map_name, key, blockhash)
+    );"#,
+    "CREATE INDEX IF NOT EXISTS map_keys_by_blockhash ON map_keys_table(blockhash);",
+    r#"
+    CREATE TABLE IF NOT EXISTS map_index_start(
+        -- height of the first block whose keys were recorded
+        block_height INTEGER NOT NULL
+    );"#,
+];
+
+/// A page of entries of a data map
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapEntriesPage {
+    /// Hex-encoded serialized (key, value) pairs, in key order
+    pub entries: Vec<(String, String)>,
+    /// Key to resume listing after, if there may be more entries
+    pub next_cursor: Option<String>,
+    /// Height of the first block whose keys were recorded, if any.  Entries which were only
+    /// written before it cannot be listed.
+    pub index_start_height: Option<u32>,
+}
+
+pub struct MapIndex;
+
+fn sqlite_error(err: rusqlite::Error) -> InterpreterError {
+    InterpreterError::SqliteError(IncomparableError { err })
+}
+
+impl MapIndex {
+    /// Create the index table, if it does not exist yet
+    pub fn instantiate(conn: &Connection) -> InterpreterResult<()> {
+        for cmd in MAP_INDEX_SCHEMA.iter() {
+            conn.execute(cmd, NO_PARAMS).map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    /// Has the index table been created?
+    pub fn exists(conn: &Connection) -> InterpreterResult<bool> {
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'map_keys_table'",
+                NO_PARAMS,
+                |_row| Ok(()),
+            )
+            .optional()
+            .map_err(sqlite_error)?
+            .is_some();
+        Ok(exists)
+    }
+
+    /// Split a MARF key of the form `vm::{contract}::{StoreType::DataMap}::{map}::{key}` into its
+    /// contract, map name, and hex-encoded key.  Returns None for keys of any other kind.
+    pub fn parse_data_map_key(key: &str) -> Option<(&str, &str, &str)> {
+        let mut parts = key.splitn(5, "::");
+        if parts.next()? != "vm" {
+            return None;
+        }
+        let contract = parts.next()?;
+        if parts.next()? != (StoreType::DataMap as u8).to_string() {
+            return None;
+        }
+        let map_name = parts.next()?;
+        let map_key = parts.next()?;
+        Some((contract, map_name, map_key))
+    }
+
+    /// Record the data map keys among `keys` as written by `blockhash`
+    pub fn insert_keys(
+        conn: &Connection,
+        blockhash: &StacksBlockId,
+        keys: &[String],
+    ) -> InterpreterResult<()> {
+        for key in keys.iter() {
+            let Some((contract, map_name, map_key)) = Self::parse_data_map_key(key) else {
+                continue;
+            };
+            conn.execute(
+                "INSERT OR IGNORE INTO map_keys_table (contract, map_name, key, blockhash) VALUES (?1, ?2, ?3, ?4)",
+                params![contract, map_name, map_key, blockhash],
+            )
+            .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    /// Move the keys recorded for the block `from` to the block `to`
+    pub fn commit_keys_to(
+        conn: &Connection,
+        from: &StacksBlockId,
+        to: &StacksBlockId,
+    ) -> InterpreterResult<()> {
+        conn.execute(
+            "UPDATE OR REPLACE map_keys_table SET blockhash = ?1 WHERE blockhash = ?2",
+            params![to, from],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Record that the keys of blocks from `block_height` on are in the index, unless an earlier
+    /// block was already recorded
+    pub fn set_start_height(conn: &Connection, block_height: u32) -> InterpreterResult<()> {
+        conn.execute(
+            "INSERT INTO map_index_start (block_height) SELECT ?1 \
+             WHERE NOT EXISTS (SELECT 1 FROM map_index_start)",
+            params![block_height],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Get the height of the first block whose keys were recorded, if any
+    pub fn get_start_height(conn: &Connection) -> InterpreterResult<Option<u32>> {
+        let start_height = conn
+            .query_row(
+                "SELECT block_height FROM map_index_start LIMIT 1",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        Ok(start_height)
+    }
+
+    /// Forget the keys recorded for the block `from`
+    pub fn drop_keys(conn: &Connection, from: &StacksBlockId) -> InterpreterResult<()> {
+        conn.execute(
+            "DELETE FROM map_keys_table WHERE blockhash = ?1",
+            params![from],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Get up to `limit` distinct keys ever written to the given map, which sort after `cursor`
+    pub fn get_keys_after(
+        conn: &Connection,
+        contract: &QualifiedContractIdentifier,
+        map_name: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> InterpreterResult<Vec<String>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT key FROM map_keys_table \
+                 WHERE contract = ?1 AND map_name = ?2 AND key > ?3 ORDER BY key LIMIT ?4",
+            )
+            .map_err(sqlite_error)?;
+        let keys = stmt
+            .query_map(
+                params![contract.to_string(), map_name, cursor.unwrap_or(""), limit],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(sqlite_error)?;
+        Ok(keys)
+    }
+}
+
+impl MarfedKV {
+    /// List up to `limit` entries of a data map as of the block `tip`, starting after the key
+    /// `cursor`.  Returns None if the map index has not been enabled.
+    pub fn get_map_entries(
+        &mut self,
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        map_name: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> InterpreterResult<Option<MapEntriesPage>> {
+        let mut store = self.begin_read_only_checked(Some(tip))?;
+        if !MapIndex::exists(store.get_side_store())? {
+            return Ok(None);
+        }
+        let index_start_height = MapIndex::get_start_height(store.get_side_store())?;
+
+        let mut entries = vec![];
+        let mut cursor = cursor.map(|c| c.to_string());
+        let mut scanned = 0;
+        loop {
+            let keys = MapIndex::get_keys_after(
+                store.get_side_store(),
+                contract,
+                map_name,
+                cursor.as_deref(),
+                MAP_INDEX_BATCH_SIZE,
+            )?;
+            let exhausted = keys.len() < MAP_INDEX_BATCH_SIZE as usize;
+            let num_keys = keys.len();
+            for (i, key) in keys.into_iter().enumerate() {
+                scanned += 1;
+                let marf_key = ClarityDatabase::make_key_for_quad(
+                    contract,
+                    StoreType::DataMap,
+                    map_name,
+                    &key,
+                );
+                let value = store
+                    .get_data(&marf_key)?
+                    .map(|value_hex| {
+                        Value::try_deserialize_hex_untyped(&value_hex).map_err(|e| {
+                            InterpreterError::Expect(format!(
+                                "Failed to deserialize map entry {}: {:?}",
+                                &marf_key, &e
+                            ))
+                        })
+                    })
+                    .transpose()?;
+
+                // deleted entries are stored as `none`
+                if let Some(Value::Optional(data)) = value {
+                    if let Some(value) = data.data {
+                        entries.push((key.clone(), value.serialize_to_hex()?));
+                    }
+                }
+                if entries.len() >= limit {
+                    let next_cursor = if exhausted && i + 1 == num_keys {
+                        None
+                    } else {
+                        Some(key)
+                    };
+                    return Ok(Some(MapEntriesPage {
+                        entries,
+                        next_cursor,
+                        index_start_height,
+                    }));
+                }
+                cursor = Some(key);
+            }
+            if exhausted {
+                return Ok(Some(MapEntriesPage {
+                    entries,
+                    next_cursor: None,
+                    index_start_height,
+                }));
+            }
+            if scanned >= MAP_INDEX_SCAN_LIMIT {
+                return Ok(Some(MapEntriesPage {
+                    entries,
+                    next_cursor: cursor,
+                    index_start_height,
+                }));
+            }
+        }
+    }
+}
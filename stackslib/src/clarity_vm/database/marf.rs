@@ -23,6 +23,7 @@ use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MarfT
 use crate::chainstate::stacks::index::{
     ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieMerkleProof,
 };
+use crate::clarity_vm::database::map_index::MapIndex;
 use crate::clarity_vm::special::handle_contract_call_special_cases;
 use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
 use crate::util_lib::db::{Error as DatabaseError, IndexDBConn};
@@ -37,6 +38,8 @@ use crate::util_lib::db::{Error as DatabaseError, IndexDBConn};
 pub struct MarfedKV {
     chain_tip: StacksBlockId,
     marf: MARF<StacksBlockId>,
+    /// If true, record the data map keys written by each block in the map index
+    map_index: bool,
 }
 
 impl MarfedKV {
@@ -94,7 +97,11 @@ impl MarfedKV {
             None => StacksBlockId::sentinel(),
         };
 
-        Ok(MarfedKV { marf, chain_tip })
+        Ok(MarfedKV {
+            marf,
+            chain_tip,
+            map_index: false,
+        })
     }
 
     pub fn open_unconfirmed(
@@ -108,7 +115,11 @@ impl MarfedKV {
             None => StacksBlockId::sentinel(),
         };
 
-        Ok(MarfedKV { marf, chain_tip })
+        Ok(MarfedKV {
+            marf,
+            chain_tip,
+            map_index: false,
+        })
     }
 
     // used by benchmarks
@@ -139,7 +150,11 @@ impl MarfedKV {
 
         let chain_tip = StacksBlockId::sentinel();
 
-        MarfedKV { marf, chain_tip }
+        MarfedKV {
+            marf,
+            chain_tip,
+            map_index: false,
+        }
     }
 
     pub fn begin_read_only<'a>(
@@ -221,6 +236,7 @@ impl MarfedKV {
         WritableMarfStore {
             chain_tip,
             marf: tx,
+            map_index: self.map_index,
         }
     }
 
@@ -246,9 +262,48 @@ impl MarfedKV {
         WritableMarfStore {
             chain_tip,
             marf: tx,
+            map_index: self.map_index,
         }
     }
 
+    /// Drop the tries and contract metadata of every block that is neither within `depth` blocks
+    /// of the highest block nor an ancestor of one that is.  Values in the side store are left
+    /// alone, since they are keyed by their hash and may be shared with surviving tries.
+    /// Returns the dropped blocks.
+    pub fn prune_unreachable_tries(&mut self, depth: u32) -> InterpreterResult<Vec<StacksBlockId>> {
+        let mut tx = self
+            .marf
+            .begin_tx()
+            .map_err(|e| InterpreterError::MarfFailure(e.to_string()))?;
+        let pruned = tx
+            .prune_unreachable_tries(depth)
+            .map_err(|e| InterpreterError::MarfFailure(e.to_string()))?;
+        let has_map_index = MapIndex::exists(tx.sqlite_tx())?;
+        for block_id in pruned.iter() {
+            SqliteConnection::drop_metadata(tx.sqlite_tx(), block_id)?;
+            if has_map_index {
+                MapIndex::drop_keys(tx.sqlite_tx(), block_id)?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| InterpreterError::MarfFailure(e.to_string()))?;
+        Ok(pruned)
+    }
+
+    /// Start recording the data map keys written by each block, so that data maps can be listed
+    /// with `get_map_entries()`.
+    pub fn enable_map_index(&mut self) -> InterpreterResult<()> {
+        let tx = self
+            .marf
+            .storage_tx()
+            .map_err(|err| InterpreterError::DBError(err.to_string()))?;
+        MapIndex::instantiate(&tx)?;
+        tx.commit()
+            .map_err(|err| InterpreterError::SqliteError(IncomparableError { err }))?;
+        self.map_index = true;
+        Ok(())
+    }
+
     pub fn get_chain_tip(&self) -> &StacksBlockId {
         &self.chain_tip
     }
@@ -273,6 +328,7 @@ impl MarfedKV {
 pub struct WritableMarfStore<'a> {
     chain_tip: StacksBlockId,
     marf: MarfTransaction<'a, StacksBlockId>,
+    map_index: bool,
 }
 
 pub struct ReadOnlyMarfStore<'a> {
@@ -511,13 +567,37 @@ impl<'a> WritableMarfStore<'a> {
     pub fn rollback_unconfirmed(self) -> InterpreterResult<()> {
         debug!("Drop unconfirmed MARF trie {}", &self.chain_tip);
         SqliteConnection::drop_metadata(self.marf.sqlite_tx(), &self.chain_tip)?;
+        if self.map_index {
+            MapIndex::drop_keys(self.marf.sqlite_tx(), &self.chain_tip)?;
+        }
         self.marf.drop_unconfirmed();
         Ok(())
     }
 
-    pub fn commit_to(self, final_bhh: &StacksBlockId) -> InterpreterResult<()> {
+    pub fn commit_to(mut self, final_bhh: &StacksBlockId) -> InterpreterResult<()> {
         debug!("commit_to({})", final_bhh);
         SqliteConnection::commit_metadata_to(self.marf.sqlite_tx(), &self.chain_tip, final_bhh)?;
+        if self.map_index {
+            MapIndex::commit_keys_to(self.marf.sqlite_tx(), &self.chain_tip, final_bhh)?;
+            let chain_tip = self.chain_tip.clone();
+            let height_opt = self
+                .marf
+                .get_block_height_of(&chain_tip, &chain_tip)
+                .map_err(|e| {
+                    error!(
+                        "Failed to get height of MARF block {}: {:?}",
+                        &chain_tip, &e
+                    );
+                    InterpreterError::Expect("Failed to get height of MARF block".into())
+                })?;
+            match height_opt {
+                Some(height) => MapIndex::set_start_height(self.marf.sqlite_tx(), height)?,
+                None => warn!(
+                    "No height for MARF block {}; not recording the map index start height",
+                    &chain_tip
+                ),
+            }
+        }
 
         let _ = self.marf.commit_to(final_bhh).map_err(|e| {
             error!("Failed to commit to MARF block {}: {:?}", &final_bhh, &e);
@@ -555,6 +635,9 @@ impl<'a> WritableMarfStore<'a> {
         //    _if_ for some reason, we do want to be able to access that mined chain state in the future,
         //    we should probably commit the data to a different table which does not have uniqueness constraints.
         SqliteConnection::drop_metadata(self.marf.sqlite_tx(), &self.chain_tip)?;
+        if self.map_index {
+            MapIndex::drop_keys(self.marf.sqlite_tx(), &self.chain_tip)?;
+        }
         let _ = self.marf.commit_mined(will_move_to).map_err(|e| {
             error!(
                 "Failed to commit to mined MARF block {}: {:?}",
@@ -726,6 +809,9 @@ impl<'a> ClarityBackingStore for WritableMarfStore<'a> {
             keys.push(key);
             values.push(marf_value);
         }
+        if self.map_index {
+            MapIndex::insert_keys(self.marf.sqlite_tx(), &self.chain_tip, &keys)?;
+        }
         self.marf
             .insert_batch(&keys, values)
             .map_err(|_| InterpreterError::Expect("ERROR: Unexpected MARF Failure".into()).into())
@@ -36,7 +36,11 @@ use crate::clarity_vm::special::handle_contract_call_special_cases;
 use crate::core::{StacksEpoch, StacksEpochId};
 use crate::util_lib::db::{DBConn, FromColumn, FromRow};
 
+pub mod map_index;
 pub mod marf;
+pub mod remote;
+pub mod rpc;
+pub mod snapshot;
 
 pub struct HeadersDBConn<'a>(pub &'a Connection);
 
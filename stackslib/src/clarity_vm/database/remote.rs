@@ -0,0 +1,628 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A `ClarityBackingStore` which forks another chain's Clarity state, so that contracts can be
+//! deployed and called against e.g. mainnet's state without syncing a node.
+//!
+//! The forked chain is read through a `ForkSource` (normally a node's RPC interface, see
+//! `RemoteClarityNode`) as of a pinned block, the fork point.  Local blocks are built on top of
+//! the fork point, and everything they write goes to a SQLite overlay with the same tables as
+//! `clarity::vm::database::SqliteConnection`.  Reads check the overlay first, so the forked
+//! chain is never written to.  Values and metadata read from the forked chain are cached in a
+//! second SQLite database, since they can never change.
+//!
+//! The overlay only holds the latest local state, so evaluation can only be time-shifted (with
+//! `at-block`) to the open local block, or to blocks of the forked chain up to the fork point.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clarity::util::hash::Sha512Trunc256Sum;
+use clarity::vm::analysis::AnalysisDatabase;
+use clarity::vm::database::sqlite::{sqlite_get_contract_hash, sqlite_insert_metadata};
+use clarity::vm::database::{
+    BurnStateDB, ClarityBackingStore, ClarityDatabase, HeadersDB, SqliteConnection,
+};
+use clarity::vm::errors::{
+    IncomparableError, InterpreterError, InterpreterResult, RuntimeErrorType,
+};
+use clarity::vm::types::QualifiedContractIdentifier;
+use rusqlite::{Connection, OptionalExtension, NO_PARAMS};
+use stacks_common::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
+
+use crate::clarity_vm::database::rpc::RPC_STORE_DEFAULT_TIMEOUT;
+use crate::net::api::getclarityblockinfo::{ClarityBlockInfoResponse, ClarityBlockSelector};
+use crate::net::api::getinfo::RPCPeerInfoData;
+use crate::net::httpcore::{StacksHttp, StacksHttpRequest, StacksHttpResponse, TipRequest};
+use crate::net::Error as NetError;
+
+/// Where a `RemoteForkStore` reads the state of the chain that it forks
+pub trait ForkSource {
+    /// Read the value stored under `key` as of `tip`
+    fn get_data(&mut self, tip: &StacksBlockId, key: &str) -> InterpreterResult<Option<String>>;
+    /// Read a contract's metadata entry as of `tip`.  If `at_height` is given, read the entry
+    /// stored in the block at that height, rather than in the block which published the
+    /// contract.
+    fn get_metadata(
+        &mut self,
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        at_height: Option<u32>,
+    ) -> InterpreterResult<Option<String>>;
+    /// The block at `height` in the fork of `tip`
+    fn get_block_at_height(
+        &mut self,
+        tip: &StacksBlockId,
+        height: u32,
+    ) -> InterpreterResult<Option<StacksBlockId>>;
+    /// The height of `block`, if it exists
+    fn get_block_height(&mut self, block: &StacksBlockId) -> InterpreterResult<Option<u32>>;
+}
+
+fn rpc_error(what: &str, err: NetError) -> InterpreterError {
+    InterpreterError::DBError(format!("Failed to read {} over RPC: {:?}", what, &err))
+}
+
+fn sqlite_error(err: rusqlite::Error) -> InterpreterError {
+    InterpreterError::SqliteError(IncomparableError { err })
+}
+
+/// The height of a block of the forked chain, which Clarity stores as a u32
+pub fn block_info_height(info: &ClarityBlockInfoResponse) -> InterpreterResult<u32> {
+    u32::try_from(info.block_height).map_err(|_| {
+        InterpreterError::DBError(format!(
+            "Block {} has an out-of-range height {}",
+            &info.index_block_hash, info.block_height
+        ))
+        .into()
+    })
+}
+
+/// Treat a 404 from the node as a missing value
+fn found<T>(result: Result<T, NetError>) -> Result<Option<T>, NetError> {
+    match result {
+        Ok(x) => Ok(Some(x)),
+        Err(NetError::NotFoundError) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A client for the Clarity state endpoints of a node's RPC interface.
+///
+/// Block info is cached, and block IDs are cached by height, so all the tips that a client is
+/// asked to read at must be in the same fork.
+#[derive(Debug, Clone)]
+pub struct RemoteClarityNode {
+    node: SocketAddr,
+    timeout: Duration,
+    block_infos: RefCell<HashMap<StacksBlockId, ClarityBlockInfoResponse>>,
+    block_ids: RefCell<HashMap<u32, StacksBlockId>>,
+}
+
+impl RemoteClarityNode {
+    pub fn new(node: SocketAddr) -> RemoteClarityNode {
+        RemoteClarityNode {
+            node,
+            timeout: RPC_STORE_DEFAULT_TIMEOUT,
+            block_infos: RefCell::new(HashMap::new()),
+            block_ids: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> RemoteClarityNode {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn node(&self) -> &SocketAddr {
+        &self.node
+    }
+
+    fn send(&self, request: StacksHttpRequest) -> Result<StacksHttpResponse, NetError> {
+        StacksHttp::send_request(&self.node, request, self.timeout)
+    }
+
+    /// Get the node's `/v2/info`
+    pub fn get_peer_info(&self) -> Result<RPCPeerInfoData, NetError> {
+        self.send(StacksHttpRequest::new_getinfo(self.node.into(), None))?
+            .decode_peer_info()
+    }
+
+    fn cache_block_info(&self, info: &ClarityBlockInfoResponse) -> InterpreterResult<()> {
+        let height = block_info_height(info)?;
+        self.block_ids
+            .borrow_mut()
+            .insert(height, info.index_block_hash.clone());
+        self.block_infos
+            .borrow_mut()
+            .insert(info.index_block_hash.clone(), info.clone());
+        Ok(())
+    }
+
+    /// Get what Clarity knows about `block`
+    pub fn get_block_info(
+        &self,
+        block: &StacksBlockId,
+    ) -> InterpreterResult<Option<ClarityBlockInfoResponse>> {
+        if let Some(info) = self.block_infos.borrow().get(block) {
+            return Ok(Some(info.clone()));
+        }
+        let request = StacksHttpRequest::new_getclarityblockinfo(
+            self.node.into(),
+            ClarityBlockSelector::BlockId(block.clone()),
+            TipRequest::SpecificTip(block.clone()),
+        );
+        let what = format!("block {}", block);
+        let response = self.send(request).map_err(|e| rpc_error(&what, e))?;
+        let info = found(response.decode_clarity_block_info_response())
+            .map_err(|e| rpc_error(&what, e))?;
+        if let Some(ref info) = info {
+            self.cache_block_info(info)?;
+        }
+        Ok(info)
+    }
+
+    /// Get what Clarity knows about the block at `height` in the fork of `tip`
+    pub fn get_block_info_at_height(
+        &self,
+        tip: &StacksBlockId,
+        height: u32,
+    ) -> InterpreterResult<Option<ClarityBlockInfoResponse>> {
+        let cached_id = self.block_ids.borrow().get(&height).cloned();
+        if let Some(block_id) = cached_id {
+            return self.get_block_info(&block_id);
+        }
+        let request = StacksHttpRequest::new_getclarityblockinfo(
+            self.node.into(),
+            ClarityBlockSelector::Height(height),
+            TipRequest::SpecificTip(tip.clone()),
+        );
+        let what = format!("block at height {}", height);
+        let response = self.send(request).map_err(|e| rpc_error(&what, e))?;
+        let info = found(response.decode_clarity_block_info_response())
+            .map_err(|e| rpc_error(&what, e))?;
+        if let Some(ref info) = info {
+            self.cache_block_info(info)?;
+        }
+        Ok(info)
+    }
+}
+
+impl ForkSource for RemoteClarityNode {
+    fn get_data(&mut self, tip: &StacksBlockId, key: &str) -> InterpreterResult<Option<String>> {
+        let request = StacksHttpRequest::new_getclaritymarfvalue(
+            self.node.into(),
+            key.to_string(),
+            TipRequest::SpecificTip(tip.clone()),
+            false,
+        );
+        let response = self.send(request).map_err(|e| rpc_error(key, e))?;
+        let value =
+            found(response.decode_clarity_marf_value_response()).map_err(|e| rpc_error(key, e))?;
+        Ok(value.map(|value| value.data))
+    }
+
+    fn get_metadata(
+        &mut self,
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        at_height: Option<u32>,
+    ) -> InterpreterResult<Option<String>> {
+        let request = StacksHttpRequest::new_getclaritymetadata(
+            self.node.into(),
+            StacksAddress::from(contract.issuer.clone()),
+            contract.name.clone(),
+            key.to_string(),
+            at_height,
+            TipRequest::SpecificTip(tip.clone()),
+        );
+        let what = format!("metadata {} of {}", key, contract);
+        let response = self.send(request).map_err(|e| rpc_error(&what, e))?;
+        let value =
+            found(response.decode_clarity_metadata_response()).map_err(|e| rpc_error(&what, e))?;
+        Ok(value.map(|value| value.data))
+    }
+
+    fn get_block_at_height(
+        &mut self,
+        tip: &StacksBlockId,
+        height: u32,
+    ) -> InterpreterResult<Option<StacksBlockId>> {
+        let info = self.get_block_info_at_height(tip, height)?;
+        Ok(info.map(|info| info.index_block_hash))
+    }
+
+    fn get_block_height(&mut self, block: &StacksBlockId) -> InterpreterResult<Option<u32>> {
+        self.get_block_info(block)?
+            .as_ref()
+            .map(block_info_height)
+            .transpose()
+    }
+}
+
+/// A backing store which forks the state of another chain.  See the module documentation.
+pub struct RemoteForkStore {
+    source: Box<dyn ForkSource>,
+    /// Last block of the forked chain, and its height
+    fork_point: StacksBlockId,
+    fork_height: u32,
+    /// Everything written by local blocks, and the local blocks themselves
+    overlay: Connection,
+    /// Values and metadata read from the forked chain
+    cache: Connection,
+    /// Last committed local block (or the fork point), and its height
+    chain_tip: StacksBlockId,
+    chain_tip_height: u32,
+    /// The local block being built, if any
+    open_block: Option<StacksBlockId>,
+    /// Block of the forked chain that reads are time-shifted to, and its height
+    read_at: Option<(StacksBlockId, u32)>,
+}
+
+impl RemoteForkStore {
+    /// Open (or create) the fork of `source` at `fork_point`, whose height is `fork_height`,
+    /// stored in the directory `path`.  If `path` is `:memory:`, nothing is stored on disk.
+    pub fn open(
+        path: &str,
+        source: Box<dyn ForkSource>,
+        fork_point: StacksBlockId,
+        fork_height: u32,
+    ) -> InterpreterResult<RemoteForkStore> {
+        let (overlay, cache) = if path == ":memory:" {
+            (SqliteConnection::memory()?, SqliteConnection::memory()?)
+        } else {
+            fs::create_dir_all(path).map_err(|e| {
+                InterpreterError::DBError(format!("Failed to create {}: {:?}", path, &e))
+            })?;
+            let open_db = |name: &str| -> InterpreterResult<Connection> {
+                let mut db_path = PathBuf::from(path);
+                db_path.push(name);
+                let conn = SqliteConnection::inner_open(&db_path.to_string_lossy())?;
+                SqliteConnection::initialize_conn(&conn)?;
+                Ok(conn)
+            };
+            (open_db("fork.sqlite")?, open_db("fork-cache.sqlite")?)
+        };
+
+        overlay
+            .execute(
+                "CREATE TABLE IF NOT EXISTS fork_blocks
+                      (height INTEGER PRIMARY KEY, block_id TEXT UNIQUE NOT NULL)",
+                NO_PARAMS,
+            )
+            .map_err(sqlite_error)?;
+
+        let local_tip: Option<(u32, StacksBlockId)> = overlay
+            .query_row(
+                "SELECT height, block_id FROM fork_blocks ORDER BY height DESC LIMIT 1",
+                NO_PARAMS,
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        let (chain_tip_height, chain_tip) = local_tip.unwrap_or((fork_height, fork_point.clone()));
+
+        Ok(RemoteForkStore {
+            source,
+            fork_point,
+            fork_height,
+            overlay,
+            cache,
+            chain_tip,
+            chain_tip_height,
+            open_block: None,
+            read_at: None,
+        })
+    }
+
+    pub fn get_fork_point(&self) -> &StacksBlockId {
+        &self.fork_point
+    }
+
+    /// Start building the local block `to` on top of `from`, which must be the local chain tip
+    pub fn begin(&mut self, from: &StacksBlockId, to: &StacksBlockId) -> InterpreterResult<()> {
+        if self.open_block.is_some() {
+            return Err(InterpreterError::DBError("A local block is already open".into()).into());
+        }
+        if from != &self.chain_tip {
+            return Err(
+                RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(from.0.clone())).into(),
+            );
+        }
+        self.overlay
+            .execute_batch("SAVEPOINT fork_block")
+            .map_err(sqlite_error)?;
+        self.open_block = Some(to.clone());
+        self.read_at = None;
+        Ok(())
+    }
+
+    /// Commit the open block, which becomes the local chain tip
+    pub fn commit(&mut self) -> InterpreterResult<()> {
+        let block = self
+            .open_block
+            .take()
+            .ok_or_else(|| InterpreterError::DBError("No local block is open".into()))?;
+        let height = self.chain_tip_height + 1;
+        self.overlay
+            .execute(
+                "INSERT INTO fork_blocks (height, block_id) VALUES (?1, ?2)",
+                rusqlite::params![height, &block],
+            )
+            .map_err(sqlite_error)?;
+        self.overlay
+            .execute_batch("RELEASE fork_block")
+            .map_err(sqlite_error)?;
+        self.chain_tip = block;
+        self.chain_tip_height = height;
+        self.read_at = None;
+        Ok(())
+    }
+
+    /// Discard everything the open block wrote
+    pub fn rollback_block(&mut self) -> InterpreterResult<()> {
+        if self.open_block.take().is_none() {
+            return Err(InterpreterError::DBError("No local block is open".into()).into());
+        }
+        self.overlay
+            .execute_batch("ROLLBACK TO fork_block; RELEASE fork_block")
+            .map_err(sqlite_error)?;
+        self.read_at = None;
+        Ok(())
+    }
+
+    pub fn as_clarity_db<'a>(
+        &'a mut self,
+        headers_db: &'a dyn HeadersDB,
+        burn_state_db: &'a dyn BurnStateDB,
+    ) -> ClarityDatabase<'a> {
+        ClarityDatabase::new(self, headers_db, burn_state_db)
+    }
+
+    pub fn as_analysis_db(&mut self) -> AnalysisDatabase {
+        AnalysisDatabase::new(self)
+    }
+
+    /// The block of the forked chain that reads go to
+    fn source_tip(&self) -> StacksBlockId {
+        match self.read_at {
+            Some((ref block, _)) => block.clone(),
+            None => self.fork_point.clone(),
+        }
+    }
+
+    /// Is `block` the open block, or a committed local block?
+    fn is_local_block(&self, block: &StacksBlockId) -> InterpreterResult<bool> {
+        if self.open_block.as_ref() == Some(block) {
+            return Ok(true);
+        }
+        let local: Option<u32> = self
+            .overlay
+            .query_row(
+                "SELECT height FROM fork_blocks WHERE block_id = ?1",
+                &[block],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        Ok(local.is_some())
+    }
+
+    /// Read a metadata entry stored in `block`, which is in the block at `at_height` if given
+    fn get_metadata_in_block(
+        &mut self,
+        block: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        at_height: Option<u32>,
+    ) -> InterpreterResult<Option<String>> {
+        let contract_str = contract.to_string();
+        if self.is_local_block(block)? {
+            return SqliteConnection::get_metadata(&self.overlay, block, &contract_str, key);
+        }
+        if let Some(value) = SqliteConnection::get_metadata(&self.cache, block, &contract_str, key)?
+        {
+            return Ok(Some(value));
+        }
+
+        let source_tip = self.source_tip();
+        let value = self
+            .source
+            .get_metadata(&source_tip, contract, key, at_height)?;
+        if let Some(ref value) = value {
+            SqliteConnection::insert_metadata(&self.cache, block, &contract_str, key, value)?;
+        }
+        Ok(value)
+    }
+}
+
+impl ClarityBackingStore for RemoteForkStore {
+    fn put_all_data(&mut self, items: Vec<(String, String)>) -> InterpreterResult<()> {
+        if self.open_block.is_none() {
+            return Err(InterpreterError::DBError("No local block is open".into()).into());
+        }
+        for (key, value) in items.into_iter() {
+            SqliteConnection::put(&self.overlay, &key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn get_data(&mut self, key: &str) -> InterpreterResult<Option<String>> {
+        if let Some((ref block, _)) = self.read_at {
+            let block = block.clone();
+            return self.source.get_data(&block, key);
+        }
+        if let Some(value) = SqliteConnection::get(&self.overlay, key)? {
+            return Ok(Some(value));
+        }
+        if let Some(value) = SqliteConnection::get(&self.cache, key)? {
+            return Ok(Some(value));
+        }
+
+        let value = self.source.get_data(&self.fork_point, key)?;
+        if let Some(ref value) = value {
+            SqliteConnection::put(&self.cache, key, value)?;
+        }
+        Ok(value)
+    }
+
+    /// There are no MARF proofs for the overlay, so this returns empty proofs
+    fn get_data_with_proof(&mut self, key: &str) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        Ok(self.get_data(key)?.map(|value| (value, vec![])))
+    }
+
+    /// Only the open block and blocks of the forked chain up to the fork point can be read
+    fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        let open_tip = self.get_open_chain_tip();
+        let prior = match self.read_at {
+            Some((ref block, _)) => block.clone(),
+            None => open_tip.clone(),
+        };
+        if bhh == open_tip {
+            self.read_at = None;
+            return Ok(prior);
+        }
+
+        let height = match self.source.get_block_height(&bhh)? {
+            Some(height) if height <= self.fork_height => height,
+            _ => {
+                return Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into())
+            }
+        };
+        if self
+            .source
+            .get_block_at_height(&self.fork_point, height)?
+            .as_ref()
+            != Some(&bhh)
+        {
+            // not an ancestor of the fork point
+            return Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into());
+        }
+        self.read_at = Some((bhh, height));
+        Ok(prior)
+    }
+
+    fn get_block_at_height(&mut self, height: u32) -> Option<StacksBlockId> {
+        if height > self.get_current_block_height() {
+            return None;
+        }
+        if height <= self.fork_height {
+            return match self.source.get_block_at_height(&self.fork_point, height) {
+                Ok(block) => block,
+                Err(e) => {
+                    warn!(
+                        "Failed to read the forked chain's block at height {}: {:?}",
+                        height, &e
+                    );
+                    None
+                }
+            };
+        }
+        if height > self.chain_tip_height {
+            return self.open_block.clone();
+        }
+        match self
+            .overlay
+            .query_row(
+                "SELECT block_id FROM fork_blocks WHERE height = ?1",
+                &[height],
+                |row| row.get(0),
+            )
+            .optional()
+        {
+            Ok(block) => block,
+            Err(e) => {
+                warn!(
+                    "Failed to read the local fork's block at height {}: {:?}",
+                    height, &e
+                );
+                None
+            }
+        }
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        match self.read_at {
+            Some((_, height)) => height,
+            None => self.get_open_chain_tip_height(),
+        }
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        if self.open_block.is_some() {
+            self.chain_tip_height + 1
+        } else {
+            self.chain_tip_height
+        }
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        self.open_block
+            .clone()
+            .unwrap_or_else(|| self.chain_tip.clone())
+    }
+
+    fn get_side_store(&mut self) -> &Connection {
+        &self.overlay
+    }
+
+    fn get_contract_hash(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+    ) -> InterpreterResult<(StacksBlockId, Sha512Trunc256Sum)> {
+        sqlite_get_contract_hash(self, contract)
+    }
+
+    fn insert_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        value: &str,
+    ) -> InterpreterResult<()> {
+        if self.open_block.is_none() {
+            return Err(InterpreterError::DBError("No local block is open".into()).into());
+        }
+        sqlite_insert_metadata(self, contract, key, value)
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        let (bhh, _) = self.get_contract_hash(contract)?;
+        self.get_metadata_in_block(&bhh, contract, key, None)
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        let bhh = self.get_block_at_height(at_height).ok_or_else(|| {
+            warn!("Unknown block height when manually querying metadata"; "block_height" => at_height);
+            RuntimeErrorType::BadBlockHeight(at_height.to_string())
+        })?;
+        self.get_metadata_in_block(&bhh, contract, key, Some(at_height))
+    }
+}
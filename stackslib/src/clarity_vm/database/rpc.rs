@@ -0,0 +1,393 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A read-only `ClarityBackingStore` which reads Clarity state from an untrusted node's RPC
+//! interface, and only returns values which it can prove.
+//!
+//! Every read asks the node for a MARF proof, and checks it against the `state_index_root` of a
+//! block header that the caller already trusts (e.g. because it verified the headers itself).
+//! A read whose proof is missing or does not verify is rejected with an error, so a node can
+//! withhold data from this store but cannot forge it.
+//!
+//! Only data vars and data map entries can be read, since those are the only values which the
+//! RPC interface returns together with the MARF keys that they are stored under.  In
+//! particular:
+//! * the MARF has no proofs of absence, so reading a key which was never set is an error
+//! (a map entry which was deleted is stored as `none`, and so can be read);
+//! * account balances cannot be checked, because `getaccount` returns them decoded and not as
+//! the serialized value that the MARF commits to;
+//! * contract constants and all other contract metadata are not stored in the MARF at all, so
+//! `getconstantval` has no proof to check.
+//!
+//! Since contract metadata (the contract's AST, analysis and data types) cannot be verified,
+//! every metadata read or write fails with an "unverifiable" error instead of returning None.
+//! A caller which needs metadata, such as the Clarity VM running contract code, therefore fails
+//! closed rather than treating the contract as missing.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use clarity::util::hash::Sha512Trunc256Sum;
+use clarity::vm::database::{ClarityBackingStore, SqliteConnection, StoreType};
+use clarity::vm::errors::{InterpreterError, InterpreterResult, RuntimeErrorType};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::{ClarityName, Value};
+use rusqlite::{Connection, Row, NO_PARAMS};
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId, TrieHash};
+
+use crate::chainstate::stacks::index::TrieMerkleProof;
+use crate::net::httpcore::TipRequest;
+use crate::net::httpcore::{StacksHttp, StacksHttpRequest};
+use crate::net::Error as NetError;
+use crate::util_lib::db::{query_rows, Error as DBError, FromColumn, FromRow};
+
+/// Default timeout for each request to the node
+pub const RPC_STORE_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A block header that the caller of `ProvenRPCStore` trusts
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustedHeader {
+    pub index_block_hash: StacksBlockId,
+    pub parent_block_id: StacksBlockId,
+    pub block_height: u32,
+    /// Root hash of the Clarity MARF after this block was processed
+    pub state_index_root: TrieHash,
+}
+
+impl FromRow<TrustedHeader> for TrustedHeader {
+    fn from_row<'a>(row: &'a Row) -> Result<TrustedHeader, DBError> {
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let parent_block_id = StacksBlockId::from_column(row, "parent_block_id")?;
+        let block_height: u64 = u64::from_column(row, "block_height")?;
+        let state_index_root = TrieHash::from_column(row, "state_index_root")?;
+        Ok(TrustedHeader {
+            index_block_hash,
+            parent_block_id,
+            block_height: u32::try_from(block_height).map_err(|_| DBError::ParseError)?,
+            state_index_root,
+        })
+    }
+}
+
+impl TrustedHeader {
+    /// Load the headers of all epoch 2.x and Nakamoto blocks in a chainstate's headers DB, in
+    /// every fork.  Only use this on a chainstate you trust.
+    pub fn load_all(headers_conn: &Connection) -> Result<Vec<TrustedHeader>, DBError> {
+        let sql = "SELECT index_block_hash, parent_block_id, block_height, state_index_root FROM block_headers
+                   UNION ALL
+                   SELECT index_block_hash, parent_block_id, block_height, state_index_root FROM nakamoto_block_headers";
+        query_rows(headers_conn, sql, NO_PARAMS)
+    }
+
+    /// Map each header's `state_index_root` to its block, as needed to verify MARF proofs
+    pub fn root_to_block_map(headers: &[TrustedHeader]) -> HashMap<TrieHash, StacksBlockId> {
+        headers
+            .iter()
+            .map(|header| {
+                (
+                    header.state_index_root.clone(),
+                    header.index_block_hash.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A MARF key whose value can be read over RPC along with a proof
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProvableKey {
+    DataVar(QualifiedContractIdentifier, ClarityName),
+    MapEntry(QualifiedContractIdentifier, ClarityName, Value),
+}
+
+impl ProvableKey {
+    /// Parse a Clarity MARF key of the form `vm::{contract}::{type}::{name}[::{key}]`, where
+    /// `type` is a data var or data map.  Returns None for any other key.
+    pub fn parse(key: &str) -> Option<ProvableKey> {
+        let mut parts = key.split("::");
+        if parts.next()? != "vm" {
+            return None;
+        }
+        let contract = QualifiedContractIdentifier::parse(parts.next()?).ok()?;
+        let store_type: u8 = parts.next()?.parse().ok()?;
+        let name = ClarityName::try_from(parts.next()?.to_string()).ok()?;
+        let map_key = parts.next();
+        if parts.next().is_some() {
+            return None;
+        }
+
+        match map_key {
+            None if store_type == StoreType::Variable as u8 => {
+                Some(ProvableKey::DataVar(contract, name))
+            }
+            Some(map_key) if store_type == StoreType::DataMap as u8 => {
+                let map_key = Value::try_deserialize_hex_untyped(map_key).ok()?;
+                Some(ProvableKey::MapEntry(contract, name, map_key))
+            }
+            _ => None,
+        }
+    }
+
+    /// Make the RPC request for this key's value and proof at `tip`
+    pub fn make_request(&self, node: SocketAddr, tip: &StacksBlockId) -> StacksHttpRequest {
+        let tip_req = TipRequest::SpecificTip(tip.clone());
+        match self {
+            ProvableKey::DataVar(contract, var_name) => StacksHttpRequest::new_getdatavar(
+                node.into(),
+                StacksAddress::from(contract.issuer.clone()),
+                contract.name.clone(),
+                var_name.clone(),
+                tip_req,
+                true,
+            ),
+            ProvableKey::MapEntry(contract, map_name, map_key) => {
+                StacksHttpRequest::new_getmapentry(
+                    node.into(),
+                    StacksAddress::from(contract.issuer.clone()),
+                    contract.name.clone(),
+                    map_name.clone(),
+                    map_key.clone(),
+                    tip_req,
+                    true,
+                )
+            }
+        }
+    }
+}
+
+fn rpc_error(key: &str, err: NetError) -> InterpreterError {
+    InterpreterError::DBError(format!("Failed to read {} over RPC: {:?}", key, &err))
+}
+
+/// A read-only backing store over an untrusted node's RPC interface.  See the module
+/// documentation for what it can and cannot read.
+pub struct ProvenRPCStore {
+    node: SocketAddr,
+    timeout: Duration,
+    chain_tip: StacksBlockId,
+    headers: HashMap<StacksBlockId, TrustedHeader>,
+    root_to_block: HashMap<TrieHash, StacksBlockId>,
+    side_store: Connection,
+}
+
+impl ProvenRPCStore {
+    /// Read state as of `tip` from the node at `node`.  `headers` must contain the trusted
+    /// headers of `tip` and of all of its ancestors, and may contain headers of other forks.
+    pub fn new(
+        node: SocketAddr,
+        tip: StacksBlockId,
+        headers: Vec<TrustedHeader>,
+    ) -> InterpreterResult<ProvenRPCStore> {
+        let root_to_block = TrustedHeader::root_to_block_map(&headers);
+        let headers: HashMap<_, _> = headers
+            .into_iter()
+            .map(|header| (header.index_block_hash.clone(), header))
+            .collect();
+        if !headers.contains_key(&tip) {
+            return Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(tip.0)).into());
+        }
+
+        Ok(ProvenRPCStore {
+            node,
+            timeout: RPC_STORE_DEFAULT_TIMEOUT,
+            chain_tip: tip,
+            headers,
+            root_to_block,
+            side_store: SqliteConnection::memory()?,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> ProvenRPCStore {
+        self.timeout = timeout;
+        self
+    }
+
+    fn tip_header(&self) -> &TrustedHeader {
+        self.headers
+            .get(&self.chain_tip)
+            .expect("FATAL: chain tip has no trusted header")
+    }
+
+    /// Check a value and proof which the node returned for `key` at the current tip.  `data`
+    /// and `proof_hex` are the hex strings from the RPC response.  Returns the value (without
+    /// the `0x` prefix) and the decoded proof.
+    pub fn check_response(
+        &self,
+        key: &str,
+        data: &str,
+        proof_hex: Option<&str>,
+    ) -> InterpreterResult<(String, Vec<u8>)> {
+        let value = data.strip_prefix("0x").unwrap_or(data);
+        let proof_hex = match proof_hex {
+            Some(proof_hex) if !proof_hex.is_empty() => proof_hex,
+            _ => {
+                return Err(InterpreterError::MarfFailure(format!(
+                    "No proof for {} at {}: the value may not exist, and absence cannot be proven",
+                    key, &self.chain_tip
+                ))
+                .into());
+            }
+        };
+        let proof = TrieMerkleProof::<StacksBlockId>::from_hex(proof_hex).map_err(|e| {
+            InterpreterError::MarfFailure(format!("Malformed proof for {}: {:?}", key, &e))
+        })?;
+
+        if !proof.verify_entry(
+            key,
+            value,
+            &self.tip_header().state_index_root,
+            &self.root_to_block,
+        ) {
+            warn!("Rejecting unproven value from {}", &self.node;
+                  "key" => key,
+                  "value" => value,
+                  "tip" => %self.chain_tip);
+            return Err(InterpreterError::MarfFailure(format!(
+                "Proof for {} at {} does not verify",
+                key, &self.chain_tip
+            ))
+            .into());
+        }
+
+        Ok((value.to_string(), proof.serialize_to_vec()))
+    }
+
+    /// Read the hex-encoded value of the MARF key `key` at the current tip, along with its
+    /// proof.  Reads which cannot be proven are errors.
+    fn fetch(&self, key: &str) -> InterpreterResult<(String, Vec<u8>)> {
+        let provable_key = ProvableKey::parse(key).ok_or_else(|| {
+            InterpreterError::DBError(format!("Cannot read {} with a proof over RPC", key))
+        })?;
+        let request = provable_key.make_request(self.node, &self.chain_tip);
+        let response = StacksHttp::send_request(&self.node, request, self.timeout)
+            .map_err(|e| rpc_error(key, e))?;
+
+        let (data, proof) = match provable_key {
+            ProvableKey::DataVar(..) => {
+                let resp = response
+                    .decode_data_var_response()
+                    .map_err(|e| rpc_error(key, e))?;
+                (resp.data, resp.marf_proof)
+            }
+            ProvableKey::MapEntry(..) => {
+                let resp = response
+                    .decode_map_entry_response()
+                    .map_err(|e| rpc_error(key, e))?;
+                (resp.data, resp.marf_proof)
+            }
+        };
+        self.check_response(key, &data, proof.as_deref())
+    }
+
+    fn read_only_error() -> InterpreterError {
+        InterpreterError::DBError("ProvenRPCStore is read-only".into())
+    }
+
+    fn unverifiable_metadata_error(contract: &QualifiedContractIdentifier) -> InterpreterError {
+        InterpreterError::DBError(format!(
+            "Metadata of {} is unverifiable: it is not committed to by the MARF",
+            contract
+        ))
+    }
+}
+
+impl ClarityBackingStore for ProvenRPCStore {
+    fn put_all_data(&mut self, _items: Vec<(String, String)>) -> InterpreterResult<()> {
+        Err(ProvenRPCStore::read_only_error().into())
+    }
+
+    /// Reads which cannot be proven are errors, not None
+    fn get_data(&mut self, key: &str) -> InterpreterResult<Option<String>> {
+        self.fetch(key).map(|(value, _)| Some(value))
+    }
+
+    /// Reads which cannot be proven are errors, not None
+    fn get_data_with_proof(&mut self, key: &str) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        self.fetch(key).map(Some)
+    }
+
+    /// Switch to another trusted block.  Returns the previous tip.
+    fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        if !self.headers.contains_key(&bhh) {
+            return Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into());
+        }
+        Ok(std::mem::replace(&mut self.chain_tip, bhh))
+    }
+
+    /// Get the tip's ancestor at `height` (or the tip itself), by following the trusted headers'
+    /// parent links.  Returns None if `height` is above the tip, or if the headers run out first.
+    fn get_block_at_height(&mut self, height: u32) -> Option<StacksBlockId> {
+        let mut header = self.tip_header();
+        while header.block_height > height {
+            header = self.headers.get(&header.parent_block_id)?;
+        }
+        if header.block_height != height {
+            return None;
+        }
+        Some(header.index_block_hash.clone())
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        self.tip_header().block_height
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        self.tip_header().block_height
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        self.chain_tip.clone()
+    }
+
+    fn get_side_store(&mut self) -> &Connection {
+        &self.side_store
+    }
+
+    fn get_contract_hash(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+    ) -> InterpreterResult<(StacksBlockId, Sha512Trunc256Sum)> {
+        Err(ProvenRPCStore::unverifiable_metadata_error(contract).into())
+    }
+
+    fn insert_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        _key: &str,
+        _value: &str,
+    ) -> InterpreterResult<()> {
+        Err(ProvenRPCStore::unverifiable_metadata_error(contract).into())
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        _key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        Err(ProvenRPCStore::unverifiable_metadata_error(contract).into())
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        _at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        _key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        Err(ProvenRPCStore::unverifiable_metadata_error(contract).into())
+    }
+}
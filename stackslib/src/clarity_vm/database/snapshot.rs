@@ -0,0 +1,837 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Snapshots of the Clarity MARF at a chosen chain tip.
+//!
+//! A snapshot holds every trie reachable from the tip (i.e. the tip's trie and the tries of all
+//! of its ancestors), the `data_table` values referenced by those tries' leaves, and the
+//! `metadata_table` rows of those blocks.  It is laid out as follows (integers are big-endian):
+//!
+//! ```text
+//! "MARFSNAP" | version: u32 | header length: u32 | header (JSON)
+//! records...
+//! ```
+//!
+//! Each record starts with a one-byte tag:
+//!
+//! * `0x01` trie: `block_id: u32 | block hash: [u8; 32] | length: u64 | trie blob`.  Tries are
+//!   written oldest-first, and keep their original block IDs since back-pointers refer to
+//!   ancestor tries by block ID.
+//! * `0x02` data: `key length: u32 | key | value length: u32 | value`
+//! * `0x03` metadata: `block hash: [u8; 32] | key length: u32 | key | value length: u32 | value`
+//! * `0xff` end of snapshot
+//!
+//! Importing a snapshot does not require trusting its source.  Every node hash of every trie is
+//! recomputed, and each trie's root hash (which commits to its ancestors' root hashes) is
+//! checked against the `state_index_root` of the corresponding block header.  Every `data_table`
+//! value is checked against its key, which is the value's hash, and every leaf of every trie must
+//! have its value in the snapshot (save for the MARF's own block height records).
+//!
+//! The `metadata_table` rows (i.e. contract sources, ASTs and analyses) are not committed to by
+//! the state root, so they are checked by replaying the deployments which wrote them.  A
+//! contract's rows must be stored under the block which the MARF says deployed it, its source
+//! must hash to the contract hash which the MARF commits to, and deploying that source on a
+//! scratch block must write exactly the same rows.  Rows which no deployment writes (e.g. the
+//! cost-voting contract's state summaries) cannot be verified, and are rejected.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::{error, fmt, fs, io};
+
+use clarity::vm::analysis::{run_analysis, AnalysisDatabase, ContractAnalysis};
+use clarity::vm::ast::{build_ast_with_rules, ASTRules};
+use clarity::vm::contexts::OwnedEnvironment;
+use clarity::vm::contracts::Contract;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::database::clarity_store::{make_contract_hash_key, ContractCommitment};
+use clarity::vm::database::{
+    BurnStateDB, ClarityBackingStore, ClarityDatabase, ClarityDeserializable, HeadersDB,
+    SqliteConnection, StoreType,
+};
+use clarity::vm::types::QualifiedContractIdentifier;
+use rusqlite::{OptionalExtension, ToSql, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use stacks_common::types::chainstate::{StacksBlockId, TrieHash};
+use stacks_common::types::StacksEpochId;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection};
+use crate::chainstate::stacks::index::node::TrieNodeType;
+use crate::chainstate::stacks::index::storage::{
+    TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection,
+};
+use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::{
+    trie_sql, ClarityMarfTrieId, Error as MARFError, MARFValue,
+};
+use crate::clarity_vm::database::marf::{MarfedKV, WritableMarfStore};
+
+/// Magic bytes at the start of every snapshot
+pub const MARF_SNAPSHOT_MAGIC: &[u8; 8] = b"MARFSNAP";
+/// Snapshot format version
+pub const MARF_SNAPSHOT_VERSION: u32 = 1;
+
+const RECORD_TRIE: u8 = 0x01;
+const RECORD_DATA: u8 = 0x02;
+const RECORD_METADATA: u8 = 0x03;
+const RECORD_END: u8 = 0xff;
+
+#[derive(Debug)]
+pub enum Error {
+    /// I/O error while reading or writing the snapshot
+    IOError(io::Error),
+    /// MARF error
+    MARFError(MARFError),
+    /// Error in the Clarity side-store
+    DBError(rusqlite::Error),
+    /// The snapshot is malformed
+    Malformed(String),
+    /// No block header is known for this block, so its trie cannot be verified
+    NoSuchHeader(StacksBlockId),
+    /// A trie's recomputed root hash does not match its block header's `state_index_root`
+    RootHashMismatch(StacksBlockId, TrieHash, TrieHash),
+    /// A `data_table` value does not hash to its key
+    BadValue(String),
+    /// A trie leaf's `data_table` value is missing
+    MissingValue(String),
+    /// A contract's `metadata_table` rows at a block could not be verified
+    BadMetadata(QualifiedContractIdentifier, StacksBlockId, String),
+    /// The destination MARF already exists
+    AlreadyExists(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IOError(ref e) => fmt::Display::fmt(e, f),
+            Error::MARFError(ref e) => fmt::Display::fmt(e, f),
+            Error::DBError(ref e) => fmt::Display::fmt(e, f),
+            Error::Malformed(ref s) => write!(f, "Malformed snapshot: {}", s),
+            Error::NoSuchHeader(ref id) => write!(f, "No block header for {}", id),
+            Error::RootHashMismatch(ref id, ref expected, ref computed) => write!(
+                f,
+                "Trie {} has root hash {}, but its block header has state root {}",
+                id, computed, expected
+            ),
+            Error::BadValue(ref key) => write!(f, "Data value for key {} does not match", key),
+            Error::MissingValue(ref key) => write!(f, "No data value for key {}", key),
+            Error::BadMetadata(ref contract_id, ref block_id, ref reason) => write!(
+                f,
+                "Metadata of {} at {} does not verify: {}",
+                contract_id, block_id, reason
+            ),
+            Error::AlreadyExists(ref path) => write!(f, "{} already exists", path),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::IOError(ref e) => Some(e),
+            Error::MARFError(ref e) => Some(e),
+            Error::DBError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::IOError(e)
+    }
+}
+
+impl From<MARFError> for Error {
+    fn from(e: MARFError) -> Error {
+        Error::MARFError(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Error {
+        Error::DBError(e)
+    }
+}
+
+/// Snapshot header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarfSnapshotHeader {
+    /// The chain tip this snapshot was taken at
+    pub tip: StacksBlockId,
+    /// The MARF root hash at `tip`
+    pub tip_root_hash: TrieHash,
+    /// Number of tries in the snapshot
+    pub num_tries: u64,
+}
+
+/// What was written to or read from a snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarfSnapshotSummary {
+    pub header: MarfSnapshotHeader,
+    pub num_data: u64,
+    pub num_metadata: u64,
+}
+
+fn write_u32<W: Write>(w: &mut W, x: u32) -> Result<(), Error> {
+    w.write_all(&x.to_be_bytes())?;
+    Ok(())
+}
+
+fn write_u64<W: Write>(w: &mut W, x: u64) -> Result<(), Error> {
+    w.write_all(&x.to_be_bytes())?;
+    Ok(())
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> Result<(), Error> {
+    let len = u32::try_from(s.len())
+        .map_err(|_| Error::Malformed(format!("string of {} bytes is too long", s.len())))?;
+    write_u32(w, len)?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, Error> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32, Error> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, Error> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_block_id<R: Read>(r: &mut R) -> Result<StacksBlockId, Error> {
+    let mut buf = [0u8; 32];
+    r.read_exact(&mut buf)?;
+    Ok(StacksBlockId(buf))
+}
+
+/// Read exactly `len` bytes.  The buffer grows as bytes arrive, so a bogus length in a truncated
+/// snapshot cannot make us allocate it all up front.
+fn read_bytes<R: Read>(r: &mut R, len: u64) -> Result<Vec<u8>, Error> {
+    let mut buf = vec![];
+    r.by_ref().take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) != len {
+        return Err(Error::IOError(io::Error::from(
+            io::ErrorKind::UnexpectedEof,
+        )));
+    }
+    Ok(buf)
+}
+
+fn read_string<R: Read>(r: &mut R) -> Result<String, Error> {
+    let len = read_u32(r)?;
+    String::from_utf8(read_bytes(r, len.into())?)
+        .map_err(|_| Error::Malformed("string is not UTF-8".into()))
+}
+
+/// Get the parent block hash from a serialized trie
+fn trie_blob_parent(blob: &[u8]) -> Result<StacksBlockId, Error> {
+    let parent_bytes: [u8; 32] = blob
+        .get(0..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Malformed("trie blob is too short".into()))?;
+    Ok(StacksBlockId(parent_bytes))
+}
+
+/// The values of the MARF's own block height records (see `MARF::set_block_heights`) in a chain
+/// of `tries`.  These are the only leaves whose values are not in the `data_table`.
+fn block_height_values(tries: &[(u32, StacksBlockId)]) -> HashSet<String> {
+    let mut values = HashSet::new();
+    for height in 0..=tries.len() {
+        values.insert(MARFValue::from(height as u32).to_hex());
+    }
+    for (_, block_hash) in tries.iter() {
+        values.insert(MARFValue::from(block_hash.clone()).to_hex());
+    }
+    values
+}
+
+/// Get the `data_table` values of the leaves of the currently-open trie, keyed by their hashes.
+/// Fails if one is missing.
+fn leaf_values(
+    storage: &mut TrieStorageConnection<StacksBlockId>,
+    height_values: &HashSet<String>,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut keys = vec![];
+    Trie::walk_trie_nodes(storage, |_, _, node, _| {
+        if let TrieNodeType::Leaf(leaf) = node {
+            keys.push(leaf.data.to_hex());
+        }
+        Ok(())
+    })?;
+
+    let mut values = vec![];
+    for key in keys.into_iter() {
+        if height_values.contains(&key) {
+            continue;
+        }
+        let value: Option<String> = storage
+            .sqlite_conn()
+            .query_row(
+                "SELECT value FROM data_table WHERE key = ?1",
+                &[&key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let value = value.ok_or_else(|| Error::MissingValue(key.clone()))?;
+        values.push((key, value));
+    }
+    Ok(values)
+}
+
+/// Find the (block ID, block hash) of every trie reachable from `tip`, oldest-first.
+fn reachable_tries(
+    storage: &mut TrieStorageConnection<StacksBlockId>,
+    tip: &StacksBlockId,
+) -> Result<Vec<(u32, StacksBlockId)>, Error> {
+    let mut tries = vec![];
+    let mut cursor = tip.clone();
+    while cursor != StacksBlockId::sentinel() {
+        let block_id = trie_sql::get_confirmed_block_identifier(storage.sqlite_conn(), &cursor)?
+            .ok_or_else(|| MARFError::NotFoundError)?;
+        let blob = storage.read_trie_blob(block_id)?;
+        let parent = trie_blob_parent(&blob)?;
+        tries.push((block_id, cursor));
+        cursor = parent;
+    }
+    tries.reverse();
+    Ok(tries)
+}
+
+/// Write a snapshot of the Clarity MARF at `marf_path` (i.e. the `marf.sqlite` file of a
+/// `MarfedKV`) as of `tip`.
+pub fn export_marf_snapshot<W: Write>(
+    marf_path: &str,
+    tip: &StacksBlockId,
+    w: &mut W,
+) -> Result<MarfSnapshotSummary, Error> {
+    let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    let mut storage = TrieFileStorage::<StacksBlockId>::open_readonly(marf_path, marf_opts)?;
+    let mut storage = storage.connection();
+
+    let tip_root_hash = storage
+        .read_all_block_hashes_and_roots()?
+        .into_iter()
+        .find_map(|(root_hash, block_hash)| (&block_hash == tip).then_some(root_hash))
+        .ok_or_else(|| MARFError::NotFoundError)?;
+
+    let tries = reachable_tries(&mut storage, tip)?;
+    let header = MarfSnapshotHeader {
+        tip: tip.clone(),
+        tip_root_hash,
+        num_tries: tries.len() as u64,
+    };
+    let header_json = serde_json::to_vec(&header)
+        .map_err(|e| Error::Malformed(format!("failed to encode header: {}", e)))?;
+
+    w.write_all(MARF_SNAPSHOT_MAGIC)?;
+    write_u32(w, MARF_SNAPSHOT_VERSION)?;
+    write_u32(w, header_json.len() as u32)?;
+    w.write_all(&header_json)?;
+
+    // the data_table keys are the hashes of the values in the tries' leaves
+    let height_values = block_height_values(&tries);
+    let mut data = BTreeMap::new();
+    for (block_id, block_hash) in tries.iter() {
+        let blob = storage.read_trie_blob(*block_id)?;
+        w.write_all(&[RECORD_TRIE])?;
+        write_u32(w, *block_id)?;
+        w.write_all(block_hash.as_bytes())?;
+        write_u64(w, blob.len() as u64)?;
+        w.write_all(&blob)?;
+
+        storage.open_block_known_id(block_hash, *block_id)?;
+        data.extend(leaf_values(&mut storage, &height_values)?);
+    }
+
+    let mut num_data = 0;
+    for (key, value) in data.iter() {
+        w.write_all(&[RECORD_DATA])?;
+        write_string(w, key)?;
+        write_string(w, value)?;
+        num_data += 1;
+    }
+
+    // in the order they were written, so that each block's deployments can be replayed in order
+    let mut num_metadata = 0;
+    for (_, block_hash) in tries.iter() {
+        let mut stmt = storage
+            .sqlite_conn()
+            .prepare("SELECT key, value FROM metadata_table WHERE blockhash = ?1 ORDER BY rowid")?;
+        let mut rows = stmt.query(&[block_hash])?;
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let value: String = row.get(1)?;
+            w.write_all(&[RECORD_METADATA])?;
+            w.write_all(block_hash.as_bytes())?;
+            write_string(w, &key)?;
+            write_string(w, &value)?;
+            num_metadata += 1;
+        }
+    }
+
+    w.write_all(&[RECORD_END])?;
+    w.flush()?;
+
+    Ok(MarfSnapshotSummary {
+        header,
+        num_data,
+        num_metadata,
+    })
+}
+
+/// Read a snapshot's magic bytes, version and header
+pub fn read_marf_snapshot_header<R: Read>(r: &mut R) -> Result<MarfSnapshotHeader, Error> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MARF_SNAPSHOT_MAGIC {
+        return Err(Error::Malformed("not a MARF snapshot".into()));
+    }
+    let version = read_u32(r)?;
+    if version != MARF_SNAPSHOT_VERSION {
+        return Err(Error::Malformed(format!(
+            "unsupported snapshot version {}",
+            version
+        )));
+    }
+    let header_len = read_u32(r)?;
+    let header_json = read_bytes(r, header_len.into())?;
+    serde_json::from_slice(&header_json).map_err(|e| Error::Malformed(format!("bad header: {}", e)))
+}
+
+/// Create a new Clarity MARF at `marf_path` from a snapshot, and verify it.
+///
+/// `expected_root_hash` returns the `state_index_root` of the given block's header, or `None` if
+/// no such header is known.  Every trie in the snapshot must have a header, and its recomputed
+/// root hash must match.
+///
+/// Contract deployments are replayed on the chain given by `mainnet` and `chain_id`, reading
+/// block and burnchain information from `headers_db` and `burn_state_db`.  A deployment is
+/// replayed on top of its block's parent, with the Clarity version recorded in its analysis, so
+/// a contract whose top-level definitions read state written earlier in the same block (or
+/// burnchain state missing from `burn_state_db`) will fail to verify.
+///
+/// On error, the MARF will have been partially written, so the caller should remove it (and its
+/// `.blobs` file).
+pub fn import_marf_snapshot<R: Read, F>(
+    marf_path: &str,
+    r: &mut R,
+    mainnet: bool,
+    chain_id: u32,
+    headers_db: &dyn HeadersDB,
+    burn_state_db: &dyn BurnStateDB,
+    mut expected_root_hash: F,
+) -> Result<MarfSnapshotSummary, Error>
+where
+    F: FnMut(&StacksBlockId) -> Result<Option<TrieHash>, Error>,
+{
+    if fs::metadata(marf_path).is_ok() {
+        return Err(Error::AlreadyExists(marf_path.to_string()));
+    }
+    let header = read_marf_snapshot_header(r)?;
+
+    let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Immediate, "noop", true);
+    let mut storage = TrieFileStorage::<StacksBlockId>::open(marf_path, marf_opts)?;
+    SqliteConnection::initialize_conn(storage.sqlite_conn())
+        .map_err(|e| Error::Malformed(format!("failed to set up Clarity tables: {:?}", e)))?;
+    let mut tx = storage.transaction()?;
+    // metadata rows are held here until their contracts' deployments are replayed
+    tx.sqlite_tx().execute(
+        "CREATE TABLE snapshot_metadata (blockhash TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL)",
+        NO_PARAMS,
+    )?;
+
+    let mut tries: Vec<(u32, StacksBlockId)> = vec![];
+    let mut trie_hashes = HashSet::new();
+    let mut num_data = 0;
+    let mut num_metadata = 0;
+    loop {
+        match read_u8(r)? {
+            RECORD_TRIE => {
+                let block_id = read_u32(r)?;
+                let block_hash = read_block_id(r)?;
+                let len = read_u64(r)?;
+                let blob = read_bytes(r, len)?;
+
+                // tries must form a single chain, oldest first
+                let parent = trie_blob_parent(&blob)?;
+                let expected_parent = tries
+                    .last()
+                    .map(|(_, block_hash)| block_hash.clone())
+                    .unwrap_or_else(StacksBlockId::sentinel);
+                if parent != expected_parent {
+                    return Err(Error::Malformed(format!(
+                        "trie {} has parent {}, expected {}",
+                        &block_hash, &parent, &expected_parent
+                    )));
+                }
+
+                tx.write_trie_blob_with_id(block_id, &block_hash, &blob)?;
+                trie_hashes.insert(block_hash.clone());
+                tries.push((block_id, block_hash));
+            }
+            RECORD_DATA => {
+                let key = read_string(r)?;
+                let value = read_string(r)?;
+                if MARFValue::from_value(&value).to_hex() != key {
+                    return Err(Error::BadValue(key));
+                }
+                let args: &[&dyn ToSql] = &[&key, &value];
+                tx.sqlite_tx().execute(
+                    "INSERT OR REPLACE INTO data_table (key, value) VALUES (?1, ?2)",
+                    args,
+                )?;
+                num_data += 1;
+            }
+            RECORD_METADATA => {
+                let block_hash = read_block_id(r)?;
+                let key = read_string(r)?;
+                let value = read_string(r)?;
+                if !trie_hashes.contains(&block_hash) {
+                    return Err(Error::Malformed(format!(
+                        "metadata for block {} which is not in the snapshot",
+                        &block_hash
+                    )));
+                }
+                let args: &[&dyn ToSql] = &[&block_hash, &key, &value];
+                tx.sqlite_tx().execute(
+                    "INSERT INTO snapshot_metadata (blockhash, key, value) VALUES (?1, ?2, ?3)",
+                    args,
+                )?;
+                num_metadata += 1;
+            }
+            RECORD_END => {
+                break;
+            }
+            tag => {
+                return Err(Error::Malformed(format!("unknown record tag {}", tag)));
+            }
+        }
+    }
+
+    if (tries.len() as u64) != header.num_tries
+        || tries.last().map(|(_, block_hash)| block_hash) != Some(&header.tip)
+    {
+        return Err(Error::Malformed(format!(
+            "expected {} tries ending at {}",
+            header.num_tries, &header.tip
+        )));
+    }
+
+    // ancestors first, since each root hash mixes in its ancestors' root hashes
+    let height_values = block_height_values(&tries);
+    for (block_id, block_hash) in tries.iter() {
+        tx.open_block_known_id(block_hash, *block_id)?;
+        let root_hash = Trie::verify_trie_hashes(&mut tx)?;
+        let expected = expected_root_hash(block_hash)?
+            .ok_or_else(|| Error::NoSuchHeader(block_hash.clone()))?;
+        if root_hash != expected {
+            return Err(Error::RootHashMismatch(
+                block_hash.clone(),
+                expected,
+                root_hash,
+            ));
+        }
+        leaf_values(&mut tx, &height_values)?;
+        debug!("Verified trie {} with root hash {}", block_hash, &root_hash);
+    }
+
+    if header.tip_root_hash != tx.get_root_hash_at(&header.tip)? {
+        return Err(Error::Malformed(format!(
+            "header claims root hash {} for tip {}",
+            &header.tip_root_hash, &header.tip
+        )));
+    }
+
+    tx.commit_tx();
+    drop(storage);
+
+    let marf_dir = Path::new(marf_path)
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .ok_or_else(|| Error::Malformed(format!("bad MARF path {}", marf_path)))?;
+    let mut marf_kv = MarfedKV::open(marf_dir, None, None)
+        .map_err(|e| Error::Malformed(format!("failed to open imported MARF: {:?}", e)))?;
+    let mut parent = StacksBlockId::sentinel();
+    for (_, block_hash) in tries.iter() {
+        verify_block_metadata(
+            &mut marf_kv,
+            &parent,
+            block_hash,
+            mainnet,
+            chain_id,
+            headers_db,
+            burn_state_db,
+        )?;
+        // verified, so later blocks' deployments can see it
+        marf_kv.get_marf().sqlite_conn().execute(
+            "INSERT INTO metadata_table (blockhash, key, value)
+             SELECT blockhash, key, value FROM snapshot_metadata WHERE blockhash = ?1 ORDER BY rowid",
+            &[block_hash],
+        )?;
+        parent = block_hash.clone();
+    }
+    marf_kv
+        .get_marf()
+        .sqlite_conn()
+        .execute("DROP TABLE snapshot_metadata", NO_PARAMS)?;
+
+    Ok(MarfSnapshotSummary {
+        header,
+        num_data,
+        num_metadata,
+    })
+}
+
+/// Verify the metadata rows held for `block_hash` by replaying the deployments which wrote them,
+/// in the order they were written, on a scratch block off of `parent`.
+fn verify_block_metadata(
+    marf_kv: &mut MarfedKV,
+    parent: &StacksBlockId,
+    block_hash: &StacksBlockId,
+    mainnet: bool,
+    chain_id: u32,
+    headers_db: &dyn HeadersDB,
+    burn_state_db: &dyn BurnStateDB,
+) -> Result<(), Error> {
+    let mut contracts: Vec<(QualifiedContractIdentifier, BTreeMap<String, String>)> = vec![];
+    {
+        let mut stmt = marf_kv.get_marf().sqlite_conn().prepare(
+            "SELECT key, value FROM snapshot_metadata WHERE blockhash = ?1 ORDER BY rowid",
+        )?;
+        let mut rows = stmt.query(&[block_hash])?;
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let value: String = row.get(1)?;
+            // see `SqliteConnection::insert_metadata`
+            let (contract_id, key) = key
+                .strip_prefix("clr-meta::")
+                .and_then(|key| key.split_once("::"))
+                .and_then(|(contract_id, key)| {
+                    let contract_id = QualifiedContractIdentifier::parse(contract_id).ok()?;
+                    Some((contract_id, key.to_string()))
+                })
+                .ok_or_else(|| Error::Malformed(format!("bad metadata key {}", &key)))?;
+            let position = match contracts.iter().position(|(id, _)| id == &contract_id) {
+                Some(position) => position,
+                None => {
+                    contracts.push((contract_id, BTreeMap::new()));
+                    contracts.len() - 1
+                }
+            };
+            if contracts[position].1.insert(key.clone(), value).is_some() {
+                return Err(Error::Malformed(format!(
+                    "duplicate metadata {} for {} at {}",
+                    &key, &contracts[position].0, block_hash
+                )));
+            }
+        }
+    }
+    if contracts.is_empty() {
+        return Ok(());
+    }
+
+    // the block's height, epoch, and each contract's commitment
+    let (block_height, epoch, commitments) = {
+        let mut store = marf_kv.begin_read_only(Some(block_hash));
+        let block_height = store.get_current_block_height();
+        let mut commitments = vec![];
+        for (contract_id, _) in contracts.iter() {
+            let commitment = store
+                .get_data(&make_contract_hash_key(contract_id))
+                .and_then(|commitment| {
+                    commitment
+                        .map(|commitment| ContractCommitment::deserialize(&commitment))
+                        .transpose()
+                })
+                .map_err(|e| {
+                    Error::BadMetadata(
+                        contract_id.clone(),
+                        block_hash.clone(),
+                        format!("failed to read its contract hash: {:?}", e),
+                    )
+                })?;
+            commitments.push(commitment);
+        }
+        let mut clarity_db = store.as_clarity_db(headers_db, burn_state_db);
+        clarity_db.begin();
+        let epoch = clarity_db.get_clarity_epoch_version();
+        clarity_db
+            .roll_back()
+            .and(epoch)
+            .map(|epoch| (block_height, epoch, commitments))
+            .map_err(|e| {
+                Error::Malformed(format!("failed to read epoch at {}: {:?}", block_hash, e))
+            })?
+    };
+
+    let replay_block = StacksBlockId(
+        Sha512Trunc256Sum::from_data(
+            &[b"snapshot-replay".as_slice(), block_hash.as_bytes()].concat(),
+        )
+        .0,
+    );
+    let mut store = marf_kv.begin(parent, &replay_block);
+    let mut result = Ok(());
+    for ((contract_id, rows), commitment) in contracts.iter().zip(commitments.into_iter()) {
+        result = replay_deployment(
+            &mut store,
+            contract_id,
+            rows,
+            commitment,
+            block_height,
+            epoch,
+            mainnet,
+            chain_id,
+            headers_db,
+            burn_state_db,
+        )
+        .map_err(|reason| Error::BadMetadata(contract_id.clone(), block_hash.clone(), reason));
+        if result.is_err() {
+            break;
+        }
+    }
+    store.rollback_block();
+    result
+}
+
+/// Deploy `contract_id` from the source in its metadata `rows` into `store`, and check that the
+/// deployment writes exactly `rows`.
+#[allow(clippy::too_many_arguments)]
+fn replay_deployment(
+    store: &mut WritableMarfStore,
+    contract_id: &QualifiedContractIdentifier,
+    rows: &BTreeMap<String, String>,
+    commitment: Option<ContractCommitment>,
+    block_height: u32,
+    epoch: StacksEpochId,
+    mainnet: bool,
+    chain_id: u32,
+    headers_db: &dyn HeadersDB,
+    burn_state_db: &dyn BurnStateDB,
+) -> Result<(), String> {
+    let commitment = commitment
+        .filter(|commitment| commitment.block_height == block_height)
+        .ok_or_else(|| "the contract was not deployed in this block".to_string())?;
+    let source = rows
+        .get(&ClarityDatabase::make_metadata_key(
+            StoreType::Contract,
+            "contract-src",
+        ))
+        .ok_or_else(|| "no contract source".to_string())?;
+    if Sha512Trunc256Sum::from_data(source.as_bytes()) != commitment.hash {
+        return Err("the contract source does not match its committed hash".into());
+    }
+    let clarity_version = rows
+        .get(AnalysisDatabase::storage_key())
+        .and_then(|analysis| {
+            <ContractAnalysis as ClarityDeserializable<ContractAnalysis>>::deserialize(analysis)
+                .ok()
+        })
+        .map(|analysis| analysis.clarity_version)
+        .ok_or_else(|| "no contract analysis".to_string())?;
+
+    // as in `StacksChainState::process_transaction_payload`
+    let contract_ast = build_ast_with_rules(
+        contract_id,
+        source,
+        &mut (),
+        clarity_version,
+        epoch,
+        ASTRules::Typical,
+    )
+    .map_err(|e| format!("failed to parse the contract: {}", e))?;
+    let contract_analysis = run_analysis(
+        contract_id,
+        &contract_ast.expressions,
+        &mut store.as_analysis_db(),
+        false,
+        LimitedCostTracker::new_free(),
+        epoch,
+        clarity_version,
+        false,
+    )
+    .map_err(|(e, _)| format!("failed to analyze the contract: {}", e))?;
+    OwnedEnvironment::new_free(
+        mainnet,
+        chain_id,
+        store.as_clarity_db(headers_db, burn_state_db),
+        epoch,
+    )
+    .initialize_contract_from_ast(
+        contract_id.clone(),
+        clarity_version,
+        &contract_ast,
+        source,
+        None,
+    )
+    .map_err(|e| format!("failed to deploy the contract: {}", e))?;
+    let mut analysis_db = store.as_analysis_db();
+    analysis_db.begin();
+    analysis_db
+        .insert_contract(contract_id, &contract_analysis)
+        .and_then(|_| analysis_db.commit())
+        .map_err(|e| format!("failed to store the contract analysis: {}", e))?;
+
+    let mut written = BTreeMap::new();
+    let open_block = store.get_open_chain_tip();
+    let prefix = format!("clr-meta::{}::", contract_id);
+    let mut stmt = store
+        .get_side_store()
+        .prepare("SELECT key, value FROM metadata_table WHERE blockhash = ?1")
+        .map_err(|e| e.to_string())?;
+    let mut query = stmt.query(&[&open_block]).map_err(|e| e.to_string())?;
+    while let Some(row) = query.next().map_err(|e| e.to_string())? {
+        let key: String = row.get(0).map_err(|e| e.to_string())?;
+        let value: String = row.get(1).map_err(|e| e.to_string())?;
+        if let Some(key) = key.strip_prefix(&prefix) {
+            written.insert(key.to_string(), value);
+        }
+    }
+
+    let contract_key = ClarityDatabase::make_metadata_key(StoreType::Contract, "contract");
+    for key in rows.keys().chain(written.keys()) {
+        let matches = match (rows.get(key), written.get(key)) {
+            (Some(row), Some(value)) if row == value => true,
+            // the contract context holds hash maps, so its serialization is not canonical
+            (Some(row), Some(value)) if key == &contract_key => {
+                match (
+                    <Contract as ClarityDeserializable<Contract>>::deserialize(row),
+                    <Contract as ClarityDeserializable<Contract>>::deserialize(value),
+                ) {
+                    (Ok(row), Ok(value)) => row == value,
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if !matches {
+            return Err(format!("{} does not match its deployment", key));
+        }
+    }
+    Ok(())
+}
@@ -6,5 +6,8 @@ pub mod special;
 /// Stacks blockchain specific Clarity database implementations and wrappers
 pub mod database;
 
+/// Typed Rust bindings for contracts, generated from their interfaces
+pub mod bindgen;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,224 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use clarity::vm::database::{ClarityBackingStore, ClarityDatabase, StoreType};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::Value;
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::chainstate::stacks::index::ClarityMarfTrieId;
+use crate::clarity_vm::database::map_index::MapIndex;
+use crate::clarity_vm::database::marf::MarfedKV;
+
+fn map_index_test_dir(name: &str) -> String {
+    let path = format!("/tmp/stacks-node-tests/map-index-{}", name);
+    if fs::metadata(&path).is_ok() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    path
+}
+
+fn block_id(i: u8) -> StacksBlockId {
+    StacksBlockId([i; 32])
+}
+
+fn key_hex(i: u128) -> String {
+    Value::UInt(i).serialize_to_hex().unwrap()
+}
+
+/// A MARF write which sets (or, if `value` is None, deletes) an entry of `contract.map`
+fn map_write(
+    contract: &QualifiedContractIdentifier,
+    map: &str,
+    key: u128,
+    value: Option<u128>,
+) -> (String, String) {
+    let value = match value {
+        Some(v) => Value::some(Value::UInt(v)).unwrap(),
+        None => Value::none(),
+    };
+    (
+        ClarityDatabase::make_key_for_quad(contract, StoreType::DataMap, map, &key_hex(key)),
+        value.serialize_to_hex().unwrap(),
+    )
+}
+
+fn list_all(
+    marf_kv: &mut MarfedKV,
+    tip: &StacksBlockId,
+    contract: &QualifiedContractIdentifier,
+    map: &str,
+    limit: usize,
+) -> Vec<(String, String)> {
+    let mut entries = vec![];
+    let mut cursor = None;
+    loop {
+        let page = marf_kv
+            .get_map_entries(tip, contract, map, cursor.as_deref(), limit)
+            .unwrap()
+            .unwrap();
+        assert!(page.entries.len() <= limit);
+        entries.extend(page.entries);
+        if page.next_cursor.is_none() {
+            return entries;
+        }
+        cursor = page.next_cursor;
+    }
+}
+
+#[test]
+fn test_parse_data_map_key() {
+    let contract = QualifiedContractIdentifier::local("foo").unwrap();
+    let (key, _) = map_write(&contract, "my-map", 1, Some(2));
+    assert_eq!(
+        MapIndex::parse_data_map_key(&key),
+        Some((
+            "S1G2081040G2081040G2081040G208105NK8PE5.foo",
+            "my-map",
+            key_hex(1).as_str()
+        ))
+    );
+
+    let var_key = ClarityDatabase::make_key_for_trip(&contract, StoreType::Variable, "my-var");
+    assert_eq!(MapIndex::parse_data_map_key(&var_key), None);
+    assert_eq!(
+        MapIndex::parse_data_map_key("vm-epoch::epoch-version"),
+        None
+    );
+}
+
+#[test]
+fn test_map_index_forks_and_deletes() {
+    let path = map_index_test_dir("forks-and-deletes");
+    let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+    let contract = QualifiedContractIdentifier::local("foo").unwrap();
+    let other = QualifiedContractIdentifier::local("bar").unwrap();
+
+    // not enabled yet
+    assert!(marf_kv
+        .get_map_entries(&StacksBlockId::sentinel(), &contract, "map", None, 10)
+        .unwrap()
+        .is_none());
+
+    // written before the index was enabled, so never listed
+    let mut store = marf_kv.begin(&StacksBlockId::sentinel(), &block_id(1));
+    store
+        .put_all_data(vec![map_write(&contract, "map", 100, Some(100))])
+        .unwrap();
+    store.commit_to(&block_id(1)).unwrap();
+
+    marf_kv.enable_map_index().unwrap();
+
+    // block 2: keys 0..10 in `map`, plus a key in another map and another contract
+    let mut store = marf_kv.begin(&block_id(1), &block_id(2));
+    let mut writes: Vec<_> = (0..10)
+        .map(|i| map_write(&contract, "map", i, Some(i * 10)))
+        .collect();
+    writes.push(map_write(&contract, "other-map", 0, Some(1)));
+    writes.push(map_write(&other, "map", 0, Some(1)));
+    store.put_all_data(writes).unwrap();
+    store.commit_to(&block_id(2)).unwrap();
+
+    // block 3: delete the even keys, and update key 1
+    let mut store = marf_kv.begin(&block_id(2), &block_id(3));
+    let mut writes: Vec<_> = (0..10)
+        .step_by(2)
+        .map(|i| map_write(&contract, "map", i, None))
+        .collect();
+    writes.push(map_write(&contract, "map", 1, Some(11)));
+    store.put_all_data(writes).unwrap();
+    store.commit_to(&block_id(3)).unwrap();
+
+    // fork off of block 2 which only adds a key
+    let mut store = marf_kv.begin(&block_id(2), &block_id(0xf0));
+    store
+        .put_all_data(vec![map_write(&contract, "map", 20, Some(200))])
+        .unwrap();
+    store.commit_to(&block_id(0xf0)).unwrap();
+
+    // a mined block's keys are not kept
+    let mut store = marf_kv.begin(&block_id(3), &block_id(0xf1));
+    store
+        .put_all_data(vec![map_write(&contract, "map", 30, Some(300))])
+        .unwrap();
+    store.commit_mined_block(&block_id(0xf1)).unwrap();
+
+    let value_hex = |v: u128| Value::UInt(v).serialize_to_hex().unwrap();
+
+    let at_2 = list_all(&mut marf_kv, &block_id(2), &contract, "map", 3);
+    let expected: Vec<_> = (0..10).map(|i| (key_hex(i), value_hex(i * 10))).collect();
+    assert_eq!(at_2, expected);
+
+    let at_3 = list_all(&mut marf_kv, &block_id(3), &contract, "map", 2);
+    let expected: Vec<_> = (1..10)
+        .step_by(2)
+        .map(|i| (key_hex(i), value_hex(if i == 1 { 11 } else { i * 10 })))
+        .collect();
+    assert_eq!(at_3, expected);
+
+    let at_fork = list_all(&mut marf_kv, &block_id(0xf0), &contract, "map", 100);
+    let mut expected: Vec<_> = (0..10).map(|i| (key_hex(i), value_hex(i * 10))).collect();
+    expected.push((key_hex(20), value_hex(200)));
+    expected.sort();
+    assert_eq!(at_fork, expected);
+
+    assert_eq!(
+        list_all(&mut marf_kv, &block_id(2), &contract, "other-map", 10),
+        vec![(key_hex(0), value_hex(1))]
+    );
+    assert_eq!(
+        list_all(&mut marf_kv, &block_id(2), &other, "map", 10),
+        vec![(key_hex(0), value_hex(1))]
+    );
+    assert!(list_all(&mut marf_kv, &block_id(1), &contract, "map", 10).is_empty());
+
+    // a page which ends on the last key has no cursor
+    let page = marf_kv
+        .get_map_entries(&block_id(0xf0), &contract, "map", Some(&key_hex(9)), 1)
+        .unwrap()
+        .unwrap();
+    assert_eq!(page.entries, vec![(key_hex(20), value_hex(200))]);
+    assert_eq!(page.next_cursor, None);
+    // block 2 was the first block indexed
+    assert_eq!(page.index_start_height, Some(1));
+
+    // ...but if later keys were only written in another fork, the page after it is empty
+    let page = marf_kv
+        .get_map_entries(&block_id(3), &contract, "map", None, 5)
+        .unwrap()
+        .unwrap();
+    assert_eq!(page.entries.len(), 5);
+    assert_eq!(page.next_cursor, Some(key_hex(9)));
+    let page = marf_kv
+        .get_map_entries(&block_id(3), &contract, "map", Some(&key_hex(9)), 5)
+        .unwrap()
+        .unwrap();
+    assert!(page.entries.is_empty());
+    assert_eq!(page.next_cursor, None);
+
+    // pruning the fork forgets its keys
+    let mut store = marf_kv.begin(&block_id(3), &block_id(4));
+    store.put_all_data(vec![]).unwrap();
+    store.commit_to(&block_id(4)).unwrap();
+    let pruned = marf_kv.prune_unreachable_tries(0).unwrap();
+    assert_eq!(pruned, vec![block_id(0xf0)]);
+    let mut store = marf_kv.begin_read_only(Some(&block_id(4)));
+    let keys =
+        MapIndex::get_keys_after(store.get_side_store(), &contract, "map", None, 100).unwrap();
+    assert_eq!(keys, (0..10).map(key_hex).collect::<Vec<_>>());
+}
@@ -22,4 +22,9 @@ pub mod epoch_switch;
 pub mod events;
 pub mod forking;
 pub mod large_contract;
+pub mod map_index;
+pub mod profiler;
+pub mod prune;
+pub mod remote_fork;
 pub mod simple_tests;
+pub mod snapshot;
@@ -0,0 +1,157 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::ast::ASTRules;
+use clarity::vm::clarity::TransactionConnection;
+use clarity::vm::profiler::{CostDimension, ExecutionProfiler};
+use clarity::vm::test_util::TEST_HEADER_DB;
+use clarity::vm::tests::{test_only_mainnet_to_chain_id, UnitTestBurnStateDB};
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use clarity::vm::ClarityVersion;
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::StacksEpochId;
+
+use crate::chainstate::stacks::index::ClarityMarfTrieId;
+use crate::clarity_vm::clarity::ClarityInstance;
+use crate::clarity_vm::database::marf::MarfedKV;
+
+const CONTRACT: &str = "(define-map balances principal uint)
+    (define-private (add (a uint) (b uint)) (+ a b))
+    (define-public (deposit (amount uint))
+      (ok (map-set balances tx-sender
+            (add amount (default-to u0 (map-get? balances tx-sender))))))";
+
+#[test]
+fn test_profile_block_transactions() {
+    let epoch = StacksEpochId::Epoch21;
+    let contract_id = QualifiedContractIdentifier::local("bank").unwrap();
+    let sender = PrincipalData::parse("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR").unwrap();
+    let burn_state_db = UnitTestBurnStateDB {
+        epoch_id: epoch,
+        ast_rules: ASTRules::PrecheckSize,
+    };
+
+    let marf = MarfedKV::temporary();
+    let mut clarity_instance =
+        ClarityInstance::new(false, test_only_mainnet_to_chain_id(false), marf);
+    clarity_instance
+        .begin_test_genesis_block(
+            &StacksBlockId::sentinel(),
+            &StacksBlockId([0; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db,
+        )
+        .commit_block();
+    {
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([0; 32]),
+            &StacksBlockId([1; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db,
+        );
+        conn.initialize_epoch_2_05().unwrap();
+        conn.initialize_epoch_2_1().unwrap();
+        conn.as_transaction(|conn| {
+            let (ast, analysis) = conn
+                .analyze_smart_contract(
+                    &contract_id,
+                    ClarityVersion::Clarity2,
+                    CONTRACT,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            conn.initialize_smart_contract(
+                &contract_id,
+                ClarityVersion::Clarity2,
+                &ast,
+                CONTRACT,
+                None,
+                |_, _| false,
+            )
+            .unwrap();
+            conn.save_analysis(&contract_id, &analysis).unwrap();
+        });
+        conn.commit_block();
+    }
+
+    clarity_instance.set_profiler(ExecutionProfiler::new());
+    let block_cost = {
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([1; 32]),
+            &StacksBlockId([2; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db,
+        );
+        for amount in [10, 20] {
+            conn.as_transaction(|conn| {
+                conn.run_contract_call(
+                    &sender,
+                    None,
+                    &contract_id,
+                    "deposit",
+                    &[Value::UInt(amount)],
+                    |_, _| false,
+                )
+                .unwrap();
+            });
+        }
+        let block_cost = conn.cost_so_far();
+        conn.commit_block();
+        block_cost
+    };
+    let profiler = clarity_instance.take_profiler().unwrap();
+
+    let functions = profiler.functions();
+    assert_eq!(functions.len(), 2);
+    let (deposit, add) = (functions[0], functions[1]);
+    assert_eq!((deposit.function.as_str(), deposit.calls), ("deposit", 2));
+    assert_eq!((add.function.as_str(), add.calls), ("add", 2));
+    assert_eq!(add.self_cost, add.total_cost);
+    // the map write is charged to `deposit` itself
+    assert_eq!(deposit.self_cost.write_count, 2);
+    assert_eq!(
+        deposit.total_cost.runtime,
+        deposit.self_cost.runtime + add.total_cost.runtime
+    );
+    // costs charged outside of expression evaluation, like loading the contract, are not attributed
+    assert!(profiler.total().runtime > 0);
+    assert!(!profiler.total().exceeds(&block_cost));
+    assert_eq!(profiler.total(), &deposit.total_cost);
+
+    let mut folded = vec![];
+    profiler
+        .write_folded(&mut folded, CostDimension::Runtime)
+        .unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    assert_eq!(
+        folded,
+        format!(
+            "{contract_id}::deposit {}\n{contract_id}::deposit;{contract_id}::add {}\n",
+            deposit.self_cost.runtime, add.total_cost.runtime
+        )
+    );
+
+    // expressions are only located if the contract was parsed with spans
+    let spans = profiler.spans();
+    assert!(spans.iter().all(|span| span.line > 0));
+    assert_eq!(
+        profiler.to_json().get("spans").is_some(),
+        !spans.is_empty()
+    );
+
+    // profiling stops once the profiler is taken back
+    assert!(clarity_instance.take_profiler().is_none());
+}
@@ -0,0 +1,270 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use clarity::vm::database::ClarityBackingStore;
+use clarity::vm::types::QualifiedContractIdentifier;
+use rusqlite::{OpenFlags, NO_PARAMS};
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::chainstate::stacks::index::file::TrieFile;
+use crate::chainstate::stacks::index::ClarityMarfTrieId;
+use crate::clarity_vm::database::marf::MarfedKV;
+use crate::util_lib::db::sqlite_open;
+
+fn prune_test_dir(name: &str) -> String {
+    let path = format!("/tmp/stacks-node-tests/marf-prune-{}", name);
+    if fs::metadata(&path).is_ok() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    path
+}
+
+fn block_id(i: u8) -> StacksBlockId {
+    StacksBlockId([i; 32])
+}
+
+/// Build a MARF with a chain of blocks 1..=6, a fork off of block 2, and a fork off of block 5
+fn make_test_marf(path: &str) {
+    let mut marf_kv = MarfedKV::open(path, None, None).unwrap();
+    let contract_id = QualifiedContractIdentifier::local("foo").unwrap();
+
+    let mut parent = StacksBlockId::sentinel();
+    for i in 1..=6 {
+        let mut store = marf_kv.begin(&parent, &block_id(i));
+        store
+            .put_all_data(vec![(format!("key-{}", i), format!("value-{}", i))])
+            .unwrap();
+        store.commit_to(&block_id(i)).unwrap();
+        parent = block_id(i);
+
+        if i == 2 {
+            let mut store = marf_kv.begin(&block_id(2), &block_id(0xf0));
+            store
+                .put_all_data(vec![("fork".to_string(), "old-fork".to_string())])
+                .unwrap();
+            store
+                .insert_metadata(&contract_id, "fork-meta", "metadata")
+                .unwrap();
+            store.commit_to(&block_id(0xf0)).unwrap();
+        }
+    }
+
+    let mut store = marf_kv.begin(&block_id(5), &block_id(0xf1));
+    store
+        .put_all_data(vec![("fork".to_string(), "new-fork".to_string())])
+        .unwrap();
+    store.commit_to(&block_id(0xf1)).unwrap();
+}
+
+#[test]
+fn test_marf_prune_and_compact() {
+    let path = prune_test_dir("prune-and-compact");
+    make_test_marf(&path);
+    let marf_path = format!("{}/marf.sqlite", &path);
+    let blobs_path = format!("{}/marf.sqlite.blobs", &path);
+
+    let root_hashes: Vec<_> = {
+        let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+        [1, 2, 3, 4, 5, 6, 0xf1]
+            .into_iter()
+            .map(|i| {
+                (
+                    block_id(i),
+                    marf_kv.get_marf().get_root_hash_at(&block_id(i)).unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    // only the fork off of block 2 is out of reach of blocks 4..=6 and 0xf1
+    let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+    let pruned = marf_kv.prune_unreachable_tries(2).unwrap();
+    assert_eq!(pruned, vec![block_id(0xf0)]);
+    assert!(marf_kv
+        .get_marf()
+        .get_root_hash_at(&block_id(0xf0))
+        .is_err());
+
+    // pruning again does nothing
+    assert!(marf_kv.prune_unreachable_tries(2).unwrap().is_empty());
+    drop(marf_kv);
+
+    let blobs_len = fs::metadata(&blobs_path).unwrap().len();
+    let mut db = sqlite_open(&marf_path, OpenFlags::SQLITE_OPEN_READ_WRITE, true).unwrap();
+    let reclaimed = TrieFile::compact_blobs(&mut db, &marf_path).unwrap();
+    assert!(reclaimed > 0);
+    assert_eq!(
+        fs::metadata(&blobs_path).unwrap().len(),
+        blobs_len - reclaimed
+    );
+
+    // already compact
+    assert_eq!(TrieFile::compact_blobs(&mut db, &marf_path).unwrap(), 0);
+    drop(db);
+
+    let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+    for (block_id, root_hash) in root_hashes.iter() {
+        assert_eq!(
+            &marf_kv.get_marf().get_root_hash_at(block_id).unwrap(),
+            root_hash
+        );
+    }
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(6)));
+    assert_eq!(
+        store.get_data("key-1").unwrap(),
+        Some("value-1".to_string())
+    );
+    assert_eq!(
+        store.get_data("key-6").unwrap(),
+        Some("value-6".to_string())
+    );
+    assert_eq!(store.get_data("fork").unwrap(), None);
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(0xf1)));
+    assert_eq!(
+        store.get_data("key-5").unwrap(),
+        Some("value-5".to_string())
+    );
+    assert_eq!(
+        store.get_data("fork").unwrap(),
+        Some("new-fork".to_string())
+    );
+
+    // the pruned fork's metadata is gone, too
+    let contract_id = QualifiedContractIdentifier::local("foo").unwrap();
+    assert_eq!(
+        marf_kv
+            .sql_conn()
+            .query_row(
+                "SELECT COUNT(*) FROM metadata_table WHERE key LIKE ?1",
+                [format!("%{}%", &contract_id)],
+                |row| row.get::<_, i64>(0)
+            )
+            .unwrap(),
+        0
+    );
+
+    // new blocks can still be appended after compaction
+    let mut store = marf_kv.begin(&block_id(6), &block_id(7));
+    store
+        .put_all_data(vec![("key-7".to_string(), "value-7".to_string())])
+        .unwrap();
+    store.commit_to(&block_id(7)).unwrap();
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(7)));
+    assert_eq!(
+        store.get_data("key-2").unwrap(),
+        Some("value-2".to_string())
+    );
+    assert_eq!(
+        store.get_data("key-7").unwrap(),
+        Some("value-7".to_string())
+    );
+}
+
+#[test]
+fn test_marf_discard_interrupted_compaction() {
+    let path = prune_test_dir("discard-interrupted");
+    make_test_marf(&path);
+    let blobs_path = format!("{}/marf.sqlite.blobs", &path);
+    let compact_path = format!("{}/marf.sqlite.blobs.compact", &path);
+
+    {
+        let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+        marf_kv.prune_unreachable_tries(2).unwrap();
+    }
+    let blobs = fs::read(&blobs_path).unwrap();
+
+    // a compacted copy was written, but the trie offsets were never updated
+    fs::write(&compact_path, &blobs[..blobs.len() / 2]).unwrap();
+
+    let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+    assert!(fs::metadata(&compact_path).is_err());
+    assert_eq!(fs::read(&blobs_path).unwrap(), blobs);
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(6)));
+    assert_eq!(
+        store.get_data("key-3").unwrap(),
+        Some("value-3".to_string())
+    );
+}
+
+#[test]
+fn test_marf_prune_only_walks_new_tries() {
+    let path = prune_test_dir("incremental");
+    make_test_marf(&path);
+
+    let mut marf_kv = MarfedKV::open(&path, None, None).unwrap();
+    assert_eq!(
+        marf_kv.prune_unreachable_tries(2).unwrap(),
+        vec![block_id(0xf0)]
+    );
+
+    // the fork off of block 5 falls out of reach once the chain grows past it
+    let mut parent = block_id(6);
+    for i in 7..=9 {
+        let mut store = marf_kv.begin(&parent, &block_id(i));
+        store
+            .put_all_data(vec![(format!("key-{}", i), format!("value-{}", i))])
+            .unwrap();
+        store.commit_to(&block_id(i)).unwrap();
+        parent = block_id(i);
+    }
+    assert_eq!(
+        marf_kv.prune_unreachable_tries(2).unwrap(),
+        vec![block_id(0xf1)]
+    );
+
+    // every surviving trie was indexed once, and the dropped ones are gone from the index
+    let count_rows = |sql: &str| {
+        marf_kv
+            .sql_conn()
+            .query_row(sql, NO_PARAMS, |row| row.get::<_, i64>(0))
+            .unwrap()
+    };
+    assert_eq!(count_rows("SELECT COUNT(*) FROM trie_ancestry"), 9);
+    assert_eq!(
+        count_rows("SELECT height FROM trie_ancestry ORDER BY height DESC LIMIT 1"),
+        8
+    );
+    assert_eq!(count_rows("SELECT height FROM trie_prune_floor"), 6);
+
+    // a fork deep below the floor is still found
+    let mut store = marf_kv.begin(&block_id(1), &block_id(0xf2));
+    store
+        .put_all_data(vec![("fork".to_string(), "late-fork".to_string())])
+        .unwrap();
+    store.commit_to(&block_id(0xf2)).unwrap();
+    let mut store = marf_kv.begin(&block_id(9), &block_id(10));
+    store
+        .put_all_data(vec![("key-10".to_string(), "value-10".to_string())])
+        .unwrap();
+    store.commit_to(&block_id(10)).unwrap();
+    assert_eq!(
+        marf_kv.prune_unreachable_tries(2).unwrap(),
+        vec![block_id(0xf2)]
+    );
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(10)));
+    assert_eq!(
+        store.get_data("key-1").unwrap(),
+        Some("value-1".to_string())
+    );
+    assert_eq!(store.get_data("fork").unwrap(), None);
+}
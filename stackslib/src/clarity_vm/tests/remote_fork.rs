@@ -0,0 +1,265 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use clarity::vm::ast::ASTRules;
+use clarity::vm::contexts::OwnedEnvironment;
+use clarity::vm::database::{
+    ClarityBackingStore, ClarityDatabase, MemoryBackingStore, StoreType, NULL_BURN_STATE_DB,
+    NULL_HEADER_DB,
+};
+use clarity::vm::errors::InterpreterResult;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use clarity::vm::Value;
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::StacksEpochId;
+
+use crate::clarity_vm::database::remote::{ForkSource, RemoteForkStore};
+
+const COUNTER_CONTRACT: &str = "
+    (define-data-var count uint u10)
+    (define-data-var untouched uint u7)
+    (define-public (increment)
+      (begin
+        (var-set count (+ (var-get count) u1))
+        (ok (var-get count))))
+    (define-read-only (get-count) (var-get count))";
+
+const CALLER_CONTRACT: &str = "
+    (define-public (bump-twice)
+      (begin
+        (try! (contract-call? .counter increment))
+        (contract-call? .counter increment)))";
+
+/// The only block of a `MemoryBackingStore`
+fn source_tip() -> StacksBlockId {
+    StacksBlockId([255; 32])
+}
+
+fn block_id(i: u8) -> StacksBlockId {
+    StacksBlockId([i; 32])
+}
+
+/// How often a `MemorySource` was read
+#[derive(Clone, Default)]
+struct SourceReads {
+    data: Rc<Cell<usize>>,
+    metadata: Rc<Cell<usize>>,
+}
+
+/// A `ForkSource` over a `MemoryBackingStore`, which counts how often it is read
+struct MemorySource {
+    store: MemoryBackingStore,
+    reads: SourceReads,
+}
+
+impl ForkSource for MemorySource {
+    fn get_data(&mut self, tip: &StacksBlockId, key: &str) -> InterpreterResult<Option<String>> {
+        assert_eq!(tip, &source_tip());
+        self.reads.data.set(self.reads.data.get() + 1);
+        self.store.get_data(key)
+    }
+
+    fn get_metadata(
+        &mut self,
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        at_height: Option<u32>,
+    ) -> InterpreterResult<Option<String>> {
+        assert_eq!(tip, &source_tip());
+        self.reads.metadata.set(self.reads.metadata.get() + 1);
+        match at_height {
+            Some(height) => self.store.get_metadata_manual(height, contract, key),
+            None => self.store.get_metadata(contract, key),
+        }
+    }
+
+    fn get_block_at_height(
+        &mut self,
+        _tip: &StacksBlockId,
+        height: u32,
+    ) -> InterpreterResult<Option<StacksBlockId>> {
+        Ok(self.store.get_block_at_height(height))
+    }
+
+    fn get_block_height(&mut self, block: &StacksBlockId) -> InterpreterResult<Option<u32>> {
+        Ok((block == &source_tip()).then_some(0))
+    }
+}
+
+/// Fork a chain which has the counter contract deployed
+fn make_fork() -> (RemoteForkStore, SourceReads) {
+    let mut store = MemoryBackingStore::new();
+    {
+        let mut owned_env = OwnedEnvironment::new(store.as_clarity_db(), StacksEpochId::latest());
+        owned_env
+            .initialize_contract(
+                QualifiedContractIdentifier::local("counter").unwrap(),
+                COUNTER_CONTRACT,
+                None,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+    }
+
+    let reads = SourceReads::default();
+    let source = MemorySource {
+        store,
+        reads: reads.clone(),
+    };
+    let fork = RemoteForkStore::open(":memory:", Box::new(source), source_tip(), 0).unwrap();
+    (fork, reads)
+}
+
+fn sender() -> PrincipalData {
+    PrincipalData::parse("S1G2081040G2081040G2081040G208105NK8PE5").unwrap()
+}
+
+fn call(fork: &mut RemoteForkStore, contract: &str, function: &str) -> Value {
+    let mut owned_env = OwnedEnvironment::new(
+        fork.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB),
+        StacksEpochId::latest(),
+    );
+    owned_env
+        .execute_transaction(
+            sender(),
+            None,
+            QualifiedContractIdentifier::local(contract).unwrap(),
+            function,
+            &[],
+        )
+        .unwrap()
+        .0
+}
+
+fn eval(fork: &mut RemoteForkStore, program: &str) -> Value {
+    let mut owned_env = OwnedEnvironment::new(
+        fork.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB),
+        StacksEpochId::latest(),
+    );
+    owned_env
+        .eval_read_only(
+            &QualifiedContractIdentifier::local("counter").unwrap(),
+            program,
+        )
+        .unwrap()
+        .0
+}
+
+#[test]
+fn test_fork_writes_go_to_overlay() {
+    let (mut fork, _) = make_fork();
+
+    fork.begin(&source_tip(), &block_id(1)).unwrap();
+    assert_eq!(fork.get_open_chain_tip_height(), 1);
+    assert_eq!(
+        call(&mut fork, "counter", "increment"),
+        Value::okay(Value::UInt(11)).unwrap()
+    );
+    fork.commit().unwrap();
+
+    // deploy a contract in the fork which calls the forked contract
+    fork.begin(&block_id(1), &block_id(2)).unwrap();
+    {
+        let mut owned_env = OwnedEnvironment::new(
+            fork.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB),
+            StacksEpochId::latest(),
+        );
+        owned_env
+            .initialize_contract(
+                QualifiedContractIdentifier::local("caller").unwrap(),
+                CALLER_CONTRACT,
+                None,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+    }
+    fork.commit().unwrap();
+
+    fork.begin(&block_id(2), &block_id(3)).unwrap();
+    assert_eq!(
+        call(&mut fork, "caller", "bump-twice"),
+        Value::okay(Value::UInt(13)).unwrap()
+    );
+    assert_eq!(eval(&mut fork, "(get-count)"), Value::UInt(13));
+    // the forked chain still has the old value
+    assert_eq!(
+        eval(
+            &mut fork,
+            "(at-block 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff (get-count))"
+        ),
+        Value::UInt(10)
+    );
+    fork.commit().unwrap();
+
+    // local blocks are numbered after the fork point
+    assert_eq!(fork.get_block_at_height(0), Some(source_tip()));
+    assert_eq!(fork.get_block_at_height(2), Some(block_id(2)));
+    assert_eq!(fork.get_block_at_height(3), Some(block_id(3)));
+    assert_eq!(fork.get_block_at_height(4), None);
+
+    // blocks must be built on the local chain tip
+    assert!(fork.begin(&block_id(2), &block_id(4)).is_err());
+    // local blocks other than the open one cannot be read
+    fork.begin(&block_id(3), &block_id(4)).unwrap();
+    assert!(fork.set_block_hash(block_id(2)).is_err());
+    assert!(fork.set_block_hash(block_id(9)).is_err());
+    fork.rollback_block().unwrap();
+}
+
+#[test]
+fn test_fork_rollback_and_cache() {
+    let (mut fork, reads) = make_fork();
+    let counter = QualifiedContractIdentifier::local("counter").unwrap();
+    let untouched_key =
+        ClarityDatabase::make_key_for_trip(&counter, StoreType::Variable, "untouched");
+
+    // reads from the forked chain are cached
+    let value = fork.get_data(&untouched_key).unwrap();
+    assert_eq!(value, Some(Value::UInt(7).serialize_to_hex().unwrap()));
+    let reads_before = reads.data.get();
+    assert_eq!(fork.get_data(&untouched_key).unwrap(), value);
+    assert_eq!(reads.data.get(), reads_before);
+    assert_eq!(fork.get_data("no-such-key").unwrap(), None);
+
+    // a rolled-back block leaves no trace
+    fork.begin(&source_tip(), &block_id(1)).unwrap();
+    assert_eq!(
+        call(&mut fork, "counter", "increment"),
+        Value::okay(Value::UInt(11)).unwrap()
+    );
+    fork.rollback_block().unwrap();
+    assert!(fork.rollback_block().is_err());
+
+    fork.begin(&source_tip(), &block_id(1)).unwrap();
+    assert_eq!(eval(&mut fork, "(get-count)"), Value::UInt(10));
+    fork.rollback_block().unwrap();
+
+    // contract metadata is cached too
+    fork.begin(&source_tip(), &block_id(1)).unwrap();
+    let reads_before = reads.metadata.get();
+    assert_eq!(eval(&mut fork, "(get-count)"), Value::UInt(10));
+    assert_eq!(reads.metadata.get(), reads_before);
+    fork.rollback_block().unwrap();
+
+    // writes need an open block
+    assert!(fork
+        .put_all_data(vec![(untouched_key, "00".to_string())])
+        .is_err());
+}
@@ -0,0 +1,427 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fs;
+
+use clarity::vm::analysis::run_analysis;
+use clarity::vm::ast::{build_ast_with_rules, ASTRules};
+use clarity::vm::contexts::OwnedEnvironment;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::database::{ClarityBackingStore, NULL_BURN_STATE_DB, NULL_HEADER_DB};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ClarityVersion;
+use rusqlite::NO_PARAMS;
+use stacks_common::consts::CHAIN_ID_TESTNET;
+use stacks_common::types::chainstate::{StacksBlockId, TrieHash};
+use stacks_common::types::StacksEpochId;
+
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue};
+use crate::clarity_vm::database::marf::{MarfedKV, WritableMarfStore};
+use crate::clarity_vm::database::snapshot::{
+    export_marf_snapshot, import_marf_snapshot, Error as SnapshotError, MarfSnapshotSummary,
+};
+
+const BASE_CONTRACT: &str = "(define-data-var count uint u0)
+(define-read-only (get-count) (var-get count))
+(define-public (incr) (ok (var-set count (+ (var-get count) u1))))";
+
+const USER_CONTRACT: &str = "(define-constant deployed-at block-height)
+(define-map seen principal uint)
+(define-fungible-token points)
+(define-public (call) (contract-call? .base incr))";
+
+fn snapshot_test_dir(name: &str) -> String {
+    let path = format!("/tmp/stacks-node-tests/marf-snapshot-{}", name);
+    if fs::metadata(&path).is_ok() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    path
+}
+
+fn block_id(i: u8) -> StacksBlockId {
+    StacksBlockId([i; 32])
+}
+
+/// Deploy a contract the way a block does: analyze it, initialize it, and then save its analysis.
+fn deploy(store: &mut WritableMarfStore, name: &str, source: &str) {
+    let contract_id = QualifiedContractIdentifier::local(name).unwrap();
+    let epoch = StacksEpochId::Epoch20;
+    let version = ClarityVersion::Clarity1;
+    let contract_ast = build_ast_with_rules(
+        &contract_id,
+        source,
+        &mut (),
+        version,
+        epoch,
+        ASTRules::PrecheckSize,
+    )
+    .unwrap();
+    let contract_analysis = run_analysis(
+        &contract_id,
+        &contract_ast.expressions,
+        &mut store.as_analysis_db(),
+        false,
+        LimitedCostTracker::new_free(),
+        epoch,
+        version,
+        false,
+    )
+    .unwrap();
+    OwnedEnvironment::new_free(
+        false,
+        CHAIN_ID_TESTNET,
+        store.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB),
+        epoch,
+    )
+    .initialize_contract_from_ast(contract_id.clone(), version, &contract_ast, source, None)
+    .unwrap();
+    let mut analysis_db = store.as_analysis_db();
+    analysis_db.begin();
+    analysis_db
+        .insert_contract(&contract_id, &contract_analysis)
+        .unwrap();
+    analysis_db.commit().unwrap();
+}
+
+/// Build a MARF with a chain of blocks 1..=4, plus a fork off of block 2.  Block 3 deploys two
+/// contracts, the second of which depends on the first.  Returns the root hash of every block.
+fn make_test_marf(path: &str) -> HashMap<StacksBlockId, TrieHash> {
+    let mut marf_kv = MarfedKV::open(path, None, None).unwrap();
+
+    let mut parent = StacksBlockId::sentinel();
+    for i in 1..=4 {
+        let mut store = marf_kv.begin(&parent, &block_id(i));
+        store
+            .put_all_data(vec![
+                (format!("key-{}", i), format!("value-{}", i)),
+                ("shared".to_string(), format!("shared-{}", i)),
+            ])
+            .unwrap();
+        if i == 3 {
+            deploy(&mut store, "base", BASE_CONTRACT);
+            deploy(&mut store, "user", USER_CONTRACT);
+        }
+        store.commit_to(&block_id(i)).unwrap();
+        parent = block_id(i);
+    }
+
+    let mut store = marf_kv.begin(&block_id(2), &block_id(0xf0));
+    store
+        .put_all_data(vec![("fork".to_string(), "fork-value".to_string())])
+        .unwrap();
+    store.commit_to(&block_id(0xf0)).unwrap();
+
+    let mut root_hashes = HashMap::new();
+    for id in [1, 2, 3, 4, 0xf0] {
+        let root_hash = marf_kv.get_marf().get_root_hash_at(&block_id(id)).unwrap();
+        root_hashes.insert(block_id(id), root_hash);
+    }
+    root_hashes
+}
+
+fn import(
+    snapshot: &[u8],
+    path: &str,
+    root_hashes: &HashMap<StacksBlockId, TrieHash>,
+) -> Result<MarfSnapshotSummary, SnapshotError> {
+    fs::create_dir_all(path).unwrap();
+    import_marf_snapshot(
+        &format!("{}/marf.sqlite", path),
+        &mut &snapshot[..],
+        false,
+        CHAIN_ID_TESTNET,
+        &NULL_HEADER_DB,
+        &NULL_BURN_STATE_DB,
+        |block_id| Ok(root_hashes.get(block_id).cloned()),
+    )
+}
+
+fn export(path: &str) -> Vec<u8> {
+    let mut snapshot = vec![];
+    export_marf_snapshot(
+        &format!("{}/marf.sqlite", path),
+        &block_id(4),
+        &mut snapshot,
+    )
+    .unwrap();
+    snapshot
+}
+
+#[test]
+fn test_marf_snapshot_round_trip() {
+    let src_path = snapshot_test_dir("round-trip-src");
+    let dest_path = snapshot_test_dir("round-trip-dest");
+    let root_hashes = make_test_marf(&src_path);
+
+    let mut snapshot = vec![];
+    let exported = export_marf_snapshot(
+        &format!("{}/marf.sqlite", &src_path),
+        &block_id(4),
+        &mut snapshot,
+    )
+    .unwrap();
+    assert_eq!(exported.header.tip, block_id(4));
+    assert_eq!(exported.header.tip_root_hash, root_hashes[&block_id(4)]);
+    assert_eq!(exported.header.num_tries, 4);
+    assert!(exported.num_metadata > 0);
+
+    let imported = import(&snapshot, &dest_path, &root_hashes).unwrap();
+    assert_eq!(imported, exported);
+
+    let mut marf_kv = MarfedKV::open(&dest_path, None, None).unwrap();
+    assert_eq!(
+        marf_kv.get_marf().get_root_hash_at(&block_id(4)).unwrap(),
+        root_hashes[&block_id(4)]
+    );
+    let mut store = marf_kv.begin_read_only(Some(&block_id(4)));
+    assert_eq!(
+        store.get_data("key-1").unwrap(),
+        Some("value-1".to_string())
+    );
+    assert_eq!(
+        store.get_data("shared").unwrap(),
+        Some("shared-4".to_string())
+    );
+    assert_eq!(store.get_data("fork").unwrap(), None);
+
+    // the contracts' metadata was verified and imported
+    let user_id = QualifiedContractIdentifier::local("user").unwrap();
+    let mut clarity_db = store.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
+    clarity_db.begin();
+    assert_eq!(
+        clarity_db.get_contract_src(&user_id),
+        Some(USER_CONTRACT.to_string())
+    );
+    assert!(clarity_db
+        .load_contract_analysis(&user_id)
+        .unwrap()
+        .is_some());
+    let contract = clarity_db.get_contract(&user_id).unwrap();
+    assert_eq!(
+        contract.contract_context.variables["deployed-at"],
+        clarity::vm::Value::UInt(2)
+    );
+    clarity_db.roll_back().unwrap();
+
+    let mut store = marf_kv.begin_read_only(Some(&block_id(2)));
+    assert_eq!(
+        store.get_data("shared").unwrap(),
+        Some("shared-2".to_string())
+    );
+    assert!(marf_kv
+        .begin_read_only_checked(Some(&block_id(0xf0)))
+        .is_err());
+
+    // can't import over an existing MARF
+    assert!(matches!(
+        import(&snapshot, &dest_path, &root_hashes),
+        Err(SnapshotError::AlreadyExists(_))
+    ));
+}
+
+#[test]
+fn test_marf_snapshot_rejects_tampering() {
+    let src_path = snapshot_test_dir("tamper-src");
+    let root_hashes = make_test_marf(&src_path);
+
+    let mut snapshot = vec![];
+    export_marf_snapshot(
+        &format!("{}/marf.sqlite", &src_path),
+        &block_id(4),
+        &mut snapshot,
+    )
+    .unwrap();
+
+    // a header that disagrees with the snapshot
+    let mut bad_roots = root_hashes.clone();
+    bad_roots.insert(block_id(3), TrieHash([0x11; 32]));
+    let dest_path = snapshot_test_dir("tamper-roots");
+    match import(&snapshot, &dest_path, &bad_roots) {
+        Err(SnapshotError::RootHashMismatch(id, expected, computed)) => {
+            assert_eq!(id, block_id(3));
+            assert_eq!(expected, TrieHash([0x11; 32]));
+            assert_eq!(computed, root_hashes[&block_id(3)]);
+        }
+        x => panic!("Expected root hash mismatch, got {:?}", x),
+    }
+
+    // no header at all
+    let mut missing_roots = root_hashes.clone();
+    missing_roots.remove(&block_id(1));
+    let dest_path = snapshot_test_dir("tamper-missing");
+    assert!(matches!(
+        import(&snapshot, &dest_path, &missing_roots),
+        Err(SnapshotError::NoSuchHeader(_))
+    ));
+
+    // a data value that doesn't match its hash
+    let pos = snapshot
+        .windows(b"value-3".len())
+        .position(|window| window == b"value-3")
+        .unwrap();
+    let mut bad_snapshot = snapshot.clone();
+    bad_snapshot[pos + 6] = b'9';
+    let dest_path = snapshot_test_dir("tamper-value");
+    assert!(matches!(
+        import(&bad_snapshot, &dest_path, &root_hashes),
+        Err(SnapshotError::BadValue(_))
+    ));
+
+    // a corrupted trie.  The tip's trie record is its tag, block ID and block hash, followed by
+    // the blob's length and then the blob.
+    let pos = snapshot
+        .windows(37)
+        .position(|window| window[0] == 0x01 && &window[5..] == block_id(4).as_bytes())
+        .unwrap()
+        + 5;
+    let blob_start = pos + 32 + 8;
+    let blob_len = u64::from_be_bytes(snapshot[pos + 32..blob_start].try_into().unwrap());
+    let mut bad_snapshot = snapshot.clone();
+    bad_snapshot[blob_start + (blob_len as usize) - 1] ^= 0x01;
+    let dest_path = snapshot_test_dir("tamper-trie");
+    assert!(matches!(
+        import(&bad_snapshot, &dest_path, &root_hashes),
+        Err(SnapshotError::MARFError(_)) | Err(SnapshotError::RootHashMismatch(..))
+    ));
+
+    // a truncated snapshot
+    let dest_path = snapshot_test_dir("tamper-truncated");
+    assert!(matches!(
+        import(&snapshot[..snapshot.len() - 1], &dest_path, &root_hashes),
+        Err(SnapshotError::IOError(_))
+    ));
+}
+
+#[test]
+fn test_marf_snapshot_rejects_unverified_metadata() {
+    let src_path = snapshot_test_dir("metadata-src");
+    let root_hashes = make_test_marf(&src_path);
+    let snapshot = export(&src_path);
+    let user_id = QualifiedContractIdentifier::local("user").unwrap();
+
+    // each tampering is done to the source MARF, and then undone
+    let tamper = |name: &str, sql: &str, undo: &str| {
+        let marf_kv = MarfedKV::open(&src_path, None, None).unwrap();
+        assert!(marf_kv.sql_conn().execute(sql, NO_PARAMS).unwrap() > 0);
+        let snapshot = export(&src_path);
+        assert!(marf_kv.sql_conn().execute(undo, NO_PARAMS).unwrap() > 0);
+        import(&snapshot, &snapshot_test_dir(name), &root_hashes)
+    };
+
+    // a source that doesn't match the contract hash in the MARF
+    match tamper(
+        "metadata-source",
+        "UPDATE metadata_table SET value = replace(value, 'uint u0', 'uint u9') WHERE key LIKE '%contract-src'",
+        "UPDATE metadata_table SET value = replace(value, 'uint u9', 'uint u0') WHERE key LIKE '%contract-src'",
+    ) {
+        Err(SnapshotError::BadMetadata(contract_id, block, _)) => {
+            assert_eq!(contract_id.name.as_str(), "base");
+            assert_eq!(block, block_id(3));
+        }
+        x => panic!("Expected bad metadata, got {:?}", x),
+    }
+
+    // an analysis that doesn't match the source
+    assert!(matches!(
+        tamper(
+            "metadata-analysis",
+            "UPDATE metadata_table SET value = replace(value, '\"is_cost_contract_eligible\":false', '\"is_cost_contract_eligible\":true') WHERE key LIKE 'clr-meta::%.user::analysis'",
+            "UPDATE metadata_table SET value = replace(value, '\"is_cost_contract_eligible\":true', '\"is_cost_contract_eligible\":false') WHERE key LIKE 'clr-meta::%.user::analysis'",
+        ),
+        Err(SnapshotError::BadMetadata(ref contract_id, _, _)) if contract_id == &user_id
+    ));
+
+    // a constant whose value doesn't match its definition
+    assert!(matches!(
+        tamper(
+            "metadata-contract",
+            "UPDATE metadata_table SET value = replace(value, '\"deployed-at\":{\"UInt\":2}', '\"deployed-at\":{\"UInt\":7}') WHERE key LIKE 'clr-meta::%.user::vm-metadata::9::contract'",
+            "UPDATE metadata_table SET value = replace(value, '\"deployed-at\":{\"UInt\":7}', '\"deployed-at\":{\"UInt\":2}') WHERE key LIKE 'clr-meta::%.user::vm-metadata::9::contract'",
+        ),
+        Err(SnapshotError::BadMetadata(ref contract_id, _, _)) if contract_id == &user_id
+    ));
+
+    // a row that no deployment writes, and rows of a block which deployed nothing
+    for (name, block) in [
+        ("metadata-extra", block_id(3)),
+        ("metadata-block", block_id(4)),
+    ] {
+        let insert = format!(
+            "INSERT INTO metadata_table (blockhash, key, value) VALUES ('{}', 'clr-meta::{}::::state_summary', '{{}}')",
+            &block, &user_id
+        );
+        let delete = format!(
+            "DELETE FROM metadata_table WHERE key = 'clr-meta::{}::::state_summary'",
+            &user_id
+        );
+        match tamper(name, &insert, &delete) {
+            Err(SnapshotError::BadMetadata(contract_id, bad_block, _)) => {
+                assert_eq!(contract_id, user_id);
+                assert_eq!(bad_block, block);
+            }
+            x => panic!("Expected bad metadata, got {:?}", x),
+        }
+    }
+
+    // the untampered snapshot still imports
+    assert!(import(&snapshot, &snapshot_test_dir("metadata-ok"), &root_hashes).is_ok());
+}
+
+#[test]
+fn test_marf_snapshot_requires_every_value() {
+    let src_path = snapshot_test_dir("values-src");
+    let root_hashes = make_test_marf(&src_path);
+    let snapshot = export(&src_path);
+    let value_hash = MARFValue::from_value("value-1").to_hex();
+
+    // a snapshot without one of the leaves' values.  Its data record is its tag, the key's length
+    // and the key, and then the value's length and the value.
+    let pos = snapshot
+        .windows(value_hash.len())
+        .position(|window| window == value_hash.as_bytes())
+        .unwrap();
+    let start = pos - 4 - 1;
+    let end = pos + value_hash.len() + 4 + "value-1".len();
+    assert_eq!(snapshot[start], 0x02);
+    assert_eq!(&snapshot[end - "value-1".len()..end], b"value-1");
+    let mut bad_snapshot = snapshot[..start].to_vec();
+    bad_snapshot.extend_from_slice(&snapshot[end..]);
+    match import(
+        &bad_snapshot,
+        &snapshot_test_dir("values-missing"),
+        &root_hashes,
+    ) {
+        Err(SnapshotError::MissingValue(key)) => assert_eq!(key, value_hash),
+        x => panic!("Expected a missing value, got {:?}", x),
+    }
+
+    // nor will one be exported
+    let marf_kv = MarfedKV::open(&src_path, None, None).unwrap();
+    marf_kv
+        .sql_conn()
+        .execute("DELETE FROM data_table WHERE key = ?1", &[&value_hash])
+        .unwrap();
+    let mut snapshot = vec![];
+    assert!(matches!(
+        export_marf_snapshot(
+            &format!("{}/marf.sqlite", &src_path),
+            &block_id(4),
+            &mut snapshot
+        ),
+        Err(SnapshotError::MissingValue(_))
+    ));
+}
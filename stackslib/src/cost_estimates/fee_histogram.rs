@@ -0,0 +1,477 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{cmp, fs};
+
+use clarity::vm::costs::ExecutionCost;
+use rusqlite::{Connection, Error as SqliteError, OpenFlags, Transaction as SqlTransaction};
+
+use super::fee_medians::{fee_rate_and_weight_from_receipt, FeeRateAndWeight, MINIMUM_TX_FEE_RATE};
+use super::metrics::{CostMetric, PROPORTION_RESOLUTION};
+use super::{EstimatorError, FeeEstimator, FeeRateEstimate, FeeRateTargets};
+use crate::chainstate::stacks::db::StacksEpochReceipt;
+use crate::util_lib::db::{sqlite_open, table_exists, tx_begin_immediate_sqlite};
+
+const CREATE_TABLE: &'static str = "
+CREATE TABLE histogram_fee_estimator (
+    measure_key INTEGER PRIMARY KEY AUTOINCREMENT,
+    fullness NUMBER NOT NULL,
+    -- JSON-encoded list of (bucket, weight) pairs
+    histogram TEXT NOT NULL
+)";
+
+/// Bucket 0 holds the minimum fee rate, and each bucket `i > 0` spans fee rates in
+/// `(1.1^(i-1), 1.1^i]`, so a bucket's fee rate is never more than 10% away from the fee rates
+/// in it.
+const FEE_BUCKET_SPACING: f64 = 1.1f64;
+
+/// Fee rates above `FEE_BUCKET_SPACING^MAX_FEE_BUCKET` (about 10^16) all land in the last
+/// bucket.
+const MAX_FEE_BUCKET: u32 = 400;
+
+/// Maximum number of pending mempool transactions considered in a single estimate.
+const MAX_MEMPOOL_TXS: u32 = 10_000;
+
+/// Percentiles reported as the low, middle, and high estimates.
+const TARGET_PERCENTILES: [f64; 3] = [0.05, 0.5, 0.95];
+
+/// Weights of fee rates, bucketed by fee rate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeeRateHistogram {
+    buckets: BTreeMap<u32, u64>,
+}
+
+impl FeeRateHistogram {
+    pub fn new() -> FeeRateHistogram {
+        FeeRateHistogram::default()
+    }
+
+    /// Find the bucket for this fee rate
+    fn bucket_of(fee_rate: f64) -> u32 {
+        if !(fee_rate > MINIMUM_TX_FEE_RATE) {
+            return 0;
+        }
+        let bucket = ((fee_rate / MINIMUM_TX_FEE_RATE).ln() / FEE_BUCKET_SPACING.ln()).ceil();
+        if bucket < 1f64 {
+            1
+        } else if bucket >= MAX_FEE_BUCKET as f64 {
+            MAX_FEE_BUCKET
+        } else {
+            bucket as u32
+        }
+    }
+
+    /// The fee rate a bucket stands for. This is the top of the bucket's range, so that an
+    /// estimate drawn from the histogram is never lower than the fee rates that produced it.
+    pub fn bucket_fee_rate(bucket: u32) -> f64 {
+        MINIMUM_TX_FEE_RATE * FEE_BUCKET_SPACING.powi(bucket as i32)
+    }
+
+    /// The fee rate that `fee_rate` is reported as, once bucketed.
+    pub fn bucketed_fee_rate(fee_rate: f64) -> f64 {
+        Self::bucket_fee_rate(Self::bucket_of(fee_rate))
+    }
+
+    pub fn add(&mut self, fee_rate: f64, weight: u64) {
+        let bucket = self.buckets.entry(Self::bucket_of(fee_rate)).or_insert(0);
+        *bucket = bucket.saturating_add(weight);
+    }
+
+    pub fn merge(&mut self, other: &FeeRateHistogram) {
+        for (bucket, weight) in other.buckets.iter() {
+            let total = self.buckets.entry(*bucket).or_insert(0);
+            *total = total.saturating_add(*weight);
+        }
+    }
+
+    pub fn total_weight(&self) -> u64 {
+        self.buckets
+            .values()
+            .fold(0u64, |total, weight| total.saturating_add(*weight))
+    }
+
+    /// If the total weight is less than `full_weight`, fill the remainder with the minimum fee
+    /// rate. As with `WeightedMedianFeeRateEstimator`, unused space in a block means a
+    /// transaction could have been included at the minimum fee rate.
+    pub fn pad_to(&mut self, full_weight: u64) {
+        let total_weight = self.total_weight();
+        if total_weight < full_weight {
+            self.add(MINIMUM_TX_FEE_RATE, full_weight - total_weight);
+        }
+    }
+
+    /// Get the fee rate at the given percentile (in `[0, 1]`) of weight, or `None` if the
+    /// histogram is empty.
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        let total_weight = self.total_weight();
+        if total_weight == 0 {
+            return None;
+        }
+        let target_weight = percentile * total_weight as f64;
+        let mut cumulative_weight = 0u64;
+        for (bucket, weight) in self.buckets.iter() {
+            cumulative_weight = cumulative_weight.saturating_add(*weight);
+            if cumulative_weight as f64 >= target_weight {
+                return Some(Self::bucket_fee_rate(*bucket));
+            }
+        }
+        self.buckets
+            .keys()
+            .next_back()
+            .map(|bucket| Self::bucket_fee_rate(*bucket))
+    }
+
+    /// Get the low, middle, and high estimates from this histogram
+    pub fn estimate(&self) -> Option<FeeRateEstimate> {
+        Some(FeeRateEstimate {
+            low: self.percentile(TARGET_PERCENTILES[0])?,
+            middle: self.percentile(TARGET_PERCENTILES[1])?,
+            high: self.percentile(TARGET_PERCENTILES[2])?,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        let pairs: Vec<(u32, u64)> = self.buckets.iter().map(|(b, w)| (*b, *w)).collect();
+        serde_json::to_string(&pairs).expect("FATAL: failed to serialize fee histogram")
+    }
+
+    fn from_json(json: &str) -> Result<FeeRateHistogram, serde_json::Error> {
+        let pairs: Vec<(u32, u64)> = serde_json::from_str(json)?;
+        let mut histogram = FeeRateHistogram::new();
+        for (bucket, weight) in pairs.into_iter() {
+            let total = histogram.buckets.entry(bucket).or_insert(0);
+            *total = total.saturating_add(weight);
+        }
+        Ok(histogram)
+    }
+}
+
+/// FeeRateEstimator which combines three signals:
+///
+/// 1) A fee rate histogram for each of the last `window_size` blocks, weighted and padded to a
+///    full block the same way as `WeightedMedianFeeRateEstimator`. Merged together, these give
+///    the historical low, middle, and high fee rates (the 5th, 50th, and 95th percentiles).
+/// 2) How full those blocks were. If recent blocks had spare room, then a transaction that can
+///    wait a few blocks need not outbid what recent blocks paid.
+/// 3) The fee rates of transactions currently pending in the mempool. These are projected into
+///    the next block(s) in descending fee rate order, and the projected blocks are treated like
+///    a historical block.
+///
+/// The estimate for the next block is the greater of the historical estimate and the projection
+/// of the next block. The estimate for inclusion within `target_blocks` blocks is the greater of
+/// the historical estimate scaled by the blocks' fullness, and the projection of the next
+/// `target_blocks` blocks.
+pub struct HistogramFeeRateEstimator<M: CostMetric> {
+    db: Connection,
+    /// Path to the node's mempool database, if the mempool should be taken into account.
+    mempool_path: Option<PathBuf>,
+    /// Read-only connection to the mempool database. It is opened on first use, since the
+    /// mempool database may not exist yet when the estimator is opened, and is dropped (to be
+    /// reopened) if a query on it fails.
+    mempool_conn: RefCell<Option<Connection>>,
+    /// We only look back `window_size` blocks when computing estimates.
+    window_size: u32,
+    /// The number of blocks used for the `within_target` estimate.
+    target_blocks: u64,
+    /// The weight of a "full block" in abstract scalar cost units. This is the weight of
+    /// a block that is filled *one single* dimension.
+    full_block_weight: u64,
+    /// Use this cost metric in fee rate calculations.
+    metric: M,
+}
+
+impl<M: CostMetric> HistogramFeeRateEstimator<M> {
+    /// Open a fee rate estimator at the given db path. Creates if not existent.
+    /// If `mempool_path` is given, then the pending transactions in that mempool database are
+    /// considered as well.
+    pub fn open(
+        p: &Path,
+        mempool_path: Option<PathBuf>,
+        metric: M,
+        window_size: u32,
+        target_blocks: u64,
+    ) -> Result<Self, SqliteError> {
+        let mut db = sqlite_open(
+            p,
+            rusqlite::OpenFlags::SQLITE_OPEN_CREATE | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE,
+            false,
+        )?;
+
+        // check if the db needs to be instantiated regardless of whether or not
+        //  it was newly created: the db itself may be shared with other fee estimators,
+        //  which would not have created the necessary table for this estimator.
+        let tx = tx_begin_immediate_sqlite(&mut db)?;
+        Self::instantiate_db(&tx)?;
+        tx.commit()?;
+
+        Ok(Self {
+            db,
+            mempool_path,
+            mempool_conn: RefCell::new(None),
+            window_size,
+            target_blocks: cmp::max(target_blocks, 1),
+            full_block_weight: PROPORTION_RESOLUTION,
+            metric,
+        })
+    }
+
+    /// Check if the SQL database was already created. Necessary to avoid races if
+    ///  different threads open an estimator at the same time.
+    fn db_already_instantiated(tx: &SqlTransaction) -> Result<bool, SqliteError> {
+        table_exists(tx, "histogram_fee_estimator")
+    }
+
+    fn instantiate_db(tx: &SqlTransaction) -> Result<(), SqliteError> {
+        if !Self::db_already_instantiated(tx)? {
+            tx.execute(CREATE_TABLE, rusqlite::NO_PARAMS)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load and merge the last `window_size` block histograms, and the average fullness of
+    /// those blocks. Returns `None` if no blocks have been seen.
+    fn get_history(&self) -> Result<Option<(FeeRateHistogram, f64)>, EstimatorError> {
+        let sql = "SELECT fullness, histogram FROM histogram_fee_estimator ORDER BY measure_key DESC LIMIT ?";
+        let mut stmt = self.db.prepare(sql).map_err(EstimatorError::SqliteError)?;
+        let rows = stmt
+            .query_and_then::<_, SqliteError, _, _>(&[self.window_size], |row| {
+                let fullness: f64 = row.get("fullness")?;
+                let histogram: String = row.get("histogram")?;
+                Ok((fullness, histogram))
+            })
+            .map_err(EstimatorError::SqliteError)?;
+
+        let mut merged = FeeRateHistogram::new();
+        let mut total_fullness = 0f64;
+        let mut num_blocks = 0u64;
+        for row in rows {
+            let (fullness, histogram_json) = row.map_err(EstimatorError::SqliteError)?;
+            let histogram = FeeRateHistogram::from_json(&histogram_json).map_err(|e| {
+                EstimatorError::SqliteError(SqliteError::FromSqlConversionFailure(
+                    1,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                ))
+            })?;
+            merged.merge(&histogram);
+            total_fullness += fullness;
+            num_blocks += 1;
+        }
+
+        if num_blocks == 0 {
+            return Ok(None);
+        }
+        Ok(Some((merged, total_fullness / num_blocks as f64)))
+    }
+
+    /// Get the fee rates and weights of the transactions pending in the mempool, in descending
+    /// order of fee rate. Transactions whose origin nonce is known to be spent are skipped.
+    fn get_mempool_fee_rates(&self) -> Vec<FeeRateAndWeight> {
+        let Some(mempool_path) = self.mempool_path.as_ref() else {
+            return vec![];
+        };
+        let mut mempool_conn = self.mempool_conn.borrow_mut();
+        if mempool_conn.is_none() {
+            if fs::metadata(mempool_path).is_err() {
+                return vec![];
+            }
+            match sqlite_open(mempool_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false) {
+                Ok(conn) => {
+                    *mempool_conn = Some(conn);
+                }
+                Err(e) => {
+                    debug!("Failed to open mempool database";
+                           "mempool_path" => %mempool_path.display(),
+                           "error" => %e);
+                    return vec![];
+                }
+            }
+        }
+        let Some(conn) = mempool_conn.as_ref() else {
+            return vec![];
+        };
+        match self.query_mempool_fee_rates(conn) {
+            Ok(fee_rates) => fee_rates,
+            Err(e) => {
+                *mempool_conn = None;
+                debug!("Failed to load mempool fee rates";
+                       "mempool_path" => %mempool_path.display(),
+                       "error" => %e);
+                vec![]
+            }
+        }
+    }
+
+    fn query_mempool_fee_rates(
+        &self,
+        conn: &Connection,
+    ) -> Result<Vec<FeeRateAndWeight>, SqliteError> {
+        let sql = "SELECT m.tx_fee, m.length, m.fee_rate FROM mempool AS m
+                   LEFT JOIN nonces AS n ON n.address = m.origin_address
+                   WHERE n.nonce IS NULL OR m.origin_nonce >= n.nonce
+                   ORDER BY m.fee_rate DESC LIMIT ?";
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_and_then::<_, SqliteError, _, _>(&[MAX_MEMPOOL_TXS], |row| {
+            let tx_fee: i64 = row.get("tx_fee")?;
+            let length: i64 = row.get("length")?;
+            let fee_rate: Option<f64> = row.get("fee_rate")?;
+            Ok((tx_fee, length, fee_rate))
+        })?;
+
+        let mut fee_rates = vec![];
+        for row in rows {
+            let (tx_fee, length, fee_rate) = row?;
+            let tx_fee = u64::try_from(tx_fee).unwrap_or(0) as f64;
+            let length = u64::try_from(length).unwrap_or(0);
+            let (fee_rate, weight) = match fee_rate {
+                Some(fee_rate) if fee_rate > 0f64 && fee_rate.is_finite() => {
+                    // the miner's estimated fee rate is fee / (scalar cost)
+                    (fee_rate, cmp::max((tx_fee / fee_rate) as u64, 1))
+                }
+                _ => {
+                    // no cost estimate, so only the transaction's length is known
+                    let weight = cmp::max(self.metric.from_len(length), 1);
+                    (tx_fee / weight as f64, weight)
+                }
+            };
+            fee_rates.push(FeeRateAndWeight {
+                fee_rate: fee_rate.max(MINIMUM_TX_FEE_RATE),
+                weight,
+            });
+        }
+
+        fee_rates.sort_by(|a, b| {
+            b.fee_rate
+                .partial_cmp(&a.fee_rate)
+                .unwrap_or(cmp::Ordering::Equal)
+        });
+        Ok(fee_rates)
+    }
+
+    /// Project the given pending transactions (in descending fee rate order) into the next
+    /// `num_blocks` blocks, and get the estimate for those blocks. Space not claimed by pending
+    /// transactions is padded with the minimum fee rate. Returns `None` if there are no
+    /// pending transactions.
+    pub fn project_mempool(
+        pending: &[FeeRateAndWeight],
+        full_block_weight: u64,
+        num_blocks: u64,
+    ) -> Option<FeeRateEstimate> {
+        if pending.is_empty() {
+            return None;
+        }
+        let capacity = full_block_weight.saturating_mul(num_blocks);
+        let mut histogram = FeeRateHistogram::new();
+        let mut used = 0u64;
+        for pending_tx in pending.iter() {
+            if used >= capacity {
+                break;
+            }
+            let weight = cmp::min(pending_tx.weight, capacity - used);
+            histogram.add(pending_tx.fee_rate, weight);
+            used += weight;
+        }
+        histogram.pad_to(capacity);
+        histogram.estimate()
+    }
+
+    fn max_estimate(a: FeeRateEstimate, b: FeeRateEstimate) -> FeeRateEstimate {
+        FeeRateEstimate {
+            high: a.high.max(b.high),
+            middle: a.middle.max(b.middle),
+            low: a.low.max(b.low),
+        }
+    }
+
+    fn clamp_estimate(estimate: FeeRateEstimate) -> FeeRateEstimate {
+        FeeRateEstimate {
+            high: estimate.high.max(MINIMUM_TX_FEE_RATE),
+            middle: estimate.middle.max(MINIMUM_TX_FEE_RATE),
+            low: estimate.low.max(MINIMUM_TX_FEE_RATE),
+        }
+    }
+
+    fn insert_histogram(&mut self, fullness: f64, histogram: &FeeRateHistogram) {
+        let tx = tx_begin_immediate_sqlite(&mut self.db).expect("SQLite failure");
+        let insert_sql = "INSERT INTO histogram_fee_estimator
+                          (fullness, histogram) VALUES (?, ?)";
+        let deletion_sql = "DELETE FROM histogram_fee_estimator
+                            WHERE measure_key <= (
+                               SELECT MAX(measure_key) - ?
+                               FROM histogram_fee_estimator )";
+        tx.execute(insert_sql, rusqlite::params![fullness, histogram.to_json()])
+            .expect("SQLite failure");
+        tx.execute(deletion_sql, rusqlite::params![self.window_size])
+            .expect("SQLite failure");
+        tx.commit().expect("SQLite failure");
+    }
+}
+
+impl<M: CostMetric> FeeEstimator for HistogramFeeRateEstimator<M> {
+    fn notify_block(
+        &mut self,
+        receipt: &StacksEpochReceipt,
+        block_limit: &ExecutionCost,
+    ) -> Result<(), EstimatorError> {
+        let mut histogram = FeeRateHistogram::new();
+        for tx_receipt in receipt.tx_receipts.iter() {
+            if let Some(rate_and_weight) =
+                fee_rate_and_weight_from_receipt(&self.metric, tx_receipt, block_limit)
+            {
+                histogram.add(rate_and_weight.fee_rate, rate_and_weight.weight);
+            }
+        }
+
+        let fullness = (histogram.total_weight() as f64 / self.full_block_weight as f64).min(1f64);
+        histogram.pad_to(self.full_block_weight);
+
+        debug!("Updating fee rate histogram for new block";
+               "fullness" => fullness,
+               "buckets" => histogram.buckets.len());
+
+        self.insert_histogram(fullness, &histogram);
+        Ok(())
+    }
+
+    fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError> {
+        Ok(self.get_rate_targets()?.next_block)
+    }
+
+    fn get_rate_targets(&self) -> Result<FeeRateTargets, EstimatorError> {
+        let history = self.get_history()?;
+        let pending = self.get_mempool_fee_rates();
+        let next_block_projection = Self::project_mempool(&pending, self.full_block_weight, 1);
+        let target_projection =
+            Self::project_mempool(&pending, self.full_block_weight, self.target_blocks);
+
+        let (next_block, within_target) = match (history, next_block_projection, target_projection)
+        {
+            (Some((histogram, fullness)), Some(next_block), Some(target)) => {
+                let historical = histogram
+                    .estimate()
+                    .ok_or(EstimatorError::NoEstimateAvailable)?;
+                (
+                    Self::max_estimate(historical.clone(), next_block),
+                    Self::max_estimate(historical * fullness, target),
+                )
+            }
+            (Some((histogram, fullness)), _, _) => {
+                let historical = histogram
+                    .estimate()
+                    .ok_or(EstimatorError::NoEstimateAvailable)?;
+                (historical.clone(), historical * fullness)
+            }
+            (None, Some(next_block), Some(target)) => (next_block, target),
+            _ => {
+                return Err(EstimatorError::NoEstimateAvailable);
+            }
+        };
+
+        Ok(FeeRateTargets {
+            next_block: Self::clamp_estimate(next_block),
+            within_target: Self::clamp_estimate(within_target),
+            target_blocks: self.target_blocks,
+        })
+    }
+}
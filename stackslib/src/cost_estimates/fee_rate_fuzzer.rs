@@ -3,7 +3,7 @@ use rand::distributions::{Distribution, Uniform};
 use rand::rngs::StdRng;
 use rand::{thread_rng, RngCore, SeedableRng};
 
-use super::{EstimatorError, FeeEstimator, FeeRateEstimate};
+use super::{EstimatorError, FeeEstimator, FeeRateEstimate, FeeRateTargets};
 use crate::chainstate::stacks::db::StacksEpochReceipt;
 
 /// The FeeRateFuzzer wraps an underlying FeeEstimator. It passes `notify_block` calls to the
@@ -86,4 +86,14 @@ impl<T: FeeEstimator> FeeEstimator for FeeRateFuzzer<T> {
         let underlying_estimate = self.underlying.get_rate_estimates()?;
         Ok(self.fuzz_estimate(underlying_estimate))
     }
+
+    /// Call underlying estimator and add the same fuzz to each target.
+    fn get_rate_targets(&self) -> Result<FeeRateTargets, EstimatorError> {
+        let underlying_targets = self.underlying.get_rate_targets()?;
+        Ok(FeeRateTargets {
+            next_block: self.fuzz_estimate(underlying_targets.next_block),
+            within_target: self.fuzz_estimate(underlying_targets.within_target),
+            target_blocks: underlying_targets.target_blocks,
+        })
+    }
 }
@@ -0,0 +1,339 @@
+use std::cmp;
+use std::path::Path;
+
+use clarity::vm::costs::ExecutionCost;
+use rusqlite::{
+    Connection, Error as SqliteError, OptionalExtension, Transaction as SqliteTransaction,
+};
+use serde_json::Value as JsonValue;
+
+use super::pessimistic::CostField;
+use super::{CostEstimator, EstimatorError, PessimisticEstimator};
+use crate::chainstate::stacks::TransactionPayload;
+use crate::core::StacksEpochId;
+use crate::util_lib::db::{sqlite_open, table_exists, tx_begin_immediate_sqlite, u64_to_sql};
+
+const CREATE_TABLE: &'static str = "
+CREATE TABLE learning_estimator (
+    estimate_key TEXT PRIMARY KEY,
+    count INTEGER NOT NULL,
+    ewma NUMBER NOT NULL,
+    -- JSON-encoded list of the most recent samples, oldest first
+    samples TEXT NOT NULL
+)";
+
+/// How many of the most recent samples are retained for computing quantiles
+const SAMPLE_WINDOW: usize = 64;
+
+/// Weight of a new sample in the exponentially-weighted moving average
+const EWMA_ALPHA: f64 = 0.2;
+
+/// How many samples are needed before an estimate can be fully trusted
+const MIN_CONFIDENT_SAMPLES: u64 = 10;
+
+/// This struct estimates the `ExecutionCost` of transaction payloads by learning from the
+/// observed costs of each operation, falling back to a `PessimisticEstimator` when it hasn't
+/// learned enough.
+///
+/// Operations are keyed the same way as in the `PessimisticEstimator` (i.e. by contract and
+/// function for contract-calls). If `condition_on_args` is set, contract-calls are also keyed by
+/// the size class of their serialized arguments, since many functions cost more when given
+/// bigger inputs.
+///
+/// For each pair of 1) operation key, and 2) dimension of ExecutionCost, the estimator keeps
+/// an EWMA of the observed costs, and the last `SAMPLE_WINDOW` observed costs. The estimate
+/// for each dimension is the greater of the EWMA and the `quantile`-th quantile of the samples.
+///
+/// Each estimate has a confidence score in `[0, 1]`, which grows with the number of samples and
+/// shrinks as the EWMA and the quantile diverge (i.e. as the cost becomes less predictable).
+/// Estimates with a confidence below `min_confidence` are not used.
+pub struct LearningEstimator {
+    db: Connection,
+    fallback: PessimisticEstimator,
+    /// Quantile of the recent samples to report, in `[0, 1]`
+    quantile: f64,
+    /// Minimum confidence score for a learned estimate to be used
+    min_confidence: f64,
+    /// Whether or not to key contract-calls by the size of their arguments
+    condition_on_args: bool,
+}
+
+/// A learned `ExecutionCost` estimate
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearnedCostEstimate {
+    pub cost: ExecutionCost,
+    /// How much the estimate can be trusted, in `[0, 1]`
+    pub confidence: f64,
+    /// The fewest samples seen for any dimension of the cost
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CostStats {
+    count: u64,
+    ewma: f64,
+    samples: Vec<u64>,
+}
+
+impl CostStats {
+    fn new() -> CostStats {
+        CostStats {
+            count: 0,
+            ewma: 0f64,
+            samples: vec![],
+        }
+    }
+
+    fn update_with(&mut self, sample: u64) {
+        self.ewma = if self.count == 0 {
+            sample as f64
+        } else {
+            EWMA_ALPHA * (sample as f64) + (1f64 - EWMA_ALPHA) * self.ewma
+        };
+        self.count = self.count.saturating_add(1);
+        self.samples.push(sample);
+        if self.samples.len() > SAMPLE_WINDOW {
+            self.samples.remove(0);
+        }
+    }
+
+    /// Nearest-rank quantile of the retained samples
+    fn quantile(&self, quantile: f64) -> u64 {
+        if self.samples.is_empty() {
+            return 0;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (quantile * sorted.len() as f64).ceil() as usize;
+        sorted[cmp::min(cmp::max(rank, 1), sorted.len()) - 1]
+    }
+
+    /// The estimate for this dimension
+    fn estimate(&self, quantile: f64) -> u64 {
+        cmp::max(self.quantile(quantile), self.ewma.ceil() as u64)
+    }
+
+    /// How predictable this dimension is, in `[0, 1]`: the ratio of the smaller to the larger of
+    /// the EWMA and the quantile.
+    fn stability(&self, quantile: f64) -> f64 {
+        let quantile = self.quantile(quantile) as f64;
+        let larger = quantile.max(self.ewma);
+        if larger <= 0f64 {
+            return 1f64;
+        }
+        (quantile.min(self.ewma) / larger).clamp(0f64, 1f64)
+    }
+
+    fn flush_sqlite(&self, tx: &SqliteTransaction, identifier: &str) {
+        let sql = "INSERT OR REPLACE INTO learning_estimator
+                     (estimate_key, count, ewma, samples) VALUES (?, ?, ?, ?)";
+        let count = u64_to_sql(self.count).unwrap_or_else(|_| i64::MAX);
+        tx.execute(
+            sql,
+            rusqlite::params![
+                identifier,
+                count,
+                self.ewma,
+                JsonValue::from(self.samples.as_slice())
+            ],
+        )
+        .expect("SQLite failure");
+    }
+
+    fn get_sqlite(conn: &Connection, identifier: &str) -> Option<CostStats> {
+        let sql = "SELECT count, ewma, samples FROM learning_estimator WHERE estimate_key = ?";
+        conn.query_row(sql, &[identifier], |row| {
+            let count: i64 = row.get(0)?;
+            let ewma: f64 = row.get(1)?;
+            let samples: JsonValue = row.get(2)?;
+            Ok((count, ewma, samples))
+        })
+        .optional()
+        .expect("SQLite failure")
+        .map(|(count, ewma, samples)| CostStats {
+            count: u64::try_from(count).expect("DB corrupt, non-u64-valid count was stored"),
+            ewma,
+            samples: serde_json::from_value(samples)
+                .expect("DB corrupt, non-u64-valid samples were stored"),
+        })
+    }
+}
+
+impl LearningEstimator {
+    /// Open a learning estimator at the given db path, which falls back to the given
+    /// pessimistic estimator. Creates if not existent.
+    pub fn open(
+        p: &Path,
+        fallback: PessimisticEstimator,
+        quantile: f64,
+        min_confidence: f64,
+        condition_on_args: bool,
+    ) -> Result<LearningEstimator, EstimatorError> {
+        if !(0.0..=1.0).contains(&quantile) {
+            return Err(EstimatorError::InvalidConfig(format!(
+                "quantile must be in [0, 1], got {}",
+                quantile
+            )));
+        }
+        if !(0.0..=1.0).contains(&min_confidence) {
+            return Err(EstimatorError::InvalidConfig(format!(
+                "min_confidence must be in [0, 1], got {}",
+                min_confidence
+            )));
+        }
+        let mut db = sqlite_open(
+            p,
+            rusqlite::OpenFlags::SQLITE_OPEN_CREATE | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE,
+            false,
+        )?;
+
+        // check if the db needs to be instantiated regardless of whether or not
+        //  it was newly created: the db itself may be shared with other estimators,
+        //  which would not have created the necessary table for this estimator.
+        let tx = tx_begin_immediate_sqlite(&mut db)?;
+        LearningEstimator::instantiate_db(&tx)?;
+        tx.commit()?;
+
+        Ok(LearningEstimator {
+            db,
+            fallback,
+            quantile,
+            min_confidence,
+            condition_on_args,
+        })
+    }
+
+    /// Check if the SQL database was already created. Necessary to avoid races if
+    ///  different threads open an estimator at the same time.
+    fn db_already_instantiated(tx: &SqliteTransaction) -> Result<bool, SqliteError> {
+        table_exists(tx, "learning_estimator")
+    }
+
+    fn instantiate_db(tx: &SqliteTransaction) -> Result<(), SqliteError> {
+        if !LearningEstimator::db_already_instantiated(tx)? {
+            tx.execute(CREATE_TABLE, rusqlite::NO_PARAMS)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the size class of a contract-call's arguments: the bit length of their total
+    /// serialized size. Returns `None` for other payloads, or if the arguments can't be
+    /// serialized.
+    fn get_args_size_class(tx: &TransactionPayload) -> Option<u32> {
+        let TransactionPayload::ContractCall(cc) = tx else {
+            return None;
+        };
+        let mut total_size = 0u64;
+        for arg in cc.function_args.iter() {
+            total_size = total_size.saturating_add(arg.serialized_size().ok()?.into());
+        }
+        Some(u64::BITS - total_size.leading_zeros())
+    }
+
+    /// Get the operation descriptors to learn and look up estimates for, most specific first.
+    fn get_descriptors(
+        &self,
+        tx: &TransactionPayload,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Vec<String> {
+        let descriptor = PessimisticEstimator::get_payload_descriptor(tx, evaluated_epoch);
+        let mut descriptors = vec![];
+        if self.condition_on_args {
+            if let Some(size_class) = LearningEstimator::get_args_size_class(tx) {
+                descriptors.push(format!("{}:args-{}", &descriptor, size_class));
+            }
+        }
+        descriptors.push(descriptor);
+        descriptors
+    }
+
+    fn get_estimate_key(descriptor: &str, field: &CostField) -> String {
+        format!("{}:{}", descriptor, field)
+    }
+
+    /// Compute the estimate for a single operation descriptor, if all of its cost dimensions
+    /// have been observed.
+    fn get_descriptor_estimate(&self, descriptor: &str) -> Option<LearnedCostEstimate> {
+        let mut cost = ExecutionCost::zero();
+        let mut samples = u64::MAX;
+        let mut stability = 1f64;
+        for field in CostField::ALL.iter() {
+            let stats = CostStats::get_sqlite(
+                &self.db,
+                &LearningEstimator::get_estimate_key(descriptor, field),
+            )?;
+            let estimate = stats.estimate(self.quantile);
+            match field {
+                CostField::RuntimeCost => cost.runtime = estimate,
+                CostField::WriteLength => cost.write_length = estimate,
+                CostField::WriteCount => cost.write_count = estimate,
+                CostField::ReadLength => cost.read_length = estimate,
+                CostField::ReadCount => cost.read_count = estimate,
+            }
+            samples = cmp::min(samples, stats.count);
+            stability = stability.min(stats.stability(self.quantile));
+        }
+
+        let sample_factor = (samples as f64 / MIN_CONFIDENT_SAMPLES as f64).min(1f64);
+        Some(LearnedCostEstimate {
+            cost,
+            confidence: sample_factor * stability,
+            samples,
+        })
+    }
+
+    /// Get the most specific learned estimate for this payload, regardless of its confidence.
+    /// Returns `None` if nothing has been learned about the payload.
+    pub fn get_learned_estimate(
+        &self,
+        tx: &TransactionPayload,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Option<LearnedCostEstimate> {
+        self.get_descriptors(tx, evaluated_epoch)
+            .iter()
+            .find_map(|descriptor| self.get_descriptor_estimate(descriptor))
+    }
+}
+
+impl CostEstimator for LearningEstimator {
+    fn notify_event(
+        &mut self,
+        tx: &TransactionPayload,
+        actual_cost: &ExecutionCost,
+        block_limit: &ExecutionCost,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Result<(), EstimatorError> {
+        self.fallback
+            .notify_event(tx, actual_cost, block_limit, evaluated_epoch)?;
+
+        let descriptors = self.get_descriptors(tx, evaluated_epoch);
+        let sql_tx = tx_begin_immediate_sqlite(&mut self.db)?;
+        for descriptor in descriptors.iter() {
+            for field in CostField::ALL.iter() {
+                let key = LearningEstimator::get_estimate_key(descriptor, field);
+                let mut stats = CostStats::get_sqlite(&sql_tx, &key).unwrap_or_else(CostStats::new);
+                stats.update_with(field.select_key(actual_cost));
+                stats.flush_sqlite(&sql_tx, &key);
+            }
+        }
+        sql_tx.commit()?;
+        Ok(())
+    }
+
+    fn estimate_cost(
+        &self,
+        tx: &TransactionPayload,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Result<ExecutionCost, EstimatorError> {
+        // use the most specific estimate that is confident enough
+        for descriptor in self.get_descriptors(tx, evaluated_epoch).iter() {
+            if let Some(estimate) = self.get_descriptor_estimate(descriptor) {
+                if estimate.confidence >= self.min_confidence {
+                    return Ok(estimate.cost);
+                }
+            }
+        }
+        self.fallback.estimate_cost(tx, evaluated_epoch)
+    }
+}
@@ -14,15 +14,18 @@ use crate::chainstate::stacks::db::StacksEpochReceipt;
 use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
 use crate::chainstate::stacks::{StacksBlock, TransactionPayload};
 
+pub mod fee_histogram;
 pub mod fee_medians;
 pub mod fee_rate_fuzzer;
 pub mod fee_scalar;
+pub mod learning;
 pub mod metrics;
 pub mod pessimistic;
 
 #[cfg(test)]
 pub mod tests;
 
+pub use self::learning::LearningEstimator;
 use self::metrics::CostMetric;
 pub use self::pessimistic::PessimisticEstimator;
 use crate::chainstate::stacks::StacksTransaction;
@@ -49,6 +52,18 @@ pub trait FeeEstimator {
     ) -> Result<(), EstimatorError>;
     /// Get the current estimates for fee rate
     fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError>;
+    /// Get the current estimates for fee rate, both for inclusion in the next block and for
+    ///  inclusion within the estimator's target number of blocks. Estimators that do not
+    ///  distinguish between the two report `get_rate_estimates()` for both, with a target of
+    ///  one block.
+    fn get_rate_targets(&self) -> Result<FeeRateTargets, EstimatorError> {
+        let estimate = self.get_rate_estimates()?;
+        Ok(FeeRateTargets {
+            next_block: estimate.clone(),
+            within_target: estimate,
+            target_blocks: 1,
+        })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
@@ -60,6 +75,15 @@ pub struct FeeRateEstimate {
     pub low: f64,
 }
 
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+/// This struct is returned from fee rate estimators as the current best estimates for
+/// fee rates to include a transaction in the next block, or within `target_blocks` blocks.
+pub struct FeeRateTargets {
+    pub next_block: FeeRateEstimate,
+    pub within_target: FeeRateEstimate,
+    pub target_blocks: u64,
+}
+
 fn saturating_f64_math(res: f64) -> f64 {
     if res.is_finite() {
         res
@@ -185,6 +209,7 @@ pub trait CostEstimator: Send {
 pub enum EstimatorError {
     NoEstimateAvailable,
     SqliteError(SqliteError),
+    InvalidConfig(String),
 }
 
 impl Error for EstimatorError {
@@ -205,6 +230,9 @@ impl Display for EstimatorError {
             EstimatorError::SqliteError(e) => {
                 write!(f, "Sqlite error from estimator: {}", e)
             }
+            EstimatorError::InvalidConfig(msg) => {
+                write!(f, "Invalid estimator configuration: {}", msg)
+            }
         }
     }
 }
@@ -219,6 +247,9 @@ impl EstimatorError {
             EstimatorError::SqliteError(_) => {
                 ("DatabaseError", Some(json!({"message": self.to_string()})))
             }
+            EstimatorError::InvalidConfig(_) => {
+                ("InvalidConfig", Some(json!({"message": self.to_string()})))
+            }
         };
         let mut result = json!({
             "error": "Estimation could not be performed",
@@ -52,7 +52,7 @@ iterable_enum!(CostField {
 
 impl CostField {
     /// Select `self` out of the given ExecutionCost
-    fn select_key(&self, from_cost: &ExecutionCost) -> u64 {
+    pub fn select_key(&self, from_cost: &ExecutionCost) -> u64 {
         match self {
             CostField::RuntimeCost => from_cost.runtime,
             CostField::WriteLength => from_cost.write_length,
@@ -216,7 +216,17 @@ impl PessimisticEstimator {
         field: &CostField,
         evaluated_epoch: &StacksEpochId,
     ) -> String {
-        let tx_descriptor = match tx {
+        let tx_descriptor = PessimisticEstimator::get_payload_descriptor(tx, evaluated_epoch);
+        format!("{}:{}", &tx_descriptor, field)
+    }
+
+    /// Get the string which identifies the operation performed by this payload. Estimates for
+    ///  each cost dimension are keyed by this descriptor.
+    pub(crate) fn get_payload_descriptor(
+        tx: &TransactionPayload,
+        evaluated_epoch: &StacksEpochId,
+    ) -> String {
+        match tx {
             TransactionPayload::TokenTransfer(..) => "stx-transfer".to_string(),
             TransactionPayload::ContractCall(cc) => {
                 // Epoch key is "" before Epoch2_05 for backwards compatibility.
@@ -245,9 +255,7 @@ impl PessimisticEstimator {
             TransactionPayload::PoisonMicroblock(_, _) => "poison-ublock".to_string(),
             TransactionPayload::Coinbase(..) => "coinbase".to_string(),
             TransactionPayload::TenureChange(..) => "tenure-change".to_string(),
-        };
-
-        format!("{}:{}", &tx_descriptor, field)
+        }
     }
 }
 
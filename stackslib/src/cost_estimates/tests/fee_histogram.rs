@@ -0,0 +1,446 @@
+use std::env;
+use std::path::PathBuf;
+
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::Value;
+use rand::Rng;
+use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::util::hash::{to_hex, Hash160};
+
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::{
+    CoinbasePayload, StacksTransaction, TransactionAuth, TransactionContractCall,
+    TransactionPayload, TransactionSpendingCondition, TransactionVersion,
+};
+use crate::core::MemPoolDB;
+use crate::cost_estimates::fee_histogram::{FeeRateHistogram, HistogramFeeRateEstimator};
+use crate::cost_estimates::metrics::{ProportionalDotProduct, UnitMetric};
+use crate::cost_estimates::tests::common::*;
+use crate::cost_estimates::{EstimatorError, FeeEstimator, FeeRateEstimate, UnitEstimator};
+
+const block_limit: ExecutionCost = ExecutionCost {
+    write_length: 100,
+    write_count: 100,
+    read_length: 100,
+    read_count: 100,
+    runtime: 100,
+};
+
+const tenth_operation_cost: ExecutionCost = ExecutionCost {
+    write_length: 0,
+    write_count: 0,
+    read_length: 0,
+    read_count: 0,
+    runtime: 10,
+};
+
+const half_operation_cost: ExecutionCost = ExecutionCost {
+    write_length: 0,
+    write_count: 0,
+    read_length: 0,
+    read_count: 0,
+    runtime: 50,
+};
+
+// The scalar cost of `make_dummy_cc_tx(_, &tenth_operation_cost)`.
+const tenth_operation_cost_basis: u64 = 1164;
+
+// The scalar cost of `make_dummy_cc_tx(_, &half_operation_cost)`.
+const half_operation_cost_basis: u64 = 5164;
+
+// The weight of a full block
+const full_block_weight: u64 = 10_000;
+
+fn temp_path(prefix: &str) -> PathBuf {
+    let mut path = env::temp_dir();
+    let random_bytes = rand::thread_rng().gen::<[u8; 32]>();
+    path.push(&format!(
+        "{}_{}.sqlite",
+        prefix,
+        &to_hex(&random_bytes)[0..8]
+    ));
+    path
+}
+
+fn instantiate_test_db(
+    mempool_path: Option<PathBuf>,
+    target_blocks: u64,
+) -> HistogramFeeRateEstimator<ProportionalDotProduct> {
+    let path = temp_path("fee_histogram_db");
+    let window_size = 5;
+    HistogramFeeRateEstimator::open(
+        &path,
+        mempool_path,
+        ProportionalDotProduct::new(10_000),
+        window_size,
+        target_blocks,
+    )
+    .expect("Test failure: could not open fee rate DB")
+}
+
+fn instantiate_test_mempool() -> (MemPoolDB, PathBuf) {
+    let path = temp_path("fee_histogram_mempool");
+    let mempool = MemPoolDB::open_db(
+        path.to_str().unwrap(),
+        Box::new(UnitEstimator),
+        Box::new(UnitMetric),
+    )
+    .expect("Test failure: could not open mempool DB");
+    (mempool, path)
+}
+
+/// Store a pending transaction with the given fee rate and weight, from an address derived
+/// from `seed`.
+fn insert_mempool_tx(mempool: &MemPoolDB, seed: u8, origin_nonce: u64, fee_rate: f64, weight: u64) {
+    let addr = StacksAddress::new(26, Hash160([seed; 20]));
+    let sponsor = StacksAddress::new(26, Hash160([seed.wrapping_add(128); 20]));
+    let txid = to_hex(&[seed; 32]);
+    let tx_fee = (fee_rate * weight as f64) as i64;
+    mempool
+        .db
+        .execute(
+            "INSERT INTO mempool
+             (txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, tx_fee, length,
+              consensus_hash, block_header_hash, height, accept_time, tx, fee_rate)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 100, ?7, ?8, 1, 1, ?9, ?10)",
+            rusqlite::params![
+                txid,
+                addr.to_string(),
+                origin_nonce as i64,
+                sponsor.to_string(),
+                origin_nonce as i64,
+                tx_fee,
+                to_hex(&[0; 20]),
+                to_hex(&[0; 32]),
+                vec![0u8],
+                fee_rate,
+            ],
+        )
+        .unwrap();
+}
+
+fn make_dummy_coinbase_tx() -> StacksTransaction {
+    StacksTransaction::new(
+        TransactionVersion::Mainnet,
+        TransactionAuth::Standard(TransactionSpendingCondition::new_initial_sighash()),
+        TransactionPayload::Coinbase(CoinbasePayload([0; 32]), None, None),
+    )
+}
+
+fn make_dummy_cc_tx(fee: u64, execution_cost: &ExecutionCost) -> StacksTransactionReceipt {
+    let mut tx = StacksTransaction::new(
+        TransactionVersion::Mainnet,
+        TransactionAuth::Standard(TransactionSpendingCondition::new_initial_sighash()),
+        TransactionPayload::ContractCall(TransactionContractCall {
+            address: StacksAddress::new(0, Hash160([0; 20])),
+            contract_name: "cc-dummy".into(),
+            function_name: "func-name".into(),
+            function_args: vec![],
+        }),
+    );
+    tx.set_tx_fee(fee);
+    StacksTransactionReceipt::from_contract_call(
+        tx,
+        vec![],
+        Value::okay(Value::Bool(true)).unwrap(),
+        0,
+        execution_cost.clone(),
+    )
+}
+
+/// Notify the estimator of a full block, paying `fee_rate` per unit of cost
+fn notify_full_block(
+    estimator: &mut HistogramFeeRateEstimator<ProportionalDotProduct>,
+    fee_rate: u64,
+) {
+    let receipt = make_block_receipt(vec![
+        StacksTransactionReceipt::from_coinbase(make_dummy_coinbase_tx()),
+        make_dummy_cc_tx(fee_rate * half_operation_cost_basis, &half_operation_cost),
+        make_dummy_cc_tx(fee_rate * half_operation_cost_basis, &half_operation_cost),
+    ]);
+    estimator
+        .notify_block(&receipt, &block_limit)
+        .expect("Should be able to process block receipt");
+}
+
+/// Returns true iff `b` is within `0.1%` of `a`.
+fn is_close_f64(a: f64, b: f64) -> bool {
+    let error = (a - b).abs() / a.abs();
+    error < 0.001
+}
+
+/// Returns `true` iff each value in `left` "close" to its counterpart in `right`.
+fn is_close(left: FeeRateEstimate, right: FeeRateEstimate) -> bool {
+    let is_ok = is_close_f64(left.high, right.high)
+        && is_close_f64(left.middle, right.middle)
+        && is_close_f64(left.low, right.low);
+    if !is_ok {
+        warn!("Estimates are not close. {:?} vs {:?}", left, right);
+    }
+    is_ok
+}
+
+fn bucketed(fee_rate: f64) -> f64 {
+    FeeRateHistogram::bucketed_fee_rate(fee_rate)
+}
+
+#[test]
+fn test_histogram_buckets() {
+    // the minimum fee rate is its own bucket
+    assert_eq!(bucketed(1f64), 1f64);
+    assert_eq!(bucketed(0.5f64), 1f64);
+
+    // bucketed fee rates are never lower than the fee rate, and at most 10% higher
+    for fee_rate in [1.01f64, 2f64, 10f64, 123.456f64, 1e9f64] {
+        let bucketed_rate = bucketed(fee_rate);
+        assert!(bucketed_rate >= fee_rate);
+        assert!(bucketed_rate <= fee_rate * 1.1);
+    }
+
+    let mut histogram = FeeRateHistogram::new();
+    assert_eq!(histogram.estimate(), None);
+
+    histogram.add(10f64, 90);
+    histogram.pad_to(100);
+    assert_eq!(histogram.total_weight(), 100);
+    assert_eq!(histogram.percentile(0.05), Some(1f64));
+    assert_eq!(histogram.percentile(0.1), Some(1f64));
+    assert!(is_close_f64(
+        histogram.percentile(0.11).unwrap(),
+        bucketed(10f64)
+    ));
+    assert!(is_close_f64(
+        histogram.percentile(1.0).unwrap(),
+        bucketed(10f64)
+    ));
+
+    let mut other = FeeRateHistogram::new();
+    other.add(100f64, 200);
+    histogram.merge(&other);
+    assert_eq!(histogram.total_weight(), 300);
+    assert!(is_close_f64(
+        histogram.percentile(0.5).unwrap(),
+        bucketed(100f64)
+    ));
+}
+
+/// Tests that we have no estimate available until we `notify`, or have pending transactions.
+#[test]
+fn test_empty_fee_estimator() {
+    let estimator = instantiate_test_db(None, 3);
+    assert_eq!(
+        estimator
+            .get_rate_targets()
+            .expect_err("Empty rate estimator should error."),
+        EstimatorError::NoEstimateAvailable
+    );
+
+    // an empty mempool doesn't help
+    let (_mempool, mempool_path) = instantiate_test_mempool();
+    let estimator = instantiate_test_db(Some(mempool_path), 3);
+    assert_eq!(
+        estimator
+            .get_rate_estimates()
+            .expect_err("Empty rate estimator should error."),
+        EstimatorError::NoEstimateAvailable
+    );
+}
+
+/// A totally empty block means the minimum fee rate is enough, in any time frame.
+#[test]
+fn test_empty_block_returns_minimum() {
+    let mut estimator = instantiate_test_db(None, 3);
+
+    estimator
+        .notify_block(&make_block_receipt(vec![]), &block_limit)
+        .expect("Should be able to process an empty block");
+
+    let minimum = FeeRateEstimate {
+        high: 1f64,
+        middle: 1f64,
+        low: 1f64,
+    };
+    let targets = estimator
+        .get_rate_targets()
+        .expect("Should be able to create estimate now");
+    assert_eq!(targets.target_blocks, 3);
+    assert!(is_close(targets.next_block, minimum.clone()));
+    assert!(is_close(targets.within_target, minimum.clone()));
+    assert!(is_close(estimator.get_rate_estimates().unwrap(), minimum));
+}
+
+/// A mostly-empty block is dominated by the padding, and since blocks have room, waiting
+/// a few blocks discounts the high estimate by the block fullness.
+#[test]
+fn test_one_block_partially_filled() {
+    let mut estimator = instantiate_test_db(None, 3);
+
+    let receipt = make_block_receipt(vec![
+        StacksTransactionReceipt::from_coinbase(make_dummy_coinbase_tx()),
+        make_dummy_cc_tx(10 * tenth_operation_cost_basis, &tenth_operation_cost),
+    ]);
+    estimator
+        .notify_block(&receipt, &block_limit)
+        .expect("Should be able to process block receipt");
+
+    let targets = estimator
+        .get_rate_targets()
+        .expect("Should be able to create estimate now");
+    assert!(is_close(
+        targets.next_block,
+        FeeRateEstimate {
+            high: bucketed(10f64),
+            middle: 1f64,
+            low: 1f64,
+        }
+    ));
+
+    let fullness = tenth_operation_cost_basis as f64 / full_block_weight as f64;
+    assert!(is_close(
+        targets.within_target,
+        FeeRateEstimate {
+            high: bucketed(10f64) * fullness,
+            middle: 1f64,
+            low: 1f64,
+        }
+    ));
+}
+
+/// Full blocks mean waiting doesn't get a discount
+#[test]
+fn test_full_blocks() {
+    let mut estimator = instantiate_test_db(None, 3);
+    notify_full_block(&mut estimator, 10);
+
+    let expected = FeeRateEstimate {
+        high: bucketed(10f64),
+        middle: bucketed(10f64),
+        low: bucketed(10f64),
+    };
+    let targets = estimator
+        .get_rate_targets()
+        .expect("Should be able to create estimate now");
+    assert!(is_close(targets.next_block, expected.clone()));
+    assert!(is_close(targets.within_target, expected));
+}
+
+/// Only the last `window_size` blocks are considered.
+#[test]
+fn test_window_size_forget_something() {
+    let mut estimator = instantiate_test_db(None, 3);
+    for _ in 0..5 {
+        notify_full_block(&mut estimator, 100);
+    }
+    for _ in 0..5 {
+        notify_full_block(&mut estimator, 10);
+    }
+
+    let expected = FeeRateEstimate {
+        high: bucketed(10f64),
+        middle: bucketed(10f64),
+        low: bucketed(10f64),
+    };
+    assert!(is_close(
+        estimator
+            .get_rate_estimates()
+            .expect("Should be able to create estimate now"),
+        expected
+    ));
+
+    // half of the window is at the higher fee rate
+    for _ in 0..3 {
+        notify_full_block(&mut estimator, 100);
+    }
+    let estimate = estimator.get_rate_estimates().unwrap();
+    assert!(is_close_f64(estimate.low, bucketed(10f64)));
+    assert!(is_close_f64(estimate.middle, bucketed(100f64)));
+    assert!(is_close_f64(estimate.high, bucketed(100f64)));
+}
+
+/// A backlog of pending transactions raises the estimates above what recent blocks paid, and
+/// the estimates for inclusion within a few blocks are lower than for the next block.
+#[test]
+fn test_mempool_pressure() {
+    let (mempool, mempool_path) = instantiate_test_mempool();
+    let mut estimator = instantiate_test_db(Some(mempool_path), 3);
+
+    // recent blocks were empty
+    estimator
+        .notify_block(&make_block_receipt(vec![]), &block_limit)
+        .expect("Should be able to process an empty block");
+
+    // one block's worth of transactions at 50, and two blocks' worth at 20
+    insert_mempool_tx(&mempool, 1, 0, 50f64, full_block_weight);
+    insert_mempool_tx(&mempool, 2, 0, 20f64, full_block_weight);
+    insert_mempool_tx(&mempool, 3, 0, 20f64, full_block_weight);
+
+    let targets = estimator
+        .get_rate_targets()
+        .expect("Should be able to create estimate now");
+    assert!(is_close(
+        targets.next_block,
+        FeeRateEstimate {
+            high: bucketed(50f64),
+            middle: bucketed(50f64),
+            low: bucketed(50f64),
+        }
+    ));
+    assert!(is_close(
+        targets.within_target,
+        FeeRateEstimate {
+            high: bucketed(50f64),
+            middle: bucketed(20f64),
+            low: bucketed(20f64),
+        }
+    ));
+
+    // the recent history still counts if it paid more than the mempool, but since only half
+    // of the recent blocks were full, the mempool dominates when waiting a few blocks
+    notify_full_block(&mut estimator, 100);
+    let targets = estimator.get_rate_targets().unwrap();
+    assert!(is_close_f64(targets.next_block.high, bucketed(100f64)));
+    assert!(bucketed(100f64) * 0.5 < bucketed(50f64));
+    assert!(is_close_f64(targets.within_target.high, bucketed(50f64)));
+}
+
+/// With no history, a mempool that doesn't fill a block is padded like a block would be.
+/// Transactions whose nonces are known to be spent are ignored.
+#[test]
+fn test_mempool_only() {
+    let (mempool, mempool_path) = instantiate_test_mempool();
+    let estimator = instantiate_test_db(Some(mempool_path), 2);
+
+    insert_mempool_tx(&mempool, 1, 0, 30f64, full_block_weight * 3 / 5);
+    insert_mempool_tx(&mempool, 2, 0, 1000f64, full_block_weight);
+
+    // the second transaction was mined already
+    let addr = StacksAddress::new(26, Hash160([2; 20]));
+    mempool
+        .db
+        .execute(
+            "INSERT INTO nonces (address, nonce) VALUES (?1, 1)",
+            rusqlite::params![addr.to_string()],
+        )
+        .unwrap();
+
+    let targets = estimator
+        .get_rate_targets()
+        .expect("Should be able to create estimate now");
+    assert_eq!(targets.target_blocks, 2);
+    assert!(is_close(
+        targets.next_block,
+        FeeRateEstimate {
+            high: bucketed(30f64),
+            middle: bucketed(30f64),
+            low: 1f64,
+        }
+    ));
+    assert!(is_close(
+        targets.within_target,
+        FeeRateEstimate {
+            high: bucketed(30f64),
+            middle: 1f64,
+            low: 1f64,
+        }
+    ));
+}
@@ -0,0 +1,267 @@
+use std::env;
+use std::path::PathBuf;
+
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::Value;
+use rand::Rng;
+use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::util::hash::{to_hex, Hash160};
+
+use crate::chainstate::stacks::{TransactionContractCall, TransactionPayload};
+use crate::core::{StacksEpochId, BLOCK_LIMIT_MAINNET_20};
+use crate::cost_estimates::{
+    CostEstimator, EstimatorError, LearningEstimator, PessimisticEstimator,
+};
+
+fn temp_path(prefix: &str) -> PathBuf {
+    let mut path = env::temp_dir();
+    let random_bytes = rand::thread_rng().gen::<[u8; 32]>();
+    path.push(&format!(
+        "{}_{}.sqlite",
+        prefix,
+        &to_hex(&random_bytes)[0..8]
+    ));
+    path
+}
+
+fn instantiate_test_db(condition_on_args: bool) -> LearningEstimator {
+    let fallback = PessimisticEstimator::open(&temp_path("pessimistic_db"), false)
+        .expect("Test failure: could not open pessimistic estimator DB");
+    LearningEstimator::open(
+        &temp_path("learning_db"),
+        fallback,
+        0.9,
+        0.8,
+        condition_on_args,
+    )
+    .expect("Test failure: could not open learning estimator DB")
+}
+
+fn make_cc_payload(function_name: &str, function_args: Vec<Value>) -> TransactionPayload {
+    TransactionPayload::ContractCall(TransactionContractCall {
+        address: StacksAddress::new(0, Hash160([0; 20])),
+        contract_name: "amm".into(),
+        function_name: function_name.into(),
+        function_args,
+    })
+}
+
+/// An `ExecutionCost` with `value` in every dimension
+fn uniform_cost(value: u64) -> ExecutionCost {
+    ExecutionCost {
+        write_length: value,
+        write_count: value,
+        read_length: value,
+        read_count: value,
+        runtime: value,
+    }
+}
+
+fn notify(estimator: &mut LearningEstimator, payload: &TransactionPayload, value: u64) {
+    estimator
+        .notify_event(
+            payload,
+            &uniform_cost(value),
+            &BLOCK_LIMIT_MAINNET_20,
+            &StacksEpochId::Epoch21,
+        )
+        .expect("Should be able to process event");
+}
+
+#[test]
+fn test_empty_learning_estimator() {
+    let estimator = instantiate_test_db(true);
+    let payload = make_cc_payload("swap", vec![]);
+    assert_eq!(
+        estimator
+            .estimate_cost(&payload, &StacksEpochId::Epoch21)
+            .expect_err("Empty learning estimator should error."),
+        EstimatorError::NoEstimateAvailable
+    );
+    assert!(estimator
+        .get_learned_estimate(&payload, &StacksEpochId::Epoch21)
+        .is_none());
+}
+
+/// With few samples, the learned estimate isn't trusted, and the pessimistic estimate is used.
+#[test]
+fn test_learning_estimator_falls_back_with_few_samples() {
+    let mut estimator = instantiate_test_db(true);
+    let payload = make_cc_payload("swap", vec![]);
+
+    notify(&mut estimator, &payload, 10);
+    notify(&mut estimator, &payload, 100);
+
+    let learned = estimator
+        .get_learned_estimate(&payload, &StacksEpochId::Epoch21)
+        .unwrap();
+    assert_eq!(learned.samples, 2);
+    assert!(learned.confidence < 0.8);
+
+    // the pessimistic estimator averages its samples
+    assert_eq!(
+        estimator
+            .estimate_cost(&payload, &StacksEpochId::Epoch21)
+            .unwrap(),
+        uniform_cost(55)
+    );
+}
+
+/// With many consistent samples, a single outlier doesn't inflate the estimate the way it
+/// does for the pessimistic estimator.
+#[test]
+fn test_learning_estimator_ignores_outliers() {
+    let mut estimator = instantiate_test_db(true);
+    let payload = make_cc_payload("swap", vec![]);
+
+    notify(&mut estimator, &payload, 1000);
+    for _ in 0..30 {
+        notify(&mut estimator, &payload, 100);
+    }
+
+    let learned = estimator
+        .get_learned_estimate(&payload, &StacksEpochId::Epoch21)
+        .unwrap();
+    assert_eq!(learned.samples, 31);
+    assert!(learned.confidence > 0.9, "{}", learned.confidence);
+
+    let estimate = estimator
+        .estimate_cost(&payload, &StacksEpochId::Epoch21)
+        .unwrap();
+    assert_eq!(estimate, learned.cost);
+    assert!(estimate.runtime >= 100 && estimate.runtime < 110);
+    assert!(estimate.write_length >= 100 && estimate.write_length < 110);
+
+    // the pessimistic estimate is the average of the top 10 samples
+    let mut pessimistic = PessimisticEstimator::open(&temp_path("pessimistic_db"), false)
+        .expect("Test failure: could not open pessimistic estimator DB");
+    pessimistic
+        .notify_event(
+            &payload,
+            &uniform_cost(1000),
+            &BLOCK_LIMIT_MAINNET_20,
+            &StacksEpochId::Epoch21,
+        )
+        .unwrap();
+    for _ in 0..30 {
+        pessimistic
+            .notify_event(
+                &payload,
+                &uniform_cost(100),
+                &BLOCK_LIMIT_MAINNET_20,
+                &StacksEpochId::Epoch21,
+            )
+            .unwrap();
+    }
+    assert_eq!(
+        pessimistic
+            .estimate_cost(&payload, &StacksEpochId::Epoch21)
+            .unwrap(),
+        uniform_cost(190)
+    );
+}
+
+/// Costs which vary wildly aren't trusted, even with many samples.
+#[test]
+fn test_learning_estimator_unpredictable_costs() {
+    let mut estimator = instantiate_test_db(true);
+    let payload = make_cc_payload("swap", vec![]);
+
+    for i in 0..40 {
+        notify(&mut estimator, &payload, if i % 2 == 0 { 10 } else { 1000 });
+    }
+
+    let learned = estimator
+        .get_learned_estimate(&payload, &StacksEpochId::Epoch21)
+        .unwrap();
+    assert_eq!(learned.samples, 40);
+    assert!(learned.confidence < 0.8, "{}", learned.confidence);
+    assert_eq!(learned.cost.runtime, 1000);
+
+    // the pessimistic estimator only remembers the ten highest samples
+    assert_eq!(
+        estimator
+            .estimate_cost(&payload, &StacksEpochId::Epoch21)
+            .unwrap(),
+        uniform_cost(1000)
+    );
+}
+
+/// Contract-calls with differently-sized arguments are learned separately, if configured.
+#[test]
+fn test_learning_estimator_conditions_on_args() {
+    let small_args = vec![Value::UInt(1)];
+    let big_args = vec![Value::buff_from(vec![0; 1000]).unwrap()];
+
+    for condition_on_args in [true, false] {
+        let mut estimator = instantiate_test_db(condition_on_args);
+
+        for _ in 0..20 {
+            notify(
+                &mut estimator,
+                &make_cc_payload("swap", small_args.clone()),
+                100,
+            );
+        }
+        for _ in 0..20 {
+            notify(
+                &mut estimator,
+                &make_cc_payload("swap", big_args.clone()),
+                100_000,
+            );
+        }
+
+        let small_estimate = estimator
+            .estimate_cost(
+                &make_cc_payload("swap", small_args.clone()),
+                &StacksEpochId::Epoch21,
+            )
+            .unwrap();
+        let big_estimate = estimator
+            .estimate_cost(
+                &make_cc_payload("swap", big_args.clone()),
+                &StacksEpochId::Epoch21,
+            )
+            .unwrap();
+
+        if condition_on_args {
+            assert!(small_estimate.runtime >= 100 && small_estimate.runtime < 110);
+            assert_eq!(big_estimate.runtime, 100_000);
+
+            // an unseen argument size uses what was learned across all argument sizes
+            let learned = estimator
+                .get_learned_estimate(&make_cc_payload("swap", vec![]), &StacksEpochId::Epoch21)
+                .unwrap();
+            assert_eq!(learned.samples, 40);
+        } else {
+            assert_eq!(small_estimate, big_estimate);
+        }
+
+        // other functions are unaffected
+        assert_eq!(
+            estimator
+                .estimate_cost(
+                    &make_cc_payload("add-liquidity", small_args.clone()),
+                    &StacksEpochId::Epoch21
+                )
+                .expect_err("No estimate for an unseen function"),
+            EstimatorError::NoEstimateAvailable
+        );
+    }
+}
+
+#[test]
+fn test_invalid_config() {
+    for (quantile, min_confidence) in [(1.5, 0.5), (-0.1, 0.5), (0.9, 1.1), (0.9, f64::NAN)] {
+        let fallback = PessimisticEstimator::open(&temp_path("pessimistic_db"), false)
+            .expect("Test failure: could not open pessimistic estimator DB");
+        let result = LearningEstimator::open(
+            &temp_path("learning_db"),
+            fallback,
+            quantile,
+            min_confidence,
+            true,
+        );
+        assert!(matches!(result, Err(EstimatorError::InvalidConfig(_))));
+    }
+}
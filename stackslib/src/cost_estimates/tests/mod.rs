@@ -2,9 +2,11 @@ use crate::cost_estimates::FeeRateEstimate;
 
 pub mod common;
 pub mod cost_estimators;
+pub mod fee_histogram;
 pub mod fee_medians;
 pub mod fee_rate_fuzzer;
 pub mod fee_scalar;
+pub mod learning;
 pub mod metrics;
 
 #[test]
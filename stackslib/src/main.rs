@@ -59,14 +59,18 @@ use blockstack_lib::chainstate::stacks::db::{
     ChainStateBootData, StacksBlockHeaderTypes, StacksChainState, StacksHeaderInfo,
 };
 use blockstack_lib::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MARF};
-use blockstack_lib::chainstate::stacks::index::ClarityMarfTrieId;
+use blockstack_lib::chainstate::stacks::index::{ClarityMarfTrieId, TrieMerkleProof};
 use blockstack_lib::chainstate::stacks::miner::*;
 use blockstack_lib::chainstate::stacks::{StacksBlockHeader, *};
 use blockstack_lib::clarity::vm::costs::ExecutionCost;
+use blockstack_lib::clarity::vm::database::{BurnStateDB, NULL_BURN_STATE_DB};
+use blockstack_lib::clarity::vm::profiler::ExecutionProfiler;
 use blockstack_lib::clarity::vm::types::StacksAddressExtensions;
 use blockstack_lib::clarity::vm::ClarityVersion;
 use blockstack_lib::clarity_cli;
 use blockstack_lib::clarity_cli::vm_execute;
+use blockstack_lib::clarity_vm::database::rpc::TrustedHeader;
+use blockstack_lib::clarity_vm::database::{snapshot, HeadersDBConn};
 use blockstack_lib::core::{MemPoolDB, *};
 use blockstack_lib::cost_estimates::metrics::UnitMetric;
 use blockstack_lib::cost_estimates::UnitEstimator;
@@ -821,6 +825,172 @@ simulating a miner.
         return;
     }
 
+    if argv[1] == "marf-export" {
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} marf-export CHAINSTATE_DIR INDEX_BLOCK_HASH SNAPSHOT_FILE",
+                argv[0]
+            );
+            eprintln!(
+                "       Writes the Clarity state MARF as of INDEX_BLOCK_HASH to SNAPSHOT_FILE"
+            );
+            process::exit(1);
+        }
+        let marf_path = format!("{}/vm/clarity/marf.sqlite", &argv[2]);
+        let tip = StacksBlockId::from_hex(&argv[3]).expect("Bad index block hash");
+        let snapshot_path = &argv[4];
+
+        let mut out = io::BufWriter::new(
+            File::create(snapshot_path)
+                .unwrap_or_else(|e| panic!("Failed to create {}: {:?}", snapshot_path, &e)),
+        );
+        match snapshot::export_marf_snapshot(&marf_path, &tip, &mut out) {
+            Ok(summary) => {
+                println!(
+                    "Exported {} tries, {} data values and {} metadata rows at {} (root hash {})",
+                    summary.header.num_tries,
+                    summary.num_data,
+                    summary.num_metadata,
+                    &summary.header.tip,
+                    &summary.header.tip_root_hash
+                );
+            }
+            Err(e) => {
+                eprintln!("Failed to export MARF snapshot: {}", &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "marf-import" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} marf-import SNAPSHOT_FILE CHAINSTATE_DIR [HEADERS_DB [SORTITION_DB]]",
+                argv[0]
+            );
+            eprintln!("       Creates CHAINSTATE_DIR/vm/clarity/marf.sqlite from SNAPSHOT_FILE, checking every trie");
+            eprintln!("       against the state roots in HEADERS_DB (default: CHAINSTATE_DIR/vm/index.sqlite), and");
+            eprintln!("       every contract's metadata by replaying its deployment.  Deployments which read");
+            eprintln!("       burnchain state can only be replayed with SORTITION_DB.");
+            process::exit(1);
+        }
+        let snapshot_path = &argv[2];
+        let marf_dir = format!("{}/vm/clarity", &argv[3]);
+        let marf_path = format!("{}/marf.sqlite", &marf_dir);
+        let headers_path = argv
+            .get(4)
+            .cloned()
+            .unwrap_or_else(|| format!("{}/vm/index.sqlite", &argv[3]));
+
+        let headers_conn = sqlite_open(&headers_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)
+            .unwrap_or_else(|e| panic!("Failed to open {}: {:?}", &headers_path, &e));
+        let db_config = StacksChainState::load_db_config(&headers_conn)
+            .unwrap_or_else(|e| panic!("Failed to load chainstate config: {:?}", &e));
+        let sort_db = argv.get(5).map(|sort_db_path| {
+            SortitionDB::open(sort_db_path, false, PoxConstants::mainnet_default())
+                .unwrap_or_else(|e| panic!("Failed to open {}: {:?}", sort_db_path, &e))
+        });
+        let sort_db_conn = sort_db.as_ref().map(|sort_db| sort_db.index_conn());
+        let burn_state_db: &dyn BurnStateDB = match sort_db_conn.as_ref() {
+            Some(sort_db_conn) => sort_db_conn,
+            None => &NULL_BURN_STATE_DB,
+        };
+        let mut snapshot_file = BufReader::new(
+            File::open(snapshot_path)
+                .unwrap_or_else(|e| panic!("Failed to open {}: {:?}", snapshot_path, &e)),
+        );
+
+        if fs::metadata(&marf_path).is_ok() {
+            eprintln!("Refusing to overwrite existing MARF {}", &marf_path);
+            process::exit(1);
+        }
+        fs::create_dir_all(&marf_dir)
+            .unwrap_or_else(|e| panic!("Failed to create {}: {:?}", &marf_dir, &e));
+
+        let result = snapshot::import_marf_snapshot(
+            &marf_path,
+            &mut snapshot_file,
+            db_config.mainnet,
+            db_config.chain_id,
+            &HeadersDBConn(&headers_conn),
+            burn_state_db,
+            |block_id| {
+                let header = NakamotoChainState::get_block_header(&headers_conn, block_id)
+                    .map_err(|e| {
+                        snapshot::Error::Malformed(format!("header DB error: {:?}", &e))
+                    })?;
+                Ok(header.map(|header| header.anchored_header.state_index_root()))
+            },
+        );
+        match result {
+            Ok(summary) => {
+                println!(
+                    "Imported and verified {} tries, {} data values and {} metadata rows at {} (root hash {})",
+                    summary.header.num_tries,
+                    summary.num_data,
+                    summary.num_metadata,
+                    &summary.header.tip,
+                    &summary.header.tip_root_hash
+                );
+            }
+            Err(e) => {
+                eprintln!("Failed to import MARF snapshot: {}", &e);
+                for suffix in ["", ".blobs", "-wal", "-shm"] {
+                    let _ = fs::remove_file(format!("{}{}", &marf_path, suffix));
+                }
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "verify-proof" {
+        if argv.len() < 7 {
+            eprintln!(
+                "Usage: {} verify-proof CHAINSTATE_DIR INDEX_BLOCK_HASH KEY VALUE PROOF",
+                argv[0]
+            );
+            eprintln!("       Checks a MARF proof returned by a node's RPC interface (e.g. with ?proof=1), which");
+            eprintln!("       shows that the Clarity MARF key KEY had the hex-encoded VALUE as of INDEX_BLOCK_HASH,");
+            eprintln!("       against the state roots in CHAINSTATE_DIR/vm/index.sqlite");
+            process::exit(1);
+        }
+        let headers_path = format!("{}/vm/index.sqlite", &argv[2]);
+        let tip = StacksBlockId::from_hex(&argv[3]).expect("Bad index block hash");
+        let key = &argv[4];
+        let value = argv[5].strip_prefix("0x").unwrap_or(&argv[5]);
+        let proof = TrieMerkleProof::<StacksBlockId>::from_hex(&argv[6]).unwrap_or_else(|e| {
+            eprintln!("Malformed proof: {:?}", &e);
+            process::exit(1);
+        });
+
+        let headers_conn = sqlite_open(&headers_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)
+            .unwrap_or_else(|e| panic!("Failed to open {}: {:?}", &headers_path, &e));
+        let headers = TrustedHeader::load_all(&headers_conn)
+            .unwrap_or_else(|e| panic!("Failed to load headers: {:?}", &e));
+        let Some(root_hash) = headers
+            .iter()
+            .find(|header| header.index_block_hash == tip)
+            .map(|header| header.state_index_root.clone())
+        else {
+            eprintln!("No such block {}", &tip);
+            process::exit(1);
+        };
+        let root_to_block = TrustedHeader::root_to_block_map(&headers);
+
+        if proof.verify_entry(key, value, &root_hash, &root_to_block) {
+            println!(
+                "Valid: {} = {} at {} (root hash {})",
+                key, value, &tip, &root_hash
+            );
+            process::exit(0);
+        } else {
+            println!("Invalid proof for {} = {} at {}", key, value, &tip);
+            process::exit(1);
+        }
+    }
+
     if argv[1] == "get-ancestors" {
         let path = &argv[2];
         let tip = BlockHeaderHash::from_hex(&argv[3]).unwrap();
@@ -873,6 +1043,14 @@ simulating a miner.
     }
 
     if argv[1] == "replay-block" {
+        let profile_prefix = match argv.iter().position(|arg| arg == "--profile") {
+            Some(i) if i + 1 < argv.len() => {
+                let prefix = argv.remove(i + 1);
+                argv.remove(i);
+                Some(prefix)
+            }
+            _ => None,
+        };
         let print_help_and_exit = || -> ! {
             let n = &argv[0];
             eprintln!("Usage:");
@@ -880,6 +1058,17 @@ simulating a miner.
             eprintln!("  {n} <chainstate_path> prefix <index-block-hash-prefix>");
             eprintln!("  {n} <chainstate_path> range <start_block> <end_block>");
             eprintln!("  {n} <chainstate_path> <first|last> <block_count>");
+            eprintln!(
+                "Pass --profile <output-prefix> to write the costs of each Clarity function and"
+            );
+            eprintln!(
+                "expression in the replayed blocks to <output-prefix>.json, and folded stacks"
+            );
+            eprintln!("for flamegraph tools to <output-prefix>.<cost-dimension>.folded.");
+            eprintln!(
+                "Deployed contracts carry no source positions, so expression costs are only"
+            );
+            eprintln!("listed for code parsed in this process by a developer-mode build.");
             process::exit(1);
         };
         if argv.len() < 2 {
@@ -928,12 +1117,20 @@ simulating a miner.
         }
 
         let total = index_block_hashes.len();
+        let mut profiler = profile_prefix.as_ref().map(|_| ExecutionProfiler::new());
         println!("Will check {total} blocks");
         for (i, index_block_hash) in index_block_hashes.iter().enumerate() {
             if i % 100 == 0 {
                 println!("Checked {i}...");
             }
-            replay_block(stacks_path, index_block_hash);
+            replay_block(stacks_path, index_block_hash, &mut profiler);
+        }
+        if let (Some(prefix), Some(profiler)) = (profile_prefix, profiler) {
+            if let Err(e) = profiler.write_files(&prefix) {
+                eprintln!("Failed to write profile to {prefix}.*: {e}");
+                process::exit(1);
+            }
+            println!("Profile written to {prefix}.json");
         }
         println!("Finished!");
         process::exit(0);
@@ -1570,7 +1767,13 @@ simulating a miner.
     process::exit(0);
 }
 
-fn replay_block(stacks_path: &str, index_block_hash_hex: &str) {
+/// Replay the block `index_block_hash_hex`, without committing it. If a profiler is given, the
+/// block's transactions are profiled with it.
+fn replay_block(
+    stacks_path: &str,
+    index_block_hash_hex: &str,
+    profiler: &mut Option<ExecutionProfiler>,
+) {
     let index_block_hash = StacksBlockId::from_hex(index_block_hash_hex).unwrap();
     let chain_state_path = format!("{stacks_path}/mainnet/chainstate/");
     let sort_db_path = format!("{stacks_path}/mainnet/burnchain/sortition");
@@ -1715,7 +1918,10 @@ fn replay_block(stacks_path: &str, index_block_hash_hex: &str) {
 
     let pox_constants = sort_tx.context.pox_constants.clone();
 
-    match StacksChainState::append_block(
+    if let Some(profiler) = profiler.take() {
+        clarity_instance.set_profiler(profiler);
+    }
+    let result = StacksChainState::append_block(
         &mut chainstate_tx,
         clarity_instance,
         &mut sort_tx,
@@ -1732,8 +1938,11 @@ fn replay_block(stacks_path: &str, index_block_hash_hex: &str) {
         next_staging_block.sortition_burn,
         block_am.weight(),
         true,
-    ) {
-        Ok((_receipt, _, _)) => {
+    )
+    .map(|_| ());
+    *profiler = clarity_instance.take_profiler();
+    match result {
+        Ok(()) => {
             info!("Block processed successfully! block = {index_block_hash}");
         }
         Err(e) => {
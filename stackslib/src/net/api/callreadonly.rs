@@ -22,12 +22,12 @@ use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::costs::{ExecutionCost, LimitedCostTracker};
 use clarity::vm::database::{ClarityDatabase, STXBalance, StoreType};
 use clarity::vm::errors::Error::Unchecked;
-use clarity::vm::errors::{Error as ClarityRuntimeError, InterpreterError};
+use clarity::vm::errors::{Error as ClarityRuntimeError, InterpreterError, RuntimeErrorType};
 use clarity::vm::representations::{
     CONTRACT_NAME_REGEX_STRING, PRINCIPAL_DATA_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING,
 };
 use clarity::vm::types::{
-    PrincipalData, QualifiedContractIdentifier, StandardPrincipalData,
+    FunctionType, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData,
     BOUND_VALUE_SERIALIZATION_HEX,
 };
 use clarity::vm::{ClarityName, ClarityVersion, ContractName, SymbolicExpression, Value};
@@ -41,6 +41,7 @@ use crate::burnchains::Burnchain;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
+use crate::clarity_vm::clarity::ClarityReadOnlyConnection;
 use crate::core::mempool::MemPoolDB;
 use crate::net::http::{
     parse_json, Error, HttpBadRequest, HttpContentType, HttpNotFound, HttpRequest,
@@ -61,7 +62,14 @@ pub struct CallReadOnlyRequestBody {
     pub sender: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sponsor: Option<String>,
+    /// Hex-encoded consensus serializations of the arguments
+    #[serde(default)]
     pub arguments: Vec<String>,
+    /// The arguments in the canonical JSON representation of Clarity values, typed by the
+    /// signature of the function. When given, the result is also returned as JSON.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments_json: Option<Vec<serde_json::Value>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -73,6 +81,9 @@ pub struct CallReadOnlyResponse {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cause: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_json: Option<serde_json::Value>,
 }
 
 #[derive(Clone)]
@@ -86,6 +97,7 @@ pub struct RPCCallReadOnlyRequestHandler {
     pub sender: Option<PrincipalData>,
     pub sponsor: Option<PrincipalData>,
     pub arguments: Option<Vec<Value>>,
+    pub arguments_json: Option<Vec<serde_json::Value>>,
 }
 
 impl RPCCallReadOnlyRequestHandler {
@@ -98,6 +110,7 @@ impl RPCCallReadOnlyRequestHandler {
             sender: None,
             sponsor: None,
             arguments: None,
+            arguments_json: None,
         }
     }
 }
@@ -167,16 +180,70 @@ impl HttpRequest for RPCCallReadOnlyRequestHandler {
             .collect::<Option<Vec<Value>>>()
             .ok_or_else(|| Error::DecodeError("Failed to deserialize argument value".into()))?;
 
+        // JSON arguments are decoded once the function's signature is known
+        if body.arguments_json.is_some() && !arguments.is_empty() {
+            return Err(Error::DecodeError(
+                "Invalid request: both `arguments` and `arguments_json` were given".into(),
+            ));
+        }
+
         self.contract_identifier = Some(contract_identifier);
         self.function = Some(function);
         self.sender = Some(sender);
         self.sponsor = sponsor;
         self.arguments = Some(arguments);
+        self.arguments_json = body.arguments_json;
 
         Ok(HttpRequestContents::new().query_string(query))
     }
 }
 
+/// Decode the JSON arguments of a call to `function`, typed by its signature
+fn decode_json_arguments(
+    clarity_tx: &mut ClarityReadOnlyConnection,
+    contract_identifier: &QualifiedContractIdentifier,
+    function: &ClarityName,
+    arguments_json: &[serde_json::Value],
+) -> Result<Vec<Value>, ClarityRuntimeError> {
+    let epoch = clarity_tx.get_epoch();
+    let function_type = clarity_tx
+        .with_analysis_db_readonly(|analysis_db| {
+            match analysis_db.get_read_only_function_type(
+                contract_identifier,
+                function.as_str(),
+                &epoch,
+            )? {
+                Some(function_type) => Ok(Some(function_type)),
+                None => analysis_db.get_public_function_type(
+                    contract_identifier,
+                    function.as_str(),
+                    &epoch,
+                ),
+            }
+        })
+        .map_err(|e| ClarityRuntimeError::from(e.err))?;
+    let Some(FunctionType::Fixed(function_type)) = function_type else {
+        return Err(CheckErrors::UndefinedFunction(function.to_string()).into());
+    };
+    if function_type.args.len() != arguments_json.len() {
+        return Err(CheckErrors::IncorrectArgumentCount(
+            function_type.args.len(),
+            arguments_json.len(),
+        )
+        .into());
+    }
+    function_type
+        .args
+        .iter()
+        .zip(arguments_json.iter())
+        .map(|(arg, json)| {
+            Value::try_deserialize_json(json, &arg.signature).map_err(|e| {
+                RuntimeErrorType::ParseError(format!("argument `{}`: {}", arg.name, e)).into()
+            })
+        })
+        .collect()
+}
+
 /// Handle the HTTP request
 impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
     /// Reset internal state
@@ -186,6 +253,7 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
         self.sender = None;
         self.sponsor = None;
         self.arguments = None;
+        self.arguments_json = None;
     }
 
     /// Make the response
@@ -219,15 +287,12 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
             .arguments
             .take()
             .ok_or(NetError::SendError("Missing `arguments`".into()))?;
+        let arguments_json = self.arguments_json.take();
+        let json_result = arguments_json.is_some();
 
         // run the read-only call
         let data_resp =
             node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
-                let args: Vec<_> = arguments
-                    .iter()
-                    .map(|x| SymbolicExpression::atom_value(x.clone()))
-                    .collect();
-
                 let mainnet = chainstate.mainnet;
                 let chain_id = chainstate.chain_id;
                 let mut cost_limit = self.read_only_call_limit.clone();
@@ -257,6 +322,20 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                             )))
                         })?;
 
+                    let arguments = match arguments_json {
+                        Some(arguments_json) => decode_json_arguments(
+                            clarity_tx,
+                            &contract_identifier,
+                            &function,
+                            &arguments_json,
+                        )?,
+                        None => arguments,
+                    };
+                    let args: Vec<_> = arguments
+                        .into_iter()
+                        .map(SymbolicExpression::atom_value)
+                        .collect();
+
                     clarity_tx.with_readonly_clarity_env(
                         mainnet,
                         chain_id,
@@ -293,6 +372,7 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                     okay: true,
                     result: Some(format!("0x{}", hex_result)),
                     cause: None,
+                    result_json: json_result.then(|| data.serialize_to_json()),
                 }
             }
             Ok(Some(Err(e))) => match e {
@@ -303,12 +383,14 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                         okay: false,
                         result: None,
                         cause: Some("NotReadOnly".to_string()),
+                        result_json: None,
                     }
                 }
                 _ => CallReadOnlyResponse {
                     okay: false,
                     result: None,
                     cause: Some(e.to_string()),
+                    result_json: None,
                 },
             },
             Ok(None) | Err(_) => {
@@ -364,6 +446,7 @@ impl StacksHttpRequest {
                     sender: sender.to_string(),
                     sponsor: sponsor.map(|s| s.to_string()),
                     arguments: function_args.into_iter().map(|v| v.to_string()).collect(),
+                    arguments_json: None,
                 })
                 .expect("FATAL: failed to encode infallible data"),
             ),
@@ -26,12 +26,13 @@ use stacks_common::types::net::PeerHost;
 use stacks_common::util::hash::to_hex;
 use {serde, serde_json};
 
+use crate::chainstate::stacks::db::prune::BlockDataPin;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::{Error as ChainError, StacksBlock};
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -62,12 +63,15 @@ pub struct StacksBlockStream {
 
     /// connection to the underlying chainstate
     blocks_path: String,
+    /// keeps the block file from being pruned while it is streamed
+    _pin: BlockDataPin,
 }
 
 impl StacksBlockStream {
     pub fn new(chainstate: &StacksChainState, block: &StacksBlockId) -> Result<Self, ChainError> {
         let _ = StacksChainState::load_staging_block_info(chainstate.db(), block)?
             .ok_or(ChainError::NoSuchBlockError)?;
+        let pin = chainstate.pin_block(block)?;
 
         let blocks_path = chainstate.blocks_path.clone();
 
@@ -76,6 +80,7 @@ impl StacksBlockStream {
             offset: 0,
             total_bytes: 0,
             blocks_path,
+            _pin: pin,
         })
     }
 }
@@ -158,6 +163,14 @@ impl RPCRequestHandler for RPCBlocksRequestHandler {
                 .try_into_contents()
                 .map_err(NetError::from)
             }
+            Err(ChainError::PrunedError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!("Block {:?} has been pruned\n", &block_id)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(e) => {
                 // nope -- error trying to check
                 let msg = format!("Failed to load block: {:?}\n", &e);
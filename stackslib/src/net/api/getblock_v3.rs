@@ -27,12 +27,13 @@ use stacks_common::util::hash::to_hex;
 use {serde, serde_json};
 
 use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoChainState, NakamotoStagingBlocksConn};
+use crate::chainstate::stacks::db::prune::BlockDataPin;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -67,6 +68,8 @@ pub struct NakamotoBlockStream {
     pub staging_db_conn: NakamotoStagingBlocksConn,
     /// rowid of the block
     pub rowid: i64,
+    /// keeps the block's body from being pruned while it is streamed
+    _pin: BlockDataPin,
 }
 
 impl NakamotoBlockStream {
@@ -82,6 +85,7 @@ impl NakamotoBlockStream {
             .conn()
             .get_nakamoto_block_rowid(&block_id)?
             .ok_or(ChainError::NoSuchBlockError)?;
+        let pin = chainstate.pin_nakamoto_block(&block_id)?;
 
         Ok(NakamotoBlockStream {
             index_block_hash: block_id,
@@ -91,15 +95,17 @@ impl NakamotoBlockStream {
             total_bytes: 0,
             staging_db_conn: db_conn,
             rowid,
+            _pin: pin,
         })
     }
 
-    /// reset the stream to send another block.
+    /// reset the stream to send another block, which the caller has pinned.
     /// Does not change the DB connection or consensus hash.
     pub fn reset(
         &mut self,
         block_id: StacksBlockId,
         parent_block_id: StacksBlockId,
+        pin: BlockDataPin,
     ) -> Result<(), ChainError> {
         let rowid = self
             .staging_db_conn
@@ -112,6 +118,7 @@ impl NakamotoBlockStream {
         self.offset = 0;
         self.total_bytes = 0;
         self.rowid = rowid;
+        self._pin = pin;
         Ok(())
     }
 }
@@ -208,6 +215,14 @@ impl RPCRequestHandler for RPCNakamotoBlockRequestHandler {
                 .try_into_contents()
                 .map_err(NetError::from)
             }
+            Err(ChainError::PrunedError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!("Block {} has been pruned\n", &block_id)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(e) => {
                 // nope -- error trying to check
                 let msg = format!("Failed to load block {}: {:?}\n", &block_id, &e);
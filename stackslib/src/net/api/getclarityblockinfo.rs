@@ -0,0 +1,260 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::database::HeadersDB;
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::{
+    BlockHeaderHash, BurnchainHeaderHash, ConsensusHash, StacksBlockId, VRFSeed,
+};
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::Error as ChainError;
+use crate::clarity_vm::database::HeadersDBConn;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// What a Clarity `HeadersDB` knows about a block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityBlockInfoResponse {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub block_hash: BlockHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub burn_block_height: u32,
+    pub burn_block_time: u64,
+    pub vrf_seed: Option<VRFSeed>,
+    /// c32-encoded address of the block's miner
+    pub miner_address: Option<String>,
+    pub burnchain_tokens_spent: Option<u128>,
+    pub burnchain_tokens_spent_for_winning_block: Option<u128>,
+    pub tokens_earned: Option<u128>,
+}
+
+/// The block a request asks about
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClarityBlockSelector {
+    /// The block with this index block hash
+    BlockId(StacksBlockId),
+    /// The block at this height in the fork of the request's tip
+    Height(u32),
+}
+
+#[derive(Clone)]
+pub struct RPCGetClarityBlockInfoRequestHandler {
+    pub block: Option<ClarityBlockSelector>,
+}
+impl RPCGetClarityBlockInfoRequestHandler {
+    pub fn new() -> Self {
+        Self { block: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetClarityBlockInfoRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(
+            r#"^/v3/clarity/block_info/((?P<block_id>[0-9a-f]{64})|height/(?P<block_height>[0-9]{1,10}))$"#,
+        )
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/clarity/block_info/:block"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let block = if captures.name("block_id").is_some() {
+            ClarityBlockSelector::BlockId(request::get_block_hash(captures, "block_id")?)
+        } else {
+            ClarityBlockSelector::Height(request::get_u32(captures, "block_height")?)
+        };
+        self.block = Some(block);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCGetClarityBlockInfoRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.block = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let block = self
+            .block
+            .take()
+            .ok_or(NetError::SendError("`block` not set".into()))?;
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let info_res =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                let block_id = match block {
+                    ClarityBlockSelector::BlockId(ref block_id) => block_id.clone(),
+                    ClarityBlockSelector::Height(block_height) => {
+                        let Some(block_id) = chainstate
+                            .index_conn()?
+                            .get_ancestor_block_hash(block_height.into(), &tip)?
+                        else {
+                            return Err(ChainError::NoSuchBlockError);
+                        };
+                        block_id
+                    }
+                };
+                let Some(header) =
+                    NakamotoChainState::get_block_header(chainstate.db(), &block_id)?
+                else {
+                    return Err(ChainError::NoSuchBlockError);
+                };
+
+                let headers_db = HeadersDBConn(chainstate.db());
+                Ok(ClarityBlockInfoResponse {
+                    block_height: header.stacks_block_height,
+                    block_hash: header.anchored_header.block_hash(),
+                    consensus_hash: header.consensus_hash,
+                    burn_header_hash: header.burn_header_hash,
+                    burn_block_height: header.burn_header_height,
+                    burn_block_time: header.burn_header_timestamp,
+                    vrf_seed: headers_db.get_vrf_seed_for_block(&block_id),
+                    miner_address: headers_db
+                        .get_miner_address(&block_id)
+                        .map(|addr| addr.to_string()),
+                    burnchain_tokens_spent: headers_db
+                        .get_burnchain_tokens_spent_for_block(&block_id),
+                    burnchain_tokens_spent_for_winning_block: headers_db
+                        .get_burnchain_tokens_spent_for_winning_block(&block_id),
+                    tokens_earned: headers_db.get_tokens_earned_for_block(&block_id),
+                    index_block_hash: block_id,
+                })
+            });
+
+        let info = match info_res {
+            Ok(info) => info,
+            Err(ChainError::NoSuchBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!(
+                        "No such block {:?} in the fork of {}\n",
+                        &block, &tip
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                // nope -- error trying to check
+                let msg = format!("Failed to load block info for {:?}: {:?}\n", &block, &e);
+                warn!("{}", &msg);
+                return StacksHttpResponse::new_error(&preamble, &HttpServerError::new(msg))
+                    .try_into_contents()
+                    .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&info)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetClarityBlockInfoRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let block_info: ClarityBlockInfoResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(block_info)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for what Clarity knows about a block.  A block given by height is
+    /// looked up in the fork of the tip.
+    pub fn new_getclarityblockinfo(
+        host: PeerHost,
+        block: ClarityBlockSelector,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        let path = match block {
+            ClarityBlockSelector::BlockId(block_id) => {
+                format!("/v3/clarity/block_info/{}", &block_id)
+            }
+            ClarityBlockSelector::Height(block_height) => {
+                format!("/v3/clarity/block_info/height/{}", block_height)
+            }
+        };
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            path,
+            HttpRequestContents::new().for_tip(tip_req),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_clarity_block_info_response(self) -> Result<ClarityBlockInfoResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: ClarityBlockInfoResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,225 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::types::BOUND_VALUE_SERIALIZATION_HEX;
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::to_hex;
+
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// Longest key a request may ask for.  Keys are at most a map key's hex serialization plus a
+/// prefix naming the contract and map.
+pub const CLARITY_MARF_KEY_MAX_LEN: u32 = BOUND_VALUE_SERIALIZATION_HEX + 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityMarfValueResponse {
+    /// The value exactly as the Clarity backing store holds it
+    pub data: String,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marf_proof: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct RPCGetClarityMarfValueRequestHandler {
+    pub key: Option<String>,
+}
+impl RPCGetClarityMarfValueRequestHandler {
+    pub fn new() -> Self {
+        Self { key: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetClarityMarfValueRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/clarity/marf$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/clarity/marf"
+    }
+
+    /// Try to decode this request.
+    /// The body must be the key to look up, encoded as a JSON string.
+    /// So, something like `"vm::SP000000000000000000002Q6VF78.pox-4::1::first-burnchain-block-height"`.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < CLARITY_MARF_KEY_MAX_LEN) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for GetClarityMarfValue ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".into(),
+            ));
+        }
+
+        let mut body_ptr = body;
+        let key: String = serde_json::from_reader(&mut body_ptr)
+            .map_err(|_e| Error::DecodeError("Failed to parse JSON body".into()))?;
+
+        self.key = Some(key);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCGetClarityMarfValueRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.key = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let key = self
+            .key
+            .take()
+            .ok_or(NetError::SendError("`key` not set".into()))?;
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+        let with_proof = contents.get_with_proof();
+
+        let data_resp =
+            node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+                chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip, |clarity_tx| {
+                    clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                        if with_proof {
+                            let (data, proof) = clarity_db
+                                .get_data_with_proof::<String>(&key)
+                                .ok()
+                                .flatten()?;
+                            Some(ClarityMarfValueResponse {
+                                data,
+                                marf_proof: Some(format!("0x{}", to_hex(&proof))),
+                            })
+                        } else {
+                            let data = clarity_db.get_data::<String>(&key).ok().flatten()?;
+                            Some(ClarityMarfValueResponse {
+                                data,
+                                marf_proof: None,
+                            })
+                        }
+                    })
+                })
+            });
+
+        let data_resp = match data_resp {
+            Ok(Some(Some(data))) => data,
+            Ok(Some(None)) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Key not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Ok(None) | Err(_) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Chain tip not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetClarityMarfValueRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let marf_value: ClarityMarfValueResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(marf_value)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the value the Clarity backing store holds for `key`
+    pub fn new_getclaritymarfvalue(
+        host: PeerHost,
+        key: String,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v3/clarity/marf".into(),
+            HttpRequestContents::new()
+                .for_tip(tip_req)
+                .query_arg("proof".into(), if with_proof { "1" } else { "0" }.into())
+                .payload_json(serde_json::Value::String(key)),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_clarity_marf_value_response(self) -> Result<ClarityMarfValueResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: ClarityMarfValueResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
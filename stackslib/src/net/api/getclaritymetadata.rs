@@ -0,0 +1,250 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::representations::{CONTRACT_NAME_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ContractName;
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::types::net::PeerHost;
+
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// Longest metadata key a request may ask for
+pub const CLARITY_METADATA_KEY_MAX_LEN: u32 = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityMetadataResponse {
+    /// The metadata entry exactly as the Clarity backing store holds it
+    pub data: String,
+}
+
+#[derive(Clone)]
+pub struct RPCGetClarityMetadataRequestHandler {
+    pub contract_identifier: Option<QualifiedContractIdentifier>,
+    pub key: Option<String>,
+    pub at_height: Option<u32>,
+}
+impl RPCGetClarityMetadataRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            contract_identifier: None,
+            key: None,
+            at_height: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetClarityMetadataRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(&format!(
+            "^/v3/clarity/metadata/(?P<address>{})/(?P<contract>{})$",
+            *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
+        ))
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/clarity/metadata/:principal/:contract_name"
+    }
+
+    /// Try to decode this request.
+    /// The body must be the metadata key to look up, encoded as a JSON string, like
+    /// `"vm-metadata::9::contract-size"`.  If a `height=` query parameter is given, the entry is
+    /// the one stored in the block at that height, rather than in the contract's own block.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < CLARITY_METADATA_KEY_MAX_LEN) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for GetClarityMetadata ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".into(),
+            ));
+        }
+
+        let contract_identifier = request::get_contract_address(captures, "address", "contract")?;
+
+        let mut body_ptr = body;
+        let key: String = serde_json::from_reader(&mut body_ptr)
+            .map_err(|_e| Error::DecodeError("Failed to parse JSON body".into()))?;
+
+        let contents = HttpRequestContents::new().query_string(query);
+        let at_height = match contents.get_query_arg("height") {
+            Some(height_str) => Some(height_str.parse::<u32>().map_err(|_| {
+                Error::DecodeError("Failed to parse height= query parameter".to_string())
+            })?),
+            None => None,
+        };
+
+        self.contract_identifier = Some(contract_identifier);
+        self.key = Some(key);
+        self.at_height = at_height;
+
+        Ok(contents)
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCGetClarityMetadataRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.contract_identifier = None;
+        self.key = None;
+        self.at_height = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let contract_identifier = self
+            .contract_identifier
+            .take()
+            .ok_or(NetError::SendError("`contract_identifier` not set".into()))?;
+        let key = self
+            .key
+            .take()
+            .ok_or(NetError::SendError("`key` not set".into()))?;
+        let at_height = self.at_height.take();
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let data_resp =
+            node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+                chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip, |clarity_tx| {
+                    clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                        let data = match at_height {
+                            Some(height) => clarity_db.store.get_metadata_manual(
+                                height,
+                                &contract_identifier,
+                                &key,
+                            ),
+                            None => clarity_db.store.get_metadata(&contract_identifier, &key),
+                        }
+                        .ok()
+                        .flatten()?;
+                        Some(ClarityMetadataResponse { data })
+                    })
+                })
+            });
+
+        let data_resp = match data_resp {
+            Ok(Some(Some(data))) => data,
+            Ok(Some(None)) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Metadata not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Ok(None) | Err(_) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Chain tip not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetClarityMetadataRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let metadata: ClarityMetadataResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(metadata)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a contract's metadata entry.  If `at_height` is given, the entry
+    /// is the one stored in the block at that height in the fork of the tip.
+    pub fn new_getclaritymetadata(
+        host: PeerHost,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        key: String,
+        at_height: Option<u32>,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new().for_tip(tip_req);
+        if let Some(height) = at_height {
+            contents = contents.query_arg("height".into(), height.to_string());
+        }
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            format!("/v3/clarity/metadata/{}/{}", &contract_addr, &contract_name),
+            contents.payload_json(serde_json::Value::String(key)),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_clarity_metadata_response(self) -> Result<ClarityMetadataResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: ClarityMetadataResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::analysis::CheckErrors;
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::database::{ClarityDatabase, StoreType};
+use clarity::vm::errors::{Error as ClarityError, InterpreterError};
+use clarity::vm::representations::{CONTRACT_NAME_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ContractName;
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::types::net::PeerHost;
+
+use crate::net::api::getmapentries::clarity_state_not_found;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataVarEntry {
+    pub name: String,
+    /// Hex-encoded serialized value
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataVarsResponse {
+    /// The contract's data vars, ordered by name
+    pub vars: Vec<DataVarEntry>,
+}
+
+#[derive(Clone)]
+pub struct RPCGetDataVarsRequestHandler {
+    pub contract_identifier: Option<QualifiedContractIdentifier>,
+}
+impl RPCGetDataVarsRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            contract_identifier: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetDataVarsRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(&format!(
+            "^/v3/contracts/(?P<address>{})/(?P<contract>{})/vars$",
+            *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
+        ))
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/contracts/:principal/:contract_name/vars"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_identifier = request::get_contract_address(captures, "address", "contract")?;
+        self.contract_identifier = Some(contract_identifier);
+
+        let contents = HttpRequestContents::new().query_string(query);
+        Ok(contents)
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCGetDataVarsRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.contract_identifier = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let contract_identifier = self.contract_identifier.take().ok_or(NetError::SendError(
+            "`contract_identifier` not set".to_string(),
+        ))?;
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let data_resp =
+            node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+                let vars_res = chainstate.maybe_read_only_clarity_tx(
+                    &sortdb.index_conn(),
+                    &tip,
+                    |clarity_tx| {
+                        clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                            let contract = match clarity_db.get_contract(&contract_identifier) {
+                                Ok(contract) => contract,
+                                Err(ClarityError::Unchecked(CheckErrors::NoSuchContract(_))) => {
+                                    return Ok(None);
+                                }
+                                Err(e) => return Err(e),
+                            };
+                            let mut var_names: Vec<_> =
+                                contract.contract_context.meta_data_var.keys().collect();
+                            var_names.sort();
+
+                            let mut vars = vec![];
+                            for var_name in var_names.into_iter() {
+                                let key = ClarityDatabase::make_key_for_trip(
+                                    &contract_identifier,
+                                    StoreType::Variable,
+                                    var_name,
+                                );
+                                let value_hex: String =
+                                    clarity_db.get_data(&key)?.ok_or_else(|| {
+                                        InterpreterError::Expect(format!(
+                                            "No value for data var {}",
+                                            var_name
+                                        ))
+                                    })?;
+                                vars.push(DataVarEntry {
+                                    name: var_name.to_string(),
+                                    value: format!("0x{}", value_hex),
+                                });
+                            }
+                            Ok(Some(DataVarsResponse { vars }))
+                        })
+                    },
+                );
+                match vars_res {
+                    Ok(Some(Ok(Some(data)))) => Ok(data),
+                    Ok(Some(Ok(None))) => Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpNotFound::new("Contract not found".to_string()),
+                    )),
+                    Ok(None) => Err(clarity_state_not_found(&preamble, chainstate, &tip)),
+                    Ok(Some(Err(e))) => Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpServerError::new(format!("Failed to load data vars: {:?}", &e)),
+                    )),
+                    Err(e) => Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpServerError::new(format!("Failed to load chain tip: {:?}", &e)),
+                    )),
+                }
+            });
+
+        let data_resp = match data_resp {
+            Ok(data) => data,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetDataVarsRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let datavars: DataVarsResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(datavars)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for all of a contract's data vars
+    pub fn new_getdatavars(
+        host: PeerHost,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v3/contracts/{}/{}/vars", &contract_addr, &contract_name),
+            HttpRequestContents::new().for_tip(tip_req),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_data_vars_response(self) -> Result<DataVarsResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: DataVarsResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,785 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clarity::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier};
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+
+use crate::net::http::{
+    parse_json, Error, HttpBadRequest, HttpChunkGenerator, HttpGone, HttpNotFound, HttpRequest,
+    HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
+    HttpResponsePayload, HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Event observer paths whose payloads can be streamed
+pub const EVENT_STREAM_NEW_BLOCK: &str = "new_block";
+pub const EVENT_STREAM_NEW_BURN_BLOCK: &str = "new_burn_block";
+pub const EVENT_STREAM_NEW_MEMPOOL_TX: &str = "new_mempool_tx";
+pub const EVENT_STREAM_STACKERDB_CHUNKS: &str = "stackerdb_chunks";
+
+/// Most events sent in a single response. If there are more, the response says so and the
+/// client should poll again right away.
+pub const EVENT_POLL_MAX_EVENTS: usize = 32;
+
+/// Longest a client may wait for new events with `wait=`.  This is well below the HTTP
+/// server's request timeout, so a waiting connection isn't closed as unresponsive.
+pub const EVENT_POLL_MAX_WAIT_SECS: u64 = 10;
+
+/// An event observer payload, as buffered by an `EventStreamBuffer`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamEvent {
+    /// sequence number of this event, which increases with each buffered event
+    pub seq: u64,
+    /// height of the Stacks chain tip when this event happened
+    pub block_height: u64,
+    /// the event observer path this payload would be sent to (e.g. `new_block`)
+    pub path: String,
+    pub payload: serde_json::Value,
+}
+
+impl StreamEvent {
+    /// The ID of this event, from which a client can resume polling
+    pub fn event_id(&self) -> String {
+        format!("{}:{}", self.block_height, self.seq)
+    }
+}
+
+/// One response's worth of events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventPollResponse {
+    pub events: Vec<StreamEvent>,
+    /// whether or not more events are already waiting after these
+    pub has_more: bool,
+    /// the cursor from which to poll next, if any events were examined. It can be past the
+    /// last of `events`, if the events after that were filtered out.
+    pub next_cursor: Option<String>,
+}
+
+/// Why the buffer can't serve events from a cursor
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamError {
+    /// Some events after the cursor have already been dropped from the buffer, or were never
+    /// buffered (e.g. they happened before the node started)
+    CursorExpired(String),
+    /// The cursor refers to an event that hasn't happened yet, e.g. because it came from
+    /// before the node restarted
+    FutureCursor(String),
+}
+
+#[derive(Debug)]
+struct EventStreamState {
+    events: VecDeque<StreamEvent>,
+    capacity: usize,
+    next_seq: u64,
+    block_height: u64,
+    /// height of the Stacks chain tip when this buffer started
+    start_height: u64,
+    /// height of the most recently dropped event, if any have been dropped
+    dropped_height: Option<u64>,
+    /// whether or not a `new_block` payload has been buffered yet
+    saw_new_block: bool,
+}
+
+/// A bounded, in-memory buffer of the most recent event observer payloads, which RPC clients
+/// read via `GET /v3/events/stream`, or poll via `GET /v3/events/poll`. Once full, the oldest events are dropped, and clients whose
+/// cursors point at or before them are told that their cursors have expired.
+#[derive(Debug, Clone)]
+pub struct EventStreamBuffer {
+    state: Arc<Mutex<EventStreamState>>,
+}
+
+impl EventStreamBuffer {
+    /// Make a buffer which retains the `capacity` most recent events
+    pub fn new(capacity: usize) -> EventStreamBuffer {
+        EventStreamBuffer {
+            state: Arc::new(Mutex::new(EventStreamState {
+                events: VecDeque::new(),
+                capacity,
+                next_seq: 0,
+                block_height: 0,
+                start_height: 0,
+                dropped_height: None,
+                saw_new_block: false,
+            })),
+        }
+    }
+
+    /// Set the Stacks chain tip height at which this buffer starts, i.e. the height of the
+    /// chainstate when the node boots. Events buffered before the first `new_block` payload
+    /// are recorded at this height, and polls from below it are reported as expired.
+    /// Does nothing once a `new_block` payload has been buffered.
+    pub fn seed_block_height(&self, block_height: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            warn!("Event stream buffer lock is poisoned");
+            return;
+        };
+        if state.saw_new_block {
+            return;
+        }
+        state.block_height = block_height;
+        state.start_height = block_height;
+        for event in state.events.iter_mut() {
+            event.block_height = block_height;
+        }
+    }
+
+    /// Can payloads sent to this event observer path be streamed?
+    pub fn is_streamed_path(path: &str) -> bool {
+        path == EVENT_STREAM_NEW_BLOCK
+            || path == EVENT_STREAM_NEW_BURN_BLOCK
+            || path == EVENT_STREAM_NEW_MEMPOOL_TX
+            || path == EVENT_STREAM_STACKERDB_CHUNKS
+    }
+
+    /// Buffer a payload which was sent to the given event observer path.
+    /// Payloads for paths which are not streamed are ignored.
+    /// A `new_block` payload advances the height at which subsequent events are recorded.
+    pub fn push(&self, path: &str, payload: &serde_json::Value) {
+        if !Self::is_streamed_path(path) {
+            return;
+        }
+        let Ok(mut state) = self.state.lock() else {
+            warn!("Event stream buffer lock is poisoned; dropping event");
+            return;
+        };
+        if path == EVENT_STREAM_NEW_BLOCK {
+            if let Some(block_height) = payload.get("block_height").and_then(|h| h.as_u64()) {
+                state.block_height = block_height;
+            }
+            state.saw_new_block = true;
+        }
+        let event = StreamEvent {
+            seq: state.next_seq,
+            block_height: state.block_height,
+            path: path.to_string(),
+            payload: payload.clone(),
+        };
+        state.next_seq = state.next_seq.saturating_add(1);
+        state.events.push_back(event);
+        while state.events.len() > state.capacity {
+            if let Some(dropped) = state.events.pop_front() {
+                state.dropped_height = Some(dropped.block_height);
+            }
+        }
+    }
+
+    /// Sequence number of the next event to be buffered.  This changes whenever an event is
+    /// buffered, so a waiting client only needs to look at the buffer again once it has.
+    pub fn next_seq(&self) -> u64 {
+        match self.state.lock() {
+            Ok(state) => state.next_seq,
+            Err(_) => {
+                warn!("Event stream buffer lock is poisoned");
+                0
+            }
+        }
+    }
+
+    /// Check that every event after `cursor` is still buffered
+    fn check_cursor(
+        state: &EventStreamState,
+        cursor: &EventStreamCursor,
+    ) -> Result<(), EventStreamError> {
+        match cursor {
+            EventStreamCursor::Oldest => Ok(()),
+            EventStreamCursor::FromHeight(height) => {
+                if *height < state.start_height {
+                    return Err(EventStreamError::CursorExpired(format!(
+                        "Events below height {} are not buffered",
+                        state.start_height
+                    )));
+                }
+                match state.dropped_height {
+                    Some(dropped_height) if *height <= dropped_height => {
+                        Err(EventStreamError::CursorExpired(format!(
+                            "Events at height {} have been dropped",
+                            dropped_height
+                        )))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            EventStreamCursor::AfterEvent { seq, .. } => {
+                if *seq >= state.next_seq {
+                    return Err(EventStreamError::FutureCursor(format!(
+                        "No event {} has been buffered",
+                        seq
+                    )));
+                }
+                let oldest_seq = state
+                    .events
+                    .front()
+                    .map(|event| event.seq)
+                    .unwrap_or(state.next_seq);
+                if seq.saturating_add(1) < oldest_seq {
+                    return Err(EventStreamError::CursorExpired(format!(
+                        "Events after {} have been dropped",
+                        seq
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Get up to `max_events` buffered events which come after `cursor` and pass `filter`.
+    /// Fails if some events after `cursor` are no longer buffered, or if `cursor` is ahead of
+    /// the buffer.
+    pub fn get_events(
+        &self,
+        cursor: &EventStreamCursor,
+        filter: &EventStreamFilter,
+        max_events: usize,
+    ) -> Result<EventPollResponse, EventStreamError> {
+        let Ok(state) = self.state.lock() else {
+            warn!("Event stream buffer lock is poisoned");
+            return Ok(EventPollResponse {
+                events: vec![],
+                has_more: false,
+                next_cursor: None,
+            });
+        };
+        Self::check_cursor(&state, cursor)?;
+
+        let mut events = vec![];
+        let mut has_more = false;
+        let mut last_examined = None;
+        for event in state.events.iter() {
+            if !cursor.includes(event) {
+                continue;
+            }
+            if let Some(payload) = filter.apply(&event.path, &event.payload) {
+                if events.len() >= max_events {
+                    has_more = true;
+                    break;
+                }
+                events.push(StreamEvent {
+                    seq: event.seq,
+                    block_height: event.block_height,
+                    path: event.path.clone(),
+                    payload,
+                });
+            }
+            last_examined = Some(event.event_id());
+        }
+
+        let next_cursor = last_examined.or_else(|| match cursor {
+            EventStreamCursor::AfterEvent { block_height, seq } => {
+                Some(format!("{}:{}", block_height, seq))
+            }
+            _ => None,
+        });
+        Ok(EventPollResponse {
+            events,
+            has_more,
+            next_cursor,
+        })
+    }
+}
+
+/// Where in the event stream a client wants to start
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamCursor {
+    /// all buffered events
+    Oldest,
+    /// all buffered events at or above this block height
+    FromHeight(u64),
+    /// all buffered events after the event with this ID
+    AfterEvent { block_height: u64, seq: u64 },
+}
+
+impl EventStreamCursor {
+    /// Parse a cursor from an event ID (i.e. `height:seq`)
+    pub fn from_event_id(event_id: &str) -> Option<EventStreamCursor> {
+        let (block_height, seq) = event_id.trim().split_once(':')?;
+        Some(EventStreamCursor::AfterEvent {
+            block_height: block_height.parse().ok()?,
+            seq: seq.parse().ok()?,
+        })
+    }
+
+    /// Should the given event be sent to a client at this cursor?
+    fn includes(&self, event: &StreamEvent) -> bool {
+        match self {
+            EventStreamCursor::Oldest => true,
+            EventStreamCursor::FromHeight(height) => event.block_height >= *height,
+            EventStreamCursor::AfterEvent { seq, .. } => event.seq > *seq,
+        }
+    }
+}
+
+/// An event subscription key, named the same way as the `events_keys` of an
+/// `[[events_observer]]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamKey {
+    /// `*`: all block events, burn blocks and mempool transactions
+    AnyEvent,
+    /// `stx`: STX transfer, mint, burn and lock events
+    STXEvent,
+    /// `memtx`: new mempool transactions
+    MemPoolTransactions,
+    /// `burn_blocks`: new burnchain blocks
+    BurnchainBlocks,
+    /// `stackerdb`: StackerDB chunks
+    StackerDBChunks,
+    /// `<contract>::<topic>`: a contract's `print` events
+    SmartContractEvent(QualifiedContractIdentifier, String),
+    /// `<address>.<contract>.<asset>`: a fungible or non-fungible token's events
+    AssetEvent(AssetIdentifier),
+}
+
+impl EventStreamKey {
+    pub fn from_string(raw_key: &str) -> Option<EventStreamKey> {
+        match raw_key {
+            "*" => return Some(EventStreamKey::AnyEvent),
+            "stx" => return Some(EventStreamKey::STXEvent),
+            "memtx" => return Some(EventStreamKey::MemPoolTransactions),
+            "burn_blocks" => return Some(EventStreamKey::BurnchainBlocks),
+            "stackerdb" => return Some(EventStreamKey::StackerDBChunks),
+            _ => {}
+        }
+
+        if let Some((contract_id, topic)) = raw_key.split_once("::") {
+            let contract_identifier = QualifiedContractIdentifier::parse(contract_id).ok()?;
+            return Some(EventStreamKey::SmartContractEvent(
+                contract_identifier,
+                topic.to_string(),
+            ));
+        }
+
+        let split: Vec<_> = raw_key.split('.').collect();
+        if split.len() != 3 {
+            return None;
+        }
+        let address = PrincipalData::parse_standard_principal(split[0]).ok()?;
+        let contract_name = split[1].to_string().try_into().ok()?;
+        let asset_name = split[2].to_string().try_into().ok()?;
+        Some(EventStreamKey::AssetEvent(AssetIdentifier {
+            contract_identifier: QualifiedContractIdentifier::new(address, contract_name),
+            asset_name,
+        }))
+    }
+
+    /// Does this key select the given transaction event, as encoded in a `new_block` payload?
+    fn matches_tx_event(&self, event: &serde_json::Value) -> bool {
+        let Some(event_type) = event.get("type").and_then(|t| t.as_str()) else {
+            return false;
+        };
+        match self {
+            EventStreamKey::AnyEvent => true,
+            EventStreamKey::STXEvent => event_type.starts_with("stx_"),
+            EventStreamKey::SmartContractEvent(contract_identifier, topic) => {
+                let Some(data) = event.get(event_type) else {
+                    return false;
+                };
+                event_type == "contract_event"
+                    && data.get("contract_identifier").and_then(|c| c.as_str())
+                        == Some(contract_identifier.to_string().as_str())
+                    && data.get("topic").and_then(|t| t.as_str()) == Some(topic.as_str())
+            }
+            EventStreamKey::AssetEvent(asset_identifier) => {
+                let Some(data) = event.get(event_type) else {
+                    return false;
+                };
+                (event_type.starts_with("ft_") || event_type.starts_with("nft_"))
+                    && data.get("asset_identifier").and_then(|a| a.as_str())
+                        == Some(asset_identifier.to_string().as_str())
+            }
+            EventStreamKey::MemPoolTransactions
+            | EventStreamKey::BurnchainBlocks
+            | EventStreamKey::StackerDBChunks => false,
+        }
+    }
+}
+
+/// Which events a client subscribed to. As with event observers, a client always receives
+/// `new_block` payloads, but they only list the transaction events it subscribed to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamFilter {
+    /// every streamed payload, unfiltered
+    All,
+    /// only the payloads and transaction events selected by these keys
+    Keys(Vec<EventStreamKey>),
+}
+
+impl EventStreamFilter {
+    /// Parse a comma-separated list of event keys
+    pub fn from_keys_str(keys_str: &str) -> Option<EventStreamFilter> {
+        let keys = keys_str
+            .split(',')
+            .filter(|key| !key.is_empty())
+            .map(EventStreamKey::from_string)
+            .collect::<Option<Vec<_>>>()?;
+        Some(EventStreamFilter::Keys(keys))
+    }
+
+    fn has_key(&self, key: &EventStreamKey) -> bool {
+        match self {
+            EventStreamFilter::All => true,
+            EventStreamFilter::Keys(keys) => keys.contains(key),
+        }
+    }
+
+    /// Filter a payload sent to the given path.
+    /// Returns `None` if the client did not subscribe to it.
+    pub fn apply(&self, path: &str, payload: &serde_json::Value) -> Option<serde_json::Value> {
+        let keys = match self {
+            EventStreamFilter::All => return Some(payload.clone()),
+            EventStreamFilter::Keys(keys) => keys,
+        };
+        match path {
+            EVENT_STREAM_NEW_BLOCK => {
+                let mut payload = payload.clone();
+                if let Some(events) = payload.get_mut("events").and_then(|e| e.as_array_mut()) {
+                    events.retain(|event| keys.iter().any(|key| key.matches_tx_event(event)));
+                }
+                Some(payload)
+            }
+            EVENT_STREAM_NEW_BURN_BLOCK => (self.has_key(&EventStreamKey::BurnchainBlocks)
+                || self.has_key(&EventStreamKey::AnyEvent))
+            .then(|| payload.clone()),
+            EVENT_STREAM_NEW_MEMPOOL_TX => (self.has_key(&EventStreamKey::MemPoolTransactions)
+                || self.has_key(&EventStreamKey::AnyEvent))
+            .then(|| payload.clone()),
+            EVENT_STREAM_STACKERDB_CHUNKS => self
+                .has_key(&EventStreamKey::StackerDBChunks)
+                .then(|| payload.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The body of a long poll: it waits until some events after the cursor pass the filter, or
+/// until its deadline, and then sends them as a single `EventPollResponse`.
+pub struct EventPollStream {
+    buffer: EventStreamBuffer,
+    cursor: EventStreamCursor,
+    filter: EventStreamFilter,
+    deadline: Instant,
+    /// `next_seq` of the buffer when it was last examined
+    examined_seq: Option<u64>,
+    /// the response, once it's ready
+    body: Option<Vec<u8>>,
+    /// whether the response has been generated, so the stream is over
+    sent: bool,
+}
+
+impl EventPollStream {
+    pub fn new(
+        buffer: EventStreamBuffer,
+        cursor: EventStreamCursor,
+        filter: EventStreamFilter,
+        wait: Duration,
+    ) -> EventPollStream {
+        EventPollStream {
+            buffer,
+            cursor,
+            filter,
+            deadline: Instant::now() + wait,
+            examined_seq: None,
+            body: None,
+            sent: false,
+        }
+    }
+
+    /// Look for events, and make the response if there are some or the deadline has passed
+    fn poll(&mut self) {
+        let next_seq = self.buffer.next_seq();
+        let expired = Instant::now() >= self.deadline;
+        if self.examined_seq == Some(next_seq) && !expired {
+            return;
+        }
+        self.examined_seq = Some(next_seq);
+
+        let data_resp =
+            match self
+                .buffer
+                .get_events(&self.cursor, &self.filter, EVENT_POLL_MAX_EVENTS)
+            {
+                Ok(data_resp) if !data_resp.events.is_empty() || expired => data_resp,
+                Ok(_) => return,
+                // the cursor expired while waiting.  Send no events, so that the client's next poll
+                // from the same cursor is told so.
+                Err(_) => EventPollResponse {
+                    events: vec![],
+                    has_more: false,
+                    next_cursor: match self.cursor {
+                        EventStreamCursor::AfterEvent { block_height, seq } => {
+                            Some(format!("{}:{}", block_height, seq))
+                        }
+                        _ => None,
+                    },
+                },
+            };
+        match serde_json::to_vec(&data_resp) {
+            Ok(body) => self.body = Some(body),
+            Err(e) => {
+                warn!("Failed to serialize event poll response: {:?}", &e);
+                self.body = Some(vec![]);
+            }
+        }
+    }
+}
+
+impl HttpChunkGenerator for EventPollStream {
+    fn hint_chunk_size(&self) -> usize {
+        4096
+    }
+
+    fn is_chunk_ready(&mut self) -> bool {
+        if !self.sent && self.body.is_none() {
+            self.poll();
+        }
+        self.sent || self.body.is_some()
+    }
+
+    /// Send the whole response as one chunk, and then end the stream
+    fn generate_next_chunk(&mut self) -> Result<Vec<u8>, String> {
+        if self.sent {
+            return Ok(vec![]);
+        }
+        let body = self.body.take().unwrap_or_default();
+        self.sent = true;
+        Ok(body)
+    }
+}
+
+#[derive(Clone)]
+pub struct RPCGetEventPollRequestHandler {
+    pub cursor: Option<EventStreamCursor>,
+    pub filter: Option<EventStreamFilter>,
+    /// how long to wait for events, if there are none after the cursor yet
+    pub wait: Option<Duration>,
+}
+
+impl RPCGetEventPollRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            cursor: None,
+            filter: None,
+            wait: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetEventPollRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/events/poll$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/events/poll"
+    }
+
+    /// Try to decode this request.
+    /// The cursor comes from the `cursor=` query parameter, which is the `next_cursor` of the
+    /// previous response, or else from the `from_height=` query parameter.
+    /// `wait=` is how many seconds to wait for events, up to `EVENT_POLL_MAX_WAIT_SECS`.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let req_contents = HttpRequestContents::new().query_string(query);
+
+        let cursor = if let Some(event_id) = req_contents.get_query_arg("cursor") {
+            EventStreamCursor::from_event_id(event_id).ok_or_else(|| {
+                Error::DecodeError("Failed to parse cursor= query parameter".to_string())
+            })?
+        } else if let Some(height_str) = req_contents.get_query_arg("from_height") {
+            let height = height_str.parse::<u64>().map_err(|_| {
+                Error::DecodeError("Failed to parse from_height= query parameter".to_string())
+            })?;
+            EventStreamCursor::FromHeight(height)
+        } else {
+            EventStreamCursor::Oldest
+        };
+
+        let filter = match req_contents.get_query_arg("events") {
+            Some(keys_str) => EventStreamFilter::from_keys_str(keys_str).ok_or_else(|| {
+                Error::DecodeError("Failed to parse events= query parameter".to_string())
+            })?,
+            None => EventStreamFilter::All,
+        };
+
+        let wait_secs = match req_contents.get_query_arg("wait") {
+            Some(wait_str) => wait_str.parse::<u64>().map_err(|_| {
+                Error::DecodeError("Failed to parse wait= query parameter".to_string())
+            })?,
+            None => 0,
+        };
+
+        self.cursor = Some(cursor);
+        self.filter = Some(filter);
+        self.wait = Some(Duration::from_secs(wait_secs.min(EVENT_POLL_MAX_WAIT_SECS)));
+
+        Ok(req_contents)
+    }
+}
+
+impl RPCRequestHandler for RPCGetEventPollRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.cursor = None;
+        self.filter = None;
+        self.wait = None;
+    }
+
+    /// Make the response.
+    /// If there are buffered events after the cursor, or the client didn't ask to wait, this
+    /// answers right away, even if there are no events.  Otherwise, this is a long poll: the
+    /// response is held until some events arrive or the wait is over, without blocking the
+    /// node's other connections.  Either way, clients poll again from the response's
+    /// `next_cursor`.
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let cursor = self
+            .cursor
+            .take()
+            .ok_or(NetError::SendError("`cursor` not set".into()))?;
+        let filter = self
+            .filter
+            .take()
+            .ok_or(NetError::SendError("`filter` not set".into()))?;
+        let wait = self
+            .wait
+            .take()
+            .ok_or(NetError::SendError("`wait` not set".into()))?;
+
+        let events_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                rpc_args.event_stream.map(|buffer| {
+                    let events = buffer.get_events(&cursor, &filter, EVENT_POLL_MAX_EVENTS);
+                    (buffer.clone(), events)
+                })
+            });
+        let (buffer, data_resp) = match events_opt {
+            Some((buffer, Ok(data_resp))) => (buffer, data_resp),
+            Some((_, Err(EventStreamError::CursorExpired(msg)))) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!("Cursor expired: {}", msg)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Some((_, Err(EventStreamError::FutureCursor(msg)))) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpBadRequest::new(format!("Invalid cursor: {}", msg)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            None => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Event stream is not enabled on this node".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = if data_resp.events.is_empty() && !wait.is_zero() {
+            HttpResponseContents::from_stream(Box::new(EventPollStream::new(
+                buffer, cursor, filter, wait,
+            )))
+        } else {
+            HttpResponseContents::try_from_json(&data_resp)?
+        };
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetEventPollRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let events: EventPollResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(events)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to poll the event stream.
+    /// `events` are event observer keys (e.g. `burn_blocks`); all events are returned if empty.
+    /// `cursor` takes precedence over `from_height`.  If `wait_secs` is given, the node holds
+    /// the request for up to that long until there are events to return.
+    pub fn new_get_event_poll(
+        host: PeerHost,
+        events: &[&str],
+        from_height: Option<u64>,
+        cursor: Option<String>,
+        wait_secs: Option<u64>,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if !events.is_empty() {
+            contents = contents.query_arg("events".into(), events.join(","));
+        }
+        if let Some(from_height) = from_height {
+            contents = contents.query_arg("from_height".into(), from_height.to_string());
+        }
+        if let Some(cursor) = cursor {
+            contents = contents.query_arg("cursor".into(), cursor);
+        }
+        if let Some(wait_secs) = wait_secs {
+            contents = contents.query_arg("wait".into(), wait_secs.to_string());
+        }
+        StacksHttpRequest::new_for_peer(host, "GET".into(), "/v3/events/poll".into(), contents)
+            .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_event_poll(self) -> Result<EventPollResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let events: EventPollResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(events)
+    }
+}
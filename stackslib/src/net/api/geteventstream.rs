@@ -0,0 +1,427 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use regex::{Captures, Regex};
+use stacks_common::codec::MAX_MESSAGE_LEN;
+use stacks_common::types::net::PeerHost;
+
+use crate::net::api::geteventpoll::{
+    EventStreamBuffer, EventStreamCursor, EventStreamError, EventStreamFilter, StreamEvent,
+};
+use crate::net::http::{
+    parse_raw_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Most events sent in a single chunk.  If there are more, they are sent in the next chunk.
+pub const EVENT_STREAM_MAX_EVENTS: usize = 32;
+
+/// How long a client should wait before reconnecting to a dropped stream, in milliseconds
+pub const EVENT_STREAM_RETRY_MS: u64 = 1000;
+
+/// How often an idle stream sends a comment to keep its connection open.  This is below the
+/// HTTP server's default idle timeout, so the node doesn't close a stream with no events.
+pub const EVENT_STREAM_KEEPALIVE_SECS: u64 = 10;
+
+impl StreamEvent {
+    /// Encode this event as a Server-Sent Event, whose ID is the event's cursor
+    pub fn to_sse(&self) -> String {
+        // serde_json's compact encoding never contains a newline
+        format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.event_id(),
+            &self.path,
+            &self.payload
+        )
+    }
+}
+
+/// Decode a `text/event-stream` body into its `retry` interval and its events.
+/// Comments, and `error` events which end an expired stream, are skipped.
+pub fn parse_event_stream(body: &str) -> Result<(Option<u64>, Vec<StreamEvent>), Error> {
+    let mut retry = None;
+    let mut events = vec![];
+    for block in body.split("\n\n") {
+        let mut event_id = None;
+        let mut path = None;
+        let mut data = None;
+        for line in block.lines() {
+            let Some((field, value)) = line.split_once(": ") else {
+                continue;
+            };
+            match field {
+                "retry" => {
+                    retry = Some(value.parse().map_err(|_| {
+                        Error::DecodeError(format!("Invalid retry interval: {}", value))
+                    })?)
+                }
+                "id" => event_id = Some(value),
+                "event" => path = Some(value),
+                "data" => data = Some(value),
+                _ => {}
+            }
+        }
+        let (Some(event_id), Some(path), Some(data)) = (event_id, path, data) else {
+            continue;
+        };
+        let Some(EventStreamCursor::AfterEvent { block_height, seq }) =
+            EventStreamCursor::from_event_id(event_id)
+        else {
+            return Err(Error::DecodeError(format!(
+                "Invalid event ID: {}",
+                event_id
+            )));
+        };
+        let payload = serde_json::from_str(data)
+            .map_err(|e| Error::DecodeError(format!("Failed to parse event data: {:?}", &e)))?;
+        events.push(StreamEvent {
+            seq,
+            block_height,
+            path: path.to_string(),
+            payload,
+        });
+    }
+    Ok((retry, events))
+}
+
+/// The body of an event stream.  It stays open, and sends each event after the cursor which
+/// passes the filter as soon as it is buffered.  If the client falls so far behind that events
+/// it has yet to receive are dropped from the buffer, the stream sends an `error` event and
+/// ends.
+pub struct EventSSEStream {
+    buffer: EventStreamBuffer,
+    cursor: EventStreamCursor,
+    filter: EventStreamFilter,
+    keepalive: Duration,
+    /// `next_seq` of the buffer when it was last examined
+    examined_seq: Option<u64>,
+    /// when the last chunk was generated
+    last_sent: Instant,
+    /// the next chunk, once it's ready
+    chunk: Option<Vec<u8>>,
+    /// whether the stream ends after `chunk`
+    closing: bool,
+}
+
+impl EventSSEStream {
+    pub fn new(
+        buffer: EventStreamBuffer,
+        cursor: EventStreamCursor,
+        filter: EventStreamFilter,
+        keepalive: Duration,
+    ) -> EventSSEStream {
+        EventSSEStream {
+            buffer,
+            cursor,
+            filter,
+            keepalive,
+            examined_seq: None,
+            last_sent: Instant::now(),
+            // tells the client the stream is open before there are any events
+            chunk: Some(format!("retry: {}\n\n", EVENT_STREAM_RETRY_MS).into_bytes()),
+            closing: false,
+        }
+    }
+
+    /// Look for events, and make the next chunk if there are some or a keepalive is due
+    fn poll(&mut self) {
+        let next_seq = self.buffer.next_seq();
+        if self.examined_seq != Some(next_seq) {
+            match self
+                .buffer
+                .get_events(&self.cursor, &self.filter, EVENT_STREAM_MAX_EVENTS)
+            {
+                Ok(data_resp) => {
+                    if let Some(cursor) = data_resp
+                        .next_cursor
+                        .as_deref()
+                        .and_then(EventStreamCursor::from_event_id)
+                    {
+                        self.cursor = cursor;
+                    }
+                    if !data_resp.has_more {
+                        self.examined_seq = Some(next_seq);
+                    }
+                    if !data_resp.events.is_empty() {
+                        let body: String =
+                            data_resp.events.iter().map(StreamEvent::to_sse).collect();
+                        self.chunk = Some(body.into_bytes());
+                        return;
+                    }
+                }
+                Err(EventStreamError::CursorExpired(msg))
+                | Err(EventStreamError::FutureCursor(msg)) => {
+                    self.chunk = Some(format!("event: error\ndata: {}\n\n", msg).into_bytes());
+                    self.closing = true;
+                    return;
+                }
+            }
+        }
+        if self.last_sent.elapsed() >= self.keepalive {
+            self.chunk = Some(b": keepalive\n\n".to_vec());
+        }
+    }
+}
+
+impl HttpChunkGenerator for EventSSEStream {
+    fn hint_chunk_size(&self) -> usize {
+        4096
+    }
+
+    fn is_chunk_ready(&mut self) -> bool {
+        if self.chunk.is_none() && !self.closing {
+            self.poll();
+        }
+        self.chunk.is_some() || self.closing
+    }
+
+    fn send_partial_chunks(&self) -> bool {
+        true
+    }
+
+    /// Send the pending events, keepalive or error.  Once an error has been sent, end the
+    /// stream.
+    fn generate_next_chunk(&mut self) -> Result<Vec<u8>, String> {
+        self.last_sent = Instant::now();
+        Ok(self.chunk.take().unwrap_or_default())
+    }
+}
+
+#[derive(Clone)]
+pub struct RPCGetEventStreamRequestHandler {
+    pub cursor: Option<EventStreamCursor>,
+    pub filter: Option<EventStreamFilter>,
+}
+
+impl RPCGetEventStreamRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            cursor: None,
+            filter: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetEventStreamRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/events/stream$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/events/stream"
+    }
+
+    /// Try to decode this request.
+    /// The cursor comes from the `Last-Event-ID` header, which an SSE client sends when it
+    /// reconnects, or else from the `cursor=` or `from_height=` query parameters.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let req_contents = HttpRequestContents::new().query_string(query);
+
+        let cursor = if let Some(event_id) = preamble.get_header("last-event-id".to_string()) {
+            EventStreamCursor::from_event_id(&event_id).ok_or_else(|| {
+                Error::DecodeError(format!("Failed to parse Last-Event-ID: {}", &event_id))
+            })?
+        } else if let Some(event_id) = req_contents.get_query_arg("cursor") {
+            EventStreamCursor::from_event_id(event_id).ok_or_else(|| {
+                Error::DecodeError("Failed to parse cursor= query parameter".to_string())
+            })?
+        } else if let Some(height_str) = req_contents.get_query_arg("from_height") {
+            let height = height_str.parse::<u64>().map_err(|_| {
+                Error::DecodeError("Failed to parse from_height= query parameter".to_string())
+            })?;
+            EventStreamCursor::FromHeight(height)
+        } else {
+            EventStreamCursor::Oldest
+        };
+
+        let filter = match req_contents.get_query_arg("events") {
+            Some(keys_str) => EventStreamFilter::from_keys_str(keys_str).ok_or_else(|| {
+                Error::DecodeError("Failed to parse events= query parameter".to_string())
+            })?,
+            None => EventStreamFilter::All,
+        };
+
+        self.cursor = Some(cursor);
+        self.filter = Some(filter);
+
+        Ok(req_contents)
+    }
+}
+
+impl RPCRequestHandler for RPCGetEventStreamRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.cursor = None;
+        self.filter = None;
+    }
+
+    /// Make the response.
+    /// The cursor is checked up front, so a client resuming from an expired or unknown cursor
+    /// gets an error status instead of a stream.  Otherwise, the response is a stream which
+    /// stays open for as long as the client keeps reading it, without blocking the node's
+    /// other connections.
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let cursor = self
+            .cursor
+            .take()
+            .ok_or(NetError::SendError("`cursor` not set".into()))?;
+        let filter = self
+            .filter
+            .take()
+            .ok_or(NetError::SendError("`filter` not set".into()))?;
+
+        let buffer_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                rpc_args.event_stream.map(|buffer| {
+                    let checked = buffer.get_events(&cursor, &filter, 0).map(|_| ());
+                    (buffer.clone(), checked)
+                })
+            });
+        let buffer = match buffer_opt {
+            Some((buffer, Ok(()))) => buffer,
+            Some((_, Err(EventStreamError::CursorExpired(msg)))) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!("Cursor expired: {}", msg)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Some((_, Err(EventStreamError::FutureCursor(msg)))) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpBadRequest::new(format!("Invalid cursor: {}", msg)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            None => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Event stream is not enabled on this node".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::from_http_request_preamble(
+            &preamble,
+            200,
+            "OK",
+            None,
+            HttpContentType::EventStream,
+        );
+        preamble.add_header("Cache-Control".into(), "no-cache".into());
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::from_stream(Box::new(EventSSEStream::new(
+            buffer,
+            cursor,
+            filter,
+            Duration::from_secs(EVENT_STREAM_KEEPALIVE_SECS),
+        )));
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetEventStreamRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let bytes = parse_raw_bytes(
+            preamble,
+            body,
+            MAX_MESSAGE_LEN.into(),
+            HttpContentType::EventStream,
+        )?;
+        Ok(HttpResponsePayload::Bytes(bytes))
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the event stream.
+    /// `events` are event observer keys (e.g. `burn_blocks`); all events are streamed if empty.
+    /// `last_event_id` takes precedence over `from_height`.
+    pub fn new_get_event_stream(
+        host: PeerHost,
+        events: &[&str],
+        from_height: Option<u64>,
+        last_event_id: Option<String>,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if !events.is_empty() {
+            contents = contents.query_arg("events".into(), events.join(","));
+        }
+        if let Some(from_height) = from_height {
+            contents = contents.query_arg("from_height".into(), from_height.to_string());
+        }
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            "/v3/events/stream".into(),
+            contents,
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        if let Some(last_event_id) = last_event_id {
+            request.add_header("Last-Event-ID".into(), last_event_id);
+        }
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    /// Decode the part of an event stream received so far into its `retry` interval and its
+    /// events
+    pub fn decode_event_stream(self) -> Result<(Option<u64>, Vec<StreamEvent>), NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let bytes: Vec<u8> = contents.try_into()?;
+        let body = std::str::from_utf8(&bytes)
+            .map_err(|_e| Error::DecodeError("Event stream is not UTF-8".to_string()))?;
+        Ok(parse_event_stream(body)?)
+    }
+}
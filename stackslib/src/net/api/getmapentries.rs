@@ -0,0 +1,357 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::analysis::CheckErrors;
+use clarity::vm::ast::parser::v1::CLARITY_NAME_REGEX;
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::errors::Error as ClarityError;
+use clarity::vm::representations::{CONTRACT_NAME_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::{ClarityName, ContractName};
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::net::http::{
+    parse_json, Error, HttpGone, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// Number of map entries returned if the request does not give a `limit=`
+pub const MAP_ENTRIES_DEFAULT_LIMIT: usize = 50;
+/// Largest `limit=` a request may give
+pub const MAP_ENTRIES_MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapEntry {
+    /// Hex-encoded serialized key
+    pub key: String,
+    /// Hex-encoded serialized value
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapEntriesResponse {
+    pub entries: Vec<MapEntry>,
+    /// Pass this as `cursor=` to get the next page.  None if there are no more entries.  Like
+    /// the keys, this is a `0x`-prefixed hex string.
+    pub next_cursor: Option<String>,
+    /// Height of the first block whose map keys this node indexed, if any.  Entries which were
+    /// only written by earlier blocks are not listed.
+    pub index_start_height: Option<u32>,
+}
+
+#[derive(Clone)]
+pub struct RPCGetMapEntriesRequestHandler {
+    pub contract_identifier: Option<QualifiedContractIdentifier>,
+    pub map_name: Option<ClarityName>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+impl RPCGetMapEntriesRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            contract_identifier: None,
+            map_name: None,
+            cursor: None,
+            limit: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetMapEntriesRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(&format!(
+            "^/v3/contracts/(?P<address>{})/(?P<contract>{})/maps/(?P<map>{})/entries$",
+            *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING, *CLARITY_NAME_REGEX
+        ))
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/contracts/:principal/:contract_name/maps/:map_name/entries"
+    }
+
+    /// Try to decode this request.
+    /// The cursor is the `next_cursor` of the previous page (a `0x`-prefixed hex-encoded key),
+    /// if any.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_identifier = request::get_contract_address(captures, "address", "contract")?;
+        let map_name = request::get_clarity_name(captures, "map")?;
+
+        let contents = HttpRequestContents::new().query_string(query);
+
+        let cursor = match contents.get_query_arg("cursor") {
+            Some(cursor) if !cursor.is_empty() => {
+                let key = cursor
+                    .strip_prefix("0x")
+                    .filter(|key| {
+                        !key.is_empty()
+                            && key
+                                .chars()
+                                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+                    })
+                    .ok_or_else(|| {
+                        Error::DecodeError("Failed to parse cursor= query parameter".to_string())
+                    })?;
+                Some(key.to_string())
+            }
+            _ => None,
+        };
+
+        let limit = match contents.get_query_arg("limit") {
+            Some(limit_str) => {
+                let limit = limit_str.parse::<usize>().map_err(|_| {
+                    Error::DecodeError("Failed to parse limit= query parameter".to_string())
+                })?;
+                if limit == 0 || limit > MAP_ENTRIES_MAX_LIMIT {
+                    return Err(Error::DecodeError(format!(
+                        "Invalid limit= query parameter: must be between 1 and {}",
+                        MAP_ENTRIES_MAX_LIMIT
+                    )));
+                }
+                limit
+            }
+            None => MAP_ENTRIES_DEFAULT_LIMIT,
+        };
+
+        self.contract_identifier = Some(contract_identifier);
+        self.map_name = Some(map_name);
+        self.cursor = cursor;
+        self.limit = Some(limit);
+
+        Ok(contents)
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCGetMapEntriesRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.contract_identifier = None;
+        self.map_name = None;
+        self.cursor = None;
+        self.limit = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let contract_identifier = self.contract_identifier.take().ok_or(NetError::SendError(
+            "`contract_identifier` not set".to_string(),
+        ))?;
+        let map_name = self
+            .map_name
+            .take()
+            .ok_or(NetError::SendError("`map_name` not set".to_string()))?;
+        let limit = self
+            .limit
+            .take()
+            .ok_or(NetError::SendError("`limit` not set".to_string()))?;
+        let cursor = self.cursor.take();
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let page_res = node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+            let map_res =
+                chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip, |clarity_tx| {
+                    clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                        match clarity_db.load_map(&contract_identifier, &map_name) {
+                            Ok(_) => Ok(true),
+                            Err(ClarityError::Unchecked(CheckErrors::NoSuchMap(_))) => Ok(false),
+                            Err(e) => Err(e),
+                        }
+                    })
+                });
+            match map_res {
+                Ok(Some(Ok(true))) => {}
+                Ok(Some(Ok(false))) => {
+                    return Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpNotFound::new("Map not found".to_string()),
+                    ));
+                }
+                Ok(None) => {
+                    return Err(clarity_state_not_found(&preamble, chainstate, &tip));
+                }
+                Ok(Some(Err(e))) => {
+                    return Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpServerError::new(format!("Failed to load map: {:?}", &e)),
+                    ));
+                }
+                Err(e) => {
+                    return Err(StacksHttpResponse::new_error(
+                        &preamble,
+                        &HttpServerError::new(format!("Failed to load chain tip: {:?}", &e)),
+                    ));
+                }
+            }
+            match chainstate.clarity_state.get_map_entries(
+                &tip,
+                &contract_identifier,
+                &map_name,
+                cursor.as_deref(),
+                limit,
+            ) {
+                Ok(Some(page)) => Ok(page),
+                Ok(None) => Err(StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Map index is not enabled on this node".to_string()),
+                )),
+                Err(e) => Err(StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to list map entries: {:?}", &e)),
+                )),
+            }
+        });
+
+        let page = match page_res {
+            Ok(page) => page,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let data_resp = MapEntriesResponse {
+            entries: page
+                .entries
+                .into_iter()
+                .map(|(key, value)| MapEntry {
+                    key: format!("0x{}", key),
+                    value: format!("0x{}", value),
+                })
+                .collect(),
+            next_cursor: page.next_cursor.map(|key| format!("0x{}", key)),
+            index_start_height: page.index_start_height,
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Make the response for a tip whose Clarity state could not be opened: 410 if the tip is a
+/// known block whose state was pruned, and 404 otherwise
+pub(crate) fn clarity_state_not_found(
+    preamble: &HttpRequestPreamble,
+    chainstate: &StacksChainState,
+    tip: &StacksBlockId,
+) -> StacksHttpResponse {
+    match NakamotoChainState::get_block_header(chainstate.db(), tip) {
+        Ok(Some(_)) => StacksHttpResponse::new_error(
+            preamble,
+            &HttpGone::new(format!("The Clarity state of {} has been pruned", tip)),
+        ),
+        Ok(None) => StacksHttpResponse::new_error(
+            preamble,
+            &HttpNotFound::new("Chain tip not found".to_string()),
+        ),
+        Err(e) => StacksHttpResponse::new_error(
+            preamble,
+            &HttpServerError::new(format!("Failed to load chain tip: {:?}", &e)),
+        ),
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetMapEntriesRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let map_entries: MapEntriesResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(map_entries)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a page of a data map's entries
+    pub fn new_getmapentries(
+        host: PeerHost,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        map_name: ClarityName,
+        cursor: Option<String>,
+        limit: Option<usize>,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new().for_tip(tip_req);
+        if let Some(cursor) = cursor {
+            contents = contents.query_arg("cursor".into(), cursor);
+        }
+        if let Some(limit) = limit {
+            contents = contents.query_arg("limit".into(), limit.to_string());
+        }
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!(
+                "/v3/contracts/{}/{}/maps/{}/entries",
+                &contract_addr, &contract_name, &map_name
+            ),
+            contents,
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_map_entries_response(self) -> Result<MapEntriesResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: MapEntriesResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -26,12 +26,13 @@ use stacks_common::types::net::PeerHost;
 use stacks_common::util::hash::to_hex;
 use {serde, serde_json};
 
+use crate::chainstate::stacks::db::prune::BlockDataPin;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::{Error as ChainError, StacksBlockHeader, StacksMicroblock};
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     request, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -64,6 +65,8 @@ pub struct StacksIndexedMicroblockStream {
 
     /// connection to the chain state
     chainstate_db: DBConn,
+    /// keeps the stream from being pruned while it is read
+    _pin: BlockDataPin,
 }
 
 impl StacksIndexedMicroblockStream {
@@ -82,6 +85,8 @@ impl StacksIndexedMicroblockStream {
             &mblock_info.consensus_hash,
             &mblock_info.anchored_block_hash,
         );
+        let pin = chainstate
+            .pin_microblock_stream(&parent_index_block_hash, tail_index_microblock_hash)?;
 
         // need to send out the consensus_serialize()'ed array length before sending microblocks.
         // this is exactly what seq tells us, though.
@@ -98,6 +103,7 @@ impl StacksIndexedMicroblockStream {
             num_items_buf: num_items_buf,
             num_items_ptr: 0,
             chainstate_db: chainstate.reopen_db()?,
+            _pin: pin,
         })
     }
 }
@@ -172,6 +178,17 @@ impl RPCRequestHandler for RPCMicroblocksIndexedRequestHandler {
                 .try_into_contents()
                 .map_err(NetError::from)
             }
+            Err(ChainError::PrunedError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!(
+                        "Microblock {:?} has been pruned\n",
+                        &tail_microblock_id
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(e) => {
                 // nope -- error trying to check
                 let msg = format!("Failed to load microblock: {:?}\n", &e);
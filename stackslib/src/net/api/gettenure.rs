@@ -30,9 +30,9 @@ use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
 use crate::net::api::getblock_v3::NakamotoBlockStream;
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -71,6 +71,8 @@ pub struct NakamotoTenureStream {
     pub total_sent: u64,
     /// stop streaming if we reach this block
     pub last_block_id: Option<StacksBlockId>,
+    /// path to the chunk store, which names the blocks that readers pin
+    blocks_path: String,
 }
 
 impl NakamotoTenureStream {
@@ -89,6 +91,7 @@ impl NakamotoTenureStream {
             headers_conn,
             total_sent: 0,
             last_block_id,
+            blocks_path: chainstate.blocks_path.clone(),
         })
     }
 
@@ -122,6 +125,18 @@ impl NakamotoTenureStream {
             return Ok(false);
         }
 
+        // stop sending if the parent was pruned
+        let pin = match StacksChainState::pin_staged_nakamoto_block(
+            &self.blocks_path,
+            &self.headers_conn,
+            &self.block_stream.staging_db_conn.conn(),
+            &self.block_stream.parent_block_id,
+        ) {
+            Ok(pin) => pin,
+            Err(ChainError::PrunedError) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
         let parent_size = self
             .block_stream
             .staging_db_conn
@@ -140,6 +155,7 @@ impl NakamotoTenureStream {
         self.block_stream.reset(
             parent_nakamoto_header.block_id(),
             parent_nakamoto_header.parent_block_id.clone(),
+            pin,
         )?;
         Ok(true)
     }
@@ -250,6 +266,14 @@ impl RPCRequestHandler for RPCNakamotoTenureRequestHandler {
                 .try_into_contents()
                 .map_err(NetError::from)
             }
+            Err(ChainError::PrunedError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!("Block {} has been pruned\n", &block_id)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(e) => {
                 // nope -- error trying to check
                 let msg = format!("Failed to load block {}: {:?}\n", &block_id, &e);
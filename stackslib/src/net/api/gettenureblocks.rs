@@ -0,0 +1,245 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::bitvec::BitVec;
+use stacks_common::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksBlockId};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+use crate::chainstate::nakamoto::NakamotoBlockHeader;
+use crate::chainstate::stacks::Error as ChainError;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Processing status of a block in a tenure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TenureBlockStatus {
+    /// the block has been stored, but not yet processed
+    Pending,
+    /// the block has been appended to the chainstate
+    Processed,
+    /// the block can never be processed
+    Orphaned,
+}
+
+/// A block in a tenure, and the signer set's signature over it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenureBlockInfo {
+    pub block_id: StacksBlockId,
+    pub block_hash: BlockHeaderHash,
+    pub parent_block_id: StacksBlockId,
+    pub height: u64,
+    pub status: TenureBlockStatus,
+    /// the message the signers signed
+    pub signer_signature_hash: Sha512Trunc256Sum,
+    /// which signers contributed to the signature
+    pub signer_bitvec: BitVec<4000>,
+    /// how many signers contributed to the signature
+    pub signer_count: u16,
+}
+
+impl TenureBlockInfo {
+    fn new(header: &NakamotoBlockHeader, processed: bool, orphaned: bool) -> Self {
+        let status = if orphaned {
+            TenureBlockStatus::Orphaned
+        } else if processed {
+            TenureBlockStatus::Processed
+        } else {
+            TenureBlockStatus::Pending
+        };
+        let signer_count = (0..header.signer_bitvec.len())
+            .filter(|i| header.signer_bitvec.get(*i).unwrap_or(false))
+            .count();
+        Self {
+            block_id: header.block_id(),
+            block_hash: header.block_hash(),
+            parent_block_id: header.parent_block_id.clone(),
+            height: header.chain_length,
+            status,
+            signer_signature_hash: header.signer_signature_hash(),
+            signer_bitvec: header.signer_bitvec.clone(),
+            signer_count: u16::try_from(signer_count).expect("FATAL: more than u16::MAX signers"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenureBlocks {
+    pub consensus_hash: ConsensusHash,
+    /// all blocks stored for this tenure, in order by height
+    pub blocks: Vec<TenureBlockInfo>,
+}
+
+#[derive(Clone)]
+pub struct RPCNakamotoTenureBlocksRequestHandler {
+    pub consensus_hash: Option<ConsensusHash>,
+}
+
+impl RPCNakamotoTenureBlocksRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            consensus_hash: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCNakamotoTenureBlocksRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/tenures/(?P<consensus_hash>[0-9a-f]{40})/blocks$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/tenures/:consensus_hash/blocks"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let consensus_hash = request::get_consensus_hash(captures, "consensus_hash")?;
+        self.consensus_hash = Some(consensus_hash);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCNakamotoTenureBlocksRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.consensus_hash = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let consensus_hash = self
+            .consensus_hash
+            .take()
+            .ok_or(NetError::SendError("Missing `consensus_hash`".into()))?;
+
+        let blocks_res =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                let headers = chainstate
+                    .nakamoto_blocks_db()
+                    .get_nakamoto_tenure_block_headers(&consensus_hash)?;
+                if headers.is_empty() {
+                    return Err(ChainError::NoSuchBlockError);
+                }
+                Ok(headers
+                    .iter()
+                    .map(|(header, processed, orphaned)| {
+                        TenureBlockInfo::new(header, *processed, *orphaned)
+                    })
+                    .collect())
+            });
+
+        let blocks = match blocks_res {
+            Ok(blocks) => blocks,
+            Err(ChainError::NoSuchBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!("No blocks in tenure {}\n", &consensus_hash)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
+            Err(e) => {
+                // nope -- error trying to check
+                let msg = format!("Failed to load tenure {}: {:?}\n", &consensus_hash, &e);
+                warn!("{}", &msg);
+                return StacksHttpResponse::new_error(&preamble, &HttpServerError::new(msg))
+                    .try_into_contents()
+                    .map_err(NetError::from);
+            }
+        };
+
+        let tenure_blocks = TenureBlocks {
+            consensus_hash,
+            blocks,
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&tenure_blocks)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCNakamotoTenureBlocksRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let tenure_blocks: TenureBlocks = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(tenure_blocks)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the list of blocks in a tenure
+    pub fn new_get_nakamoto_tenure_blocks(
+        host: PeerHost,
+        consensus_hash: ConsensusHash,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v3/tenures/{}/blocks", &consensus_hash),
+            HttpRequestContents::new(),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_nakamoto_tenure_blocks(self) -> Result<TenureBlocks, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let tenure_blocks: TenureBlocks = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(tenure_blocks)
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::{ConsensusHash, StacksBlockId};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::{
+    MerklePath, MerklePathOrder, MerklePathPoint, MerkleTree, Sha512Trunc256Sum,
+};
+
+use crate::burnchains::Txid;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState};
+use crate::chainstate::stacks::{Error as ChainError, StacksTransaction};
+use crate::net::http::{
+    parse_json, Error, HttpGone, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// Which side of its parent node the running hash is on at a step of a Merkle path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionMerklePathOrder {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionMerklePathPoint {
+    pub order: TransactionMerklePathOrder,
+    pub hash: Sha512Trunc256Sum,
+}
+
+/// Proof that a transaction is included in a block: the path from the txid's
+/// leaf up to the block header's `tx_merkle_root`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionMerkleProof {
+    pub tx_merkle_root: Sha512Trunc256Sum,
+    pub path: Vec<TransactionMerklePathPoint>,
+}
+
+impl TransactionMerkleProof {
+    fn new(tx_merkle_root: Sha512Trunc256Sum, path: MerklePath<Sha512Trunc256Sum>) -> Self {
+        let path = path
+            .into_iter()
+            .map(|point| TransactionMerklePathPoint {
+                order: match point.order {
+                    MerklePathOrder::Left => TransactionMerklePathOrder::Left,
+                    MerklePathOrder::Right => TransactionMerklePathOrder::Right,
+                },
+                hash: point.hash,
+            })
+            .collect();
+        Self {
+            tx_merkle_root,
+            path,
+        }
+    }
+
+    pub fn to_merkle_path(&self) -> MerklePath<Sha512Trunc256Sum> {
+        self.path
+            .iter()
+            .map(|point| MerklePathPoint {
+                order: match point.order {
+                    TransactionMerklePathOrder::Left => MerklePathOrder::Left,
+                    TransactionMerklePathOrder::Right => MerklePathOrder::Right,
+                },
+                hash: point.hash.clone(),
+            })
+            .collect()
+    }
+
+    /// Check that `txid` is a leaf of the tree with root `tx_merkle_root`
+    pub fn verify(&self, txid: &Txid) -> bool {
+        MerkleTree::<Sha512Trunc256Sum>::path_verify(
+            txid.as_bytes(),
+            &self.to_merkle_path(),
+            &self.tx_merkle_root,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionResponse {
+    /// block which confirmed this transaction
+    pub index_block_hash: StacksBlockId,
+    /// tenure which produced that block
+    pub consensus_hash: ConsensusHash,
+    pub block_height: u64,
+    /// position of the transaction in the block.
+    /// None if the transaction is not in the block body, e.g. because it was in a
+    /// microblock confirmed by the block, or it is a burnchain operation.
+    pub tx_index: Option<u32>,
+    /// hex-encoded transaction
+    pub tx: String,
+    /// the transaction's result, as a Clarity value representation
+    pub result: String,
+    /// only included if requested, and if the transaction is in the block body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_proof: Option<TransactionMerkleProof>,
+}
+
+#[derive(Clone)]
+pub struct RPCGetTransactionRequestHandler {
+    pub txid: Option<Txid>,
+}
+impl RPCGetTransactionRequestHandler {
+    pub fn new() -> Self {
+        Self { txid: None }
+    }
+}
+
+/// Load the transactions in the body of the block with the given header, along with its
+/// transaction Merkle root.  Fails with `PrunedError` if the block's body was pruned.
+fn load_block_txs(
+    chainstate: &StacksChainState,
+    index_block_hash: &StacksBlockId,
+    consensus_hash: &ConsensusHash,
+    header: &StacksBlockHeaderTypes,
+) -> Result<Option<(Vec<StacksTransaction>, Sha512Trunc256Sum)>, ChainError> {
+    match header {
+        StacksBlockHeaderTypes::Nakamoto(_) => {
+            let _pin = chainstate.pin_nakamoto_block(index_block_hash)?;
+            Ok(chainstate
+                .nakamoto_blocks_db()
+                .get_nakamoto_block(index_block_hash)?
+                .map(|(block, _)| (block.txs, block.header.tx_merkle_root)))
+        }
+        StacksBlockHeaderTypes::Epoch2(header) => {
+            let _pin = chainstate.pin_block(index_block_hash)?;
+            Ok(StacksChainState::load_block(
+                &chainstate.blocks_path,
+                consensus_hash,
+                &header.block_hash(),
+            )?
+            .map(|block| (block.txs, block.header.tx_merkle_root)))
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetTransactionRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/transactions/(?P<txid>[0-9a-f]{64})$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/transactions/:txid"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body for GetTransaction".to_string(),
+            ));
+        }
+
+        let txid = request::get_txid(captures, "txid")?;
+        self.txid = Some(txid);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetTransactionRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.txid = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let txid = self
+            .txid
+            .take()
+            .ok_or(NetError::SendError("`txid` not set".into()))?;
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+        let with_proof = contents.get_with_proof();
+
+        let txindex_enabled =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                chainstate.txindex
+            });
+        if !txindex_enabled {
+            return StacksHttpResponse::new_error(
+                &preamble,
+                &HttpNotFound::new("Transaction index is not enabled on this node".to_string()),
+            )
+            .try_into_contents()
+            .map_err(NetError::from);
+        }
+
+        let txinfo_res: Result<Option<TransactionResponse>, ChainError> =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                // the same transaction can be mined in more than one fork, so find the
+                // instance which is an ancestor of the requested tip
+                let mut indexed_tx = None;
+                for candidate in StacksChainState::get_indexed_transactions(chainstate.db(), &txid)?
+                {
+                    if chainstate
+                        .index_conn()?
+                        .get_ancestor_block_height(&candidate.index_block_hash, &tip)?
+                        .is_some()
+                    {
+                        indexed_tx = Some(candidate);
+                        break;
+                    }
+                }
+                let Some(indexed_tx) = indexed_tx else {
+                    return Ok(None);
+                };
+
+                let header = NakamotoChainState::get_block_header(
+                    chainstate.db(),
+                    &indexed_tx.index_block_hash,
+                )?
+                .ok_or(ChainError::NoSuchBlockError)?;
+
+                let mut tx_index = None;
+                let mut merkle_proof = None;
+                // the transaction itself is in the index, so only its position and proof are
+                // unavailable if the block was pruned
+                let block_txs = match load_block_txs(
+                    chainstate,
+                    &indexed_tx.index_block_hash,
+                    &header.consensus_hash,
+                    &header.anchored_header,
+                ) {
+                    Err(ChainError::PrunedError) if !with_proof => None,
+                    res => res?,
+                };
+                if let Some((txs, tx_merkle_root)) = block_txs {
+                    let txids: Vec<_> =
+                        txs.iter().map(|tx| tx.txid().as_bytes().to_vec()).collect();
+                    tx_index = txids
+                        .iter()
+                        .position(|leaf| leaf.as_slice() == txid.as_bytes())
+                        .map(|index| u32::try_from(index).expect("FATAL: more than 2^32 txs"));
+                    if with_proof && tx_index.is_some() {
+                        merkle_proof = MerkleTree::<Sha512Trunc256Sum>::new(&txids)
+                            .path(txid.as_bytes())
+                            .map(|path| TransactionMerkleProof::new(tx_merkle_root, path));
+                    }
+                }
+
+                Ok(Some(TransactionResponse {
+                    index_block_hash: indexed_tx.index_block_hash,
+                    consensus_hash: header.consensus_hash,
+                    block_height: header.stacks_block_height,
+                    tx_index,
+                    tx: indexed_tx.tx_hex,
+                    result: indexed_tx.result,
+                    merkle_proof,
+                }))
+            });
+
+        let txinfo = match txinfo_res {
+            Ok(Some(txinfo)) => txinfo,
+            Ok(None) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!(
+                        "Transaction {} not found in the transaction index",
+                        &txid
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(ChainError::PrunedError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!(
+                        "The block containing transaction {} has been pruned, so no proof is available",
+                        &txid
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!(
+                        "Failed to query transaction {}: {:?}",
+                        &txid, &e
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&txinfo)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetTransactionRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let txinfo: TransactionResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(txinfo)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a confirmed transaction
+    pub fn new_gettransaction(
+        host: PeerHost,
+        txid: Txid,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v3/transactions/{}", &txid),
+            HttpRequestContents::new()
+                .for_tip(tip_req)
+                .query_arg("proof".into(), if with_proof { "1" } else { "0" }.into()),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_gettransaction(self) -> Result<TransactionResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let txinfo: TransactionResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(txinfo)
+    }
+}
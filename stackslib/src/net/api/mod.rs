@@ -37,13 +37,21 @@ pub mod getattachment;
 pub mod getattachmentsinv;
 pub mod getblock;
 pub mod getblock_v3;
+pub mod getblockbyheight;
+pub mod getclarityblockinfo;
+pub mod getclaritymarfvalue;
+pub mod getclaritymetadata;
 pub mod getconstantval;
 pub mod getcontractabi;
 pub mod getcontractsrc;
 pub mod getdatavar;
+pub mod getdatavars;
+pub mod geteventpoll;
+pub mod geteventstream;
 pub mod getheaders;
 pub mod getinfo;
 pub mod getistraitimplemented;
+pub mod getmapentries;
 pub mod getmapentry;
 pub mod getmicroblocks_confirmed;
 pub mod getmicroblocks_indexed;
@@ -55,7 +63,9 @@ pub mod getstackerdbmetadata;
 pub mod getstackers;
 pub mod getstxtransfercost;
 pub mod gettenure;
+pub mod gettenureblocks;
 pub mod gettenureinfo;
+pub mod gettransaction;
 pub mod gettransaction_unconfirmed;
 pub mod liststackerdbreplicas;
 pub mod postblock;
@@ -65,6 +75,7 @@ pub mod postmempoolquery;
 pub mod postmicroblock;
 pub mod poststackerdbchunk;
 pub mod posttransaction;
+pub mod posttransaction_simulate;
 
 #[cfg(test)]
 mod tests;
@@ -82,15 +93,23 @@ impl StacksHttp {
         self.register_rpc_endpoint(getattachmentsinv::RPCGetAttachmentsInvRequestHandler::new());
         self.register_rpc_endpoint(getblock::RPCBlocksRequestHandler::new());
         self.register_rpc_endpoint(getblock_v3::RPCNakamotoBlockRequestHandler::new());
+        self.register_rpc_endpoint(getblockbyheight::RPCNakamotoBlockByHeightRequestHandler::new());
+        self.register_rpc_endpoint(getclarityblockinfo::RPCGetClarityBlockInfoRequestHandler::new());
+        self.register_rpc_endpoint(getclaritymarfvalue::RPCGetClarityMarfValueRequestHandler::new());
+        self.register_rpc_endpoint(getclaritymetadata::RPCGetClarityMetadataRequestHandler::new());
         self.register_rpc_endpoint(getconstantval::RPCGetConstantValRequestHandler::new());
         self.register_rpc_endpoint(getcontractabi::RPCGetContractAbiRequestHandler::new());
         self.register_rpc_endpoint(getcontractsrc::RPCGetContractSrcRequestHandler::new());
         self.register_rpc_endpoint(getdatavar::RPCGetDataVarRequestHandler::new());
+        self.register_rpc_endpoint(getdatavars::RPCGetDataVarsRequestHandler::new());
+        self.register_rpc_endpoint(geteventpoll::RPCGetEventPollRequestHandler::new());
+        self.register_rpc_endpoint(geteventstream::RPCGetEventStreamRequestHandler::new());
         self.register_rpc_endpoint(getheaders::RPCHeadersRequestHandler::new());
         self.register_rpc_endpoint(getinfo::RPCPeerInfoRequestHandler::new());
         self.register_rpc_endpoint(
             getistraitimplemented::RPCGetIsTraitImplementedRequestHandler::new(),
         );
+        self.register_rpc_endpoint(getmapentries::RPCGetMapEntriesRequestHandler::new());
         self.register_rpc_endpoint(getmapentry::RPCGetMapEntryRequestHandler::new());
         self.register_rpc_endpoint(
             getmicroblocks_confirmed::RPCMicroblocksConfirmedRequestHandler::new(),
@@ -110,7 +129,9 @@ impl StacksHttp {
         );
         self.register_rpc_endpoint(getstackers::GetStackersRequestHandler::default());
         self.register_rpc_endpoint(gettenure::RPCNakamotoTenureRequestHandler::new());
+        self.register_rpc_endpoint(gettenureblocks::RPCNakamotoTenureBlocksRequestHandler::new());
         self.register_rpc_endpoint(gettenureinfo::RPCNakamotoTenureInfoRequestHandler::new());
+        self.register_rpc_endpoint(gettransaction::RPCGetTransactionRequestHandler::new());
         self.register_rpc_endpoint(
             gettransaction_unconfirmed::RPCGetTransactionUnconfirmedRequestHandler::new(),
         );
@@ -126,6 +147,12 @@ impl StacksHttp {
         self.register_rpc_endpoint(postmicroblock::RPCPostMicroblockRequestHandler::new());
         self.register_rpc_endpoint(poststackerdbchunk::RPCPostStackerDBChunkRequestHandler::new());
         self.register_rpc_endpoint(posttransaction::RPCPostTransactionRequestHandler::new());
+        self.register_rpc_endpoint(
+            posttransaction_simulate::RPCSimulateTransactionRequestHandler::new(
+                self.enable_transaction_simulation,
+                self.transaction_simulation_token.clone(),
+            ),
+        );
         self.register_rpc_endpoint(getstackers::GetStackersRequestHandler::default());
     }
 }
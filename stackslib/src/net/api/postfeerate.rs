@@ -85,8 +85,14 @@ impl RPCFeeEstimate {
 pub struct RPCFeeEstimateResponse {
     pub estimated_cost: ExecutionCost,
     pub estimated_cost_scalar: u64,
+    /// low, middle, and high estimates for inclusion in the next block
     pub estimations: Vec<RPCFeeEstimate>,
     pub cost_scalar_change_by_byte: f64,
+    /// low, middle, and high estimates for inclusion within `target_blocks` blocks
+    #[serde(default)]
+    pub estimations_within_target: Vec<RPCFeeEstimate>,
+    #[serde(default)]
+    pub target_blocks: u64,
 }
 
 #[derive(Clone)]
@@ -117,7 +123,7 @@ impl RPCPostFeeRateRequestHandler {
     ) -> Result<RPCFeeEstimateResponse, StacksHttpResponse> {
         let scalar_cost =
             metric.from_cost_and_len(&estimated_cost, &stacks_epoch.block_limit, estimated_len);
-        let fee_rates = fee_estimator.get_rate_estimates().map_err(|e| {
+        let fee_rates = fee_estimator.get_rate_targets().map_err(|e| {
             StacksHttpResponse::new_error(
                 &preamble,
                 &HttpBadRequest::new(format!(
@@ -127,11 +133,17 @@ impl RPCPostFeeRateRequestHandler {
             )
         })?;
 
-        let mut estimations = RPCFeeEstimate::estimate_fees(scalar_cost, fee_rates).to_vec();
+        let mut estimations =
+            RPCFeeEstimate::estimate_fees(scalar_cost, fee_rates.next_block).to_vec();
+        let mut estimations_within_target =
+            RPCFeeEstimate::estimate_fees(scalar_cost, fee_rates.within_target).to_vec();
 
         let minimum_fee = estimated_len * MINIMUM_TX_FEE_RATE_PER_BYTE;
 
-        for estimate in estimations.iter_mut() {
+        for estimate in estimations
+            .iter_mut()
+            .chain(estimations_within_target.iter_mut())
+        {
             if estimate.fee < minimum_fee {
                 estimate.fee = minimum_fee;
             }
@@ -142,6 +154,8 @@ impl RPCPostFeeRateRequestHandler {
             estimations,
             estimated_cost_scalar: scalar_cost,
             cost_scalar_change_by_byte: metric.change_per_byte(),
+            estimations_within_target,
+            target_blocks: fee_rates.target_blocks,
         })
     }
 }
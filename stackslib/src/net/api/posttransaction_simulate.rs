@@ -0,0 +1,348 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::costs::ExecutionCost;
+use regex::{Captures, Regex};
+use stacks_common::codec::{Error as CodecError, StacksMessageCodec, MAX_PAYLOAD_LEN};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::{hex_bytes, to_hex};
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::{Error as ChainError, StacksTransaction};
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulateTransactionRequestBody {
+    /// hex-encoded transaction
+    pub tx: String,
+    /// if true, evaluate the transaction with its origin and sponsor accounts' next nonces
+    /// instead of the nonces it carries
+    #[serde(default)]
+    pub mock_nonce: bool,
+}
+
+/// The would-be receipt of a simulated transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedTransactionReceipt {
+    /// hex-encoded Clarity value
+    pub result: String,
+    pub post_condition_aborted: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_error: Option<String>,
+    pub events: Vec<serde_json::Value>,
+    pub execution_cost: ExecutionCost,
+    pub fee: u64,
+    pub stx_burned: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulateTransactionResponse {
+    pub txid: Txid,
+    /// whether or not the transaction could be mined
+    pub okay: bool,
+    /// why the transaction could not be mined
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<SimulatedTransactionReceipt>,
+}
+
+impl SimulatedTransactionReceipt {
+    fn try_from_receipt(
+        txid: &Txid,
+        fee: u64,
+        receipt: StacksTransactionReceipt,
+    ) -> Result<Self, NetError> {
+        let result = receipt
+            .result
+            .serialize_to_hex()
+            .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
+        let events = receipt
+            .events
+            .iter()
+            .enumerate()
+            .map(|(event_index, event)| event.json_serialize(event_index, txid, false))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
+
+        Ok(Self {
+            result: format!("0x{}", result),
+            post_condition_aborted: receipt.post_condition_aborted,
+            vm_error: receipt.vm_error,
+            events,
+            execution_cost: receipt.execution_cost,
+            fee,
+            stx_burned: receipt.stx_burned,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct RPCSimulateTransactionRequestHandler {
+    pub tx: Option<StacksTransaction>,
+    pub mock_nonce: bool,
+    pub enabled: bool,
+    pub auth: Option<String>,
+}
+impl RPCSimulateTransactionRequestHandler {
+    pub fn new(enabled: bool, auth: Option<String>) -> Self {
+        Self {
+            tx: None,
+            mock_nonce: false,
+            enabled,
+            auth,
+        }
+    }
+
+    /// Decode a bare transaction
+    fn parse_transaction(mut body: &[u8]) -> Result<StacksTransaction, Error> {
+        StacksTransaction::consensus_deserialize(&mut body).map_err(|e| {
+            if let CodecError::DeserializeError(msg) = e {
+                Error::DecodeError(format!("Failed to deserialize posted transaction: {}", msg))
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    /// Decode a JSON-encoded transaction and its simulation options
+    fn parse_simulate_json(body: &[u8]) -> Result<(StacksTransaction, bool), Error> {
+        let body: SimulateTransactionRequestBody = serde_json::from_slice(body)
+            .map_err(|_e| Error::DecodeError("Failed to parse body".into()))?;
+        let tx_bytes =
+            hex_bytes(&body.tx).map_err(|_e| Error::DecodeError("Failed to parse tx".into()))?;
+        let tx = Self::parse_transaction(&tx_bytes)?;
+        Ok((tx, body.mock_nonce))
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCSimulateTransactionRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/transactions/simulate$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/transactions/simulate"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // Each simulation opens a block on a fresh chainstate handle, so the node operator has to
+        // opt in to serving them, and may require a token of their own choosing
+        if !self.enabled {
+            return Err(Error::Http(
+                400,
+                "Bad Request: transaction simulation is not enabled".into(),
+            ));
+        }
+        if let Some(password) = &self.auth {
+            if preamble.headers.get("authorization") != Some(password) {
+                return Err(Error::Http(401, "Unauthorized".into()));
+            }
+        }
+        if preamble.get_content_length() == 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected non-zero-length body for SimulateTransaction"
+                    .to_string(),
+            ));
+        }
+
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(Error::DecodeError(
+                "Invalid Http request: SimulateTransaction body is too big".to_string(),
+            ));
+        }
+
+        match preamble.content_type {
+            None => {
+                return Err(Error::DecodeError(
+                    "Missing Content-Type for transaction".to_string(),
+                ));
+            }
+            Some(HttpContentType::Bytes) => {
+                // expect a bare transaction
+                self.tx = Some(Self::parse_transaction(body)?);
+                self.mock_nonce = false;
+            }
+            Some(HttpContentType::JSON) => {
+                // expect a transaction and simulation options
+                let (tx, mock_nonce) = Self::parse_simulate_json(body)?;
+                self.tx = Some(tx);
+                self.mock_nonce = mock_nonce;
+            }
+            _ => {
+                return Err(Error::DecodeError(
+                    "Wrong Content-Type for transaction; expected application/json or application/octet-stream".to_string(),
+                ));
+            }
+        }
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCSimulateTransactionRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.tx = None;
+        self.mock_nonce = false;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let tx = self
+            .tx
+            .take()
+            .ok_or(NetError::SendError("`tx` not set".into()))?;
+        let mock_nonce = self.mock_nonce;
+        let txid = tx.txid();
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let simulate_res =
+            node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+                // microblock state can't be built upon here, so simulate on top of the
+                // anchored block that the unconfirmed tip confirms
+                let tip = match chainstate.unconfirmed_state {
+                    Some(ref unconfirmed_state)
+                        if unconfirmed_state.unconfirmed_chain_tip == tip =>
+                    {
+                        unconfirmed_state.confirmed_chain_tip.clone()
+                    }
+                    _ => tip,
+                };
+                StacksChainState::simulate_transaction(
+                    chainstate,
+                    &sortdb.index_conn(),
+                    &tip,
+                    &tx,
+                    mock_nonce,
+                )
+            });
+
+        let data_resp = match simulate_res {
+            Ok((fee, receipt)) => SimulateTransactionResponse {
+                txid: txid.clone(),
+                okay: true,
+                cause: None,
+                receipt: Some(SimulatedTransactionReceipt::try_from_receipt(
+                    &txid, fee, receipt,
+                )?),
+            },
+            Err(ChainError::NoSuchBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Chain tip not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => SimulateTransactionResponse {
+                txid,
+                okay: false,
+                cause: Some(e.to_string()),
+                receipt: None,
+            },
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCSimulateTransactionRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let simulation: SimulateTransactionResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(simulation)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to simulate a transaction
+    pub fn new_simulate_transaction(
+        host: PeerHost,
+        tx: StacksTransaction,
+        mock_nonce: bool,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v3/transactions/simulate".to_string(),
+            HttpRequestContents::new().for_tip(tip_req).payload_json(
+                serde_json::to_value(SimulateTransactionRequestBody {
+                    tx: to_hex(&tx.serialize_to_vec()),
+                    mock_nonce,
+                })
+                .expect("FATAL: failed to construct request from infallible data"),
+            ),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_simulate_transaction(self) -> Result<SimulateTransactionResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let simulation: SimulateTransactionResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(simulation)
+    }
+}
@@ -18,6 +18,7 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions};
 use clarity::vm::{ClarityName, ContractName};
+use serde_json::json;
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::types::net::PeerHost;
@@ -27,6 +28,7 @@ use super::test_rpc;
 use crate::core::BLOCK_LIMIT_MAINNET_21;
 use crate::net::api::*;
 use crate::net::connection::ConnectionOptions;
+use crate::net::http::HttpRequestContents;
 use crate::net::httpcore::{
     HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp,
     StacksHttpRequest,
@@ -101,6 +103,55 @@ fn test_try_parse_request() {
     assert!(handler.sender.is_none());
     assert!(handler.sponsor.is_none());
     assert!(handler.arguments.is_none());
+    assert!(handler.arguments_json.is_none());
+}
+
+/// Make a request to run a read-only function, with arguments in JSON
+fn new_callreadonlyfunction_json(
+    host: PeerHost,
+    function_name: &str,
+    arguments_json: serde_json::Value,
+) -> StacksHttpRequest {
+    StacksHttpRequest::new_for_peer(
+        host,
+        "POST".into(),
+        format!(
+            "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/{}",
+            function_name
+        ),
+        HttpRequestContents::new()
+            .for_tip(TipRequest::UseLatestAnchoredTip)
+            .payload_json(json!({
+                "sender": "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R",
+                "arguments_json": arguments_json,
+            })),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_try_parse_request_json() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = new_callreadonlyfunction_json(addr.into(), "set-bar", json!(["6", "-2"]));
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler =
+        callreadonly::RPCCallReadOnlyRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+
+    // JSON arguments are only decoded once the function's signature is loaded
+    assert_eq!(handler.arguments, Some(vec![]));
+    assert_eq!(handler.arguments_json, Some(vec![json!("6"), json!("-2")]));
+
+    handler.restart();
+    assert!(handler.arguments_json.is_none());
 }
 
 #[test]
@@ -184,6 +235,18 @@ fn test_try_make_response() {
     );
     requests.push(request);
 
+    // query with arguments in JSON
+    let request = new_callreadonlyfunction_json(addr.into(), "ro-confirmed", json!([]));
+    requests.push(request);
+
+    // query with JSON arguments of the wrong type
+    let request = new_callreadonlyfunction_json(addr.into(), "set-bar", json!(["6", 0]));
+    requests.push(request);
+
+    // query with JSON arguments, which are passed to the function
+    let request = new_callreadonlyfunction_json(addr.into(), "set-bar", json!(["6", "0"]));
+    requests.push(request);
+
     let mut responses = test_rpc(function_name!(), requests);
 
     // confirmed tip
@@ -270,4 +333,23 @@ fn test_try_make_response() {
 
     let (preamble, payload) = response.destruct();
     assert_eq!(preamble.status_code, 404);
+
+    // arguments in JSON
+    let response = responses.remove(0);
+    let resp = response.decode_call_readonly_response().unwrap();
+    assert!(resp.okay);
+    assert_eq!(resp.result.unwrap(), "0x0100000000000000000000000000000001");
+    assert_eq!(resp.result_json, Some(json!("1")));
+
+    // JSON arguments of the wrong type
+    let response = responses.remove(0);
+    let resp = response.decode_call_readonly_response().unwrap();
+    assert!(!resp.okay);
+    assert!(resp.cause.unwrap().contains("argument `y`"));
+
+    // JSON arguments are passed to the function
+    let response = responses.remove(0);
+    let resp = response.decode_call_readonly_response().unwrap();
+    assert!(!resp.okay);
+    assert!(resp.cause.unwrap().contains("DivisionByZero"));
 }
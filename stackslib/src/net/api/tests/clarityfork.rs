@@ -0,0 +1,253 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests which fork the test chain's Clarity state over the `/v3/clarity/*` endpoints, with
+//! `RemoteClarityNode` and `clarity-cli initialize --fork` talking to a real socket.
+
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use clarity::vm::database::{ClarityDatabase, StoreType};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::Value;
+use rand::Rng;
+use stacks_common::types::chainstate::StacksBlockId;
+
+use super::TestRPC;
+use crate::clarity_cli::invoke_command;
+use crate::clarity_vm::database::remote::{block_info_height, ForkSource, RemoteClarityNode};
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::HttpContentType;
+use crate::net::httpcore::{StacksHttp, StacksHttpMessage, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+const CONTRACT: &str = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world";
+
+/// Read one request from `sock`, answer it with `rpc_test`, and close the connection
+fn serve_request(rpc_test: &mut TestRPC, mut sock: TcpStream) {
+    sock.set_nonblocking(false).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+
+    let addr = sock.local_addr().unwrap();
+    let mut buf = vec![];
+    let mut chunk = [0u8; 4096];
+    let (request, body) = loop {
+        let nread = sock.read(&mut chunk).unwrap();
+        assert!(nread > 0, "Client hung up before sending a request");
+        buf.extend_from_slice(&chunk[..nread]);
+
+        let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+        let Ok((preamble, offset)) = http.read_preamble(&buf) else {
+            continue;
+        };
+        match http.read_payload(&preamble, &buf[offset..]) {
+            Ok((StacksHttpMessage::Request(request), len)) => {
+                break (request, buf[offset..offset + len].to_vec())
+            }
+            Ok(_) => panic!("Not a valid request: {:?}", &preamble),
+            Err(_) => continue,
+        }
+    };
+
+    // parsing a request consumes its body, so put it back before the harness sends it again
+    let (preamble, contents) = request.destruct();
+    let contents = match preamble.content_type {
+        Some(HttpContentType::JSON) => {
+            contents.payload_json(serde_json::from_slice(&body).unwrap())
+        }
+        _ if !body.is_empty() => contents.payload_bytes(body),
+        _ => contents,
+    };
+    let request = StacksHttpRequest::new_for_peer(
+        preamble.host,
+        preamble.verb,
+        preamble.path_and_query_str,
+        contents,
+    )
+    .unwrap();
+    let response = rpc_test.run_request(request);
+    sock.write_all(&response.try_serialize().unwrap()).unwrap();
+    sock.shutdown(Shutdown::Write).unwrap();
+    let _ = sock.read_to_end(&mut vec![]);
+}
+
+/// Run `client` on its own thread, with the address of a node that answers its requests from
+/// `rpc_test`'s chain.  Returns what `client` returns.
+fn with_test_node<T, F>(mut rpc_test: TestRPC, client: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(SocketAddr) -> T + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || client(addr));
+    while !client.is_finished() {
+        match listener.accept() {
+            Ok((sock, _)) => serve_request(&mut rpc_test, sock),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => panic!("Failed to accept a connection: {:?}", &e),
+        }
+    }
+    client.join().unwrap()
+}
+
+#[test]
+fn test_remote_clarity_node() {
+    let rpc_test = TestRPC::setup(function_name!());
+    let tip = rpc_test.canonical_tip.clone();
+
+    with_test_node(rpc_test, move |addr| {
+        let mut node = RemoteClarityNode::new(addr);
+        let contract = QualifiedContractIdentifier::parse(CONTRACT).unwrap();
+
+        // block info, by block and by height
+        let info = node.get_block_info(&tip).unwrap().unwrap();
+        assert_eq!(info.index_block_hash, tip);
+        let height = block_info_height(&info).unwrap();
+        assert_eq!(node.get_block_height(&tip).unwrap(), Some(height));
+        assert_eq!(
+            node.get_block_at_height(&tip, height).unwrap(),
+            Some(tip.clone())
+        );
+        assert_eq!(node.get_block_at_height(&tip, height + 1).unwrap(), None);
+        assert!(node
+            .get_block_info(&StacksBlockId([0x11; 32]))
+            .unwrap()
+            .is_none());
+
+        // MARF values
+        let key =
+            ClarityDatabase::make_key_for_data_map_entry(&contract, "test-map", &Value::UInt(1))
+                .unwrap();
+        assert_eq!(
+            node.get_data(&tip, &key).unwrap(),
+            Some(
+                Value::some(Value::UInt(2))
+                    .unwrap()
+                    .serialize_to_hex()
+                    .unwrap()
+            )
+        );
+        let key = ClarityDatabase::make_key_for_trip(&contract, StoreType::Variable, "bar");
+        assert_eq!(
+            node.get_data(&tip, &key).unwrap(),
+            Some(Value::Int(0).serialize_to_hex().unwrap())
+        );
+        let key = ClarityDatabase::make_key_for_trip(&contract, StoreType::Variable, "nope");
+        assert_eq!(node.get_data(&tip, &key).unwrap(), None);
+
+        // contract metadata
+        let key = ClarityDatabase::make_metadata_key(StoreType::Contract, "contract-size");
+        let size = node.get_metadata(&tip, &contract, &key, None).unwrap();
+        assert!(size.unwrap().parse::<u64>().unwrap() > 0);
+        let key = ClarityDatabase::make_metadata_key(StoreType::Contract, "does-not-exist");
+        assert_eq!(
+            node.get_metadata(&tip, &contract, &key, None).unwrap(),
+            None
+        );
+    });
+}
+
+#[test]
+fn test_cli_initialize_fork() {
+    let rpc_test = TestRPC::setup(function_name!());
+    let tip = rpc_test.canonical_tip.clone();
+
+    let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
+    let map_get_name = format!("/tmp/fork-map-get_{}.clar", rand::thread_rng().gen::<i32>());
+    let var_get_name = format!("/tmp/fork-var-get_{}.clar", rand::thread_rng().gen::<i32>());
+    fs::write(&map_get_name, "(map-get? test-map u1)").unwrap();
+    fs::write(&var_get_name, "(var-get bar)").unwrap();
+
+    with_test_node(rpc_test, move |addr| {
+        let eval = |program: &str| {
+            let (exit, result) = invoke_command(
+                "test",
+                &[
+                    "eval_at_chaintip".to_string(),
+                    CONTRACT.to_string(),
+                    program.to_string(),
+                    db_name.clone(),
+                ],
+            );
+            assert_eq!(exit, 0, "{:?}", &result);
+            result.unwrap()["output_serialized"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let (exit, result) = invoke_command(
+            "test",
+            &[
+                "initialize".to_string(),
+                "--fork".to_string(),
+                addr.to_string(),
+                db_name.clone(),
+            ],
+        );
+        assert_eq!(exit, 0, "{:?}", &result);
+        let result = result.unwrap();
+        assert_eq!(result["network"], "testnet");
+        assert_eq!(result["fork"]["node"], addr.to_string());
+        assert_eq!(result["fork"]["index_block_hash"], tip.to_string());
+
+        // reads go to the forked chain
+        assert_eq!(
+            eval(&map_get_name),
+            Value::some(Value::UInt(2))
+                .unwrap()
+                .serialize_to_hex()
+                .unwrap()
+        );
+        assert_eq!(
+            eval(&var_get_name),
+            Value::Int(0).serialize_to_hex().unwrap()
+        );
+
+        // ...until a local block writes over them
+        let (exit, result) = invoke_command(
+            "test",
+            &[
+                "execute".to_string(),
+                db_name.clone(),
+                CONTRACT.to_string(),
+                "set-bar".to_string(),
+                "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                "10".to_string(),
+                "2".to_string(),
+            ],
+        );
+        assert_eq!(exit, 0, "{:?}", &result);
+        // (this is the `ok` response's inner value)
+        assert_eq!(
+            result.unwrap()["output_serialized"],
+            Value::Int(5).serialize_to_hex().unwrap()
+        );
+        assert_eq!(
+            eval(&var_get_name),
+            Value::Int(5).serialize_to_hex().unwrap()
+        );
+    });
+}
@@ -25,6 +25,7 @@ use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
 
 use super::TestRPC;
+use crate::chainstate::nakamoto::NakamotoChainState;
 use crate::chainstate::stacks::db::blocks::test::*;
 use crate::chainstate::stacks::db::test::instantiate_chainstate;
 use crate::chainstate::stacks::db::{ExtendedStacksHeader, StacksChainState};
@@ -111,6 +112,65 @@ fn test_try_make_response() {
     assert_eq!(preamble.status_code, 404);
 }
 
+#[test]
+fn test_try_make_response_pruned() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let stacks_chain_tip = rpc_test.canonical_tip.clone();
+
+    // prune everything below a tip one block higher than the real one, so the tip's block file
+    // goes away
+    for peer in [&mut rpc_test.peer_1, &mut rpc_test.peer_2] {
+        let tip_height =
+            NakamotoChainState::get_block_header(peer.chainstate().db(), &stacks_chain_tip)
+                .unwrap()
+                .unwrap()
+                .stacks_block_height;
+        let summary = peer
+            .chainstate()
+            .prune_chainstate(tip_height + 1, 0)
+            .unwrap();
+        assert_eq!(summary.blocks, 1);
+        assert!(
+            StacksChainState::is_block_pruned(peer.chainstate().db(), &stacks_chain_tip).unwrap()
+        );
+    }
+
+    let mut requests = vec![];
+
+    // query pruned block
+    let request = StacksHttpRequest::new_getblock(addr.into(), stacks_chain_tip.clone());
+    requests.push(request);
+
+    // its header is still available
+    let request = StacksHttpRequest::new_getheaders(
+        addr.into(),
+        1,
+        TipRequest::SpecificTip(stacks_chain_tip.clone()),
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    // block is gone
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 410);
+
+    // header is not
+    let response = responses.remove(0);
+    let headers = response.decode_stacks_headers().unwrap();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(
+        StacksBlockHeader::make_index_block_hash(
+            &headers[0].consensus_hash,
+            &headers[0].header.block_hash()
+        ),
+        stacks_chain_tip
+    );
+}
+
 #[test]
 fn test_stream_blocks() {
     let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
@@ -186,3 +246,71 @@ fn test_stream_blocks() {
     let staging_block = StacksBlock::consensus_deserialize(&mut &all_block_bytes[..]).unwrap();
     assert_eq!(staging_block, block);
 }
+
+#[test]
+fn test_stream_pruned_block() {
+    let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+    let privk = StacksPrivateKey::from_hex(
+        "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+    )
+    .unwrap();
+
+    let block = make_16k_block(&privk);
+
+    let consensus_hash = ConsensusHash([2u8; 20]);
+    let parent_consensus_hash = ConsensusHash([1u8; 20]);
+    let index_block_header =
+        StacksBlockHeader::make_index_block_hash(&consensus_hash, &block.block_hash());
+    let block_path =
+        StacksChainState::get_index_block_path(&chainstate.blocks_path, &index_block_header)
+            .unwrap();
+
+    store_staging_block(
+        &mut chainstate,
+        &consensus_hash,
+        &block,
+        &parent_consensus_hash,
+        1,
+        2,
+    );
+    set_block_processed(&mut chainstate, &consensus_hash, &block.block_hash(), true);
+
+    // start streaming the block, then prune it
+    let mut stream = StacksBlockStream::new(&chainstate, &index_block_header).unwrap();
+    let summary = chainstate.prune_chainstate(1000, 0).unwrap();
+    assert_eq!(summary.blocks, 1);
+    assert!(StacksChainState::is_block_pruned(chainstate.db(), &index_block_header).unwrap());
+
+    // the prune height is in the chainstate DB
+    assert_eq!(
+        StacksChainState::get_pruned_height(&chainstate.reopen_db().unwrap()).unwrap(),
+        1000
+    );
+
+    // new readers are turned away...
+    match StacksBlockStream::new(&chainstate, &index_block_header) {
+        Err(chainstate_error::PrunedError) => {}
+        res => panic!("Expected PrunedError, got {:?}", &res),
+    }
+
+    // ...but the stream that was already going gets the whole block
+    let mut all_block_bytes = vec![];
+    loop {
+        let mut next_bytes = stream.generate_next_chunk().unwrap();
+        if next_bytes.is_empty() {
+            break;
+        }
+        all_block_bytes.append(&mut next_bytes);
+    }
+    let streamed_block = StacksBlock::consensus_deserialize(&mut &all_block_bytes[..]).unwrap();
+    assert_eq!(streamed_block, block);
+
+    // and the block file goes with it
+    assert!(std::fs::metadata(&block_path).is_ok());
+    drop(stream);
+    assert!(std::fs::metadata(&block_path).is_err());
+
+    // pruning again does not go back over it
+    let summary = chainstate.prune_chainstate(1000, 0).unwrap();
+    assert_eq!(summary.blocks, 0);
+}
@@ -186,3 +186,136 @@ fn test_stream_nakamoto_blocks() {
     let staging_block = NakamotoBlock::consensus_deserialize(&mut &all_block_bytes[..]).unwrap();
     assert_eq!(staging_block.header.block_id(), nakamoto_tip_block_id);
 }
+
+#[test]
+fn test_stream_pruned_nakamoto_block() {
+    let test_observer = TestEventObserver::new();
+    let bitvecs = vec![vec![
+        true, true, true, true, true, true, true, true, true, true,
+    ]];
+
+    let mut peer =
+        make_nakamoto_peer_from_invs(function_name!(), &test_observer, 10, 3, bitvecs.clone());
+
+    let nakamoto_tip = {
+        let sortdb = peer.sortdb.take().unwrap();
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+        let ih = sortdb.index_handle(&tip.sortition_id);
+        let nakamoto_tip = ih.get_nakamoto_tip().unwrap().unwrap();
+        peer.sortdb = Some(sortdb);
+        nakamoto_tip
+    };
+
+    // the tip's parent is in the same tenure, but doesn't start it
+    let nakamoto_tip_block_id = StacksBlockId::new(&nakamoto_tip.0, &nakamoto_tip.1);
+    let tip_header_info = NakamotoChainState::get_block_header_nakamoto(
+        peer.chainstate().db(),
+        &nakamoto_tip_block_id,
+    )
+    .unwrap()
+    .unwrap();
+    let tip_height = tip_header_info.stacks_block_height;
+    let tip_header = tip_header_info
+        .anchored_header
+        .as_stacks_nakamoto()
+        .cloned()
+        .unwrap();
+    let block_id = tip_header.parent_block_id.clone();
+    let header = NakamotoChainState::get_block_header_nakamoto(peer.chainstate().db(), &block_id)
+        .unwrap()
+        .unwrap()
+        .anchored_header
+        .as_stacks_nakamoto()
+        .cloned()
+        .unwrap();
+    assert_eq!(header.consensus_hash, tip_header.consensus_hash);
+
+    let tenure_start_block_id = peer
+        .chainstate()
+        .nakamoto_blocks_db()
+        .get_nakamoto_tenure_start_block(&header.consensus_hash)
+        .unwrap()
+        .unwrap()
+        .header
+        .block_id();
+    assert_ne!(tenure_start_block_id, block_id);
+
+    // start streaming the block, then prune everything up to the tip
+    let mut stream = NakamotoBlockStream::new(
+        peer.chainstate(),
+        block_id.clone(),
+        header.consensus_hash.clone(),
+        header.parent_block_id.clone(),
+    )
+    .unwrap();
+    let summary = peer.chainstate().prune_chainstate(tip_height, 0).unwrap();
+    assert!(summary.nakamoto_blocks > 0);
+
+    let chainstate = peer.chainstate();
+    assert!(StacksChainState::is_nakamoto_block_pruned(
+        chainstate.db(),
+        &chainstate.nakamoto_blocks_db(),
+        &block_id
+    )
+    .unwrap());
+    assert!(!StacksChainState::is_nakamoto_block_pruned(
+        chainstate.db(),
+        &chainstate.nakamoto_blocks_db(),
+        &nakamoto_tip_block_id
+    )
+    .unwrap());
+    assert!(!StacksChainState::is_nakamoto_block_pruned(
+        chainstate.db(),
+        &chainstate.nakamoto_blocks_db(),
+        &tenure_start_block_id
+    )
+    .unwrap());
+
+    // new readers are turned away...
+    match NakamotoBlockStream::new(
+        chainstate,
+        block_id.clone(),
+        header.consensus_hash.clone(),
+        header.parent_block_id.clone(),
+    ) {
+        Err(chainstate_error::PrunedError) => {}
+        Err(e) => panic!("Expected PrunedError, got {:?}", &e),
+        Ok(_) => panic!("Expected PrunedError"),
+    }
+
+    // ...but the stream that was already going gets the whole block
+    let mut all_block_bytes = vec![];
+    loop {
+        let mut next_bytes = stream.generate_next_chunk().unwrap();
+        if next_bytes.is_empty() {
+            break;
+        }
+        all_block_bytes.append(&mut next_bytes);
+    }
+    let streamed_block = NakamotoBlock::consensus_deserialize(&mut &all_block_bytes[..]).unwrap();
+    assert_eq!(streamed_block.header.block_id(), block_id);
+    assert!(!streamed_block.txs.is_empty());
+
+    // and the next pass prunes it
+    drop(stream);
+    let summary = chainstate.prune_chainstate(tip_height, 0).unwrap();
+    assert_eq!(summary.nakamoto_blocks, 1);
+
+    // only its header is left
+    let staging_conn = chainstate.nakamoto_blocks_db();
+    assert_eq!(
+        staging_conn.get_nakamoto_block_size(&block_id).unwrap(),
+        Some(header.serialize_to_vec().len() as u64)
+    );
+    assert!(staging_conn
+        .get_nakamoto_tenure_block_headers(&header.consensus_hash)
+        .unwrap()
+        .iter()
+        .any(|(tenure_header, ..)| tenure_header == &header));
+
+    // the tenure-start block is still there
+    assert!(staging_conn
+        .get_nakamoto_block(&tenure_start_block_id)
+        .unwrap()
+        .is_some());
+}
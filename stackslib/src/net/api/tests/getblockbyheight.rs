@@ -0,0 +1,137 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::chainstate::StacksBlockId;
+
+use super::TestRPC;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::test::TestEventObserver;
+use crate::net::{ProtocolFamily, TipRequest};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = StacksHttpRequest::new_get_nakamoto_block_by_height(
+        addr.into(),
+        123,
+        TipRequest::SpecificTip(StacksBlockId([0x22; 32])),
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getblockbyheight::RPCNakamotoBlockByHeightRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    // consumed path args
+    assert_eq!(handler.block_height, Some(123));
+
+    assert_eq!(&preamble, request.preamble());
+    assert_eq!(
+        contents.tip_request(),
+        TipRequest::SpecificTip(StacksBlockId([0x22; 32]))
+    );
+
+    handler.restart();
+    assert!(handler.block_height.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let test_observer = TestEventObserver::new();
+    let mut rpc_test = TestRPC::setup_nakamoto(function_name!(), &test_observer);
+
+    let nakamoto_chain_tip = rpc_test.canonical_tip.clone();
+    let tip_header = NakamotoChainState::get_block_header(
+        rpc_test.peer_2.chainstate().db(),
+        &nakamoto_chain_tip,
+    )
+    .unwrap()
+    .unwrap();
+    let tip_height = tip_header.stacks_block_height;
+    let parent_block_id = tip_header
+        .anchored_header
+        .as_stacks_nakamoto()
+        .unwrap()
+        .parent_block_id
+        .clone();
+
+    let mut requests = vec![];
+
+    // query the tip by its height
+    let request = StacksHttpRequest::new_get_nakamoto_block_by_height(
+        addr.into(),
+        tip_height,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // query the tip's parent by its height, in the fork of the tip
+    let request = StacksHttpRequest::new_get_nakamoto_block_by_height(
+        addr.into(),
+        tip_height - 1,
+        TipRequest::SpecificTip(nakamoto_chain_tip.clone()),
+    );
+    requests.push(request);
+
+    // query a height beyond the tip
+    let request = StacksHttpRequest::new_get_nakamoto_block_by_height(
+        addr.into(),
+        tip_height + 1,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    // got the tip
+    let response = responses.remove(0);
+    let resp = response.decode_nakamoto_block().unwrap();
+    assert_eq!(resp.header.block_id(), nakamoto_chain_tip);
+
+    // got the parent
+    let response = responses.remove(0);
+    let resp = response.decode_nakamoto_block().unwrap();
+    assert_eq!(resp.header.block_id(), parent_block_id);
+
+    // no block
+    let response = responses.remove(0);
+    let (preamble, body) = response.destruct();
+
+    assert_eq!(preamble.status_code, 404);
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::chainstate::StacksBlockId;
+
+use super::test_rpc;
+use crate::net::api::getclarityblockinfo::ClarityBlockSelector;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp,
+    StacksHttpRequest,
+};
+use crate::net::{ProtocolFamily, TipRequest};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    for block in [
+        ClarityBlockSelector::BlockId(StacksBlockId([0x11; 32])),
+        ClarityBlockSelector::Height(123),
+    ] {
+        let request = StacksHttpRequest::new_getclarityblockinfo(
+            addr.into(),
+            block.clone(),
+            TipRequest::SpecificTip(StacksBlockId([0x22; 32])),
+        );
+        assert_eq!(
+            request.contents().tip_request(),
+            TipRequest::SpecificTip(StacksBlockId([0x22; 32]))
+        );
+
+        let bytes = request.try_serialize().unwrap();
+
+        debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+        let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let mut handler = getclarityblockinfo::RPCGetClarityBlockInfoRequestHandler::new();
+        let mut parsed_request = http
+            .handle_try_parse_request(
+                &mut handler,
+                &parsed_preamble.expect_request(),
+                &bytes[offset..],
+            )
+            .unwrap();
+
+        // consumed path args
+        assert_eq!(handler.block, Some(block));
+
+        // parsed request consumes headers that would not be in a constructed reqeuest
+        parsed_request.clear_headers();
+        let (preamble, contents) = parsed_request.destruct();
+
+        assert_eq!(&preamble, request.preamble());
+
+        handler.restart();
+        assert!(handler.block.is_none());
+    }
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut requests = vec![];
+
+    // query the chain tip by height
+    let request = StacksHttpRequest::new_getclarityblockinfo(
+        addr.into(),
+        ClarityBlockSelector::Height(1),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // query a height above the chain tip
+    let request = StacksHttpRequest::new_getclarityblockinfo(
+        addr.into(),
+        ClarityBlockSelector::Height(100),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // query non-existant block
+    let request = StacksHttpRequest::new_getclarityblockinfo(
+        addr.into(),
+        ClarityBlockSelector::BlockId(StacksBlockId([0x11; 32])),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    let mut responses = test_rpc(function_name!(), requests);
+
+    // chain tip
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    assert_eq!(
+        response.preamble().get_canonical_stacks_tip_height(),
+        Some(1)
+    );
+
+    let resp = response.decode_clarity_block_info_response().unwrap();
+    assert_eq!(resp.block_height, 1);
+    assert_eq!(
+        resp.index_block_hash,
+        StacksBlockId::new(&resp.consensus_hash, &resp.block_hash)
+    );
+    assert!(resp.vrf_seed.is_some());
+    assert!(resp.miner_address.is_some());
+
+    // no such height
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+
+    // no such block
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
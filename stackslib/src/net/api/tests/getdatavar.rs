@@ -16,14 +16,16 @@
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
+use clarity::vm::database::{ClarityDatabase, StoreType};
 use clarity::vm::types::{QualifiedContractIdentifier, StacksAddressExtensions};
-use clarity::vm::{ClarityName, ContractName};
+use clarity::vm::{ClarityName, ContractName, Value};
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
 
-use super::test_rpc;
+use super::{test_rpc, TestRPC};
+use crate::clarity_vm::database::rpc::{ProvableKey, ProvenRPCStore, TrustedHeader};
 use crate::net::api::*;
 use crate::net::connection::ConnectionOptions;
 use crate::net::httpcore::{
@@ -192,3 +194,34 @@ fn test_try_make_response() {
     let (preamble, body) = response.destruct();
     assert_eq!(preamble.status_code, 404);
 }
+
+#[test]
+fn test_try_make_response_verified() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let tip = rpc_test.canonical_tip.clone();
+    let headers = TrustedHeader::load_all(rpc_test.peer_1.chainstate().db()).unwrap();
+    let store = ProvenRPCStore::new(addr, tip.clone(), headers).unwrap();
+
+    let contract =
+        QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world")
+            .unwrap();
+    let key = ClarityDatabase::make_key_for_trip(&contract, StoreType::Variable, "bar");
+    let provable_key = ProvableKey::parse(&key).unwrap();
+    assert_eq!(
+        provable_key,
+        ProvableKey::DataVar(contract.clone(), "bar".into())
+    );
+
+    let mut responses = rpc_test.run(vec![provable_key.make_request(addr, &tip)]);
+
+    let resp = responses.remove(0).decode_data_var_response().unwrap();
+    let proof = resp.marf_proof.as_deref();
+    let (value, _) = store.check_response(&key, &resp.data, proof).unwrap();
+    assert_eq!(value, Value::Int(0).serialize_to_hex().unwrap());
+
+    let forged = Value::Int(1).serialize_to_hex().unwrap();
+    assert!(store.check_response(&key, &forged, proof).is_err());
+    assert!(store.check_response(&key, &resp.data, None).is_err());
+}
@@ -0,0 +1,564 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use serde_json::json;
+
+use super::TestRPC;
+use crate::net::api::geteventpoll::{
+    EventPollResponse, EventPollStream, EventStreamBuffer, EventStreamCursor, EventStreamError,
+    EventStreamFilter, EventStreamKey, StreamEvent, EVENT_POLL_MAX_EVENTS,
+    EVENT_POLL_MAX_WAIT_SECS,
+};
+use crate::net::http::HttpChunkGenerator;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+const CONTRACT_ID: &str = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world";
+
+fn make_new_block_payload(block_height: u64) -> serde_json::Value {
+    json!({
+        "block_height": block_height,
+        "events": [
+            {
+                "event_index": 0,
+                "type": "stx_transfer_event",
+                "stx_transfer_event": { "amount": "1" }
+            },
+            {
+                "event_index": 1,
+                "type": "contract_event",
+                "contract_event": {
+                    "contract_identifier": CONTRACT_ID,
+                    "topic": "print",
+                }
+            },
+            {
+                "event_index": 2,
+                "type": "ft_mint_event",
+                "ft_mint_event": {
+                    "asset_identifier": format!("{}::tokens", CONTRACT_ID),
+                    "amount": "10"
+                }
+            }
+        ]
+    })
+}
+
+fn paths(events: &[StreamEvent]) -> Vec<String> {
+    events.iter().map(|event| event.path.clone()).collect()
+}
+
+/// Fill a buffer with a burn block, a Stacks block, a mempool transaction and a StackerDB
+/// chunk, at each of heights 1 to `num_blocks`.
+fn make_buffer(capacity: usize, num_blocks: u64) -> EventStreamBuffer {
+    let buffer = EventStreamBuffer::new(capacity);
+    for height in 1..=num_blocks {
+        buffer.push(
+            "new_burn_block",
+            &json!({ "burn_block_height": height + 100 }),
+        );
+        buffer.push("new_block", &make_new_block_payload(height));
+        buffer.push("new_mempool_tx", &json!(["0x00"]));
+        buffer.push("stackerdb_chunks", &json!({ "modified_slots": [] }));
+        // not streamed
+        buffer.push("drop_mempool_tx", &json!({ "dropped_txids": [] }));
+    }
+    buffer
+}
+
+#[test]
+fn test_event_stream_keys() {
+    assert_eq!(
+        EventStreamKey::from_string("burn_blocks"),
+        Some(EventStreamKey::BurnchainBlocks)
+    );
+    assert_eq!(
+        EventStreamKey::from_string("*"),
+        Some(EventStreamKey::AnyEvent)
+    );
+    assert!(matches!(
+        EventStreamKey::from_string(&format!("{}::print", CONTRACT_ID)),
+        Some(EventStreamKey::SmartContractEvent(_, topic)) if topic == "print"
+    ));
+    assert!(matches!(
+        EventStreamKey::from_string(&format!("{}.tokens", CONTRACT_ID)),
+        Some(EventStreamKey::AssetEvent(_))
+    ));
+    assert_eq!(EventStreamKey::from_string("block_proposal"), None);
+    assert_eq!(EventStreamKey::from_string("not-a-contract.foo"), None);
+    assert_eq!(EventStreamFilter::from_keys_str("stx,bogus"), None);
+}
+
+#[test]
+fn test_event_stream_buffer() {
+    let buffer = make_buffer(100, 3);
+    let all = EventStreamFilter::All;
+
+    // unfiltered, from the start
+    let resp = buffer
+        .get_events(&EventStreamCursor::Oldest, &all, 100)
+        .unwrap();
+    assert!(!resp.has_more);
+    assert_eq!(resp.next_cursor, Some("3:11".to_string()));
+    let events = resp.events;
+    assert_eq!(events.len(), 12);
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event.seq, i as u64);
+    }
+    // the burn block is recorded at the height of the previous Stacks block
+    assert_eq!(events[0].block_height, 0);
+    assert_eq!(events[1].path, "new_block");
+    assert_eq!(events[1].block_height, 1);
+    assert_eq!(events[2].block_height, 1);
+    assert_eq!(events[11].block_height, 3);
+
+    // by height
+    let events = buffer
+        .get_events(&EventStreamCursor::FromHeight(3), &all, 100)
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].path, "new_block");
+    assert_eq!(events[0].payload["block_height"], 3);
+
+    // by event ID
+    let cursor = EventStreamCursor::from_event_id("2:6").unwrap();
+    let events = buffer.get_events(&cursor, &all, 100).unwrap().events;
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[0].seq, 7);
+
+    // caught up: the cursor stays put
+    let cursor = EventStreamCursor::from_event_id("3:11").unwrap();
+    let resp = buffer.get_events(&cursor, &all, 100).unwrap();
+    assert!(resp.events.is_empty());
+    assert_eq!(resp.next_cursor, Some("3:11".to_string()));
+
+    // an event ID which hasn't happened yet (e.g. from before a restart) is rejected
+    let cursor = EventStreamCursor::from_event_id("3:12").unwrap();
+    assert!(matches!(
+        buffer.get_events(&cursor, &all, 100),
+        Err(EventStreamError::FutureCursor(_))
+    ));
+
+    // in batches
+    let resp = buffer
+        .get_events(&EventStreamCursor::Oldest, &all, 5)
+        .unwrap();
+    assert!(resp.has_more);
+    assert_eq!(resp.events.len(), 5);
+    assert_eq!(resp.next_cursor, Some("1:4".to_string()));
+    let cursor = EventStreamCursor::from_event_id(&resp.next_cursor.unwrap()).unwrap();
+    let resp = buffer.get_events(&cursor, &all, 12).unwrap();
+    assert!(!resp.has_more);
+    assert_eq!(resp.events.len(), 7);
+    assert_eq!(resp.events[0].seq, 5);
+
+    // filtered-out events still advance the cursor
+    let filter = EventStreamFilter::from_keys_str("burn_blocks").unwrap();
+    let resp = buffer
+        .get_events(&EventStreamCursor::FromHeight(3), &filter, 100)
+        .unwrap();
+    assert_eq!(paths(&resp.events), vec!["new_block"]);
+    assert_eq!(resp.next_cursor, Some("3:11".to_string()));
+
+    // oldest events are dropped once full, after which cursors pointing at or before them
+    // have expired
+    let buffer = make_buffer(6, 3);
+    let events = buffer
+        .get_events(&EventStreamCursor::Oldest, &all, 100)
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0].seq, 6);
+    for cursor in [
+        EventStreamCursor::FromHeight(0),
+        EventStreamCursor::FromHeight(2),
+        EventStreamCursor::from_event_id("1:4").unwrap(),
+    ] {
+        assert!(matches!(
+            buffer.get_events(&cursor, &all, 100),
+            Err(EventStreamError::CursorExpired(_))
+        ));
+    }
+    // ...but not if nothing after them was dropped
+    let cursor = EventStreamCursor::from_event_id("2:5").unwrap();
+    assert_eq!(
+        buffer.get_events(&cursor, &all, 100).unwrap().events.len(),
+        6
+    );
+    assert_eq!(
+        buffer
+            .get_events(&EventStreamCursor::FromHeight(3), &all, 100)
+            .unwrap()
+            .events
+            .len(),
+        3
+    );
+}
+
+#[test]
+fn test_event_stream_seed_height() {
+    let all = EventStreamFilter::All;
+    let buffer = EventStreamBuffer::new(100);
+
+    // events which arrive before the first block are recorded at the seeded height
+    buffer.push("new_mempool_tx", &json!(["0x00"]));
+    buffer.seed_block_height(1000);
+    buffer.push("new_burn_block", &json!({ "burn_block_height": 2000 }));
+    let events = buffer
+        .get_events(&EventStreamCursor::Oldest, &all, 100)
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| event.block_height == 1000));
+
+    // events from before the node started aren't buffered
+    assert!(matches!(
+        buffer.get_events(&EventStreamCursor::FromHeight(999), &all, 100),
+        Err(EventStreamError::CursorExpired(_))
+    ));
+    assert_eq!(
+        buffer
+            .get_events(&EventStreamCursor::FromHeight(1000), &all, 100)
+            .unwrap()
+            .events
+            .len(),
+        2
+    );
+
+    // once a block arrives, the seed no longer applies
+    buffer.push("new_block", &make_new_block_payload(1001));
+    buffer.seed_block_height(5);
+    let events = buffer
+        .get_events(&EventStreamCursor::Oldest, &all, 100)
+        .unwrap()
+        .events;
+    assert_eq!(events[2].block_height, 1001);
+    assert_eq!(events[0].block_height, 1000);
+}
+
+#[test]
+fn test_event_stream_filter() {
+    let buffer = make_buffer(100, 2);
+    let cursor = EventStreamCursor::Oldest;
+
+    let get_events = |keys: &str| {
+        let filter = EventStreamFilter::from_keys_str(keys).unwrap();
+        buffer.get_events(&cursor, &filter, 100).unwrap().events
+    };
+    let event_types = |payload: &serde_json::Value| -> Vec<String> {
+        payload["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["type"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // blocks are always sent, but only with the subscribed events
+    let events = get_events("burn_blocks");
+    assert_eq!(
+        paths(&events),
+        vec!["new_burn_block", "new_block", "new_burn_block", "new_block"]
+    );
+    assert!(event_types(&events[1].payload).is_empty());
+
+    let events = get_events("stx,memtx");
+    assert_eq!(
+        paths(&events),
+        vec!["new_block", "new_mempool_tx", "new_block", "new_mempool_tx"]
+    );
+    assert_eq!(event_types(&events[0].payload), vec!["stx_transfer_event"]);
+
+    let events = get_events(&format!("{}::print,{}.tokens", CONTRACT_ID, CONTRACT_ID));
+    assert_eq!(
+        event_types(&events[0].payload),
+        vec!["contract_event", "ft_mint_event"]
+    );
+
+    let events = get_events(&format!("{}::other-topic", CONTRACT_ID));
+    assert!(event_types(&events[0].payload).is_empty());
+
+    // as with event observers, `*` doesn't include StackerDB chunks
+    let events = get_events("*");
+    assert_eq!(events.len(), 6);
+    assert_eq!(event_types(&events[1].payload).len(), 3);
+
+    let events = get_events("stackerdb");
+    assert_eq!(
+        paths(&events),
+        vec![
+            "new_block",
+            "stackerdb_chunks",
+            "new_block",
+            "stackerdb_chunks"
+        ]
+    );
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = StacksHttpRequest::new_get_event_poll(
+        addr.into(),
+        &["burn_blocks", "memtx"],
+        Some(123),
+        None,
+        None,
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = geteventpoll::RPCGetEventPollRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+
+    assert_eq!(handler.cursor, Some(EventStreamCursor::FromHeight(123)));
+    assert_eq!(
+        handler.filter,
+        Some(EventStreamFilter::Keys(vec![
+            EventStreamKey::BurnchainBlocks,
+            EventStreamKey::MemPoolTransactions
+        ]))
+    );
+
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.cursor.is_none());
+    assert!(handler.filter.is_none());
+
+    // cursor takes precedence over from_height
+    let request = StacksHttpRequest::new_get_event_poll(
+        addr.into(),
+        &[],
+        Some(123),
+        Some("5:67".to_string()),
+        None,
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+
+    assert_eq!(
+        handler.cursor,
+        Some(EventStreamCursor::AfterEvent {
+            block_height: 5,
+            seq: 67
+        })
+    );
+    assert_eq!(handler.filter, Some(EventStreamFilter::All));
+
+    // no cursor at all starts from the oldest buffered event
+    handler.restart();
+    let request = StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, None, None);
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(handler.cursor, Some(EventStreamCursor::Oldest));
+    assert_eq!(handler.wait, Some(Duration::ZERO));
+
+    // waits are capped
+    handler.restart();
+    let request = StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, None, Some(3600));
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(
+        handler.wait,
+        Some(Duration::from_secs(EVENT_POLL_MAX_WAIT_SECS))
+    );
+}
+
+#[test]
+fn test_long_poll_waits_for_events() {
+    let buffer = make_buffer(20, 10);
+    let mut stream = EventPollStream::new(
+        buffer.clone(),
+        EventStreamCursor::from_event_id("10:39").unwrap(),
+        EventStreamFilter::from_keys_str("burn_blocks").unwrap(),
+        Duration::from_secs(60),
+    );
+    assert!(!stream.is_chunk_ready());
+
+    // events which were filtered out don't end the wait
+    buffer.push("new_mempool_tx", &json!(["0x01"]));
+    assert!(!stream.is_chunk_ready());
+
+    buffer.push("new_burn_block", &json!({ "burn_block_height": 111 }));
+    assert!(stream.is_chunk_ready());
+    let resp: EventPollResponse =
+        serde_json::from_slice(&stream.generate_next_chunk().unwrap()).unwrap();
+    assert_eq!(paths(&resp.events), vec!["new_burn_block"]);
+    assert_eq!(resp.next_cursor, Some("10:41".to_string()));
+
+    // the whole response is a single chunk
+    assert!(stream.is_chunk_ready());
+    assert!(stream.generate_next_chunk().unwrap().is_empty());
+
+    // once the wait is over, the response has no events
+    let mut stream = EventPollStream::new(
+        buffer,
+        EventStreamCursor::from_event_id("10:41").unwrap(),
+        EventStreamFilter::All,
+        Duration::ZERO,
+    );
+    assert!(stream.is_chunk_ready());
+    let resp: EventPollResponse =
+        serde_json::from_slice(&stream.generate_next_chunk().unwrap()).unwrap();
+    assert!(resp.events.is_empty());
+    assert_eq!(resp.next_cursor, Some("10:41".to_string()));
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    rpc_test.event_stream = Some(make_buffer(20, 10));
+
+    let mut requests = vec![];
+
+    // everything
+    let request = StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, None, None);
+    requests.push(request);
+
+    // burn blocks from height 9
+    let request =
+        StacksHttpRequest::new_get_event_poll(addr.into(), &["burn_blocks"], Some(9), None, None);
+    requests.push(request);
+
+    // resume after the last event
+    let request =
+        StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, Some("10:39".into()), None);
+    requests.push(request);
+
+    // events after this one have been dropped
+    let request =
+        StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, Some("2:5".into()), None);
+    requests.push(request);
+
+    // this event hasn't happened yet
+    let request =
+        StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, Some("10:40".into()), None);
+    requests.push(request);
+
+    // wait for events after the last one, but none arrive
+    let request =
+        StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, Some("10:39".into()), Some(1));
+    requests.push(request);
+
+    // bad filter (the request is rejected before it is handled, which ends the conversation)
+    let request = StacksHttpRequest::new_get_event_poll(addr.into(), &["bogus"], None, None, None);
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    assert_eq!(
+        response.preamble().get_header("content-type".into()),
+        Some("application/json".into())
+    );
+    let resp = response.decode_event_poll().unwrap();
+    assert!(!resp.has_more);
+    assert_eq!(resp.events.len(), 20);
+    assert!(resp.events.len() <= EVENT_POLL_MAX_EVENTS);
+    assert_eq!(resp.events[0].seq, 20);
+    assert_eq!(resp.events[1].event_id(), "6:21");
+    assert_eq!(resp.events[1].payload, make_new_block_payload(6));
+    assert_eq!(resp.next_cursor, Some("10:39".to_string()));
+
+    let response = responses.remove(0);
+    let resp = response.decode_event_poll().unwrap();
+    // the burn block which triggered Stacks block 10 happened at height 9
+    assert_eq!(resp.events.len(), 3);
+    assert_eq!(resp.events[0].path, "new_block");
+    assert_eq!(resp.events[1].path, "new_burn_block");
+    assert_eq!(resp.events[1].payload["burn_block_height"], 110);
+    assert_eq!(resp.next_cursor, Some("10:39".to_string()));
+
+    let response = responses.remove(0);
+    let resp = response.decode_event_poll().unwrap();
+    assert!(resp.events.is_empty());
+    assert_eq!(resp.next_cursor, Some("10:39".to_string()));
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 410);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 400);
+
+    let response = responses.remove(0);
+    let resp = response.decode_event_poll().unwrap();
+    assert!(resp.events.is_empty());
+    assert_eq!(resp.next_cursor, Some("10:39".to_string()));
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 400);
+}
+
+#[test]
+fn test_try_make_response_disabled() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let request = StacksHttpRequest::new_get_event_poll(addr.into(), &[], None, None, None);
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
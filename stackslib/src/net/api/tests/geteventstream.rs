@@ -0,0 +1,288 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use serde_json::json;
+use stacks_common::util::chunked_encoding::HttpChunkedTransferWriterState;
+use stacks_common::util::pipe::Pipe;
+
+use super::TestRPC;
+use crate::net::api::geteventpoll::{
+    EventStreamBuffer, EventStreamCursor, EventStreamFilter, EventStreamKey,
+};
+use crate::net::api::geteventstream::{parse_event_stream, EventSSEStream};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::HttpChunkGenerator;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+/// Fill a buffer with a burn block and a Stacks block at each of heights 1 to `num_blocks`
+fn make_buffer(capacity: usize, num_blocks: u64) -> EventStreamBuffer {
+    let buffer = EventStreamBuffer::new(capacity);
+    for height in 1..=num_blocks {
+        buffer.push(
+            "new_burn_block",
+            &json!({ "burn_block_height": height + 100 }),
+        );
+        buffer.push(
+            "new_block",
+            &json!({ "block_height": height, "events": [] }),
+        );
+    }
+    buffer
+}
+
+/// Get the next chunk of a stream as a string
+fn next_chunk(stream: &mut EventSSEStream) -> String {
+    assert!(stream.is_chunk_ready());
+    String::from_utf8(stream.generate_next_chunk().unwrap()).unwrap()
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request =
+        StacksHttpRequest::new_get_event_stream(addr.into(), &["burn_blocks"], Some(123), None);
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = geteventstream::RPCGetEventStreamRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+
+    assert_eq!(handler.cursor, Some(EventStreamCursor::FromHeight(123)));
+    assert_eq!(
+        handler.filter,
+        Some(EventStreamFilter::Keys(vec![
+            EventStreamKey::BurnchainBlocks
+        ]))
+    );
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.cursor.is_none());
+    assert!(handler.filter.is_none());
+
+    // a reconnecting client's Last-Event-ID takes precedence over from_height
+    let request = StacksHttpRequest::new_get_event_stream(
+        addr.into(),
+        &[],
+        Some(123),
+        Some("5:67".to_string()),
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(
+        handler.cursor,
+        Some(EventStreamCursor::AfterEvent {
+            block_height: 5,
+            seq: 67
+        })
+    );
+    assert_eq!(handler.filter, Some(EventStreamFilter::All));
+
+    // no cursor at all starts from the oldest buffered event
+    handler.restart();
+    let request = StacksHttpRequest::new_get_event_stream(addr.into(), &[], None, None);
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(handler.cursor, Some(EventStreamCursor::Oldest));
+
+    // bad Last-Event-ID
+    handler.restart();
+    let request =
+        StacksHttpRequest::new_get_event_stream(addr.into(), &[], None, Some("bogus".into()));
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_stream_sends_events_as_they_arrive() {
+    let buffer = make_buffer(20, 10);
+    let mut stream = EventSSEStream::new(
+        buffer.clone(),
+        EventStreamCursor::from_event_id("9:17").unwrap(),
+        EventStreamFilter::from_keys_str("burn_blocks").unwrap(),
+        Duration::from_secs(60),
+    );
+
+    // the stream opens with the retry interval, and then the buffered events
+    assert_eq!(next_chunk(&mut stream), "retry: 1000\n\n");
+    let (_, events) = parse_event_stream(&next_chunk(&mut stream)).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event_id(), "9:18");
+    assert_eq!(events[0].path, "new_burn_block");
+    assert_eq!(events[1].event_id(), "10:19");
+    assert_eq!(events[1].path, "new_block");
+
+    // then it waits, even for events which were filtered out
+    assert!(!stream.is_chunk_ready());
+    buffer.push("new_mempool_tx", &json!(["0x01"]));
+    assert!(!stream.is_chunk_ready());
+
+    buffer.push("new_burn_block", &json!({ "burn_block_height": 111 }));
+    let chunk = next_chunk(&mut stream);
+    assert_eq!(
+        chunk,
+        "id: 10:21\nevent: new_burn_block\ndata: {\"burn_block_height\":111}\n\n"
+    );
+    assert!(!stream.is_chunk_ready());
+
+    // an idle stream sends keepalives
+    let mut stream = EventSSEStream::new(
+        buffer.clone(),
+        EventStreamCursor::from_event_id("10:21").unwrap(),
+        EventStreamFilter::All,
+        Duration::ZERO,
+    );
+    assert_eq!(next_chunk(&mut stream), "retry: 1000\n\n");
+    assert_eq!(next_chunk(&mut stream), ": keepalive\n\n");
+
+    // a client which falls behind gets an error, and then the stream ends
+    let mut stream = EventSSEStream::new(
+        buffer.clone(),
+        EventStreamCursor::from_event_id("10:21").unwrap(),
+        EventStreamFilter::All,
+        Duration::from_secs(60),
+    );
+    assert_eq!(next_chunk(&mut stream), "retry: 1000\n\n");
+    for height in 11..=31 {
+        buffer.push(
+            "new_block",
+            &json!({ "block_height": height, "events": [] }),
+        );
+    }
+    let chunk = next_chunk(&mut stream);
+    assert!(chunk.starts_with("event: error\ndata: "));
+    assert!(parse_event_stream(&chunk).unwrap().1.is_empty());
+    assert!(stream.is_chunk_ready());
+    assert!(stream.generate_next_chunk().unwrap().is_empty());
+}
+
+#[test]
+fn test_stream_sends_each_chunk_right_away() {
+    let buffer = make_buffer(20, 10);
+    let mut stream = EventSSEStream::new(
+        buffer,
+        EventStreamCursor::from_event_id("10:19").unwrap(),
+        EventStreamFilter::All,
+        Duration::from_secs(60),
+    );
+
+    // the retry interval is much smaller than a full chunk, but it is sent anyway
+    let (mut pipe_read, mut pipe_write) = Pipe::new();
+    let mut encoder_state = HttpChunkedTransferWriterState::new(stream.hint_chunk_size());
+    assert!(stream.is_chunk_ready());
+    stream
+        .stream_to(&mut encoder_state, &mut pipe_write)
+        .unwrap();
+    pipe_read.set_nonblocking(true);
+    let mut bytes = vec![];
+    loop {
+        let flushed = pipe_write.try_flush().unwrap();
+        let mut buf = [0u8; 64];
+        match pipe_read.read(&mut buf) {
+            Ok(nr) => bytes.extend_from_slice(&buf[..nr]),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock && flushed => break,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => panic!("Failed to read from pipe: {:?}", &e),
+        }
+    }
+    assert_eq!(
+        std::str::from_utf8(&bytes).unwrap(),
+        "d\r\nretry: 1000\n\n\r\n"
+    );
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    rpc_test.event_stream = Some(make_buffer(10, 10));
+
+    let mut requests = vec![];
+
+    // events after this one have been dropped
+    let request =
+        StacksHttpRequest::new_get_event_stream(addr.into(), &[], None, Some("2:3".into()));
+    requests.push(request);
+
+    // this event hasn't happened yet
+    let request =
+        StacksHttpRequest::new_get_event_stream(addr.into(), &[], None, Some("10:20".into()));
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 410);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 400);
+}
+
+#[test]
+fn test_try_make_response_disabled() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let request = StacksHttpRequest::new_get_event_stream(addr.into(), &[], None, None);
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
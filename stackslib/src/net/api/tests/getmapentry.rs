@@ -14,16 +14,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener};
+use std::thread;
 
+use clarity::vm::database::{ClarityBackingStore, ClarityDatabase};
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions};
 use clarity::vm::{ClarityName, ContractName, Value};
-use stacks_common::codec::StacksMessageCodec;
-use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::codec::{StacksMessageCodec, MAX_MESSAGE_LEN};
+use stacks_common::types::chainstate::{StacksAddress, TrieHash};
 use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
+use stacks_common::util::hash::to_hex;
 
-use super::test_rpc;
+use super::{test_rpc, TestRPC};
+use crate::clarity_vm::database::rpc::{ProvableKey, ProvenRPCStore, TrustedHeader};
 use crate::core::BLOCK_LIMIT_MAINNET_21;
 use crate::net::api::*;
 use crate::net::connection::ConnectionOptions;
@@ -213,6 +218,222 @@ fn test_try_make_response() {
     assert_eq!(resp.marf_proof, Some("".to_string()));
 }
 
+#[test]
+fn test_try_make_response_verified() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let tip = rpc_test.canonical_tip.clone();
+    let headers = TrustedHeader::load_all(rpc_test.peer_1.chainstate().db()).unwrap();
+    let other_block = headers
+        .iter()
+        .find(|header| header.index_block_hash != tip)
+        .unwrap()
+        .index_block_hash
+        .clone();
+    let store = ProvenRPCStore::new(addr, tip.clone(), headers.clone()).unwrap();
+    let other_store = ProvenRPCStore::new(addr, other_block, headers).unwrap();
+
+    let contract =
+        QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world")
+            .unwrap();
+    let key = ClarityDatabase::make_key_for_data_map_entry(&contract, "test-map", &Value::UInt(1))
+        .unwrap();
+    let missing_key =
+        ClarityDatabase::make_key_for_data_map_entry(&contract, "test-map", &Value::UInt(2))
+            .unwrap();
+
+    let provable_key = ProvableKey::parse(&key).unwrap();
+    assert_eq!(
+        provable_key,
+        ProvableKey::MapEntry(contract.clone(), "test-map".into(), Value::UInt(1))
+    );
+    assert_eq!(
+        ProvableKey::parse(&format!("vm-account::{}::18", &contract)),
+        None
+    );
+
+    let requests = vec![
+        provable_key.make_request(addr, &tip),
+        ProvableKey::parse(&missing_key)
+            .unwrap()
+            .make_request(addr, &tip),
+    ];
+    let mut responses = rpc_test.run(requests);
+
+    // existing entry: its proof verifies against the tip's state root
+    let resp = responses.remove(0).decode_map_entry_response().unwrap();
+    let proof = resp.marf_proof.as_deref();
+    let (value, proof_bytes) = store.check_response(&key, &resp.data, proof).unwrap();
+    assert_eq!(
+        value,
+        Value::some(Value::UInt(2))
+            .unwrap()
+            .serialize_to_hex()
+            .unwrap()
+    );
+    assert_eq!(
+        Some(format!("0x{}", to_hex(&proof_bytes))).as_deref(),
+        proof
+    );
+
+    // ...but not for another value, another key, or another block
+    let forged = Value::some(Value::UInt(3))
+        .unwrap()
+        .serialize_to_hex()
+        .unwrap();
+    assert!(store.check_response(&key, &forged, proof).is_err());
+    assert!(store
+        .check_response(&missing_key, &resp.data, proof)
+        .is_err());
+    assert!(other_store.check_response(&key, &resp.data, proof).is_err());
+
+    // missing entry: no proof, so it can't be read
+    let resp = responses.remove(0).decode_map_entry_response().unwrap();
+    assert_eq!(resp.marf_proof, Some("".to_string()));
+    assert!(store
+        .check_response(&missing_key, &resp.data, resp.marf_proof.as_deref())
+        .is_err());
+}
+
+/// Serve each of `responses` to one connection, in order
+fn serve_responses(responses: Vec<Vec<u8>>) -> (SocketAddr, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        for response in responses {
+            let (mut sock, _) = listener.accept().unwrap();
+            // the client hangs up on an overlong response
+            let _ = sock.write_all(&response);
+            let _ = sock.shutdown(Shutdown::Write);
+            // drain the request, so that closing the socket doesn't reset the connection
+            let _ = sock.read_to_end(&mut vec![]);
+        }
+    });
+    (addr, handle)
+}
+
+#[test]
+fn test_proven_store_get_data() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let tip = rpc_test.canonical_tip.clone();
+    let headers = TrustedHeader::load_all(rpc_test.peer_1.chainstate().db()).unwrap();
+
+    let contract =
+        QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world")
+            .unwrap();
+    let key = ClarityDatabase::make_key_for_data_map_entry(&contract, "test-map", &Value::UInt(1))
+        .unwrap();
+    let missing_key =
+        ClarityDatabase::make_key_for_data_map_entry(&contract, "test-map", &Value::UInt(2))
+            .unwrap();
+
+    let requests = vec![
+        ProvableKey::parse(&key).unwrap().make_request(addr, &tip),
+        ProvableKey::parse(&missing_key)
+            .unwrap()
+            .make_request(addr, &tip),
+    ];
+    let mut responses = rpc_test.run(requests);
+    let found = responses.remove(0).try_serialize().unwrap();
+    let missing = responses.remove(0).try_serialize().unwrap();
+
+    // the same response, but claiming a different value of the same length
+    let value = Value::some(Value::UInt(2))
+        .unwrap()
+        .serialize_to_hex()
+        .unwrap();
+    let forged_value = Value::some(Value::UInt(3))
+        .unwrap()
+        .serialize_to_hex()
+        .unwrap();
+    let forged = String::from_utf8(found.clone())
+        .unwrap()
+        .replace(&value, &forged_value)
+        .into_bytes();
+    assert_ne!(forged, found);
+    assert_eq!(forged.len(), found.len());
+
+    let mut overlong = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+    overlong.resize(MAX_MESSAGE_LEN as usize + 8192, b'0');
+
+    let (node, server) = serve_responses(vec![found, forged, missing, overlong]);
+    let mut store = ProvenRPCStore::new(node, tip.clone(), headers).unwrap();
+
+    assert_eq!(store.get_data(&key).unwrap(), Some(value));
+    assert!(store.get_data(&key).is_err());
+    assert!(store.get_data(&missing_key).is_err());
+    let err = store.get_data(&key).unwrap_err();
+    assert!(
+        format!("{:?}", &err).contains("OverflowError"),
+        "{:?}",
+        &err
+    );
+    server.join().unwrap();
+
+    // keys which have no provable RPC endpoint are rejected without a request
+    assert!(store
+        .get_data(&format!("vm-account::{}::18", &contract))
+        .is_err());
+
+    // contract metadata is not in the MARF, so it cannot be read or written at all
+    for err in [
+        store.get_metadata(&contract, "contract-size").unwrap_err(),
+        store
+            .get_metadata_manual(0, &contract, "contract-size")
+            .unwrap_err(),
+        store
+            .insert_metadata(&contract, "contract-size", "1")
+            .unwrap_err(),
+        store.get_contract_hash(&contract).unwrap_err(),
+    ] {
+        assert!(format!("{:?}", &err).contains("unverifiable"), "{:?}", &err);
+    }
+}
+
+#[test]
+fn test_proven_store_block_at_height() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let header = |id: u8, parent: u8, height: u32| TrustedHeader {
+        index_block_hash: StacksBlockId([id; 32]),
+        parent_block_id: StacksBlockId([parent; 32]),
+        block_height: height,
+        state_index_root: TrieHash([id; 32]),
+    };
+
+    // 1 <- 2 <- 3, and a fork 1 <- 0x12 <- 0x13
+    let headers = vec![
+        header(1, 0, 0),
+        header(2, 1, 1),
+        header(3, 2, 2),
+        header(0x12, 1, 1),
+        header(0x13, 0x12, 2),
+    ];
+    let mut store = ProvenRPCStore::new(addr, StacksBlockId([3; 32]), headers).unwrap();
+    assert_eq!(store.get_current_block_height(), 2);
+    assert_eq!(store.get_block_at_height(0), Some(StacksBlockId([1; 32])));
+    assert_eq!(store.get_block_at_height(1), Some(StacksBlockId([2; 32])));
+    assert_eq!(store.get_block_at_height(2), Some(StacksBlockId([3; 32])));
+    assert_eq!(store.get_block_at_height(3), None);
+
+    let old_tip = store.set_block_hash(StacksBlockId([0x13; 32])).unwrap();
+    assert_eq!(old_tip, StacksBlockId([3; 32]));
+    assert_eq!(store.get_block_at_height(0), Some(StacksBlockId([1; 32])));
+    assert_eq!(
+        store.get_block_at_height(1),
+        Some(StacksBlockId([0x12; 32]))
+    );
+    assert_eq!(
+        store.get_block_at_height(2),
+        Some(StacksBlockId([0x13; 32]))
+    );
+
+    assert!(store.set_block_hash(StacksBlockId([4; 32])).is_err());
+    assert_eq!(store.get_open_chain_tip(), StacksBlockId([0x13; 32]));
+}
+
 /*
 #[test]
 #[ignore]
@@ -203,3 +203,83 @@ fn test_stream_nakamoto_tenure() {
         nakamoto_tip_block_id
     );
 }
+
+#[test]
+fn test_stream_pruned_nakamoto_tenure() {
+    let test_observer = TestEventObserver::new();
+    let bitvecs = vec![vec![
+        true, true, true, true, true, true, true, true, true, true,
+    ]];
+
+    let mut peer =
+        make_nakamoto_peer_from_invs(function_name!(), &test_observer, 10, 3, bitvecs.clone());
+
+    let nakamoto_tip = {
+        let sortdb = peer.sortdb.take().unwrap();
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+        let ih = sortdb.index_handle(&tip.sortition_id);
+        let nakamoto_tip = ih.get_nakamoto_tip().unwrap().unwrap();
+        peer.sortdb = Some(sortdb);
+        nakamoto_tip
+    };
+
+    let nakamoto_tip_block_id = StacksBlockId::new(&nakamoto_tip.0, &nakamoto_tip.1);
+    let header_info = NakamotoChainState::get_block_header_nakamoto(
+        peer.chainstate().db(),
+        &nakamoto_tip_block_id,
+    )
+    .unwrap()
+    .unwrap();
+    let nakamoto_header = header_info
+        .anchored_header
+        .as_stacks_nakamoto()
+        .cloned()
+        .unwrap();
+
+    // prune everything below the tip
+    let summary = peer
+        .chainstate()
+        .prune_chainstate(header_info.stacks_block_height, 0)
+        .unwrap();
+    assert!(summary.nakamoto_blocks > 0);
+
+    // the tip is still served, but the stream stops at its pruned parent
+    let mut stream = NakamotoTenureStream::new(
+        peer.chainstate(),
+        nakamoto_tip_block_id.clone(),
+        nakamoto_header.consensus_hash.clone(),
+        nakamoto_header.parent_block_id.clone(),
+        None,
+    )
+    .unwrap();
+    let mut all_block_bytes = vec![];
+    loop {
+        let mut next_bytes = stream.generate_next_chunk().unwrap();
+        if next_bytes.is_empty() {
+            break;
+        }
+        all_block_bytes.append(&mut next_bytes);
+    }
+
+    let ptr = &mut all_block_bytes.as_slice();
+    let mut blocks = vec![];
+    while ptr.len() > 0 {
+        let block = NakamotoBlock::consensus_deserialize(ptr).unwrap();
+        blocks.push(block);
+    }
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].header.block_id(), nakamoto_tip_block_id);
+
+    // and the parent itself is gone
+    match NakamotoTenureStream::new(
+        peer.chainstate(),
+        nakamoto_header.parent_block_id.clone(),
+        nakamoto_header.consensus_hash.clone(),
+        StacksBlockId([0x33; 32]),
+        None,
+    ) {
+        Err(chainstate_error::PrunedError) => {}
+        Err(e) => panic!("Expected PrunedError, got {:?}", &e),
+        Ok(_) => panic!("Expected PrunedError"),
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::chainstate::ConsensusHash;
+
+use super::TestRPC;
+use crate::net::api::gettenureblocks::TenureBlockStatus;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::test::TestEventObserver;
+use crate::net::ProtocolFamily;
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request =
+        StacksHttpRequest::new_get_nakamoto_tenure_blocks(addr.into(), ConsensusHash([0x11; 20]));
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = gettenureblocks::RPCNakamotoTenureBlocksRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    // consumed path args
+    assert_eq!(handler.consensus_hash, Some(ConsensusHash([0x11; 20])));
+
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.consensus_hash.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let test_observer = TestEventObserver::new();
+    let rpc_test = TestRPC::setup_nakamoto(function_name!(), &test_observer);
+
+    let nakamoto_chain_tip = rpc_test.canonical_tip.clone();
+    let consensus_hash = rpc_test.consensus_hash.clone();
+
+    let mut requests = vec![];
+
+    // query existing tenure
+    let request =
+        StacksHttpRequest::new_get_nakamoto_tenure_blocks(addr.into(), consensus_hash.clone());
+    requests.push(request);
+
+    // query non-existant tenure
+    let request =
+        StacksHttpRequest::new_get_nakamoto_tenure_blocks(addr.into(), ConsensusHash([0x11; 20]));
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    // got the tenure's blocks
+    let response = responses.remove(0);
+    let resp = response.decode_nakamoto_tenure_blocks().unwrap();
+
+    assert_eq!(resp.consensus_hash, consensus_hash);
+    assert!(!resp.blocks.is_empty());
+    assert_eq!(resp.blocks.last().unwrap().block_id, nakamoto_chain_tip);
+    for (i, block) in resp.blocks.iter().enumerate() {
+        assert_eq!(block.status, TenureBlockStatus::Processed);
+        assert_eq!(block.signer_count, block.signer_bitvec.len());
+        if i > 0 {
+            assert_eq!(block.parent_block_id, resp.blocks[i - 1].block_id);
+            assert_eq!(block.height, resp.blocks[i - 1].height + 1);
+        }
+    }
+
+    // no tenure
+    let response = responses.remove(0);
+    let (preamble, body) = response.destruct();
+
+    assert_eq!(preamble.status_code, 404);
+}
@@ -0,0 +1,165 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::util::hash::to_hex;
+
+use super::TestRPC;
+use crate::burnchains::Txid;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{ProtocolFamily, TipRequest};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = StacksHttpRequest::new_gettransaction(
+        addr.into(),
+        Txid([0x11; 32]),
+        TipRequest::SpecificTip(StacksBlockId([0x22; 32])),
+        true,
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = gettransaction::RPCGetTransactionRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.txid, Some(Txid([0x11; 32])));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+    assert_eq!(
+        contents.tip_request(),
+        TipRequest::SpecificTip(StacksBlockId([0x22; 32]))
+    );
+    assert!(contents.get_with_proof());
+
+    handler.restart();
+    assert!(handler.txid.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+
+    // the chain tip's block has a coinbase and a smart contract
+    let canonical_tip = rpc_test.canonical_tip.clone();
+    let chainstate = rpc_test.peer_2.chainstate();
+    let header = NakamotoChainState::get_block_header(chainstate.db(), &canonical_tip)
+        .unwrap()
+        .unwrap();
+    let block = StacksChainState::load_block(
+        &chainstate.blocks_path,
+        &header.consensus_hash,
+        &header.anchored_header.block_hash(),
+    )
+    .unwrap()
+    .unwrap();
+    let contract_tx = block.txs[1].clone();
+
+    let mut requests = vec![];
+
+    // get confirmed txn with proof
+    let request = StacksHttpRequest::new_gettransaction(
+        addr.into(),
+        contract_tx.txid(),
+        TipRequest::UseLatestAnchoredTip,
+        true,
+    );
+    requests.push(request);
+
+    // get confirmed txn without proof
+    let request = StacksHttpRequest::new_gettransaction(
+        addr.into(),
+        contract_tx.txid(),
+        TipRequest::UseLatestAnchoredTip,
+        false,
+    );
+    requests.push(request);
+
+    // get unknown txn
+    let request = StacksHttpRequest::new_gettransaction(
+        addr.into(),
+        Txid([0x21; 32]),
+        TipRequest::UseLatestAnchoredTip,
+        true,
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_gettransaction().unwrap();
+    assert_eq!(resp.index_block_hash, canonical_tip);
+    assert_eq!(resp.consensus_hash, header.consensus_hash);
+    assert_eq!(resp.block_height, header.stacks_block_height);
+    assert_eq!(resp.tx_index, Some(1));
+    assert_eq!(resp.tx, to_hex(&contract_tx.serialize_to_vec()));
+
+    let proof = resp.merkle_proof.unwrap();
+    assert_eq!(proof.tx_merkle_root, block.header.tx_merkle_root);
+    assert!(proof.verify(&contract_tx.txid()));
+    assert!(!proof.verify(&block.txs[0].txid()));
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_gettransaction().unwrap();
+    assert_eq!(resp.index_block_hash, canonical_tip);
+    assert_eq!(resp.tx_index, Some(1));
+    assert!(resp.merkle_proof.is_none());
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    let (preamble, body) = response.destruct();
+
+    assert_eq!(preamble.status_code, 404);
+}
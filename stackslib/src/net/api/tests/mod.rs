@@ -40,6 +40,9 @@ use crate::chainstate::stacks::{
     TransactionAuth, TransactionPayload, TransactionPostConditionMode, TransactionVersion,
 };
 use crate::core::MemPoolDB;
+use crate::cost_estimates::metrics::UnitMetric;
+use crate::cost_estimates::{FeeEstimator, UnitEstimator};
+use crate::net::api::geteventpoll::EventStreamBuffer;
 use crate::net::db::PeerDB;
 use crate::net::httpcore::{StacksHttpRequest, StacksHttpResponse};
 use crate::net::relay::Relayer;
@@ -51,18 +54,27 @@ use crate::net::{
 };
 
 mod callreadonly;
+mod clarityfork;
 mod getaccount;
 mod getattachment;
 mod getattachmentsinv;
 mod getblock;
 mod getblock_v3;
+mod getblockbyheight;
+mod getclarityblockinfo;
+mod getclaritymarfvalue;
+mod getclaritymetadata;
 mod getconstantval;
 mod getcontractabi;
 mod getcontractsrc;
 mod getdatavar;
+mod getdatavars;
+mod geteventpoll;
+mod geteventstream;
 mod getheaders;
 mod getinfo;
 mod getistraitimplemented;
+mod getmapentries;
 mod getmapentry;
 mod getmicroblocks_confirmed;
 mod getmicroblocks_indexed;
@@ -73,7 +85,9 @@ mod getstackerdbchunk;
 mod getstackerdbmetadata;
 mod getstxtransfercost;
 mod gettenure;
+mod gettenureblocks;
 mod gettenureinfo;
+mod gettransaction;
 mod gettransaction_unconfirmed;
 mod liststackerdbreplicas;
 mod postblock;
@@ -82,6 +96,7 @@ mod postmempoolquery;
 mod postmicroblock;
 mod poststackerdbchunk;
 mod posttransaction;
+mod posttransaction_simulate;
 
 const TEST_CONTRACT: &'static str = "
     (define-trait test-trait
@@ -206,6 +221,11 @@ pub struct TestRPC<'a> {
     pub sendable_txs: Vec<StacksTransaction>,
     /// whether or not to maintain unconfirmed microblocks (e.g. this is false for nakamoto)
     pub unconfirmed_state: bool,
+    /// event stream buffer made available to the node answering requests, if any
+    pub event_stream: Option<EventStreamBuffer>,
+    /// fee estimator made available to the node answering requests, if any.  It is paired with
+    /// a unit cost estimator and cost metric.
+    pub fee_estimator: Option<Box<dyn FeeEstimator>>,
 }
 
 impl<'a> TestRPC<'a> {
@@ -268,6 +288,12 @@ impl<'a> TestRPC<'a> {
         };
         peer_2_config.connection_opts.maximum_call_argument_size = 4096;
 
+        // enables the opt-in endpoints
+        peer_1_config.connection_opts.enable_transaction_simulation = true;
+        peer_1_config.connection_opts.transaction_simulation_token = Some("password".to_string());
+        peer_2_config.connection_opts.enable_transaction_simulation = true;
+        peer_2_config.connection_opts.transaction_simulation_token = Some("password".to_string());
+
         // stacker DBs get initialized thru reconfiguration when the above block gets processed
         peer_1_config.add_stacker_db(
             QualifiedContractIdentifier::new(addr1.clone().into(), "hello-world".into()),
@@ -299,6 +325,36 @@ impl<'a> TestRPC<'a> {
         let mut peer_1 = TestPeer::new(peer_1_config);
         let mut peer_2 = TestPeer::new(peer_2_config);
 
+        // index confirmed transactions so they can be looked up by txid
+        peer_1.chainstate().txindex = true;
+        peer_1.coord.chain_state_db.txindex = true;
+        peer_2.chainstate().txindex = true;
+        peer_2.coord.chain_state_db.txindex = true;
+
+        // index data map keys so that maps can be listed
+        peer_1
+            .chainstate()
+            .clarity_state
+            .enable_map_index()
+            .unwrap();
+        peer_1
+            .coord
+            .chain_state_db
+            .clarity_state
+            .enable_map_index()
+            .unwrap();
+        peer_2
+            .chainstate()
+            .clarity_state
+            .enable_map_index()
+            .unwrap();
+        peer_2
+            .coord
+            .chain_state_db
+            .clarity_state
+            .enable_map_index()
+            .unwrap();
+
         // mine one block with a contract in it
         // first the coinbase
         // make a coinbase for this miner
@@ -818,6 +874,8 @@ impl<'a> TestRPC<'a> {
             next_microblock: Some(microblock),
             sendable_txs,
             unconfirmed_state: true,
+            event_stream: None,
+            fee_estimator: None,
         }
     }
 
@@ -904,159 +962,171 @@ impl<'a> TestRPC<'a> {
             next_microblock: None,
             sendable_txs: vec![],
             unconfirmed_state: false,
+            event_stream: None,
+            fee_estimator: None,
         }
     }
 
     /// Run zero or more HTTP requests on this setup RPC test harness.
     /// Return the list of responses.
-    pub fn run(self, requests: Vec<StacksHttpRequest>) -> Vec<StacksHttpResponse> {
-        let mut peer_1 = self.peer_1;
-        let mut peer_2 = self.peer_2;
-        let peer_1_indexer = self.peer_1_indexer;
-        let peer_2_indexer = self.peer_2_indexer;
-        let mut convo_1 = self.convo_1;
-        let mut convo_2 = self.convo_2;
-        let unconfirmed_state = self.unconfirmed_state;
-
-        let mut responses = vec![];
-        for request in requests.into_iter() {
-            peer_1.refresh_burnchain_view();
-            peer_2.refresh_burnchain_view();
-
-            convo_1.send_request(request.clone()).unwrap();
-            let mut peer_1_mempool = peer_1.mempool.take().unwrap();
-            let peer_2_mempool = peer_2.mempool.take().unwrap();
-
-            debug!("test_rpc: Peer 1 sends to Peer 2");
-            convo_send_recv(&mut convo_1, &mut convo_2);
-
-            // hack around the borrow-checker
-            let peer_1_sortdb = peer_1.sortdb.take().unwrap();
-            let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
-
-            if unconfirmed_state {
-                Relayer::setup_unconfirmed_state(
-                    &mut peer_1_stacks_node.chainstate,
-                    &peer_1_sortdb,
-                )
-                .unwrap();
-            }
+    pub fn run(mut self, requests: Vec<StacksHttpRequest>) -> Vec<StacksHttpResponse> {
+        requests
+            .into_iter()
+            .map(|request| self.run_request(request))
+            .collect()
+    }
 
-            {
-                let rpc_args = RPCHandlerArgs::default();
-                let mut node_state = StacksNodeState::new(
-                    &mut peer_1.network,
-                    &peer_1_sortdb,
-                    &mut peer_1_stacks_node.chainstate,
-                    &mut peer_1_mempool,
-                    &rpc_args,
-                );
-                convo_1.chat(&mut node_state).unwrap();
-            }
+    /// Run one HTTP request on this setup RPC test harness, and return its response.
+    pub fn run_request(&mut self, request: StacksHttpRequest) -> StacksHttpResponse {
+        let TestRPC {
+            peer_1,
+            peer_2,
+            peer_1_indexer,
+            peer_2_indexer,
+            convo_1,
+            convo_2,
+            unconfirmed_state,
+            event_stream,
+            fee_estimator,
+            ..
+        } = self;
 
-            peer_1.sortdb = Some(peer_1_sortdb);
-            peer_1.stacks_node = Some(peer_1_stacks_node);
-            peer_1.mempool = Some(peer_1_mempool);
-            peer_2.mempool = Some(peer_2_mempool);
+        peer_1.refresh_burnchain_view();
+        peer_2.refresh_burnchain_view();
 
-            debug!("test_rpc: Peer 2 sends to Peer 1");
+        convo_1.send_request(request.clone()).unwrap();
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+        let peer_2_mempool = peer_2.mempool.take().unwrap();
 
-            // hack around the borrow-checker
-            let peer_2_sortdb = peer_2.sortdb.take().unwrap();
-            let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
-            let mut peer_2_mempool = peer_2.mempool.take().unwrap();
+        debug!("test_rpc: Peer 1 sends to Peer 2");
+        convo_send_recv(convo_1, convo_2);
 
-            let _ = peer_2
-                .network
-                .refresh_burnchain_view(
-                    &peer_2_indexer,
-                    &peer_2_sortdb,
-                    &mut peer_2_stacks_node.chainstate,
-                    false,
-                )
-                .unwrap();
+        // hack around the borrow-checker
+        let peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
 
-            if unconfirmed_state {
-                Relayer::setup_unconfirmed_state(
-                    &mut peer_2_stacks_node.chainstate,
-                    &peer_2_sortdb,
-                )
+        if *unconfirmed_state {
+            Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
                 .unwrap();
-            }
+        }
 
-            {
-                let rpc_args = RPCHandlerArgs::default();
-                let mut node_state = StacksNodeState::new(
-                    &mut peer_2.network,
-                    &peer_2_sortdb,
-                    &mut peer_2_stacks_node.chainstate,
-                    &mut peer_2_mempool,
-                    &rpc_args,
-                );
-                convo_2.chat(&mut node_state).unwrap();
-            }
+        {
+            let rpc_args = RPCHandlerArgs::default();
+            let mut node_state = StacksNodeState::new(
+                &mut peer_1.network,
+                &peer_1_sortdb,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &rpc_args,
+            );
+            convo_1.chat(&mut node_state).unwrap();
+        }
 
-            peer_2.sortdb = Some(peer_2_sortdb);
-            peer_2.stacks_node = Some(peer_2_stacks_node);
-            let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
+        peer_2.mempool = Some(peer_2_mempool);
 
-            convo_send_recv(&mut convo_2, &mut convo_1);
+        debug!("test_rpc: Peer 2 sends to Peer 1");
 
-            debug!("test_rpc: Peer 1 flush");
+        // hack around the borrow-checker
+        let peer_2_sortdb = peer_2.sortdb.take().unwrap();
+        let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
+        let mut peer_2_mempool = peer_2.mempool.take().unwrap();
 
-            // hack around the borrow-checker
-            convo_send_recv(&mut convo_1, &mut convo_2);
+        let _ = peer_2
+            .network
+            .refresh_burnchain_view(
+                peer_2_indexer,
+                &peer_2_sortdb,
+                &mut peer_2_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
 
-            peer_2.mempool = Some(peer_2_mempool);
+        if *unconfirmed_state {
+            Relayer::setup_unconfirmed_state(&mut peer_2_stacks_node.chainstate, &peer_2_sortdb)
+                .unwrap();
+        }
 
-            let peer_1_sortdb = peer_1.sortdb.take().unwrap();
-            let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+        {
+            let rpc_args = match fee_estimator.as_ref() {
+                Some(fee_estimator) => RPCHandlerArgs {
+                    event_stream: event_stream.as_ref(),
+                    cost_estimator: Some(&UnitEstimator),
+                    fee_estimator: Some(fee_estimator.as_ref()),
+                    cost_metric: Some(&UnitMetric),
+                    ..RPCHandlerArgs::default()
+                },
+                None => RPCHandlerArgs {
+                    event_stream: event_stream.as_ref(),
+                    ..RPCHandlerArgs::default()
+                },
+            };
+            let mut node_state = StacksNodeState::new(
+                &mut peer_2.network,
+                &peer_2_sortdb,
+                &mut peer_2_stacks_node.chainstate,
+                &mut peer_2_mempool,
+                &rpc_args,
+            );
+            convo_2.chat(&mut node_state).unwrap();
+        }
 
-            let _ = peer_1
-                .network
-                .refresh_burnchain_view(
-                    &peer_1_indexer,
-                    &peer_1_sortdb,
-                    &mut peer_1_stacks_node.chainstate,
-                    false,
-                )
-                .unwrap();
+        peer_2.sortdb = Some(peer_2_sortdb);
+        peer_2.stacks_node = Some(peer_2_stacks_node);
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
 
-            if unconfirmed_state {
-                Relayer::setup_unconfirmed_state(
-                    &mut peer_1_stacks_node.chainstate,
-                    &peer_1_sortdb,
-                )
-                .unwrap();
-            }
+        convo_send_recv(convo_2, convo_1);
 
-            {
-                let rpc_args = RPCHandlerArgs::default();
-                let mut node_state = StacksNodeState::new(
-                    &mut peer_1.network,
-                    &peer_1_sortdb,
-                    &mut peer_1_stacks_node.chainstate,
-                    &mut peer_1_mempool,
-                    &rpc_args,
-                );
-                convo_1.chat(&mut node_state).unwrap();
-            }
+        debug!("test_rpc: Peer 1 flush");
+
+        // hack around the borrow-checker
+        convo_send_recv(convo_1, convo_2);
 
-            convo_1.try_flush().unwrap();
+        peer_2.mempool = Some(peer_2_mempool);
 
-            peer_1.sortdb = Some(peer_1_sortdb);
-            peer_1.stacks_node = Some(peer_1_stacks_node);
-            peer_1.mempool = Some(peer_1_mempool);
+        let peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
 
-            // should have gotten a reply
-            let resp_opt = convo_1.try_get_response();
-            assert!(resp_opt.is_some());
+        let _ = peer_1
+            .network
+            .refresh_burnchain_view(
+                peer_1_indexer,
+                &peer_1_sortdb,
+                &mut peer_1_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
 
-            let resp = resp_opt.unwrap();
-            responses.push(resp);
+        if *unconfirmed_state {
+            Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
+                .unwrap();
         }
 
-        return responses;
+        {
+            let rpc_args = RPCHandlerArgs::default();
+            let mut node_state = StacksNodeState::new(
+                &mut peer_1.network,
+                &peer_1_sortdb,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &rpc_args,
+            );
+            convo_1.chat(&mut node_state).unwrap();
+        }
+
+        convo_1.try_flush().unwrap();
+
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
+
+        // should have gotten a reply
+        let resp_opt = convo_1.try_get_response();
+        assert!(resp_opt.is_some());
+
+        resp_opt.unwrap()
     }
 }
 
@@ -16,6 +16,7 @@
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
+use clarity::vm::costs::ExecutionCost;
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions};
 use clarity::vm::{ClarityName, ContractName, Value};
 use stacks_common::types::chainstate::StacksAddress;
@@ -23,9 +24,11 @@ use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
 use stacks_common::util::hash::to_hex;
 
-use super::test_rpc;
+use super::{test_rpc, TestRPC};
+use crate::chainstate::stacks::db::StacksEpochReceipt;
 use crate::chainstate::stacks::TransactionPayload;
 use crate::core::BLOCK_LIMIT_MAINNET_21;
+use crate::cost_estimates::{EstimatorError, FeeEstimator, FeeRateEstimate, FeeRateTargets};
 use crate::net::api::*;
 use crate::net::connection::ConnectionOptions;
 use crate::net::httpcore::{
@@ -110,3 +113,108 @@ fn test_try_make_response() {
     let (preamble, body) = response.destruct();
     assert_eq!(preamble.status_code, 400);
 }
+
+/// Fee estimator with fixed, distinct estimates for the next block and for a later target
+struct FixedTargetsEstimator;
+
+impl FeeEstimator for FixedTargetsEstimator {
+    fn notify_block(
+        &mut self,
+        _receipt: &StacksEpochReceipt,
+        _block_limit: &ExecutionCost,
+    ) -> Result<(), EstimatorError> {
+        Ok(())
+    }
+
+    fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError> {
+        Ok(self.get_rate_targets()?.next_block)
+    }
+
+    fn get_rate_targets(&self) -> Result<FeeRateTargets, EstimatorError> {
+        Ok(FeeRateTargets {
+            next_block: FeeRateEstimate {
+                high: 3000f64,
+                middle: 2000f64,
+                low: 1000f64,
+            },
+            within_target: FeeRateEstimate {
+                high: 300f64,
+                middle: 200f64,
+                low: 100f64,
+            },
+            target_blocks: 3,
+        })
+    }
+}
+
+#[test]
+fn test_try_make_response_with_targets() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let sender_addr =
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+    let tx_payload =
+        TransactionPayload::new_contract_call(sender_addr, "hello-world", "add-unit", vec![])
+            .unwrap();
+
+    let request = StacksHttpRequest::new_post_fee_rate(
+        addr.into(),
+        postfeerate::FeeRateEstimateRequestBody {
+            estimated_len: Some(150),
+            transaction_payload: to_hex(&tx_payload.serialize_to_vec()),
+        },
+    );
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    rpc_test.fee_estimator = Some(Box::new(FixedTargetsEstimator));
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_fee_estimate().unwrap();
+
+    // the unit cost metric always reports a scalar cost of 1
+    assert_eq!(resp.estimated_cost_scalar, 1);
+    assert_eq!(resp.target_blocks, 3);
+    assert_eq!(
+        resp.estimations,
+        vec![
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 1000f64,
+                fee: 1000,
+            },
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 2000f64,
+                fee: 2000,
+            },
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 3000f64,
+                fee: 3000,
+            },
+        ]
+    );
+
+    // the within-target fees are still subject to the minimum relay fee
+    let minimum_fee = 150;
+    assert_eq!(
+        resp.estimations_within_target,
+        vec![
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 100f64,
+                fee: minimum_fee,
+            },
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 200f64,
+                fee: 200,
+            },
+            postfeerate::RPCFeeEstimate {
+                fee_rate: 300f64,
+                fee: 300,
+            },
+        ]
+    );
+}
@@ -0,0 +1,214 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::Value;
+use stacks_common::address::{AddressHashMode, C32_ADDRESS_VERSION_TESTNET_SINGLESIG};
+use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
+
+use super::TestRPC;
+use crate::chainstate::stacks::{
+    StacksTransaction, TransactionAuth, TransactionPayload, TransactionVersion,
+};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::Error;
+use crate::net::httpcore::{
+    HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{Error as NetError, ProtocolFamily, TipRequest};
+
+/// Make an unsigned contract-call to `hello-world.add-unit`
+fn make_add_unit_tx(nonce: u64) -> StacksTransaction {
+    // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
+    let privk1 = StacksPrivateKey::from_hex(
+        "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+    )
+    .unwrap();
+
+    let addr1 = StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(&privk1)],
+    )
+    .unwrap();
+
+    let mut tx_cc = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        TransactionAuth::from_p2pkh(&privk1).unwrap(),
+        TransactionPayload::new_contract_call(addr1, "hello-world", "add-unit", vec![]).unwrap(),
+    );
+
+    tx_cc.chain_id = 0x80000000;
+    tx_cc.auth.set_origin_nonce(nonce);
+    tx_cc.set_tx_fee(123);
+    tx_cc
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let tx_cc = make_add_unit_tx(2);
+    let mut request = StacksHttpRequest::new_simulate_transaction(
+        addr.into(),
+        tx_cc.clone(),
+        true,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    request.add_header("authorization".to_string(), "password".to_string());
+    let bytes = request.try_serialize().unwrap();
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let parsed_preamble = parsed_preamble.expect_request();
+    let mut handler = posttransaction_simulate::RPCSimulateTransactionRequestHandler::new(
+        true,
+        Some("password".to_string()),
+    );
+    let mut parsed_request = http
+        .handle_try_parse_request(&mut handler, &parsed_preamble, &bytes[offset..])
+        .unwrap();
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    parsed_request.add_header("authorization".to_string(), "password".to_string());
+    let (preamble, contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+    assert_eq!(contents.tip_request(), TipRequest::UseLatestAnchoredTip);
+    assert_eq!(handler.tx, Some(tx_cc.clone()));
+    assert!(handler.mock_nonce);
+
+    handler.restart();
+    assert!(handler.tx.is_none());
+    assert!(!handler.mock_nonce);
+
+    // wrong password
+    let mut handler = posttransaction_simulate::RPCSimulateTransactionRequestHandler::new(
+        true,
+        Some("other-password".to_string()),
+    );
+    match http.handle_try_parse_request(&mut handler, &parsed_preamble, &bytes[offset..]) {
+        Err(NetError::Http(Error::Http(401, _))) => {}
+        x => panic!("expected 401, got {:?}", &x),
+    }
+
+    // enabled without a password, so no authorization is needed
+    let mut handler =
+        posttransaction_simulate::RPCSimulateTransactionRequestHandler::new(true, None);
+    http.handle_try_parse_request(&mut handler, &parsed_preamble, &bytes[offset..])
+        .unwrap();
+    assert_eq!(handler.tx, Some(tx_cc));
+
+    // disabled, even with the right password
+    let mut handler = posttransaction_simulate::RPCSimulateTransactionRequestHandler::new(
+        false,
+        Some("password".to_string()),
+    );
+    match http.handle_try_parse_request(&mut handler, &parsed_preamble, &bytes[offset..]) {
+        Err(NetError::Http(Error::Http(400, _))) => {}
+        x => panic!("expected 400, got {:?}", &x),
+    }
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let sendable_tx = rpc_test.sendable_txs[0].clone();
+    let mut requests = vec![];
+
+    // unsigned contract-call with the right nonce
+    let mut request = StacksHttpRequest::new_simulate_transaction(
+        addr.into(),
+        make_add_unit_tx(2),
+        false,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    request.add_header("authorization".to_string(), "password".to_string());
+    requests.push(request);
+
+    // STX transfer with a nonce that's too high
+    let mut request = StacksHttpRequest::new_simulate_transaction(
+        addr.into(),
+        sendable_tx.clone(),
+        false,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    request.add_header("authorization".to_string(), "password".to_string());
+    requests.push(request);
+
+    // same STX transfer, but with a mocked nonce
+    let mut request = StacksHttpRequest::new_simulate_transaction(
+        addr.into(),
+        sendable_tx.clone(),
+        true,
+        TipRequest::UseLatestAnchoredTip,
+    );
+    request.add_header("authorization".to_string(), "password".to_string());
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_simulate_transaction().unwrap();
+    assert!(resp.okay, "{:?}", &resp.cause);
+    let receipt = resp.receipt.unwrap();
+    assert_eq!(
+        Value::try_deserialize_hex_untyped(&receipt.result).unwrap(),
+        Value::okay(Value::Int(1)).unwrap()
+    );
+    assert!(!receipt.post_condition_aborted);
+    assert_eq!(receipt.fee, 123);
+    assert!(receipt.execution_cost.runtime > 0);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_simulate_transaction().unwrap();
+    assert_eq!(resp.txid, sendable_tx.txid());
+    assert!(!resp.okay);
+    assert!(resp.cause.is_some());
+    assert!(resp.receipt.is_none());
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_simulate_transaction().unwrap();
+    assert!(resp.okay, "{:?}", &resp.cause);
+    let receipt = resp.receipt.unwrap();
+    assert_eq!(
+        Value::try_deserialize_hex_untyped(&receipt.result).unwrap(),
+        Value::okay_true()
+    );
+    assert_eq!(receipt.events.len(), 1);
+    assert_eq!(receipt.events[0]["type"], "stx_transfer_event");
+}
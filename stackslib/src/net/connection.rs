@@ -417,6 +417,10 @@ pub struct ConnectionOptions {
     pub force_nakamoto_epoch_transition: bool,
     /// The authorization token to enable the block proposal RPC endpoint
     pub block_proposal_token: Option<String>,
+    /// Whether to enable the transaction simulation RPC endpoint
+    pub enable_transaction_simulation: bool,
+    /// If set, the authorization token required by the transaction simulation RPC endpoint
+    pub transaction_simulation_token: Option<String>,
 }
 
 impl std::default::Default for ConnectionOptions {
@@ -511,6 +515,8 @@ impl std::default::Default for ConnectionOptions {
             force_disconnect_interval: None,
             force_nakamoto_epoch_transition: false,
             block_proposal_token: None,
+            enable_transaction_simulation: false,
+            transaction_simulation_token: None,
         }
     }
 }
@@ -1066,7 +1066,7 @@ impl PeerNetwork {
 
     /// Do we need to download an anchored block?
     /// already have an anchored block?
-    fn need_anchored_block(
+    pub(crate) fn need_anchored_block(
         _local_peer: &LocalPeer,
         chainstate: &StacksChainState,
         consensus_hash: &ConsensusHash,
@@ -1084,6 +1084,16 @@ impl PeerNetwork {
             );
             return Ok(false);
         }
+        if StacksChainState::is_block_pruned(chainstate.db(), &index_block_hash)? {
+            test_debug!(
+                "{:?}: Block was processed and then pruned: {}/{} ({})",
+                _local_peer,
+                consensus_hash,
+                block_hash,
+                &index_block_hash
+            );
+            return Ok(false);
+        }
         Ok(true)
     }
 
@@ -33,13 +33,13 @@ use serde_json;
 use stacks_common::codec::Error as CodecError;
 
 pub use crate::net::http::common::{
-    parse_bytes, parse_bytestream, parse_json, HttpReservedHeader, HttpVersion,
+    parse_bytes, parse_bytestream, parse_json, parse_raw_bytes, HttpReservedHeader, HttpVersion,
     HTTP_PREAMBLE_MAX_NUM_HEADERS,
 };
 pub use crate::net::http::error::{
     http_error_from_code_and_text, http_reason, HttpBadRequest, HttpError, HttpErrorResponse,
-    HttpForbidden, HttpNotFound, HttpPaymentRequired, HttpServerError, HttpServiceUnavailable,
-    HttpUnauthorized,
+    HttpForbidden, HttpGone, HttpNotFound, HttpPaymentRequired, HttpServerError,
+    HttpServiceUnavailable, HttpUnauthorized,
 };
 pub use crate::net::http::request::{
     HttpRequest, HttpRequestContents, HttpRequestPayload, HttpRequestPreamble,
@@ -153,6 +153,7 @@ pub enum HttpContentType {
     Bytes,
     Text,
     JSON,
+    EventStream,
 }
 
 impl fmt::Display for HttpContentType {
@@ -167,6 +168,7 @@ impl HttpContentType {
             HttpContentType::Bytes => "application/octet-stream",
             HttpContentType::Text => "text/plain",
             HttpContentType::JSON => "application/json",
+            HttpContentType::EventStream => "text/event-stream",
         }
     }
 }
@@ -182,6 +184,8 @@ impl FromStr for HttpContentType {
             Ok(HttpContentType::Text)
         } else if s == "application/json" {
             Ok(HttpContentType::JSON)
+        } else if s == "text/event-stream" {
+            Ok(HttpContentType::EventStream)
         } else {
             Err(CodecError::DeserializeError(
                 "Unsupported HTTP content type".to_string(),
@@ -111,6 +111,17 @@ impl HttpResponseContents {
         }
     }
 
+    /// Is there data ready to be written with `pipe_out()`?  Only a stream whose generator is
+    /// waiting for its data is not ready.
+    pub fn is_ready(&mut self) -> bool {
+        match self {
+            HttpResponseContents::Stream(ref mut inner_stream) => {
+                inner_stream.generator.is_chunk_ready()
+            }
+            HttpResponseContents::RAM(..) => true,
+        }
+    }
+
     /// Write data for this to a pipe writer, which buffers it up.
     /// Return Ok(Some(..)) if there is mroe data to send.
     /// Once all data is sent, return Ok(None)
@@ -36,6 +36,20 @@ pub trait HttpChunkGenerator: Send {
     fn generate_next_chunk(&mut self) -> Result<Vec<u8>, String>;
     fn hint_chunk_size(&self) -> usize;
 
+    /// Is the next chunk ready to be generated?  A generator which waits for its data (e.g. a
+    /// long poll) returns false until it is, and is asked again on the next pass of the event
+    /// loop.  This never blocks.
+    fn is_chunk_ready(&mut self) -> bool {
+        true
+    }
+
+    /// Should each chunk be sent as soon as it's generated, instead of being buffered until
+    /// `hint_chunk_size()` bytes have been generated?  A generator which sends small messages
+    /// as they happen (e.g. an event stream) wants this.
+    fn send_partial_chunks(&self) -> bool {
+        false
+    }
+
     /// Stream one chunk to the pipe writer.  This never blocks.
     /// Returns Ok(num-bytes > 0) if there are more chunks (i.e. the caller should call this again)
     /// Returns Ok(0) if there are no more chunks (i.e. the caller should not call this again)
@@ -61,6 +75,9 @@ pub trait HttpChunkGenerator: Send {
             }
         } else {
             encoder.write_all(&chunk)?;
+            if self.send_partial_chunks() {
+                encoder.send_buffered_chunk()?;
+            }
         }
 
         Ok(chunk.len() as u64)
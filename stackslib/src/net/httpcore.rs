@@ -17,7 +17,8 @@
 /// This module binds the http library to Stacks as a `ProtocolFamily` implementation
 use std::collections::{BTreeMap, HashMap};
 use std::io::{Read, Write};
-use std::net::SocketAddr;
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
 use std::{fmt, io, mem};
 
 use clarity::vm::costs::ExecutionCost;
@@ -291,6 +292,21 @@ pub mod request {
         };
         Ok(u)
     }
+
+    /// Get and parse a u64 from a path's captures, given the name of the regex field.
+    pub fn get_u64(captures: &Captures, key: &str) -> Result<u64, HttpError> {
+        let u = if let Some(u64_str) = captures.name(key) {
+            match u64_str.as_str().parse::<u64>() {
+                Ok(x) => x,
+                Err(_e) => {
+                    return Err(HttpError::Http(400, format!("Failed to decode `{}`", key)));
+                }
+            }
+        } else {
+            return Err(HttpError::Http(404, format!("Missing `{}`", key)));
+        };
+        Ok(u)
+    }
 }
 
 /// Extension to HttpRequestContents to give it awareness of Stacks-specific fields
@@ -883,6 +899,10 @@ pub struct StacksHttp {
     pub read_only_call_limit: ExecutionCost,
     /// The authorization token to enable the block proposal RPC endpoint
     pub block_proposal_token: Option<String>,
+    /// Whether to enable the transaction simulation RPC endpoint
+    pub enable_transaction_simulation: bool,
+    /// If set, the authorization token required by the transaction simulation RPC endpoint
+    pub transaction_simulation_token: Option<String>,
 }
 
 impl StacksHttp {
@@ -899,6 +919,8 @@ impl StacksHttp {
             maximum_call_argument_size: conn_opts.maximum_call_argument_size,
             read_only_call_limit: conn_opts.read_only_call_limit.clone(),
             block_proposal_token: conn_opts.block_proposal_token.clone(),
+            enable_transaction_simulation: conn_opts.enable_transaction_simulation,
+            transaction_simulation_token: conn_opts.transaction_simulation_token.clone(),
         };
         http.register_rpc_methods();
         http
@@ -1275,7 +1297,6 @@ impl StacksHttp {
     }
 
     /// Given a fully-formed single HTTP response, parse it (used by clients).
-    #[cfg(test)]
     pub fn parse_response(
         verb: &str,
         request_path: &str,
@@ -1318,6 +1339,62 @@ impl StacksHttp {
             Ok(message)
         }
     }
+
+    /// Send a single request to the node at `addr` over a blocking socket, and read back and
+    /// parse its response (used by clients).  The connection is closed afterwards.
+    pub fn send_request(
+        addr: &SocketAddr,
+        mut request: StacksHttpRequest,
+        timeout: Duration,
+    ) -> Result<StacksHttpResponse, NetError> {
+        request.preamble_mut().keep_alive = false;
+        let mut request_bytes = vec![];
+        request.send(&mut request_bytes)?;
+
+        let mut sock = TcpStream::connect_timeout(addr, timeout).map_err(|e| {
+            debug!("Failed to connect to {}: {:?}", addr, &e);
+            NetError::ConnectionError
+        })?;
+        sock.set_read_timeout(Some(timeout))
+            .map_err(NetError::ReadError)?;
+        sock.set_write_timeout(Some(timeout))
+            .map_err(NetError::WriteError)?;
+
+        sock.write_all(&request_bytes)
+            .map_err(NetError::WriteError)?;
+
+        // a response is a preamble and a message, so don't read any more than the largest of each
+        let max_len = u64::from(HTTP_PREAMBLE_MAX_ENCODED_SIZE) + u64::from(MAX_MESSAGE_LEN);
+        let mut response_bytes = vec![];
+        (&mut sock)
+            .take(max_len + 1)
+            .read_to_end(&mut response_bytes)
+            .map_err(NetError::ReadError)?;
+        if response_bytes.len() as u64 > max_len {
+            debug!("Response from {} is longer than {} bytes", addr, max_len);
+            return Err(NetError::OverflowError(format!(
+                "Response from {} is too long",
+                addr
+            )));
+        }
+        if response_bytes.is_empty() {
+            return Err(NetError::PermanentlyDrained);
+        }
+
+        // handlers match the path without its query string
+        let verb = &request.preamble().verb;
+        let (decoded_path, _) = decode_request_path(&request.preamble().path_and_query_str)?;
+        match StacksHttp::parse_response(verb, &decoded_path, &response_bytes)? {
+            StacksHttpMessage::Response(response) => Ok(response),
+            StacksHttpMessage::Error(path, response) => {
+                debug!("Request to {} for {} failed", addr, &path);
+                Ok(response)
+            }
+            StacksHttpMessage::Request(_) => Err(NetError::DeserializeError(
+                "Invalid HTTP message: expected a response".to_string(),
+            )),
+        }
+    }
 }
 
 impl ProtocolFamily for StacksHttp {
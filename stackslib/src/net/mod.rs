@@ -83,6 +83,7 @@ use crate::core::mempool::*;
 use crate::core::{StacksEpoch, POX_REWARD_CYCLE_LENGTH};
 use crate::cost_estimates::metrics::CostMetric;
 use crate::cost_estimates::{CostEstimator, FeeEstimator, FeeRateEstimate};
+use crate::net::api::geteventpoll::EventStreamBuffer;
 use crate::net::atlas::{Attachment, AttachmentInstance};
 use crate::net::dns::*;
 use crate::net::http::error::{HttpNotFound, HttpServerError};
@@ -629,6 +630,8 @@ pub struct RPCHandlerArgs<'a> {
     pub cost_metric: Option<&'a dyn CostMetric>,
     /// coordinator channels
     pub coord_comms: Option<&'a CoordinatorChannels>,
+    /// buffer of recent events for the RPC event stream, if enabled
+    pub event_stream: Option<&'a EventStreamBuffer>,
 }
 
 impl<'a> RPCHandlerArgs<'a> {
@@ -116,6 +116,8 @@ pub struct ConversationHttp {
     pending_response: Option<StacksHttpResponse>,
     /// whether or not there's an error response pending
     pending_error_response: bool,
+    /// whether or not the reply being sent is waiting for its data (e.g. a long poll)
+    reply_waiting: bool,
     /// how much data to buffer (i.e. the socket's send buffer size)
     socket_send_buffer_size: u32,
 }
@@ -166,6 +168,7 @@ impl ConversationHttp {
             pending_request: None,
             pending_response: None,
             pending_error_response: false,
+            reply_waiting: false,
             keep_alive: true,
             total_request_count: 0,
             total_reply_count: 0,
@@ -312,6 +315,7 @@ impl ConversationHttp {
             self.reply_streams.len()
         );
         let _self_str = format!("{}", &self);
+        self.reply_waiting = false;
 
         if let Some((ref mut reply, ref mut http_response, ref keep_alive)) =
             self.reply_streams.front_mut()
@@ -319,6 +323,12 @@ impl ConversationHttp {
             do_keep_alive = *keep_alive;
 
             while !drained_stream {
+                if !http_response.is_ready() {
+                    // the handler is still waiting for the data to send; try again on the next
+                    // pass of the event loop
+                    self.reply_waiting = true;
+                    break;
+                }
                 // write out the last-generated data into the write-end of the reply handle's pipe
                 if let Some(pipe_fd) = reply.inner_pipe_out() {
                     let num_written = http_response.pipe_out(pipe_fd)?;
@@ -479,6 +489,12 @@ impl ConversationHttp {
         Ok(())
     }
 
+    /// Is the reply being sent waiting for its data?  Such a reply has to be pushed out to the
+    /// socket once it's ready, since the socket won't become ready in the meantime.
+    pub fn is_reply_waiting(&self) -> bool {
+        self.reply_waiting
+    }
+
     /// Is the connection idle?
     pub fn is_idle(&self) -> bool {
         self.pending_response.is_none()
@@ -614,10 +614,22 @@ impl HttpPeer {
 
         // flush each outgoing conversation
         for (event_id, ref mut convo) in self.peers.iter_mut() {
+            let was_waiting = convo.is_reply_waiting();
             if let Err(e) = convo.try_flush() {
                 info!("Broken HTTP connection {:?}: {:?}", convo, &e);
                 close.push(*event_id);
             }
+            if was_waiting {
+                // a reply which was waiting for its data may have generated some (e.g. a long
+                // poll which is now ready, or an event stream which sent another event), but its
+                // socket has been idle, so there won't be a socket event to send it on
+                if let Some(client_sock) = self.sockets.get_mut(event_id) {
+                    if let Err(e) = HttpPeer::saturate_http_socket(client_sock, convo) {
+                        debug!("Failed to send HTTP data to event {}: {:?}", event_id, &e);
+                        close.push(*event_id);
+                    }
+                }
+            }
             if convo.is_drained() && !convo.is_keep_alive() {
                 // did some work, but nothing more to do and we're not keep-alive
                 debug!("Close drained HTTP connection {:?}", convo);
@@ -694,7 +706,7 @@ mod test {
     use crate::chainstate::stacks::db::blocks::test::*;
     use crate::chainstate::stacks::db::StacksChainState;
     use crate::chainstate::stacks::test::*;
-    use crate::chainstate::stacks::{Error as chain_error, StacksBlockHeader, *, *};
+    use crate::chainstate::stacks::{Error as chain_error, StacksBlockHeader, *};
     use crate::net::codec::*;
     use crate::net::http::*;
     use crate::net::httpcore::*;
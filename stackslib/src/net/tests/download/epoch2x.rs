@@ -29,11 +29,17 @@ use super::*;
 use crate::burnchains::tests::TestMiner;
 use crate::chainstate::burn::db::sortdb::*;
 use crate::chainstate::burn::operations::*;
+use crate::chainstate::stacks::db::blocks::test::{
+    make_16k_block, set_block_processed, store_staging_block,
+};
 use crate::chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
+use crate::chainstate::stacks::db::test::instantiate_chainstate;
 use crate::chainstate::stacks::miner::*;
 use crate::chainstate::stacks::tests::*;
 use crate::chainstate::stacks::*;
+use crate::core::NETWORK_P2P_PORT;
 use crate::net::codec::*;
+use crate::net::db::LocalPeer;
 use crate::net::download::BlockDownloader;
 use crate::net::inv::inv2x::*;
 use crate::net::relay::*;
@@ -1510,3 +1516,69 @@ pub fn test_get_blocks_and_microblocks_2_peers_download_multiple_microblock_desc
         );
     })
 }
+
+#[test]
+fn test_need_anchored_block_pruned() {
+    let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+    let privk = StacksPrivateKey::from_hex(
+        "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+    )
+    .unwrap();
+    let local_peer = LocalPeer::new(
+        0x80000000,
+        0x80000000,
+        PeerAddress::from_ipv4(127, 0, 0, 1),
+        NETWORK_P2P_PORT,
+        None,
+        get_epoch_time_secs() + 123456,
+        UrlString::try_from("http://foo.com").unwrap(),
+        vec![],
+    );
+
+    let block = make_16k_block(&privk);
+    let consensus_hash = ConsensusHash([2u8; 20]);
+    let parent_consensus_hash = ConsensusHash([1u8; 20]);
+
+    // not stored yet
+    assert!(PeerNetwork::need_anchored_block(
+        &local_peer,
+        &chainstate,
+        &consensus_hash,
+        &block.block_hash()
+    )
+    .unwrap());
+
+    store_staging_block(
+        &mut chainstate,
+        &consensus_hash,
+        &block,
+        &parent_consensus_hash,
+        1,
+        2,
+    );
+    set_block_processed(&mut chainstate, &consensus_hash, &block.block_hash(), true);
+
+    let summary = chainstate.prune_chainstate(1000, 0).unwrap();
+    assert_eq!(summary.blocks, 1);
+
+    // a pruned block was had, so it is neither fetched again nor reported missing
+    assert!(!PeerNetwork::need_anchored_block(
+        &local_peer,
+        &chainstate,
+        &consensus_hash,
+        &block.block_hash()
+    )
+    .unwrap());
+    assert!(StacksChainState::has_stored_block(
+        chainstate.db(),
+        &chainstate.blocks_path,
+        &consensus_hash,
+        &block.block_hash()
+    )
+    .unwrap());
+    let inv = chainstate
+        .get_blocks_inventory(&[(consensus_hash.clone(), Some(block.block_hash()))])
+        .unwrap();
+    assert_eq!(inv.bitlen, 1);
+    assert_eq!(inv.block_bitvec, vec![1]);
+}
@@ -16,6 +16,7 @@ use stacks::burnchains::bitcoin::BitcoinNetworkType;
 use stacks::burnchains::{Burnchain, MagicBytes, PoxConstants, BLOCKSTACK_MAGIC_MAINNET};
 use stacks::chainstate::nakamoto::signer_set::NakamotoSigners;
 use stacks::chainstate::stacks::boot::MINERS_NAME;
+use stacks::chainstate::stacks::db::prune::MIN_PRUNE_DEPTH;
 use stacks::chainstate::stacks::index::marf::MARFOpenOpts;
 use stacks::chainstate::stacks::index::storage::TrieHashCalculationMode;
 use stacks::chainstate::stacks::miner::{BlockBuilderSettings, MinerStatus};
@@ -27,11 +28,14 @@ use stacks::core::{
     BITCOIN_TESTNET_STACKS_25_REORGED_HEIGHT, CHAIN_ID_MAINNET, CHAIN_ID_TESTNET,
     PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
 };
+use stacks::cost_estimates::fee_histogram::HistogramFeeRateEstimator;
 use stacks::cost_estimates::fee_medians::WeightedMedianFeeRateEstimator;
 use stacks::cost_estimates::fee_rate_fuzzer::FeeRateFuzzer;
 use stacks::cost_estimates::fee_scalar::ScalarFeeRateEstimator;
 use stacks::cost_estimates::metrics::{CostMetric, ProportionalDotProduct, UnitMetric};
-use stacks::cost_estimates::{CostEstimator, FeeEstimator, PessimisticEstimator, UnitEstimator};
+use stacks::cost_estimates::{
+    CostEstimator, FeeEstimator, LearningEstimator, PessimisticEstimator, UnitEstimator,
+};
 use stacks::net::atlas::AtlasConfig;
 use stacks::net::connection::ConnectionOptions;
 use stacks::net::{Neighbor, NeighborKey};
@@ -54,6 +58,8 @@ const DEFAULT_RBF_FEE_RATE_INCREMENT: u64 = 5;
 const LEADER_KEY_TX_ESTIM_SIZE: u64 = 290;
 const BLOCK_COMMIT_TX_ESTIM_SIZE: u64 = 350;
 const INV_REWARD_CYCLES_TESTNET: u64 = 6;
+const DEFAULT_EVENT_OBSERVER_RETRY_BACKOFF_MS: u64 = 1_000;
+const DEFAULT_EVENT_OBSERVER_MAX_RETRY_BACKOFF_MS: u64 = 60_000;
 
 #[derive(Clone, Deserialize, Default, Debug)]
 pub struct ConfigFile {
@@ -143,6 +149,55 @@ mod tests {
         assert!(Config::from_config_file(ConfigFile::from_str("").unwrap(), false).is_ok());
     }
 
+    #[test]
+    fn test_fee_estimation_config() {
+        assert_eq!(
+            format!("Fee estimation config error: Invalid value for `cost_estimator_quantile`: 1.5. Expected a value in [0, 1]"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [fee_estimation]
+                    cost_estimator = "learning"
+                    cost_estimator_quantile = 1.5
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+
+        assert_eq!(
+            format!("Fee estimation config error: Invalid value for `cost_estimator_min_confidence`: -0.1. Expected a value in [0, 1]"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [fee_estimation]
+                    cost_estimator = "learning"
+                    cost_estimator_min_confidence = -0.1
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [fee_estimation]
+                cost_estimator = "learning"
+                cost_estimator_quantile = 1.0
+                cost_estimator_min_confidence = 0.0
+                "#,
+            )
+            .unwrap(),
+            false
+        )
+        .is_ok());
+    }
+
     #[test]
     fn should_load_legacy_mstx_balances_toml() {
         let config = ConfigFile::from_str(
@@ -208,6 +263,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_load_transaction_simulation_options() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                block_proposal_token = "password"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert!(!config.connection_options.enable_transaction_simulation);
+        assert_eq!(config.connection_options.transaction_simulation_token, None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                enable_transaction_simulation = true
+                transaction_simulation_token = "simulate"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse transaction simulation options from file");
+        assert!(config.connection_options.enable_transaction_simulation);
+        assert_eq!(
+            config.connection_options.transaction_simulation_token,
+            Some("simulate".to_string())
+        );
+    }
+
+    #[test]
+    fn should_reject_shallow_prune_depth() {
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                prune_depth = 100
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "node.prune_depth must be at least 2100 (one reward cycle)"
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                prune_depth = 2100
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse prune_depth from file");
+        assert_eq!(config.node.prune_depth, Some(2100));
+    }
+
     #[test]
     fn should_load_affirmation_map() {
         let affirmation_string = "nnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnpppppnnnnnnnnnnnnnnnnnnnnnnnpppppppppppppppnnnnnnnnnnnnnnnnnnnnnnnppppppppppnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnppppppppnnnnnnnnnnnnnnnnnnnnnnnppnppnnnnnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnnnppppppnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnnpppppppnnnnnnnnnnnnnnnnnnnnnnnnnnpnnnnnnnnnnnnnnnnnnnnnnnnnpppnppppppppppppppnnppppnpa";
@@ -305,6 +427,46 @@ mod tests {
         assert_eq!(config.burnchain.affirmation_overrides.len(), 5);
         assert_eq!(config.burnchain.affirmation_overrides[&413], affirmation);
     }
+
+    #[test]
+    fn should_parse_transaction_event_keys() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("SP000000000000000000002Q6VF78.pox-4").unwrap();
+        assert_eq!(
+            EventKeyType::from_string("principal:SP000000000000000000002Q6VF78"),
+            Some(EventKeyType::PrincipalTransactions(
+                PrincipalData::parse("SP000000000000000000002Q6VF78").unwrap()
+            ))
+        );
+        assert_eq!(
+            EventKeyType::from_string("principal:SP000000000000000000002Q6VF78.pox-4"),
+            Some(EventKeyType::PrincipalTransactions(
+                contract_id.clone().into()
+            ))
+        );
+        assert_eq!(
+            EventKeyType::from_string(
+                "contract_call:SP000000000000000000002Q6VF78.pox-4::stack-stx"
+            ),
+            Some(EventKeyType::ContractCallTransactions((
+                contract_id.clone(),
+                "stack-stx".to_string()
+            )))
+        );
+        // without the prefix, this is a print topic
+        assert_eq!(
+            EventKeyType::from_string("SP000000000000000000002Q6VF78.pox-4::stack-stx"),
+            Some(EventKeyType::SmartContractEvent((
+                contract_id,
+                "stack-stx".to_string()
+            )))
+        );
+        assert_eq!(EventKeyType::from_string("principal:not-a-principal"), None);
+        assert_eq!(
+            EventKeyType::from_string("contract_call:SP000000000000000000002Q6VF78.pox-4"),
+            None
+        );
+    }
 }
 
 impl ConfigFile {
@@ -1093,9 +1255,18 @@ impl Config {
 
                     let endpoint = format!("{}", observer.endpoint);
 
+                    let default_observer = EventObserverConfig::default();
                     observers.insert(EventObserverConfig {
                         endpoint,
                         events_keys,
+                        timeout_ms: observer.timeout_ms,
+                        retry_backoff_ms: observer
+                            .retry_backoff_ms
+                            .unwrap_or(default_observer.retry_backoff_ms),
+                        max_retry_backoff_ms: observer
+                            .max_retry_backoff_ms
+                            .unwrap_or(default_observer.max_retry_backoff_ms),
+                        disable_after_failures: observer.disable_after_failures,
                     });
                 }
                 observers
@@ -1109,6 +1280,7 @@ impl Config {
                 events_observers.insert(EventObserverConfig {
                     endpoint: val,
                     events_keys: vec![EventKeyType::AnyEvent],
+                    ..EventObserverConfig::default()
                 });
                 ()
             }
@@ -1125,6 +1297,10 @@ impl Config {
             None => default_estimator,
         };
 
+        estimation
+            .validate()
+            .map_err(|e| format!("Fee estimation config error: {e}"))?;
+
         let atlas = match config_file.atlas {
             Some(f) => f.into_config(is_mainnet),
             None => AtlasConfig::new(is_mainnet),
@@ -1174,6 +1350,20 @@ impl Config {
         path
     }
 
+    /// Returns the path to the event observers' delivery queue database, which lives
+    /// next to the chainstate, and ensures its directory exists.
+    pub fn get_event_observer_db_path(&self) -> PathBuf {
+        let mut path = self.get_chainstate_path();
+        fs::create_dir_all(&path).unwrap_or_else(|_| {
+            panic!(
+                "Failed to create chainstate directory at {}",
+                path.to_string_lossy()
+            )
+        });
+        path.push("event_observers.sqlite");
+        path
+    }
+
     pub fn get_chainstate_path_str(&self) -> String {
         self.get_chainstate_path()
             .to_str()
@@ -1817,17 +2007,38 @@ pub struct NodeConfig {
     pub chain_liveness_poll_time_secs: u64,
     /// stacker DBs we replicate
     pub stacker_dbs: Vec<QualifiedContractIdentifier>,
+    /// Record every processed transaction in the chainstate's txid index,
+    /// so that confirmed transactions can be looked up over RPC.
+    pub txindex: bool,
+    /// If set, delete the data of processed blocks and microblocks which are more than this
+    /// many blocks below the canonical tip, along with the Clarity MARF tries of forks abandoned
+    /// that far back.  Nakamoto blocks keep their headers, and tenure-start blocks are kept
+    /// whole.  The Clarity state of the canonical chain is kept in full, so the savings are
+    /// mostly block data.  A pruned node cannot serve old blocks, nor process forks deeper than
+    /// this.
+    /// Must be at least one reward cycle (`MIN_PRUNE_DEPTH`, 2100 blocks).
+    pub prune_depth: Option<u64>,
+    /// Record the keys of contract data maps as they are written, so that maps can be listed
+    /// over RPC (`/v3/contracts/:principal/:contract_name/maps/:map_name/entries`).
+    /// Only keys written after this is turned on are listed.
+    pub map_index: bool,
+    /// How many recent events to keep for the RPC event stream (`/v3/events/stream` and
+    /// `/v3/events/poll`).
+    /// The event stream is disabled if this is 0.
+    pub event_stream_buffer: usize,
 }
 
 #[derive(Clone, Debug)]
 pub enum CostEstimatorName {
     NaivePessimistic,
+    Learning,
 }
 
 #[derive(Clone, Debug)]
 pub enum FeeEstimatorName {
     ScalarFeeRate,
     FuzzedWeightedMedianFeeRate,
+    HistogramFeeRate,
 }
 
 #[derive(Clone, Debug)]
@@ -1857,6 +2068,8 @@ impl CostEstimatorName {
     fn panic_parse(s: String) -> CostEstimatorName {
         if &s.to_lowercase() == "naive_pessimistic" {
             CostEstimatorName::NaivePessimistic
+        } else if &s.to_lowercase() == "learning" {
+            CostEstimatorName::Learning
         } else {
             panic!(
                 "Bad cost estimator name supplied in configuration file: {}",
@@ -1872,6 +2085,8 @@ impl FeeEstimatorName {
             FeeEstimatorName::ScalarFeeRate
         } else if &s.to_lowercase() == "fuzzed_weighted_median_fee_rate" {
             FeeEstimatorName::FuzzedWeightedMedianFeeRate
+        } else if &s.to_lowercase() == "histogram_fee_rate" {
+            FeeEstimatorName::HistogramFeeRate
         } else {
             panic!(
                 "Bad fee estimator name supplied in configuration file: {}",
@@ -1900,9 +2115,22 @@ pub struct FeeEstimationConfig {
     /// If using FeeRateFuzzer, the amount of random noise, as a percentage of the base value (in
     /// [0, 1]) to add for fuzz. See comments on FeeRateFuzzer.
     pub fee_rate_fuzzer_fraction: f64,
-    /// If using WeightedMedianFeeRateEstimator, the window size to use. See comments on
-    /// WeightedMedianFeeRateEstimator.
+    /// If using WeightedMedianFeeRateEstimator or HistogramFeeRateEstimator, the window size to
+    /// use. See comments on WeightedMedianFeeRateEstimator.
     pub fee_rate_window_size: u64,
+    /// If using HistogramFeeRateEstimator, the number of blocks within which a transaction
+    /// should be included for its `within_target` estimate. See comments on
+    /// HistogramFeeRateEstimator.
+    pub fee_rate_target_blocks: u64,
+    /// If using LearningEstimator, the quantile of recent costs to estimate (in [0, 1]). See
+    /// comments on LearningEstimator.
+    pub cost_estimator_quantile: f64,
+    /// If using LearningEstimator, the confidence score (in [0, 1]) below which estimates fall
+    /// back to the pessimistic estimator.
+    pub cost_estimator_min_confidence: f64,
+    /// If using LearningEstimator, whether or not contract-calls are also keyed by the size of
+    /// their arguments.
+    pub cost_estimator_condition_on_args: bool,
 }
 
 impl Default for FeeEstimationConfig {
@@ -1914,6 +2142,28 @@ impl Default for FeeEstimationConfig {
             log_error: false,
             fee_rate_fuzzer_fraction: 0.1f64,
             fee_rate_window_size: 5u64,
+            fee_rate_target_blocks: 6u64,
+            cost_estimator_quantile: 0.9f64,
+            cost_estimator_min_confidence: 0.5f64,
+            cost_estimator_condition_on_args: true,
+        }
+    }
+}
+
+impl FeeEstimationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.cost_estimator_quantile) {
+            Err(format!(
+                "Invalid value for `cost_estimator_quantile`: {}. Expected a value in [0, 1]",
+                self.cost_estimator_quantile
+            ))
+        } else if !(0.0..=1.0).contains(&self.cost_estimator_min_confidence) {
+            Err(format!(
+                "Invalid value for `cost_estimator_min_confidence`: {}. Expected a value in [0, 1]",
+                self.cost_estimator_min_confidence
+            ))
+        } else {
+            Ok(())
         }
     }
 }
@@ -1928,6 +2178,10 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
                 log_error: false,
                 fee_rate_fuzzer_fraction: 0f64,
                 fee_rate_window_size: 0u64,
+                fee_rate_target_blocks: 0u64,
+                cost_estimator_quantile: 0f64,
+                cost_estimator_min_confidence: 0f64,
+                cost_estimator_condition_on_args: false,
             };
         }
         let cost_estimator = f
@@ -1950,6 +2204,10 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
             log_error,
             fee_rate_fuzzer_fraction: f.fee_rate_fuzzer_fraction.unwrap_or(0.1f64),
             fee_rate_window_size: f.fee_rate_window_size.unwrap_or(5u64),
+            fee_rate_target_blocks: f.fee_rate_target_blocks.unwrap_or(6u64),
+            cost_estimator_quantile: f.cost_estimator_quantile.unwrap_or(0.9f64),
+            cost_estimator_min_confidence: f.cost_estimator_min_confidence.unwrap_or(0.5f64),
+            cost_estimator_condition_on_args: f.cost_estimator_condition_on_args.unwrap_or(true),
         }
     }
 }
@@ -1962,6 +2220,10 @@ impl Config {
                     self.estimation
                         .make_pessimistic_cost_estimator(self.get_estimates_path()),
                 ),
+                CostEstimatorName::Learning => Box::new(
+                    self.estimation
+                        .make_learning_cost_estimator(self.get_estimates_path()),
+                ),
             };
 
         Some(cost_estimator)
@@ -1986,6 +2248,11 @@ impl Config {
             FeeEstimatorName::FuzzedWeightedMedianFeeRate => self
                 .estimation
                 .make_fuzzed_weighted_median_fee_estimator(self.get_estimates_path(), metric),
+            FeeEstimatorName::HistogramFeeRate => self.estimation.make_histogram_fee_estimator(
+                self.get_estimates_path(),
+                self.get_chainstate_path_str(),
+                metric,
+            ),
         };
 
         Some(fee_estimator)
@@ -2006,6 +2273,29 @@ impl FeeEstimationConfig {
         }
     }
 
+    // Creates a LearningEstimator which falls back to the pessimistic estimator's data.
+    pub fn make_learning_cost_estimator(&self, estimates_path: PathBuf) -> LearningEstimator {
+        if let Some(CostEstimatorName::Learning) = self.cost_estimator.as_ref() {
+            let mut pessimistic_path = estimates_path.clone();
+            pessimistic_path.push("cost_estimator_pessimistic.sqlite");
+            let fallback = PessimisticEstimator::open(&pessimistic_path, self.log_error)
+                .expect("Error opening cost estimator");
+
+            let mut learning_path = estimates_path;
+            learning_path.push("cost_estimator_learning.sqlite");
+            LearningEstimator::open(
+                &learning_path,
+                fallback,
+                self.cost_estimator_quantile,
+                self.cost_estimator_min_confidence,
+                self.cost_estimator_condition_on_args,
+            )
+            .expect("Error opening cost estimator")
+        } else {
+            panic!("BUG: Expected to configure a learning cost estimator");
+        }
+    }
+
     pub fn make_scalar_fee_estimator<CM: CostMetric + 'static>(
         &self,
         mut estimates_path: PathBuf,
@@ -2047,6 +2337,36 @@ impl FeeEstimationConfig {
             panic!("BUG: Expected to configure a weighted median fee estimator");
         }
     }
+
+    // Creates a HistogramFeeRateEstimator which also considers the pending transactions in the
+    // mempool under `chainstate_path`.
+    pub fn make_histogram_fee_estimator<CM: CostMetric + 'static>(
+        &self,
+        mut estimates_path: PathBuf,
+        chainstate_path: String,
+        metric: CM,
+    ) -> Box<dyn FeeEstimator> {
+        if let Some(FeeEstimatorName::HistogramFeeRate) = self.fee_estimator.as_ref() {
+            estimates_path.push("fee_histogram.sqlite");
+            let mempool_path = MemPoolDB::db_path(&chainstate_path)
+                .expect("Unable to produce mempool path")
+                .into();
+            Box::new(
+                HistogramFeeRateEstimator::open(
+                    &estimates_path,
+                    Some(mempool_path),
+                    metric,
+                    self.fee_rate_window_size
+                        .try_into()
+                        .expect("Configured fee rate window size out of bounds."),
+                    self.fee_rate_target_blocks,
+                )
+                .expect("Error opening fee estimator"),
+            )
+        } else {
+            panic!("BUG: Expected to configure a histogram fee estimator");
+        }
+    }
 }
 
 impl Default for NodeConfig {
@@ -2098,6 +2418,10 @@ impl Default for NodeConfig {
             fault_injection_hide_blocks: false,
             chain_liveness_poll_time_secs: 300,
             stacker_dbs: vec![],
+            txindex: false,
+            prune_depth: None,
+            map_index: false,
+            event_stream_buffer: 0,
         }
     }
 }
@@ -2389,6 +2713,8 @@ pub struct ConnectionOptionsFile {
     pub antientropy_public: Option<bool>,
     pub private_neighbors: Option<bool>,
     pub block_proposal_token: Option<String>,
+    pub enable_transaction_simulation: Option<bool>,
+    pub transaction_simulation_token: Option<String>,
     pub antientropy_retry: Option<u64>,
 }
 
@@ -2515,6 +2841,8 @@ impl ConnectionOptionsFile {
             antientropy_public: self.antientropy_public.unwrap_or(true),
             private_neighbors: self.private_neighbors.unwrap_or(true),
             block_proposal_token: self.block_proposal_token,
+            enable_transaction_simulation: self.enable_transaction_simulation.unwrap_or(false),
+            transaction_simulation_token: self.transaction_simulation_token,
             antientropy_retry: self.antientropy_retry.unwrap_or(default.antientropy_retry),
             ..default
         })
@@ -2554,6 +2882,14 @@ pub struct NodeConfigFile {
     pub chain_liveness_poll_time_secs: Option<u64>,
     /// Stacker DBs we replicate
     pub stacker_dbs: Option<Vec<String>>,
+    /// Maintain an index of confirmed transactions
+    pub txindex: Option<bool>,
+    /// Prune block data more than this many blocks below the tip (at least one reward cycle)
+    pub prune_depth: Option<u64>,
+    /// Maintain an index of contract data map keys
+    pub map_index: Option<bool>,
+    /// Number of recent events to serve over the RPC event stream (0 disables it)
+    pub event_stream_buffer: Option<usize>,
 }
 
 impl NodeConfigFile {
@@ -2561,6 +2897,14 @@ impl NodeConfigFile {
         let rpc_bind = self.rpc_bind.unwrap_or(default_node_config.rpc_bind);
         let miner = self.miner.unwrap_or(default_node_config.miner);
         let stacker = self.stacker.unwrap_or(default_node_config.stacker);
+        if let Some(prune_depth) = self.prune_depth {
+            if prune_depth < MIN_PRUNE_DEPTH {
+                return Err(format!(
+                    "node.prune_depth must be at least {} (one reward cycle)",
+                    MIN_PRUNE_DEPTH
+                ));
+            }
+        }
         let node_config = NodeConfig {
             name: self.name.unwrap_or(default_node_config.name),
             seed: match self.seed {
@@ -2632,6 +2976,12 @@ impl NodeConfigFile {
                 .iter()
                 .filter_map(|contract_id| QualifiedContractIdentifier::parse(contract_id).ok())
                 .collect(),
+            txindex: self.txindex.unwrap_or(default_node_config.txindex),
+            prune_depth: self.prune_depth.or(default_node_config.prune_depth),
+            map_index: self.map_index.unwrap_or(default_node_config.map_index),
+            event_stream_buffer: self
+                .event_stream_buffer
+                .unwrap_or(default_node_config.event_stream_buffer),
         };
         Ok(node_config)
     }
@@ -2646,6 +2996,10 @@ pub struct FeeEstimationConfigFile {
     pub log_error: Option<bool>,
     pub fee_rate_fuzzer_fraction: Option<f64>,
     pub fee_rate_window_size: Option<u64>,
+    pub fee_rate_target_blocks: Option<u64>,
+    pub cost_estimator_quantile: Option<f64>,
+    pub cost_estimator_min_confidence: Option<f64>,
+    pub cost_estimator_condition_on_args: Option<bool>,
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
@@ -2810,18 +3164,52 @@ impl AtlasConfigFile {
 pub struct EventObserverConfigFile {
     pub endpoint: String,
     pub events_keys: Vec<String>,
+    /// How long to wait for the observer to accept a payload before retrying. This includes the
+    /// time the observer spends processing the payload, so it must be longer than the observer
+    /// takes to ingest the largest block. If unset, a delivery only fails if its connection does.
+    pub timeout_ms: Option<u64>,
+    /// Delay before the first retry of a failed delivery; doubled on each further failure
+    pub retry_backoff_ms: Option<u64>,
+    /// Upper bound on the retry delay
+    pub max_retry_backoff_ms: Option<u64>,
+    /// Disable the observer after this many consecutive failures: it is then only probed once per
+    /// `max_retry_backoff_ms`. Payloads stay queued, and are replayed once it responds again.
+    pub disable_after_failures: Option<u64>,
 }
 
-#[derive(Clone, Default, Debug, Hash, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct EventObserverConfig {
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    /// If `None`, deliveries are not timed out
+    pub timeout_ms: Option<u64>,
+    pub retry_backoff_ms: u64,
+    pub max_retry_backoff_ms: u64,
+    /// If `None`, failed deliveries are retried forever
+    pub disable_after_failures: Option<u64>,
+}
+
+impl Default for EventObserverConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            events_keys: vec![],
+            timeout_ms: None,
+            retry_backoff_ms: DEFAULT_EVENT_OBSERVER_RETRY_BACKOFF_MS,
+            max_retry_backoff_ms: DEFAULT_EVENT_OBSERVER_MAX_RETRY_BACKOFF_MS,
+            disable_after_failures: None,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum EventKeyType {
     SmartContractEvent((QualifiedContractIdentifier, String)),
     AssetEvent(AssetIdentifier),
+    /// Transactions sent by this principal, or whose asset events it sends or receives
+    PrincipalTransactions(PrincipalData),
+    /// Transactions which call this contract function
+    ContractCallTransactions((QualifiedContractIdentifier, String)),
     STXEvent,
     MemPoolTransactions,
     Microblocks,
@@ -2863,6 +3251,21 @@ impl EventKeyType {
             return Some(EventKeyType::BlockProposal);
         }
 
+        if let Some(principal) = raw_key.strip_prefix("principal:") {
+            return PrincipalData::parse(principal)
+                .ok()
+                .map(EventKeyType::PrincipalTransactions);
+        }
+
+        if let Some(function_id) = raw_key.strip_prefix("contract_call:") {
+            let (contract_id, function_name) = function_id.split_once("::")?;
+            let contract_identifier = QualifiedContractIdentifier::parse(contract_id).ok()?;
+            return Some(EventKeyType::ContractCallTransactions((
+                contract_identifier,
+                function_name.to_string(),
+            )));
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split('.').collect();
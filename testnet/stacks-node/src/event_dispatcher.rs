@@ -1,17 +1,13 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Mutex;
-use std::thread::sleep;
-use std::time::Duration;
 
-use async_h1::client;
-use async_std::net::TcpStream;
 use clarity::vm::analysis::contract_interface_builder::build_contract_interface;
 use clarity::vm::costs::ExecutionCost;
 use clarity::vm::events::{FTEventType, NFTEventType, STXEventType};
-use clarity::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
-use http_types::{Method, Request, Url};
+use clarity::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier, Value};
 use serde_json::json;
 use stacks::burnchains::{PoxConstants, Txid};
 use stacks::chainstate::burn::operations::BlockstackOperationType;
@@ -35,6 +31,7 @@ use stacks::chainstate::stacks::{
 };
 use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher, ProposalCallbackReceiver};
 use stacks::libstackerdb::StackerDBChunkData;
+use stacks::net::api::geteventpoll::EventStreamBuffer;
 use stacks::net::api::postblock_proposal::{
     BlockValidateOk, BlockValidateReject, BlockValidateResponse,
 };
@@ -47,11 +44,22 @@ use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, Sta
 use stacks_common::util::hash::{bytes_to_hex, Sha512Trunc256Sum};
 use stacks_common::util::secp256k1::MessageSignature;
 
+use self::payload_queue::{ObserverQueue, PayloadQueueDB};
 use super::config::{EventKeyType, EventObserverConfig};
 
+pub mod payload_queue;
+
+#[derive(Debug, Clone)]
+enum ObserverTransport {
+    /// Durable outbox and background worker delivering payloads to an HTTP endpoint
+    Http(ObserverQueue),
+    /// In-memory buffer read by clients of the node's RPC event stream
+    Stream(EventStreamBuffer),
+}
+
 #[derive(Debug, Clone)]
 struct EventObserver {
-    endpoint: String,
+    transport: ObserverTransport,
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -295,64 +303,24 @@ impl RewardSetEventPayload {
 }
 
 impl EventObserver {
-    pub fn send_payload(&self, payload: &serde_json::Value, path: &str) {
-        let body = match serde_json::to_vec(&payload) {
-            Ok(body) => body,
-            Err(err) => {
-                error!("Event dispatcher: serialization failed  - {:?}", err);
-                return;
-            }
-        };
-
-        let url = {
-            let joined_components = match path.starts_with('/') {
-                true => format!("{}{}", &self.endpoint, path),
-                false => format!("{}/{}", &self.endpoint, path),
-            };
-            let url = format!("http://{}", joined_components);
-            Url::parse(&url)
-                .unwrap_or_else(|_| panic!("Event dispatcher: unable to parse {} as a URL", url))
-        };
-
-        let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
-
-        loop {
-            let body = body.clone();
-            let mut req = Request::new(Method::Post, url.clone());
-            req.append_header("Content-Type", "application/json");
-            req.set_body(body);
-
-            let response = async_std::task::block_on(async {
-                let stream = match TcpStream::connect(self.endpoint.clone()).await {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        warn!("Event dispatcher: connection failed  - {:?}", err);
-                        return None;
-                    }
-                };
+    fn new(queue: ObserverQueue) -> Self {
+        EventObserver {
+            transport: ObserverTransport::Http(queue),
+        }
+    }
 
-                match client::connect(stream, req).await {
-                    Ok(response) => Some(response),
-                    Err(err) => {
-                        warn!("Event dispatcher: rpc invocation failed  - {:?}", err);
-                        return None;
-                    }
-                }
-            });
+    fn new_stream(event_stream: EventStreamBuffer) -> Self {
+        EventObserver {
+            transport: ObserverTransport::Stream(event_stream),
+        }
+    }
 
-            if let Some(response) = response {
-                if response.status().is_success() {
-                    debug!(
-                        "Event dispatcher: Successful POST"; "url" => %url
-                    );
-                    break;
-                } else {
-                    error!(
-                        "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
-                    );
-                }
-            }
-            sleep(backoff);
+    /// Queue a payload for delivery to this observer. Delivery happens on the
+    /// observer's background worker, so this never blocks on the observer.
+    pub fn send_payload(&self, payload: &serde_json::Value, path: &str) {
+        match &self.transport {
+            ObserverTransport::Http(queue) => queue.send(payload, path),
+            ObserverTransport::Stream(event_stream) => event_stream.push(path, payload),
         }
     }
 
@@ -568,6 +536,7 @@ impl EventObserver {
         block: &StacksBlockEventData,
         metadata: &StacksHeaderInfo,
         receipts: &[StacksTransactionReceipt],
+        filtered_txs: Option<&HashSet<usize>>,
         parent_index_hash: &StacksBlockId,
         winner_txid: &Txid,
         mature_rewards: &serde_json::Value,
@@ -592,9 +561,12 @@ impl EventObserver {
 
         let mut tx_index: u32 = 0;
         let mut serialized_txs = vec![];
-        for receipt in receipts.iter() {
-            let payload = EventObserver::make_new_block_txs_payload(receipt, tx_index);
-            serialized_txs.push(payload);
+        for (receipt_index, receipt) in receipts.iter().enumerate() {
+            // transactions keep their index in the block, even if others are filtered out
+            if filtered_txs.map_or(true, |txs| txs.contains(&receipt_index)) {
+                let payload = EventObserver::make_new_block_txs_payload(receipt, tx_index);
+                serialized_txs.push(payload);
+            }
             tx_index += 1;
         }
 
@@ -669,8 +641,18 @@ impl EventObserver {
 #[derive(Clone)]
 pub struct EventDispatcher {
     registered_observers: Vec<EventObserver>,
+    /// Outbox of payloads which have not yet been delivered to their observers
+    queue_db: PayloadQueueDB,
+    /// Delivery queue for each observer endpoint. Observers registered with the same
+    /// endpoint share a queue, so that only one worker delivers that endpoint's payloads.
+    observer_queues: HashMap<String, ObserverQueue>,
     contract_events_observers_lookup: HashMap<(QualifiedContractIdentifier, String), HashSet<u16>>,
     assets_observers_lookup: HashMap<AssetIdentifier, HashSet<u16>>,
+    principal_observers_lookup: HashMap<PrincipalData, HashSet<u16>>,
+    contract_call_observers_lookup: HashMap<(QualifiedContractIdentifier, String), HashSet<u16>>,
+    /// Observers which only receive the transactions matching their principal and
+    /// contract-call keys
+    tx_filter_observers_lookup: HashSet<u16>,
     burn_block_observers_lookup: HashSet<u16>,
     mempool_observers_lookup: HashSet<u16>,
     microblock_observers_lookup: HashSet<u16>,
@@ -680,6 +662,8 @@ pub struct EventDispatcher {
     mined_microblocks_observers_lookup: HashSet<u16>,
     stackerdb_observers_lookup: HashSet<u16>,
     block_proposal_observers_lookup: HashSet<u16>,
+    /// Buffer of recent events served over RPC, if the event stream is enabled
+    event_stream: Option<EventStreamBuffer>,
 }
 
 /// This struct is used specifically for receiving proposal responses.
@@ -860,11 +844,20 @@ impl BlockEventDispatcher for EventDispatcher {
 }
 
 impl EventDispatcher {
-    pub fn new() -> EventDispatcher {
+    /// Create a dispatcher whose undelivered payloads are kept in the SQLite
+    /// database at `queue_db_path`, or in memory if no path is given.
+    pub fn new(queue_db_path: Option<&Path>) -> EventDispatcher {
+        let queue_db = PayloadQueueDB::open(queue_db_path)
+            .expect("FATAL: failed to open event observer queue database");
         EventDispatcher {
             registered_observers: vec![],
+            queue_db,
+            observer_queues: HashMap::new(),
             contract_events_observers_lookup: HashMap::new(),
             assets_observers_lookup: HashMap::new(),
+            principal_observers_lookup: HashMap::new(),
+            contract_call_observers_lookup: HashMap::new(),
+            tx_filter_observers_lookup: HashSet::new(),
             stx_observers_lookup: HashSet::new(),
             any_event_observers_lookup: HashSet::new(),
             burn_block_observers_lookup: HashSet::new(),
@@ -874,9 +867,30 @@ impl EventDispatcher {
             mined_microblocks_observers_lookup: HashSet::new(),
             stackerdb_observers_lookup: HashSet::new(),
             block_proposal_observers_lookup: HashSet::new(),
+            event_stream: None,
         }
     }
 
+    /// Enable the RPC event stream, which retains the `capacity` most recent events for clients
+    /// of `/v3/events/poll`.
+    /// The stream receives everything an observer of `*` and `stackerdb` would, and its
+    /// clients filter it themselves.
+    pub fn enable_event_stream(&mut self, capacity: usize) {
+        let event_stream = EventStreamBuffer::new(capacity);
+        let observer_index = u16::try_from(self.registered_observers.len())
+            .expect("FATAL: more than 2^16 observers");
+        self.any_event_observers_lookup.insert(observer_index);
+        self.stackerdb_observers_lookup.insert(observer_index);
+        self.registered_observers
+            .push(EventObserver::new_stream(event_stream.clone()));
+        self.event_stream = Some(event_stream);
+    }
+
+    /// Get the RPC event stream's buffer, if it's enabled
+    pub fn event_stream(&self) -> Option<&EventStreamBuffer> {
+        self.event_stream.as_ref()
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
@@ -907,27 +921,52 @@ impl EventDispatcher {
     /// Iterates through tx receipts, and then the events corresponding to each receipt to
     /// generate a dispatch matrix & event vector.
     ///
+    /// Observers with principal or contract-call keys are subscribed to all the events of the
+    /// transactions which match those keys. Their transactions also include those of any event
+    /// they are subscribed to through other keys, so that every event they receive refers to a
+    /// transaction in the same payload.
+    ///
     /// # Returns
     /// - dispatch_matrix: a vector where each index corresponds to the hashset of event indexes
     ///     that each respective event observer is subscribed to
     /// - events: a vector of all events from all the tx receipts
+    /// - tx_dispatch_matrix: a vector where each index corresponds to the hashset of receipt
+    ///     indexes that each respective event observer is subscribed to, or `None` if the
+    ///     observer receives all transactions
     fn create_dispatch_matrix_and_event_vector<'a>(
         &self,
         receipts: &'a Vec<StacksTransactionReceipt>,
     ) -> (
         Vec<HashSet<usize>>,
         Vec<(bool, Txid, &'a StacksTransactionEvent)>,
+        Vec<Option<HashSet<usize>>>,
     ) {
         let mut dispatch_matrix: Vec<HashSet<usize>> = self
             .registered_observers
             .iter()
             .map(|_| HashSet::new())
             .collect();
+        let mut tx_dispatch_matrix: Vec<Option<HashSet<usize>>> =
+            (0..self.registered_observers.len())
+                .map(|o_i| {
+                    let lookup_ix = u16::try_from(o_i).expect("FATAL: more than 2^16 observers");
+                    self.tx_filter_observers_lookup
+                        .contains(&lookup_ix)
+                        .then(HashSet::new)
+                })
+                .collect();
         let mut events: Vec<(bool, Txid, &StacksTransactionEvent)> = vec![];
+        let mut event_receipt_indexes: Vec<usize> = vec![];
         let mut i: usize = 0;
 
-        for receipt in receipts {
+        for (receipt_index, receipt) in receipts.iter().enumerate() {
             let tx_hash = receipt.transaction.txid();
+            let tx_observers = self.get_tx_filter_observers(receipt);
+            for o_i in tx_observers.iter() {
+                if let Some(tx_ids) = tx_dispatch_matrix[*o_i as usize].as_mut() {
+                    tx_ids.insert(receipt_index);
+                }
+            }
             for event in receipt.events.iter() {
                 match event {
                     StacksTransactionEvent::SmartContractEvent(event_data) => {
@@ -993,14 +1032,105 @@ impl EventDispatcher {
                     }
                 }
                 events.push((!receipt.post_condition_aborted, tx_hash, event));
+                event_receipt_indexes.push(receipt_index);
                 for o_i in &self.any_event_observers_lookup {
                     dispatch_matrix[*o_i as usize].insert(i);
                 }
+                for o_i in &tx_observers {
+                    dispatch_matrix[*o_i as usize].insert(i);
+                }
                 i += 1;
             }
         }
 
-        (dispatch_matrix, events)
+        for (event_ids, tx_ids) in dispatch_matrix.iter().zip(tx_dispatch_matrix.iter_mut()) {
+            if let Some(tx_ids) = tx_ids.as_mut() {
+                tx_ids.extend(event_ids.iter().map(|i| event_receipt_indexes[*i]));
+            }
+        }
+
+        (dispatch_matrix, events, tx_dispatch_matrix)
+    }
+
+    /// Get the principals a transaction touches: its sender and sponsor, and the senders and
+    /// recipients of its asset events.
+    fn get_receipt_principals(receipt: &StacksTransactionReceipt) -> HashSet<PrincipalData> {
+        let mut principals = HashSet::new();
+        if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+            principals.insert(tx.origin_address().into());
+            if let Some(sponsor) = tx.sponsor_address() {
+                principals.insert(sponsor.into());
+            }
+        }
+        for event in receipt.events.iter() {
+            match event {
+                StacksTransactionEvent::SmartContractEvent(_) => {}
+                StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(event_data)) => {
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                }
+                StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(event_data)) => {
+                    principals.insert(event_data.locked_address.clone());
+                }
+                StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => {
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::NFTEvent(NFTEventType::NFTBurnEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                }
+                StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => {
+                    principals.insert(event_data.recipient.clone());
+                }
+                StacksTransactionEvent::FTEvent(FTEventType::FTBurnEvent(event_data)) => {
+                    principals.insert(event_data.sender.clone());
+                }
+            }
+        }
+        principals
+    }
+
+    /// Get the observers whose principal or contract-call keys match this transaction
+    fn get_tx_filter_observers(&self, receipt: &StacksTransactionReceipt) -> HashSet<u16> {
+        let mut observer_indexes = HashSet::new();
+        if self.tx_filter_observers_lookup.is_empty() {
+            return observer_indexes;
+        }
+
+        if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+            if let TransactionPayload::ContractCall(ref contract_call) = tx.payload {
+                let key = (
+                    contract_call.to_clarity_contract_id(),
+                    contract_call.function_name.to_string(),
+                );
+                if let Some(o_is) = self.contract_call_observers_lookup.get(&key) {
+                    observer_indexes.extend(o_is);
+                }
+            }
+        }
+
+        if !self.principal_observers_lookup.is_empty() {
+            for principal in Self::get_receipt_principals(receipt).iter() {
+                if let Some(o_is) = self.principal_observers_lookup.get(principal) {
+                    observer_indexes.extend(o_is);
+                }
+            }
+        }
+
+        observer_indexes
     }
 
     pub fn process_chain_tip(
@@ -1022,7 +1152,8 @@ impl EventDispatcher {
         signer_bitvec: &Option<BitVec<4000>>,
     ) {
         let all_receipts = receipts.to_owned();
-        let (dispatch_matrix, events) = self.create_dispatch_matrix_and_event_vector(&all_receipts);
+        let (dispatch_matrix, events, tx_dispatch_matrix) =
+            self.create_dispatch_matrix_and_event_vector(&all_receipts);
 
         if dispatch_matrix.len() > 0 {
             let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
@@ -1060,6 +1191,7 @@ impl EventDispatcher {
                         &block,
                         metadata,
                         receipts,
+                        tx_dispatch_matrix[observer_id].as_ref(),
                         parent_index_hash,
                         &winner_txid,
                         &mature_rewards,
@@ -1108,7 +1240,7 @@ impl EventDispatcher {
             .iter()
             .flat_map(|(_, _, r)| r.clone())
             .collect();
-        let (dispatch_matrix, events) =
+        let (dispatch_matrix, events, tx_dispatch_matrix) =
             self.create_dispatch_matrix_and_event_vector(&flattened_receipts);
 
         // Serialize receipts
@@ -1131,10 +1263,24 @@ impl EventDispatcher {
                 .map(|event_id| (*event_id, &events[*event_id]))
                 .collect();
 
+            let filtered_txs: Vec<_>;
+            let observer_txs = match &tx_dispatch_matrix[*obs_id] {
+                Some(tx_ids) => {
+                    filtered_txs = serialized_txs
+                        .iter()
+                        .enumerate()
+                        .filter(|(tx_id, _)| tx_ids.contains(tx_id))
+                        .map(|(_, tx)| tx.clone())
+                        .collect();
+                    &filtered_txs
+                }
+                None => &serialized_txs,
+            };
+
             observer.send_new_microblocks(
                 parent_index_block_hash,
                 filtered_events,
-                &serialized_txs,
+                observer_txs,
                 processed_unconfirmed_state.burn_block_hash,
                 processed_unconfirmed_state.burn_block_height,
                 processed_unconfirmed_state.burn_block_timestamp,
@@ -1361,9 +1507,21 @@ impl EventDispatcher {
 
     pub fn register_observer(&mut self, conf: &EventObserverConfig) {
         info!("Registering event observer at: {}", conf.endpoint);
-        let event_observer = EventObserver {
-            endpoint: conf.endpoint.clone(),
+        let queue = match self.observer_queues.get(&conf.endpoint) {
+            Some(queue) => {
+                if !queue.has_delivery_settings(conf) {
+                    warn!("Event dispatcher: observers share the endpoint {}, keeping the delivery settings of the first one", conf.endpoint);
+                }
+                queue.clone()
+            }
+            None => {
+                let queue = ObserverQueue::spawn(self.queue_db.clone(), conf);
+                self.observer_queues
+                    .insert(conf.endpoint.clone(), queue.clone());
+                queue
+            }
         };
+        let event_observer = EventObserver::new(queue);
 
         let observer_index = self.registered_observers.len() as u16;
 
@@ -1408,6 +1566,20 @@ impl EventDispatcher {
                         }
                     };
                 }
+                EventKeyType::PrincipalTransactions(principal) => {
+                    self.principal_observers_lookup
+                        .entry(principal.clone())
+                        .or_insert_with(HashSet::new)
+                        .insert(observer_index);
+                    self.tx_filter_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::ContractCallTransactions(function_key) => {
+                    self.contract_call_observers_lookup
+                        .entry(function_key.clone())
+                        .or_insert_with(HashSet::new)
+                        .insert(observer_index);
+                    self.tx_filter_observers_lookup.insert(observer_index);
+                }
                 EventKeyType::AnyEvent => {
                     self.any_event_observers_lookup.insert(observer_index);
                 }
@@ -1433,20 +1605,48 @@ impl EventDispatcher {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
     use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::events::{
+        FTEventType, FTTransferEventData, STXEventType, STXTransferEventData,
+    };
+    use clarity::vm::types::{
+        AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, Value,
+    };
     use stacks::burnchains::{PoxConstants, Txid};
     use stacks::chainstate::stacks::db::StacksHeaderInfo;
-    use stacks::chainstate::stacks::StacksBlock;
+    use stacks::chainstate::stacks::events::{StacksTransactionEvent, StacksTransactionReceipt};
+    use stacks::chainstate::stacks::{
+        StacksBlock, StacksPrivateKey, StacksPublicKey, StacksTransaction, TokenTransferMemo,
+        TransactionAuth, TransactionContractCall, TransactionPayload, TransactionVersion,
+    };
+    use stacks::core::mempool::MemPoolDropReason;
+    use stacks::net::api::geteventpoll::{EventStreamCursor, EventStreamFilter};
     use stacks_common::bitvec::BitVec;
-    use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksBlockId};
+    use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
 
-    use crate::event_dispatcher::EventObserver;
+    use crate::config::{EventKeyType, EventObserverConfig};
+    use crate::event_dispatcher::payload_queue::{ObserverQueue, PayloadQueueDB};
+    use crate::event_dispatcher::{
+        EventDispatcher, EventObserver, PATH_BURN_BLOCK_SUBMIT, PATH_MEMPOOL_TX_SUBMIT,
+        PATH_STACKERDB_CHUNKS,
+    };
 
     #[test]
     fn build_block_processed_event() {
-        let observer = EventObserver {
-            endpoint: "nowhere".to_string(),
-        };
+        let observer = EventObserver::new(ObserverQueue::spawn(
+            PayloadQueueDB::open(None).unwrap(),
+            &EventObserverConfig {
+                endpoint: "nowhere".to_string(),
+                ..EventObserverConfig::default()
+            },
+        ));
 
         let filtered_events = vec![];
         let block = StacksBlock::genesis_block();
@@ -1468,6 +1668,7 @@ mod test {
             &block.into(),
             &metadata,
             &receipts,
+            None,
             &parent_index_hash,
             &winner_txid,
             &mature_rewards,
@@ -1499,4 +1700,394 @@ mod test {
             expected_bitvec_str
         );
     }
+
+    /// Accept `count` POSTs on `listener`, acknowledging each one, and forward
+    /// their request paths and bodies to the returned channel
+    fn serve_observer(
+        listener: TcpListener,
+        count: usize,
+    ) -> std::sync::mpsc::Receiver<(String, String)> {
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+                tx.send((path, String::from_utf8(body).unwrap())).unwrap();
+            }
+        });
+        rx
+    }
+
+    fn wait_for<F: Fn() -> bool>(check: F) {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while !check() {
+            assert!(Instant::now() < deadline, "Timed out waiting for condition");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn pending_payloads_are_replayed_after_restart() {
+        let db_path = "/tmp/stacks-node-tests/pending_payloads_are_replayed_after_restart.sqlite";
+        if std::fs::metadata(db_path).is_ok() {
+            std::fs::remove_file(db_path).unwrap();
+        }
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+
+        // queue payloads without a worker, as if the node stopped before delivering them
+        let queue_db = PayloadQueueDB::open(Some(std::path::Path::new(db_path))).unwrap();
+        queue_db.push(&endpoint, "/new_block", "{\"n\":1}").unwrap();
+        queue_db
+            .push(&endpoint, "new_burn_block", "{\"n\":2}")
+            .unwrap();
+        drop(queue_db);
+
+        let received = serve_observer(listener, 3);
+        let queue_db = PayloadQueueDB::open(Some(std::path::Path::new(db_path))).unwrap();
+        let queue = ObserverQueue::spawn(
+            queue_db,
+            &EventObserverConfig {
+                endpoint: endpoint.clone(),
+                ..EventObserverConfig::default()
+            },
+        );
+        queue.send(&serde_json::json!({"n": 3}), "new_block");
+
+        let timeout = Duration::from_secs(30);
+        assert_eq!(
+            received.recv_timeout(timeout).unwrap(),
+            ("/new_block".to_string(), "{\"n\":1}".to_string())
+        );
+        assert_eq!(
+            received.recv_timeout(timeout).unwrap(),
+            ("/new_burn_block".to_string(), "{\"n\":2}".to_string())
+        );
+        assert_eq!(
+            received.recv_timeout(timeout).unwrap(),
+            ("/new_block".to_string(), "{\"n\":3}".to_string())
+        );
+        wait_for(|| queue.pending().unwrap() == 0);
+    }
+
+    #[test]
+    fn observers_sharing_an_endpoint_share_a_queue() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let received = serve_observer(listener, 3);
+
+        let mut dispatcher = EventDispatcher::new(None);
+        for events_keys in [vec![EventKeyType::AnyEvent], vec![EventKeyType::STXEvent]] {
+            dispatcher.register_observer(&EventObserverConfig {
+                endpoint: endpoint.clone(),
+                events_keys,
+                ..EventObserverConfig::default()
+            });
+        }
+        assert_eq!(dispatcher.observer_queues.len(), 1);
+
+        // each observer's payloads are delivered exactly once, in the order they were queued
+        for (i, observer) in dispatcher.registered_observers.iter().enumerate() {
+            observer.send_payload(&serde_json::json!({ "n": i }), "new_block");
+        }
+        let timeout = Duration::from_secs(30);
+        for i in 0..2 {
+            assert_eq!(
+                received.recv_timeout(timeout).unwrap(),
+                ("/new_block".to_string(), format!("{{\"n\":{i}}}"))
+            );
+        }
+        assert!(received.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn unresponsive_observer_is_disabled() {
+        // accept connections but never respond, so every attempt times out
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let _streams: Vec<TcpStream> = listener.incoming().map(|s| s.unwrap()).collect();
+        });
+
+        let queue = ObserverQueue::spawn(
+            PayloadQueueDB::open(None).unwrap(),
+            &EventObserverConfig {
+                endpoint,
+                timeout_ms: Some(50),
+                retry_backoff_ms: 10,
+                max_retry_backoff_ms: 20,
+                disable_after_failures: Some(3),
+                ..EventObserverConfig::default()
+            },
+        );
+
+        // queuing must not block on the observer
+        let start = Instant::now();
+        queue.send(&serde_json::json!({}), "new_block");
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        wait_for(|| queue.is_disabled());
+
+        // the undelivered payload is kept for replay, and new payloads are still queued
+        queue.send(&serde_json::json!({}), "new_block");
+        assert_eq!(queue.pending().unwrap(), 2);
+    }
+
+    #[test]
+    fn disabled_observer_is_replayed_when_it_returns() {
+        // nothing listens on the endpoint at first, so every attempt fails
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let queue = ObserverQueue::spawn(
+            PayloadQueueDB::open(None).unwrap(),
+            &EventObserverConfig {
+                endpoint: addr.to_string(),
+                timeout_ms: Some(50),
+                retry_backoff_ms: 10,
+                max_retry_backoff_ms: 20,
+                disable_after_failures: Some(3),
+                ..EventObserverConfig::default()
+            },
+        );
+        queue.send(&serde_json::json!({"n": 1}), "new_block");
+        wait_for(|| queue.is_disabled());
+        queue.send(&serde_json::json!({"n": 2}), "new_block");
+        assert_eq!(queue.pending().unwrap(), 2);
+
+        // once the observer is back, everything queued while it was disabled is delivered in order
+        let received = serve_observer(TcpListener::bind(addr).unwrap(), 2);
+        let timeout = Duration::from_secs(30);
+        assert_eq!(
+            received.recv_timeout(timeout).unwrap(),
+            ("/new_block".to_string(), "{\"n\":1}".to_string())
+        );
+        assert_eq!(
+            received.recv_timeout(timeout).unwrap(),
+            ("/new_block".to_string(), "{\"n\":2}".to_string())
+        );
+        wait_for(|| queue.pending().unwrap() == 0);
+        assert!(!queue.is_disabled());
+    }
+
+    #[test]
+    fn event_stream_receives_events() {
+        let mut dispatcher = EventDispatcher::new(None);
+        dispatcher.enable_event_stream(10);
+
+        dispatcher.process_burn_block(&BurnchainHeaderHash([0x01; 32]), 123, vec![], 0, vec![]);
+        dispatcher.process_new_mempool_txs(vec![]);
+        dispatcher.process_new_stackerdb_chunks(QualifiedContractIdentifier::transient(), vec![]);
+        // not streamed
+        dispatcher.process_dropped_mempool_txs(vec![], MemPoolDropReason::STALE_COLLECT);
+
+        let resp = dispatcher
+            .event_stream()
+            .unwrap()
+            .get_events(&EventStreamCursor::Oldest, &EventStreamFilter::All, 100)
+            .unwrap();
+        assert!(!resp.has_more);
+        let events = resp.events;
+        let paths: Vec<_> = events.iter().map(|event| event.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PATH_BURN_BLOCK_SUBMIT,
+                PATH_MEMPOOL_TX_SUBMIT,
+                PATH_STACKERDB_CHUNKS
+            ]
+        );
+        assert_eq!(events[0].payload["burn_block_height"], 123);
+    }
+
+    #[test]
+    fn transaction_filters() {
+        let alice_key = StacksPrivateKey::new();
+        let carol_key = StacksPrivateKey::new();
+        let make_tx = |key: &StacksPrivateKey, payload: TransactionPayload| {
+            StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(key).unwrap(),
+                payload,
+            )
+        };
+        let bob: PrincipalData = StacksAddress::burn_address(false).into();
+        let dave: PrincipalData = StacksAddress::burn_address(true).into();
+
+        // alice sends STX to bob
+        let alice_tx = make_tx(
+            &alice_key,
+            TransactionPayload::TokenTransfer(bob.clone(), 100, TokenTransferMemo([0; 34])),
+        );
+        let alice: PrincipalData = alice_tx.origin_address().into();
+        let stx_event = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: alice.clone(),
+                recipient: bob.clone(),
+                amount: 100,
+                memo: BuffData { data: vec![] },
+            },
+        ));
+
+        // carol calls her contract, which sends tokens to dave
+        let carol_address = StacksAddress::p2pkh(false, &StacksPublicKey::from_private(&carol_key));
+        let amm = QualifiedContractIdentifier::new(carol_address.clone().into(), "amm".into());
+        let make_call = |function_name: &str| {
+            TransactionPayload::ContractCall(TransactionContractCall {
+                address: carol_address.clone(),
+                contract_name: "amm".into(),
+                function_name: function_name.into(),
+                function_args: vec![],
+            })
+        };
+        let ft_event =
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(FTTransferEventData {
+                asset_identifier: AssetIdentifier {
+                    contract_identifier: amm.clone(),
+                    asset_name: "lp".into(),
+                },
+                sender: amm.clone().into(),
+                recipient: dave.clone(),
+                amount: 1,
+            }));
+
+        let receipts = vec![
+            StacksTransactionReceipt::from_stx_transfer(
+                alice_tx,
+                vec![stx_event],
+                Value::okay_true(),
+                ExecutionCost::zero(),
+            ),
+            StacksTransactionReceipt::from_contract_call(
+                make_tx(&carol_key, make_call("swap")),
+                vec![ft_event],
+                Value::okay_true(),
+                0,
+                ExecutionCost::zero(),
+            ),
+            StacksTransactionReceipt::from_contract_call(
+                make_tx(&carol_key, make_call("add-liquidity")),
+                vec![],
+                Value::okay_true(),
+                0,
+                ExecutionCost::zero(),
+            ),
+        ];
+
+        let mut dispatcher = EventDispatcher::new(None);
+        let observers_keys = vec![
+            vec![EventKeyType::PrincipalTransactions(bob)],
+            vec![EventKeyType::PrincipalTransactions(dave)],
+            vec![EventKeyType::ContractCallTransactions((
+                amm.clone(),
+                "swap".into(),
+            ))],
+            vec![EventKeyType::AnyEvent],
+            vec![
+                EventKeyType::PrincipalTransactions(carol_address.into()),
+                EventKeyType::STXEvent,
+            ],
+        ];
+        for events_keys in observers_keys.into_iter() {
+            dispatcher.register_observer(&EventObserverConfig {
+                endpoint: "nowhere".to_string(),
+                events_keys,
+                ..EventObserverConfig::default()
+            });
+        }
+
+        let (dispatch_matrix, events, tx_dispatch_matrix) =
+            dispatcher.create_dispatch_matrix_and_event_vector(&receipts);
+        assert_eq!(events.len(), 2);
+
+        let expected: Vec<(Vec<usize>, Option<Vec<usize>>)> = vec![
+            (vec![0], Some(vec![0])),
+            (vec![1], Some(vec![1])),
+            (vec![1], Some(vec![1])),
+            (vec![0, 1], None),
+            // carol's transactions, and all STX events along with their transactions
+            (vec![0, 1], Some(vec![0, 1, 2])),
+        ];
+        for (o_i, (event_ids, tx_ids)) in expected.into_iter().enumerate() {
+            assert_eq!(dispatch_matrix[o_i], event_ids.into_iter().collect());
+            assert_eq!(
+                tx_dispatch_matrix[o_i],
+                tx_ids.map(|tx_ids| tx_ids.into_iter().collect())
+            );
+        }
+
+        // filtered transactions keep their index in the block
+        let observer = EventObserver::new(ObserverQueue::spawn(
+            PayloadQueueDB::open(None).unwrap(),
+            &EventObserverConfig {
+                endpoint: "nowhere".to_string(),
+                ..EventObserverConfig::default()
+            },
+        ));
+        let payload = observer.make_new_block_processed_payload(
+            vec![],
+            &StacksBlock::genesis_block().into(),
+            &StacksHeaderInfo::regtest_genesis(),
+            &receipts,
+            tx_dispatch_matrix[4].as_ref(),
+            &StacksBlockId([0; 32]),
+            &Txid([0; 32]),
+            &serde_json::Value::Array(vec![]),
+            BurnchainHeaderHash([0; 32]),
+            0,
+            0,
+            &ExecutionCost::zero(),
+            &ExecutionCost::zero(),
+            &PoxConstants::testnet_default(),
+            &None,
+            &None,
+        );
+        let tx_indexes: Vec<_> = payload["transactions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| tx["tx_index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(tx_indexes, vec![0, 1, 2]);
+
+        // every event in the payload refers to one of its transactions
+        let txids: HashSet<_> = payload["transactions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| tx["txid"].as_str().unwrap().to_string())
+            .collect();
+        let observer_events: Vec<_> = dispatch_matrix[4]
+            .iter()
+            .map(|event_id| events[*event_id].1.to_hex())
+            .collect();
+        assert_eq!(observer_events.len(), 2);
+        for txid in observer_events.iter() {
+            assert!(txids.contains(&format!("0x{}", txid)));
+        }
+    }
 }
@@ -0,0 +1,396 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Durable delivery of event observer payloads.
+//!
+//! Every payload destined for an observer is first written to a SQLite outbox
+//! (`pending_payloads`), and each observer has a background worker thread
+//! which POSTs its pending payloads in the order they were queued. A payload
+//! is only removed from the outbox once the observer acknowledges it, so
+//! anything still pending when the node stops is replayed on the next start.
+//!
+//! An observer which fails too many deliveries in a row is disabled: its
+//! payloads are still queued, but its worker only probes it once per maximum
+//! retry backoff. Once a probe succeeds, the observer is re-enabled and its
+//! queue is replayed.
+//!
+//! Payloads are queued per endpoint, and each endpoint must have exactly one
+//! worker: the dispatcher hands observers which share an endpoint the same
+//! `ObserverQueue`.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fmt, thread};
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+use rusqlite::{params, Connection, OpenFlags, Row, NO_PARAMS};
+use stacks::util_lib::db::{query_count, query_row, sqlite_open, Error as DBError, FromRow};
+
+use crate::config::EventObserverConfig;
+
+const CREATE_PENDING_PAYLOADS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS pending_payloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    endpoint TEXT NOT NULL,
+    path TEXT NOT NULL,
+    payload TEXT NOT NULL
+)";
+
+const CREATE_PENDING_PAYLOADS_INDEX: &str = "
+CREATE INDEX IF NOT EXISTS index_pending_payloads_endpoint ON pending_payloads(endpoint, id)";
+
+/// A payload which has been queued for an observer, but not yet acknowledged by it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingPayload {
+    pub id: i64,
+    pub path: String,
+    pub payload: String,
+}
+
+impl FromRow<PendingPayload> for PendingPayload {
+    fn from_row<'a>(row: &'a Row) -> Result<PendingPayload, DBError> {
+        Ok(PendingPayload {
+            id: row.get("id")?,
+            path: row.get("path")?,
+            payload: row.get("payload")?,
+        })
+    }
+}
+
+/// The SQLite outbox shared by all of a node's event observers.
+/// The connection is shared (rather than opened per-thread) so that the
+/// in-memory outbox used when no path is configured behaves the same way.
+#[derive(Clone)]
+pub struct PayloadQueueDB {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl fmt::Debug for PayloadQueueDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PayloadQueueDB")
+    }
+}
+
+impl PayloadQueueDB {
+    /// Open (or create) the outbox at `path`, or an in-memory outbox if no path is given
+    pub fn open(path: Option<&Path>) -> Result<Self, DBError> {
+        let conn = match path {
+            Some(path) => sqlite_open(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+                false,
+            )?,
+            None => Connection::open_in_memory()?,
+        };
+        conn.execute(CREATE_PENDING_PAYLOADS_TABLE, NO_PARAMS)?;
+        conn.execute(CREATE_PENDING_PAYLOADS_INDEX, NO_PARAMS)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn
+            .lock()
+            .expect("FATAL: event observer queue lock poisoned")
+    }
+
+    /// Append a payload to `endpoint`'s queue, returning its id
+    pub fn push(&self, endpoint: &str, path: &str, payload: &str) -> Result<i64, DBError> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO pending_payloads (endpoint, path, payload) VALUES (?1, ?2, ?3)",
+            params![endpoint, path, payload],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Get the oldest payload still pending for `endpoint`
+    pub fn peek(&self, endpoint: &str) -> Result<Option<PendingPayload>, DBError> {
+        query_row(
+            &self.conn(),
+            "SELECT id, path, payload FROM pending_payloads WHERE endpoint = ?1 ORDER BY id ASC LIMIT 1",
+            params![endpoint],
+        )
+    }
+
+    /// Remove a payload once it has been delivered (or can never be delivered)
+    pub fn remove(&self, id: i64) -> Result<(), DBError> {
+        self.conn()
+            .execute("DELETE FROM pending_payloads WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// How many payloads are pending for `endpoint`?
+    pub fn count(&self, endpoint: &str) -> Result<u64, DBError> {
+        let count = query_count(
+            &self.conn(),
+            "SELECT COUNT(*) FROM pending_payloads WHERE endpoint = ?1",
+            params![endpoint],
+        )?;
+        Ok(count as u64)
+    }
+}
+
+/// Handle to an observer's queue. Cloning the handle shares the same worker;
+/// the worker exits once every handle has been dropped.
+#[derive(Debug, Clone)]
+pub struct ObserverQueue {
+    endpoint: String,
+    /// The observer config this queue's worker was started with
+    conf: EventObserverConfig,
+    db: PayloadQueueDB,
+    notify: Sender<()>,
+    disabled: Arc<AtomicBool>,
+}
+
+impl ObserverQueue {
+    /// Start the delivery worker for the observer described by `conf`.
+    /// Any payloads already pending for this endpoint are replayed first.
+    pub fn spawn(db: PayloadQueueDB, conf: &EventObserverConfig) -> Self {
+        let (notify, notify_rx) = channel();
+        let disabled = Arc::new(AtomicBool::new(false));
+        let worker = DeliveryWorker {
+            endpoint: conf.endpoint.clone(),
+            db: db.clone(),
+            notify: notify_rx,
+            disabled: disabled.clone(),
+            timeout: conf.timeout_ms.map(Duration::from_millis),
+            retry_backoff: Duration::from_millis(conf.retry_backoff_ms),
+            max_retry_backoff: Duration::from_millis(
+                conf.max_retry_backoff_ms.max(conf.retry_backoff_ms),
+            ),
+            disable_after_failures: conf.disable_after_failures,
+        };
+        thread::Builder::new()
+            .name(format!("event-observer-{}", &conf.endpoint))
+            .spawn(move || worker.run())
+            .expect("FATAL: failed to spawn event observer delivery thread");
+
+        Self {
+            endpoint: conf.endpoint.clone(),
+            conf: conf.clone(),
+            db,
+            notify,
+            disabled,
+        }
+    }
+
+    /// Would a worker started for `conf` deliver payloads the same way as this queue's worker?
+    pub fn has_delivery_settings(&self, conf: &EventObserverConfig) -> bool {
+        self.conf.timeout_ms == conf.timeout_ms
+            && self.conf.retry_backoff_ms == conf.retry_backoff_ms
+            && self.conf.max_retry_backoff_ms == conf.max_retry_backoff_ms
+            && self.conf.disable_after_failures == conf.disable_after_failures
+    }
+
+    /// Has this observer been disabled after too many consecutive failures?
+    /// Payloads are still queued for a disabled observer, and are delivered once it responds again.
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::SeqCst)
+    }
+
+    /// Number of payloads waiting to be delivered to this observer
+    pub fn pending(&self) -> Result<u64, DBError> {
+        self.db.count(&self.endpoint)
+    }
+
+    /// Queue a payload for delivery. This only writes to the outbox; the
+    /// POST itself happens on the worker thread.
+    pub fn send(&self, payload: &serde_json::Value, path: &str) {
+        let payload = match serde_json::to_string(payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!("Event dispatcher: serialization failed  - {:?}", err);
+                return;
+            }
+        };
+        if let Err(err) = self.db.push(&self.endpoint, path, &payload) {
+            error!("Event dispatcher: failed to queue payload";
+                   "endpoint" => %self.endpoint, "path" => path, "err" => ?err);
+            return;
+        }
+        // the worker only goes away once every handle is dropped, so this can't fail
+        let _ = self.notify.send(());
+    }
+}
+
+/// Background worker which drains a single observer's queue
+struct DeliveryWorker {
+    endpoint: String,
+    db: PayloadQueueDB,
+    notify: Receiver<()>,
+    disabled: Arc<AtomicBool>,
+    /// Bound on a whole delivery attempt, including the observer's processing time.
+    /// If `None`, an attempt only fails once the connection does.
+    timeout: Option<Duration>,
+    retry_backoff: Duration,
+    max_retry_backoff: Duration,
+    disable_after_failures: Option<u64>,
+}
+
+impl DeliveryWorker {
+    fn run(self) {
+        let mut backoff = self.retry_backoff;
+        let mut failures: u64 = 0;
+        loop {
+            let pending = match self.db.peek(&self.endpoint) {
+                Ok(pending) => pending,
+                Err(err) => {
+                    error!("Event dispatcher: failed to read pending payloads";
+                           "endpoint" => %self.endpoint, "err" => ?err);
+                    if !self.wait(backoff) {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            let Some(pending) = pending else {
+                // nothing left to deliver; sleep until something is queued
+                if self.notify.recv().is_err() {
+                    return;
+                }
+                continue;
+            };
+
+            let delivered = match self.url(&pending.path) {
+                Some(url) => self.post(url, pending.payload.into_bytes()),
+                // an unparseable URL will never succeed, so don't retry it
+                None => true,
+            };
+
+            if delivered {
+                if let Err(err) = self.db.remove(pending.id) {
+                    error!("Event dispatcher: failed to remove delivered payload";
+                           "endpoint" => %self.endpoint, "id" => pending.id, "err" => ?err);
+                }
+                if self.disabled.swap(false, Ordering::SeqCst) {
+                    info!("Event dispatcher: observer is responding again, replaying its queued payloads";
+                          "endpoint" => %self.endpoint);
+                }
+                failures = 0;
+                backoff = self.retry_backoff;
+                continue;
+            }
+
+            failures = failures.saturating_add(1);
+            if let Some(max_failures) = self.disable_after_failures {
+                if failures >= max_failures && !self.disabled.load(Ordering::SeqCst) {
+                    error!("Event dispatcher: disabling observer after repeated failures. Payloads will be queued and replayed once it responds again.";
+                           "endpoint" => %self.endpoint, "failures" => failures);
+                    self.disabled.store(true, Ordering::SeqCst);
+                }
+            }
+            if self.disabled.load(Ordering::SeqCst) {
+                // only probe a disabled observer once per maximum backoff
+                backoff = self.max_retry_backoff;
+            }
+            if !self.wait(backoff) {
+                return;
+            }
+            backoff = backoff.saturating_mul(2).min(self.max_retry_backoff);
+        }
+    }
+
+    /// Sleep for `duration`, discarding any notifications which arrive in the meantime.
+    /// Returns false if every queue handle has been dropped.
+    fn wait(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return true;
+            }
+            match self.notify.recv_timeout(remaining) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return false,
+            }
+        }
+    }
+
+    fn url(&self, path: &str) -> Option<Url> {
+        let joined_components = match path.starts_with('/') {
+            true => format!("{}{}", &self.endpoint, path),
+            false => format!("{}/{}", &self.endpoint, path),
+        };
+        let url = format!("http://{}", joined_components);
+        match Url::parse(&url) {
+            Ok(url) => Some(url),
+            Err(err) => {
+                error!("Event dispatcher: unable to parse {} as a URL, dropping payload", url;
+                       "err" => ?err);
+                None
+            }
+        }
+    }
+
+    /// Make a single delivery attempt, returning true if the observer accepted the payload
+    fn post(&self, url: Url, body: Vec<u8>) -> bool {
+        let mut req = Request::new(Method::Post, url.clone());
+        req.append_header("Content-Type", "application/json");
+        req.set_body(body);
+
+        let attempt = async {
+            let stream = match TcpStream::connect(self.endpoint.clone()).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!("Event dispatcher: connection failed  - {:?}", err);
+                    return None;
+                }
+            };
+
+            match client::connect(stream, req).await {
+                Ok(response) => Some(response),
+                Err(err) => {
+                    warn!("Event dispatcher: rpc invocation failed  - {:?}", err);
+                    None
+                }
+            }
+        };
+        let response = async_std::task::block_on(async {
+            match self.timeout {
+                Some(timeout) => async_std::future::timeout(timeout, attempt).await,
+                None => Ok(attempt.await),
+            }
+        });
+
+        match response {
+            Ok(Some(response)) if response.status().is_success() => {
+                debug!(
+                    "Event dispatcher: Successful POST"; "url" => %url
+                );
+                true
+            }
+            Ok(Some(response)) => {
+                error!(
+                    "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
+                );
+                false
+            }
+            Ok(None) => false,
+            Err(_) => {
+                warn!("Event dispatcher: POST timed out"; "url" => %url, "timeout_ms" => self.timeout.map(|t| t.as_millis()));
+                false
+            }
+        }
+    }
+}
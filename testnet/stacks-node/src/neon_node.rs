@@ -368,6 +368,9 @@ pub(crate) fn open_chainstate_with_faults(
     )?;
 
     chainstate.fault_injection.hide_blocks = config.node.fault_injection_hide_blocks;
+    if config.node.txindex {
+        chainstate.txindex = true;
+    }
     Ok(chainstate)
 }
 
@@ -4306,6 +4309,7 @@ impl PeerThread {
                 cost_estimator: Some(cost_estimator.as_ref()),
                 cost_metric: Some(cost_metric.as_ref()),
                 fee_estimator: fee_estimator.map(|boxed_estimator| boxed_estimator.as_ref()),
+                event_stream: event_dispatcher.event_stream(),
                 ..RPCHandlerArgs::default()
             };
             p2p_thread.with_network(|_, net| {
@@ -89,10 +89,13 @@ impl RunLoop {
             config.burnchain.burn_fee_cap,
         )));
 
-        let mut event_dispatcher = EventDispatcher::new();
+        let mut event_dispatcher = EventDispatcher::new(Some(&config.get_event_observer_db_path()));
         for observer in config.events_observers.iter() {
             event_dispatcher.register_observer(observer);
         }
+        if config.node.event_stream_buffer > 0 {
+            event_dispatcher.enable_event_stream(config.node.event_stream_buffer);
+        }
 
         Self {
             config,
@@ -242,7 +245,16 @@ impl RunLoop {
             get_bulk_initial_names: Some(Box::new(move || get_names(use_test_genesis_data))),
         };
 
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
+        if self.config.node.prune_depth.is_some() {
+            // reclaim the space left by tries pruned in the last run, while nothing has the
+            // Clarity MARF open
+            match StacksChainState::compact_clarity_marf(&self.config.get_chainstate_path_str()) {
+                Ok(reclaimed) => info!("Compacted Clarity MARF, reclaiming {} bytes", reclaimed),
+                Err(e) => warn!("Failed to compact Clarity MARF: {:?}", &e),
+            }
+        }
+
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
             self.config.is_mainnet(),
             self.config.burnchain.chain_id,
             &self.config.get_chainstate_path_str(),
@@ -250,6 +262,16 @@ impl RunLoop {
             Some(self.config.node.get_marf_opts()),
         )
         .unwrap();
+        if self.config.node.txindex {
+            chain_state_db.txindex = true;
+        }
+        chain_state_db.prune_depth = self.config.node.prune_depth;
+        if self.config.node.map_index {
+            chain_state_db
+                .clarity_state
+                .enable_map_index()
+                .expect("FATAL: failed to enable the map index");
+        }
         run_loop::announce_boot_receipts(
             &mut self.event_dispatcher,
             &chain_state_db,
@@ -477,6 +499,11 @@ impl RunLoop {
             sn
         };
 
+        // until the first `new_block` payload arrives, the RPC event stream records events at
+        // the height of the Stacks chain tip the node resumes from
+        if let Some(event_stream) = self.event_dispatcher.event_stream() {
+            event_stream.seed_block_height(burnchain_tip_snapshot.canonical_stacks_tip_height);
+        }
         globals.set_last_sortition(burnchain_tip_snapshot);
 
         // Boot up the p2p network and relayer, and figure out how many sortitions we have so far
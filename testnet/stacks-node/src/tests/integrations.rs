@@ -668,7 +668,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![Value::UInt(3).serialize_to_hex().unwrap()]
+                    arguments: vec![Value::UInt(3).serialize_to_hex().unwrap()],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -690,7 +691,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![]
+                    arguments: vec![],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -717,7 +719,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![]
+                    arguments: vec![],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -736,7 +739,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![Value::UInt(3).serialize_to_hex().unwrap()]
+                    arguments: vec![Value::UInt(3).serialize_to_hex().unwrap()],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -759,7 +763,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![Value::UInt(100).serialize_to_hex().unwrap()]
+                    arguments: vec![Value::UInt(100).serialize_to_hex().unwrap()],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -778,7 +783,8 @@ fn integration_test_get_info() {
                 let body = CallReadOnlyRequestBody {
                     sender: "'SP139Q3N9RXCJCD1XVA4N5RYWQ5K9XQ0T9PKQ8EE5".into(),
                     sponsor: None,
-                    arguments: vec![]
+                    arguments: vec![],
+                    arguments_json: None,
                 };
 
                 let res = client.post(&path)
@@ -726,6 +726,7 @@ pub fn get_key_for_cycle(
         arguments: vec![clarity::vm::Value::UInt(reward_cycle as u128)
             .serialize_to_hex()
             .map_err(|_| "Failed to serialize reward cycle")?],
+        arguments_json: None,
     };
     let res = client
         .post(&path)
@@ -1005,6 +1006,7 @@ fn simple_neon_integration() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1246,6 +1248,7 @@ fn mine_multiple_per_tenure_integration() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1441,6 +1444,7 @@ fn correct_burn_outs() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1783,6 +1787,7 @@ fn block_proposal_api_endpoint() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::BlockProposal],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2152,6 +2157,7 @@ fn miner_writes_proposed_block_to_stackerdb() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent, EventKeyType::MinedBlocks],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2277,6 +2283,7 @@ fn vote_for_aggregate_key_burn_op() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2537,6 +2544,7 @@ fn follower_bootup() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2781,6 +2789,7 @@ fn stack_stx_burn_op_integration_test() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -3225,6 +3234,7 @@ fn forked_tenure_is_ignored() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent, EventKeyType::MinedBlocks],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -3506,6 +3516,7 @@ fn check_block_heights() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -789,6 +789,7 @@ fn setup_stx_btc_node(
                 EventKeyType::BlockProposal,
                 EventKeyType::BurnchainBlocks,
             ],
+            ..EventObserverConfig::default()
         });
     }
 
@@ -802,6 +803,7 @@ fn setup_stx_btc_node(
             EventKeyType::BlockProposal,
             EventKeyType::MinedBlocks,
         ],
+        ..EventObserverConfig::default()
     });
 
     // The signers need some initial balances in order to pay for epoch 2.5 transaction votes
@@ -1142,7 +1144,7 @@ fn stackerdb_delayed_dkg() {
     let mut stackerdbs: Vec<_> = signer_slot_ids
         .iter()
         .map(|i| {
-            StackerDB::new(
+            StackerDB::<MessageSlotID>::new(
                 &signer_test.running_nodes.conf.node.rpc_bind,
                 StacksPrivateKey::new(), // Doesn't matter what key we use. We are just reading, not writing
                 false,
@@ -1468,7 +1470,7 @@ fn stackerdb_filter_bad_transactions() {
     let next_reward_cycle = signer_test.get_current_reward_cycle().saturating_add(1);
     // Must submit to the NEXT reward cycle slots as they are the ones looked at by the CURRENT miners
     let signer_index = signer_test.get_signer_index(next_reward_cycle);
-    let mut stackerdb = StackerDB::new(
+    let mut stackerdb = StackerDB::<MessageSlotID>::new(
         &signer_test.running_nodes.conf.node.rpc_bind,
         signer_private_key,
         false,